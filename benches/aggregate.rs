@@ -0,0 +1,71 @@
+//! Throughput of aggregating 1-minute candles.
+//!
+//! Run with `cargo bench --bench aggregate`.
+
+use std::{hint::black_box, sync::Arc};
+
+use chrono::{DateTime, Duration};
+use chrono_tz::America::New_York;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use databento::examples::es_futures_pmz::{
+    aggregate_candles, aggregate_candles_by, BucketAnchor, Candle,
+};
+
+const CANDLES: usize = 1_000_000;
+const INSTRUMENTS: u32 = 4;
+
+fn candles() -> Vec<Candle> {
+    let symbols: Vec<Arc<str>> = (0..INSTRUMENTS)
+        .map(|i| Arc::from(format!("ES{i}")))
+        .collect();
+    let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    (0..CANDLES)
+        .map(|i| {
+            let instrument_id = i as u32 % INSTRUMENTS;
+            let minute = (i / INSTRUMENTS as usize) as i64;
+            let price = 4_500.0 + (i % 97) as f64 * 0.25;
+            Candle {
+                timestamp: (start + Duration::minutes(minute)).with_timezone(&New_York),
+                instrument_id,
+                symbol: symbols[instrument_id as usize].clone(),
+                open: price,
+                high: price + 1.0,
+                low: price - 1.0,
+                close: price + 0.5,
+                volume: 10,
+            }
+        })
+        .collect()
+}
+
+fn bench_aggregate(c: &mut Criterion) {
+    let candles = candles();
+    let mut group = c.benchmark_group("aggregate");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(candles.len() as u64));
+    group.bench_function("5-minute, local midnight", |b| {
+        b.iter(|| aggregate_candles(black_box(&candles), 5))
+    });
+    group.bench_function("1-hour, local midnight", |b| {
+        b.iter(|| aggregate_candles(black_box(&candles), 60))
+    });
+    group.bench_function("5-minute, epoch", |b| {
+        b.iter(|| {
+            aggregate_candles_by(
+                black_box(&candles),
+                Duration::minutes(5),
+                BucketAnchor::Epoch,
+            )
+        })
+    });
+    #[cfg(feature = "parallel")]
+    group.bench_function("5-minute, parallel", |b| {
+        b.iter(|| {
+            databento::examples::es_futures_pmz::par_aggregate_candles(black_box(&candles), 5)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_aggregate);
+criterion_main!(benches);
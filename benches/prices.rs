@@ -0,0 +1,86 @@
+//! Throughput of converting DBN fixed-point prices.
+//!
+//! Run with `cargo bench --bench prices`.
+
+use std::{hint::black_box, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use databento::{
+    dbn::{rtype, OhlcvMsg, RecordHeader},
+    examples::es_futures_pmz::{Candle, CandlePrice, DEFAULT_CANDLE_TZ},
+};
+
+const PRICES: usize = 4_000_000;
+
+fn bench_prices(c: &mut Criterion) {
+    let prices: Vec<i64> = (0..PRICES as i64)
+        .map(|i| 5_300_000_000_000 + (i % 1_000) * 250_000_000)
+        .collect();
+    let mut group = c.benchmark_group("prices");
+    group.throughput(Throughput::Elements(PRICES as u64));
+    group.bench_function("one at a time", |b| {
+        b.iter(|| {
+            black_box(&prices)
+                .iter()
+                .map(|&px| f64::from_fixed(px))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            let mut converted = Vec::new();
+            f64::extend_from_fixed(&mut converted, black_box(&prices));
+            converted
+        })
+    });
+    group.finish();
+
+    let records: Vec<OhlcvMsg> = prices
+        .chunks_exact(4)
+        .enumerate()
+        .map(|(i, px)| OhlcvMsg {
+            hd: RecordHeader::new::<OhlcvMsg>(
+                rtype::OHLCV_1M,
+                1,
+                1,
+                1_745_242_200_000_000_000 + i as u64 * 60_000_000_000,
+            ),
+            open: px[0],
+            high: px[1],
+            low: px[2],
+            close: px[3],
+            volume: 10,
+        })
+        .collect();
+    let symbol: Arc<str> = Arc::from("ES.c.0");
+    let mut group = c.benchmark_group("candles");
+    group.throughput(Throughput::Elements(records.len() as u64));
+    group.bench_function("one at a time", |b| {
+        b.iter(|| {
+            black_box(&records)
+                .iter()
+                .map(|ohlcv| {
+                    Candle::<f64>::try_with_symbol(ohlcv, symbol.clone(), DEFAULT_CANDLE_TZ)
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        })
+    });
+    group.bench_function("batch", |b| {
+        b.iter(|| {
+            let mut candles = Vec::new();
+            Candle::<f64>::extend_from_ohlcv(
+                &mut candles,
+                black_box(&records),
+                |_| symbol.clone(),
+                DEFAULT_CANDLE_TZ,
+            )
+            .unwrap();
+            candles
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_prices);
+criterion_main!(benches);
@@ -3,5 +3,29 @@ fn main() {
     println!("cargo:rerun-if-changed=src/ffi.rs");
     println!("cargo:rerun-if-changed=src/examples/es_futures_pmz.rs");
 
-    // No more UniFFI code generation needed
-}
\ No newline at end of file
+    #[cfg(feature = "cbindgen")]
+    generate_header();
+}
+
+/// Regenerates `include/databento_pmz.h` from the FFI module and the `#[repr(C)]` types
+/// it uses from other modules.
+#[cfg(feature = "cbindgen")]
+fn generate_header() {
+    // Only these files are parsed, so other public items in the crate can't end up in
+    // the header
+    const SOURCES: [&str; 3] = ["src/ffi.rs", "src/alerts.rs", "src/backtest.rs"];
+
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set");
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml"))
+        .expect("cbindgen.toml is valid");
+    let mut builder = cbindgen::Builder::new().with_config(config);
+    for src in SOURCES {
+        println!("cargo:rerun-if-changed={src}");
+        builder = builder.with_src(format!("{crate_dir}/{src}"));
+    }
+    builder
+        .generate()
+        .expect("Unable to generate C header")
+        .write_to_file(format!("{crate_dir}/include/databento_pmz.h"));
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use databento::{
+    calendar::{Session, TradingCalendar},
     dbn::{Encoding, OhlcvMsg, Schema, SType},
     historical::{
         metadata::ListFieldsParams,
@@ -7,11 +8,11 @@ use databento::{
         timeseries::GetRangeParams, ClientBuilder,
         DateRange, DateTimeRange,
     },
+    timeconv::{ToChrono, ToOffsetDateTime, ToTimeDate},
 };
-use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Datelike};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Datelike};
 use chrono_tz::{America::New_York, US::Eastern};
 use std::{collections::HashMap, env, str::FromStr};
-use time::{Date, OffsetDateTime};
 
 // PMZ calculation result structure
 #[derive(Debug, Clone)]
@@ -41,12 +42,9 @@ struct Candle {
 
 impl Candle {
     // Simplified constructor for this example, assuming symbol is known
-    fn new(ohlcv: &OhlcvMsg, symbol: &str) -> Self {
+    fn new(ohlcv: &OhlcvMsg, symbol: &str) -> databento::Result<Self> {
         // Convert timestamp from nanos to a DateTime (UTC)
-        let ts_nanos = ohlcv.hd.ts_event as i64;
-        let seconds = ts_nanos / 1_000_000_000;
-        let nanos = (ts_nanos % 1_000_000_000) as u32;
-        let utc_timestamp = Utc.timestamp_opt(seconds, nanos).single().unwrap();
+        let utc_timestamp = ohlcv.hd.ts_event.to_chrono()?;
 
         // Convert UTC to Eastern Time
         let est_timestamp = utc_timestamp.with_timezone(&Eastern);
@@ -54,7 +52,7 @@ impl Candle {
         // Convert fixed point prices (with 1e-9 scaling) to floating point
         let scaling_factor = 1e-9; // Use 1e-9 directly
 
-        Candle {
+        Ok(Candle {
             timestamp: est_timestamp,
             instrument_id: ohlcv.hd.instrument_id,
             symbol: symbol.to_string(), // Use the passed symbol
@@ -63,12 +61,7 @@ impl Candle {
             low: ohlcv.low as f64 * scaling_factor,
             close: ohlcv.close as f64 * scaling_factor,
             volume: ohlcv.volume,
-        }
-    }
-
-    // Format the timestamp to yyyy-mm-dd HH:MM (Eastern Time)
-    fn format_timestamp(&self) -> String {
-        self.timestamp.format("%Y-%m-%d %H:%M").to_string()
+        })
     }
 }
 
@@ -128,26 +121,10 @@ fn aggregate_candles(candles: &[Candle], interval_minutes: u32) -> Vec<Candle> {
         });
     }
 
-    result.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    result.sort_by_key(|a| a.timestamp);
     result
 }
 
-// Function to check if a given date is a weekend
-fn is_weekend(date: &NaiveDate) -> bool {
-    use chrono::Weekday::*;
-    let weekday = date.weekday();
-    weekday == Sat || weekday == Sun
-}
-
-// Function to get the previous trading day (skipping weekends)
-fn get_previous_trading_day(date: NaiveDate) -> NaiveDate {
-    let mut prev_day = date - Duration::days(1);
-    while is_weekend(&prev_day) {
-        prev_day -= Duration::days(1);
-    }
-    prev_day
-}
-
 /// Calculate PMZ values for a given date
 /// 
 /// This function handles:
@@ -170,17 +147,11 @@ pub async fn calculate_pmz(
 
     // --- Date and Time Setup ---
     let today_naive = Utc::now().date_naive(); // Today's date in UTC
-    // Use provided date or default to today (adjusting for weekends)
-    let mut current_trading_day_naive = match date_opt {
-        Some(date) => date,
-        None => today_naive,
-    };
-    
-    // Ensure we're using a weekday
-    while is_weekend(&current_trading_day_naive) {
-        current_trading_day_naive = current_trading_day_naive - Duration::days(1);
-    }
-    let previous_trading_day_naive = get_previous_trading_day(current_trading_day_naive);
+    // Use provided date or default to today, adjusting for weekends with the CME
+    // Globex session hours
+    let globex = Session::CME_GLOBEX;
+    let current_trading_day_naive = globex.trading_day_on_or_before(date_opt.unwrap_or(today_naive));
+    let previous_trading_day_naive = globex.previous_trading_day(current_trading_day_naive);
 
     // Define the time range in New York time
     let tz = New_York;
@@ -197,8 +168,8 @@ pub async fn calculate_pmz(
     let query_end_dt_utc = tz.from_local_datetime(&query_end_dt_naive).unwrap().with_timezone(&Utc);
 
     // Convert query times for databento API
-    let query_start_dt_offset = OffsetDateTime::from_unix_timestamp_nanos(query_start_dt_utc.timestamp_nanos_opt().unwrap_or(0).into())?;
-    let query_end_dt_offset = OffsetDateTime::from_unix_timestamp_nanos(query_end_dt_utc.timestamp_nanos_opt().unwrap_or(0).into())?;
+    let query_start_dt_offset = query_start_dt_utc.to_offset_date_time()?;
+    let query_end_dt_offset = query_end_dt_utc.to_offset_date_time()?;
 
     if verbose {
         println!(
@@ -232,7 +203,7 @@ pub async fn calculate_pmz(
 
     while let Some(record) = data_decoder.decode_record::<OhlcvMsg>().await? {
          record_count += 1;
-         let candle = Candle::new(&record, &symbol);
+         let candle = Candle::new(record, symbol)?;
          all_one_min_candles.push(candle);
     }
 
@@ -351,15 +322,7 @@ pub async fn calculate_pmz(
                 }
 
                 // Convert chrono::NaiveDate to time::Date
-                // Convert month() u32 to u8 before TryFrom
-                let month_u8 = current_trading_day_naive.month() as u8;
-                let time_month = time::Month::try_from(month_u8)?;
-
-                let target_date_time = Date::from_calendar_date(
-                    current_trading_day_naive.year(),
-                    time_month, // Use the converted time::Month
-                    current_trading_day_naive.day() as u8,
-                )?;
+                let target_date_time = current_trading_day_naive.to_time_date()?;
 
                 // Construct DateRange using From trait
                 let date_range = DateRange::from((target_date_time, target_date_time));
@@ -1,14 +1,15 @@
-//! Example to retrieve 5-minute historical candles for ES futures over the last 5 days.
+//! Example to retrieve 5-minute historical candles for ES futures over the last 5 trading sessions.
 use std::{collections::HashMap, error::Error};
 
-use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use chrono::{DateTime, Datelike, Timelike, Utc};
 use chrono_tz::US::Eastern;
 use databento::{
+    calendar::{Session, TradingCalendar},
     dbn::{OhlcvMsg, Schema, SType},
-    historical::timeseries::GetRangeParams,
+    historical::{timeseries::GetRangeParams, DateTimeRange},
+    timeconv::ToChrono,
     HistoricalClient,
 };
-use time;
 
 // A simplified representation of an OHLCV candle for display and aggregation
 struct Candle {
@@ -23,12 +24,9 @@ struct Candle {
 }
 
 impl Candle {
-    fn new(ohlcv: &OhlcvMsg, symbol_map: &HashMap<u32, String>) -> Self {
+    fn new(ohlcv: &OhlcvMsg, symbol: Option<&str>) -> databento::Result<Self> {
         // Convert timestamp from nanos to a DateTime (UTC)
-        let ts_nanos = ohlcv.hd.ts_event as i64;
-        let seconds = ts_nanos / 1_000_000_000;
-        let nanos = (ts_nanos % 1_000_000_000) as u32;
-        let utc_timestamp = Utc.timestamp_opt(seconds, nanos).single().unwrap();
+        let utc_timestamp = ohlcv.hd.ts_event.to_chrono()?;
         
         // Convert UTC to Eastern Time
         let est_timestamp = utc_timestamp.with_timezone(&Eastern);
@@ -37,11 +35,11 @@ impl Candle {
         let scaling_factor = 0.000000001;
         
         // Look up the symbol for this instrument id, or use a placeholder
-        let symbol = symbol_map.get(&ohlcv.hd.instrument_id)
-            .cloned()
+        let symbol = symbol
+            .map(str::to_owned)
             .unwrap_or_else(|| format!("Unknown_{}", ohlcv.hd.instrument_id));
         
-        Candle {
+        Ok(Candle {
             timestamp: est_timestamp,
             instrument_id: ohlcv.hd.instrument_id,
             symbol,
@@ -50,7 +48,7 @@ impl Candle {
             low: ohlcv.low as f64 * scaling_factor,
             close: ohlcv.close as f64 * scaling_factor,
             volume: ohlcv.volume,
-        }
+        })
     }
 
     // Format the timestamp to yyyy-mm-dd HH:MM (Eastern Time)
@@ -91,7 +89,7 @@ fn aggregate_to_5min(candles: &[Candle]) -> Vec<Candle> {
         }
 
         // Parse the key back to a DateTime in Eastern Time
-        let timestamp = match DateTime::parse_from_str(&format!("{}:00 {}", timestamp_key, group[0].timestamp.format("%z").to_string()), "%Y-%m-%d %H:%M:%S %z") {
+        let timestamp = match DateTime::parse_from_str(&format!("{}:00 {}", timestamp_key, group[0].timestamp.format("%z")), "%Y-%m-%d %H:%M:%S %z") {
             Ok(dt) => dt.with_timezone(&Eastern),
             Err(_) => continue,
         };
@@ -117,16 +115,7 @@ fn aggregate_to_5min(candles: &[Candle]) -> Vec<Candle> {
 
     // Sort by timestamp and then by instrument ID
     result.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.instrument_id.cmp(&b.instrument_id)));
-    return result;
-}
-
-// Convert from chrono::DateTime to time::OffsetDateTime
-// Note: We convert from Eastern Time to UTC when passing to the API
-fn chrono_to_time_datetime(dt: &DateTime<chrono_tz::Tz>) -> time::OffsetDateTime {
-    // Convert Eastern time to UTC before getting timestamp
-    let utc_dt = dt.with_timezone(&Utc);
-    let nanos = utc_dt.timestamp_nanos_opt().unwrap();
-    time::OffsetDateTime::from_unix_timestamp_nanos(nanos as i128).unwrap()
+    result
 }
 
 #[tokio::main]
@@ -143,56 +132,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
     println!("Building client...");
     let mut client = HistoricalClient::builder().key_from_env()?.build()?;
     
-    // Get current time in Eastern Time Zone
-    let now_eastern = Utc::now().with_timezone(&Eastern);
+    // CME Globex sessions open at 6pm ET the evening before the trading day and close
+    // at 5pm ET, which skips weekends and the daily maintenance break
+    let globex = Session::CME_GLOBEX;
     
-    // Determine a valid market time that avoids weekends and maintenance break
-    // Futures market hours: Sunday 6pm to Friday 5pm EST (except 5-6pm EST daily maintenance)
-    let mut end_time = now_eastern;
-    
-    // Adjust for weekend - if it's weekend, move to Friday 4:30pm
-    let weekday = end_time.weekday();
-    if weekday == chrono::Weekday::Sat || 
-       (weekday == chrono::Weekday::Sun && end_time.hour() < 18) || 
-       (weekday == chrono::Weekday::Fri && end_time.hour() >= 17) {
-        // Find the most recent Friday at 4:30pm EST
-        let days_to_subtract = match weekday {
-            chrono::Weekday::Sat => 1,
-            chrono::Weekday::Sun => if end_time.hour() < 18 { 2 } else { 0 },
-            chrono::Weekday::Fri => if end_time.hour() >= 17 { 0 } else { 7 },
-            _ => 0,
-        };
-        
-        if days_to_subtract > 0 {
-            end_time = (end_time - Duration::days(days_to_subtract))
-                .with_hour(16)
-                .unwrap()
-                .with_minute(30)
-                .unwrap()
-                .with_second(0)
-                .unwrap()
-                .with_nanosecond(0)
-                .unwrap();
-        }
-    }
-    
-    // Avoid the daily maintenance break (5pm-6pm EST)
-    if end_time.hour() == 17 {
-        // Move to 4:30pm instead
-        end_time = end_time
-            .with_hour(16)
-            .unwrap()
-            .with_minute(30)
-            .unwrap();
+    // Cover the last 5 complete sessions
+    let today = Utc::now().with_timezone(&Eastern).date_naive();
+    let last_day = globex.previous_trading_day(today);
+    let mut first_day = last_day;
+    for _ in 1..5 {
+        first_day = globex.previous_trading_day(first_day);
     }
-    
-    // Calculate start time (5 trading days back)
-    // Note: We're using calendar days here, not adjusting for weekends in the start time
-    let start_time = end_time - Duration::days(5);
-    
-    // Convert to time crate's OffsetDateTime for the API
-    let end_datetime = chrono_to_time_datetime(&end_time);
-    let start_datetime = chrono_to_time_datetime(&start_time);
+    let start_datetime = DateTimeRange::trading_day(first_day, &globex)?.start();
+    let end_datetime = DateTimeRange::trading_day(last_day, &globex)?.end();
+    let start_time = start_datetime.to_chrono()?.with_timezone(&Eastern);
+    let end_time = end_datetime.to_chrono()?.with_timezone(&Eastern);
     
     let dataset = "GLBX.MDP3";
     let symbol = "ES.FUT"; // ES futures
@@ -209,13 +163,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 .symbols(symbol)
                 .stype_in(SType::Parent)
                 .stype_out(SType::InstrumentId)
-                .date_range(databento::historical::DateTimeRange::from((start_datetime, end_datetime)))
+                .date_range(DateTimeRange::from((start_datetime, end_datetime)))
                 .build(),
         )
         .await?;
     
-    // Create a mapping from instrument ID to symbol
-    let mut instrument_id_to_symbol: HashMap<u32, String> = HashMap::new();
+    // Create a mapping between instrument IDs and symbols over time
+    let timeline = resolution.timeline()?;
     
     // Print detailed metadata for all instruments
     println!("\nInstrument Metadata from Symbol Resolution:");
@@ -223,17 +177,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
              "Instrument ID", "Symbol", "Date Range");
     println!("{:-<12} | {:-<15} | {:-<20}", "", "", "");
     
-    for (symbol, mappings) in &resolution.mappings {
-        for mapping in mappings {
-            let instrument_id = mapping.symbol.parse::<u32>().unwrap_or_default();
-            instrument_id_to_symbol.insert(instrument_id, symbol.clone());
-            
+    for symbol in resolution.mappings.keys() {
+        for (start_date, end_date, instrument_id) in timeline.instruments(symbol) {
             // Print metadata for all instruments
             println!("{:<12} | {:<15} | {} to {}", 
                     instrument_id, 
                     symbol,
-                    mapping.start_date,
-                    mapping.end_date);
+                    start_date,
+                    end_date);
         }
     }
     println!();
@@ -241,7 +192,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Request 1-minute candles
     let mut decoder = client
         .timeseries()
-        .get_range(
+        .get_range_with_symbols(
             &GetRangeParams::builder()
                 .dataset(dataset)
                 .date_time_range((start_datetime, end_datetime))
@@ -256,8 +207,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
     
     // Process the OHLCV messages
     let mut candles = Vec::new();
-    while let Some(ohlcv) = decoder.decode_record::<OhlcvMsg>().await? {
-        candles.push(Candle::new(&ohlcv, &instrument_id_to_symbol));
+    while let Some((ohlcv, symbol)) = decoder.decode_record_with_symbol::<OhlcvMsg>().await? {
+        candles.push(Candle::new(ohlcv, symbol)?);
     }
     
     println!("Retrieved {} one-minute candles", candles.len());
@@ -0,0 +1,52 @@
+//! Prints the settlement price and open interest of the front-month ES contract.
+use std::error::Error;
+
+use chrono::NaiveDate;
+use databento::{
+    dbn::{SType, Schema, StatMsg},
+    historical::timeseries::GetRangeParams,
+    statistics::{get_settlement, OpenInterest},
+    HistoricalClient,
+};
+use time::macros::date;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut client = HistoricalClient::builder().key_from_env()?.build()?;
+    let dataset = "GLBX.MDP3";
+    let symbol = "ES.c.0";
+
+    let date = NaiveDate::from_ymd_opt(2025, 4, 21).ok_or("invalid date")?;
+    match get_settlement(&mut client, dataset, symbol, SType::Continuous, date).await? {
+        Some(settlement) => println!(
+            "{symbol} {} settlement: {:.2} ({})",
+            settlement.date,
+            settlement.price,
+            if settlement.is_final {
+                "final"
+            } else {
+                "preliminary"
+            }
+        ),
+        None => println!("No settlement for {symbol} on {date}"),
+    }
+
+    let mut decoder = client
+        .timeseries()
+        .get_range(
+            &GetRangeParams::builder()
+                .dataset(dataset)
+                .symbols(symbol)
+                .stype_in(SType::Continuous)
+                .schema(Schema::Statistics)
+                .date_time_range(date!(2025 - 04 - 21))
+                .build(),
+        )
+        .await?;
+    while let Some(stat) = decoder.decode_record::<StatMsg>().await? {
+        if let Some(oi) = OpenInterest::from_stat(stat) {
+            println!("{symbol} open interest at {}: {}", oi.ts_event, oi.quantity);
+        }
+    }
+    Ok(())
+}
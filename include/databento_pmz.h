@@ -0,0 +1,915 @@
+#ifndef DATABENTO_PMZ_H
+#define DATABENTO_PMZ_H
+
+/* Generated by cbindgen from src/ffi.rs. Don't edit by hand; rebuild with `--features cbindgen`. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the Pre-Market High is missing
+ */
+#define PMZ_MISSING_PMH (1 << 0)
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the Pre-Market Low is missing
+ */
+#define PMZ_MISSING_PML (1 << 1)
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the previous day's LIS is missing
+ */
+#define PMZ_MISSING_PREV_DAY_LIS (1 << 2)
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the 9:25 close is missing
+ */
+#define PMZ_MISSING_CLOSE_925 (1 << 3)
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the gap direction is unknown
+ */
+#define PMZ_MISSING_GAP_DIRECTION (1 << 4)
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the PMZ high is missing
+ */
+#define PMZ_MISSING_PMZ_HIGH (1 << 5)
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the PMZ low is missing
+ */
+#define PMZ_MISSING_PMZ_LOW (1 << 6)
+
+/**
+ * Bit set in `CPmzResult::missing_flags` when the risk is missing
+ */
+#define PMZ_MISSING_RISK (1 << 7)
+
+/**
+ * The layout version of `CPmzResult` returned by this library, in its
+ * `struct_version` field. Fields are only ever appended after version 3, which moved
+ * `struct_version` to the start of the struct after `struct_size`, so a consumer can
+ * read any field introduced at or before the version it finds.
+ */
+#define PMZ_RESULT_VERSION 3
+
+/**
+ * The version of the FFI layer returned by `db_ffi_version`. It's incremented whenever
+ * a struct layout or function signature changes.
+ */
+#define DB_FFI_VERSION 2
+
+/**
+ * Error codes for PMZ calculation functions.
+ */
+typedef enum {
+  /**
+   * No error occurred
+   */
+  PMZ_ERROR_CODE_SUCCESS = 0,
+  /**
+   * Invalid API key
+   */
+  PMZ_ERROR_CODE_INVALID_API_KEY = 1,
+  /**
+   * Invalid date format
+   */
+  PMZ_ERROR_CODE_INVALID_DATE = 2,
+  /**
+   * API request failed
+   */
+  PMZ_ERROR_CODE_API_REQUEST_FAILED = 3,
+  /**
+   * Data processing failed
+   */
+  PMZ_ERROR_CODE_DATA_PROCESSING_FAILED = 4,
+  /**
+   * Insufficient data for calculation
+   */
+  PMZ_ERROR_CODE_INSUFFICIENT_DATA = 5,
+  /**
+   * The request was cancelled with `db_request_cancel`
+   */
+  PMZ_ERROR_CODE_CANCELLED = 6,
+  /**
+   * Other error
+   */
+  PMZ_ERROR_CODE_OTHER = 99,
+} PmzErrorCode;
+
+/**
+ * What a level represents.
+ */
+typedef enum {
+  /**
+   * The PMZ high.
+   */
+  LEVEL_KIND_PMZ_HIGH = 0,
+  /**
+   * The PMZ low.
+   */
+  LEVEL_KIND_PMZ_LOW = 1,
+  /**
+   * The previous day's line in the sand (LIS).
+   */
+  LEVEL_KIND_LIS = 2,
+  /**
+   * A user-defined level.
+   */
+  LEVEL_KIND_CUSTOM = 3,
+} LevelKind;
+
+/**
+ * When a level alerts again after it's been crossed. See `RetriggerPolicy`.
+ */
+typedef enum {
+  /**
+   * Alert on every crossing
+   */
+  C_RETRIGGER_POLICY_ALWAYS = 0,
+  /**
+   * Alert on a crossing only if the previous alert was at least `debounce_ms` before
+   */
+  C_RETRIGGER_POLICY_DEBOUNCE = 1,
+  /**
+   * Alert on the first crossing only, then remove the level
+   */
+  C_RETRIGGER_POLICY_ONCE = 2,
+} CRetriggerPolicy;
+
+/**
+ * The side of an order.
+ */
+typedef enum {
+  /**
+   * Buy, increasing the position.
+   */
+  ORDER_SIDE_BUY = 0,
+  /**
+   * Sell, decreasing the position.
+   */
+  ORDER_SIDE_SELL = 1,
+} OrderSide;
+
+/**
+ * An opaque handle to an alert engine for registering price levels and polling
+ * alerts when prices cross them.
+ */
+typedef struct DbAlertEngine DbAlertEngine;
+
+/**
+ * A reusable handle wrapping a blocking historical client.
+ *
+ * The handle can be shared between threads, and each call made with it reuses the
+ * same connection pool, avoiding a new TLS handshake per call.
+ */
+typedef struct DbClient DbClient;
+
+/**
+ * An opaque handle to a position and PnL tracker for one instrument.
+ */
+typedef struct DbPnlTracker DbPnlTracker;
+
+/**
+ * An opaque handle to a board of the latest quote of each instrument.
+ */
+typedef struct DbQuoteBoard DbQuoteBoard;
+
+/**
+ * An opaque token for cancelling an in-flight request from another thread.
+ */
+typedef struct DbRequest DbRequest;
+
+/**
+ * C-compatible PMZ result struct
+ */
+typedef struct {
+  /**
+   * The size of this struct in bytes, for detecting a mismatched layout
+   */
+  uint32_t struct_size;
+  /**
+   * The layout version of this struct, `PMZ_RESULT_VERSION`
+   */
+  uint32_t struct_version;
+  /**
+   * Error code (0 = success). `InsufficientData` with a non-null `date` indicates a
+   * partial result: see `missing_flags`
+   */
+  PmzErrorCode error_code;
+  /**
+   * Error message if error_code != 0, otherwise null
+   */
+  char *error_message;
+  /**
+   * Date for which PMZ values were calculated (format: YYYY-MM-DD)
+   */
+  char *date;
+  /**
+   * Pre-Market High value, NaN if missing
+   */
+  double pmh;
+  /**
+   * Pre-Market Low value, NaN if missing
+   */
+  double pml;
+  /**
+   * Previous day's Line in Sand (LIS) value, NaN if missing
+   */
+  double prev_day_lis;
+  /**
+   * Indicates if market gapped up (1) or down (0), -1 if unknown
+   */
+  int32_t is_gap_up;
+  /**
+   * PMZ high value (buy zone), NaN if missing
+   */
+  double pmz_high;
+  /**
+   * PMZ low value (sell zone), NaN if missing
+   */
+  double pmz_low;
+  /**
+   * Risk value (PMZ High - PMZ Low), NaN if missing
+   */
+  double risk;
+  /**
+   * Bitmask of the components that couldn't be calculated (0 = complete).
+   * See the `PMZ_MISSING_*` constants for the bit of each component.
+   */
+  uint32_t missing_flags;
+  /**
+   * The 9:25 close minus the previous day's LIS in points, NaN if unknown
+   */
+  double gap_points;
+  /**
+   * The gap as a percentage of the previous day's LIS, NaN if unknown
+   */
+  double gap_percent;
+  /**
+   * The UTC offset of New York time on `date` in seconds, e.g. -14400 for EDT, 0 if
+   * unknown
+   */
+  int32_t utc_offset_secs;
+  /**
+   * Start of the pre-market window as UNIX nanoseconds in UTC, 0 if unknown
+   */
+  uint64_t pmz_window_start_ns;
+  /**
+   * End of the pre-market window (exclusive) as UNIX nanoseconds in UTC, 0 if unknown
+   */
+  uint64_t pmz_window_end_ns;
+  /**
+   * Start of the previous day's LIS candle as UNIX nanoseconds in UTC, 0 if unknown
+   */
+  uint64_t lis_start_ns;
+  /**
+   * End of the previous day's LIS candle (exclusive) as UNIX nanoseconds in UTC, 0 if
+   * unknown
+   */
+  uint64_t lis_end_ns;
+} CPmzResult;
+
+/**
+ * A callback invoked with the result of an asynchronous PMZ calculation and the
+ * `user_data` passed when starting it. The callback owns `result` and must free it by
+ * calling `pmz_free_result`.
+ */
+typedef void (*PmzCallback)(CPmzResult *result, void *user_data);
+
+/**
+ * A callback invoked with each progress or diagnostic message of a PMZ calculation
+ * and the `user_data` passed when starting it. `message` is a null-terminated C
+ * string that's only valid for the duration of the call.
+ */
+typedef void (*PmzDiagnosticCallback)(const char *message, void *user_data);
+
+/**
+ * C-compatible alert for a price crossing a level
+ */
+typedef struct {
+  /**
+   * The size of this struct in bytes. The caller must set it before passing the
+   * struct to `db_alerts_poll`, which checks it's large enough and sets it to the
+   * size it wrote
+   */
+  uint32_t struct_size;
+  /**
+   * The ID returned by `db_alerts_add_level`
+   */
+  uint64_t level_id;
+  /**
+   * What the level represents
+   */
+  LevelKind kind;
+  /**
+   * The price of the level
+   */
+  double level_price;
+  /**
+   * 1 if the price rose through the level, -1 if it fell
+   */
+  int32_t direction;
+  /**
+   * The instrument ID of the trade
+   */
+  uint32_t instrument_id;
+  /**
+   * The trade price
+   */
+  double price;
+  /**
+   * The trade timestamp in nanoseconds since the UNIX epoch
+   */
+  uint64_t ts_event;
+} CAlertEvent;
+
+/**
+ * C-compatible position and PnL. See `PnL`.
+ */
+typedef struct {
+  /**
+   * The size of this struct in bytes. The caller must set it before passing the
+   * struct to `db_pnl_get`, which checks it's large enough and sets it to the
+   * size it wrote
+   */
+  uint32_t struct_size;
+  /**
+   * The net quantity, negative when short
+   */
+  int64_t position;
+  /**
+   * The average entry price of the open position, NaN if flat
+   */
+  double average_price;
+  /**
+   * The latest price the position was marked at, NaN if none
+   */
+  double mark_price;
+  /**
+   * The realized PnL in ticks
+   */
+  double realized_ticks;
+  /**
+   * The realized PnL in dollars
+   */
+  double realized_dollars;
+  /**
+   * The unrealized PnL in ticks, 0 when there's no mark price
+   */
+  double unrealized_ticks;
+  /**
+   * The unrealized PnL in dollars, 0 when there's no mark price
+   */
+  double unrealized_dollars;
+} CPnL;
+
+/**
+ * C-compatible best bid and offer. See `Quote`.
+ */
+typedef struct {
+  /**
+   * The size of this struct in bytes. The caller must set it before passing the
+   * struct to `db_quotes_get`, which checks it's large enough and sets it to the
+   * size it wrote
+   */
+  uint32_t struct_size;
+  /**
+   * The instrument ID
+   */
+  uint32_t instrument_id;
+  /**
+   * The best bid price, NaN if there's no bid
+   */
+  double bid_px;
+  /**
+   * The best offer price, NaN if there's no offer
+   */
+  double ask_px;
+  /**
+   * The total quantity at the best bid
+   */
+  uint32_t bid_sz;
+  /**
+   * The total quantity at the best offer
+   */
+  uint32_t ask_sz;
+  /**
+   * When the exchange published the update in nanoseconds since the UNIX epoch
+   */
+  uint64_t ts_event;
+  /**
+   * When Databento received the update in nanoseconds since the UNIX epoch
+   */
+  uint64_t ts_recv;
+} CQuote;
+
+/**
+ * C-compatible prior-day key levels result struct
+ */
+typedef struct {
+  /**
+   * The size of this struct in bytes, for detecting a mismatched layout
+   */
+  uint32_t struct_size;
+  /**
+   * Error code (0 = success)
+   */
+  PmzErrorCode error_code;
+  /**
+   * Error message if error_code != 0, otherwise null
+   */
+  char *error_message;
+  /**
+   * Trading date the levels are for (format: YYYY-MM-DD)
+   */
+  char *date;
+  /**
+   * Previous trading date (format: YYYY-MM-DD)
+   */
+  char *prev_date;
+  /**
+   * Previous regular trading hours (RTH) session high, NaN if missing
+   */
+  double prev_high;
+  /**
+   * Previous RTH session low, NaN if missing
+   */
+  double prev_low;
+  /**
+   * Previous RTH session close, NaN if missing
+   */
+  double prev_close;
+  /**
+   * Previous day's settlement price, NaN if missing
+   */
+  double prev_settlement;
+  /**
+   * High between the previous RTH close and the current RTH open, NaN if missing
+   */
+  double overnight_high;
+  /**
+   * Low between the previous RTH close and the current RTH open, NaN if missing
+   */
+  double overnight_low;
+} CLevelsResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Frees memory allocated by `pmz_calculate`.
+ * 
+ * # Safety
+ * 
+ * This function must be called with a pointer returned by `pmz_calculate`.
+ * Calling it with any other pointer is undefined behavior.
+ */
+void pmz_free_result(CPmzResult *result);
+
+/**
+ * Calculates PMZ (Pre-Market Zone) values for E-mini S&P 500 futures.
+ * 
+ * # Parameters
+ * 
+ * * `api_key` - Databento API key (null-terminated C string)
+ * * `date` - Optional date in YYYY-MM-DD format (null-terminated C string), or NULL for today
+ * 
+ * # Returns
+ * 
+ * A pointer to a heap-allocated `CPmzResult` struct. The caller must free this memory
+ * by calling `pmz_free_result` when done.
+ * 
+ * # Safety
+ * 
+ * This function is unsafe because it interacts with C strings and memory that
+ * crosses the FFI boundary.
+ */
+CPmzResult *pmz_calculate(const char *api_key, const char *date);
+
+/**
+ * Starts calculating PMZ values like `pmz_calculate` on a worker thread and returns
+ * immediately. `callback` is invoked on the worker thread when finished.
+ *
+ * # Parameters
+ *
+ * * `api_key` - Databento API key (null-terminated C string)
+ * * `date` - Optional date in YYYY-MM-DD format (null-terminated C string), or NULL for today
+ * * `callback` - Function called with the result and `user_data`
+ * * `user_data` - Opaque pointer passed through to `callback`
+ *
+ * # Returns
+ *
+ * `Success` if the calculation was started, otherwise an error code, in which case
+ * `callback` isn't invoked. `api_key` and `date` are copied, so they can be freed once
+ * this function returns.
+ *
+ * # Safety
+ *
+ * `api_key` and `date` must be null or valid null-terminated C strings, and
+ * `callback` must be safe to call from another thread with `user_data`.
+ */
+PmzErrorCode pmz_calculate_async(const char *api_key,
+                                 const char *date,
+                                 PmzCallback callback,
+                                 void *user_data);
+
+/**
+ * Creates a client handle for use with `pmz_calculate_with_client`.
+ *
+ * # Parameters
+ *
+ * * `api_key` - Databento API key (null-terminated C string)
+ *
+ * # Returns
+ *
+ * A pointer to a heap-allocated `DbClient`, or NULL if the API key is invalid or the
+ * client couldn't be created, in which case `db_last_error_message` describes why.
+ * The caller must free the handle by calling `db_client_destroy` when done.
+ *
+ * # Safety
+ *
+ * `api_key` must be null or a valid null-terminated C string.
+ */
+DbClient *db_client_create(const char *api_key);
+
+/**
+ * Frees a client handle created by `db_client_create`.
+ *
+ * # Safety
+ *
+ * This function must be called with a pointer returned by `db_client_create` once no
+ * other thread is using it. Calling it with any other pointer is undefined behavior.
+ */
+void db_client_destroy(DbClient *client);
+
+/**
+ * Checks that an API key is valid by making a cheap authenticated request to
+ * Databento, e.g. to verify credentials at startup before calculating PMZ values.
+ *
+ * # Parameters
+ *
+ * * `api_key` - Databento API key (null-terminated C string)
+ *
+ * # Returns
+ *
+ * `Success` if the key is valid, `InvalidApiKey` if it's malformed or rejected by
+ * Databento, or another error code if the request failed, in which case
+ * `db_last_error_message` describes why.
+ *
+ * # Safety
+ *
+ * `api_key` must be null or a valid null-terminated C string.
+ */
+PmzErrorCode pmz_validate_key(const char *api_key);
+
+/**
+ * Calculates PMZ values like `pmz_calculate`, but with a client handle created by
+ * `db_client_create`. The handle can be used from multiple threads concurrently.
+ *
+ * # Returns
+ *
+ * A pointer to a heap-allocated `CPmzResult` struct. The caller must free this memory
+ * by calling `pmz_free_result` when done.
+ *
+ * # Safety
+ *
+ * `client` must be null or a pointer returned by `db_client_create` that hasn't been
+ * destroyed, and `date` must be null or a valid null-terminated C string.
+ */
+CPmzResult *pmz_calculate_with_client(const DbClient *client, const char *date);
+
+/**
+ * Calculates PMZ values like `pmz_calculate_with_client`, passing each progress and
+ * diagnostic message to `callback` as the calculation runs. `callback` is invoked on
+ * the calling thread before this function returns.
+ *
+ * # Returns
+ *
+ * A pointer to a heap-allocated `CPmzResult` struct. The caller must free this memory
+ * by calling `pmz_free_result` when done.
+ *
+ * # Safety
+ *
+ * `client` must be null or a pointer returned by `db_client_create` that hasn't been
+ * destroyed, `date` must be null or a valid null-terminated C string, and `callback`
+ * must be null or safe to call with `user_data`.
+ */
+CPmzResult *pmz_calculate_with_diagnostics(const DbClient *client,
+                                           const char *date,
+                                           PmzDiagnosticCallback callback,
+                                           void *user_data);
+
+/**
+ * Creates a request token to pass to `pmz_calculate_cancellable`.
+ *
+ * # Returns
+ *
+ * A pointer to a heap-allocated `DbRequest`. The caller must free it by calling
+ * `db_request_free` once the request has finished.
+ */
+DbRequest *db_request_begin(void);
+
+/**
+ * Cancels the request associated with `request`. The call using it returns promptly
+ * with `PmzErrorCode::Cancelled`, and calls made with an already-cancelled token
+ * return immediately. Can be called from any thread.
+ *
+ * # Safety
+ *
+ * `request` must be null or a pointer returned by `db_request_begin` that hasn't been
+ * freed.
+ */
+void db_request_cancel(const DbRequest *request);
+
+/**
+ * Frees a request token created by `db_request_begin`.
+ *
+ * # Safety
+ *
+ * This function must be called with a pointer returned by `db_request_begin` once no
+ * call is using it. Calling it with any other pointer is undefined behavior.
+ */
+void db_request_free(DbRequest *request);
+
+/**
+ * Calculates PMZ values like `pmz_calculate_with_client`, but can be aborted from
+ * another thread by passing `request` to `db_request_cancel`. Cancelling drops the
+ * in-flight historical requests.
+ *
+ * # Returns
+ *
+ * A pointer to a heap-allocated `CPmzResult` struct. The caller must free this memory
+ * by calling `pmz_free_result` when done.
+ *
+ * # Safety
+ *
+ * `client` must be null or a pointer returned by `db_client_create` that hasn't been
+ * destroyed, `date` must be null or a valid null-terminated C string, and `request`
+ * must be null or a pointer returned by `db_request_begin` that hasn't been freed.
+ */
+CPmzResult *pmz_calculate_cancellable(const DbClient *client,
+                                      const char *date,
+                                      const DbRequest *request);
+
+/**
+ * Creates an alert engine that buffers up to `capacity` alerts for
+ * `db_alerts_poll`. The caller must free the handle by calling `db_alerts_destroy`
+ * when done.
+ */
+DbAlertEngine *db_alerts_create(uintptr_t capacity);
+
+/**
+ * Frees an alert engine created by `db_alerts_create`.
+ *
+ * # Safety
+ *
+ * This function must be called with a pointer returned by `db_alerts_create` once no
+ * other thread is using it. Calling it with any other pointer is undefined behavior.
+ */
+void db_alerts_destroy(DbAlertEngine *engine);
+
+/**
+ * Registers a level at `price`. `kind` is a `LevelKind` and `policy` a
+ * `CRetriggerPolicy`. `debounce_ms` is only used with `CRetriggerPolicy::Debounce`.
+ *
+ * # Returns
+ *
+ * The level's ID for `db_alerts_remove_level`, or -1 if `engine` is null or `kind` or
+ * `policy` isn't one of their values.
+ *
+ * # Safety
+ *
+ * `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+ * destroyed.
+ */
+int64_t db_alerts_add_level(const DbAlertEngine *engine,
+                            uint32_t kind,
+                            double price,
+                            uint32_t policy,
+                            uint64_t debounce_ms);
+
+/**
+ * Removes the level with `level_id`, returning `false` if it wasn't registered.
+ *
+ * # Safety
+ *
+ * `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+ * destroyed.
+ */
+bool db_alerts_remove_level(const DbAlertEngine *engine, uint64_t level_id);
+
+/**
+ * Checks a trade at `price` against the registered levels.
+ *
+ * # Returns
+ *
+ * The number of alerts generated, which can be retrieved with `db_alerts_poll`.
+ *
+ * # Safety
+ *
+ * `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+ * destroyed.
+ */
+uintptr_t db_alerts_on_trade(const DbAlertEngine *engine,
+                             uint32_t instrument_id,
+                             double price,
+                             uint64_t ts_event);
+
+/**
+ * Retrieves the oldest unread alert without blocking. Alerts are dropped when more
+ * than the engine's capacity are unread.
+ *
+ * # Returns
+ *
+ * `true` if an alert was written to `event`, `false` if there are none or
+ * `event.struct_size` is smaller than this library's `CAlertEvent`.
+ *
+ * # Safety
+ *
+ * `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+ * destroyed, and `event` must be null or valid for reads and writes of
+ * `event.struct_size` bytes.
+ */
+bool db_alerts_poll(const DbAlertEngine *engine, CAlertEvent *event);
+
+/**
+ * Creates a position and PnL tracker for the instrument with `instrument_id`, whose
+ * `tick_size` and `multiplier` can be taken from its instrument definition. The
+ * caller must free the handle by calling `db_pnl_destroy` when done.
+ */
+DbPnlTracker *db_pnl_create(uint32_t instrument_id, double tick_size, double multiplier);
+
+/**
+ * Frees a tracker created by `db_pnl_create`.
+ *
+ * # Safety
+ *
+ * This function must be called with a pointer returned by `db_pnl_create` once no
+ * other thread is using it. Calling it with any other pointer is undefined behavior.
+ */
+void db_pnl_destroy(DbPnlTracker *tracker);
+
+/**
+ * Applies a fill of `quantity` contracts at `price` and marks the position at it.
+ * `side` is an `OrderSide`.
+ *
+ * # Returns
+ *
+ * `false` if `tracker` is null or `side` isn't one of the values of `OrderSide`.
+ *
+ * # Safety
+ *
+ * `tracker` must be null or a pointer returned by `db_pnl_create` that hasn't been
+ * destroyed.
+ */
+bool db_pnl_on_fill(const DbPnlTracker *tracker, uint32_t side, uint32_t quantity, double price);
+
+/**
+ * Marks the position at the price of a trade. Trades in other instruments are
+ * ignored.
+ *
+ * # Safety
+ *
+ * `tracker` must be null or a pointer returned by `db_pnl_create` that hasn't been
+ * destroyed.
+ */
+void db_pnl_on_trade(const DbPnlTracker *tracker, uint32_t instrument_id, double price);
+
+/**
+ * Retrieves the current position and PnL.
+ *
+ * # Returns
+ *
+ * `true` if the PnL was written to `pnl`, `false` if either pointer is null or
+ * `pnl.struct_size` is smaller than this library's `CPnL`.
+ *
+ * # Safety
+ *
+ * `tracker` must be null or a pointer returned by `db_pnl_create` that hasn't been
+ * destroyed, and `pnl` must be null or valid for reads and writes of
+ * `pnl.struct_size` bytes.
+ */
+bool db_pnl_get(const DbPnlTracker *tracker, CPnL *pnl);
+
+/**
+ * Creates an empty quote board. The caller must free the handle by calling
+ * `db_quotes_destroy` when done.
+ */
+DbQuoteBoard *db_quotes_create(void);
+
+/**
+ * Frees a quote board created by `db_quotes_create`.
+ *
+ * # Safety
+ *
+ * This function must be called with a pointer returned by `db_quotes_create` once no
+ * other thread is using it. Calling it with any other pointer is undefined behavior.
+ */
+void db_quotes_destroy(DbQuoteBoard *board);
+
+/**
+ * Updates the quote of the instrument with `instrument_id` and maps `symbol` to it
+ * unless `symbol` is null. Pass NaN for an empty side. Updates older than the current
+ * quote by `ts_recv` are ignored.
+ *
+ * # Returns
+ *
+ * `false` if `board` is null or `symbol` isn't valid UTF-8.
+ *
+ * # Safety
+ *
+ * `board` must be null or a pointer returned by `db_quotes_create` that hasn't been
+ * destroyed, and `symbol` must be null or a valid null-terminated string.
+ */
+bool db_quotes_update(const DbQuoteBoard *board,
+                      const char *symbol,
+                      uint32_t instrument_id,
+                      double bid_px,
+                      uint32_t bid_sz,
+                      double ask_px,
+                      uint32_t ask_sz,
+                      uint64_t ts_event,
+                      uint64_t ts_recv);
+
+/**
+ * Retrieves the latest quote of the instrument mapped to `symbol`.
+ *
+ * # Returns
+ *
+ * `true` if the quote was written to `quote`, `false` if there's no quote for
+ * `symbol`, any pointer is null, or `quote.struct_size` is smaller than this library's
+ * `CQuote`.
+ *
+ * # Safety
+ *
+ * `board` must be null or a pointer returned by `db_quotes_create` that hasn't been
+ * destroyed, `symbol` must be null or a valid null-terminated string, and `quote`
+ * must be null or valid for reads and writes of `quote.struct_size` bytes.
+ */
+bool db_quotes_get(const DbQuoteBoard *board, const char *symbol, CQuote *quote);
+
+/**
+ * Fetches the prior-day key levels of a CME Globex futures symbol: the previous
+ * regular trading hours (RTH) session's high, low, close, and settlement, and the
+ * overnight high and low before the RTH open.
+ *
+ * # Parameters
+ *
+ * * `api_key` - Databento API key (null-terminated C string)
+ * * `dataset` - Dataset code such as `GLBX.MDP3` (null-terminated C string)
+ * * `symbol` - Symbol such as `ES.c.0` or `ESM5` (null-terminated C string)
+ * * `date` - Optional date in YYYY-MM-DD format (null-terminated C string), or NULL for today
+ *
+ * # Returns
+ *
+ * A pointer to a heap-allocated `CLevelsResult` struct. The caller must free this
+ * memory by calling `levels_free_result` when done.
+ *
+ * # Safety
+ *
+ * `api_key`, `dataset`, `symbol`, and `date` must be null or valid null-terminated C
+ * strings.
+ */
+CLevelsResult *levels_calculate(const char *api_key,
+                                const char *dataset,
+                                const char *symbol,
+                                const char *date);
+
+/**
+ * Frees memory allocated by `levels_calculate`.
+ *
+ * # Safety
+ *
+ * This function must be called with a pointer returned by `levels_calculate`.
+ * Calling it with any other pointer is undefined behavior.
+ */
+void levels_free_result(CLevelsResult *result);
+
+/**
+ * Returns the version of the FFI layer of the loaded library, `DB_FFI_VERSION`. Callers
+ * should check it matches the version of the header or bindings they were built
+ * against before calling any other function.
+ */
+uint32_t db_ffi_version(void);
+
+/**
+ * Returns a description of the last error from an FFI function called on the current
+ * thread, or NULL if the last call succeeded. For `pmz_calculate_async`, errors from
+ * the calculation are set on the worker thread, so they can be retrieved from the
+ * callback.
+ *
+ * The returned string is owned by the library and is valid until the next FFI call
+ * on the same thread. It must not be freed.
+ */
+const char *db_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DATABENTO_PMZ_H */
@@ -0,0 +1,191 @@
+//! Adjusting equity candles for splits and dividends.
+//!
+//! Prices before a corporate action aren't comparable with those after it: a
+//! four-for-one split cuts the price to a quarter overnight, so levels and gaps
+//! spanning the split are meaningless. An [`AdjustmentTable`] holds the
+//! [`Adjustment`]s of each symbol, either supplied by the user or fetched from the
+//! adjustment factors API with
+//! [`AdjustmentClient`](crate::historical::adjustment::AdjustmentClient), and
+//! [`adjust_candles()`] applies them backward, scaling candles before each ex-date so
+//! the whole series is comparable with the latest prices.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::examples::es_futures_pmz::Candle;
+
+/// The kind of corporate action behind an [`Adjustment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdjustmentKind {
+    /// A stock split or reverse split, which also scales volumes.
+    Split,
+    /// A dividend, which only scales prices.
+    Dividend,
+    /// Any other action that only scales prices, e.g. a rights issue.
+    Other,
+}
+
+/// A price adjustment for a corporate action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustment {
+    /// The first date the security trades without the entitlement. Candles before
+    /// this date are adjusted.
+    pub ex_date: NaiveDate,
+    /// The factor to multiply prices before the ex-date by, e.g. `0.25` for a
+    /// four-for-one split.
+    pub factor: f64,
+    /// The kind of corporate action.
+    pub kind: AdjustmentKind,
+}
+
+impl Adjustment {
+    /// Creates an adjustment for a split of `new_shares` for every `old_shares`, e.g.
+    /// 4 for 1.
+    pub fn split(ex_date: NaiveDate, new_shares: u32, old_shares: u32) -> Self {
+        Self {
+            ex_date,
+            factor: f64::from(old_shares) / f64::from(new_shares),
+            kind: AdjustmentKind::Split,
+        }
+    }
+
+    /// Creates an adjustment for a cash dividend of `amount` per share, relative to the
+    /// close `prev_close` before the ex-date.
+    pub fn dividend(ex_date: NaiveDate, amount: f64, prev_close: f64) -> Self {
+        Self {
+            ex_date,
+            factor: 1.0 - amount / prev_close,
+            kind: AdjustmentKind::Dividend,
+        }
+    }
+}
+
+/// The adjustments of each symbol.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdjustmentTable {
+    adjustments: HashMap<String, Vec<Adjustment>>,
+}
+
+impl AdjustmentTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `adjustment` for `symbol`.
+    pub fn insert(&mut self, symbol: impl ToString, adjustment: Adjustment) {
+        let adjustments = self.adjustments.entry(symbol.to_string()).or_default();
+        adjustments.push(adjustment);
+        adjustments.sort_by_key(|adjustment| adjustment.ex_date);
+    }
+
+    /// Returns the adjustments of `symbol` in ex-date order.
+    pub fn get(&self, symbol: &str) -> &[Adjustment] {
+        self.adjustments.get(symbol).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the cumulative price and volume factors for a candle of `symbol` on
+    /// `date`: the products of the factors of every later ex-date.
+    pub fn factors_on(&self, symbol: &str, date: NaiveDate) -> (f64, f64) {
+        self.get(symbol)
+            .iter()
+            .filter(|adjustment| adjustment.ex_date > date)
+            .fold((1.0, 1.0), |(price, volume), adjustment| {
+                let volume = match adjustment.kind {
+                    AdjustmentKind::Split => volume / adjustment.factor,
+                    _ => volume,
+                };
+                (price * adjustment.factor, volume)
+            })
+    }
+
+    /// Creates a table from the factors returned by the adjustment factors API. Event
+    /// types containing `SPLT`, like forward and reverse splits, are splits, and those
+    /// starting with `DIV` are dividends.
+    #[cfg(feature = "historical")]
+    pub fn from_factors(factors: &[crate::historical::adjustment::AdjustmentFactor]) -> Self {
+        let mut table = Self::new();
+        for factor in factors {
+            let kind = if factor.event_type.contains("SPLT") {
+                AdjustmentKind::Split
+            } else if factor.event_type.starts_with("DIV") {
+                AdjustmentKind::Dividend
+            } else {
+                AdjustmentKind::Other
+            };
+            let ex_date = factor.ex_date;
+            let Some(ex_date) = NaiveDate::from_ymd_opt(
+                ex_date.year(),
+                ex_date.month() as u32,
+                ex_date.day().into(),
+            ) else {
+                continue;
+            };
+            table.insert(
+                &factor.symbol,
+                Adjustment {
+                    ex_date,
+                    factor: factor.factor,
+                    kind,
+                },
+            );
+        }
+        table
+    }
+}
+
+/// Adjusts `candles` in place for the adjustments of their symbols in `table`, scaling
+/// the prices of each candle by the factors of every later ex-date and the volumes by
+/// the inverse of the split factors. Candles are dated by their local date.
+pub fn adjust_candles(candles: &mut [Candle], table: &AdjustmentTable) {
+    for candle in candles {
+        let (price, volume) = table.factors_on(&candle.symbol, candle.timestamp.date_naive());
+        if price != 1.0 {
+            candle.open *= price;
+            candle.high *= price;
+            candle.low *= price;
+            candle.close *= price;
+        }
+        if volume != 1.0 {
+            candle.volume = (candle.volume as f64 * volume).round() as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn candle(day: u32, close: f64, volume: u64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2024, 6, day, 9, 30))
+            .symbol("NVDA")
+            .price(close)
+            .volume(volume)
+            .build()
+    }
+
+    #[test]
+    fn test_adjust_candles() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 6, day).unwrap();
+        let mut table = AdjustmentTable::new();
+        table.insert("NVDA", Adjustment::dividend(date(11), 0.1, 100.0));
+        table.insert("NVDA", Adjustment::split(date(10), 10, 1));
+        assert_eq!(table.get("NVDA")[0].ex_date, date(10));
+        assert!(table.get("AAPL").is_empty());
+
+        let mut candles = [
+            candle(7, 1200.0, 100),
+            candle(10, 120.0, 1_000),
+            candle(11, 121.0, 1_000),
+        ];
+        adjust_candles(&mut candles, &table);
+        assert!((candles[0].close - 119.88).abs() < 1e-9);
+        assert_eq!(candles[0].volume, 1_000);
+        assert!((candles[1].close - 119.88).abs() < 1e-9);
+        assert_eq!(candles[1].volume, 1_000);
+        assert_eq!(candles[2].close, 121.0);
+    }
+}
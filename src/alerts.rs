@@ -0,0 +1,308 @@
+//! Alerts when live trades cross price levels.
+//!
+//! Register levels such as the PMZ high and low, the previous day's LIS, or custom
+//! prices with an [`AlertEngine`], feed it trades, and receive an [`AlertEvent`] on
+//! every [`subscribe()`](AlertEngine::subscribe)d receiver when a trade crosses a
+//! level, subject to the level's [`RetriggerPolicy`].
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use dbn::TradeMsg;
+use tokio::sync::broadcast;
+
+use crate::examples::es_futures_pmz::PmzResult;
+
+/// What a level represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub enum LevelKind {
+    /// The PMZ high.
+    PmzHigh = 0,
+    /// The PMZ low.
+    PmzLow = 1,
+    /// The previous day's line in the sand (LIS).
+    Lis = 2,
+    /// A user-defined level.
+    Custom = 3,
+}
+
+impl TryFrom<u32> for LevelKind {
+    type Error = crate::Error;
+
+    fn try_from(value: u32) -> crate::Result<Self> {
+        match value {
+            0 => Ok(Self::PmzHigh),
+            1 => Ok(Self::PmzLow),
+            2 => Ok(Self::Lis),
+            3 => Ok(Self::Custom),
+            _ => Err(crate::Error::bad_arg(
+                "kind",
+                format!("unknown level kind {value}"),
+            )),
+        }
+    }
+}
+
+/// Identifies a registered level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LevelId(pub u64);
+
+/// When a level alerts again after it's been crossed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetriggerPolicy {
+    /// Alert on every crossing.
+    #[default]
+    Always,
+    /// Alert on a crossing only if the level's previous alert was at least this long
+    /// before, by trade timestamps, so price chopping around a level alerts once.
+    Debounce(Duration),
+    /// Alert on the first crossing only, then remove the level.
+    Once,
+}
+
+/// A price level to alert on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    /// The level's ID.
+    pub id: LevelId,
+    /// What the level represents.
+    pub kind: LevelKind,
+    /// The price of the level.
+    pub price: f64,
+    /// When the level alerts again after it's been crossed.
+    pub policy: RetriggerPolicy,
+}
+
+/// The direction a trade crossed a level in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CrossDirection {
+    /// The price rose to or through the level.
+    Up,
+    /// The price fell to or through the level.
+    Down,
+}
+
+/// A trade crossing a level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    /// The level that was crossed.
+    pub level: Level,
+    /// The direction of the cross.
+    pub direction: CrossDirection,
+    /// The instrument ID of the trade.
+    pub instrument_id: u32,
+    /// The trade price.
+    pub price: f64,
+    /// The trade's `ts_event`.
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct RegisteredLevel {
+    level: Level,
+    last_alert: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: u64,
+    levels: Vec<RegisteredLevel>,
+    // The last trade price of each instrument
+    last_prices: HashMap<u32, f64>,
+}
+
+/// Detects trades crossing registered levels and broadcasts [`AlertEvent`]s.
+///
+/// Levels apply to trades in every instrument fed to the engine. The engine can be
+/// shared across tasks in an [`Arc`](std::sync::Arc): levels can be registered and
+/// removed while trades are being processed.
+#[derive(Debug)]
+pub struct AlertEngine {
+    state: Mutex<State>,
+    tx: broadcast::Sender<AlertEvent>,
+}
+
+impl AlertEngine {
+    /// Creates an engine whose subscribers can lag at most `capacity` events behind
+    /// before missing events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::default(),
+            tx: broadcast::channel(capacity.max(1)).0,
+        }
+    }
+
+    /// Returns a receiver for alerts sent after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<AlertEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Registers a level at `price`, returning its ID.
+    pub fn add_level(&self, kind: LevelKind, price: f64, policy: RetriggerPolicy) -> LevelId {
+        let mut state = self.state();
+        let id = LevelId(state.next_id);
+        state.next_id += 1;
+        state.levels.push(RegisteredLevel {
+            level: Level {
+                id,
+                kind,
+                price,
+                policy,
+            },
+            last_alert: None,
+        });
+        id
+    }
+
+    /// Registers the PMZ high and low and the previous day's LIS from `pmz`, skipping
+    /// any that are missing, and returns their IDs.
+    pub fn add_pmz_levels(&self, pmz: &PmzResult, policy: RetriggerPolicy) -> Vec<LevelId> {
+        [
+            (LevelKind::PmzHigh, pmz.pmz_high),
+            (LevelKind::PmzLow, pmz.pmz_low),
+            (LevelKind::Lis, pmz.prev_day_lis),
+        ]
+        .into_iter()
+        .filter_map(|(kind, price)| Some(self.add_level(kind, price?, policy)))
+        .collect()
+    }
+
+    /// Removes the level with `id`, returning `false` if it wasn't registered.
+    pub fn remove_level(&self, id: LevelId) -> bool {
+        let mut state = self.state();
+        let len = state.levels.len();
+        state.levels.retain(|registered| registered.level.id != id);
+        state.levels.len() != len
+    }
+
+    /// Returns the registered levels.
+    pub fn levels(&self) -> Vec<Level> {
+        self.state()
+            .levels
+            .iter()
+            .map(|registered| registered.level.clone())
+            .collect()
+    }
+
+    /// Checks `trade` against the registered levels and sends an alert for each level
+    /// it crossed since the previous trade in the same instrument. Returns the number
+    /// of alerts generated, which are dropped while there are no subscribers.
+    pub fn on_trade(&self, trade: &TradeMsg) -> usize {
+        self.on_price(
+            trade.hd.instrument_id,
+            trade.price as f64 * 1e-9,
+            DateTime::from_timestamp_nanos(trade.hd.ts_event as i64),
+        )
+    }
+
+    /// Like [`on_trade()`](Self::on_trade), but for a price from any source.
+    pub fn on_price(&self, instrument_id: u32, price: f64, timestamp: DateTime<Utc>) -> usize {
+        let mut state = self.state();
+        let Some(prev) = state.last_prices.insert(instrument_id, price) else {
+            return 0;
+        };
+        let mut events = Vec::new();
+        state.levels.retain_mut(|registered| {
+            let level_price = registered.level.price;
+            let direction = if prev < level_price && price >= level_price {
+                CrossDirection::Up
+            } else if prev > level_price && price <= level_price {
+                CrossDirection::Down
+            } else {
+                return true;
+            };
+            if let (RetriggerPolicy::Debounce(interval), Some(last_alert)) =
+                (registered.level.policy, registered.last_alert)
+            {
+                if timestamp - last_alert < interval {
+                    return true;
+                }
+            }
+            registered.last_alert = Some(timestamp);
+            events.push(AlertEvent {
+                level: registered.level.clone(),
+                direction,
+                instrument_id,
+                price,
+                timestamp,
+            });
+            registered.level.policy != RetriggerPolicy::Once
+        });
+        drop(state);
+        let count = events.len();
+        for event in events {
+            // Only fails when there are no subscribers
+            let _ = self.tx.send(event);
+        }
+        count
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The state is always consistent, so recover from a panic in another thread
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_745_242_200 + seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn test_crossings() {
+        let engine = AlertEngine::new(16);
+        let mut rx = engine.subscribe();
+        let pmz = PmzResult::from_inputs(
+            NaiveDate::from_ymd_opt(2025, 4, 21).unwrap(),
+            Some(5310.0),
+            Some(5290.0),
+            Some(5300.0),
+            Some(5305.0),
+        );
+        let ids = engine.add_pmz_levels(&pmz, RetriggerPolicy::Always);
+        assert_eq!(ids.len(), 3);
+        let once = engine.add_level(LevelKind::Custom, 5301.0, RetriggerPolicy::Once);
+
+        assert_eq!(engine.on_price(1, 5299.0, at(0)), 0);
+        // Crosses the LIS and the custom level
+        assert_eq!(engine.on_price(1, 5301.0, at(1)), 2);
+        let event = rx.try_recv().unwrap();
+        assert_eq!(event.level.kind, LevelKind::Lis);
+        assert_eq!(event.direction, CrossDirection::Up);
+        assert_eq!(rx.try_recv().unwrap().level.id, once);
+        assert_eq!(engine.levels().len(), 3);
+        // Back down through the LIS
+        assert_eq!(engine.on_price(1, 5299.5, at(2)), 1);
+        assert_eq!(rx.try_recv().unwrap().direction, CrossDirection::Down);
+        // Another instrument has its own last price
+        assert_eq!(engine.on_price(2, 5400.0, at(3)), 0);
+        assert!(engine.remove_level(ids[2]));
+        assert!(!engine.remove_level(ids[2]));
+    }
+
+    #[test]
+    fn test_debounce() {
+        let engine = AlertEngine::new(16);
+        engine.add_level(
+            LevelKind::Custom,
+            5300.0,
+            RetriggerPolicy::Debounce(Duration::seconds(30)),
+        );
+        assert_eq!(engine.on_price(1, 5299.75, at(0)), 0);
+        assert_eq!(engine.on_price(1, 5300.0, at(1)), 1);
+        assert_eq!(engine.on_price(1, 5299.75, at(10)), 0);
+        assert_eq!(engine.on_price(1, 5300.25, at(20)), 0);
+        assert_eq!(engine.on_price(1, 5299.75, at(31)), 1);
+    }
+}
@@ -0,0 +1,486 @@
+//! Converting candles to [Apache Arrow](https://arrow.apache.org) for Polars,
+//! DataFusion, and other Arrow-based tools.
+//!
+//! [`candles_to_arrow()`] converts a slice of candles to a single [`RecordBatch`], and
+//! [`ArrowCandleWriter`] writes candles as they're produced to an Arrow IPC stream,
+//! which Polars reads with `pl.read_ipc_stream()`.
+//!
+//! Both use the schema from [`candle_schema()`]: a `timestamp` column with the start
+//! of each candle in nanoseconds annotated with the candles' timezone, followed by
+//! `instrument_id`, `symbol`, `open`, `high`, `low`, `close`, and `volume`.
+//!
+//! [`ohlcv_to_arrow()`] converts OHLCV records straight to a batch in the same schema
+//! without building candles first, for large downloads.
+//!
+//! [`footprints_to_arrow()`] converts [`FootprintBar`]s to a record batch in the
+//! [`footprint_schema()`], with a row for each price level of each bar.
+
+use std::{io::Write, sync::Arc};
+
+use arrow_array::{
+    builder::{
+        Float64Builder, StringBuilder, TimestampNanosecondBuilder, UInt32Builder, UInt64Builder,
+    },
+    ArrayRef, Float64Array, RecordBatch, TimestampNanosecondArray, UInt32Array, UInt64Array,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use chrono_tz::Tz;
+use dbn::OhlcvMsg;
+
+use crate::{
+    examples::es_futures_pmz::{Candle, CandlePrice, SymbolTable, DEFAULT_CANDLE_TZ},
+    orderflow::FootprintBar,
+};
+
+/// The default number of candles per record batch written by [`ArrowCandleWriter`].
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+/// Returns the Arrow schema of candles with timestamps in `tz`.
+pub fn candle_schema(tz: Tz) -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, Some(tz.name().into())),
+            false,
+        ),
+        Field::new("instrument_id", DataType::UInt32, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("open", DataType::Float64, false),
+        Field::new("high", DataType::Float64, false),
+        Field::new("low", DataType::Float64, false),
+        Field::new("close", DataType::Float64, false),
+        Field::new("volume", DataType::UInt64, false),
+    ]))
+}
+
+/// Converts `candles` to a record batch with the [`candle_schema()`] of the first
+/// candle's timezone, or [`DEFAULT_CANDLE_TZ`] if there are none.
+///
+/// # Errors
+/// This function returns an error if a timestamp is out of range for nanoseconds.
+pub fn candles_to_arrow(candles: &[Candle]) -> crate::Result<RecordBatch> {
+    let tz = candles
+        .first()
+        .map_or(DEFAULT_CANDLE_TZ, |candle| candle.timestamp.timezone());
+    let mut builder = BatchBuilder::new(tz, candles.len());
+    for candle in candles {
+        builder.append(candle)?;
+    }
+    builder.finish()
+}
+
+/// Converts OHLCV `records` directly to a record batch with the [`candle_schema()`] of
+/// `tz`, without creating [`Candle`]s. Prices are converted in batches, and symbols are
+/// looked up in `symbols`, falling back to the instrument ID.
+///
+/// # Errors
+/// This function returns an error if a `ts_event` is out of range for nanoseconds.
+pub fn ohlcv_to_arrow(
+    records: &[OhlcvMsg],
+    symbols: &SymbolTable,
+    tz: Tz,
+) -> crate::Result<RecordBatch> {
+    let timestamps = records
+        .iter()
+        .map(|ohlcv| {
+            i64::try_from(ohlcv.hd.ts_event).map_err(|_| {
+                crate::Error::bad_arg(
+                    "records",
+                    format!("{} is out of range for nanoseconds", ohlcv.hd.ts_event),
+                )
+            })
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    let mut symbol = StringBuilder::new();
+    for ohlcv in records {
+        match symbols.get(ohlcv.hd.instrument_id) {
+            Some(sym) => symbol.append_value(sym),
+            None => symbol.append_value(ohlcv.hd.instrument_id.to_string()),
+        }
+    }
+    let price = |field: fn(&OhlcvMsg) -> i64| -> ArrayRef {
+        let fixed: Vec<i64> = records.iter().map(field).collect();
+        let mut prices = Vec::with_capacity(fixed.len());
+        f64::extend_from_fixed(&mut prices, &fixed);
+        Arc::new(Float64Array::from(prices))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(TimestampNanosecondArray::from(timestamps).with_timezone(tz.name())),
+        Arc::new(UInt32Array::from_iter_values(
+            records.iter().map(|ohlcv| ohlcv.hd.instrument_id),
+        )),
+        Arc::new(symbol.finish()),
+        price(|ohlcv| ohlcv.open),
+        price(|ohlcv| ohlcv.high),
+        price(|ohlcv| ohlcv.low),
+        price(|ohlcv| ohlcv.close),
+        Arc::new(UInt64Array::from_iter_values(
+            records.iter().map(|ohlcv| ohlcv.volume),
+        )),
+    ];
+    Ok(RecordBatch::try_new(candle_schema(tz), columns)?)
+}
+
+/// Returns the Arrow schema of footprint bars with timestamps in `tz`: the
+/// `timestamp`, `instrument_id`, and `symbol` of the bar followed by the `price`,
+/// `buy_volume`, `sell_volume`, and `unclassified_volume` of a price level.
+pub fn footprint_schema(tz: Tz) -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Nanosecond, Some(tz.name().into())),
+            false,
+        ),
+        Field::new("instrument_id", DataType::UInt32, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("buy_volume", DataType::UInt64, false),
+        Field::new("sell_volume", DataType::UInt64, false),
+        Field::new("unclassified_volume", DataType::UInt64, false),
+    ]))
+}
+
+/// Converts `bars` to a record batch with the [`footprint_schema()`] of the first bar's
+/// timezone, or [`DEFAULT_CANDLE_TZ`] if there are none. Each price level is a row, in
+/// the order of the bars and then ascending price.
+///
+/// # Errors
+/// This function returns an error if a timestamp is out of range for nanoseconds.
+pub fn footprints_to_arrow(bars: &[FootprintBar]) -> crate::Result<RecordBatch> {
+    let tz = bars
+        .first()
+        .map_or(DEFAULT_CANDLE_TZ, |bar| bar.timestamp.timezone());
+    let rows = bars.iter().map(|bar| bar.levels.len()).sum();
+    let mut timestamp = TimestampNanosecondBuilder::with_capacity(rows);
+    let mut instrument_id = UInt32Builder::with_capacity(rows);
+    let mut symbol = StringBuilder::new();
+    let mut price = Float64Builder::with_capacity(rows);
+    let mut buy_volume = UInt64Builder::with_capacity(rows);
+    let mut sell_volume = UInt64Builder::with_capacity(rows);
+    let mut unclassified_volume = UInt64Builder::with_capacity(rows);
+    for bar in bars {
+        let ts = bar.timestamp.timestamp_nanos_opt().ok_or_else(|| {
+            crate::Error::bad_arg(
+                "bars",
+                format!("{} is out of range for nanoseconds", bar.timestamp),
+            )
+        })?;
+        for level in &bar.levels {
+            timestamp.append_value(ts);
+            instrument_id.append_value(bar.instrument_id);
+            symbol.append_value(&bar.symbol);
+            price.append_value(level.price);
+            buy_volume.append_value(level.buy_volume);
+            sell_volume.append_value(level.sell_volume);
+            unclassified_volume.append_value(level.unclassified_volume);
+        }
+    }
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(timestamp.finish().with_timezone(tz.name())),
+        Arc::new(instrument_id.finish()),
+        Arc::new(symbol.finish()),
+        Arc::new(price.finish()),
+        Arc::new(buy_volume.finish()),
+        Arc::new(sell_volume.finish()),
+        Arc::new(unclassified_volume.finish()),
+    ];
+    Ok(RecordBatch::try_new(footprint_schema(tz), columns)?)
+}
+
+/// Writes candles to an Arrow IPC stream in record batches.
+///
+/// Candles are buffered until a batch is full, so call [`finish()`](Self::finish) once
+/// done to write the last partial batch and the end-of-stream marker.
+pub struct ArrowCandleWriter<W: Write> {
+    writer: StreamWriter<W>,
+    builder: BatchBuilder,
+    batch_size: usize,
+}
+
+impl<W: Write> ArrowCandleWriter<W> {
+    /// Creates a writer of candles with timestamps in `tz` to `writer`, writing
+    /// [`DEFAULT_BATCH_SIZE`] candles per batch.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to write the schema.
+    pub fn new(writer: W, tz: Tz) -> crate::Result<Self> {
+        Self::with_batch_size(writer, tz, DEFAULT_BATCH_SIZE)
+    }
+
+    /// Creates a writer like [`new()`](Self::new) that writes `batch_size` candles per
+    /// batch.
+    ///
+    /// # Errors
+    /// This function returns an error if `batch_size` is 0 or it fails to write the
+    /// schema.
+    pub fn with_batch_size(writer: W, tz: Tz, batch_size: usize) -> crate::Result<Self> {
+        if batch_size == 0 {
+            return Err(crate::Error::bad_arg("batch_size", "must be at least 1"));
+        }
+        let builder = BatchBuilder::new(tz, batch_size);
+        Ok(Self {
+            writer: StreamWriter::try_new(writer, &builder.schema)?,
+            builder,
+            batch_size,
+        })
+    }
+
+    /// Buffers `candle`, writing a batch once it's full. The timestamp is written in
+    /// the writer's timezone regardless of the candle's.
+    ///
+    /// # Errors
+    /// This function returns an error if the timestamp is out of range for nanoseconds
+    /// or it fails to write a batch.
+    pub fn write(&mut self, candle: &Candle) -> crate::Result<()> {
+        self.builder.append(candle)?;
+        if self.builder.len == self.batch_size {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered candles and the end-of-stream marker, returning the inner
+    /// writer.
+    ///
+    /// # Errors
+    /// This function returns an error if it fails to write to the inner writer.
+    pub fn finish(mut self) -> crate::Result<W> {
+        if self.builder.len > 0 {
+            self.flush_batch()?;
+        }
+        self.writer.finish()?;
+        Ok(self.writer.into_inner()?)
+    }
+
+    fn flush_batch(&mut self) -> crate::Result<()> {
+        let batch = self.builder.finish()?;
+        self.writer.write(&batch)?;
+        Ok(())
+    }
+}
+
+impl<W: Write> std::fmt::Debug for ArrowCandleWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArrowCandleWriter")
+            .field("schema", &self.builder.schema)
+            .field("buffered", &self.builder.len)
+            .field("batch_size", &self.batch_size)
+            .finish_non_exhaustive()
+    }
+}
+
+// Builds the columns of a record batch in the candle schema
+struct BatchBuilder {
+    schema: SchemaRef,
+    tz: Arc<str>,
+    len: usize,
+    timestamp: TimestampNanosecondBuilder,
+    instrument_id: UInt32Builder,
+    symbol: StringBuilder,
+    open: Float64Builder,
+    high: Float64Builder,
+    low: Float64Builder,
+    close: Float64Builder,
+    volume: UInt64Builder,
+}
+
+impl BatchBuilder {
+    fn new(tz: Tz, capacity: usize) -> Self {
+        Self {
+            schema: candle_schema(tz),
+            tz: tz.name().into(),
+            len: 0,
+            timestamp: TimestampNanosecondBuilder::with_capacity(capacity),
+            instrument_id: UInt32Builder::with_capacity(capacity),
+            symbol: StringBuilder::new(),
+            open: Float64Builder::with_capacity(capacity),
+            high: Float64Builder::with_capacity(capacity),
+            low: Float64Builder::with_capacity(capacity),
+            close: Float64Builder::with_capacity(capacity),
+            volume: UInt64Builder::with_capacity(capacity),
+        }
+    }
+
+    fn append(&mut self, candle: &Candle) -> crate::Result<()> {
+        let timestamp = candle.timestamp.timestamp_nanos_opt().ok_or_else(|| {
+            crate::Error::bad_arg(
+                "candle",
+                format!("{} is out of range for nanoseconds", candle.timestamp),
+            )
+        })?;
+        self.timestamp.append_value(timestamp);
+        self.instrument_id.append_value(candle.instrument_id);
+        self.symbol.append_value(&candle.symbol);
+        self.open.append_value(candle.open);
+        self.high.append_value(candle.high);
+        self.low.append_value(candle.low);
+        self.close.append_value(candle.close);
+        self.volume.append_value(candle.volume);
+        self.len += 1;
+        Ok(())
+    }
+
+    // Returns the appended candles as a batch and resets the builder
+    fn finish(&mut self) -> crate::Result<RecordBatch> {
+        self.len = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.timestamp.finish().with_timezone(Arc::clone(&self.tz))),
+            Arc::new(self.instrument_id.finish()),
+            Arc::new(self.symbol.finish()),
+            Arc::new(self.open.finish()),
+            Arc::new(self.high.finish()),
+            Arc::new(self.low.finish()),
+            Arc::new(self.close.finish()),
+            Arc::new(self.volume.finish()),
+        ];
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{cast::AsArray, types::TimestampNanosecondType};
+    use arrow_ipc::reader::StreamReader;
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn candles(count: i64) -> Vec<Candle> {
+        let start = DEFAULT_CANDLE_TZ
+            .with_ymd_and_hms(2025, 4, 21, 9, 30, 0)
+            .unwrap();
+        let symbol: Arc<str> = "ES.c.0".into();
+        (0..count)
+            .map(|i| Candle {
+                timestamp: start + Duration::minutes(i),
+                instrument_id: 1,
+                symbol: symbol.clone(),
+                open: 5300.0 + i as f64,
+                high: 5301.0 + i as f64,
+                low: 5299.0 + i as f64,
+                close: 5300.5 + i as f64,
+                volume: 10,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_candles_to_arrow() {
+        let candles = candles(3);
+        let batch = candles_to_arrow(&candles).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.schema(), candle_schema(DEFAULT_CANDLE_TZ));
+        let timestamp = batch.column(0).as_primitive::<TimestampNanosecondType>();
+        assert_eq!(
+            timestamp.value(1),
+            candles[1].timestamp.timestamp_nanos_opt().unwrap()
+        );
+        assert_eq!(batch.column(2).as_string::<i32>().value(0), "ES.c.0");
+        assert_eq!(
+            batch
+                .column(6)
+                .as_primitive::<arrow_array::types::Float64Type>()
+                .value(2),
+            5302.5
+        );
+    }
+
+    #[test]
+    fn test_ohlcv_to_arrow() {
+        use dbn::{rtype, RecordHeader};
+
+        let records: Vec<OhlcvMsg> = (0..10u64)
+            .map(|i| OhlcvMsg {
+                hd: RecordHeader::new::<OhlcvMsg>(
+                    rtype::OHLCV_1M,
+                    1,
+                    1 + i as u32 % 2,
+                    1_745_242_200_000_000_000 + i * 60_000_000_000,
+                ),
+                open: 5_300_000_000_000 + i as i64 * 250_000_000,
+                high: 5_301_000_000_000,
+                low: 5_299_000_000_000,
+                close: 5_300_500_000_000,
+                volume: i,
+            })
+            .collect();
+        let mut symbols = SymbolTable::new();
+        let symbol = symbols.insert(1, "ESM5");
+        let batch = ohlcv_to_arrow(&records, &symbols, DEFAULT_CANDLE_TZ).unwrap();
+        let candles: Vec<Candle> = records
+            .iter()
+            .map(|ohlcv| {
+                let symbol = match ohlcv.hd.instrument_id {
+                    1 => symbol.clone(),
+                    id => id.to_string().into(),
+                };
+                Candle::try_with_symbol(ohlcv, symbol, DEFAULT_CANDLE_TZ).unwrap()
+            })
+            .collect();
+        assert_eq!(batch, candles_to_arrow(&candles).unwrap());
+        assert_eq!(batch.column(2).as_string::<i32>().value(1), "2");
+
+        let mut bad = records;
+        bad[3].hd.ts_event = u64::MAX;
+        assert!(ohlcv_to_arrow(&bad, &symbols, DEFAULT_CANDLE_TZ).is_err());
+    }
+
+    #[test]
+    fn test_footprints_to_arrow() {
+        use crate::orderflow::FootprintLevel;
+
+        let level = |price, buy_volume| FootprintLevel {
+            price,
+            buy_volume,
+            sell_volume: 1,
+            unclassified_volume: 0,
+        };
+        let candles = candles(2);
+        let bars: Vec<_> = candles
+            .iter()
+            .map(|candle| FootprintBar {
+                timestamp: candle.timestamp,
+                instrument_id: candle.instrument_id,
+                symbol: candle.symbol.clone(),
+                levels: vec![level(candle.low, 2), level(candle.high, 3)],
+            })
+            .collect();
+        let batch = footprints_to_arrow(&bars).unwrap();
+        assert_eq!(batch.num_rows(), 4);
+        assert_eq!(batch.schema(), footprint_schema(DEFAULT_CANDLE_TZ));
+        let timestamp = batch.column(0).as_primitive::<TimestampNanosecondType>();
+        assert_eq!(timestamp.value(1), timestamp.value(0));
+        assert_eq!(
+            timestamp.value(2),
+            candles[1].timestamp.timestamp_nanos_opt().unwrap()
+        );
+        assert_eq!(
+            batch
+                .column(3)
+                .as_primitive::<arrow_array::types::Float64Type>()
+                .value(3),
+            5302.0
+        );
+        assert_eq!(
+            batch
+                .column(4)
+                .as_primitive::<arrow_array::types::UInt64Type>()
+                .value(1),
+            3
+        );
+    }
+
+    #[test]
+    fn test_writer_batches() {
+        let mut writer =
+            ArrowCandleWriter::with_batch_size(Vec::new(), DEFAULT_CANDLE_TZ, 2).unwrap();
+        for candle in candles(5) {
+            writer.write(&candle).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let reader = StreamReader::try_new(bytes.as_slice(), None).unwrap();
+        let rows: Vec<_> = reader.map(|batch| batch.unwrap().num_rows()).collect();
+        assert_eq!(rows, [2, 2, 1]);
+    }
+}
@@ -0,0 +1,320 @@
+//! Opening and closing auction pressure from imbalance records.
+//!
+//! Equity venues such as Nasdaq (`XNAS.ITCH`) publish order imbalance indicators in the
+//! minutes before their opening and closing crosses. An [`AuctionTracker`] follows the
+//! [`ImbalanceMsg`] records of each symbol and summarizes every auction as an
+//! [`AuctionPressure`]: the latest paired and imbalance quantities, the indicative
+//! clearing prices, and how the imbalance evolved. The pressure of the opening cross
+//! gives equities the pre-open context the PMZ provides for futures.
+//! [`get_auction_pressure()`] fetches and summarizes the auctions of a day.
+
+use std::{collections::HashMap, ffi::c_char, sync::Arc};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use dbn::{enums::Side, ImbalanceMsg};
+
+use crate::statistics::Imbalance;
+
+/// The kind of auction an imbalance is published for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AuctionKind {
+    /// The opening cross.
+    Opening,
+    /// The closing cross.
+    Closing,
+    /// Any other cross, e.g. for an IPO or after a halt.
+    Other,
+}
+
+impl AuctionKind {
+    /// Returns the kind of the venue-specific `auction_type` code of an
+    /// [`ImbalanceMsg`], where `O` is the opening cross and `C` is the closing cross.
+    pub fn from_code(code: c_char) -> Self {
+        match code as u8 {
+            b'O' => Self::Opening,
+            b'C' => Self::Closing,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The imbalance of a symbol leading up to an auction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuctionPressure {
+    /// The instrument ID.
+    pub instrument_id: u32,
+    /// The symbol of the instrument.
+    pub symbol: Arc<str>,
+    /// The local date of the auction.
+    pub date: NaiveDate,
+    /// The kind of auction.
+    pub kind: AuctionKind,
+    /// When the first imbalance for the auction was published.
+    pub first_update: DateTime<Utc>,
+    /// When the latest imbalance for the auction was published.
+    pub last_update: DateTime<Utc>,
+    /// The number of imbalance records for the auction.
+    pub updates: u32,
+    /// The latest quantity eligible to be matched at the reference price.
+    pub paired_qty: u32,
+    /// The latest quantity not paired at the reference price.
+    pub imbalance_qty: u32,
+    /// The latest side of the imbalance, if any.
+    pub side: Option<Side>,
+    /// The largest imbalance quantity published for the auction.
+    pub max_imbalance_qty: u32,
+    /// The number of times the imbalance switched between the bid and ask sides.
+    pub side_flips: u32,
+    /// The latest reference price.
+    pub ref_price: Option<f64>,
+    /// The latest indicative clearing price for both cross and continuous orders, the
+    /// near price.
+    pub near_price: Option<f64>,
+    /// The latest indicative clearing price for cross orders only, the far price.
+    pub far_price: Option<f64>,
+}
+
+impl AuctionPressure {
+    /// Returns the latest imbalance quantity, positive for buy imbalances and negative
+    /// for sell imbalances.
+    pub fn signed_imbalance(&self) -> i64 {
+        match self.side {
+            Some(Side::Bid) => i64::from(self.imbalance_qty),
+            Some(Side::Ask) => -i64::from(self.imbalance_qty),
+            _ => 0,
+        }
+    }
+
+    /// Returns the imbalance as a fraction of the paired and imbalance quantities, from
+    /// -1 for only sellers to 1 for only buyers, or `None` if both quantities are zero.
+    pub fn imbalance_ratio(&self) -> Option<f64> {
+        let total = u64::from(self.paired_qty) + u64::from(self.imbalance_qty);
+        (total > 0).then(|| self.signed_imbalance() as f64 / total as f64)
+    }
+
+    /// Returns how far the near price is from the reference price in basis points,
+    /// positive when the auction is indicated to clear above it.
+    pub fn price_pressure_bps(&self) -> Option<f64> {
+        let ref_price = self.ref_price.filter(|price| *price != 0.0)?;
+        Some((self.near_price? - ref_price) / ref_price * 10_000.0)
+    }
+}
+
+/// Summarizes the imbalance records of each symbol into an [`AuctionPressure`] per
+/// auction.
+#[derive(Debug, Clone)]
+pub struct AuctionTracker {
+    tz: Tz,
+    auctions: HashMap<(u32, NaiveDate, AuctionKind), AuctionPressure>,
+}
+
+impl Default for AuctionTracker {
+    fn default() -> Self {
+        Self::new(chrono_tz::America::New_York)
+    }
+}
+
+impl AuctionTracker {
+    /// Creates a tracker that dates auctions in `tz`, the venue's timezone.
+    pub fn new(tz: Tz) -> Self {
+        Self {
+            tz,
+            auctions: HashMap::new(),
+        }
+    }
+
+    /// Adds `imbalance` for `symbol`, returning the updated summary of its auction.
+    /// Records must be in publication order.
+    pub fn push(&mut self, imbalance: &ImbalanceMsg, symbol: &str) -> &AuctionPressure {
+        let kind = AuctionKind::from_code(imbalance.auction_type);
+        let update = Imbalance::from(imbalance);
+        let date = update.ts_event.with_timezone(&self.tz).date_naive();
+        let pressure = self
+            .auctions
+            .entry((update.instrument_id, date, kind))
+            .or_insert_with(|| AuctionPressure {
+                instrument_id: update.instrument_id,
+                symbol: symbol.into(),
+                date,
+                kind,
+                first_update: update.ts_event,
+                last_update: update.ts_event,
+                updates: 0,
+                paired_qty: 0,
+                imbalance_qty: 0,
+                side: None,
+                max_imbalance_qty: 0,
+                side_flips: 0,
+                ref_price: None,
+                near_price: None,
+                far_price: None,
+            });
+        if let (Some(prev), Some(side)) = (pressure.side, update.side) {
+            if prev != side {
+                pressure.side_flips += 1;
+            }
+        }
+        pressure.last_update = update.ts_event;
+        pressure.updates += 1;
+        pressure.paired_qty = update.paired_qty;
+        pressure.imbalance_qty = update.total_imbalance_qty;
+        pressure.side = update.side;
+        pressure.max_imbalance_qty = pressure.max_imbalance_qty.max(update.total_imbalance_qty);
+        pressure.ref_price = update.ref_price;
+        pressure.near_price = update.cont_book_clr_price;
+        pressure.far_price = update.auct_interest_clr_price;
+        pressure
+    }
+
+    /// Returns the summary of the `kind` auction of `instrument_id` on `date`, if any
+    /// imbalances were published for it.
+    pub fn get(
+        &self,
+        instrument_id: u32,
+        date: NaiveDate,
+        kind: AuctionKind,
+    ) -> Option<&AuctionPressure> {
+        self.auctions.get(&(instrument_id, date, kind))
+    }
+
+    /// Returns the summaries of every auction, ordered by date, kind, and then
+    /// instrument ID.
+    pub fn pressures(&self) -> Vec<AuctionPressure> {
+        let mut pressures: Vec<_> = self.auctions.values().cloned().collect();
+        pressures.sort_by_key(|p| (p.date, p.kind, p.instrument_id));
+        pressures
+    }
+
+    /// Forgets every auction, e.g. between days.
+    pub fn reset(&mut self) {
+        self.auctions.clear();
+    }
+}
+
+/// Summarizes `imbalances`, in publication order, into the pressure of each auction
+/// dated in `tz`, labeled with `symbol`.
+///
+/// Summaries are ordered by date, kind, and then instrument ID.
+pub fn auction_pressure(imbalances: &[ImbalanceMsg], symbol: &str, tz: Tz) -> Vec<AuctionPressure> {
+    let mut tracker = AuctionTracker::new(tz);
+    for imbalance in imbalances {
+        tracker.push(imbalance, symbol);
+    }
+    tracker.pressures()
+}
+
+/// Fetches the imbalance records of `symbols` from `dataset`, e.g. `XNAS.ITCH`, for
+/// `date` and summarizes the pressure of each auction that day in New York time.
+///
+/// Summaries are ordered by kind and then instrument ID.
+///
+/// # Errors
+/// This function returns an error when `date` is out of range, the request fails, or
+/// the response can't be decoded.
+#[cfg(feature = "historical")]
+pub async fn get_auction_pressure(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    symbols: impl Into<crate::Symbols>,
+    date: NaiveDate,
+) -> crate::Result<Vec<AuctionPressure>> {
+    use crate::timeconv::ToTimeDate;
+
+    let start = date.to_time_date()?;
+    let params = crate::historical::timeseries::GetRangeParams::builder()
+        .dataset(dataset)
+        .symbols(symbols)
+        .schema(dbn::Schema::Imbalance)
+        .date_time_range(start)
+        .build();
+    let mut decoder = client.get_range(&params).await?;
+    let symbol_map = decoder.metadata().symbol_map_for_date(start)?;
+    let mut tracker = AuctionTracker::default();
+    while let Some(imbalance) = decoder.decode_record::<ImbalanceMsg>().await? {
+        let instrument_id = imbalance.hd.instrument_id;
+        match symbol_map.get(instrument_id) {
+            Some(symbol) => tracker.push(imbalance, symbol),
+            None => tracker.push(imbalance, &instrument_id.to_string()),
+        };
+    }
+    let mut pressures = tracker.pressures();
+    pressures.retain(|p| p.date == date);
+    Ok(pressures)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::America::New_York;
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+
+    fn imbalance(
+        minute: u32,
+        auction_type: u8,
+        side: u8,
+        paired_qty: u32,
+        total_imbalance_qty: u32,
+    ) -> ImbalanceMsg {
+        let ts = New_York
+            .with_ymd_and_hms(2025, 4, 22, 9, 25 + minute, 0)
+            .unwrap()
+            .timestamp_nanos_opt()
+            .unwrap() as u64;
+        ImbalanceMsg {
+            hd: RecordHeader::new::<ImbalanceMsg>(rtype::IMBALANCE, 2, 1, ts),
+            ts_recv: ts,
+            ref_price: 200_000_000_000,
+            cont_book_clr_price: 200_500_000_000,
+            auct_interest_clr_price: 201_000_000_000,
+            paired_qty,
+            total_imbalance_qty,
+            auction_type: auction_type as c_char,
+            side: side as c_char,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_auction_pressure() {
+        let imbalances = [
+            imbalance(0, b'O', b'A', 1_000, 500),
+            imbalance(1, b'O', b'B', 2_000, 3_000),
+            imbalance(2, b'O', b'B', 3_000, 1_000),
+        ];
+        let pressures = auction_pressure(&imbalances, "AAPL", New_York);
+        assert_eq!(pressures.len(), 1);
+        let open = &pressures[0];
+        assert_eq!(open.kind, AuctionKind::Opening);
+        assert_eq!(open.date, NaiveDate::from_ymd_opt(2025, 4, 22).unwrap());
+        assert_eq!(&*open.symbol, "AAPL");
+        assert_eq!((open.updates, open.side_flips), (3, 1));
+        assert_eq!((open.imbalance_qty, open.max_imbalance_qty), (1_000, 3_000));
+        assert_eq!(open.signed_imbalance(), 1_000);
+        assert_eq!(open.imbalance_ratio(), Some(0.25));
+        assert_eq!(open.price_pressure_bps(), Some(25.0));
+        assert_eq!(open.far_price, Some(201.0));
+    }
+
+    #[test]
+    fn test_tracker_separates_auctions() {
+        let mut tracker = AuctionTracker::default();
+        tracker.push(&imbalance(0, b'O', b'A', 1_000, 500), "AAPL");
+        let close = tracker.push(&imbalance(1, b'C', b'A', 1_000, 200), "AAPL");
+        assert_eq!(close.kind, AuctionKind::Closing);
+        assert_eq!(close.signed_imbalance(), -200);
+        let date = NaiveDate::from_ymd_opt(2025, 4, 22).unwrap();
+        assert_eq!(
+            tracker
+                .get(1, date, AuctionKind::Opening)
+                .unwrap()
+                .imbalance_qty,
+            500
+        );
+        assert_eq!(tracker.pressures().len(), 2);
+        tracker.reset();
+        assert!(tracker.get(1, date, AuctionKind::Closing).is_none());
+    }
+}
@@ -0,0 +1,428 @@
+//! Incremental backfills that resume from the last stored record.
+//!
+//! A [`Manager`] records the `ts_event` of the last record written to a
+//! [`RecordSink`] per dataset, symbol, and schema in a JSON state file. Each
+//! [`backfill()`](Manager::backfill) only requests the range after that timestamp, so
+//! running the same backfill on a schedule downloads just the new tail instead of the
+//! full range. Because sinks upsert, a range that's requested again after an
+//! interrupted run doesn't create duplicates.
+//!
+//! ```no_run
+//! # async fn example(mut sink: impl databento::sink::RecordSink) -> databento::Result<()> {
+//! use databento::{backfill::{BackfillParams, Manager}, dbn::Schema};
+//! use time::macros::datetime;
+//!
+//! let client = databento::HistoricalClient::builder().key_from_env()?.build()?;
+//! let mut manager = Manager::open(client, "backfill.json")?;
+//! let params = BackfillParams::builder()
+//!     .dataset("GLBX.MDP3")
+//!     .symbol("ES.c.0")
+//!     .stype_in(databento::dbn::SType::Continuous)
+//!     .schema(Schema::Ohlcv1M)
+//!     .start(datetime!(2025-01-01 00:00 UTC))
+//!     .end(time::OffsetDateTime::now_utc())
+//!     .build();
+//! let report = manager.backfill(&params, &mut sink).await?;
+//! println!("Wrote {} records", report.records);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use dbn::{OhlcvMsg, SType, Schema, TradeMsg};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use typed_builder::TypedBuilder;
+
+use crate::{
+    historical::{timeseries::GetRangeParams, DateTimeRange, HistoricalApi},
+    sink::{write_decoded, RecordSink},
+    timeconv::ToOffsetDateTime,
+};
+
+/// The name of the state file in the cache directory used by
+/// [`Manager::from_config()`].
+pub const STATE_FILE_NAME: &str = "backfill.json";
+
+/// The parameters for [`Manager::backfill()`]. Use [`BackfillParams::builder()`] to
+/// get a builder type with all the preset defaults.
+#[derive(Debug, Clone, TypedBuilder, PartialEq, Eq)]
+pub struct BackfillParams {
+    /// The dataset code.
+    #[builder(setter(transform = |dt: impl ToString| dt.to_string()))]
+    pub dataset: String,
+    /// The symbol to backfill.
+    #[builder(setter(transform = |symbol: impl ToString| symbol.to_string()))]
+    pub symbol: String,
+    /// The symbology type of `symbol`. Defaults to
+    /// [`RawSymbol`](dbn::enums::SType::RawSymbol).
+    #[builder(default = SType::RawSymbol)]
+    pub stype_in: SType,
+    /// The data record schema. Must be an OHLCV schema or [`Schema::Trades`], the
+    /// schemas a [`RecordSink`] stores.
+    pub schema: Schema,
+    /// The start of the full range (inclusive). Only used when nothing is stored yet
+    /// or the stored records end before it.
+    pub start: OffsetDateTime,
+    /// The end of the range (exclusive).
+    pub end: OffsetDateTime,
+}
+
+/// The outcome of a [`Manager::backfill()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackfillReport {
+    /// The start of the requested range, or `None` if the stored records were already
+    /// up to date and nothing was requested.
+    pub requested_start: Option<OffsetDateTime>,
+    /// The number of records written to the sink.
+    pub records: usize,
+    /// The `ts_event` of the last stored record after the backfill, if any.
+    pub last_ts_event: Option<u64>,
+}
+
+/// Tracks the last stored record per dataset, symbol, and schema and downloads only
+/// the missing tail of each backfill.
+#[derive(Debug)]
+pub struct Manager<C> {
+    client: C,
+    state_path: PathBuf,
+    state: State,
+}
+
+// Keyed by `dataset/symbol/schema` since JSON object keys must be strings
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    last_ts_event: BTreeMap<String, u64>,
+}
+
+impl<C: HistoricalApi> Manager<C> {
+    /// Creates a manager making requests with `client` that persists its state to
+    /// `state_path`, loading the existing state if the file exists.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to read or parse an existing state
+    /// file.
+    pub fn open(client: C, state_path: impl Into<PathBuf>) -> crate::Result<Self> {
+        let state_path = state_path.into();
+        let state = match std::fs::read(&state_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                crate::Error::bad_arg(
+                    "state_path",
+                    format!("invalid backfill state in {}: {e}", state_path.display()),
+                )
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => State::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            client,
+            state_path,
+            state,
+        })
+    }
+
+    /// Creates a manager like [`open()`](Self::open) with its state in
+    /// [`STATE_FILE_NAME`] in the `cache_dir` of `config`.
+    ///
+    /// # Errors
+    /// This function returns an error when `config` has no `cache_dir` or it fails to
+    /// read or parse an existing state file.
+    #[cfg(feature = "config")]
+    pub fn from_config(client: C, config: &crate::config::Config) -> crate::Result<Self> {
+        let cache_dir = config
+            .cache_dir
+            .as_ref()
+            .ok_or_else(|| crate::Error::bad_arg("config", "missing `cache_dir`"))?;
+        Self::open(client, cache_dir.join(STATE_FILE_NAME))
+    }
+
+    /// Returns the path of the state file.
+    pub fn state_path(&self) -> &Path {
+        &self.state_path
+    }
+
+    /// Returns the `ts_event` of the last record stored for `symbol` in `dataset` and
+    /// `schema`, if any.
+    pub fn last_ts_event(&self, dataset: &str, symbol: &str, schema: Schema) -> Option<u64> {
+        self.state
+            .last_ts_event
+            .get(&state_key(dataset, symbol, schema))
+            .copied()
+    }
+
+    /// Forgets the last stored record for `symbol` in `dataset` and `schema`, so the
+    /// next backfill requests the full range.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to write the state file.
+    pub fn reset(&mut self, dataset: &str, symbol: &str, schema: Schema) -> crate::Result<()> {
+        if self
+            .state
+            .last_ts_event
+            .remove(&state_key(dataset, symbol, schema))
+            .is_some()
+        {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Requests the records in the range of `params` after the last stored record,
+    /// writes them to `sink`, and saves the `ts_event` of the last one to the state
+    /// file. Nothing is requested when the stored records already reach the end of the
+    /// range.
+    ///
+    /// The state is only updated after the records are written, so a failed backfill
+    /// is retried from the same point on the next run.
+    ///
+    /// # Errors
+    /// This function returns an error when `params` has an unsupported schema, the
+    /// request, decoding, or a sink write fails, or it fails to write the state file.
+    pub async fn backfill(
+        &mut self,
+        params: &BackfillParams,
+        sink: &mut impl RecordSink,
+    ) -> crate::Result<BackfillReport> {
+        if !is_supported(params.schema) {
+            return Err(crate::Error::bad_arg(
+                "params",
+                format!("schema {} isn't stored by record sinks", params.schema),
+            ));
+        }
+        let key = state_key(&params.dataset, &params.symbol, params.schema);
+        let last_ts_event = self.state.last_ts_event.get(&key).copied();
+        let start = match last_ts_event {
+            Some(last) => {
+                let resume = (last + 1)
+                    .to_offset_date_time()
+                    .map_err(|e| crate::Error::internal(format!("invalid ts_event: {e}")))?;
+                resume.max(params.start)
+            }
+            None => params.start,
+        };
+        if start >= params.end {
+            return Ok(BackfillReport {
+                requested_start: None,
+                records: 0,
+                last_ts_event,
+            });
+        }
+        let range_params = GetRangeParams::builder()
+            .dataset(&params.dataset)
+            .symbols(params.symbol.as_str())
+            .stype_in(params.stype_in)
+            .schema(params.schema)
+            .date_time_range(DateTimeRange::from((start, params.end)))
+            .build();
+        let mut decoder = self.client.get_range(&range_params).await?;
+        let mut tracking = LastTsEvent { sink, last: None };
+        let records = write_decoded(&mut decoder, &mut tracking).await?;
+        drop(decoder);
+        let last_ts_event = match (last_ts_event, tracking.last) {
+            (Some(prev), Some(last)) => Some(prev.max(last)),
+            (prev, last) => last.or(prev),
+        };
+        if let Some(last) = last_ts_event {
+            self.state.last_ts_event.insert(key, last);
+            self.save()?;
+        }
+        Ok(BackfillReport {
+            requested_start: Some(start),
+            records,
+            last_ts_event,
+        })
+    }
+
+    // Writes to a temporary file first so an interrupted write doesn't corrupt the state
+    fn save(&self) -> crate::Result<()> {
+        let json = serde_json::to_vec_pretty(&self.state)
+            .map_err(|e| crate::Error::internal(format!("failed to serialize state: {e}")))?;
+        if let Some(parent) = self.state_path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let tmp_path = self.state_path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, &self.state_path)?;
+        Ok(())
+    }
+}
+
+fn state_key(dataset: &str, symbol: &str, schema: Schema) -> String {
+    format!("{dataset}/{symbol}/{schema}")
+}
+
+fn is_supported(schema: Schema) -> bool {
+    matches!(
+        schema,
+        Schema::Ohlcv1S
+            | Schema::Ohlcv1M
+            | Schema::Ohlcv1H
+            | Schema::Ohlcv1D
+            | Schema::OhlcvEod
+            | Schema::Trades
+    )
+}
+
+// Passes records through to a sink, recording the latest `ts_event` written
+struct LastTsEvent<'a, S> {
+    sink: &'a mut S,
+    last: Option<u64>,
+}
+
+impl<S> LastTsEvent<'_, S> {
+    fn update(&mut self, ts_event: Option<u64>) {
+        if let Some(ts_event) = ts_event {
+            self.last = Some(self.last.map_or(ts_event, |last| last.max(ts_event)));
+        }
+    }
+}
+
+impl<S: RecordSink> RecordSink for LastTsEvent<'_, S> {
+    fn write_ohlcv(&mut self, records: &[OhlcvMsg]) -> crate::Result<usize> {
+        let count = self.sink.write_ohlcv(records)?;
+        self.update(records.iter().map(|rec| rec.hd.ts_event).max());
+        Ok(count)
+    }
+
+    fn write_trades(&mut self, records: &[TradeMsg]) -> crate::Result<usize> {
+        let count = self.sink.write_trades(records)?;
+        self.update(records.iter().map(|rec| rec.hd.ts_event).max());
+        Ok(count)
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use dbn::{rtype, RecordHeader};
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::testing::MockHistoricalClient;
+
+    const DATASET: &str = "GLBX.MDP3";
+    const SYMBOL: &str = "ES.c.0";
+
+    #[derive(Default)]
+    struct VecSink {
+        ohlcv: Vec<OhlcvMsg>,
+    }
+
+    impl RecordSink for VecSink {
+        fn write_ohlcv(&mut self, records: &[OhlcvMsg]) -> crate::Result<usize> {
+            self.ohlcv.extend_from_slice(records);
+            Ok(records.len())
+        }
+
+        fn write_trades(&mut self, records: &[TradeMsg]) -> crate::Result<usize> {
+            Ok(records.len())
+        }
+    }
+
+    fn bars(start: OffsetDateTime, count: i64) -> Vec<OhlcvMsg> {
+        (0..count)
+            .map(|i| {
+                let ts_event = (start + time::Duration::minutes(i)).unix_timestamp_nanos();
+                OhlcvMsg {
+                    hd: RecordHeader::new::<OhlcvMsg>(rtype::OHLCV_1M, 1, 42, ts_event as u64),
+                    open: 5_300_000_000_000,
+                    high: 5_301_000_000_000,
+                    low: 5_299_000_000_000,
+                    close: 5_300_500_000_000,
+                    volume: 10,
+                }
+            })
+            .collect()
+    }
+
+    fn params(start: OffsetDateTime, end: OffsetDateTime) -> BackfillParams {
+        BackfillParams::builder()
+            .dataset(DATASET)
+            .symbol(SYMBOL)
+            .stype_in(SType::Continuous)
+            .schema(Schema::Ohlcv1M)
+            .start(start)
+            .end(end)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_backfill_resumes_from_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join("state").join(STATE_FILE_NAME);
+        let start = datetime!(2025-04-21 13:30 UTC);
+        let end = datetime!(2025-04-21 14:00 UTC);
+        let records = bars(start, 3);
+        let client = MockHistoricalClient::new()
+            .with_records(DATASET, Schema::Ohlcv1M, &records)
+            .unwrap();
+        let mut sink = VecSink::default();
+
+        let mut manager = Manager::open(client.clone(), &state_path).unwrap();
+        let report = manager
+            .backfill(&params(start, end), &mut sink)
+            .await
+            .unwrap();
+        assert_eq!(report.requested_start, Some(start));
+        assert_eq!(report.records, 3);
+        let last = records[2].hd.ts_event;
+        assert_eq!(report.last_ts_event, Some(last));
+        assert_eq!(sink.ohlcv.len(), 3);
+
+        // A new manager loads the state and only requests after the last record
+        let mut manager = Manager::open(client, &state_path).unwrap();
+        assert_eq!(
+            manager.last_ts_event(DATASET, SYMBOL, Schema::Ohlcv1M),
+            Some(last)
+        );
+        assert_eq!(manager.last_ts_event(DATASET, SYMBOL, Schema::Trades), None);
+        let resume = OffsetDateTime::from_unix_timestamp_nanos(i128::from(last) + 1).unwrap();
+        let report = manager
+            .backfill(&params(start, end), &mut sink)
+            .await
+            .unwrap();
+        assert_eq!(report.requested_start, Some(resume));
+        assert_eq!(
+            manager.client.requests()[0].date_time_range,
+            DateTimeRange::from((resume, end))
+        );
+
+        // Up to date
+        let report = manager
+            .backfill(&params(start, resume), &mut sink)
+            .await
+            .unwrap();
+        assert_eq!(report.requested_start, None);
+        assert_eq!(report.records, 0);
+        assert_eq!(manager.client.requests().len(), 1);
+
+        manager.reset(DATASET, SYMBOL, Schema::Ohlcv1M).unwrap();
+        assert_eq!(
+            manager.last_ts_event(DATASET, SYMBOL, Schema::Ohlcv1M),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backfill_unsupported_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = Manager::open(
+            MockHistoricalClient::new(),
+            dir.path().join(STATE_FILE_NAME),
+        )
+        .unwrap();
+        let mut params = params(
+            datetime!(2025-04-21 13:30 UTC),
+            datetime!(2025-04-21 14:00 UTC),
+        );
+        params.schema = Schema::Mbp1;
+        let res = manager.backfill(&params, &mut VecSink::default()).await;
+        assert!(matches!(res, Err(crate::Error::BadArgument { .. })));
+        assert!(manager.client.requests().is_empty());
+    }
+}
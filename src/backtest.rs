@@ -0,0 +1,521 @@
+//! Backtesting strategies over historical candles and trades.
+//!
+//! Implement [`Strategy`] and run it over 1-minute candles with [`run()`], or feed a
+//! [`Backtest`] candles and trades one at a time, e.g. from a
+//! [`Replay`](crate::replay::Replay). Orders submitted through the [`Context`] fill
+//! against the next candle or trade, never the one that triggered them, so strategies
+//! can't trade on prices they haven't seen yet. Fills are adjusted by the
+//! [`SlippageModel`] and charged the [`CommissionModel`] of the [`BacktestConfig`].
+//!
+//! Prices, cash, and equity are in the instrument's price units multiplied by
+//! [`BacktestConfig::multiplier`], e.g. 50 for dollars of E-mini S&P 500 futures.
+
+use chrono::{DateTime, Utc};
+use dbn::TradeMsg;
+
+use crate::examples::es_futures_pmz::Candle;
+
+/// The side of an order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub enum OrderSide {
+    /// Buy, increasing the position.
+    Buy = 0,
+    /// Sell, decreasing the position.
+    Sell = 1,
+}
+
+impl TryFrom<u32> for OrderSide {
+    type Error = crate::Error;
+
+    fn try_from(value: u32) -> crate::Result<Self> {
+        match value {
+            0 => Ok(Self::Buy),
+            1 => Ok(Self::Sell),
+            _ => Err(crate::Error::bad_arg(
+                "side",
+                format!("unknown order side {value}"),
+            )),
+        }
+    }
+}
+
+/// When an order fills.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderKind {
+    /// Fill at the open of the next candle or the price of the next trade.
+    Market,
+    /// Fill once the price reaches the limit or better, at the limit or the open if
+    /// the next candle opens through it.
+    Limit(f64),
+    /// Fill as a market order once the price reaches the stop, at the stop or the open
+    /// if the next candle opens through it.
+    Stop(f64),
+}
+
+/// An order to simulate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Order {
+    /// The side of the order.
+    pub side: OrderSide,
+    /// The number of contracts.
+    pub quantity: u32,
+    /// When the order fills.
+    pub kind: OrderKind,
+}
+
+impl Order {
+    /// Creates a market order.
+    pub fn market(side: OrderSide, quantity: u32) -> Self {
+        Self {
+            side,
+            quantity,
+            kind: OrderKind::Market,
+        }
+    }
+
+    /// Creates a limit order at `price`.
+    pub fn limit(side: OrderSide, quantity: u32, price: f64) -> Self {
+        Self {
+            side,
+            quantity,
+            kind: OrderKind::Limit(price),
+        }
+    }
+
+    /// Creates a stop order at `price`.
+    pub fn stop(side: OrderSide, quantity: u32, price: f64) -> Self {
+        Self {
+            side,
+            quantity,
+            kind: OrderKind::Stop(price),
+        }
+    }
+
+    // Returns the price the order fills at before slippage against a bar, or `None` if
+    // it doesn't fill. A trade is a bar whose prices are all the trade price
+    fn fill_price(&self, open: f64, high: f64, low: f64) -> Option<f64> {
+        match (self.kind, self.side) {
+            (OrderKind::Market, _) => Some(open),
+            (OrderKind::Limit(limit), OrderSide::Buy) => (low <= limit).then(|| open.min(limit)),
+            (OrderKind::Limit(limit), OrderSide::Sell) => (high >= limit).then(|| open.max(limit)),
+            (OrderKind::Stop(stop), OrderSide::Buy) => (high >= stop).then(|| open.max(stop)),
+            (OrderKind::Stop(stop), OrderSide::Sell) => (low <= stop).then(|| open.min(stop)),
+        }
+    }
+}
+
+/// How fill prices are adjusted against the strategy.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SlippageModel {
+    /// Fill at the simulated price.
+    #[default]
+    None,
+    /// Fill this many price units worse, e.g. one tick.
+    Fixed(f64),
+    /// Fill this percentage of the price worse.
+    Percent(f64),
+}
+
+impl SlippageModel {
+    fn apply(self, side: OrderSide, price: f64) -> f64 {
+        let slippage = match self {
+            Self::None => 0.0,
+            Self::Fixed(amount) => amount,
+            Self::Percent(percent) => price * percent / 100.0,
+        };
+        match side {
+            OrderSide::Buy => price + slippage,
+            OrderSide::Sell => price - slippage,
+        }
+    }
+}
+
+/// The commission charged for each fill.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CommissionModel {
+    /// No commission.
+    #[default]
+    None,
+    /// A fixed amount per contract.
+    PerContract(f64),
+    /// A fixed amount per fill regardless of its size.
+    PerOrder(f64),
+}
+
+impl CommissionModel {
+    fn commission(self, quantity: u32) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::PerContract(amount) => amount * f64::from(quantity),
+            Self::PerOrder(amount) => amount,
+        }
+    }
+}
+
+/// Settings for a [`Backtest`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestConfig {
+    /// The starting cash. Defaults to 100,000.
+    pub initial_capital: f64,
+    /// The value of a one-point price move for one contract. Defaults to 1.
+    pub multiplier: f64,
+    /// How fill prices are adjusted. Defaults to no slippage.
+    pub slippage: SlippageModel,
+    /// The commission charged for each fill. Defaults to no commission.
+    pub commission: CommissionModel,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            initial_capital: 100_000.0,
+            multiplier: 1.0,
+            slippage: SlippageModel::default(),
+            commission: CommissionModel::default(),
+        }
+    }
+}
+
+/// A simulated fill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    /// When the order filled: the start of the candle or the trade's `ts_event`.
+    pub timestamp: DateTime<Utc>,
+    /// The side of the order.
+    pub side: OrderSide,
+    /// The number of contracts.
+    pub quantity: u32,
+    /// The fill price including slippage.
+    pub price: f64,
+    /// The commission charged.
+    pub commission: f64,
+}
+
+/// The account state after a candle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EquityPoint {
+    /// The start of the candle.
+    pub timestamp: DateTime<Utc>,
+    /// The cash plus the value of the position marked to the candle's close.
+    pub equity: f64,
+    /// The position in contracts, negative when short.
+    pub position: i64,
+}
+
+/// The outcome of a backtest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestResult {
+    /// The starting cash.
+    pub initial_capital: f64,
+    /// Every fill in order.
+    pub fills: Vec<Fill>,
+    /// The equity after each candle.
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+impl BacktestResult {
+    /// Returns the equity after the last candle, or the initial capital if there were
+    /// no candles.
+    pub fn final_equity(&self) -> f64 {
+        self.equity_curve
+            .last()
+            .map_or(self.initial_capital, |point| point.equity)
+    }
+
+    /// Returns the profit or loss net of commissions.
+    pub fn net_pnl(&self) -> f64 {
+        self.final_equity() - self.initial_capital
+    }
+
+    /// Returns the total return as a percentage of the initial capital.
+    pub fn total_return_percent(&self) -> f64 {
+        self.net_pnl() / self.initial_capital * 100.0
+    }
+
+    /// Returns the total commission paid.
+    pub fn total_commission(&self) -> f64 {
+        self.fills.iter().map(|fill| fill.commission).sum()
+    }
+
+    /// Returns the largest peak-to-trough decline in equity.
+    pub fn max_drawdown(&self) -> f64 {
+        let mut peak = self.initial_capital;
+        let mut max_drawdown = 0.0_f64;
+        for point in &self.equity_curve {
+            peak = peak.max(point.equity);
+            max_drawdown = max_drawdown.max(peak - point.equity);
+        }
+        max_drawdown
+    }
+}
+
+/// The account a [`Strategy`] trades through.
+#[derive(Debug, Clone)]
+pub struct Context {
+    cash: f64,
+    position: i64,
+    last_price: Option<f64>,
+    multiplier: f64,
+    pending: Vec<Order>,
+}
+
+impl Context {
+    /// Returns the position in contracts, negative when short.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// Returns the cash balance.
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    /// Returns the cash plus the value of the position marked to the latest price.
+    pub fn equity(&self) -> f64 {
+        self.cash + self.position as f64 * self.last_price.unwrap_or(0.0) * self.multiplier
+    }
+
+    /// Submits `order` to fill against the next candle or trade. Orders that don't
+    /// fill stay pending until they do or are canceled.
+    pub fn submit(&mut self, order: Order) {
+        self.pending.push(order);
+    }
+
+    /// Returns the orders that haven't filled.
+    pub fn pending_orders(&self) -> &[Order] {
+        &self.pending
+    }
+
+    /// Cancels every pending order.
+    pub fn cancel_all(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// A trading strategy driven by candles and trades.
+pub trait Strategy {
+    /// Called after each candle closes, once pending orders have been filled against
+    /// it.
+    fn on_candle(&mut self, candle: &Candle, ctx: &mut Context);
+
+    /// Called after each trade, once pending orders have been filled against it. Does
+    /// nothing by default.
+    fn on_trade(&mut self, trade: &TradeMsg, ctx: &mut Context) {
+        let _ = (trade, ctx);
+    }
+}
+
+/// Simulates a [`Strategy`] over candles and trades fed in timestamp order.
+#[derive(Debug)]
+pub struct Backtest<S> {
+    strategy: S,
+    config: BacktestConfig,
+    ctx: Context,
+    fills: Vec<Fill>,
+    equity_curve: Vec<EquityPoint>,
+}
+
+impl<S: Strategy> Backtest<S> {
+    /// Creates a backtest of `strategy` with the settings in `config`.
+    pub fn new(strategy: S, config: BacktestConfig) -> Self {
+        Self {
+            strategy,
+            ctx: Context {
+                cash: config.initial_capital,
+                position: 0,
+                last_price: None,
+                multiplier: config.multiplier,
+                pending: Vec::new(),
+            },
+            config,
+            fills: Vec::new(),
+            equity_curve: Vec::new(),
+        }
+    }
+
+    /// Returns the strategy.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    /// Returns the account the strategy trades through.
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    /// Fills pending orders against `candle`, passes it to the strategy, and records
+    /// the equity at its close.
+    pub fn on_candle(&mut self, candle: &Candle) {
+        let timestamp = candle.timestamp.with_timezone(&Utc);
+        self.fill_pending(timestamp, candle.open, candle.high, candle.low);
+        self.ctx.last_price = Some(candle.close);
+        self.strategy.on_candle(candle, &mut self.ctx);
+        self.equity_curve.push(EquityPoint {
+            timestamp,
+            equity: self.ctx.equity(),
+            position: self.ctx.position,
+        });
+    }
+
+    /// Fills pending orders against `trade` and passes it to the strategy.
+    pub fn on_trade(&mut self, trade: &TradeMsg) {
+        let price = trade.price as f64 * 1e-9;
+        let timestamp = DateTime::from_timestamp_nanos(trade.hd.ts_event as i64);
+        self.fill_pending(timestamp, price, price, price);
+        self.ctx.last_price = Some(price);
+        self.strategy.on_trade(trade, &mut self.ctx);
+    }
+
+    /// Ends the backtest, returning the fills and equity curve. Orders still pending
+    /// are discarded.
+    pub fn finish(self) -> BacktestResult {
+        BacktestResult {
+            initial_capital: self.config.initial_capital,
+            fills: self.fills,
+            equity_curve: self.equity_curve,
+        }
+    }
+
+    fn fill_pending(&mut self, timestamp: DateTime<Utc>, open: f64, high: f64, low: f64) {
+        let pending = std::mem::take(&mut self.ctx.pending);
+        for order in pending {
+            let Some(price) = order.fill_price(open, high, low) else {
+                self.ctx.pending.push(order);
+                continue;
+            };
+            let price = self.config.slippage.apply(order.side, price);
+            let commission = self.config.commission.commission(order.quantity);
+            let notional = f64::from(order.quantity) * price * self.config.multiplier;
+            match order.side {
+                OrderSide::Buy => {
+                    self.ctx.position += i64::from(order.quantity);
+                    self.ctx.cash -= notional;
+                }
+                OrderSide::Sell => {
+                    self.ctx.position -= i64::from(order.quantity);
+                    self.ctx.cash += notional;
+                }
+            }
+            self.ctx.cash -= commission;
+            self.fills.push(Fill {
+                timestamp,
+                side: order.side,
+                quantity: order.quantity,
+                price,
+                commission,
+            });
+        }
+    }
+}
+
+/// Runs `strategy` over `candles` in order and returns the result.
+pub fn run<S: Strategy>(strategy: S, candles: &[Candle], config: BacktestConfig) -> BacktestResult {
+    let mut backtest = Backtest::new(strategy, config);
+    for candle in candles {
+        backtest.on_candle(candle);
+    }
+    backtest.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn candle(minute: i64, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2025, 4, 21, 9, 30) + Duration::minutes(minute))
+            .symbol("ES.c.0")
+            .ohlc(open, high, low, close)
+            .build()
+    }
+
+    // Buys on the first candle and sells on the third
+    struct RoundTrip {
+        candles: usize,
+    }
+
+    impl Strategy for RoundTrip {
+        fn on_candle(&mut self, _candle: &Candle, ctx: &mut Context) {
+            self.candles += 1;
+            match self.candles {
+                1 => ctx.submit(Order::market(OrderSide::Buy, 2)),
+                3 => ctx.submit(Order::market(OrderSide::Sell, 2)),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_market_round_trip() {
+        let candles = [
+            candle(0, 100.0, 101.0, 99.0, 100.0),
+            candle(1, 101.0, 103.0, 100.0, 102.0),
+            candle(2, 102.0, 102.0, 98.0, 99.0),
+            candle(3, 104.0, 105.0, 103.0, 104.0),
+        ];
+        let config = BacktestConfig {
+            initial_capital: 10_000.0,
+            multiplier: 50.0,
+            slippage: SlippageModel::Fixed(0.25),
+            commission: CommissionModel::PerContract(2.0),
+        };
+        let res = run(RoundTrip { candles: 0 }, &candles, config);
+        assert_eq!(res.fills.len(), 2);
+        assert_eq!(res.fills[0].price, 101.25);
+        assert_eq!(res.fills[1].price, 103.75);
+        assert_eq!(res.total_commission(), 8.0);
+        // 2.5 points on 2 contracts at 50, less commission
+        assert_eq!(res.net_pnl(), 242.0);
+        assert_eq!(
+            res.equity_curve
+                .iter()
+                .map(|p| p.position)
+                .collect::<Vec<_>>(),
+            [0, 2, 2, 0]
+        );
+        // From 10,071 at the second close to 9,771 at the third
+        assert_eq!(res.max_drawdown(), 300.0);
+    }
+
+    #[test]
+    fn test_limit_and_stop_fills() {
+        let buy_limit = Order::limit(OrderSide::Buy, 1, 99.0);
+        assert_eq!(buy_limit.fill_price(100.0, 101.0, 99.5), None);
+        assert_eq!(buy_limit.fill_price(100.0, 101.0, 98.0), Some(99.0));
+        // Opens through the limit
+        assert_eq!(buy_limit.fill_price(98.0, 99.0, 97.0), Some(98.0));
+        let sell_stop = Order::stop(OrderSide::Sell, 1, 99.0);
+        assert_eq!(sell_stop.fill_price(100.0, 101.0, 99.5), None);
+        assert_eq!(sell_stop.fill_price(100.0, 101.0, 98.0), Some(99.0));
+        assert_eq!(sell_stop.fill_price(97.0, 98.0, 96.0), Some(97.0));
+    }
+
+    #[test]
+    fn test_pending_orders_fill_on_trades() {
+        struct BuyOnce;
+        impl Strategy for BuyOnce {
+            fn on_candle(&mut self, _candle: &Candle, ctx: &mut Context) {
+                if ctx.position() == 0 && ctx.pending_orders().is_empty() {
+                    ctx.submit(Order::limit(OrderSide::Buy, 1, 99.0));
+                }
+            }
+        }
+
+        let mut backtest = Backtest::new(BuyOnce, BacktestConfig::default());
+        backtest.on_candle(&candle(0, 100.0, 101.0, 99.5, 100.0));
+        let trade = |price| TradeMsg {
+            price,
+            ..Default::default()
+        };
+        backtest.on_trade(&trade(99_500_000_000));
+        assert_eq!(backtest.context().pending_orders().len(), 1);
+        backtest.on_trade(&trade(98_750_000_000));
+        assert_eq!(backtest.context().position(), 1);
+        let res = backtest.finish();
+        assert_eq!(res.fills[0].price, 98.75);
+    }
+}
@@ -0,0 +1,846 @@
+//! Building session, trade-count, volume, and price-based bars.
+//!
+//! [`daily_bars()`] builds one bar per trading session from the candles within the
+//! session hours of a [`TradingCalendar`], e.g. regular trading hours (RTH) only with
+//! [`UsEquityCalendar`](crate::calendar::UsEquityCalendar), and [`weekly_bars()`]
+//! combines those into calendar or ISO weeks.
+//!
+//! [`TradeBarBuilder`] builds bars with a fixed number of trades or contracts from
+//! [`TradeMsg`] records, either from a live session or with [`trade_bars()`] for
+//! historical trades. [`RangeBarBuilder`] and [`RenkoBuilder`] build bars from price
+//! movement measured in ticks of the instrument's minimum price increment.
+//!
+//! [`QuoteCandleBuilder`] builds time bars from quote mid-prices, which update far
+//! more often than trades in illiquid contracts.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate};
+use chrono_tz::Tz;
+use dbn::{BboMsg, InstrumentDefMsg, Mbp1Msg, TradeMsg, UNDEF_PRICE};
+
+use crate::{
+    calendar::TradingCalendar,
+    examples::es_futures_pmz::{bucket_start, combine_candles, BucketAnchor, Candle, CandlePrice},
+};
+
+/// The day weeks start on for [`weekly_bars()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WeekStart {
+    /// ISO 8601 weeks starting on Monday.
+    #[default]
+    Monday,
+    /// Calendar weeks starting on Sunday.
+    Sunday,
+}
+
+/// Builds a bar for each instrument and trading session from the `candles` that start
+/// within the session's hours in `calendar`. Candles outside a session, such as
+/// pre-market and overnight candles, are ignored.
+///
+/// Each bar's timestamp is the session open in the calendar's timezone. Bars are
+/// ordered by timestamp and then instrument ID.
+pub fn daily_bars<P, C>(candles: &[Candle<P>], calendar: &C) -> Vec<Candle<P>>
+where
+    P: CandlePrice,
+    C: TradingCalendar + ?Sized,
+{
+    let tz = calendar.timezone();
+    let mut sessions: BTreeMap<(DateTime<Tz>, u32), Vec<&Candle<P>>> = BTreeMap::new();
+    for candle in candles {
+        let Some((_, open)) = session_containing(calendar, &candle.timestamp.with_timezone(&tz))
+        else {
+            continue;
+        };
+        sessions
+            .entry((open, candle.instrument_id))
+            .or_default()
+            .push(candle);
+    }
+    sessions
+        .into_iter()
+        .filter_map(|((open, instrument_id), group)| combine_candles(open, instrument_id, &group))
+        .collect()
+}
+
+/// Builds a bar for each instrument and week from the session bars of
+/// [`daily_bars()`]. Each bar's timestamp is the open of the first session in the
+/// week.
+pub fn weekly_bars<P, C>(
+    candles: &[Candle<P>],
+    calendar: &C,
+    week_start: WeekStart,
+) -> Vec<Candle<P>>
+where
+    P: CandlePrice,
+    C: TradingCalendar + ?Sized,
+{
+    let daily = daily_bars(candles, calendar);
+    let mut weeks: BTreeMap<(NaiveDate, u32), Vec<&Candle<P>>> = BTreeMap::new();
+    for bar in &daily {
+        // Group by trading date, since overnight sessions open on the day before
+        let date = session_containing(calendar, &bar.timestamp)
+            .map_or_else(|| bar.timestamp.date_naive(), |(date, _)| date);
+        weeks
+            .entry((week_of(date, week_start), bar.instrument_id))
+            .or_default()
+            .push(bar);
+    }
+    let mut bars: Vec<_> = weeks
+        .into_values()
+        .filter_map(|group| {
+            let first = group.first()?;
+            combine_candles(first.timestamp, first.instrument_id, &group)
+        })
+        .collect();
+    bars.sort_by_key(|bar| (bar.timestamp, bar.instrument_id));
+    bars
+}
+
+// Returns the trading date and open of the session in `calendar` containing `ts`.
+// Overnight sessions open on the day before their trading date.
+fn session_containing<C>(calendar: &C, ts: &DateTime<Tz>) -> Option<(NaiveDate, DateTime<Tz>)>
+where
+    C: TradingCalendar + ?Sized,
+{
+    let tz = calendar.timezone();
+    let date = ts.with_timezone(&tz).date_naive();
+    [Some(date), date.succ_opt()]
+        .into_iter()
+        .flatten()
+        .find_map(|date| {
+            let (open, close) = calendar.session(date)?.open_close(tz).ok()?;
+            (open <= *ts && *ts < close).then_some((date, open))
+        })
+}
+
+/// The size of each bar built by [`TradeBarBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TradeBarSize {
+    /// Bars of this many trades.
+    Ticks(u64),
+    /// Bars of this many contracts. Trades that would overfill a bar are split across
+    /// bars so every completed bar has exactly this volume.
+    Volume(u64),
+}
+
+/// Incrementally builds tick or volume bars from the trades of a single instrument,
+/// emitting each bar once it's complete.
+///
+/// Each bar's timestamp is the `ts_event` of its first trade.
+#[derive(Debug, Clone)]
+pub struct TradeBarBuilder<P = f64> {
+    size: TradeBarSize,
+    tz: Tz,
+    current: Option<Candle<P>>,
+    ticks: u64,
+}
+
+impl<P: CandlePrice> TradeBarBuilder<P> {
+    /// Creates a builder for bars of `size` with timestamps in `tz`. A size of 0 is
+    /// treated as 1.
+    pub fn new(size: TradeBarSize, tz: Tz) -> Self {
+        let size = match size {
+            TradeBarSize::Ticks(n) => TradeBarSize::Ticks(n.max(1)),
+            TradeBarSize::Volume(n) => TradeBarSize::Volume(n.max(1)),
+        };
+        Self {
+            size,
+            tz,
+            current: None,
+            ticks: 0,
+        }
+    }
+
+    /// Returns the in-progress bar, if any.
+    pub fn current(&self) -> Option<&Candle<P>> {
+        self.current.as_ref()
+    }
+
+    /// Adds a trade for `symbol`, returning the bars it completed. A single large
+    /// trade can complete several volume bars.
+    pub fn push(&mut self, trade: &TradeMsg, symbol: &str) -> Vec<Candle<P>> {
+        let price = P::from_fixed(trade.price);
+        let tz = self.tz;
+        let mut remaining = u64::from(trade.size);
+        let mut bars = Vec::new();
+        loop {
+            let current = self.current.get_or_insert_with(|| Candle {
+                volume: 0,
+                ..trade_candle(trade, symbol, tz, trade.price)
+            });
+            current.high = P::max_price(current.high, price);
+            current.low = P::min_price(current.low, price);
+            current.close = price;
+            let (fill, complete) = match self.size {
+                TradeBarSize::Ticks(n) => {
+                    self.ticks += 1;
+                    (remaining, self.ticks >= n)
+                }
+                TradeBarSize::Volume(n) => {
+                    let fill = remaining.min(n - current.volume);
+                    (fill, current.volume + fill >= n)
+                }
+            };
+            current.volume += fill;
+            remaining -= fill;
+            if complete {
+                bars.extend(self.flush());
+            }
+            if remaining == 0 {
+                return bars;
+            }
+        }
+    }
+
+    /// Returns the in-progress bar and resets the builder, e.g. at the end of a
+    /// session.
+    pub fn flush(&mut self) -> Option<Candle<P>> {
+        self.ticks = 0;
+        self.current.take()
+    }
+}
+
+/// Builds tick or volume bars of `size` for each instrument in `trades`, labeled with
+/// `symbol`. The final incomplete bar of each instrument is included.
+///
+/// Bars are ordered by timestamp and then instrument ID.
+pub fn trade_bars<P: CandlePrice>(
+    trades: &[TradeMsg],
+    size: TradeBarSize,
+    symbol: &str,
+    tz: Tz,
+) -> Vec<Candle<P>> {
+    let mut builders: HashMap<u32, TradeBarBuilder<P>> = HashMap::new();
+    let mut bars = Vec::new();
+    for trade in trades {
+        let builder = builders
+            .entry(trade.hd.instrument_id)
+            .or_insert_with(|| TradeBarBuilder::new(size, tz));
+        bars.extend(builder.push(trade, symbol));
+    }
+    bars.extend(builders.values_mut().filter_map(TradeBarBuilder::flush));
+    bars.sort_by_key(|bar| (bar.timestamp, bar.instrument_id));
+    bars
+}
+
+/// Fetches trades for `symbol` and builds tick or volume bars of `size` with
+/// timestamps in `tz`.
+///
+/// # Errors
+/// This function returns an error when the request fails or the response can't be
+/// decoded.
+#[cfg(feature = "historical")]
+pub async fn fetch_trade_bars(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    symbol: &str,
+    stype_in: dbn::SType,
+    date_time_range: impl Into<crate::historical::DateTimeRange>,
+    size: TradeBarSize,
+    tz: Tz,
+) -> crate::Result<Vec<Candle>> {
+    let params = crate::historical::timeseries::GetRangeParams::builder()
+        .dataset(dataset)
+        .symbols(symbol)
+        .stype_in(stype_in)
+        .schema(dbn::Schema::Trades)
+        .date_time_range(date_time_range.into())
+        .build();
+    let mut decoder = client.get_range(&params).await?;
+    let mut trades = Vec::new();
+    while let Some(trade) = decoder.decode_record::<TradeMsg>().await? {
+        trades.push(trade.clone());
+    }
+    Ok(trade_bars(&trades, size, symbol, tz))
+}
+
+/// Incrementally builds range bars from the trades of a single instrument, where the
+/// high and low of each bar are at most a fixed number of ticks apart.
+///
+/// A bar is emitted when a trade would exceed its range, and that trade opens the next
+/// bar. Each bar's timestamp is the `ts_event` of its first trade.
+#[derive(Debug, Clone)]
+pub struct RangeBarBuilder<P = f64> {
+    range: i64,
+    tz: Tz,
+    current: Option<Candle<P>>,
+    // The fixed-point high and low of the current bar
+    high: i64,
+    low: i64,
+}
+
+impl<P: CandlePrice> RangeBarBuilder<P> {
+    /// Creates a builder for bars spanning at most `range_ticks` ticks of
+    /// `tick_size`, a fixed-point price with 1e-9 scaling like
+    /// `InstrumentDefMsg::min_price_increment`. Sizes below one tick are treated as
+    /// one tick.
+    pub fn new(range_ticks: u32, tick_size: i64, tz: Tz) -> Self {
+        Self {
+            range: brick_size(range_ticks, tick_size),
+            tz,
+            current: None,
+            high: 0,
+            low: 0,
+        }
+    }
+
+    /// Creates a builder for bars spanning at most `range_ticks` ticks of the minimum
+    /// price increment in `definition`.
+    pub fn from_definition(definition: &InstrumentDefMsg, range_ticks: u32, tz: Tz) -> Self {
+        Self::new(range_ticks, definition.min_price_increment, tz)
+    }
+
+    /// Returns the in-progress bar, if any.
+    pub fn current(&self) -> Option<&Candle<P>> {
+        self.current.as_ref()
+    }
+
+    /// Adds a trade for `symbol`, returning the previous bar if the trade falls outside
+    /// its range.
+    pub fn push(&mut self, trade: &TradeMsg, symbol: &str) -> Option<Candle<P>> {
+        let high = self.high.max(trade.price);
+        let low = self.low.min(trade.price);
+        match self.current.as_mut() {
+            Some(current) if high.saturating_sub(low) <= self.range => {
+                let price = P::from_fixed(trade.price);
+                current.high = P::max_price(current.high, price);
+                current.low = P::min_price(current.low, price);
+                current.close = price;
+                current.volume += u64::from(trade.size);
+                self.high = high;
+                self.low = low;
+                None
+            }
+            _ => {
+                self.high = trade.price;
+                self.low = trade.price;
+                self.current
+                    .replace(trade_candle(trade, symbol, self.tz, trade.price))
+            }
+        }
+    }
+
+    /// Returns the in-progress bar and resets the builder, e.g. at the end of a
+    /// session.
+    pub fn flush(&mut self) -> Option<Candle<P>> {
+        self.current.take()
+    }
+}
+
+/// Incrementally builds Renko bricks from the trades of a single instrument.
+///
+/// A brick is emitted each time the price closes a full brick beyond the last brick:
+/// above its top to continue up or below its bottom to continue down, so reversals
+/// require a move of two bricks from the last close. A single trade can complete
+/// several bricks. Bricks have no wicks: the high and low are the brick's edges.
+///
+/// Each brick's timestamp is the `ts_event` of the trade that completed it and its
+/// volume is that of the trades since the previous brick.
+#[derive(Debug, Clone)]
+pub struct RenkoBuilder<P = f64> {
+    brick: i64,
+    tz: Tz,
+    // The fixed-point bottom and top of the last brick, or the first price before any
+    // bricks
+    bottom: Option<i64>,
+    top: i64,
+    volume: u64,
+    _price: std::marker::PhantomData<P>,
+}
+
+impl<P: CandlePrice> RenkoBuilder<P> {
+    /// Creates a builder for bricks of `brick_ticks` ticks of `tick_size`, a
+    /// fixed-point price with 1e-9 scaling like
+    /// `InstrumentDefMsg::min_price_increment`. Sizes below one tick are treated as
+    /// one tick.
+    pub fn new(brick_ticks: u32, tick_size: i64, tz: Tz) -> Self {
+        Self {
+            brick: brick_size(brick_ticks, tick_size),
+            tz,
+            bottom: None,
+            top: 0,
+            volume: 0,
+            _price: std::marker::PhantomData,
+        }
+    }
+
+    /// Creates a builder for bricks of `brick_ticks` ticks of the minimum price
+    /// increment in `definition`.
+    pub fn from_definition(definition: &InstrumentDefMsg, brick_ticks: u32, tz: Tz) -> Self {
+        Self::new(brick_ticks, definition.min_price_increment, tz)
+    }
+
+    /// Adds a trade for `symbol`, returning the bricks it completed in order.
+    pub fn push(&mut self, trade: &TradeMsg, symbol: &str) -> Vec<Candle<P>> {
+        self.volume += u64::from(trade.size);
+        let Some(mut bottom) = self.bottom else {
+            self.bottom = Some(trade.price);
+            self.top = trade.price;
+            return Vec::new();
+        };
+        let mut bricks = Vec::new();
+        while trade.price >= self.top.saturating_add(self.brick) {
+            let open = self.top;
+            self.top += self.brick;
+            bottom = open;
+            bricks.push(self.brick_candle(trade, symbol, open, self.top));
+        }
+        while trade.price <= bottom.saturating_sub(self.brick) {
+            let open = bottom;
+            bottom -= self.brick;
+            self.top = open;
+            bricks.push(self.brick_candle(trade, symbol, open, bottom));
+        }
+        self.bottom = Some(bottom);
+        bricks
+    }
+
+    fn brick_candle(&mut self, trade: &TradeMsg, symbol: &str, open: i64, close: i64) -> Candle<P> {
+        Candle {
+            open: P::from_fixed(open),
+            high: P::from_fixed(open.max(close)),
+            low: P::from_fixed(open.min(close)),
+            close: P::from_fixed(close),
+            volume: std::mem::take(&mut self.volume),
+            ..trade_candle(trade, symbol, self.tz, close)
+        }
+    }
+}
+
+/// Incrementally builds candles from the mid-price of the best bid and offer of a
+/// single instrument, emitting each candle once a quote arrives for a later interval.
+///
+/// Quotes where either side is missing or the book is crossed are ignored. The volume
+/// of a candle is that of the trades in the MBP-1 records pushed, and zero for BBO
+/// records.
+#[derive(Debug, Clone)]
+pub struct QuoteCandleBuilder<P = f64> {
+    interval: Duration,
+    anchor: BucketAnchor,
+    tz: Tz,
+    current: Option<Candle<P>>,
+}
+
+impl<P: CandlePrice> QuoteCandleBuilder<P> {
+    /// Creates a builder for `interval` candles aligned to `anchor` with timestamps in
+    /// `tz`.
+    pub fn new(interval: Duration, anchor: BucketAnchor, tz: Tz) -> Self {
+        Self {
+            interval,
+            anchor,
+            tz,
+            current: None,
+        }
+    }
+
+    /// Returns the in-progress candle, if any.
+    pub fn current(&self) -> Option<&Candle<P>> {
+        self.current.as_ref()
+    }
+
+    /// Adds an MBP-1 record for `symbol`, returning the previous candle if the record
+    /// starts a new interval. Trades add to the candle's volume.
+    pub fn push_mbp1(&mut self, mbp1: &Mbp1Msg, symbol: &str) -> Option<Candle<P>> {
+        let level = &mbp1.levels[0];
+        let volume = if mbp1.action as u8 == b'T' {
+            u64::from(mbp1.size)
+        } else {
+            0
+        };
+        self.push_quote(
+            mbp1.hd.ts_event,
+            mbp1.hd.instrument_id,
+            level.bid_px,
+            level.ask_px,
+            volume,
+            symbol,
+        )
+    }
+
+    /// Adds a BBO record for `symbol` timestamped at the end of its sampling interval,
+    /// returning the previous candle if the record starts a new interval.
+    pub fn push_bbo(&mut self, bbo: &BboMsg, symbol: &str) -> Option<Candle<P>> {
+        let level = &bbo.levels[0];
+        self.push_quote(
+            bbo.ts_recv,
+            bbo.hd.instrument_id,
+            level.bid_px,
+            level.ask_px,
+            0,
+            symbol,
+        )
+    }
+
+    pub(crate) fn push_quote(
+        &mut self,
+        ts: u64,
+        instrument_id: u32,
+        bid_px: i64,
+        ask_px: i64,
+        volume: u64,
+        symbol: &str,
+    ) -> Option<Candle<P>> {
+        let timestamp = DateTime::from_timestamp_nanos(ts as i64).with_timezone(&self.tz);
+        let timestamp = bucket_start(timestamp, self.interval, self.anchor);
+        let Some(mid) = mid_price(bid_px, ask_px) else {
+            // Still count trades while the book is one-sided
+            if let Some(current) = self
+                .current
+                .as_mut()
+                .filter(|current| current.timestamp == timestamp)
+            {
+                current.volume += volume;
+            }
+            return None;
+        };
+        let mid = P::from_fixed(mid);
+        match self.current.as_mut() {
+            Some(current) if current.timestamp == timestamp => {
+                current.high = P::max_price(current.high, mid);
+                current.low = P::min_price(current.low, mid);
+                current.close = mid;
+                current.volume += volume;
+                None
+            }
+            _ => self.current.replace(Candle {
+                timestamp,
+                instrument_id,
+                symbol: symbol.into(),
+                open: mid,
+                high: mid,
+                low: mid,
+                close: mid,
+                volume,
+            }),
+        }
+    }
+
+    /// Returns the in-progress candle and resets the builder, e.g. at the end of a
+    /// session.
+    pub fn flush(&mut self) -> Option<Candle<P>> {
+        self.current.take()
+    }
+}
+
+/// Builds `interval` mid-price candles aligned to `anchor` for each instrument in the
+/// MBP-1 records `quotes`, labeled with `symbol`.
+///
+/// Candles are ordered by timestamp and then instrument ID.
+pub fn quote_candles<P: CandlePrice>(
+    quotes: &[Mbp1Msg],
+    interval: Duration,
+    anchor: BucketAnchor,
+    symbol: &str,
+    tz: Tz,
+) -> Vec<Candle<P>> {
+    let mut builders: HashMap<u32, QuoteCandleBuilder<P>> = HashMap::new();
+    let mut candles = Vec::new();
+    for quote in quotes {
+        let builder = builders
+            .entry(quote.hd.instrument_id)
+            .or_insert_with(|| QuoteCandleBuilder::new(interval, anchor, tz));
+        candles.extend(builder.push_mbp1(quote, symbol));
+    }
+    candles.extend(builders.values_mut().filter_map(QuoteCandleBuilder::flush));
+    candles.sort_by_key(|candle| (candle.timestamp, candle.instrument_id));
+    candles
+}
+
+// Returns the fixed-point mid-price, or `None` if the book is one-sided or crossed
+fn mid_price(bid_px: i64, ask_px: i64) -> Option<i64> {
+    if bid_px == UNDEF_PRICE || ask_px == UNDEF_PRICE || bid_px > ask_px {
+        return None;
+    }
+    Some(bid_px + (ask_px - bid_px) / 2)
+}
+
+// Returns a brick or range size in fixed-point price units
+fn brick_size(ticks: u32, tick_size: i64) -> i64 {
+    i64::from(ticks.max(1)).saturating_mul(tick_size.max(1))
+}
+
+// Returns a single-trade candle at `price`
+fn trade_candle<P: CandlePrice>(trade: &TradeMsg, symbol: &str, tz: Tz, price: i64) -> Candle<P> {
+    let price = P::from_fixed(price);
+    Candle {
+        timestamp: DateTime::from_timestamp_nanos(trade.hd.ts_event as i64).with_timezone(&tz),
+        instrument_id: trade.hd.instrument_id,
+        symbol: symbol.into(),
+        open: price,
+        high: price,
+        low: price,
+        close: price,
+        volume: u64::from(trade.size),
+    }
+}
+
+// Returns the first day of the week containing `date`
+fn week_of(date: NaiveDate, week_start: WeekStart) -> NaiveDate {
+    let days_into_week = match week_start {
+        WeekStart::Monday => date.weekday().num_days_from_monday(),
+        WeekStart::Sunday => date.weekday().num_days_from_sunday(),
+    };
+    date - Duration::days(days_into_week.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use chrono::{NaiveTime, TimeZone};
+    use chrono_tz::America::New_York;
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+    use crate::{
+        calendar::{FixedHoursCalendar, Session, UsEquityCalendar},
+        test_util::{self, eastern},
+    };
+
+    fn candle(day: u32, hour: u32, minute: u32, price: f64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2025, 4, day, hour, minute))
+            .ohlc(price, price + 1.0, price - 1.0, price)
+            .build()
+    }
+
+    fn fixture() -> Vec<Candle> {
+        vec![
+            // Pre-market
+            candle(17, 8, 0, 5000.0),
+            candle(17, 9, 30, 5300.0),
+            candle(17, 15, 59, 5310.0),
+            // After hours
+            candle(17, 16, 0, 5400.0),
+            // Good Friday
+            candle(18, 10, 0, 5200.0),
+            candle(21, 9, 30, 5320.0),
+            candle(21, 12, 0, 5290.0),
+        ]
+    }
+
+    #[test]
+    fn test_daily_bars_rth() {
+        let bars = daily_bars(&fixture(), &UsEquityCalendar);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(
+            bars[0].timestamp,
+            New_York.with_ymd_and_hms(2025, 4, 17, 9, 30, 0).unwrap()
+        );
+        assert_eq!(bars[0].open, 5300.0);
+        assert_eq!(bars[0].high, 5311.0);
+        assert_eq!(bars[0].low, 5299.0);
+        assert_eq!(bars[0].close, 5310.0);
+        assert_eq!(bars[0].volume, 20);
+        assert_eq!(bars[1].close, 5290.0);
+
+        // Custom session hours including Good Friday
+        let calendar = FixedHoursCalendar::new(
+            NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            New_York,
+        );
+        let bars = daily_bars(&fixture(), &calendar);
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[0].open, 5000.0);
+        assert_eq!(bars[0].close, 5400.0);
+    }
+
+    #[test]
+    fn test_daily_bars_globex() {
+        let mut candles = fixture();
+        candles.push(candle(20, 18, 0, 5250.0));
+        // Maintenance break
+        candles.push(candle(21, 17, 30, 5280.0));
+        candles.sort_by_key(|candle| candle.timestamp);
+        let bars = daily_bars(&candles, &Session::CME_GLOBEX);
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[0].open, 5000.0);
+        assert_eq!(bars[0].close, 5400.0);
+        // Monday's session opens on Sunday evening
+        assert_eq!(
+            bars[2].timestamp,
+            New_York.with_ymd_and_hms(2025, 4, 20, 18, 0, 0).unwrap()
+        );
+        assert_eq!(bars[2].open, 5250.0);
+        assert_eq!(bars[2].close, 5290.0);
+        assert_eq!(bars[2].volume, 30);
+    }
+
+    #[test]
+    fn test_weekly_bars() {
+        let mut candles = fixture();
+        // Sunday evening is outside RTH
+        candles.push(candle(20, 18, 0, 5250.0));
+        let bars = weekly_bars(&candles, &UsEquityCalendar, WeekStart::Monday);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].close, 5310.0);
+        assert_eq!(bars[1].open, 5320.0);
+
+        let calendar = FixedHoursCalendar::new(
+            NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            New_York,
+        );
+        let bars = weekly_bars(&candles, &calendar, WeekStart::Sunday);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].volume, 50);
+        assert_eq!(
+            bars[1].timestamp,
+            New_York.with_ymd_and_hms(2025, 4, 21, 8, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_weekly_bars_globex() {
+        let mut candles = fixture();
+        candles.push(candle(20, 18, 0, 5250.0));
+        candles.push(candle(22, 12, 0, 5330.0));
+        candles.sort_by_key(|candle| candle.timestamp);
+        let bars = weekly_bars(&candles, &Session::CME_GLOBEX, WeekStart::Monday);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].close, 5200.0);
+        // Monday's session opens on Sunday evening but belongs to the week of Monday
+        assert_eq!(
+            bars[1].timestamp,
+            New_York.with_ymd_and_hms(2025, 4, 20, 18, 0, 0).unwrap()
+        );
+        assert_eq!(bars[1].open, 5250.0);
+        assert_eq!(bars[1].close, 5330.0);
+        assert_eq!(bars[1].volume, 40);
+    }
+
+    fn trade(ts_event: u64, price: f64, size: u32) -> TradeMsg {
+        test_util::trade(1, ts_event, (price * 1e9) as i64, size)
+    }
+
+    #[test]
+    fn test_tick_bars() {
+        let trades: Vec<_> = [5300.0, 5301.0, 5299.0, 5302.0, 5303.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, price)| trade(i as u64, price, 2))
+            .collect();
+        let bars: Vec<Candle> = trade_bars(&trades, TradeBarSize::Ticks(3), "ESM5", Tz::UTC);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].open, 5300.0);
+        assert_eq!(bars[0].high, 5301.0);
+        assert_eq!(bars[0].low, 5299.0);
+        assert_eq!(bars[0].close, 5299.0);
+        assert_eq!(bars[0].volume, 6);
+        // The incomplete bar
+        assert_eq!(bars[1].timestamp.timestamp_nanos_opt(), Some(3));
+        assert_eq!(bars[1].volume, 4);
+    }
+
+    #[test]
+    fn test_volume_bars() {
+        let mut builder = TradeBarBuilder::<f64>::new(TradeBarSize::Volume(5), Tz::UTC);
+        assert!(builder.push(&trade(0, 5300.0, 3), "ESM5").is_empty());
+        // Completes the first bar and fills two more
+        let bars = builder.push(&trade(1, 5301.0, 13), "ESM5");
+        assert_eq!(bars.len(), 3);
+        assert!(bars.iter().all(|bar| bar.volume == 5));
+        assert_eq!(bars[0].open, 5300.0);
+        assert_eq!(bars[0].close, 5301.0);
+        assert_eq!(bars[1].open, 5301.0);
+        assert_eq!(builder.current().unwrap().volume, 1);
+        assert_eq!(builder.flush().unwrap().volume, 1);
+        assert!(builder.current().is_none());
+    }
+
+    #[test]
+    fn test_range_bars() {
+        // 4 ticks of 0.25
+        let mut builder = RangeBarBuilder::<f64>::new(4, 250_000_000, Tz::UTC);
+        assert!(builder.push(&trade(0, 5300.0, 1), "ESM5").is_none());
+        assert!(builder.push(&trade(1, 5300.75, 1), "ESM5").is_none());
+        assert!(builder.push(&trade(2, 5299.75, 1), "ESM5").is_none());
+        let bar = builder.push(&trade(3, 5301.0, 2), "ESM5").unwrap();
+        assert_eq!(bar.open, 5300.0);
+        assert_eq!(bar.high, 5300.75);
+        assert_eq!(bar.low, 5299.75);
+        assert_eq!(bar.close, 5299.75);
+        assert_eq!(bar.volume, 3);
+        let current = builder.current().unwrap();
+        assert_eq!(current.open, 5301.0);
+        assert_eq!(current.volume, 2);
+    }
+
+    #[test]
+    fn test_renko() {
+        let definition = InstrumentDefMsg {
+            min_price_increment: 250_000_000,
+            ..Default::default()
+        };
+        // 1-point bricks
+        let mut builder = RenkoBuilder::<f64>::from_definition(&definition, 4, Tz::UTC);
+        assert!(builder.push(&trade(0, 5300.0, 1), "ESM5").is_empty());
+        assert!(builder.push(&trade(1, 5300.75, 1), "ESM5").is_empty());
+        let bricks = builder.push(&trade(2, 5302.5, 1), "ESM5");
+        assert_eq!(bricks.len(), 2);
+        assert_eq!((bricks[0].open, bricks[0].close), (5300.0, 5301.0));
+        assert_eq!(bricks[0].volume, 3);
+        assert_eq!((bricks[1].open, bricks[1].close), (5301.0, 5302.0));
+        assert_eq!(bricks[1].volume, 0);
+        // A one-brick pullback doesn't reverse
+        assert!(builder.push(&trade(3, 5300.5, 1), "ESM5").is_empty());
+        let bricks = builder.push(&trade(4, 5300.0, 1), "ESM5");
+        assert_eq!(bricks.len(), 1);
+        assert_eq!((bricks[0].open, bricks[0].close), (5301.0, 5300.0));
+        assert_eq!(bricks[0].high, 5301.0);
+        assert_eq!(bricks[0].low, 5300.0);
+        assert_eq!(bricks[0].volume, 2);
+    }
+
+    fn quote(ts_event: u64, bid: f64, ask: f64, action: u8, size: u32) -> Mbp1Msg {
+        let mut quote = Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 0, 1, ts_event),
+            action: action as c_char,
+            size,
+            ..Default::default()
+        };
+        quote.levels[0].bid_px = (bid * 1e9) as i64;
+        quote.levels[0].ask_px = (ask * 1e9) as i64;
+        quote
+    }
+
+    #[test]
+    fn test_quote_candles() {
+        const MINUTE: u64 = 60_000_000_000;
+        let quotes = [
+            quote(0, 5300.0, 5300.5, b'A', 1),
+            quote(1, 5300.5, 5301.0, b'A', 1),
+            quote(2, 5300.5, 5301.0, b'T', 3),
+            // Crossed
+            quote(3, 5302.0, 5301.0, b'A', 1),
+            quote(MINUTE, 5299.0, 5299.5, b'C', 1),
+        ];
+        let candles: Vec<Candle> = quote_candles(
+            &quotes,
+            Duration::minutes(1),
+            BucketAnchor::Epoch,
+            "ESM5",
+            Tz::UTC,
+        );
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[0].open, 5300.25);
+        assert_eq!(candles[0].high, 5300.75);
+        assert_eq!(candles[0].low, 5300.25);
+        assert_eq!(candles[0].close, 5300.75);
+        assert_eq!(candles[0].volume, 3);
+        assert_eq!(candles[1].open, 5299.25);
+        assert_eq!(candles[1].volume, 0);
+    }
+
+    #[test]
+    fn test_week_of() {
+        let sunday = NaiveDate::from_ymd_opt(2025, 4, 20).unwrap();
+        assert_eq!(week_of(sunday, WeekStart::Sunday), sunday);
+        assert_eq!(
+            week_of(sunday, WeekStart::Monday),
+            NaiveDate::from_ymd_opt(2025, 4, 14).unwrap()
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Command-line interface for PMZ calculations, candles, and symbology resolution.
+//!
+//! Reads settings from the config file passed with `--config` or otherwise from
+//! environment variables such as `DATABENTO_API_KEY`.
+use std::{error::Error, io::Write, path::PathBuf};
+
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand, ValueEnum};
+use databento::{
+    config::Config,
+    dbn::SType,
+    examples::es_futures_pmz::{calculate_pmz_from_config, fetch_candles, infer_stype, Candle},
+    historical::symbology::ResolveParams,
+    timeconv::ToTimeDate,
+    HistoricalClient,
+};
+use time::{format_description::well_known::Rfc3339, macros::format_description, OffsetDateTime};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// Path to a TOML config file
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Calculate PMZ values for a trading day
+    Pmz {
+        /// The trading day in YYYY-MM-DD format. Defaults to today
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Log progress and diagnostic information to stderr
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Fetch OHLCV candles aggregated from 1-minute bars
+    Candles {
+        /// The symbol to fetch, e.g. ES.c.0 or ESM5
+        #[arg(long)]
+        symbol: String,
+        /// The candle interval in minutes
+        #[arg(long, default_value_t = 1)]
+        interval: u32,
+        /// The inclusive start as YYYY-MM-DD or an RFC 3339 datetime
+        #[arg(long, value_parser = parse_datetime)]
+        start: OffsetDateTime,
+        /// The exclusive end as YYYY-MM-DD or an RFC 3339 datetime
+        #[arg(long, value_parser = parse_datetime)]
+        end: OffsetDateTime,
+        /// The dataset. Defaults to the dataset in the config or GLBX.MDP3
+        #[arg(long)]
+        dataset: Option<String>,
+        /// The symbology type of the symbol. Inferred from the symbol by default
+        #[arg(long)]
+        stype: Option<SType>,
+        /// The timezone for candle timestamps
+        #[arg(long, default_value = "America/New_York")]
+        tz: Tz,
+        /// The output format
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+    },
+    /// Resolve a symbol to instrument IDs
+    Resolve {
+        /// The symbol to resolve
+        #[arg(long)]
+        symbol: String,
+        /// The date to resolve the symbol on in YYYY-MM-DD format. Defaults to today
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// The dataset. Defaults to the dataset in the config or GLBX.MDP3
+        #[arg(long)]
+        dataset: Option<String>,
+        /// The symbology type of the symbol. Inferred from the symbol by default
+        #[arg(long)]
+        stype: Option<SType>,
+    },
+    /// Serve PMZ values and candles over HTTP
+    #[cfg(feature = "server")]
+    Serve {
+        /// The address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+}
+
+const DEFAULT_DATASET: &str = "GLBX.MDP3";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let verbose = matches!(args.command, Command::Pmz { verbose: true, .. });
+    tracing_subscriber::fmt()
+        .with_max_level(if verbose {
+            tracing::Level::INFO
+        } else {
+            tracing::Level::WARN
+        })
+        .with_writer(std::io::stderr)
+        .init();
+    let config = match args.config {
+        Some(path) => Config::from_file(path)?,
+        None => Config::from_env()?,
+    };
+    match args.command {
+        Command::Pmz { date, verbose } => {
+            let result = calculate_pmz_from_config(&config, date, |diagnostic| {
+                if verbose {
+                    eprintln!("{diagnostic}");
+                }
+            })
+            .await?;
+            let fmt = |val: Option<f64>| val.map_or("N/A".to_owned(), |v| format!("{v:.2}"));
+            println!("PMZ for {}", result.date);
+            println!("PMH: {}", fmt(result.pmh));
+            println!("PML: {}", fmt(result.pml));
+            println!("Previous Day LIS: {}", fmt(result.prev_day_lis));
+            println!(
+                "Gap Direction: {}",
+                match result.is_gap_up {
+                    Some(true) => "Up",
+                    Some(false) => "Down",
+                    None => "N/A",
+                }
+            );
+            println!(
+                "Gap Size: {}",
+                result
+                    .gap_points
+                    .zip(result.gap_percent)
+                    .map_or("N/A".to_owned(), |(points, percent)| format!(
+                        "{points:+.2} ({percent:+.2}%)"
+                    ))
+            );
+            println!("PMZ High: {}", fmt(result.pmz_high));
+            println!("PMZ Low: {}", fmt(result.pmz_low));
+            println!("Risk: {}", fmt(result.risk));
+            if !result.is_complete() {
+                let missing: Vec<_> = result.missing.iter().map(|c| c.as_str()).collect();
+                eprintln!("Missing: {}", missing.join(", "));
+            }
+        }
+        Command::Candles {
+            symbol,
+            interval,
+            start,
+            end,
+            dataset,
+            stype,
+            tz,
+            format,
+        } => {
+            let mut client = HistoricalClient::builder().config(&config)?.build()?;
+            let candles = fetch_candles(
+                &mut client,
+                &dataset_or_default(dataset, &config),
+                &symbol,
+                stype.unwrap_or_else(|| infer_stype(&symbol)),
+                (start, end),
+                interval,
+                tz,
+            )
+            .await?;
+            write_candles(&candles, format)?;
+        }
+        Command::Resolve {
+            symbol,
+            date,
+            dataset,
+            stype,
+        } => {
+            let mut client = HistoricalClient::builder().config(&config)?.build()?;
+            let date = date.unwrap_or_else(|| chrono::Utc::now().date_naive());
+            let start = date.to_time_date()?;
+            let resolution = client
+                .symbology()
+                .resolve(
+                    &ResolveParams::builder()
+                        .dataset(dataset_or_default(dataset, &config))
+                        .symbols(symbol.as_str())
+                        .stype_in(stype.unwrap_or_else(|| infer_stype(&symbol)))
+                        .date_range((start, start.next_day().unwrap_or(start)))
+                        .build(),
+                )
+                .await?;
+            for (input, intervals) in resolution.mappings {
+                for interval in intervals {
+                    println!(
+                        "{input} {} - {}: {}",
+                        interval.start_date, interval.end_date, interval.symbol
+                    );
+                }
+            }
+            for input in resolution.not_found {
+                eprintln!("{input} not found");
+            }
+        }
+        #[cfg(feature = "server")]
+        Command::Serve { addr } => databento::server::serve(addr, &config).await?,
+    }
+    Ok(())
+}
+
+fn dataset_or_default(dataset: Option<String>, config: &Config) -> String {
+    dataset
+        .or_else(|| config.dataset.clone())
+        .unwrap_or_else(|| DEFAULT_DATASET.to_owned())
+}
+
+fn parse_datetime(s: &str) -> Result<OffsetDateTime, String> {
+    if let Ok(dt) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Ok(dt);
+    }
+    time::Date::parse(s, format_description!("[year]-[month]-[day]"))
+        .map(|d| d.midnight().assume_utc())
+        .map_err(|e| format!("expected YYYY-MM-DD or an RFC 3339 datetime: {e}"))
+}
+
+fn write_candles(candles: &[Candle], format: Format) -> std::io::Result<()> {
+    let mut out = std::io::stdout().lock();
+    match format {
+        Format::Csv => {
+            writeln!(out, "timestamp,symbol,open,high,low,close,volume")?;
+            for c in candles {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{}",
+                    c.timestamp.to_rfc3339(),
+                    c.symbol,
+                    c.open,
+                    c.high,
+                    c.low,
+                    c.close,
+                    c.volume
+                )?;
+            }
+        }
+        Format::Json => {
+            let candles: Vec<_> = candles
+                .iter()
+                .map(|c| {
+                    serde_json::json!({
+                        "timestamp": c.timestamp.to_rfc3339(),
+                        "symbol": &*c.symbol,
+                        "open": c.open,
+                        "high": c.high,
+                        "low": c.low,
+                        "close": c.close,
+                        "volume": c.volume,
+                    })
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut out, &candles)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
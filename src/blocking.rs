@@ -0,0 +1,418 @@
+//! A blocking (synchronous) wrapper around the historical client.
+//!
+//! [`HistoricalClient`] owns a single-threaded Tokio runtime and drives the async
+//! [`crate::HistoricalClient`] to completion on each call, so scripts, the FFI layer,
+//! and other non-async consumers don't need to manage a runtime themselves.
+//!
+//! <div class="warning">
+//! The methods of this client must not be called from within an async context, as
+//! blocking on a runtime from within another runtime will panic.
+//! </div>
+
+use std::{fmt, future::Future, path::PathBuf};
+
+use dbn::{HasRType, Metadata};
+use tokio::runtime::Runtime;
+
+use crate::historical::{
+    self,
+    batch::{BatchJob, DownloadParams, ListJobsParams, SubmitJobParams},
+    metadata::{
+        DatasetConditionDetail, DatasetRange, GetCostParams, GetDatasetConditionParams,
+        GetRecordCountParams,
+    },
+    symbology::{Resolution, ResolveParams},
+    timeseries::{GetRangeParams, GetRangeToFileParams},
+    DateRange, Entitlements, HistoricalGateway,
+};
+
+/// The blocking Historical client. Wraps [`crate::HistoricalClient`] with an internal
+/// runtime.
+pub struct HistoricalClient {
+    inner: historical::Client,
+    runtime: Runtime,
+}
+
+impl HistoricalClient {
+    /// Creates a new blocking client with the given API key.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to build the HTTP client or the
+    /// internal runtime.
+    pub fn new(key: String, gateway: HistoricalGateway) -> crate::Result<Self> {
+        Self::from_async(historical::Client::new(key, gateway)?)
+    }
+
+    /// Creates a new blocking client with the API key read from the
+    /// `DATABENTO_API_KEY` environment variable.
+    ///
+    /// # Errors
+    /// This function returns an error when the environment variable is not set, the API
+    /// key is invalid, or it fails to build the HTTP client or the internal runtime.
+    pub fn from_env() -> crate::Result<Self> {
+        Self::from_async(historical::Client::builder().key_from_env()?.build()?)
+    }
+
+    /// Creates a new blocking client with a specific API URL. This is an advanced
+    /// method and [`new()`](Self::new) should be used instead.
+    ///
+    /// # Errors
+    /// This function returns an error when the `url` is invalid or it fails to build
+    /// the internal runtime.
+    pub fn with_url(
+        url: impl reqwest::IntoUrl,
+        key: String,
+        gateway: HistoricalGateway,
+    ) -> crate::Result<Self> {
+        Self::from_async(historical::Client::with_url(url, key, gateway)?)
+    }
+
+    /// Wraps an existing async client.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to build the internal runtime.
+    pub fn from_async(inner: historical::Client) -> crate::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Self { inner, runtime })
+    }
+
+    /// Returns a reference to the wrapped async client.
+    pub fn as_async(&self) -> &historical::Client {
+        &self.inner
+    }
+
+    /// Returns the API key used by the instance of the client.
+    pub fn key(&self) -> &str {
+        self.inner.key()
+    }
+
+    /// Returns the configured Historical gateway.
+    pub fn gateway(&self) -> HistoricalGateway {
+        self.inner.gateway()
+    }
+
+    /// Runs `future` to completion on the internal runtime. This is for async APIs
+    /// without a blocking counterpart, like the PMZ calculation, which can be passed a
+    /// clone of [`as_async()`](Self::as_async). It can be called from multiple threads
+    /// at once.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Checks that the API key is valid by making a cheap authenticated request, and
+    /// returns the datasets it grants access to.
+    ///
+    /// # Errors
+    /// This function returns an [`Error::Auth`](crate::Error::Auth) when the API key is
+    /// rejected, and another error when it otherwise fails to communicate with the
+    /// Databento API.
+    pub fn validate_key(&mut self) -> crate::Result<Entitlements> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.validate_key())
+    }
+
+    /// Requests timeseries data and decodes all records of type `R`, returning them
+    /// along with the DBN metadata of the response.
+    ///
+    /// Records of other types are skipped. For larger requests, consider
+    /// [`get_range_to_file()`](Self::get_range_to_file).
+    ///
+    /// <div class="warning">
+    /// Calling this method will incur a cost.
+    /// </div>
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API,
+    /// the API indicates there's an issue with the request, or it fails to decode the
+    /// response.
+    pub fn get_range<R>(&mut self, params: &GetRangeParams) -> crate::Result<(Metadata, Vec<R>)>
+    where
+        R: HasRType + Clone,
+    {
+        let Self { inner, runtime } = self;
+        runtime.block_on(async {
+            let mut decoder = inner.timeseries().get_range(params).await?;
+            let metadata = decoder.metadata().clone();
+            let mut records = Vec::new();
+            while let Some(rec) = decoder.decode_record_ref().await? {
+                if let Some(rec) = rec.get::<R>() {
+                    records.push(rec.clone());
+                }
+            }
+            Ok((metadata, records))
+        })
+    }
+
+    /// Requests timeseries data and persists it to the file at `params.path` without
+    /// decoding it, returning the path.
+    ///
+    /// <div class="warning">
+    /// Calling this method will incur a cost.
+    /// </div>
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API,
+    /// the API indicates there's an issue with the request, or it fails to write the file.
+    pub fn get_range_to_file(&mut self, params: &GetRangeToFileParams) -> crate::Result<PathBuf> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(async {
+            inner.timeseries().download_range(params).await?;
+            Ok(params.path.clone())
+        })
+    }
+
+    /// Resolves a list of symbols from an input symbology type to an output one.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn resolve(&mut self, params: &ResolveParams) -> crate::Result<Resolution> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.symbology().resolve(params))
+    }
+
+    /// Lists all available dataset codes on Databento.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn list_datasets(&mut self, date_range: Option<DateRange>) -> crate::Result<Vec<String>> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.metadata().list_datasets(date_range))
+    }
+
+    /// Gets the dataset condition from Databento.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn get_dataset_condition(
+        &mut self,
+        params: &GetDatasetConditionParams,
+    ) -> crate::Result<Vec<DatasetConditionDetail>> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.metadata().get_dataset_condition(params))
+    }
+
+    /// Gets the available range for the dataset given the user's entitlements.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn get_dataset_range(&mut self, dataset: &str) -> crate::Result<DatasetRange> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.metadata().get_dataset_range(dataset))
+    }
+
+    /// Gets the record count of the time series data query.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn get_record_count(&mut self, params: &GetRecordCountParams) -> crate::Result<u64> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.metadata().get_record_count(params))
+    }
+
+    /// Gets the cost in US dollars for a historical streaming or batch download
+    /// request.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn get_cost(&mut self, params: &GetCostParams) -> crate::Result<f64> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.metadata().get_cost(params))
+    }
+
+    /// Submits a new batch job and returns a description and identifiers for the job.
+    ///
+    /// <div class="warning">
+    /// Calling this method will incur a cost.
+    /// </div>
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn submit_job(&mut self, params: &SubmitJobParams) -> crate::Result<BatchJob> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.batch().submit_job(params))
+    }
+
+    /// Lists previous batch jobs with filtering by `params`.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub fn list_jobs(&mut self, params: &ListJobsParams) -> crate::Result<Vec<BatchJob>> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.batch().list_jobs(params))
+    }
+
+    /// Downloads the file specified in `params` or all files associated with the job ID.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request. It will also return an
+    /// error if it encounters an issue downloading a file.
+    pub fn download(&mut self, params: &DownloadParams) -> crate::Result<Vec<PathBuf>> {
+        let Self { inner, runtime } = self;
+        runtime.block_on(inner.batch().download(params))
+    }
+}
+
+impl fmt::Debug for HistoricalClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistoricalClient")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{
+        encode::{DbnEncoder, EncodeRecord},
+        record::{MboMsg, TradeMsg},
+        MetadataBuilder, SType, Schema,
+    };
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use time::macros::{date, datetime};
+    use wiremock::{
+        matchers::{basic_auth, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{body_contains, historical::API_VERSION, zst_test_data_path};
+
+    const API_KEY: &str = "test-API";
+
+    fn mock_server() -> (Runtime, MockServer) {
+        let rt = Runtime::new().unwrap();
+        let server = rt.block_on(MockServer::start());
+        (rt, server)
+    }
+
+    #[test]
+    fn test_get_range() {
+        let (rt, mock_server) = mock_server();
+        let bytes = std::fs::read(zst_test_data_path(Schema::Trades)).unwrap();
+        rt.block_on(
+            Mock::given(method("POST"))
+                .and(basic_auth(API_KEY, ""))
+                .and(path(format!("/v{API_VERSION}/timeseries.get_range")))
+                .and(body_contains("schema", "trades"))
+                .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes))
+                .mount(&mock_server),
+        );
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let (metadata, trades) = target
+            .get_range::<TradeMsg>(
+                &GetRangeParams::builder()
+                    .dataset(dbn::Dataset::XnasItch)
+                    .schema(Schema::Trades)
+                    .symbols(vec!["SPOT", "AAPL"])
+                    .date_time_range((
+                        datetime!(2023 - 06 - 14 00:00 UTC),
+                        datetime!(2023 - 06 - 17 00:00 UTC),
+                    ))
+                    .build(),
+            )
+            .unwrap();
+        assert_eq!(metadata.schema, Some(Schema::Trades));
+        assert_eq!(trades.len(), 2);
+    }
+
+    #[test]
+    fn test_get_range_skips_other_rtypes() {
+        let (rt, mock_server) = mock_server();
+        let metadata = MetadataBuilder::new()
+            .dataset(dbn::Dataset::XnasItch.to_string())
+            .schema(None)
+            .start(0)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .build();
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = DbnEncoder::with_zstd(&mut bytes, &metadata).unwrap();
+            encoder.encode_record(&TradeMsg::default()).unwrap();
+            encoder.encode_record(&MboMsg::default()).unwrap();
+            encoder.encode_record(&TradeMsg::default()).unwrap();
+        }
+        rt.block_on(
+            Mock::given(method("POST"))
+                .and(basic_auth(API_KEY, ""))
+                .and(path(format!("/v{API_VERSION}/timeseries.get_range")))
+                .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes))
+                .mount(&mock_server),
+        );
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let (_, trades) = target
+            .get_range::<TradeMsg>(
+                &GetRangeParams::builder()
+                    .dataset(dbn::Dataset::XnasItch)
+                    .schema(Schema::Trades)
+                    .symbols(vec!["SPOT"])
+                    .date_time_range((
+                        datetime!(2023 - 06 - 14 00:00 UTC),
+                        datetime!(2023 - 06 - 17 00:00 UTC),
+                    ))
+                    .build(),
+            )
+            .unwrap();
+        assert_eq!(trades.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let (rt, mock_server) = mock_server();
+        rt.block_on(
+            Mock::given(method("POST"))
+                .and(basic_auth(API_KEY, ""))
+                .and(path(format!("/v{API_VERSION}/symbology.resolve")))
+                .and(body_contains("stype_in", "continuous"))
+                .respond_with(
+                    ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!({
+                        "result": {
+                            "ES.c.0": [{ "d0": "2023-06-14", "d1": "2023-06-15", "s": "10245" }]
+                        },
+                        "partial": [],
+                        "not_found": []
+                    })),
+                )
+                .mount(&mock_server),
+        );
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let res = target
+            .resolve(
+                &ResolveParams::builder()
+                    .dataset(dbn::Dataset::GlbxMdp3)
+                    .symbols("ES.c.0")
+                    .stype_in(SType::Continuous)
+                    .date_range((date!(2023 - 06 - 14), date!(2023 - 06 - 15)))
+                    .build(),
+            )
+            .unwrap();
+        assert_eq!(res.mappings["ES.c.0"][0].symbol, "10245");
+    }
+}
@@ -0,0 +1,449 @@
+//! Trading calendars for determining trading days and session times.
+//!
+//! [`UsEquityCalendar`] follows the NYSE holiday and early close schedule, which CME
+//! equity index futures such as ES follow for the regular trading hours (RTH) session.
+//! [`Session`] describes the hours of overnight futures sessions such as
+//! [CME Globex](Session::CME_GLOBEX)'s for finding the session containing a timestamp.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Weekday};
+use chrono_tz::Tz;
+
+use crate::{
+    timeutil::{resolve_local, LocalTimePolicy},
+    Error,
+};
+
+/// The hours of a single trading session in the calendar's local timezone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TradingSession {
+    /// The trading date.
+    pub date: NaiveDate,
+    /// The local time the session opens.
+    pub open: NaiveTime,
+    /// The local time the session closes. This is earlier than usual on half days.
+    pub close: NaiveTime,
+    /// Whether the session closes earlier than usual.
+    pub early_close: bool,
+}
+
+impl TradingSession {
+    /// Returns `true` if the session closes at or before the time it opens, i.e. it
+    /// opens on the evening before its trading date.
+    pub fn is_overnight(&self) -> bool {
+        self.close <= self.open
+    }
+
+    /// Returns the instants the session opens and closes with the session times local
+    /// to `tz`. Times falling in a daylight saving time transition are resolved to the
+    /// earliest instant.
+    ///
+    /// # Errors
+    /// This function returns an error when the session times are out of range.
+    pub fn open_close(&self, tz: Tz) -> crate::Result<(DateTime<Tz>, DateTime<Tz>)> {
+        let open_date = if self.is_overnight() {
+            self.date
+                .pred_opt()
+                .ok_or_else(|| Error::bad_arg("session", "date out of range"))?
+        } else {
+            self.date
+        };
+        let open = resolve_local(
+            open_date.and_time(self.open),
+            &tz,
+            LocalTimePolicy::Earliest,
+        )?;
+        let close = resolve_local(
+            self.date.and_time(self.close),
+            &tz,
+            LocalTimePolicy::Earliest,
+        )?;
+        Ok((open, close))
+    }
+}
+
+/// A calendar of trading days and session times for a market.
+pub trait TradingCalendar {
+    /// Returns the timezone the session times are expressed in.
+    fn timezone(&self) -> Tz;
+
+    /// Returns the trading session for `date`, or `None` if the market is closed.
+    fn session(&self, date: NaiveDate) -> Option<TradingSession>;
+
+    /// Returns `true` if the market is open on `date`.
+    fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.session(date).is_some()
+    }
+
+    /// Returns the last trading day strictly before `date`.
+    fn previous_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut prev_day = date - Duration::days(1);
+        while !self.is_trading_day(prev_day) {
+            prev_day -= Duration::days(1);
+        }
+        prev_day
+    }
+
+    /// Returns the first trading day strictly after `date`.
+    fn next_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut next_day = date + Duration::days(1);
+        while !self.is_trading_day(next_day) {
+            next_day += Duration::days(1);
+        }
+        next_day
+    }
+
+    /// Returns `date` if it's a trading day, otherwise the last trading day before it.
+    fn trading_day_on_or_before(&self, date: NaiveDate) -> NaiveDate {
+        if self.is_trading_day(date) {
+            date
+        } else {
+            self.previous_trading_day(date)
+        }
+    }
+}
+
+/// The US equity market calendar: 9:30–16:00 ET with NYSE holidays and 13:00 ET
+/// early closes on the day before Independence Day, the day after Thanksgiving, and
+/// Christmas Eve.
+///
+/// Only rule-based holidays are included; unscheduled closures such as national days
+/// of mourning are not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsEquityCalendar;
+
+impl UsEquityCalendar {
+    /// The regular session open time.
+    pub const OPEN: NaiveTime = match NaiveTime::from_hms_opt(9, 30, 0) {
+        Some(t) => t,
+        None => unreachable!(),
+    };
+    /// The regular session close time.
+    pub const CLOSE: NaiveTime = match NaiveTime::from_hms_opt(16, 0, 0) {
+        Some(t) => t,
+        None => unreachable!(),
+    };
+    /// The close time on early close days.
+    pub const EARLY_CLOSE: NaiveTime = match NaiveTime::from_hms_opt(13, 0, 0) {
+        Some(t) => t,
+        None => unreachable!(),
+    };
+
+    /// Returns `true` if `date` is a weekday market holiday.
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        let year = date.year();
+        let nth = |month, weekday, n| NaiveDate::from_weekday_of_month_opt(year, month, weekday, n);
+        let holidays = [
+            // New Year's Day isn't observed on the preceding Friday when it falls on a
+            // Saturday
+            observed(ymd(year, 1, 1)).filter(|d| d.year() == year),
+            nth(1, Weekday::Mon, 3),
+            nth(2, Weekday::Mon, 3),
+            easter(year).map(|d| d - Duration::days(2)),
+            nth(5, Weekday::Mon, 5).or_else(|| nth(5, Weekday::Mon, 4)),
+            if year >= 2022 {
+                observed(ymd(year, 6, 19))
+            } else {
+                None
+            },
+            observed(ymd(year, 7, 4)),
+            nth(9, Weekday::Mon, 1),
+            nth(11, Weekday::Thu, 4),
+            observed(ymd(year, 12, 25)),
+        ];
+        holidays.contains(&Some(date))
+    }
+
+    /// Returns `true` if the market closes early on `date`. Doesn't check whether
+    /// `date` is a trading day.
+    pub fn is_early_close(&self, date: NaiveDate) -> bool {
+        let year = date.year();
+        let is_mon_to_thu = !matches!(date.weekday(), Weekday::Fri | Weekday::Sat | Weekday::Sun);
+        let day_after_thanksgiving =
+            NaiveDate::from_weekday_of_month_opt(year, 11, Weekday::Thu, 4)
+                .map(|d| d + Duration::days(1));
+        (is_mon_to_thu && [ymd(year, 7, 3), ymd(year, 12, 24)].contains(&Some(date)))
+            || Some(date) == day_after_thanksgiving
+    }
+}
+
+impl TradingCalendar for UsEquityCalendar {
+    fn timezone(&self) -> Tz {
+        chrono_tz::America::New_York
+    }
+
+    fn session(&self, date: NaiveDate) -> Option<TradingSession> {
+        if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) || self.is_holiday(date) {
+            return None;
+        }
+        let early_close = self.is_early_close(date);
+        Some(TradingSession {
+            date,
+            open: Self::OPEN,
+            close: if early_close {
+                Self::EARLY_CLOSE
+            } else {
+                Self::CLOSE
+            },
+            early_close,
+        })
+    }
+}
+
+/// A calendar with the same session hours every weekday and no holidays, for custom
+/// session definitions such as 8:30–15:15 CT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedHoursCalendar {
+    /// The local time the session opens.
+    pub open: NaiveTime,
+    /// The local time the session closes.
+    pub close: NaiveTime,
+    /// The timezone of `open` and `close`.
+    pub tz: Tz,
+}
+
+impl FixedHoursCalendar {
+    /// Creates a calendar with sessions from `open` to `close` in `tz` every weekday.
+    pub fn new(open: NaiveTime, close: NaiveTime, tz: Tz) -> Self {
+        Self { open, close, tz }
+    }
+}
+
+impl TradingCalendar for FixedHoursCalendar {
+    fn timezone(&self) -> Tz {
+        self.tz
+    }
+
+    fn session(&self, date: NaiveDate) -> Option<TradingSession> {
+        if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            return None;
+        }
+        Some(TradingSession {
+            date,
+            open: self.open,
+            close: self.close,
+            early_close: false,
+        })
+    }
+}
+
+/// The hours of a futures market with a session every weekday, where sessions that
+/// close at or before the time they open start on the evening before their trading
+/// date.
+///
+/// For example, [`CME_GLOBEX`](Self::CME_GLOBEX) trades from 18:00 ET Sunday to 17:00
+/// ET Friday with a daily maintenance break from 17:00 to 18:00 ET. Holidays aren't
+/// taken into account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    /// The local time the session opens.
+    pub open: NaiveTime,
+    /// The local time the session closes.
+    pub close: NaiveTime,
+    /// The timezone of `open` and `close`.
+    pub tz: Tz,
+}
+
+impl Session {
+    /// The CME Globex session from 18:00 ET the previous day to 17:00 ET.
+    pub const CME_GLOBEX: Self = Self {
+        open: match NaiveTime::from_hms_opt(18, 0, 0) {
+            Some(t) => t,
+            None => unreachable!(),
+        },
+        close: match NaiveTime::from_hms_opt(17, 0, 0) {
+            Some(t) => t,
+            None => unreachable!(),
+        },
+        tz: chrono_tz::America::New_York,
+    };
+
+    /// Creates a session from `open` to `close` in `tz` for every weekday.
+    pub const fn new(open: NaiveTime, close: NaiveTime, tz: Tz) -> Self {
+        Self { open, close, tz }
+    }
+
+    /// Returns the instants the session for trading date `date` opens and closes, or
+    /// `None` if there's no session on `date`.
+    pub fn open_close(&self, date: NaiveDate) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+        self.session(date)?.open_close(self.tz).ok()
+    }
+
+    /// Returns the trading date of the session containing `ts`, or `None` if the
+    /// market is closed at `ts`, e.g. during the maintenance break or on weekends.
+    pub fn trading_date<T: TimeZone>(&self, ts: &DateTime<T>) -> Option<NaiveDate> {
+        let ts = ts.with_timezone(&self.tz);
+        let date = ts.date_naive();
+        [Some(date), date.succ_opt()]
+            .into_iter()
+            .flatten()
+            .find(|&date| {
+                self.open_close(date)
+                    .is_some_and(|(open, close)| open <= ts && ts < close)
+            })
+    }
+
+    /// Returns `true` if the market is open at `ts`.
+    pub fn contains<T: TimeZone>(&self, ts: &DateTime<T>) -> bool {
+        self.trading_date(ts).is_some()
+    }
+
+    /// Returns the first session open at or after `ts`.
+    pub fn next_open<T: TimeZone>(&self, ts: &DateTime<T>) -> Option<DateTime<Tz>> {
+        let ts = ts.with_timezone(&self.tz);
+        let date = ts.date_naive();
+        (0..=7)
+            .filter_map(|days| self.open_close(date + Duration::days(days)))
+            .map(|(open, _)| open)
+            .find(|&open| open >= ts)
+    }
+
+    /// Returns the last session close at or before `ts`.
+    pub fn previous_close<T: TimeZone>(&self, ts: &DateTime<T>) -> Option<DateTime<Tz>> {
+        let ts = ts.with_timezone(&self.tz);
+        let date = ts.date_naive();
+        (0..=7)
+            .filter_map(|days| self.open_close(date - Duration::days(days)))
+            .map(|(_, close)| close)
+            .find(|&close| close <= ts)
+    }
+}
+
+impl TradingCalendar for Session {
+    fn timezone(&self) -> Tz {
+        self.tz
+    }
+
+    fn session(&self, date: NaiveDate) -> Option<TradingSession> {
+        if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            return None;
+        }
+        Some(TradingSession {
+            date,
+            open: self.open,
+            close: self.close,
+            early_close: false,
+        })
+    }
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Shifts a holiday falling on a Saturday to Friday and on a Sunday to Monday.
+fn observed(date: Option<NaiveDate>) -> Option<NaiveDate> {
+    date.map(|d| match d.weekday() {
+        Weekday::Sat => d - Duration::days(1),
+        Weekday::Sun => d + Duration::days(1),
+        _ => d,
+    })
+}
+
+/// Computes the date of Easter Sunday in the Gregorian calendar.
+fn easter(year: i32) -> Option<NaiveDate> {
+    // Anonymous Gregorian algorithm
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    ymd(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd_unchecked(year: i32, month: u32, day: u32) -> NaiveDate {
+        ymd(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_holidays() {
+        let cal = UsEquityCalendar;
+        // Good Friday
+        assert!(!cal.is_trading_day(ymd_unchecked(2025, 4, 18)));
+        // Thanksgiving
+        assert!(!cal.is_trading_day(ymd_unchecked(2024, 11, 28)));
+        // Independence Day on a Saturday is observed on Friday
+        assert!(!cal.is_trading_day(ymd_unchecked(2026, 7, 3)));
+        // New Year's Day on a Saturday isn't observed
+        assert!(cal.is_trading_day(ymd_unchecked(2021, 12, 31)));
+        // Juneteenth wasn't a holiday before 2022
+        assert!(cal.is_trading_day(ymd_unchecked(2021, 6, 18)));
+        assert!(!cal.is_trading_day(ymd_unchecked(2024, 6, 19)));
+    }
+
+    #[test]
+    fn test_early_close() {
+        let cal = UsEquityCalendar;
+        let session = cal.session(ymd_unchecked(2024, 11, 29)).unwrap();
+        assert!(session.early_close);
+        assert_eq!(session.close, UsEquityCalendar::EARLY_CLOSE);
+        assert!(
+            cal.session(ymd_unchecked(2024, 12, 24))
+                .unwrap()
+                .early_close
+        );
+        assert!(cal.session(ymd_unchecked(2025, 7, 3)).unwrap().early_close);
+        let session = cal.session(ymd_unchecked(2024, 12, 23)).unwrap();
+        assert!(!session.early_close);
+        assert_eq!(session.close, UsEquityCalendar::CLOSE);
+    }
+
+    #[test]
+    fn test_globex_session() {
+        use chrono_tz::America::New_York;
+
+        let session = Session::CME_GLOBEX;
+        let at = |day, hour, minute| {
+            New_York
+                .with_ymd_and_hms(2025, 4, day, hour, minute, 0)
+                .unwrap()
+        };
+        // Sunday evening belongs to Monday's session
+        assert_eq!(
+            session.trading_date(&at(20, 18, 0)),
+            Some(ymd_unchecked(2025, 4, 21))
+        );
+        assert!(!session.contains(&at(20, 17, 59)));
+        assert!(session.contains(&at(21, 16, 59)));
+        // Maintenance break
+        assert!(!session.contains(&at(21, 17, 0)));
+        assert!(!session.contains(&at(21, 17, 30).with_timezone(&chrono::Utc)));
+        assert_eq!(session.next_open(&at(21, 17, 30)), Some(at(21, 18, 0)));
+        assert_eq!(session.previous_close(&at(21, 17, 30)), Some(at(21, 17, 0)));
+        // Weekend
+        assert!(!session.contains(&at(26, 12, 0)));
+        assert_eq!(session.next_open(&at(25, 17, 0)), Some(at(27, 18, 0)));
+        assert_eq!(session.previous_close(&at(27, 12, 0)), Some(at(25, 17, 0)));
+        assert_eq!(session.next_open(&at(21, 18, 0)), Some(at(21, 18, 0)));
+    }
+
+    #[test]
+    fn test_previous_trading_day() {
+        let cal = UsEquityCalendar;
+        // Tuesday after Martin Luther King Jr. Day
+        assert_eq!(
+            cal.previous_trading_day(ymd_unchecked(2025, 1, 21)),
+            ymd_unchecked(2025, 1, 17)
+        );
+        assert_eq!(
+            cal.next_trading_day(ymd_unchecked(2024, 12, 24)),
+            ymd_unchecked(2024, 12, 26)
+        );
+        assert_eq!(
+            cal.trading_day_on_or_before(ymd_unchecked(2025, 4, 20)),
+            ymd_unchecked(2025, 4, 17)
+        );
+    }
+}
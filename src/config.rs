@@ -0,0 +1,210 @@
+//! Loading client and PMZ settings from a TOML config file.
+//!
+//! ```toml
+//! key = "db-..."
+//! dataset = "GLBX.MDP3"
+//! cache_dir = "/var/cache/databento"
+//!
+//! [retry]
+//! max_retries = 3
+//! initial_backoff_ms = 500
+//! max_backoff_ms = 30000
+//!
+//! [pmz]
+//! symbol = "ES.c.0"
+//! start = "07:25:00"
+//! end = "09:25:00"
+//! include_candles = false
+//! dataset_info = false
+//! zone_width_factor = 0.2
+//! zone_offset_factor = 0.4
+//! gap_reference = "lis_close"
+//! ```
+//!
+//! Every setting is optional. Settings can be overridden with the environment
+//! variables `DATABENTO_API_KEY`, `DATABENTO_DATASET`, `DATABENTO_CACHE_DIR`, and
+//! `DATABENTO_MAX_RETRIES`.
+
+use std::{path::PathBuf, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{examples::es_futures_pmz::PmzConfig, historical::RetryPolicy, ApiKey, Error, Result};
+
+/// Client and PMZ settings. Consumed by
+/// [`historical::ClientBuilder::config()`](crate::historical::ClientBuilder::config)
+/// and [`calculate_pmz_from_config()`](crate::examples::es_futures_pmz::calculate_pmz_from_config).
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// The API key.
+    pub key: Option<ApiKey>,
+    /// The default dataset for requests.
+    pub dataset: Option<String>,
+    /// The directory for caching downloaded data.
+    pub cache_dir: Option<PathBuf>,
+    /// The policy for retrying failed historical requests.
+    pub retry: RetryPolicy,
+    /// The PMZ calculation settings.
+    pub pmz: PmzConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    key: Option<String>,
+    dataset: Option<String>,
+    cache_dir: Option<PathBuf>,
+    retry: Option<RetryConfig>,
+    pmz: PmzConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RetryConfig {
+    max_retries: u32,
+    initial_backoff_ms: Option<u64>,
+    max_backoff_ms: Option<u64>,
+}
+
+impl Config {
+    /// Loads the config from the TOML file at `path` and applies any overrides from
+    /// environment variables.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to read or parse the file, or the
+    /// API key or an environment variable is invalid.
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let contents = std::fs::read_to_string(&path)?;
+        let mut config = Self::from_toml(&contents)
+            .map_err(|e| Error::bad_arg("path", format!("{}: {e}", path.display())))?;
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Creates a config from the defaults and any overrides from environment variables.
+    ///
+    /// # Errors
+    /// This function returns an error when an environment variable is invalid.
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Parses a config from a TOML string without applying environment variable
+    /// overrides.
+    ///
+    /// # Errors
+    /// This function returns an error when `toml` isn't a valid config or the API key
+    /// is invalid.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(toml).map_err(|e| Error::bad_arg("toml", e))?;
+        let retry = file.retry.map_or(RetryPolicy::default(), |retry| {
+            let default = RetryPolicy::new(retry.max_retries);
+            RetryPolicy {
+                max_retries: retry.max_retries,
+                initial_backoff: retry
+                    .initial_backoff_ms
+                    .map_or(default.initial_backoff, Duration::from_millis),
+                max_backoff: retry
+                    .max_backoff_ms
+                    .map_or(default.max_backoff, Duration::from_millis),
+            }
+        });
+        Ok(Self {
+            key: file.key.map(ApiKey::new).transpose()?,
+            dataset: file.dataset,
+            cache_dir: file.cache_dir,
+            retry,
+            pmz: file.pmz,
+        })
+    }
+
+    /// Overrides settings with those set in environment variables.
+    ///
+    /// # Errors
+    /// This function returns an error when an environment variable is invalid.
+    pub fn apply_env(&mut self) -> Result<()> {
+        if let Some(key) = env_var("DATABENTO_API_KEY")? {
+            self.key = Some(ApiKey::new(key)?);
+        }
+        if let Some(dataset) = env_var("DATABENTO_DATASET")? {
+            self.dataset = Some(dataset);
+        }
+        if let Some(cache_dir) = env_var("DATABENTO_CACHE_DIR")? {
+            self.cache_dir = Some(PathBuf::from(cache_dir));
+        }
+        if let Some(max_retries) = env_var("DATABENTO_MAX_RETRIES")? {
+            let max_retries = max_retries.parse().map_err(|e| {
+                Error::bad_arg("DATABENTO_MAX_RETRIES", format!("{e}: {max_retries}"))
+            })?;
+            self.retry = RetryPolicy {
+                max_retries,
+                ..if self.retry == RetryPolicy::NONE {
+                    RetryPolicy::new(max_retries)
+                } else {
+                    self.retry
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+fn env_var(name: &str) -> Result<Option<String>> {
+    match std::env::var(name) {
+        Ok(val) => Ok(Some(val)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(std::env::VarError::NotUnicode(_)) => Err(Error::bad_arg(
+            name,
+            format!("environment variable {name} contains invalid unicode"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+
+    use super::*;
+    use crate::Symbols;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(
+            r#"
+key = "32-character-with-lots-of-filler"
+dataset = "XNAS.ITCH"
+
+[retry]
+max_retries = 3
+initial_backoff_ms = 100
+
+[pmz]
+symbol = "NQ.c.0"
+start = "07:30:00"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.key.unwrap().as_str(),
+            "32-character-with-lots-of-filler"
+        );
+        assert_eq!(config.dataset.as_deref(), Some("XNAS.ITCH"));
+        assert_eq!(config.retry.max_retries, 3);
+        assert_eq!(config.retry.initial_backoff, Duration::from_millis(100));
+        assert_eq!(config.retry.max_backoff, RetryPolicy::new(3).max_backoff);
+        assert_eq!(config.pmz.symbol, Symbols::from("NQ.c.0"));
+        assert_eq!(config.pmz.start, NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+        assert_eq!(config.pmz.end, PmzConfig::default().end);
+        assert_eq!(config.pmz.dataset, PmzConfig::default().dataset);
+    }
+
+    #[test]
+    fn test_from_toml_rejects_unknown_fields() {
+        assert!(Config::from_toml("max_retries = 3").is_err());
+        assert!(Config::from_toml("key = \"too-short\"").is_err());
+        assert!(Config::from_toml("[pmz]\ndataset = \"GLBX.MPD3\"").is_err());
+    }
+}
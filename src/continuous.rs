@@ -0,0 +1,208 @@
+//! Translating continuous contracts like `ES.c.0` back to tradeable contracts.
+//!
+//! [`front_month()`] finds the contract a continuous symbol refers to on a date under
+//! a [`RollRule`], and [`roll_calendar()`] lists the quarterly contracts of a year
+//! with their expirations and the dates positions are customarily rolled out of them.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+// March, June, September, and December
+const QUARTERLY_MONTHS: [(u32, char); 4] = [(3, 'H'), (6, 'M'), (9, 'U'), (12, 'Z')];
+
+/// How a continuous contract chooses the contract it refers to, corresponding to the
+/// rules of Databento's continuous symbology.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RollRule {
+    /// By expiration, e.g. `ES.c.0`.
+    #[default]
+    Calendar,
+    /// By the previous day's volume, e.g. `ES.v.0`.
+    Volume,
+    /// By the previous day's open interest, e.g. `ES.n.0`.
+    OpenInterest,
+}
+
+impl RollRule {
+    /// Returns the character identifying the rule in continuous symbols.
+    pub const fn as_char(self) -> char {
+        match self {
+            RollRule::Calendar => 'c',
+            RollRule::Volume => 'v',
+            RollRule::OpenInterest => 'n',
+        }
+    }
+
+    /// Returns the continuous symbol for the contract of `root` ranked `rank` under
+    /// this rule, where 0 is the front month, e.g. `ES.c.0`.
+    pub fn continuous_symbol(self, root: &str, rank: u32) -> String {
+        format!("{root}.{}.{rank}", self.as_char())
+    }
+}
+
+/// A contract a continuous symbol referred to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrontMonth {
+    /// The instrument ID of the contract.
+    pub instrument_id: u32,
+    /// The raw symbol of the contract, e.g. `ESM5`.
+    pub raw_symbol: String,
+    /// The date the contract expires in UTC, if known.
+    pub expiration: Option<NaiveDate>,
+}
+
+/// A quarterly contract and its customary roll date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractRoll {
+    /// The raw symbol of the contract, e.g. `ESM5`.
+    pub raw_symbol: String,
+    /// The last trading day of the contract, the third Friday of its month.
+    pub expiration: NaiveDate,
+    /// The day volume customarily moves to the next contract, the Thursday eight days
+    /// before expiration.
+    pub roll_date: NaiveDate,
+}
+
+/// Returns the quarterly contracts of `root` expiring in `year` in order, following
+/// the CME equity index futures conventions, where contracts expire on the third
+/// Friday of March, June, September, and December and are rolled eight days earlier.
+/// Holidays aren't taken into account.
+pub fn roll_calendar(root: &str, year: i32) -> Vec<ContractRoll> {
+    QUARTERLY_MONTHS
+        .iter()
+        .filter_map(|&(month, code)| {
+            let expiration = NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Fri, 3)?;
+            Some(ContractRoll {
+                raw_symbol: format!("{root}{code}{}", year.rem_euclid(10)),
+                expiration,
+                roll_date: expiration - Duration::days(8),
+            })
+        })
+        .collect()
+}
+
+/// Returns the quarterly contract of `root` that's front month on `date` by the
+/// [`roll_calendar()`], i.e. the first contract whose roll date is after `date`.
+/// Returns `None` if `date` is at the end of the range of dates.
+pub fn calendar_front_month(root: &str, date: NaiveDate) -> Option<ContractRoll> {
+    roll_calendar(root, date.year())
+        .into_iter()
+        .chain(roll_calendar(root, date.year() + 1))
+        .find(|contract| contract.roll_date > date)
+}
+
+/// Fetches the contract of `root` that's front month on `date` under `rule`, as
+/// determined by Databento's continuous symbology from expirations, volume, or open
+/// interest, from the instrument definition of the continuous symbol that day.
+///
+/// # Errors
+/// This function returns an error when `date` is out of range, the request fails, the
+/// response can't be decoded, or there's no definition for the continuous symbol on
+/// `date`.
+#[cfg(feature = "historical")]
+pub async fn front_month(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    root: &str,
+    date: NaiveDate,
+    rule: RollRule,
+) -> crate::Result<FrontMonth> {
+    use dbn::{InstrumentDefMsg, UNDEF_TIMESTAMP};
+
+    use crate::timeconv::ToTimeDate;
+
+    let start = date.to_time_date()?;
+    let symbol = rule.continuous_symbol(root, 0);
+    let params = crate::historical::timeseries::GetRangeParams::builder()
+        .dataset(dataset)
+        .symbols(symbol.as_str())
+        .stype_in(dbn::SType::Continuous)
+        .schema(dbn::Schema::Definition)
+        .date_time_range(start)
+        .build();
+    let mut decoder = client.get_range(&params).await?;
+    let mut front_month = None;
+    while let Some(definition) = decoder.decode_record::<InstrumentDefMsg>().await? {
+        front_month = Some(FrontMonth {
+            instrument_id: definition.hd.instrument_id,
+            raw_symbol: definition.raw_symbol()?.to_owned(),
+            expiration: (definition.expiration != UNDEF_TIMESTAMP).then(|| {
+                chrono::DateTime::from_timestamp_nanos(definition.expiration as i64).date_naive()
+            }),
+        });
+    }
+    front_month.ok_or_else(|| {
+        crate::Error::bad_arg("date", format!("no definition for {symbol} on {date}"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_roll_calendar() {
+        let calendar = roll_calendar("ES", 2025);
+        let symbols: Vec<_> = calendar.iter().map(|c| c.raw_symbol.as_str()).collect();
+        assert_eq!(symbols, ["ESH5", "ESM5", "ESU5", "ESZ5"]);
+        assert_eq!(calendar[1].expiration, date(2025, 6, 20));
+        assert_eq!(calendar[1].roll_date, date(2025, 6, 12));
+        assert_eq!(calendar[1].roll_date.weekday(), Weekday::Thu);
+        assert_eq!(calendar[3].expiration, date(2025, 12, 19));
+        assert_eq!(RollRule::Volume.continuous_symbol("ES", 1), "ES.v.1");
+    }
+
+    #[test]
+    fn test_calendar_front_month() {
+        for (day, expected) in [
+            (date(2025, 6, 11), "ESM5"),
+            (date(2025, 6, 12), "ESU5"),
+            (date(2025, 12, 31), "ESH6"),
+            (date(2025, 1, 1), "ESH5"),
+        ] {
+            assert_eq!(
+                calendar_front_month("ES", day).unwrap().raw_symbol,
+                expected,
+                "{day}"
+            );
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_front_month() {
+        use dbn::{rtype, InstrumentDefMsg, RecordHeader, SType, Schema};
+
+        use crate::testing::MockHistoricalClient;
+
+        let mut definition = InstrumentDefMsg {
+            hd: RecordHeader::new::<InstrumentDefMsg>(rtype::INSTRUMENT_DEF, 1, 42, 0),
+            expiration: 1_750_426_200_000_000_000,
+            ..Default::default()
+        };
+        for (dst, src) in definition.raw_symbol.iter_mut().zip(b"ESM5") {
+            *dst = *src as std::ffi::c_char;
+        }
+        let mut client = MockHistoricalClient::new()
+            .with_records("GLBX.MDP3", Schema::Definition, &[definition])
+            .unwrap();
+        let front = front_month(
+            &mut client,
+            "GLBX.MDP3",
+            "ES",
+            date(2025, 6, 2),
+            RollRule::Volume,
+        )
+        .await
+        .unwrap();
+        assert_eq!(front.instrument_id, 42);
+        assert_eq!(front.raw_symbol, "ESM5");
+        assert_eq!(front.expiration, Some(date(2025, 6, 20)));
+        let request = &client.requests()[0];
+        assert_eq!(request.stype_in, SType::Continuous);
+        assert_eq!(request.symbols.to_api_string(), "ES.v.0");
+    }
+}
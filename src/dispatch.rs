@@ -0,0 +1,122 @@
+//! Routing records to typed handlers.
+//!
+//! Register a handler per record type with [`RecordDispatcher::on()`], then pass each
+//! record to [`dispatch()`](RecordDispatcher::dispatch), or let
+//! [`LiveClient::dispatch()`](crate::LiveClient::dispatch) route every record of a
+//! live session, instead of chaining `rec.get::<T>()` calls by hand.
+
+use std::fmt;
+
+use dbn::{HasRType, RecordRef};
+
+type Handler<'a> = Box<dyn FnMut(&RecordRef) -> bool + Send + 'a>;
+type Fallback<'a> = Box<dyn FnMut(&RecordRef) + Send + 'a>;
+
+/// Routes records to the handlers registered for their record type.
+///
+/// A record is passed to every handler whose type it can be read as, in the order
+/// they were registered, so a record type can have several handlers. Records without
+/// a handler are passed to the [`on_other()`](Self::on_other) handler, if any.
+#[derive(Default)]
+pub struct RecordDispatcher<'a> {
+    handlers: Vec<Handler<'a>>,
+    fallback: Option<Fallback<'a>>,
+}
+
+impl<'a> RecordDispatcher<'a> {
+    /// Creates a new dispatcher without any handlers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for records of type `T`, e.g. `on::<TradeMsg>(|trade| ...)`.
+    pub fn on<T: HasRType>(mut self, mut handler: impl FnMut(&T) + Send + 'a) -> Self {
+        self.handlers
+            .push(Box::new(move |rec| match rec.get::<T>() {
+                Some(rec) => {
+                    handler(rec);
+                    true
+                }
+                None => false,
+            }));
+        self
+    }
+
+    /// Sets `handler` as the handler for records without a handler registered for
+    /// their type, replacing any previous one.
+    pub fn on_other(mut self, handler: impl FnMut(&RecordRef) + Send + 'a) -> Self {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Passes `rec` to the handlers for its type. Returns `true` if at least one
+    /// handler registered with [`on()`](Self::on) matched.
+    pub fn dispatch(&mut self, rec: &RecordRef) -> bool {
+        let mut handled = false;
+        for handler in self.handlers.iter_mut() {
+            handled |= handler(rec);
+        }
+        if !handled {
+            if let Some(fallback) = self.fallback.as_mut() {
+                fallback(rec);
+            }
+        }
+        handled
+    }
+}
+
+impl fmt::Debug for RecordDispatcher<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordDispatcher")
+            .field("handlers", &self.handlers.len())
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{rtype, Mbp1Msg, OhlcvMsg, Record, RecordHeader, TradeMsg};
+
+    use super::*;
+
+    #[test]
+    fn test_dispatch() {
+        let trade = TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 1, 0),
+            price: 5_300_000_000_000,
+            ..Default::default()
+        };
+        let quote = Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, 2, 0),
+            ..Default::default()
+        };
+        let bar = OhlcvMsg {
+            hd: RecordHeader::new::<OhlcvMsg>(rtype::OHLCV_1M, 1, 3, 0),
+            open: 1,
+            high: 2,
+            low: 3,
+            close: 4,
+            volume: 5,
+        };
+
+        let mut prices = Vec::new();
+        let mut trade_count = 0;
+        let mut quote_ids = Vec::new();
+        let mut other = Vec::new();
+        let mut dispatcher = RecordDispatcher::new()
+            .on::<TradeMsg>(|trade| prices.push(trade.price))
+            .on::<TradeMsg>(|_| trade_count += 1)
+            .on::<Mbp1Msg>(|quote| quote_ids.push(quote.hd.instrument_id))
+            .on_other(|rec| other.push(rec.header().rtype));
+        assert!(dispatcher.dispatch(&RecordRef::from(&trade)));
+        assert!(dispatcher.dispatch(&RecordRef::from(&quote)));
+        assert!(!dispatcher.dispatch(&RecordRef::from(&bar)));
+        drop(dispatcher);
+
+        assert_eq!(prices, [5_300_000_000_000]);
+        assert_eq!(trade_count, 1);
+        assert_eq!(quote_ids, [2]);
+        assert_eq!(other, [rtype::OHLCV_1M]);
+    }
+}
@@ -33,6 +33,29 @@ pub enum Error {
     /// An when authentication failed.
     #[error("authentication failed: {0}")]
     Auth(String),
+    /// A downloaded or cached file doesn't match its checksum, e.g. because it was
+    /// corrupted on disk.
+    #[error("corrupt file {}: expected SHA256 {expected} but got {actual}", path.display())]
+    CorruptCache {
+        /// The path of the corrupt file.
+        path: std::path::PathBuf,
+        /// The expected SHA256 hash in hex.
+        expected: String,
+        /// The SHA256 hash of the file in hex.
+        actual: String,
+    },
+    /// An error from converting or writing Arrow data.
+    #[cfg(feature = "arrow")]
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+    /// An error from a SQLite database.
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    /// An error from a DuckDB database.
+    #[cfg(feature = "duckdb")]
+    #[error("DuckDB error: {0}")]
+    DuckDb(#[from] duckdb::Error),
 }
 /// An alias for a `Result` with [`databento::Error`](crate::Error) as the error type.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,232 +1,1925 @@
 //! Examples moved here from the examples directory
 //! This module contains the PMZ calculation logic
 
-use anyhow::Result;
 use crate::{
-    dbn::{Encoding, OhlcvMsg, Schema, SType},
+    calendar::{Session, TradingCalendar, TradingSession, UsEquityCalendar},
+    dbn::{decode::AsyncDbnDecoder, Dataset, Metadata, OhlcvMsg, SType, Schema},
     historical::{
-        metadata::ListFieldsParams,
-        symbology::ResolveParams,
-        timeseries::GetRangeParams, ClientBuilder,
-        DateRange, DateTimeRange,
+        metadata::{DatasetCondition, DatasetInfo},
+        timeseries::GetRangeParams,
+        ClientBuilder, DateRange, DateTimeRange, HistoricalApi,
     },
+    quality::{check_candles_within, Gap},
+    series::CandleSeries,
+    source::{DataRequest, MarketDataSource},
+    timeconv::{ToChrono, ToOffsetDateTime, ToTimeDate},
+    timeutil::{resolve_local, LocalTimePolicy},
+    Symbols,
 };
-use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Datelike};
-use chrono_tz::{America::New_York, US::Eastern};
-use std::{collections::HashMap};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::{America::New_York, Tz, US::Eastern};
+use std::{collections::HashMap, fmt, sync::Arc};
 use time::{Date, OffsetDateTime};
+use tokio::io::AsyncReadExt;
+
+/// An alias for a `Result` with [`PmzError`] as the error type.
+pub type Result<T> = std::result::Result<T, PmzError>;
+
+/// An error that can occur while calculating PMZ values.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum PmzError {
+    /// No data was available for the requested date.
+    #[error("no data: {0}")]
+    NoData(String),
+    /// Data was retrieved, but some of the inputs to the calculation were missing.
+    /// Returned by [`PmzResult::ensure_complete()`].
+    #[error("partial data, missing: {}", missing_fields.join(", "))]
+    PartialData {
+        /// The names of the values that couldn't be calculated.
+        missing_fields: Vec<&'static str>,
+    },
+    /// An error from the Databento client or API.
+    #[error("API error: {0}")]
+    ApiError(#[from] crate::Error),
+    /// The symbol couldn't be resolved for the requested date.
+    #[error("symbology error: {0}")]
+    SymbologyError(String),
+    /// The requested date was invalid or couldn't be converted.
+    #[error("invalid date: {0}")]
+    InvalidDate(String),
+    /// A record's UNIX nanosecond timestamp was out of the range of [`DateTime`], e.g.
+    /// [`dbn::UNDEF_TIMESTAMP`].
+    #[error("invalid timestamp: {0} is out of range")]
+    InvalidTimestamp(u64),
+}
+
+impl From<dbn::Error> for PmzError {
+    fn from(err: dbn::Error) -> Self {
+        Self::ApiError(err.into())
+    }
+}
 
 /// PMZ calculation result structure
-#[derive(Debug, Clone)]
+///
+/// Each level is `None` when it couldn't be calculated, for example when the previous
+/// day's LIS candle is missing after a holiday. The components that couldn't be
+/// calculated are listed in [`missing`](Self::missing).
+#[derive(Debug, Clone, PartialEq)]
 pub struct PmzResult {
     /// The date for which PMZ values were calculated
     pub date: NaiveDate,
     /// Pre-Market High value
-    pub pmh: f64,
+    pub pmh: Option<f64>,
     /// Pre-Market Low value
-    pub pml: f64,
+    pub pml: Option<f64>,
     /// Previous day's Line in Sand (LIS) value
-    pub prev_day_lis: f64,
+    pub prev_day_lis: Option<f64>,
+    /// The price the gap is measured against: the previous day's LIS unless another
+    /// [`GapReference`] was configured
+    pub gap_reference: Option<f64>,
+    /// The close of the 9:25 candle, used to determine the gap direction
+    pub close_925: Option<f64>,
     /// Indicates if the market gapped up (true) or down (false)
-    pub is_gap_up: bool,
+    pub is_gap_up: Option<bool>,
+    /// The size of the gap in points: the 9:25 close minus the gap reference
+    pub gap_points: Option<f64>,
+    /// The size of the gap as a percentage of the gap reference
+    pub gap_percent: Option<f64>,
     /// PMZ high value (buy zone)
-    pub pmz_high: f64,
+    pub pmz_high: Option<f64>,
     /// PMZ low value (sell zone)
-    pub pmz_low: f64,
+    pub pmz_low: Option<f64>,
     /// Risk value (PMZ High - PMZ Low)
-    pub risk: f64,
+    pub risk: Option<f64>,
+    /// The components that couldn't be calculated
+    pub missing: Vec<PmzComponent>,
+    /// The trading session of [`date`](Self::date)
+    pub session: Option<TradingSession>,
+    /// The previous trading session, whose close determines the LIS window. This is
+    /// earlier than usual after a half day
+    pub prev_session: Option<TradingSession>,
+    /// The local start and end times of the pre-market window the PMH and PML were
+    /// calculated over
+    pub pre_market_window: Option<(NaiveTime, NaiveTime)>,
+    /// The candles the values were calculated from, when requested with
+    /// [`PmzConfig::include_candles`]
+    pub candles: Option<PmzCandles>,
+}
+
+/// The five-minute candles behind the PMZ levels, e.g. for charting them without
+/// requesting the data again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PmzCandles {
+    /// The previous day's LIS candle, whose close is the LIS. Empty when it's missing.
+    pub lis: Vec<Candle>,
+    /// The candles of the pre-market window, whose highs and lows determine the PMH
+    /// and PML. The close of the last is the 9:25 close.
+    pub pre_market: Vec<Candle>,
+}
+
+/// A component of the PMZ calculation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PmzComponent {
+    /// The Pre-Market High.
+    Pmh,
+    /// The Pre-Market Low.
+    Pml,
+    /// The previous day's Line in Sand.
+    PrevDayLis,
+    /// The close of the 9:25 candle.
+    Close925,
+    /// The gap direction.
+    GapDirection,
+    /// The PMZ high.
+    PmzHigh,
+    /// The PMZ low.
+    PmzLow,
+    /// The risk.
+    Risk,
+}
+
+impl PmzComponent {
+    /// All components in calculation order.
+    pub const ALL: [PmzComponent; 8] = [
+        PmzComponent::Pmh,
+        PmzComponent::Pml,
+        PmzComponent::PrevDayLis,
+        PmzComponent::Close925,
+        PmzComponent::GapDirection,
+        PmzComponent::PmzHigh,
+        PmzComponent::PmzLow,
+        PmzComponent::Risk,
+    ];
+
+    /// Converts the enum to its `str` representation.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            PmzComponent::Pmh => "pmh",
+            PmzComponent::Pml => "pml",
+            PmzComponent::PrevDayLis => "prev_day_lis",
+            PmzComponent::Close925 => "close_925",
+            PmzComponent::GapDirection => "gap_direction",
+            PmzComponent::PmzHigh => "pmz_high",
+            PmzComponent::PmzLow => "pmz_low",
+            PmzComponent::Risk => "risk",
+        }
+    }
+
+    /// Returns the bit flag for this component, used in the FFI layer to report
+    /// missing components as a bitmask.
+    pub const fn flag(&self) -> u32 {
+        1 << (*self as u32)
+    }
+}
+
+/// Progress and diagnostic information reported while calculating PMZ values.
+///
+/// The [`Display`](fmt::Display) implementation gives a human-readable message.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    /// The calculation started for `date`, with the LIS from `previous_date`.
+    Started {
+        /// The trading day PMZ values are calculated for.
+        date: NaiveDate,
+        /// The previous trading day.
+        previous_date: NaiveDate,
+    },
+    /// The previous trading day closed early, so the LIS candle was shifted.
+    EarlyClose {
+        /// The early close in New York time.
+        close: NaiveTime,
+        /// The start of the LIS candle in New York time.
+        lis_start: NaiveTime,
+        /// The end of the LIS candle in New York time.
+        lis_end: NaiveTime,
+    },
+    /// One-minute candles are being requested over a range.
+    Querying {
+        /// The start of the range (inclusive).
+        start: DateTime<Utc>,
+        /// The end of the range (exclusive).
+        end: DateTime<Utc>,
+    },
+    /// The publishers, schemas, and available range of the dataset were retrieved. Only
+    /// reported with [`PmzConfig::dataset_info`].
+    Dataset(DatasetInfo),
+    /// The dataset's data for a date is degraded and may be incomplete.
+    DegradedData {
+        /// The dataset code.
+        dataset: String,
+        /// The degraded date.
+        date: NaiveDate,
+    },
+    /// The end of the query was clamped to the end of the available data.
+    Clamped {
+        /// The end of the available data.
+        end: DateTime<Utc>,
+    },
+    /// One-minute candles were retrieved.
+    CandlesRetrieved {
+        /// The number of candles.
+        count: usize,
+    },
+    /// One-minute candles were found within the pre-market window.
+    PreMarketCandles {
+        /// The number of candles.
+        count: usize,
+        /// The start of the window in New York time.
+        start: NaiveTime,
+        /// The end of the window in New York time.
+        end: NaiveTime,
+    },
+    /// One-minute candles are missing from the pre-market window.
+    MissingCandles(Gap),
+    /// The pre-market window was aggregated into five-minute candles.
+    Aggregated {
+        /// The number of five-minute candles.
+        count: usize,
+    },
+    /// Some PMZ values couldn't be calculated.
+    Incomplete {
+        /// The components that couldn't be calculated.
+        missing: Vec<PmzComponent>,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Started {
+                date,
+                previous_date,
+            } => write!(
+                f,
+                "Calculating PMZ for {date} (previous trading day: {previous_date})"
+            ),
+            Diagnostic::EarlyClose {
+                close,
+                lis_start,
+                lis_end,
+            } => write!(
+                f,
+                "Previous trading day closed early at {}, using LIS candle {} - {}",
+                close.format("%H:%M"),
+                lis_start.format("%H:%M"),
+                lis_end.format("%H:%M")
+            ),
+            Diagnostic::Querying { start, end } => {
+                write!(f, "Querying one-minute candles from {start} to {end}")
+            }
+            Diagnostic::Dataset(info) => write!(f, "Dataset {info}"),
+            Diagnostic::DegradedData { dataset, date } => {
+                write!(
+                    f,
+                    "{dataset} data for {date} is degraded and may be incomplete"
+                )
+            }
+            Diagnostic::Clamped { end } => {
+                write!(f, "Clamped query end to the end of available data: {end}")
+            }
+            Diagnostic::CandlesRetrieved { count } => {
+                write!(f, "Retrieved {count} one-minute candles")
+            }
+            Diagnostic::PreMarketCandles { count, start, end } => write!(
+                f,
+                "Found {count} one-minute candles within the pre-market window ({} - {} ET)",
+                start.format("%H:%M"),
+                end.format("%H:%M")
+            ),
+            Diagnostic::MissingCandles(gap) => write!(
+                f,
+                "Missing {} one-minute candles from {} to {}",
+                gap.missing,
+                gap.start.format("%H:%M"),
+                gap.end.format("%H:%M")
+            ),
+            Diagnostic::Aggregated { count } => {
+                write!(
+                    f,
+                    "Aggregated the pre-market window into {count} five-minute candles"
+                )
+            }
+            Diagnostic::Incomplete { missing } => {
+                let missing: Vec<_> = missing.iter().map(|c| c.as_str()).collect();
+                write!(
+                    f,
+                    "Failed to calculate complete PMZ values, missing: {}",
+                    missing.join(", ")
+                )
+            }
+        }
+    }
+}
+
+// Reports `diagnostic` to the caller and as a tracing event
+fn report(diagnostics: &mut impl FnMut(Diagnostic), diagnostic: Diagnostic) {
+    tracing::debug!(%diagnostic);
+    diagnostics(diagnostic);
 }
 
+impl PmzResult {
+    /// Derives the gap direction, PMZ levels, and risk from the raw inputs,
+    /// calculating as much as possible when some inputs are missing. The PMZ levels use
+    /// the default zone factors of [`PmzConfig`]; use [`Self::with_zone_factors()`] to
+    /// change them.
+    pub fn from_inputs(
+        date: NaiveDate,
+        pmh: Option<f64>,
+        pml: Option<f64>,
+        prev_day_lis: Option<f64>,
+        close_925: Option<f64>,
+    ) -> Self {
+        let mut result = Self {
+            date,
+            pmh,
+            pml,
+            prev_day_lis,
+            gap_reference: prev_day_lis,
+            close_925,
+            is_gap_up: None,
+            gap_points: None,
+            gap_percent: None,
+            pmz_high: None,
+            pmz_low: None,
+            risk: None,
+            missing: Vec::new(),
+            session: None,
+            prev_session: None,
+            pre_market_window: None,
+            candles: None,
+        };
+        result.set_gap();
+        result.set_zone(DEFAULT_ZONE_WIDTH_FACTOR, DEFAULT_ZONE_OFFSET_FACTOR);
+        result
+    }
+
+    /// Measures the gap against `reference` instead of the previous day's LIS, e.g. the
+    /// prior settlement, recalculating the gap and the PMZ levels with the default zone
+    /// factors. Call this before [`Self::with_zone_factors()`].
+    pub fn with_gap_reference(mut self, reference: Option<f64>) -> Self {
+        self.gap_reference = reference;
+        self.set_gap();
+        self.set_zone(DEFAULT_ZONE_WIDTH_FACTOR, DEFAULT_ZONE_OFFSET_FACTOR);
+        self
+    }
+
+    fn set_gap(&mut self) {
+        // --- Determine Gap Direction (Using 9:25 AM Close) ---
+        // Cannot determine gap if 9:25 close or the reference is missing
+        let close_and_reference = self.close_925.zip(self.gap_reference);
+        self.is_gap_up = close_and_reference.map(|(close, reference)| close >= reference);
+        self.gap_points = close_and_reference.map(|(close, reference)| close - reference);
+        self.gap_percent = self
+            .gap_points
+            .zip(self.gap_reference)
+            .filter(|(_, reference)| *reference != 0.0)
+            .map(|(points, reference)| points / reference * 100.0);
+    }
+
+    /// Recalculates the PMZ levels and risk with the given fractions of the pre-market
+    /// range. See [`PmzConfig::zone_width_factor`] and [`PmzConfig::zone_offset_factor`].
+    pub fn with_zone_factors(mut self, width_factor: f64, offset_factor: f64) -> Self {
+        self.set_zone(width_factor, offset_factor);
+        self
+    }
+
+    fn set_zone(&mut self, width_factor: f64, offset_factor: f64) {
+        // --- Calculate Risk Range ---
+        let risk_range = self.pmh.zip(self.pml).map(|(h, l)| h - l);
+
+        // --- Calculate PMZ High/Low based on Gap ---
+        // The far edge of the zone is `offset_factor` of the range from the extreme the
+        // market gapped toward and the near edge is `width_factor` closer to it
+        let (near, far) = (offset_factor - width_factor, offset_factor);
+        let (pmz_high, pmz_low) = match (self.is_gap_up, self.pmh, self.pml, risk_range) {
+            (Some(true), Some(h), _, Some(r)) => (Some(h - r * near), Some(h - r * far)), // Gap Up
+            (Some(false), _, Some(l), Some(r)) => (Some(l + r * far), Some(l + r * near)), // Gap Down
+            _ => (None, None), // Cannot calculate if gap or PMH/PML/Risk is missing
+        };
+
+        // --- Calculate Risk (PMZ High - PMZ Low) ---
+        self.pmz_high = pmz_high;
+        self.pmz_low = pmz_low;
+        self.risk = pmz_high.zip(pmz_low).map(|(h, l)| h - l);
+        self.missing = PmzComponent::ALL
+            .into_iter()
+            .filter(|c| !self.has(*c))
+            .collect();
+    }
+
+    /// Sets the effective trading sessions the result was calculated with.
+    pub fn with_sessions(mut self, session: TradingSession, prev_session: TradingSession) -> Self {
+        self.session = Some(session);
+        self.prev_session = Some(prev_session);
+        self
+    }
+
+    /// Sets the local start and end times of the pre-market window the result was
+    /// calculated over.
+    pub fn with_pre_market_window(mut self, start: NaiveTime, end: NaiveTime) -> Self {
+        self.pre_market_window = Some((start, end));
+        self
+    }
+
+    /// Sets the candles the result was calculated from.
+    pub fn with_candles(mut self, candles: PmzCandles) -> Self {
+        self.candles = Some(candles);
+        self
+    }
+
+    /// Returns the local start and end times of the previous day's LIS candle, which is
+    /// the last five minutes of the previous session.
+    pub fn lis_window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        self.prev_session.map(|s| lis_window(&s))
+    }
+
+    /// Returns the start and end of the pre-market window on [`date`](Self::date) in
+    /// New York time.
+    pub fn pre_market_times(&self) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+        let (start, end) = self.pre_market_window?;
+        Some((
+            ny_local(self.date, start).ok()?,
+            ny_local(self.date, end).ok()?,
+        ))
+    }
+
+    /// Returns the start and end of the previous day's LIS candle in New York time.
+    pub fn lis_times(&self) -> Option<(DateTime<Tz>, DateTime<Tz>)> {
+        let prev_session = self.prev_session?;
+        let (start, end) = lis_window(&prev_session);
+        Some((
+            ny_local(prev_session.date, start).ok()?,
+            ny_local(prev_session.date, end).ok()?,
+        ))
+    }
+
+    /// Returns `true` if `component` was calculated.
+    pub fn has(&self, component: PmzComponent) -> bool {
+        match component {
+            PmzComponent::Pmh => self.pmh.is_some(),
+            PmzComponent::Pml => self.pml.is_some(),
+            PmzComponent::PrevDayLis => self.prev_day_lis.is_some(),
+            PmzComponent::Close925 => self.close_925.is_some(),
+            PmzComponent::GapDirection => self.is_gap_up.is_some(),
+            PmzComponent::PmzHigh => self.pmz_high.is_some(),
+            PmzComponent::PmzLow => self.pmz_low.is_some(),
+            PmzComponent::Risk => self.risk.is_some(),
+        }
+    }
+
+    /// Returns `true` if every component was calculated.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Returns the bitmask of [`PmzComponent::flag()`]s for the missing components.
+    pub fn missing_flags(&self) -> u32 {
+        self.missing.iter().fold(0, |acc, c| acc | c.flag())
+    }
+
+    /// Returns `self` if every component was calculated.
+    ///
+    /// # Errors
+    /// This function returns [`PmzError::PartialData`] listing the missing components
+    /// if the result is incomplete.
+    pub fn ensure_complete(self) -> Result<Self> {
+        if self.is_complete() {
+            Ok(self)
+        } else {
+            Err(PmzError::PartialData {
+                missing_fields: self.missing.iter().map(PmzComponent::as_str).collect(),
+            })
+        }
+    }
+}
+
+/// A price representation that [`Candle`]s can be built with.
+///
+/// `f64` is always available. With the `decimal` feature enabled,
+/// [`rust_decimal::Decimal`] is also supported, which converts DBN fixed-point prices
+/// exactly and avoids floating point drift when aggregating.
+pub trait CandlePrice: Copy + PartialOrd + std::fmt::Debug {
+    /// Converts a DBN fixed-point price (1e-9 scaling) to this price type.
+    fn from_fixed(px: i64) -> Self;
+
+    /// Converts every DBN fixed-point price in `src` like
+    /// [`from_fixed()`](Self::from_fixed) and appends them to `dst`. Implementations
+    /// can override this with a faster batch conversion.
+    fn extend_from_fixed(dst: &mut Vec<Self>, src: &[i64]) {
+        dst.extend(src.iter().map(|&px| Self::from_fixed(px)));
+    }
+
+    /// Converts this price to an `f64`, possibly losing precision.
+    fn to_f64(self) -> f64;
+
+    /// Converts this price to a DBN fixed-point price (1e-9 scaling), rounding to the
+    /// nearest nanounit.
+    fn to_fixed(self) -> i64 {
+        (self.to_f64() * 1e9).round() as i64
+    }
+
+    /// Returns the greater of `self` and `other`.
+    fn max_price(self, other: Self) -> Self {
+        if other > self {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Returns the lesser of `self` and `other`.
+    fn min_price(self, other: Self) -> Self {
+        if other < self {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl CandlePrice for f64 {
+    fn from_fixed(px: i64) -> Self {
+        px as f64 * 1e-9
+    }
+
+    fn extend_from_fixed(dst: &mut Vec<Self>, src: &[i64]) {
+        // x86 has no packed i64 to f64 conversion before AVX-512, so `as f64` is done
+        // one price at a time. Integers within ±2^51 convert exactly by adding them to
+        // the mantissa of 1.5 * 2^52 and subtracting it again, which compiles to packed
+        // integer and float instructions and gives the same result as `from_fixed()`
+        const MAGIC: f64 = 6_755_399_441_055_744.0;
+        const LIMIT: i64 = 1 << 51;
+        dst.reserve(src.len());
+        let mut chunks = src.chunks_exact(8);
+        for chunk in &mut chunks {
+            // Non-short-circuiting so the range check vectorizes too
+            let in_range = chunk.iter().fold(true, |in_range, &px| {
+                in_range & ((px.wrapping_add(LIMIT) as u64) < (2 * LIMIT) as u64)
+            });
+            if in_range {
+                let mut converted = [0.0; 8];
+                for (converted, &px) in converted.iter_mut().zip(chunk) {
+                    *converted =
+                        (f64::from_bits(MAGIC.to_bits().wrapping_add(px as u64)) - MAGIC) * 1e-9;
+                }
+                dst.extend_from_slice(&converted);
+            } else {
+                // E.g. `UNDEF_PRICE`
+                dst.extend(chunk.iter().map(|&px| Self::from_fixed(px)));
+            }
+        }
+        dst.extend(chunks.remainder().iter().map(|&px| Self::from_fixed(px)));
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl CandlePrice for rust_decimal::Decimal {
+    fn from_fixed(px: i64) -> Self {
+        // Exact: the mantissa is the raw fixed-point value with a scale of 9
+        rust_decimal::Decimal::new(px, 9).normalize()
+    }
+
+    fn to_f64(self) -> f64 {
+        use rust_decimal::prelude::ToPrimitive;
+        ToPrimitive::to_f64(&self).unwrap_or(f64::NAN)
+    }
+
+    fn to_fixed(self) -> i64 {
+        use rust_decimal::prelude::ToPrimitive;
+        // Out-of-range prices become undefined
+        self.checked_mul(rust_decimal::Decimal::from(1_000_000_000))
+            .and_then(|px| px.round().to_i64())
+            .unwrap_or(dbn::UNDEF_PRICE)
+    }
+}
+
+/// A candle with exact decimal prices.
+#[cfg(feature = "decimal")]
+pub type DecimalCandle = Candle<rust_decimal::Decimal>;
+
+/// The timezone candle timestamps are converted to by default.
+pub const DEFAULT_CANDLE_TZ: Tz = Eastern;
+
 // --- Candle Struct ---
-#[derive(Debug, Clone)]
-struct Candle {
-    timestamp: DateTime<chrono_tz::Tz>, // Use timezone-aware DateTime (Eastern)
-    instrument_id: u32,
-    symbol: String, // Assuming a single symbol for simplicity here
-    open: f64,
-    high: f64,
-    low: f64,
-    close: f64,
-    volume: u64,
+/// An OHLCV candle with a timezone-aware timestamp, generic over its price type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle<P = f64> {
+    /// The start of the candle, in US/Eastern time unless created with
+    /// [`Candle::try_with_tz()`].
+    pub timestamp: DateTime<Tz>,
+    /// The instrument ID from the record header.
+    pub instrument_id: u32,
+    /// The symbol associated with the instrument, shared between candles created with
+    /// [`Candle::try_with_symbol()`] or aggregated from the same candles.
+    pub symbol: Arc<str>,
+    /// The open price.
+    pub open: P,
+    /// The high price.
+    pub high: P,
+    /// The low price.
+    pub low: P,
+    /// The close price.
+    pub close: P,
+    /// The total volume.
+    pub volume: u64,
 }
 
-impl Candle {
-    // Simplified constructor for this example, assuming symbol is known
-    fn new(ohlcv: &OhlcvMsg, symbol: &str) -> Self {
+impl<P: CandlePrice> Candle<P> {
+    /// Creates a candle from an OHLCV record, assuming `symbol` is known, with its
+    /// timestamp in [`DEFAULT_CANDLE_TZ`].
+    ///
+    /// A `ts_event` past `i64::MAX` nanoseconds, like [`dbn::UNDEF_TIMESTAMP`], silently
+    /// wraps around to a timestamp before 1970.
+    #[deprecated(
+        since = "0.25.0",
+        note = "use `try_new`, which returns an error for an out-of-range `ts_event`"
+    )]
+    pub fn new(ohlcv: &OhlcvMsg, symbol: &str) -> Self {
+        Self::with_wrapped_timestamp(ohlcv, Arc::from(symbol), DEFAULT_CANDLE_TZ)
+    }
+
+    /// Creates a candle from an OHLCV record, assuming `symbol` is known, with its
+    /// timestamp in `tz`, e.g. the exchange's timezone or [`Tz::UTC`].
+    ///
+    /// A `ts_event` past `i64::MAX` nanoseconds, like [`dbn::UNDEF_TIMESTAMP`], silently
+    /// wraps around to a timestamp before 1970.
+    #[deprecated(
+        since = "0.25.0",
+        note = "use `try_with_tz`, which returns an error for an out-of-range `ts_event`"
+    )]
+    pub fn with_tz(ohlcv: &OhlcvMsg, symbol: &str, tz: Tz) -> Self {
+        Self::with_wrapped_timestamp(ohlcv, Arc::from(symbol), tz)
+    }
+
+    /// Creates a candle from an OHLCV record with a shared `symbol`, e.g. from a
+    /// [`SymbolTable`], which avoids allocating a copy of the symbol for every candle.
+    ///
+    /// A `ts_event` past `i64::MAX` nanoseconds, like [`dbn::UNDEF_TIMESTAMP`], silently
+    /// wraps around to a timestamp before 1970.
+    #[deprecated(
+        since = "0.25.0",
+        note = "use `try_with_symbol`, which returns an error for an out-of-range `ts_event`"
+    )]
+    pub fn with_symbol(ohlcv: &OhlcvMsg, symbol: Arc<str>, tz: Tz) -> Self {
+        Self::with_wrapped_timestamp(ohlcv, symbol, tz)
+    }
+
+    fn with_wrapped_timestamp(ohlcv: &OhlcvMsg, symbol: Arc<str>, tz: Tz) -> Self {
         // Convert timestamp from nanos to a DateTime (UTC)
-        let ts_nanos = ohlcv.hd.ts_event as i64;
-        let seconds = ts_nanos / 1_000_000_000;
-        let nanos = (ts_nanos % 1_000_000_000) as u32;
-        let utc_timestamp = Utc.timestamp_opt(seconds, nanos).single().unwrap();
+        let utc_timestamp = DateTime::from_timestamp_nanos(ohlcv.hd.ts_event as i64);
+        Self::with_timestamp(ohlcv, symbol, utc_timestamp.with_timezone(&tz))
+    }
 
-        // Convert UTC to Eastern Time
-        let est_timestamp = utc_timestamp.with_timezone(&Eastern);
+    /// Creates a candle from an OHLCV record, assuming `symbol` is known, with its
+    /// timestamp in [`DEFAULT_CANDLE_TZ`].
+    ///
+    /// # Errors
+    /// This function returns [`PmzError::InvalidTimestamp`] when the record's
+    /// `ts_event` is out of range, e.g. when it's undefined.
+    pub fn try_new(ohlcv: &OhlcvMsg, symbol: &str) -> Result<Self> {
+        Self::try_with_tz(ohlcv, symbol, DEFAULT_CANDLE_TZ)
+    }
 
-        // Convert fixed point prices (with 1e-9 scaling) to floating point
-        let scaling_factor = 1e-9; // Use 1e-9 directly
+    /// Creates a candle from an OHLCV record, assuming `symbol` is known, with its
+    /// timestamp in `tz`, e.g. the exchange's timezone or [`Tz::UTC`].
+    ///
+    /// # Errors
+    /// This function returns [`PmzError::InvalidTimestamp`] when the record's
+    /// `ts_event` is out of range, e.g. when it's undefined.
+    pub fn try_with_tz(ohlcv: &OhlcvMsg, symbol: &str, tz: Tz) -> Result<Self> {
+        Self::try_with_symbol(ohlcv, Arc::from(symbol), tz)
+    }
+
+    /// Creates a candle from an OHLCV record with a shared `symbol`, e.g. from a
+    /// [`SymbolTable`], which avoids allocating a copy of the symbol for every candle.
+    ///
+    /// # Errors
+    /// This function returns [`PmzError::InvalidTimestamp`] when the record's
+    /// `ts_event` is out of range, e.g. when it's undefined.
+    pub fn try_with_symbol(ohlcv: &OhlcvMsg, symbol: Arc<str>, tz: Tz) -> Result<Self> {
+        let ts_event = ohlcv.hd.ts_event;
+        let nanos = i64::try_from(ts_event).map_err(|_| PmzError::InvalidTimestamp(ts_event))?;
+        let timestamp = DateTime::from_timestamp_nanos(nanos).with_timezone(&tz);
+        Ok(Self::with_timestamp(ohlcv, symbol, timestamp))
+    }
 
+    /// Creates a candle from each OHLCV record in `records` like
+    /// [`Candle::try_with_symbol()`] and appends them to `candles`, converting the
+    /// prices of all the records in one batch with
+    /// [`CandlePrice::extend_from_fixed()`]. `symbol` returns the symbol of an
+    /// instrument ID.
+    ///
+    /// # Errors
+    /// This function returns [`PmzError::InvalidTimestamp`] when a record's `ts_event`
+    /// is out of range, in which case no candles are appended.
+    pub fn extend_from_ohlcv(
+        candles: &mut Vec<Self>,
+        records: &[OhlcvMsg],
+        mut symbol: impl FnMut(u32) -> Arc<str>,
+        tz: Tz,
+    ) -> Result<()> {
+        let fixed: Vec<i64> = records
+            .iter()
+            .flat_map(|ohlcv| [ohlcv.open, ohlcv.high, ohlcv.low, ohlcv.close])
+            .collect();
+        let mut prices = Vec::with_capacity(fixed.len());
+        P::extend_from_fixed(&mut prices, &fixed);
+        let start = candles.len();
+        candles.reserve(records.len());
+        for (ohlcv, prices) in records.iter().zip(prices.chunks_exact(4)) {
+            let ts_event = ohlcv.hd.ts_event;
+            let Ok(nanos) = i64::try_from(ts_event) else {
+                candles.truncate(start);
+                return Err(PmzError::InvalidTimestamp(ts_event));
+            };
+            candles.push(Candle {
+                timestamp: DateTime::from_timestamp_nanos(nanos).with_timezone(&tz),
+                instrument_id: ohlcv.hd.instrument_id,
+                symbol: symbol(ohlcv.hd.instrument_id),
+                open: prices[0],
+                high: prices[1],
+                low: prices[2],
+                close: prices[3],
+                volume: ohlcv.volume,
+            });
+        }
+        Ok(())
+    }
+
+    fn with_timestamp(ohlcv: &OhlcvMsg, symbol: Arc<str>, timestamp: DateTime<Tz>) -> Self {
         Candle {
-            timestamp: est_timestamp,
+            timestamp,
             instrument_id: ohlcv.hd.instrument_id,
-            symbol: symbol.to_string(), // Use the passed symbol
-            open: ohlcv.open as f64 * scaling_factor,
-            high: ohlcv.high as f64 * scaling_factor,
-            low: ohlcv.low as f64 * scaling_factor,
-            close: ohlcv.close as f64 * scaling_factor,
+            symbol,
+            open: P::from_fixed(ohlcv.open),
+            high: P::from_fixed(ohlcv.high),
+            low: P::from_fixed(ohlcv.low),
+            close: P::from_fixed(ohlcv.close),
             volume: ohlcv.volume,
         }
     }
 
-    // Format the timestamp to yyyy-mm-dd HH:MM (Eastern Time)
-    fn format_timestamp(&self) -> String {
+    /// Returns the candle with its timestamp converted to `tz`.
+    pub fn in_tz(mut self, tz: Tz) -> Self {
+        self.timestamp = self.timestamp.with_timezone(&tz);
+        self
+    }
+
+    /// Formats the timestamp as `yyyy-mm-dd HH:MM` in the candle's timezone.
+    pub fn format_timestamp(&self) -> String {
         self.timestamp.format("%Y-%m-%d %H:%M").to_string()
     }
 }
 
+/// An interned table of the symbols of instrument IDs, so candles for the same
+/// instrument share one copy of their symbol instead of allocating one per record.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<u32, Arc<str>>,
+}
+
+impl SymbolTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a table from the symbology mappings in `metadata` on `date`.
+    ///
+    /// # Errors
+    /// This function returns an error when the mappings can't be parsed as instrument
+    /// IDs.
+    pub fn from_metadata(metadata: &Metadata, date: Date) -> Result<Self> {
+        let symbol_map = metadata.symbol_map_for_date(date)?;
+        Ok(Self {
+            symbols: symbol_map
+                .inner()
+                .iter()
+                .map(|(instrument_id, symbol)| (*instrument_id, Arc::from(symbol.as_str())))
+                .collect(),
+        })
+    }
+
+    /// Sets the symbol of `instrument_id`, returning the shared symbol. The existing
+    /// allocation is reused if the symbol hasn't changed.
+    pub fn insert(&mut self, instrument_id: u32, symbol: &str) -> Arc<str> {
+        let entry = self
+            .symbols
+            .entry(instrument_id)
+            .or_insert_with(|| Arc::from(symbol));
+        if &**entry != symbol {
+            *entry = Arc::from(symbol);
+        }
+        entry.clone()
+    }
+
+    /// Returns the symbol of `instrument_id`.
+    pub fn get(&self, instrument_id: u32) -> Option<&str> {
+        self.symbols.get(&instrument_id).map(|symbol| &**symbol)
+    }
+
+    /// Returns the shared symbol of `instrument_id`, falling back to the instrument ID
+    /// itself for unknown instruments, which is then interned.
+    pub fn get_or_id(&mut self, instrument_id: u32) -> Arc<str> {
+        self.symbols
+            .entry(instrument_id)
+            .or_insert_with(|| Arc::from(instrument_id.to_string()))
+            .clone()
+    }
+}
+
+/// Decodes the OHLCV records from `decoder` and calls `visit` with a candle for each,
+/// without collecting them, returning the number of candles visited. Symbols are
+/// looked up in `symbols`, so no symbol is copied per record.
+///
+/// # Errors
+/// This function returns an error when a record can't be decoded, isn't an OHLCV
+/// record, or has an out-of-range timestamp.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn visit_candles<R, F>(
+    decoder: &mut AsyncDbnDecoder<R>,
+    symbols: &mut SymbolTable,
+    tz: Tz,
+    mut visit: F,
+) -> Result<u64>
+where
+    R: AsyncReadExt + Unpin,
+    F: FnMut(&Candle),
+{
+    let mut count = 0;
+    while let Some(record) = decoder.decode_record::<OhlcvMsg>().await? {
+        let symbol = symbols.get_or_id(record.hd.instrument_id);
+        visit(&Candle::try_with_symbol(record, symbol, tz)?);
+        count += 1;
+    }
+    tracing::debug!(candles = count, "Decoded candles");
+    Ok(count)
+}
+
 // --- Aggregation Function ---
-// Takes a slice of 1-min candles and aggregates them into interval_minutes candles
-fn aggregate_candles(candles: &[Candle], interval_minutes: u32) -> Vec<Candle> {
-    let mut result = Vec::new();
-    let mut candle_map: HashMap<String, Vec<&Candle>> = HashMap::new();
+/// Aggregates a slice of 1-minute candles into `interval_minutes` candles for each
+/// instrument, ordered by timestamp and then instrument ID.
+///
+/// Intervals are aligned to local midnight in each candle's timezone, and the
+/// aggregated candles keep that timezone.
+pub fn aggregate_candles<P: CandlePrice>(
+    candles: &[Candle<P>],
+    interval_minutes: u32,
+) -> Vec<Candle<P>> {
+    aggregate_candles_impl(
+        candles,
+        Duration::minutes(interval_minutes.into()),
+        BucketAnchor::LocalMidnight,
+        None,
+    )
+}
+
+/// Aggregates a slice of 1-minute candles into `interval_minutes` candles with their
+/// timestamps in `tz`.
+///
+/// Intervals are aligned to local midnight in `tz`, which matters for hourly or
+/// longer intervals in timezones with non-whole-hour offsets.
+pub fn aggregate_candles_in_tz<P: CandlePrice>(
+    candles: &[Candle<P>],
+    interval_minutes: u32,
+    tz: Tz,
+) -> Vec<Candle<P>> {
+    aggregate_candles_impl(
+        candles,
+        Duration::minutes(interval_minutes.into()),
+        BucketAnchor::LocalMidnight,
+        Some(tz),
+    )
+}
+
+/// Aggregates a slice of candles into buckets of any positive `interval`, e.g. 7 or
+/// 90 minutes, 4 hours, or a day, aligned according to `anchor`.
+pub fn aggregate_candles_by<P: CandlePrice>(
+    candles: &[Candle<P>],
+    interval: Duration,
+    anchor: BucketAnchor,
+) -> Vec<Candle<P>> {
+    aggregate_candles_impl(candles, interval, anchor, None)
+}
+
+/// Aggregates candles like [`aggregate_candles()`], but partitions them by instrument
+/// across threads, which speeds up dataset-wide scans with many instruments. The
+/// result is the same as [`aggregate_candles()`].
+#[cfg(feature = "parallel")]
+pub fn par_aggregate_candles<P: CandlePrice + Send + Sync>(
+    candles: &[Candle<P>],
+    interval_minutes: u32,
+) -> Vec<Candle<P>> {
+    par_aggregate_candles_by(
+        candles,
+        Duration::minutes(interval_minutes.into()),
+        BucketAnchor::LocalMidnight,
+    )
+}
 
-    // Group by interval_minutes intervals
+/// Aggregates candles like [`aggregate_candles_by()`], but partitions them by
+/// instrument across threads. The result is the same as [`aggregate_candles_by()`].
+#[cfg(feature = "parallel")]
+pub fn par_aggregate_candles_by<P: CandlePrice + Send + Sync>(
+    candles: &[Candle<P>],
+    interval: Duration,
+    anchor: BucketAnchor,
+) -> Vec<Candle<P>> {
+    // Below this, distributing the work costs more than it saves
+    const MIN_PARALLEL_CANDLES: usize = 100_000;
+
+    if rayon::current_num_threads() == 1 || candles.len() < MIN_PARALLEL_CANDLES {
+        return aggregate_candles_impl(candles, interval, anchor, None);
+    }
+    aggregate_candles_partitioned(candles, interval, anchor)
+}
+
+#[cfg(feature = "parallel")]
+fn aggregate_candles_partitioned<P: CandlePrice + Send + Sync>(
+    candles: &[Candle<P>],
+    interval: Duration,
+    anchor: BucketAnchor,
+) -> Vec<Candle<P>> {
+    use rayon::prelude::*;
+
+    // Every candle of an instrument goes to the same partition in its original order, so
+    // each bucket combines the same candles as the sequential version
+    let mut partitions: HashMap<u32, Vec<&Candle<P>>> = HashMap::new();
     for candle in candles {
-        let minute = candle.timestamp.minute();
-        let normalized_minute = (minute / interval_minutes) * interval_minutes;
-
-        let key = format!(
-            "{:04}-{:02}-{:02} {:02}:{:02}",
-            candle.timestamp.year(),
-            candle.timestamp.month(),
-            candle.timestamp.day(),
-            candle.timestamp.hour(),
-            normalized_minute
+        partitions
+            .entry(candle.instrument_id)
+            .or_default()
+            .push(candle);
+    }
+    let partitions: Vec<_> = partitions.into_values().collect();
+    let mut result: Vec<Candle<P>> = partitions
+        .par_iter()
+        .flat_map_iter(|partition| {
+            aggregate_candles_impl(partition.iter().copied(), interval, anchor, None)
+        })
+        .collect();
+    result.sort_unstable_by_key(|candle| (candle.timestamp, candle.instrument_id));
+    result
+}
+
+/// The reference point aggregation buckets are aligned to. Buckets start at whole
+/// multiples of the interval after the anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketAnchor {
+    /// The Unix epoch, i.e. UTC midnight for intervals that divide a day.
+    Epoch,
+    /// Midnight of each candle's local date in its timezone, so buckets restart every
+    /// day.
+    LocalMidnight,
+    /// A fixed instant such as a session open, e.g. for daily bars that start at
+    /// 18:00 ET on the previous day.
+    At(DateTime<Tz>),
+}
+
+/// Returns the start of the `interval`-long bucket containing `timestamp`, computed as
+/// `timestamp - (timestamp - anchor) % interval` over nanoseconds. Returns `timestamp`
+/// unchanged when `interval` isn't positive.
+pub fn bucket_start(
+    timestamp: DateTime<Tz>,
+    interval: Duration,
+    anchor: BucketAnchor,
+) -> DateTime<Tz> {
+    let tz = timestamp.timezone();
+    let anchor = match anchor {
+        BucketAnchor::Epoch => DateTime::from_timestamp_nanos(0).with_timezone(&tz),
+        BucketAnchor::LocalMidnight => resolve_local(
+            timestamp.date_naive().and_time(NaiveTime::MIN),
+            &tz,
+            LocalTimePolicy::Earliest,
+        )
+        .unwrap_or(timestamp),
+        BucketAnchor::At(anchor) => anchor,
+    };
+    // Truncate in absolute time rather than rebuilding the local time so
+    // the repeated hour when clocks fall back stays distinct
+    match (
+        (timestamp - anchor).num_nanoseconds(),
+        interval.num_nanoseconds().filter(|&i| i > 0),
+    ) {
+        (Some(elapsed), Some(interval)) => {
+            timestamp - Duration::nanoseconds(elapsed.rem_euclid(interval))
+        }
+        _ => timestamp,
+    }
+}
+
+fn aggregate_candles_impl<'a, P: CandlePrice + 'a>(
+    candles: impl IntoIterator<Item = &'a Candle<P>>,
+    interval: Duration,
+    anchor: BucketAnchor,
+    tz: Option<Tz>,
+) -> Vec<Candle<P>> {
+    let candles = candles.into_iter();
+    let mut bucketer = Bucketer::new(interval, anchor);
+    // Preallocate for 1-minute input, the common case
+    let per_bucket = bucketer
+        .interval_ns
+        .map_or(1, |i| (i / 60_000_000_000).max(1)) as usize;
+    let mut result: Vec<Candle<P>> = Vec::with_capacity(candles.size_hint().0.div_ceil(per_bucket));
+    // The start in nanoseconds since the epoch and instrument ID of each bucket in
+    // `result`
+    let mut keys: Vec<(i64, u32)> = Vec::with_capacity(result.capacity());
+    // Group by instrument and bucket so different contracts, e.g. from a parent
+    // symbol, aren't merged. Time-ordered input only ever extends the latest bucket of
+    // each instrument, so it's tracked in `open` as (instrument ID, bucket start, index
+    // in `result`) without hashing every candle. Instruments usually arrive in the
+    // same order every interval, so the one after the last is checked first
+    let mut open: Vec<(u32, i64, usize)> = Vec::new();
+    let mut open_slots: HashMap<u32, usize> = HashMap::new();
+    let mut next_slot = 0;
+    // Every bucket, only indexed once a candle arrives out of order
+    let mut all: Option<HashMap<(u32, i64), usize>> = None;
+
+    for candle in candles {
+        let local = match tz {
+            Some(tz) => candle.timestamp.with_timezone(&tz),
+            None => candle.timestamp,
+        };
+        let start = bucketer.start(&local);
+        let instrument_id = candle.instrument_id;
+        let idx = if let Some(all) = all.as_mut() {
+            *all.entry((instrument_id, start))
+                .or_insert_with(|| push_bucket(&mut result, &mut keys, candle, local, start))
+        } else {
+            let slot = if open
+                .get(next_slot)
+                .is_some_and(|&(id, ..)| id == instrument_id)
+            {
+                Some(next_slot)
+            } else {
+                open_slots.get(&instrument_id).copied()
+            };
+            match slot {
+                Some(slot) if open[slot].1 >= start => {
+                    next_slot = (slot + 1) % open.len();
+                    if open[slot].1 == start {
+                        open[slot].2
+                    } else {
+                        let mut buckets: HashMap<(u32, i64), usize> = keys
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, &(start, instrument_id))| ((instrument_id, start), idx))
+                            .collect();
+                        let idx = *buckets.entry((instrument_id, start)).or_insert_with(|| {
+                            push_bucket(&mut result, &mut keys, candle, local, start)
+                        });
+                        all = Some(buckets);
+                        idx
+                    }
+                }
+                Some(slot) => {
+                    let idx = push_bucket(&mut result, &mut keys, candle, local, start);
+                    open[slot] = (instrument_id, start, idx);
+                    next_slot = (slot + 1) % open.len();
+                    idx
+                }
+                None => {
+                    let idx = push_bucket(&mut result, &mut keys, candle, local, start);
+                    open.push((instrument_id, start, idx));
+                    open_slots.insert(instrument_id, open.len() - 1);
+                    next_slot = 0;
+                    idx
+                }
+            }
+        };
+        let bar = &mut result[idx];
+        bar.high = P::max_price(bar.high, candle.high);
+        bar.low = P::min_price(bar.low, candle.low);
+        bar.close = candle.close;
+        bar.volume += candle.volume;
+    }
+
+    // Time-ordered input with instruments in a consistent order is already sorted
+    if !keys.is_sorted() {
+        result.sort_unstable_by(|a, b| {
+            (a.timestamp, a.instrument_id).cmp(&(b.timestamp, b.instrument_id))
+        });
+    }
+    result
+}
+
+// Adds a bucket starting at `start` for `candle`, returning its index
+fn push_bucket<P: CandlePrice>(
+    result: &mut Vec<Candle<P>>,
+    keys: &mut Vec<(i64, u32)>,
+    candle: &Candle<P>,
+    local: DateTime<Tz>,
+    start: i64,
+) -> usize {
+    // Usually the first candle starts its bucket, which avoids a timezone lookup
+    let timestamp = if local.timestamp_nanos_opt() == Some(start) {
+        local
+    } else {
+        DateTime::from_timestamp_nanos(start).with_timezone(&local.timezone())
+    };
+    result.push(Candle {
+        timestamp,
+        volume: 0,
+        ..candle.clone()
+    });
+    keys.push((start, candle.instrument_id));
+    result.len() - 1
+}
+
+/// Computes bucket starts in nanoseconds since the epoch like [`bucket_start()`], but
+/// with integer arithmetic and the current local day cached for
+/// [`BucketAnchor::LocalMidnight`].
+struct Bucketer {
+    interval: Duration,
+    interval_ns: Option<i64>,
+    anchor: BucketAnchor,
+    // The start and end of the latest local day and its timezone
+    day: Option<(i64, i64, Tz)>,
+}
+
+impl Bucketer {
+    fn new(interval: Duration, anchor: BucketAnchor) -> Self {
+        Self {
+            interval,
+            interval_ns: interval.num_nanoseconds().filter(|&i| i > 0),
+            anchor,
+            day: None,
+        }
+    }
+
+    fn start(&mut self, timestamp: &DateTime<Tz>) -> i64 {
+        let Some(ts) = timestamp.timestamp_nanos_opt() else {
+            // Only reachable for timestamps outside of 1677-2262, which DBN can't
+            // represent
+            return self
+                .fallback(timestamp)
+                .unwrap_or(if timestamp.timestamp() < 0 {
+                    i64::MIN
+                } else {
+                    i64::MAX
+                });
+        };
+        let Some(interval_ns) = self.interval_ns else {
+            return ts;
+        };
+        let anchor_ns = match self.anchor {
+            BucketAnchor::Epoch => Some(0),
+            BucketAnchor::LocalMidnight => self.local_midnight(timestamp, ts),
+            BucketAnchor::At(anchor) => anchor.timestamp_nanos_opt(),
+        };
+        match anchor_ns.and_then(|anchor_ns| ts.checked_sub(anchor_ns)) {
+            Some(elapsed) => ts - elapsed.rem_euclid(interval_ns),
+            None => self.fallback(timestamp).unwrap_or(ts),
+        }
+    }
+
+    fn local_midnight(&mut self, timestamp: &DateTime<Tz>, ts: i64) -> Option<i64> {
+        let tz = timestamp.timezone();
+        if let Some((start, end, day_tz)) = self.day {
+            if day_tz == tz && (start..end).contains(&ts) {
+                return Some(start);
+            }
+        }
+        let date = timestamp.date_naive();
+        let midnight = |date: NaiveDate| {
+            resolve_local(
+                date.and_time(NaiveTime::MIN),
+                &tz,
+                LocalTimePolicy::Earliest,
+            )
+            .ok()
+            .and_then(|midnight| midnight.timestamp_nanos_opt())
+        };
+        let start = midnight(date)?;
+        if let Some(end) = date.succ_opt().and_then(midnight) {
+            self.day = Some((start, end, tz));
+        }
+        Some(start)
+    }
+
+    fn fallback(&self, timestamp: &DateTime<Tz>) -> Option<i64> {
+        bucket_start(*timestamp, self.interval, self.anchor).timestamp_nanos_opt()
+    }
+}
+
+/// Combines `group`, in timestamp order, into a single candle starting at `timestamp`.
+/// Returns `None` if `group` is empty.
+pub(crate) fn combine_candles<P: CandlePrice>(
+    timestamp: DateTime<Tz>,
+    instrument_id: u32,
+    group: &[&Candle<P>],
+) -> Option<Candle<P>> {
+    let first = group.first()?;
+    Some(Candle {
+        timestamp,
+        instrument_id,
+        symbol: first.symbol.clone(),
+        open: first.open,
+        high: group.iter().map(|c| c.high).reduce(P::max_price)?,
+        low: group.iter().map(|c| c.low).reduce(P::min_price)?,
+        close: group.last()?.close,
+        volume: group.iter().map(|c| c.volume).sum(),
+    })
+}
+
+/// Incrementally aggregates a live stream of 1-minute candles for a single
+/// instrument into `interval_minutes` candles, emitting each one once it's complete.
+#[derive(Debug, Clone)]
+pub struct LiveCandleBuilder<P = f64> {
+    interval_minutes: u32,
+    tz: Tz,
+    session: Option<Session>,
+    current: Option<Candle<P>>,
+}
+
+impl<P: CandlePrice> LiveCandleBuilder<P> {
+    /// Creates a builder for `interval_minutes` candles aligned to the local clock of
+    /// `tz`. An interval of 0 is treated as 1.
+    pub fn new(interval_minutes: u32, tz: Tz) -> Self {
+        Self {
+            interval_minutes: interval_minutes.max(1),
+            tz,
+            session: None,
+            current: None,
+        }
+    }
+
+    /// Restricts the builder to the hours of `session`. Candles outside the session,
+    /// such as during the daily maintenance break, are ignored, and intervals are
+    /// aligned to each session's open rather than local midnight so no candle spans a
+    /// break.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Returns the in-progress candle, if any.
+    pub fn current(&self) -> Option<&Candle<P>> {
+        self.current.as_ref()
+    }
+
+    /// Adds a 1-minute candle, returning the previous aggregated candle if `candle`
+    /// starts a new interval.
+    pub fn push(&mut self, candle: Candle<P>) -> Option<Candle<P>> {
+        let local = candle.timestamp.with_timezone(&self.tz);
+        let anchor = match self.session {
+            Some(session) => {
+                let (open, _) = session
+                    .trading_date(&local)
+                    .and_then(|date| session.open_close(date))?;
+                BucketAnchor::At(open.with_timezone(&self.tz))
+            }
+            None => BucketAnchor::LocalMidnight,
+        };
+        let timestamp = bucket_start(
+            local,
+            Duration::minutes(self.interval_minutes.into()),
+            anchor,
         );
+        match self.current.as_mut() {
+            Some(current) if current.timestamp == timestamp => {
+                current.high = P::max_price(current.high, candle.high);
+                current.low = P::min_price(current.low, candle.low);
+                current.close = candle.close;
+                current.volume += candle.volume;
+                None
+            }
+            _ => self.current.replace(Candle {
+                timestamp,
+                ..candle
+            }),
+        }
+    }
 
-        candle_map.entry(key).or_default().push(candle);
+    /// Returns the in-progress candle and resets the builder, e.g. at the end of a
+    /// session.
+    pub fn flush(&mut self) -> Option<Candle<P>> {
+        self.current.take()
     }
+}
 
-    // Aggregate each group
-    for (timestamp_key, group) in candle_map {
-        if group.is_empty() {
-            continue;
+/// The pre-market high and low so far on a trading day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PremarketRange<P = f64> {
+    /// The trading date in New York time.
+    pub date: NaiveDate,
+    /// The pre-market high (PMH) so far.
+    pub high: P,
+    /// The pre-market low (PML) so far.
+    pub low: P,
+}
+
+/// Tracks the intraday pre-market high and low from a live stream of candles using
+/// the window in a [`PmzConfig`].
+#[derive(Debug, Clone)]
+pub struct PremarketTracker<P = f64> {
+    start: NaiveTime,
+    end: NaiveTime,
+    range: Option<PremarketRange<P>>,
+}
+
+impl<P: CandlePrice> PremarketTracker<P> {
+    /// Creates a tracker for the pre-market window in `config`.
+    pub fn new(config: &PmzConfig) -> Self {
+        Self {
+            start: config.start,
+            end: config.end,
+            range: None,
         }
+    }
 
-        // Parse the key back to a DateTime in Eastern Time
-        let timestamp = match DateTime::parse_from_str(&format!("{}:00 +0000", timestamp_key), "%Y-%m-%d %H:%M:%S %z") {
-             Ok(dt_utc) => dt_utc.with_timezone(&Eastern),
-             Err(e) => {
-                 eprintln!("Error parsing timestamp key '{}': {}", timestamp_key, e);
-                 continue;
-             }
-         };
+    /// Returns the current pre-market range, if any candles have fallen within the
+    /// window.
+    pub fn range(&self) -> Option<&PremarketRange<P>> {
+        self.range.as_ref()
+    }
 
-        let open = group.first().unwrap().open;
-        let close = group.last().unwrap().close;
-        let high = group.iter().map(|c| c.high).fold(f64::MIN, f64::max);
-        let low = group.iter().map(|c| c.low).fold(f64::MAX, f64::min);
-        let volume = group.iter().map(|c| c.volume).sum();
+    /// Updates the range with `candle`, returning the new range if the PMH or PML
+    /// changed. The range resets on the first pre-market candle of a new day.
+    pub fn update(&mut self, candle: &Candle<P>) -> Option<PremarketRange<P>> {
+        let local = candle.timestamp.with_timezone(&New_York);
+        let time = local.time();
+        if time < self.start || time >= self.end {
+            return None;
+        }
+        let date = local.date_naive();
+        let range = match self.range {
+            Some(range) if range.date == date => {
+                let high = P::max_price(range.high, candle.high);
+                let low = P::min_price(range.low, candle.low);
+                if high == range.high && low == range.low {
+                    return None;
+                }
+                PremarketRange { date, high, low }
+            }
+            _ => PremarketRange {
+                date,
+                high: candle.high,
+                low: candle.low,
+            },
+        };
+        self.range = Some(range);
+        Some(range)
+    }
+}
 
-        result.push(Candle {
-            timestamp,
-            instrument_id: group.first().unwrap().instrument_id,
-            symbol: group.first().unwrap().symbol.clone(),
-            open,
-            high,
-            low,
-            close,
-            volume,
-        });
+/// An update from [`PmzTracker::update()`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PmzEvent {
+    /// The PMH or PML changed, so the provisional PMZ values may have too.
+    Provisional(PmzResult),
+    /// The pre-market window ended and the PMZ values are final for the day.
+    Locked(PmzResult),
+}
+
+/// Tracks PMZ values intraday from a live stream of 1-minute candles.
+///
+/// The tracker is seeded with the previous day's LIS, e.g. with
+/// [`PmzTracker::seed()`], then updates the PMH, PML, and provisional PMZ levels as
+/// pre-market candles arrive. The levels lock in once a candle reaches the end of the
+/// pre-market window at 9:25 ET by default, after which further candles are ignored.
+#[derive(Debug, Clone)]
+pub struct PmzTracker {
+    date: NaiveDate,
+    prev_day_lis: Option<f64>,
+    end: NaiveTime,
+    zone_width_factor: f64,
+    zone_offset_factor: f64,
+    premarket: PremarketTracker,
+    last_close: Option<f64>,
+    locked: Option<PmzResult>,
+}
+
+impl PmzTracker {
+    /// Creates a tracker for the trading day `date` with the pre-market window in
+    /// `config` and the previous day's LIS.
+    pub fn new(config: &PmzConfig, date: NaiveDate, prev_day_lis: Option<f64>) -> Self {
+        Self {
+            date,
+            prev_day_lis,
+            end: config.end,
+            zone_width_factor: config.zone_width_factor,
+            zone_offset_factor: config.zone_offset_factor,
+            premarket: PremarketTracker::new(config),
+            last_close: None,
+            locked: None,
+        }
     }
 
-    result.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
-    result
+    /// Creates a tracker for the trading day on or before `date` with the previous
+    /// day's LIS fetched from `source`, e.g. the historical client.
+    ///
+    /// # Errors
+    /// This function returns an error when the LIS request fails.
+    pub async fn seed(
+        source: &mut impl MarketDataSource,
+        config: &PmzConfig,
+        date: NaiveDate,
+    ) -> Result<Self> {
+        let calendar = UsEquityCalendar;
+        let date = calendar.trading_day_on_or_before(date);
+        let prev_session = calendar
+            .session(calendar.previous_trading_day(date))
+            .ok_or_else(|| PmzError::InvalidDate(format!("no trading day before {date}")))?;
+        let (lis_start, lis_end) = lis_window(&prev_session);
+        let request = DataRequest {
+            dataset: config.dataset.to_string(),
+            symbol: config.continuous_symbol()?.to_owned(),
+            stype_in: SType::Continuous,
+            start: ny_local(prev_session.date, lis_start)?.with_timezone(&Utc),
+            end: ny_local(prev_session.date, lis_end)?.with_timezone(&Utc),
+        };
+        let lis_candles = aggregate_candles(&source.get_candles(&request).await?, 5);
+        Ok(Self::new(
+            config,
+            date,
+            lis_candles.first().map(|c| c.close),
+        ))
+    }
+
+    /// Returns the trading day being tracked.
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Returns the pre-market high so far.
+    pub fn current_pmh(&self) -> Option<f64> {
+        self.premarket.range().map(|range| range.high)
+    }
+
+    /// Returns the pre-market low so far.
+    pub fn current_pml(&self) -> Option<f64> {
+        self.premarket.range().map(|range| range.low)
+    }
+
+    /// Returns the PMZ values calculated from the candles so far, using the latest
+    /// close in place of the 9:25 close. Once locked, this is the final result.
+    pub fn provisional(&self) -> PmzResult {
+        if let Some(locked) = &self.locked {
+            return locked.clone();
+        }
+        PmzResult::from_inputs(
+            self.date,
+            self.current_pmh(),
+            self.current_pml(),
+            self.prev_day_lis,
+            self.last_close,
+        )
+        .with_zone_factors(self.zone_width_factor, self.zone_offset_factor)
+        .with_pre_market_window(self.premarket.start, self.end)
+    }
+
+    /// Returns the provisional PMZ high.
+    pub fn pmz_high(&self) -> Option<f64> {
+        self.provisional().pmz_high
+    }
+
+    /// Returns the provisional PMZ low.
+    pub fn pmz_low(&self) -> Option<f64> {
+        self.provisional().pmz_low
+    }
+
+    /// Returns the final PMZ values once they've locked in.
+    pub fn locked(&self) -> Option<&PmzResult> {
+        self.locked.as_ref()
+    }
+
+    /// Updates the tracker with a 1-minute `candle`. Returns [`PmzEvent::Locked`] once
+    /// when the candle reaches the end of the pre-market window, and
+    /// [`PmzEvent::Provisional`] when the PMH or PML changes before then. Candles from
+    /// other days are ignored.
+    pub fn update(&mut self, candle: &Candle) -> Option<PmzEvent> {
+        if self.locked.is_some() {
+            return None;
+        }
+        let local = candle.timestamp.with_timezone(&New_York);
+        if local.date_naive() != self.date {
+            return None;
+        }
+        if local.time() >= self.end {
+            return Some(self.finalize());
+        }
+        let changed = self.premarket.update(candle).is_some();
+        if self.premarket.range().is_some() {
+            self.last_close = Some(candle.close);
+        }
+        if local.time() + Duration::minutes(1) >= self.end {
+            Some(self.finalize())
+        } else if changed {
+            Some(PmzEvent::Provisional(self.provisional()))
+        } else {
+            None
+        }
+    }
+
+    /// Locks in the PMZ values from the candles so far, e.g. from a timer at the end of
+    /// the pre-market window when the final candle is missing.
+    pub fn finalize(&mut self) -> PmzEvent {
+        let result = self.provisional();
+        self.locked = Some(result.clone());
+        PmzEvent::Locked(result)
+    }
 }
 
-// Function to check if a given date is a weekend
-fn is_weekend(date: &NaiveDate) -> bool {
-    use chrono::Weekday::*;
-    let weekday = date.weekday();
-    weekday == Sat || weekday == Sun
+// Convert a chrono date to a time date for the Databento API
+pub(crate) fn to_time_date(date: NaiveDate) -> Result<Date> {
+    date.to_time_date()
+        .map_err(|e| PmzError::InvalidDate(e.to_string()))
 }
 
-// Function to get the previous trading day (skipping weekends)
-fn get_previous_trading_day(date: NaiveDate) -> NaiveDate {
-    let mut prev_day = date - Duration::days(1);
-    while is_weekend(&prev_day) {
-        prev_day -= Duration::days(1);
+// Convert a chrono UTC datetime to a time datetime for the Databento API
+pub(crate) fn to_offset_date_time(dt: DateTime<Utc>) -> Result<OffsetDateTime> {
+    dt.to_offset_date_time()
+        .map_err(|e| PmzError::InvalidDate(e.to_string()))
+}
+
+/// Infers the symbology type of `symbol`: continuous contract symbols look like
+/// `ES.c.0` and parent symbols like `ES.FUT`. Anything else is treated as a raw symbol.
+pub fn infer_stype(symbol: &str) -> SType {
+    let parts: Vec<_> = symbol.split('.').collect();
+    match parts.as_slice() {
+        [_, "c" | "n" | "v", rank] if rank.parse::<u32>().is_ok() => SType::Continuous,
+        [_, "FUT" | "OPT"] => SType::Parent,
+        _ => SType::RawSymbol,
     }
-    prev_day
+}
+
+/// Fetches 1-minute OHLCV candles for `symbol` with timestamps in `tz` and
+/// aggregates them into `interval_minutes` candles.
+///
+/// # Errors
+/// This function returns an error when the historical request fails or a record
+/// can't be decoded or has an out-of-range timestamp.
+#[tracing::instrument(skip(client, date_time_range, tz))]
+pub async fn fetch_candles(
+    client: &mut impl HistoricalApi,
+    dataset: &str,
+    symbol: &str,
+    stype_in: SType,
+    date_time_range: impl Into<DateTimeRange>,
+    interval_minutes: u32,
+    tz: Tz,
+) -> Result<Vec<Candle>> {
+    let params = GetRangeParams::builder()
+        .dataset(dataset)
+        .symbols(symbol)
+        .stype_in(stype_in)
+        .schema(Schema::Ohlcv1M)
+        .date_time_range(date_time_range.into())
+        .build();
+    let mut decoder = client.get_range(&params).await?;
+    if decoder.metadata().not_found.iter().any(|s| s == symbol) {
+        return Err(PmzError::SymbologyError(format!(
+            "{} could not be resolved in {}",
+            symbol, dataset
+        )));
+    }
+    // Records are buffered so their prices are converted in batches
+    const BATCH_SIZE: usize = 4096;
+
+    let shared_symbol = Arc::<str>::from(symbol);
+    let mut candles = Vec::new();
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while let Some(record) = decoder.decode_record::<OhlcvMsg>().await? {
+        batch.push(record.clone());
+        if batch.len() == BATCH_SIZE {
+            Candle::extend_from_ohlcv(&mut candles, &batch, |_| shared_symbol.clone(), tz)?;
+            batch.clear();
+        }
+    }
+    Candle::extend_from_ohlcv(&mut candles, &batch, |_| shared_symbol.clone(), tz)?;
+    tracing::debug!(candles = candles.len(), "Decoded one-minute candles");
+    if interval_minutes > 1 {
+        // Parent and all-symbols queries span many instruments
+        #[cfg(feature = "parallel")]
+        let aggregated = par_aggregate_candles(&candles, interval_minutes);
+        #[cfg(not(feature = "parallel"))]
+        let aggregated = aggregate_candles(&candles, interval_minutes);
+        candles = aggregated;
+    }
+    Ok(candles)
+}
+
+// Convert a New York local time to an instant, resolving times around DST transitions
+// to the earlier instant rather than panicking
+fn ny_local(date: NaiveDate, time: NaiveTime) -> Result<DateTime<Tz>> {
+    resolve_local(
+        NaiveDateTime::new(date, time),
+        &New_York,
+        LocalTimePolicy::Earliest,
+    )
+    .map_err(|e| PmzError::InvalidDate(e.to_string()))
+}
+
+// The LIS candle is the last five-minute candle of a session
+fn lis_window(session: &TradingSession) -> (NaiveTime, NaiveTime) {
+    (session.close - Duration::minutes(5), session.close)
+}
+
+/// The price the gap into the pre-market is measured against. Practitioners define the
+/// gap differently, which can change the gap direction and so the PMZ levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "snake_case"))]
+pub enum GapReference {
+    /// The close of the previous day's LIS candle, the last five minutes of the session.
+    #[default]
+    LisClose,
+    /// The previous day's settlement price from the Statistics schema, preferring the
+    /// final settlement to a preliminary one.
+    Settlement,
+    /// The close of the last 1-minute candle of the previous day's regular trading
+    /// hours. This only differs from the LIS close when the candles at the end of the
+    /// session are missing.
+    RthClose,
+}
+
+/// Settings for the PMZ calculation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct PmzConfig {
+    /// The dataset to query. Parsing the dataset code rejects unknown datasets, e.g.
+    /// a misspelled `GLBX.MPD3`.
+    pub dataset: Dataset,
+    /// The continuous contract symbol to calculate PMZ values for, which must be a
+    /// single symbol like `ES.c.0`. See [`continuous_symbol()`](Self::continuous_symbol).
+    pub symbol: Symbols,
+    /// The start of the pre-market window in New York time (inclusive).
+    pub start: NaiveTime,
+    /// The end of the pre-market window in New York time (exclusive). The close of the
+    /// candle before this time determines the gap direction.
+    pub end: NaiveTime,
+    /// Whether to return the LIS and pre-market candles in [`PmzResult::candles`].
+    /// Defaults to `false`.
+    pub include_candles: bool,
+    /// Whether to request the dataset's publishers and schemas and report them in a
+    /// [`Diagnostic::Dataset`]. Defaults to `false`, which avoids two metadata requests.
+    pub dataset_info: bool,
+    /// The width of the PMZ as a fraction of the pre-market range. Defaults to 0.2.
+    pub zone_width_factor: f64,
+    /// The distance of the far edge of the PMZ from the PMH on a gap up, or the PML on
+    /// a gap down, as a fraction of the pre-market range. Defaults to 0.4.
+    pub zone_offset_factor: f64,
+    /// The price the gap is measured against. Defaults to [`GapReference::LisClose`].
+    pub gap_reference: GapReference,
+}
+
+const DEFAULT_ZONE_WIDTH_FACTOR: f64 = 0.2;
+const DEFAULT_ZONE_OFFSET_FACTOR: f64 = 0.4;
+
+impl Default for PmzConfig {
+    fn default() -> Self {
+        Self {
+            dataset: Dataset::GlbxMdp3,      // CME Globex MDP3
+            symbol: Symbols::from("ES.c.0"), // Continuous front-month ES contract
+            start: NaiveTime::from_hms_opt(7, 25, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(9, 25, 0).unwrap(),
+            include_candles: false,
+            dataset_info: false,
+            zone_width_factor: DEFAULT_ZONE_WIDTH_FACTOR,
+            zone_offset_factor: DEFAULT_ZONE_OFFSET_FACTOR,
+            gap_reference: GapReference::LisClose,
+        }
+    }
+}
+
+impl PmzConfig {
+    /// Returns [`symbol`](Self::symbol) after checking it's a single continuous
+    /// contract symbol of the form `ROOT.RULE.RANK`, e.g. `ES.c.0` or `NQ.v.1`.
+    ///
+    /// # Errors
+    /// This function returns an error when `symbol` isn't a single continuous contract
+    /// symbol.
+    pub fn continuous_symbol(&self) -> Result<&str> {
+        let Symbols::Symbols(symbols) = &self.symbol else {
+            return Err(PmzError::SymbologyError(format!(
+                "expected a continuous contract symbol, got {}",
+                self.symbol
+            )));
+        };
+        match symbols.as_slice() {
+            [symbol] if is_continuous_symbol(symbol) => Ok(symbol),
+            [symbol] => Err(PmzError::SymbologyError(format!(
+                "{symbol} isn't a continuous contract symbol like ES.c.0"
+            ))),
+            _ => Err(PmzError::SymbologyError(format!(
+                "expected a single symbol, got {}",
+                self.symbol
+            ))),
+        }
+    }
+}
+
+// Whether `symbol` has the form `ROOT.RULE.RANK`, e.g. `ES.c.0`
+fn is_continuous_symbol(symbol: &str) -> bool {
+    let mut parts = symbol.split('.');
+    let (Some(root), Some(rule), Some(rank), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    !root.is_empty()
+        && root.chars().all(|c| c.is_ascii_alphanumeric())
+        && matches!(rule, "c" | "v" | "n")
+        && rank.parse::<u32>().is_ok()
+}
+
+/// Calculate PMZ values for a given date with the default [`PmzConfig`].
+///
+/// See [`calculate_pmz_with_client()`] for details.
+///
+/// # Errors
+/// This function returns an error when the client can't be built or the calculation
+/// fails.
+pub async fn calculate_pmz(
+    api_key: &str,
+    date_opt: Option<NaiveDate>,
+    diagnostics: impl FnMut(Diagnostic),
+) -> Result<PmzResult> {
+    let client = ClientBuilder::new().key(api_key)?.build()?;
+    calculate_pmz_with_client(client, &PmzConfig::default(), date_opt, diagnostics).await
+}
+
+/// Calculate PMZ values for a given date using the API key, retry policy, and PMZ
+/// settings from `config`.
+///
+/// See [`calculate_pmz_with_client()`] for details.
+///
+/// # Errors
+/// This function returns an error when the client can't be built from `config` or
+/// the calculation fails.
+#[cfg(feature = "config")]
+pub async fn calculate_pmz_from_config(
+    config: &crate::config::Config,
+    date_opt: Option<NaiveDate>,
+    diagnostics: impl FnMut(Diagnostic),
+) -> Result<PmzResult> {
+    let client = ClientBuilder::new().config(config)?.build()?;
+    calculate_pmz_with_client(client, &config.pmz, date_opt, diagnostics).await
 }
 
 /// Calculate PMZ values for a given date
-/// 
+///
 /// This function handles:
 /// 1. Retrieving data from Databento for both the previous and current trading day
 /// 2. Calculating the previous day's LIS (Line in Sand)
-/// 3. Calculating PMH and PML from 7:25-9:25 EST on the current day
+/// 3. Calculating PMH and PML from the pre-market window (7:25-9:25 EST by default) on
+///    the current day
 /// 4. Determining gap direction using 9:25 close price
 /// 5. Calculating PMZ High, PMZ Low, and Risk
 ///
-/// Returns a PmzResult structure with all calculated values
-pub async fn calculate_pmz(
-    api_key: &str,
+/// Returns a PmzResult structure with all values that could be calculated. Use
+/// [`PmzResult::ensure_complete()`] to treat missing values as an error.
+///
+/// Progress and diagnostic information is passed to `diagnostics` as it occurs, e.g.
+/// `|d| diags.push(d)` to collect it or `|_| {}` to ignore it, and logged as
+/// [`tracing`] events within a `calculate_pmz` span.
+///
+/// # Errors
+/// This function returns an error when a historical request fails, the symbol can't
+/// be resolved, or there's no data for the pre-market window.
+#[tracing::instrument(
+    name = "calculate_pmz",
+    skip_all,
+    fields(dataset = %config.dataset, symbol = %config.symbol, date = ?date_opt)
+)]
+pub async fn calculate_pmz_with_client(
+    mut client: impl HistoricalApi,
+    config: &PmzConfig,
     date_opt: Option<NaiveDate>,
-    verbose: bool
+    mut diagnostics: impl FnMut(Diagnostic),
 ) -> Result<PmzResult> {
     // --- Configuration ---
-    let dataset = "GLBX.MDP3"; // CME Globex MDP3
-    let symbol = "ES.c.0"; // Continuous front-month ES contract
+    let dataset = config.dataset.as_str();
+    let symbol = config.continuous_symbol()?;
     let schema = Schema::Ohlcv1M; // 1-minute candles
 
     // --- Date and Time Setup ---
-    let today_naive = Utc::now().date_naive(); // Today's date in UTC
-    // Use provided date or default to today (adjusting for weekends)
-    let mut current_trading_day_naive = match date_opt {
-        Some(date) => date,
-        None => today_naive,
-    };
-    
-    // Ensure we're using a weekday
-    while is_weekend(&current_trading_day_naive) {
-        current_trading_day_naive = current_trading_day_naive - Duration::days(1);
-    }
-    let previous_trading_day_naive = get_previous_trading_day(current_trading_day_naive);
+    // Today's date in UTC
+    let today_naive = Utc::now().date_naive();
+    // Use provided date or default to today (adjusting for weekends and holidays)
+    let calendar = UsEquityCalendar;
+    let current_trading_day_naive =
+        calendar.trading_day_on_or_before(date_opt.unwrap_or(today_naive));
+    let previous_trading_day_naive = calendar.previous_trading_day(current_trading_day_naive);
+    let (current_session, previous_session) = calendar
+        .session(current_trading_day_naive)
+        .zip(calendar.session(previous_trading_day_naive))
+        .ok_or_else(|| {
+            PmzError::InvalidDate(format!("{current_trading_day_naive} is not a trading day"))
+        })?;
 
     // Define the time range in New York time
-    let tz = New_York;
-    let pmz_start_time = NaiveTime::from_hms_opt(7, 25, 0).unwrap(); // PMZ Start (inclusive)
-    let pmz_end_time = NaiveTime::from_hms_opt(9, 25, 0).unwrap();   // PMZ End (exclusive)
-    let lis_time = NaiveTime::from_hms_opt(15, 55, 0).unwrap(); // LIS candle start (ends 16:00)
-    let lis_end_time = NaiveTime::from_hms_opt(16, 0, 0).unwrap(); // LIS candle end
+    // PMZ End (exclusive)
+    let pmz_end_time = config.end;
+    // LIS candle start and end, shifted to the early close on half days
+    let (lis_time, lis_end_time) = lis_window(&previous_session);
 
-    // Define UTC query range: Previous day LIS time to Current day LIS time + buffer
-    let query_start_dt_naive = NaiveDateTime::new(previous_trading_day_naive, NaiveTime::from_hms_opt(15, 50, 0).unwrap());
-    let query_end_dt_naive = NaiveDateTime::new(current_trading_day_naive, NaiveTime::from_hms_opt(16, 5, 0).unwrap());
-
-    let query_start_dt_utc = tz.from_local_datetime(&query_start_dt_naive).unwrap().with_timezone(&Utc);
-    let query_end_dt_utc = tz.from_local_datetime(&query_end_dt_naive).unwrap().with_timezone(&Utc);
+    // Define UTC query range: Previous day LIS time to Current day close + buffer
+    let query_start_dt_utc =
+        ny_local(previous_trading_day_naive, lis_time - Duration::minutes(5))?.with_timezone(&Utc);
+    let query_end_dt_utc = ny_local(
+        current_trading_day_naive,
+        current_session.close + Duration::minutes(5),
+    )?
+    .with_timezone(&Utc);
 
     // Convert query times for databento API
-    let query_start_dt_offset = OffsetDateTime::from_unix_timestamp_nanos(query_start_dt_utc.timestamp_nanos_opt().unwrap_or(0).into())?;
-    let query_end_dt_offset = OffsetDateTime::from_unix_timestamp_nanos(query_end_dt_utc.timestamp_nanos_opt().unwrap_or(0).into())?;
-
-    if verbose {
-        println!(
-            "Calculating PMZ for {} (Previous Trading Day: {})",
-            current_trading_day_naive.format("%Y-%m-%d"),
-            previous_trading_day_naive.format("%Y-%m-%d")
+    let query_start_dt_offset = to_offset_date_time(query_start_dt_utc)?;
+    let query_end_dt_offset = to_offset_date_time(query_end_dt_utc)?;
+
+    report(
+        &mut diagnostics,
+        Diagnostic::Started {
+            date: current_trading_day_naive,
+            previous_date: previous_trading_day_naive,
+        },
+    );
+    if previous_session.early_close {
+        report(
+            &mut diagnostics,
+            Diagnostic::EarlyClose {
+                close: previous_session.close,
+                lis_start: lis_time,
+                lis_end: lis_end_time,
+            },
         );
-        println!("Querying 1-min data from {} to {}", query_start_dt_utc, query_end_dt_utc);
     }
+    report(
+        &mut diagnostics,
+        Diagnostic::Querying {
+            start: query_start_dt_utc,
+            end: query_end_dt_utc,
+        },
+    );
 
-    // --- Databento Client ---
-    let mut client = ClientBuilder::new()
-        .key(api_key)?
-        .build()?;
+    // --- Check Data Availability ---
+    let previous_trading_day = to_time_date(previous_trading_day_naive)?;
+    let current_trading_day = to_time_date(current_trading_day_naive)?;
+    let end = current_trading_day.next_day().ok_or_else(|| {
+        PmzError::InvalidDate(format!("no day after {current_trading_day_naive}"))
+    })?;
+    let availability = client
+        .get_data_availability(dataset, DateRange::from((previous_trading_day, end)))
+        .await?;
+    if config.dataset_info {
+        // Only for diagnostics, so a failure doesn't fail the calculation
+        match client
+            .get_dataset_info(dataset, availability.range.clone())
+            .await
+        {
+            Ok(info) => report(&mut diagnostics, Diagnostic::Dataset(info)),
+            Err(err) => tracing::warn!(dataset, %err, "Failed to get dataset info"),
+        }
+    }
+    for day in [previous_trading_day, current_trading_day] {
+        if availability.condition_on(day) == Some(DatasetCondition::Missing) {
+            return Err(PmzError::NoData(format!(
+                "{} data is missing for {}",
+                dataset, day
+            )));
+        }
+    }
+    for day in availability.degraded_dates() {
+        tracing::warn!(dataset, %day, "Data is degraded and may be incomplete");
+        if let Ok(date) = day.to_chrono() {
+            report(
+                &mut diagnostics,
+                Diagnostic::DegradedData {
+                    dataset: dataset.to_owned(),
+                    date,
+                },
+            );
+        }
+    }
+    let pmz_end_utc = ny_local(current_trading_day_naive, pmz_end_time)?.with_timezone(&Utc);
+    if availability.range.end < to_offset_date_time(pmz_end_utc)? {
+        return Err(PmzError::NoData(format!(
+            "{} data is only available through {}, before the end of the PMZ window",
+            dataset, availability.range.end
+        )));
+    }
 
     // --- Fetch Data ---
-    let date_time_range = DateTimeRange::from((query_start_dt_offset, query_end_dt_offset));
+    let requested_range = DateTimeRange::from((query_start_dt_offset, query_end_dt_offset));
+    // Clamp the end to the available data, e.g. when calculating PMZ intraday
+    let date_time_range = availability.clamp(&requested_range).ok_or_else(|| {
+        PmzError::NoData(format!(
+            "query range is outside the available range of {}",
+            dataset
+        ))
+    })?;
+    if date_time_range != requested_range {
+        let end = availability.range.end.to_chrono()?;
+        report(&mut diagnostics, Diagnostic::Clamped { end });
+    }
     let params = GetRangeParams::builder()
         .dataset(dataset.to_string())
         .symbols(vec![symbol.to_string()])
@@ -235,158 +1928,804 @@ pub async fn calculate_pmz(
         .date_time_range(date_time_range)
         .build();
 
-    let mut data_decoder = client.timeseries().get_range(&params).await?;
+    let mut data_decoder = client.get_range(&params).await?;
+    if data_decoder
+        .metadata()
+        .not_found
+        .iter()
+        .any(|s| s == symbol)
+    {
+        return Err(PmzError::SymbologyError(format!(
+            "{} could not be resolved in {}",
+            symbol, dataset
+        )));
+    }
 
     // --- Process 1-min Candles ---
     let mut all_one_min_candles: Vec<Candle> = Vec::new();
     let mut record_count = 0;
 
+    let shared_symbol = Arc::<str>::from(symbol);
     while let Some(record) = data_decoder.decode_record::<OhlcvMsg>().await? {
-         record_count += 1;
-         let candle = Candle::new(&record, &symbol);
-         all_one_min_candles.push(candle);
+        record_count += 1;
+        let candle = Candle::try_with_symbol(record, shared_symbol.clone(), DEFAULT_CANDLE_TZ)?;
+        all_one_min_candles.push(candle);
     }
+    drop(data_decoder);
 
-    if verbose {
-        println!("Retrieved {} one-minute records in query range.", record_count);
+    report(
+        &mut diagnostics,
+        Diagnostic::CandlesRetrieved {
+            count: record_count,
+        },
+    );
+    if record_count == 0 {
+        return Err(PmzError::NoData(format!(
+            "no {} records for {} between {} and {}",
+            schema, symbol, query_start_dt_utc, query_end_dt_utc
+        )));
     }
 
+    let settlement = match config.gap_reference {
+        GapReference::Settlement => crate::statistics::get_settlement(
+            &mut client,
+            dataset,
+            symbol,
+            SType::Continuous,
+            previous_trading_day_naive,
+        )
+        .await?
+        .map(|s| s.price),
+        _ => None,
+    };
+
+    pmz_from_candles(
+        &CandleSeries::new(all_one_min_candles),
+        settlement,
+        config,
+        current_trading_day_naive,
+        &mut diagnostics,
+    )
+}
+
+/// Calculate PMZ values for a given date from any [`MarketDataSource`], such as a
+/// [`Replay`](crate::replay::Replay) of a recorded session or a mock in tests.
+///
+/// Unlike [`calculate_pmz_with_client()`], the dataset's availability isn't checked
+/// before requesting candles.
+///
+/// # Errors
+/// This function returns an error when a request to `source` fails or there's no data
+/// for the pre-market window.
+#[tracing::instrument(
+    name = "calculate_pmz",
+    skip_all,
+    fields(dataset = %config.dataset, symbol = %config.symbol, date = ?date_opt)
+)]
+pub async fn calculate_pmz_from_source(
+    source: &mut impl MarketDataSource,
+    config: &PmzConfig,
+    date_opt: Option<NaiveDate>,
+    mut diagnostics: impl FnMut(Diagnostic),
+) -> Result<PmzResult> {
+    let calendar = UsEquityCalendar;
+    let current_trading_day_naive =
+        calendar.trading_day_on_or_before(date_opt.unwrap_or_else(|| Utc::now().date_naive()));
+    let (current_session, previous_session) = calendar
+        .session(current_trading_day_naive)
+        .zip(calendar.session(calendar.previous_trading_day(current_trading_day_naive)))
+        .ok_or_else(|| {
+            PmzError::InvalidDate(format!("{current_trading_day_naive} is not a trading day"))
+        })?;
+    let (lis_time, _) = lis_window(&previous_session);
+    let request = DataRequest {
+        dataset: config.dataset.to_string(),
+        symbol: config.continuous_symbol()?.to_owned(),
+        stype_in: SType::Continuous,
+        start: ny_local(previous_session.date, lis_time - Duration::minutes(5))?
+            .with_timezone(&Utc),
+        end: ny_local(
+            current_trading_day_naive,
+            current_session.close + Duration::minutes(5),
+        )?
+        .with_timezone(&Utc),
+    };
+    let candles = source.get_candles(&request).await?;
+    let settlement = match config.gap_reference {
+        GapReference::Settlement => source
+            .get_settlement(
+                &request.dataset,
+                &request.symbol,
+                request.stype_in,
+                previous_session.date,
+            )
+            .await?
+            .map(|s| s.price),
+        _ => None,
+    };
+    report(
+        &mut diagnostics,
+        Diagnostic::CandlesRetrieved {
+            count: candles.len(),
+        },
+    );
+    if candles.is_empty() {
+        return Err(PmzError::NoData(format!(
+            "no candles for {} between {} and {}",
+            request.symbol, request.start, request.end
+        )));
+    }
+    pmz_from_candles(
+        &CandleSeries::new(candles),
+        settlement,
+        config,
+        current_trading_day_naive,
+        &mut diagnostics,
+    )
+}
+
+// Calculates PMZ values for the trading day `current_trading_day_naive` from 1-minute
+// candles covering the previous day's LIS candle and the pre-market window, and the
+// previous day's settlement when it's the configured gap reference
+#[tracing::instrument(level = "debug", skip_all, fields(date = %current_trading_day_naive))]
+fn pmz_from_candles(
+    candles: &CandleSeries,
+    settlement: Option<f64>,
+    config: &PmzConfig,
+    current_trading_day_naive: NaiveDate,
+    diagnostics: &mut impl FnMut(Diagnostic),
+) -> Result<PmzResult> {
+    let calendar = UsEquityCalendar;
+    let previous_trading_day_naive = calendar.previous_trading_day(current_trading_day_naive);
+    let (current_session, previous_session) = calendar
+        .session(current_trading_day_naive)
+        .zip(calendar.session(previous_trading_day_naive))
+        .ok_or_else(|| {
+            PmzError::InvalidDate(format!("{current_trading_day_naive} is not a trading day"))
+        })?;
+    let pmz_start_time = config.start;
+    let pmz_end_time = config.end;
+    let (lis_time, lis_end_time) = lis_window(&previous_session);
+
     // --- Calculate Previous Day LIS ---
-    let prev_lis_start_est = tz.from_local_datetime(&NaiveDateTime::new(previous_trading_day_naive, lis_time)).unwrap();
-    let prev_lis_end_est = tz.from_local_datetime(&NaiveDateTime::new(previous_trading_day_naive, lis_end_time)).unwrap();
-    let prev_lis_one_min: Vec<Candle> = all_one_min_candles
-        .iter()
-        .filter(|c| c.timestamp >= prev_lis_start_est && c.timestamp < prev_lis_end_est)
-        .cloned()
-        .collect();
-    let prev_lis_five_min = aggregate_candles(&prev_lis_one_min, 5);
+    let prev_lis_start_est = ny_local(previous_trading_day_naive, lis_time)?;
+    let prev_lis_end_est = ny_local(previous_trading_day_naive, lis_end_time)?;
+    let prev_lis_one_min = candles.slice(prev_lis_start_est..prev_lis_end_est);
+    let prev_lis_five_min = aggregate_candles(prev_lis_one_min, 5);
     let prev_day_lis: Option<f64> = prev_lis_five_min.first().map(|c| c.close);
 
+    // --- Select Gap Reference ---
+    let gap_reference = match config.gap_reference {
+        GapReference::LisClose => prev_day_lis,
+        GapReference::Settlement => settlement,
+        GapReference::RthClose => {
+            let prev_open_est = ny_local(previous_trading_day_naive, previous_session.open)?;
+            candles
+                .slice(prev_open_est..prev_lis_end_est)
+                .last()
+                .map(|c| c.close)
+        }
+    };
+
     // --- Filter & Aggregate PMZ Candles (Current Day 7:25 - 9:25 EST) ---
-    let pmz_filter_start_est = tz.from_local_datetime(&NaiveDateTime::new(current_trading_day_naive, pmz_start_time)).unwrap();
-    let pmz_filter_end_est = tz.from_local_datetime(&NaiveDateTime::new(current_trading_day_naive, pmz_end_time)).unwrap();
-    let pmz_one_min_candles: Vec<Candle> = all_one_min_candles
-        .iter()
-        .filter(|c| c.timestamp >= pmz_filter_start_est && c.timestamp < pmz_filter_end_est)
-        .cloned()
-        .collect();
-    
-    if verbose {
-        println!("Found {} one-minute candles within PMZ ({} - {} EST).", 
-            pmz_one_min_candles.len(), pmz_start_time.format("%H:%M:%S"), pmz_end_time.format("%H:%M:%S"));
-    }
-    
-    let pmz_five_min_candles = aggregate_candles(&pmz_one_min_candles, 5);
-    
-    if verbose {
-        println!("Aggregated PMZ into {} five-minute candles.", pmz_five_min_candles.len());
+    let pmz_filter_start_est = ny_local(current_trading_day_naive, pmz_start_time)?;
+    let pmz_filter_end_est = ny_local(current_trading_day_naive, pmz_end_time)?;
+    let pmz_one_min_candles = candles.slice(pmz_filter_start_est..pmz_filter_end_est);
+
+    report(
+        diagnostics,
+        Diagnostic::PreMarketCandles {
+            count: pmz_one_min_candles.len(),
+            start: pmz_start_time,
+            end: pmz_end_time,
+        },
+    );
+
+    let quality = check_candles_within(
+        pmz_one_min_candles,
+        Duration::minutes(1),
+        pmz_filter_start_est,
+        pmz_filter_end_est,
+    );
+    if !quality.gaps.is_empty() {
+        tracing::warn!(
+            date = %current_trading_day_naive,
+            missing = quality.missing_intervals(),
+            gaps = quality.gaps.len(),
+            "Pre-market candles are incomplete, PMH and PML may be inaccurate"
+        );
+        for gap in &quality.gaps {
+            report(diagnostics, Diagnostic::MissingCandles(*gap));
+        }
     }
 
+    let pmz_five_min_candles = aggregate_candles(pmz_one_min_candles, 5);
+
+    report(
+        diagnostics,
+        Diagnostic::Aggregated {
+            count: pmz_five_min_candles.len(),
+        },
+    );
+
     // --- Get 9:25 AM Close Price (Estimate for Market Open) ---
     let current_day_925_close: Option<f64> = pmz_five_min_candles.last().map(|c| c.close);
 
-    // --- Determine Gap Direction (Using 9:25 AM Close) ---
-    let gap_up: Option<bool> = match (current_day_925_close, prev_day_lis) {
-        (Some(close_925), Some(lis)) => Some(close_925 >= lis),
-        _ => None, // Cannot determine gap if 9:25 close or prev LIS is missing
-    };
-    
     // --- Calculate PMH and PML ---
-    let pmh: Option<f64> = pmz_five_min_candles.iter().map(|c| c.high).fold(None, |max_h, h| Some(max_h.map_or(h, |current_max| current_max.max(h))));
-    let pml: Option<f64> = pmz_five_min_candles.iter().map(|c| c.low).fold(None, |min_l, l| Some(min_l.map_or(l, |current_min| current_min.min(l))));
+    let pmh = candles.highest_high(pmz_filter_start_est..pmz_filter_end_est);
+    let pml = candles.lowest_low(pmz_filter_start_est..pmz_filter_end_est);
+
+    // --- Create result structure ---
+    let mut result = PmzResult::from_inputs(
+        current_trading_day_naive,
+        pmh,
+        pml,
+        prev_day_lis,
+        current_day_925_close,
+    )
+    .with_gap_reference(gap_reference)
+    .with_zone_factors(config.zone_width_factor, config.zone_offset_factor)
+    .with_sessions(current_session, previous_session)
+    .with_pre_market_window(pmz_start_time, pmz_end_time);
+    if config.include_candles {
+        result = result.with_candles(PmzCandles {
+            lis: prev_lis_five_min,
+            pre_market: pmz_five_min_candles,
+        });
+    }
+    if !result.is_complete() {
+        report(
+            diagnostics,
+            Diagnostic::Incomplete {
+                missing: result.missing.clone(),
+            },
+        );
+    }
+    Ok(result)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, eastern};
 
-    // --- Calculate Risk Range ---
-    let risk_range: Option<f64> = pmh.zip(pml).map(|(h, l)| h - l);
+    const NANOS_PER_MIN: u64 = 60_000_000_000;
+    // 2025-04-21 13:30:00 UTC
+    const START_NANOS: u64 = 1_745_242_200_000_000_000;
 
-    // --- Calculate PMZ High/Low based on Gap ---
-    let (pmz_high, pmz_low) = match (gap_up, pmh, pml, risk_range) {
-        (Some(true), Some(h), _, Some(r)) => (Some(h - r * 0.2), Some(h - r * 0.4)), // Gap Up
-        (Some(false), _, Some(l), Some(r)) => (Some(l + r * 0.4), Some(l + r * 0.2)), // Gap Down
-        _ => (None, None), // Cannot calculate if gap or PMH/PML/Risk is missing
-    };
+    fn ohlcv(minute: u64, open: i64, high: i64, low: i64, close: i64) -> OhlcvMsg {
+        OhlcvMsg {
+            open,
+            high,
+            low,
+            close,
+            ..test_util::ohlcv(1, START_NANOS + minute * NANOS_PER_MIN)
+        }
+    }
 
-    // --- Calculate Risk (PMZ High - PMZ Low) ---
-    let pmz_risk = pmz_high.zip(pmz_low).map(|(h, l)| h - l);
+    fn fixture() -> Vec<OhlcvMsg> {
+        vec![
+            ohlcv(
+                0,
+                5_300_100_000_000,
+                5_301_000_000_000,
+                5_299_750_000_000,
+                5_300_250_000_000,
+            ),
+            ohlcv(
+                1,
+                5_300_250_000_000,
+                5_302_500_000_000,
+                5_300_000_000_000,
+                5_302_000_000_000,
+            ),
+            ohlcv(
+                4,
+                5_302_000_000_000,
+                5_302_250_000_000,
+                5_298_500_000_000,
+                5_299_000_000_000,
+            ),
+            ohlcv(
+                5,
+                5_299_000_000_000,
+                5_299_500_000_000,
+                5_297_000_000_000,
+                5_297_250_000_000,
+            ),
+        ]
+    }
 
-    // --- Create result structure ---
-    match (pmh, pml, prev_day_lis, gap_up, pmz_high, pmz_low, pmz_risk) {
-        (Some(pmh_val), Some(pml_val), Some(lis_val), Some(is_gap_up), Some(high), Some(low), Some(risk)) => {
-            Ok(PmzResult {
-                date: current_trading_day_naive,
-                pmh: pmh_val,
-                pml: pml_val,
-                prev_day_lis: lis_val,
-                is_gap_up,
-                pmz_high: high,
-                pmz_low: low,
-                risk,
-            })
-        },
-        _ => {
-            // If we can't calculate everything, display diagnostic information
-            if verbose {
-                println!("Failed to calculate complete PMZ values. Debug info:");
-                println!("PMH: {:?}", pmh);
-                println!("PML: {:?}", pml);
-                println!("Previous Day LIS: {:?}", prev_day_lis);
-                println!("Gap Direction: {:?}", gap_up);
-                println!("PMZ High: {:?}", pmz_high);
-                println!("PMZ Low: {:?}", pmz_low);
-                println!("Risk: {:?}", pmz_risk);
+    #[test]
+    fn test_extend_from_fixed() {
+        const LIMIT: i64 = 1 << 51;
+        let mut fixed: Vec<i64> = (0..20)
+            .map(|i| 5_300_000_000_000 + i * 250_000_000)
+            .collect();
+        fixed.extend([
+            0,
+            -1,
+            -5_300_000_000_000,
+            LIMIT - 1,
+            -LIMIT,
+            LIMIT,
+            -LIMIT - 1,
+            i64::MIN,
+        ]);
+        fixed.extend((0..8).map(|i| -i * 1_000_000_007));
+        fixed.push(dbn::UNDEF_PRICE);
+        for len in 0..=fixed.len() {
+            let mut converted = vec![1.0];
+            f64::extend_from_fixed(&mut converted, &fixed[..len]);
+            assert_eq!(converted.len(), len + 1);
+            for (converted, &px) in converted[1..].iter().zip(&fixed) {
+                assert_eq!(converted.to_bits(), f64::from_fixed(px).to_bits(), "{px}");
             }
-            
-            // Try to fetch metadata if data is insufficient
-            if verbose && (pmh.is_none() || pml.is_none()) {
-                println!("Attempting to fetch metadata for dataset {}...", dataset);
-
-                // Correct metadata calls: Pass dataset directly if no Params struct exists
-                match client.metadata().list_schemas(dataset).await { // Pass dataset directly
-                    Ok(schemas) => println!("Available schemas: {:?}", schemas),
-                    Err(e) => eprintln!("Failed to fetch schemas: {}", e),
-                }
+        }
+    }
 
-                // ListFieldsParams builder only takes encoding and schema
-                let fields_params = ListFieldsParams::builder()
-                    .encoding(Encoding::Dbn) // Added encoding (assuming DBN)
-                    .schema(schema) // Changed to required schema, not Option<Schema>
-                    .build();
-                match client.metadata().list_fields(&fields_params).await {
-                    Ok(fields) => println!("Fields for schema {:?}: {:?}", schema, fields),
-                    Err(e) => eprintln!("Failed to fetch fields for schema {:?}: {}", schema, e),
-                }
+    #[test]
+    fn test_candles_extend_from_ohlcv() {
+        let records = fixture();
+        let symbol: Arc<str> = Arc::from("ES.c.0");
+        let mut candles: Vec<Candle> = Vec::new();
+        Candle::extend_from_ohlcv(&mut candles, &records, |_| symbol.clone(), Tz::UTC).unwrap();
+        let expected: Vec<Candle> = records
+            .iter()
+            .map(|r| Candle::try_with_symbol(r, symbol.clone(), Tz::UTC).unwrap())
+            .collect();
+        assert_eq!(candles, expected);
 
-                // Pass dataset directly for list_unit_prices
-                match client.metadata().list_unit_prices(dataset).await { // Pass dataset directly
-                    Ok(prices) => println!("Unit prices for dataset {}: {:?}", dataset, prices),
-                    Err(e) => eprintln!("Failed to fetch unit prices: {}", e),
-                }
+        let mut bad = records.clone();
+        bad[2].hd.ts_event = u64::MAX;
+        assert!(matches!(
+            Candle::<f64>::extend_from_ohlcv(&mut candles, &bad, |_| symbol.clone(), Tz::UTC),
+            Err(PmzError::InvalidTimestamp(u64::MAX))
+        ));
+        assert_eq!(candles.len(), records.len());
+    }
 
-                // Convert chrono::NaiveDate to time::Date
-                // Convert month() u32 to u8 before TryFrom
-                let month_u8 = current_trading_day_naive.month() as u8;
-                let time_month = time::Month::try_from(month_u8)?;
-
-                let target_date_time = Date::from_calendar_date(
-                    current_trading_day_naive.year(),
-                    time_month, // Use the converted time::Month
-                    current_trading_day_naive.day() as u8,
-                )?;
-
-                // Construct DateRange using From trait
-                let date_range = DateRange::from((target_date_time, target_date_time));
-
-                let resolve_params = ResolveParams::builder()
-                    .dataset(dataset.to_string())
-                    .symbols(vec![symbol.to_string()])
-                    .date_range(date_range) // Pass the constructed range
-                    .build();
-                match client.symbology().resolve(&resolve_params).await {
-                    Ok(resolution) => println!("Symbology resolution for {}: {:?}", symbol, resolution),
-                    Err(e) => eprintln!("Failed to resolve symbology for {}: {}", symbol, e),
-                }
+    #[test]
+    fn test_aggregate_candles_f64() {
+        let candles: Vec<Candle> = fixture()
+            .iter()
+            .map(|r| Candle::try_new(r, "ES.c.0").unwrap())
+            .collect();
+        let agg = aggregate_candles(&candles, 5);
+        assert_eq!(agg.len(), 2);
+        assert!((agg[0].open - 5300.1).abs() < 1e-9);
+        assert_eq!(agg[0].high, 5302.5);
+        assert_eq!(agg[0].low, 5298.5);
+        assert_eq!(agg[0].close, 5299.0);
+        assert_eq!(agg[0].volume, 30);
+        assert_eq!(agg[1].volume, 10);
+        // 13:30 UTC is 9:30 EDT
+        assert_eq!(agg[0].format_timestamp(), "2025-04-21 09:30");
+        assert_eq!(agg[1].format_timestamp(), "2025-04-21 09:35");
+    }
+
+    #[test]
+    fn test_aggregate_candles_per_instrument() {
+        let mut records = fixture();
+        let mut other = ohlcv(
+            2,
+            5_310_000_000_000,
+            5_320_000_000_000,
+            5_305_000_000_000,
+            5_315_000_000_000,
+        );
+        other.hd.instrument_id = 2;
+        records.push(other);
+        let candles: Vec<Candle> = records
+            .iter()
+            .map(|r| Candle::try_new(r, "ES.FUT").unwrap())
+            .collect();
+        let agg = aggregate_candles(&candles, 5);
+        assert_eq!(agg.len(), 3);
+        assert_eq!(agg[0].instrument_id, 1);
+        assert_eq!(agg[0].high, 5302.5);
+        assert_eq!(agg[0].volume, 30);
+        assert_eq!(agg[1].instrument_id, 2);
+        assert_eq!(agg[1].timestamp, agg[0].timestamp);
+        assert_eq!(agg[1].high, 5320.0);
+        assert_eq!(agg[1].volume, 10);
+        assert_eq!(agg[2].instrument_id, 1);
+    }
+
+    #[test]
+    fn test_aggregate_candles_out_of_order() {
+        let candles: Vec<Candle> = fixture()
+            .iter()
+            .map(|r| Candle::try_new(r, "ES.c.0").unwrap())
+            .collect();
+        let expected = aggregate_candles(&candles, 5);
+        // A late candle for an earlier bucket falls back to indexing every bucket
+        let mut shuffled = candles.clone();
+        let late = shuffled.remove(1);
+        shuffled.push(late);
+        let agg = aggregate_candles(&shuffled, 5);
+        assert_eq!(agg.len(), expected.len());
+        for (agg, expected) in agg.iter().zip(&expected) {
+            assert_eq!(agg.timestamp, expected.timestamp);
+            assert_eq!(agg.high, expected.high);
+            assert_eq!(agg.low, expected.low);
+            assert_eq!(agg.volume, expected.volume);
+        }
+        // Newer buckets come first, but the result is still in timestamp order
+        let reversed: Vec<Candle> = candles.iter().rev().cloned().collect();
+        let agg = aggregate_candles(&reversed, 5);
+        assert_eq!(agg.len(), 2);
+        assert!(agg[0].timestamp < agg[1].timestamp);
+        assert_eq!(agg[0].volume, 30);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_par_aggregate_candles() {
+        let symbol: Arc<str> = Arc::from("ES.FUT");
+        let start = DateTime::from_timestamp(1_745_242_200, 0).unwrap();
+        let candles: Vec<Candle> = (0..20_000)
+            .map(|i: i64| Candle {
+                timestamp: (start + Duration::minutes(i / 10)).with_timezone(&New_York),
+                instrument_id: (i % 10) as u32,
+                symbol: symbol.clone(),
+                open: i as f64,
+                high: (i % 7) as f64 + 100.0,
+                low: (i % 13) as f64,
+                close: i as f64 + 0.5,
+                volume: i as u64,
+            })
+            .collect();
+        for interval in [5, 60] {
+            let expected = aggregate_candles(&candles, interval);
+            assert_eq!(par_aggregate_candles(&candles, interval), expected);
+            let interval = Duration::minutes(interval.into());
+            for threads in [2, 3, 16] {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .unwrap();
+                assert_eq!(
+                    pool.install(|| {
+                        aggregate_candles_partitioned(
+                            &candles,
+                            interval,
+                            BucketAnchor::LocalMidnight,
+                        )
+                    }),
+                    expected
+                );
             }
-            
-            anyhow::bail!("Could not calculate complete PMZ values. Missing required data.")
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_aggregate_candles_by_duration() {
+        let candles: Vec<Candle> = fixture()
+            .iter()
+            .map(|r| Candle::try_new(r, "ES.c.0").unwrap())
+            .collect();
+        // 9:30 is 570 minutes after midnight, so 7-minute buckets start at 9:27 and 9:34
+        let agg = aggregate_candles_by(&candles, Duration::minutes(7), BucketAnchor::LocalMidnight);
+        assert_eq!(agg.len(), 2);
+        assert_eq!(agg[0].format_timestamp(), "2025-04-21 09:27");
+        assert_eq!(agg[0].volume, 20);
+        assert_eq!(agg[1].format_timestamp(), "2025-04-21 09:34");
+        let agg =
+            aggregate_candles_by(&candles, Duration::minutes(90), BucketAnchor::LocalMidnight);
+        assert_eq!(agg.len(), 1);
+        assert_eq!(agg[0].format_timestamp(), "2025-04-21 09:00");
+        // 13:30 UTC is a multiple of 90 minutes after the epoch
+        let agg = aggregate_candles_by(&candles, Duration::minutes(90), BucketAnchor::Epoch);
+        assert_eq!(agg[0].format_timestamp(), "2025-04-21 09:30");
+        // Daily bars for a session opening at 18:00 the previous day
+        let open = ny_local(
+            NaiveDate::from_ymd_opt(2025, 4, 20).unwrap(),
+            NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+        )
+        .unwrap();
+        let agg = aggregate_candles_by(&candles, Duration::days(1), BucketAnchor::At(open));
+        assert_eq!(agg.len(), 1);
+        assert_eq!(agg[0].timestamp, open);
+        assert_eq!(agg[0].volume, 40);
+    }
+
+    #[test]
+    fn test_live_candle_builder() {
+        let candles: Vec<Candle> = fixture()
+            .iter()
+            .map(|r| Candle::try_new(r, "ES.c.0").unwrap())
+            .collect();
+        let mut builder = LiveCandleBuilder::new(5, DEFAULT_CANDLE_TZ);
+        let mut completed: Vec<_> = candles
+            .iter()
+            .cloned()
+            .filter_map(|c| builder.push(c))
+            .collect();
+        completed.extend(builder.flush());
+        assert_eq!(completed, aggregate_candles(&candles, 5));
+        assert!(builder.current().is_none());
+    }
+
+    #[test]
+    fn test_live_candle_builder_session() {
+        let candle = |hour, minute, price| {
+            test_util::candle()
+                .timestamp(eastern(2025, 4, 21, hour, minute))
+                .price(price)
+                .volume(1)
+                .build()
+        };
+        let mut builder =
+            LiveCandleBuilder::new(240, DEFAULT_CANDLE_TZ).with_session(Session::CME_GLOBEX);
+        assert!(builder.push(candle(16, 30, 5300.0)).is_none());
+        // Maintenance break
+        assert!(builder.push(candle(17, 30, 5310.0)).is_none());
+        let completed = builder.push(candle(18, 0, 5320.0)).unwrap();
+        // Intervals are aligned to the 18:00 open the previous day
+        assert_eq!(completed.format_timestamp(), "2025-04-21 14:00");
+        assert_eq!(completed.close, 5300.0);
+        assert_eq!(completed.volume, 1);
+        assert_eq!(
+            builder.current().unwrap().format_timestamp(),
+            "2025-04-21 18:00"
+        );
+    }
+
+    #[test]
+    fn test_continuous_symbol() {
+        assert_eq!(PmzConfig::default().continuous_symbol().unwrap(), "ES.c.0");
+        for symbol in [Symbols::from("NQ.v.1"), Symbols::from("ES.n.0")] {
+            let config = PmzConfig {
+                symbol,
+                ..PmzConfig::default()
+            };
+            assert!(config.continuous_symbol().is_ok());
+        }
+        for symbol in [
+            Symbols::from("ESM5"),
+            Symbols::from("ES.c0"),
+            Symbols::from("ES.x.0"),
+            Symbols::from("ES.c.0.1"),
+            Symbols::from(["ES.c.0", "NQ.c.0"]),
+            Symbols::from(5482),
+            Symbols::All,
+        ] {
+            let config = PmzConfig {
+                symbol,
+                ..PmzConfig::default()
+            };
+            assert!(matches!(
+                config.continuous_symbol(),
+                Err(PmzError::SymbologyError(_))
+            ));
+        }
+        assert!("GLBX.MPD3".parse::<Dataset>().is_err());
+    }
+
+    #[test]
+    fn test_premarket_tracker() {
+        let config = PmzConfig {
+            start: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(9, 35, 0).unwrap(),
+            ..PmzConfig::default()
+        };
+        let mut tracker = PremarketTracker::<f64>::new(&config);
+        let updates: Vec<_> = fixture()
+            .iter()
+            .map(|r| tracker.update(&Candle::try_new(r, "ES.c.0").unwrap()))
+            .collect();
+        assert!(updates[..3].iter().all(Option::is_some));
+        // Outside the window
+        assert!(updates[3].is_none());
+        let range = tracker.range().unwrap();
+        assert_eq!(range.high, 5302.5);
+        assert_eq!(range.low, 5298.5);
+    }
+
+    #[test]
+    fn test_pmz_tracker() {
+        let config = PmzConfig {
+            start: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(9, 35, 0).unwrap(),
+            ..PmzConfig::default()
+        };
+        let date = NaiveDate::from_ymd_opt(2025, 4, 21).unwrap();
+        let mut tracker = PmzTracker::new(&config, date, Some(5300.0));
+        let events: Vec<_> = fixture()
+            .iter()
+            .map(|r| tracker.update(&Candle::try_new(r, "ES.c.0").unwrap()))
+            .collect();
+        assert!(matches!(&events[0], Some(PmzEvent::Provisional(res)) if res.pmh == Some(5301.0)));
+        // The 9:34 candle completes the window
+        let Some(PmzEvent::Locked(res)) = &events[2] else {
+            panic!("expected locked event, got {:?}", events[2]);
+        };
+        assert_eq!(res.pmh, Some(5302.5));
+        assert_eq!(res.pml, Some(5298.5));
+        assert_eq!(res.is_gap_up, Some(false));
+        assert!(res.is_complete());
+        assert!(events[3].is_none());
+        assert_eq!(tracker.current_pmh(), Some(5302.5));
+        assert_eq!(tracker.pmz_high(), res.pmz_high);
+    }
+
+    #[test]
+    fn test_aggregate_candles_in_tz() {
+        let candles: Vec<Candle> = fixture()
+            .iter()
+            .map(|r| Candle::try_with_tz(r, "ES.c.0", Tz::UTC).unwrap())
+            .collect();
+        assert_eq!(candles[0].format_timestamp(), "2025-04-21 13:30");
+        assert_eq!(
+            aggregate_candles(&candles, 5)[0].format_timestamp(),
+            "2025-04-21 13:30"
+        );
+        let agg = aggregate_candles_in_tz(&candles, 60, Tz::Asia__Kolkata);
+        // 13:30 UTC is 19:00 IST, so all candles fall in the same hour
+        assert_eq!(agg.len(), 1);
+        assert_eq!(agg[0].format_timestamp(), "2025-04-21 19:00");
+        assert_eq!(agg[0].volume, 40);
+    }
+
+    #[test]
+    fn test_pmz_result_from_inputs_gap_up() {
+        let date = NaiveDate::from_ymd_opt(2025, 4, 22).unwrap();
+        let res = PmzResult::from_inputs(date, Some(110.0), Some(100.0), Some(95.0), Some(108.0));
+        assert!(res.is_complete());
+        assert_eq!(res.is_gap_up, Some(true));
+        assert_eq!(res.pmz_high, Some(108.0));
+        assert_eq!(res.pmz_low, Some(106.0));
+        assert_eq!(res.risk, Some(2.0));
+        assert_eq!(res.missing_flags(), 0);
+    }
+
+    #[test]
+    fn test_pmz_result_with_zone_factors() {
+        let date = NaiveDate::from_ymd_opt(2025, 4, 22).unwrap();
+        let res = PmzResult::from_inputs(date, Some(110.0), Some(100.0), Some(112.0), Some(101.0))
+            .with_zone_factors(0.25, 0.5);
+        assert_eq!(res.is_gap_up, Some(false));
+        assert_eq!(res.pmz_high, Some(105.0));
+        assert_eq!(res.pmz_low, Some(102.5));
+        assert_eq!(res.risk, Some(2.5));
+        assert!(res.is_complete());
+    }
+
+    #[test]
+    fn test_pmz_result_from_inputs_missing_lis() {
+        let date = NaiveDate::from_ymd_opt(2025, 4, 22).unwrap();
+        let res = PmzResult::from_inputs(date, Some(110.0), Some(100.0), None, Some(108.0));
+        assert!(!res.is_complete());
+        assert_eq!(res.pmh, Some(110.0));
+        assert_eq!(res.pml, Some(100.0));
+        assert_eq!(
+            res.missing,
+            vec![
+                PmzComponent::PrevDayLis,
+                PmzComponent::GapDirection,
+                PmzComponent::PmzHigh,
+                PmzComponent::PmzLow,
+                PmzComponent::Risk
+            ]
+        );
+        assert_eq!(
+            res.missing_flags(),
+            PmzComponent::PrevDayLis.flag()
+                | PmzComponent::GapDirection.flag()
+                | PmzComponent::PmzHigh.flag()
+                | PmzComponent::PmzLow.flag()
+                | PmzComponent::Risk.flag()
+        );
+        assert!(matches!(
+            res.ensure_complete(),
+            Err(PmzError::PartialData { missing_fields }) if missing_fields[0] == "prev_day_lis"
+        ));
+    }
+
+    #[test]
+    fn test_pmz_result_lis_window_after_half_day() {
+        let calendar = UsEquityCalendar;
+        let date = NaiveDate::from_ymd_opt(2024, 12, 2).unwrap();
+        let prev_date = calendar.previous_trading_day(date);
+        assert_eq!(prev_date, NaiveDate::from_ymd_opt(2024, 11, 29).unwrap());
+        let res = PmzResult::from_inputs(date, None, None, None, None).with_sessions(
+            calendar.session(date).unwrap(),
+            calendar.session(prev_date).unwrap(),
+        );
+        assert!(res.prev_session.unwrap().early_close);
+        assert_eq!(
+            res.lis_window(),
+            Some((
+                NaiveTime::from_hms_opt(12, 55, 0).unwrap(),
+                NaiveTime::from_hms_opt(13, 0, 0).unwrap()
+            ))
+        );
+        let (lis_start, lis_end) = res.lis_times().unwrap();
+        assert_eq!(
+            lis_start,
+            ny_local(prev_date, NaiveTime::from_hms_opt(12, 55, 0).unwrap()).unwrap()
+        );
+        assert_eq!(
+            lis_end.with_timezone(&Utc).to_rfc3339(),
+            "2024-11-29T18:00:00+00:00"
+        );
+        assert!(res.pre_market_times().is_none());
+        let res = res.with_pre_market_window(
+            NaiveTime::from_hms_opt(7, 25, 0).unwrap(),
+            NaiveTime::from_hms_opt(9, 25, 0).unwrap(),
+        );
+        let (start, _) = res.pre_market_times().unwrap();
+        assert_eq!(
+            start.with_timezone(&Utc).to_rfc3339(),
+            "2024-12-02T12:25:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_symbol_table() {
+        let mut symbols = SymbolTable::new();
+        let esm5 = symbols.insert(1, "ESM5");
+        assert!(Arc::ptr_eq(&esm5, &symbols.insert(1, "ESM5")));
+        assert_eq!(symbols.get(1), Some("ESM5"));
+        assert_eq!(&*symbols.insert(1, "ESU5"), "ESU5");
+        assert_eq!(&*symbols.get_or_id(2), "2");
+
+        let candles: Vec<Candle> = fixture()
+            .iter()
+            .map(|r| Candle::try_with_symbol(r, symbols.get_or_id(1), Tz::UTC).unwrap())
+            .collect();
+        assert!(Arc::ptr_eq(&candles[0].symbol, &candles[3].symbol));
+        let agg = aggregate_candles(&candles, 5);
+        assert!(Arc::ptr_eq(&agg[1].symbol, &candles[0].symbol));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_candle_try_new() {
+        let mut record = fixture().remove(0);
+        assert_eq!(
+            Candle::<f64>::try_new(&record, "ES.c.0").unwrap(),
+            Candle::new(&record, "ES.c.0")
+        );
+        record.hd.ts_event = dbn::UNDEF_TIMESTAMP;
+        assert!(matches!(
+            Candle::<f64>::try_new(&record, "ES.c.0"),
+            Err(PmzError::InvalidTimestamp(dbn::UNDEF_TIMESTAMP))
+        ));
+        assert!(matches!(
+            Candle::<f64>::try_with_tz(&record, "ES.c.0", Tz::UTC),
+            Err(PmzError::InvalidTimestamp(dbn::UNDEF_TIMESTAMP))
+        ));
+        // The deprecated constructors wrap around to before the UNIX epoch
+        assert!(Candle::<f64>::new(&record, "ES.c.0").timestamp.timestamp() < 0);
+    }
+
+    #[tokio::test]
+    async fn test_visit_candles() {
+        let path = crate::zst_test_data_path(Schema::Ohlcv1M);
+        let mut decoder = AsyncDbnDecoder::from_zstd_file(path).await.unwrap();
+        let mut symbols = SymbolTable::new();
+        let mut volume = 0;
+        let count = visit_candles(&mut decoder, &mut symbols, Tz::UTC, |c| volume += c.volume)
+            .await
+            .unwrap();
+        assert!(count > 0);
+        assert!(volume > 0);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_aggregate_candles_decimal_is_exact() {
+        use rust_decimal::Decimal;
+
+        let candles: Vec<DecimalCandle> = fixture()
+            .iter()
+            .map(|r| Candle::try_new(r, "ES.c.0").unwrap())
+            .collect();
+        let agg = aggregate_candles(&candles, 5);
+        assert_eq!(agg[0].open, Decimal::new(53001, 1));
+        assert_eq!(agg[0].low, Decimal::new(52985, 1));
+        assert_eq!(agg[1].close, Decimal::new(529725, 2));
+        assert_eq!(agg[0].open.to_f64(), 5300.1);
+    }
+}
@@ -1,21 +1,34 @@
 //! C-compatible FFI layer for the Databento PMZ calculation
 //! This interface is designed to be called from C# via P/Invoke
+//!
+//! This module provides a C-compatible interface for the Databento client library,
+//! allowing it to be used from C, C#, or other languages that support C FFI.
+//!
+//! The main functionality exposed is the PMZ (Pre-Market Zone) calculation
+//! via the `pmz_calculate` function.
 
-/// FFI module for C/C# interoperability.
-/// 
-/// This module provides a C-compatible interface for the Databento client library,
-/// allowing it to be used from C, C#, or other languages that support C FFI.
-/// 
-/// The main functionality exposed is the PMZ (Pre-Market Zone) calculation
-/// via the `pmz_calculate` function.
-
-use crate::examples::es_futures_pmz;
-use chrono::NaiveDate;
+use crate::{
+    alerts::{AlertEngine, AlertEvent, CrossDirection, LevelId, LevelKind, RetriggerPolicy},
+    backtest::OrderSide,
+    blocking,
+    examples::es_futures_pmz::{self, PmzConfig, PmzError, PmzResult},
+    levels,
+    portfolio::{InstrumentSpec, PnLTracker},
+    quotes::{Quote, QuoteBoard},
+    ApiKey, HistoricalClient,
+};
+use chrono::{DateTime, NaiveDate, Offset};
+use chrono_tz::Tz;
 use std::{
-    ffi::{c_char, CStr, CString},
+    cell::RefCell,
+    ffi::{c_char, c_void, CStr, CString},
+    panic::{self, AssertUnwindSafe},
     ptr,
+    sync::Mutex,
 };
-use tokio::runtime::Runtime;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use zeroize::Zeroize;
 
 /// Error codes for PMZ calculation functions.
 #[repr(C)]
@@ -33,34 +46,107 @@ pub enum PmzErrorCode {
     DataProcessingFailed = 4,
     /// Insufficient data for calculation
     InsufficientData = 5,
+    /// The request was cancelled with `db_request_cancel`
+    Cancelled = 6,
     /// Other error
     Other = 99,
 }
 
+impl From<&PmzError> for PmzErrorCode {
+    fn from(err: &PmzError) -> Self {
+        match err {
+            PmzError::NoData(_) | PmzError::PartialData { .. } | PmzError::SymbologyError(_) => {
+                PmzErrorCode::InsufficientData
+            }
+            PmzError::InvalidDate(_) => PmzErrorCode::InvalidDate,
+            PmzError::ApiError(crate::Error::BadArgument { param_name, .. }) if param_name == "key" => {
+                PmzErrorCode::InvalidApiKey
+            }
+            PmzError::ApiError(crate::Error::Auth(_)) => PmzErrorCode::InvalidApiKey,
+            #[cfg(feature = "historical")]
+            PmzError::ApiError(crate::Error::Api(api_err))
+                if api_err.status_code == reqwest::StatusCode::UNAUTHORIZED =>
+            {
+                PmzErrorCode::InvalidApiKey
+            }
+            PmzError::ApiError(crate::Error::Dbn(_) | crate::Error::CorruptCache { .. })
+            | PmzError::InvalidTimestamp(_) => PmzErrorCode::DataProcessingFailed,
+            PmzError::ApiError(_) => PmzErrorCode::ApiRequestFailed,
+        }
+    }
+}
+
+/// Bit set in `CPmzResult::missing_flags` when the Pre-Market High is missing
+pub const PMZ_MISSING_PMH: u32 = 1 << 0;
+/// Bit set in `CPmzResult::missing_flags` when the Pre-Market Low is missing
+pub const PMZ_MISSING_PML: u32 = 1 << 1;
+/// Bit set in `CPmzResult::missing_flags` when the previous day's LIS is missing
+pub const PMZ_MISSING_PREV_DAY_LIS: u32 = 1 << 2;
+/// Bit set in `CPmzResult::missing_flags` when the 9:25 close is missing
+pub const PMZ_MISSING_CLOSE_925: u32 = 1 << 3;
+/// Bit set in `CPmzResult::missing_flags` when the gap direction is unknown
+pub const PMZ_MISSING_GAP_DIRECTION: u32 = 1 << 4;
+/// Bit set in `CPmzResult::missing_flags` when the PMZ high is missing
+pub const PMZ_MISSING_PMZ_HIGH: u32 = 1 << 5;
+/// Bit set in `CPmzResult::missing_flags` when the PMZ low is missing
+pub const PMZ_MISSING_PMZ_LOW: u32 = 1 << 6;
+/// Bit set in `CPmzResult::missing_flags` when the risk is missing
+pub const PMZ_MISSING_RISK: u32 = 1 << 7;
+
+/// The layout version of `CPmzResult` returned by this library, in its
+/// `struct_version` field. Fields are only ever appended after version 3, which moved
+/// `struct_version` to the start of the struct after `struct_size`, so a consumer can
+/// read any field introduced at or before the version it finds.
+pub const PMZ_RESULT_VERSION: u32 = 3;
+
 /// C-compatible PMZ result struct
 #[repr(C)]
 #[derive(Debug)]
 pub struct CPmzResult {
-    /// Error code (0 = success)
+    /// The size of this struct in bytes, for detecting a mismatched layout
+    pub struct_size: u32,
+    /// The layout version of this struct, `PMZ_RESULT_VERSION`
+    pub struct_version: u32,
+    /// Error code (0 = success). `InsufficientData` with a non-null `date` indicates a
+    /// partial result: see `missing_flags`
     pub error_code: PmzErrorCode,
     /// Error message if error_code != 0, otherwise null
     pub error_message: *mut c_char,
     /// Date for which PMZ values were calculated (format: YYYY-MM-DD)
     pub date: *mut c_char,
-    /// Pre-Market High value
+    /// Pre-Market High value, NaN if missing
     pub pmh: f64,
-    /// Pre-Market Low value
+    /// Pre-Market Low value, NaN if missing
     pub pml: f64,
-    /// Previous day's Line in Sand (LIS) value
+    /// Previous day's Line in Sand (LIS) value, NaN if missing
     pub prev_day_lis: f64,
-    /// Indicates if market gapped up (1) or down (0)
+    /// Indicates if market gapped up (1) or down (0), -1 if unknown
     pub is_gap_up: i32,
-    /// PMZ high value (buy zone)
+    /// PMZ high value (buy zone), NaN if missing
     pub pmz_high: f64,
-    /// PMZ low value (sell zone) 
+    /// PMZ low value (sell zone), NaN if missing
     pub pmz_low: f64,
-    /// Risk value (PMZ High - PMZ Low)
+    /// Risk value (PMZ High - PMZ Low), NaN if missing
     pub risk: f64,
+    /// Bitmask of the components that couldn't be calculated (0 = complete).
+    /// See the `PMZ_MISSING_*` constants for the bit of each component.
+    pub missing_flags: u32,
+    /// The 9:25 close minus the previous day's LIS in points, NaN if unknown
+    pub gap_points: f64,
+    /// The gap as a percentage of the previous day's LIS, NaN if unknown
+    pub gap_percent: f64,
+    /// The UTC offset of New York time on `date` in seconds, e.g. -14400 for EDT, 0 if
+    /// unknown
+    pub utc_offset_secs: i32,
+    /// Start of the pre-market window as UNIX nanoseconds in UTC, 0 if unknown
+    pub pmz_window_start_ns: u64,
+    /// End of the pre-market window (exclusive) as UNIX nanoseconds in UTC, 0 if unknown
+    pub pmz_window_end_ns: u64,
+    /// Start of the previous day's LIS candle as UNIX nanoseconds in UTC, 0 if unknown
+    pub lis_start_ns: u64,
+    /// End of the previous day's LIS candle (exclusive) as UNIX nanoseconds in UTC, 0 if
+    /// unknown
+    pub lis_end_ns: u64,
 }
 
 /// Frees memory allocated by `pmz_calculate`.
@@ -71,22 +157,24 @@ pub struct CPmzResult {
 /// Calling it with any other pointer is undefined behavior.
 #[no_mangle]
 pub unsafe extern "C" fn pmz_free_result(result: *mut CPmzResult) {
-    if !result.is_null() {
-        let result_ref = &mut *result;
+    catch_panic(|| {
+        if !result.is_null() {
+            let result_ref = &mut *result;
         
-        // Free error_message if it's not null
-        if !result_ref.error_message.is_null() {
-            let _ = CString::from_raw(result_ref.error_message);
-        }
+            // Free error_message if it's not null
+            if !result_ref.error_message.is_null() {
+                let _ = CString::from_raw(result_ref.error_message);
+            }
         
-        // Free date if it's not null
-        if !result_ref.date.is_null() {
-            let _ = CString::from_raw(result_ref.date);
-        }
+            // Free date if it's not null
+            if !result_ref.date.is_null() {
+                let _ = CString::from_raw(result_ref.date);
+            }
         
-        // Free the result struct itself
-        drop(Box::from_raw(result));
-    }
+            // Free the result struct itself
+            drop(Box::from_raw(result));
+        }
+    })
 }
 
 /// Calculates PMZ (Pre-Market Zone) values for E-mini S&P 500 futures.
@@ -110,65 +198,1171 @@ pub unsafe extern "C" fn pmz_calculate(
     api_key: *const c_char,
     date: *const c_char,
 ) -> *mut CPmzResult {
-    // Check if API key is null
-    if api_key.is_null() {
-        return create_error_result(
-            PmzErrorCode::InvalidApiKey,
-            "API key cannot be null",
-        );
+    catch_panic(|| {
+        let api_key = match parse_api_key(api_key) {
+            Ok(key) => key,
+            Err(e) => return e,
+        };
+        let parse_date = match parse_date(date) {
+            Ok(d) => d,
+            Err(e) => return e,
+        };
+        let client = match DbClient::with_key(api_key) {
+            Ok(client) => client,
+            Err(e) => return create_error_result(PmzErrorCode::Other, &e),
+        };
+        into_c_result(client.calculate_pmz(&PmzConfig::default(), parse_date, |_| {}))
+    })
+}
+
+/// A callback invoked with the result of an asynchronous PMZ calculation and the
+/// `user_data` passed when starting it. The callback owns `result` and must free it by
+/// calling `pmz_free_result`.
+pub type PmzCallback = Option<extern "C" fn(result: *mut CPmzResult, user_data: *mut c_void)>;
+
+/// Wrapper for sending a caller-provided pointer to the worker thread. The caller is
+/// responsible for its thread safety.
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+/// Starts calculating PMZ values like `pmz_calculate` on a worker thread and returns
+/// immediately. `callback` is invoked on the worker thread when finished.
+///
+/// # Parameters
+///
+/// * `api_key` - Databento API key (null-terminated C string)
+/// * `date` - Optional date in YYYY-MM-DD format (null-terminated C string), or NULL for today
+/// * `callback` - Function called with the result and `user_data`
+/// * `user_data` - Opaque pointer passed through to `callback`
+///
+/// # Returns
+///
+/// `Success` if the calculation was started, otherwise an error code, in which case
+/// `callback` isn't invoked. `api_key` and `date` are copied, so they can be freed once
+/// this function returns.
+///
+/// # Safety
+///
+/// `api_key` and `date` must be null or valid null-terminated C strings, and
+/// `callback` must be safe to call from another thread with `user_data`.
+#[no_mangle]
+pub unsafe extern "C" fn pmz_calculate_async(
+    api_key: *const c_char,
+    date: *const c_char,
+    callback: PmzCallback,
+    user_data: *mut c_void,
+) -> PmzErrorCode {
+    catch_panic(|| {
+        let Some(callback) = callback else {
+            set_last_error("Callback cannot be null");
+            return PmzErrorCode::Other;
+        };
+        let to_owned = |s: *const c_char| (!s.is_null()).then(|| CStr::from_ptr(s).to_owned());
+        let api_key = to_owned(api_key);
+        let date = to_owned(date);
+        let user_data = UserData(user_data);
+        let spawned = std::thread::Builder::new()
+            .name("pmz-calculate".to_owned())
+            .spawn(move || {
+                let as_ptr = |s: &Option<CString>| s.as_ref().map_or(ptr::null(), |s| s.as_ptr());
+                let result = pmz_calculate(as_ptr(&api_key), as_ptr(&date));
+                // Zero out the key before handing control back to the caller
+                if let Some(api_key) = api_key {
+                    api_key.into_bytes().zeroize();
+                }
+                // Move the whole wrapper rather than capturing its non-`Send` field
+                let user_data = user_data;
+                callback(result, user_data.0);
+            });
+        match spawned {
+            Ok(_) => {
+                clear_last_error();
+                PmzErrorCode::Success
+            }
+            Err(e) => {
+                set_last_error(&format!("Failed to spawn worker thread: {e}"));
+                PmzErrorCode::Other
+            }
+        }
+    })
+}
+
+/// A reusable handle wrapping a blocking historical client.
+///
+/// The handle can be shared between threads, and each call made with it reuses the
+/// same connection pool, avoiding a new TLS handshake per call.
+pub struct DbClient {
+    client: blocking::HistoricalClient,
+}
+
+impl DbClient {
+    unsafe fn new(api_key: *const c_char) -> Result<Self, String> {
+        let api_key = to_api_key(api_key)?;
+        Self::with_key(api_key)
     }
 
-    // Try to convert API key to Rust string
-    let api_key_cstr = match CStr::from_ptr(api_key).to_str() {
-        Ok(s) => s,
-        Err(_) => {
-            return create_error_result(
-                PmzErrorCode::InvalidApiKey,
-                "API key contains invalid UTF-8",
-            );
+    fn with_key(api_key: ApiKey) -> Result<Self, String> {
+        let client = HistoricalClient::builder()
+            .api_key(api_key)
+            .build()
+            .and_then(blocking::HistoricalClient::from_async)
+            .map_err(|e| format!("Failed to create client: {e}"))?;
+        Ok(Self { client })
+    }
+
+    /// Calculates PMZ values for `date` with a clone of the async client, so the
+    /// handle can be used from multiple threads at once.
+    fn calculate_pmz(
+        &self,
+        config: &PmzConfig,
+        date: Option<NaiveDate>,
+        diagnostics: impl FnMut(es_futures_pmz::Diagnostic),
+    ) -> es_futures_pmz::Result<PmzResult> {
+        self.client
+            .block_on(es_futures_pmz::calculate_pmz_with_client(
+                self.client.as_async().clone(),
+                config,
+                date,
+                diagnostics,
+            ))
+    }
+}
+
+/// Creates a client handle for use with `pmz_calculate_with_client`.
+///
+/// # Parameters
+///
+/// * `api_key` - Databento API key (null-terminated C string)
+///
+/// # Returns
+///
+/// A pointer to a heap-allocated `DbClient`, or NULL if the API key is invalid or the
+/// client couldn't be created, in which case `db_last_error_message` describes why.
+/// The caller must free the handle by calling `db_client_destroy` when done.
+///
+/// # Safety
+///
+/// `api_key` must be null or a valid null-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn db_client_create(api_key: *const c_char) -> *mut DbClient {
+    catch_panic(|| match DbClient::new(api_key) {
+        Ok(client) => {
+            clear_last_error();
+            Box::into_raw(Box::new(client))
         }
-    };
+        Err(message) => {
+            set_last_error(&message);
+            ptr::null_mut()
+        }
+    })
+}
+
+/// Frees a client handle created by `db_client_create`.
+///
+/// # Safety
+///
+/// This function must be called with a pointer returned by `db_client_create` once no
+/// other thread is using it. Calling it with any other pointer is undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn db_client_destroy(client: *mut DbClient) {
+    catch_panic(|| {
+        if !client.is_null() {
+            drop(Box::from_raw(client));
+        }
+    })
+}
+
+/// Checks that an API key is valid by making a cheap authenticated request to
+/// Databento, e.g. to verify credentials at startup before calculating PMZ values.
+///
+/// # Parameters
+///
+/// * `api_key` - Databento API key (null-terminated C string)
+///
+/// # Returns
+///
+/// `Success` if the key is valid, `InvalidApiKey` if it's malformed or rejected by
+/// Databento, or another error code if the request failed, in which case
+/// `db_last_error_message` describes why.
+///
+/// # Safety
+///
+/// `api_key` must be null or a valid null-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn pmz_validate_key(api_key: *const c_char) -> PmzErrorCode {
+    catch_panic(|| {
+        let mut client = match to_api_key(api_key).and_then(DbClient::with_key) {
+            Ok(client) => client,
+            Err(message) => {
+                set_last_error(&message);
+                return PmzErrorCode::InvalidApiKey;
+            }
+        };
+        match client.client.validate_key() {
+            Ok(_) => {
+                clear_last_error();
+                PmzErrorCode::Success
+            }
+            Err(e) => {
+                set_last_error(&format!("API key validation failed: {e}"));
+                PmzErrorCode::from(&PmzError::ApiError(e))
+            }
+        }
+    })
+}
+
+/// Calculates PMZ values like `pmz_calculate`, but with a client handle created by
+/// `db_client_create`. The handle can be used from multiple threads concurrently.
+///
+/// # Returns
+///
+/// A pointer to a heap-allocated `CPmzResult` struct. The caller must free this memory
+/// by calling `pmz_free_result` when done.
+///
+/// # Safety
+///
+/// `client` must be null or a pointer returned by `db_client_create` that hasn't been
+/// destroyed, and `date` must be null or a valid null-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn pmz_calculate_with_client(
+    client: *const DbClient,
+    date: *const c_char,
+) -> *mut CPmzResult {
+    catch_panic(|| {
+        let Some(client) = client.as_ref() else {
+            return create_error_result(PmzErrorCode::Other, "Client cannot be null");
+        };
+        let parse_date = match parse_date(date) {
+            Ok(d) => d,
+            Err(e) => return e,
+        };
+        into_c_result(client.calculate_pmz(&PmzConfig::default(), parse_date, |_| {}))
+    })
+}
+
+/// A callback invoked with each progress or diagnostic message of a PMZ calculation
+/// and the `user_data` passed when starting it. `message` is a null-terminated C
+/// string that's only valid for the duration of the call.
+pub type PmzDiagnosticCallback =
+    Option<extern "C" fn(message: *const c_char, user_data: *mut c_void)>;
+
+/// Calculates PMZ values like `pmz_calculate_with_client`, passing each progress and
+/// diagnostic message to `callback` as the calculation runs. `callback` is invoked on
+/// the calling thread before this function returns.
+///
+/// # Returns
+///
+/// A pointer to a heap-allocated `CPmzResult` struct. The caller must free this memory
+/// by calling `pmz_free_result` when done.
+///
+/// # Safety
+///
+/// `client` must be null or a pointer returned by `db_client_create` that hasn't been
+/// destroyed, `date` must be null or a valid null-terminated C string, and `callback`
+/// must be null or safe to call with `user_data`.
+#[no_mangle]
+pub unsafe extern "C" fn pmz_calculate_with_diagnostics(
+    client: *const DbClient,
+    date: *const c_char,
+    callback: PmzDiagnosticCallback,
+    user_data: *mut c_void,
+) -> *mut CPmzResult {
+    catch_panic(|| {
+        let Some(client) = client.as_ref() else {
+            return create_error_result(PmzErrorCode::Other, "Client cannot be null");
+        };
+        let parse_date = match parse_date(date) {
+            Ok(d) => d,
+            Err(e) => return e,
+        };
+        let config = PmzConfig {
+            dataset_info: true,
+            ..PmzConfig::default()
+        };
+        let result = client.calculate_pmz(&config, parse_date, |diagnostic| {
+            let Some(callback) = callback else {
+                return;
+            };
+            if let Ok(message) = CString::new(diagnostic.to_string()) {
+                callback(message.as_ptr(), user_data);
+            }
+        });
+        into_c_result(result)
+    })
+}
+
+/// An opaque token for cancelling an in-flight request from another thread.
+pub struct DbRequest {
+    token: CancellationToken,
+}
+
+/// Creates a request token to pass to `pmz_calculate_cancellable`.
+///
+/// # Returns
+///
+/// A pointer to a heap-allocated `DbRequest`. The caller must free it by calling
+/// `db_request_free` once the request has finished.
+#[no_mangle]
+pub extern "C" fn db_request_begin() -> *mut DbRequest {
+    catch_panic(|| {
+        Box::into_raw(Box::new(DbRequest {
+            token: CancellationToken::new(),
+        }))
+    })
+}
 
-    // Parse the date if provided
-    let parse_date = if date.is_null() {
-        None
-    } else {
-        match CStr::from_ptr(date).to_str() {
-            Ok(date_str) => match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-                Ok(d) => Some(d),
+/// Cancels the request associated with `request`. The call using it returns promptly
+/// with `PmzErrorCode::Cancelled`, and calls made with an already-cancelled token
+/// return immediately. Can be called from any thread.
+///
+/// # Safety
+///
+/// `request` must be null or a pointer returned by `db_request_begin` that hasn't been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn db_request_cancel(request: *const DbRequest) {
+    catch_panic(|| {
+        if let Some(request) = request.as_ref() {
+            request.token.cancel();
+        }
+    })
+}
+
+/// Frees a request token created by `db_request_begin`.
+///
+/// # Safety
+///
+/// This function must be called with a pointer returned by `db_request_begin` once no
+/// call is using it. Calling it with any other pointer is undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn db_request_free(request: *mut DbRequest) {
+    catch_panic(|| {
+        if !request.is_null() {
+            drop(Box::from_raw(request));
+        }
+    })
+}
+
+/// Calculates PMZ values like `pmz_calculate_with_client`, but can be aborted from
+/// another thread by passing `request` to `db_request_cancel`. Cancelling drops the
+/// in-flight historical requests.
+///
+/// # Returns
+///
+/// A pointer to a heap-allocated `CPmzResult` struct. The caller must free this memory
+/// by calling `pmz_free_result` when done.
+///
+/// # Safety
+///
+/// `client` must be null or a pointer returned by `db_client_create` that hasn't been
+/// destroyed, `date` must be null or a valid null-terminated C string, and `request`
+/// must be null or a pointer returned by `db_request_begin` that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn pmz_calculate_cancellable(
+    client: *const DbClient,
+    date: *const c_char,
+    request: *const DbRequest,
+) -> *mut CPmzResult {
+    catch_panic(|| {
+        let Some(request) = request.as_ref() else {
+            return pmz_calculate_with_client(client, date);
+        };
+        let Some(client) = client.as_ref() else {
+            return create_error_result(PmzErrorCode::Other, "Client cannot be null");
+        };
+        let parse_date = match parse_date(date) {
+            Ok(d) => d,
+            Err(e) => return e,
+        };
+        let config = PmzConfig::default();
+        let result = client.client.block_on(async {
+            tokio::select! {
+                // Check for cancellation first so an already-cancelled request doesn't
+                // start any historical requests
+                biased;
+                _ = request.token.cancelled() => None,
+                res = es_futures_pmz::calculate_pmz_with_client(
+                    client.client.as_async().clone(),
+                    &config,
+                    parse_date,
+                    |_| {},
+                ) => Some(res),
+            }
+        });
+        match result {
+            Some(result) => into_c_result(result),
+            None => create_error_result(PmzErrorCode::Cancelled, "Request was cancelled"),
+        }
+    })
+}
+
+/// An opaque handle to an alert engine for registering price levels and polling
+/// alerts when prices cross them.
+pub struct DbAlertEngine {
+    engine: AlertEngine,
+    rx: Mutex<broadcast::Receiver<AlertEvent>>,
+}
+
+/// When a level alerts again after it's been crossed. See `RetriggerPolicy`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub enum CRetriggerPolicy {
+    /// Alert on every crossing
+    Always = 0,
+    /// Alert on a crossing only if the previous alert was at least `debounce_ms` before
+    Debounce = 1,
+    /// Alert on the first crossing only, then remove the level
+    Once = 2,
+}
+
+impl TryFrom<u32> for CRetriggerPolicy {
+    type Error = crate::Error;
+
+    fn try_from(value: u32) -> crate::Result<Self> {
+        match value {
+            0 => Ok(Self::Always),
+            1 => Ok(Self::Debounce),
+            2 => Ok(Self::Once),
+            _ => Err(crate::Error::bad_arg(
+                "policy",
+                format!("unknown retrigger policy {value}"),
+            )),
+        }
+    }
+}
+
+/// C-compatible alert for a price crossing a level
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct CAlertEvent {
+    /// The size of this struct in bytes. The caller must set it before passing the
+    /// struct to `db_alerts_poll`, which checks it's large enough and sets it to the
+    /// size it wrote
+    pub struct_size: u32,
+    /// The ID returned by `db_alerts_add_level`
+    pub level_id: u64,
+    /// What the level represents
+    pub kind: LevelKind,
+    /// The price of the level
+    pub level_price: f64,
+    /// 1 if the price rose through the level, -1 if it fell
+    pub direction: i32,
+    /// The instrument ID of the trade
+    pub instrument_id: u32,
+    /// The trade price
+    pub price: f64,
+    /// The trade timestamp in nanoseconds since the UNIX epoch
+    pub ts_event: u64,
+}
+
+/// Creates an alert engine that buffers up to `capacity` alerts for
+/// `db_alerts_poll`. The caller must free the handle by calling `db_alerts_destroy`
+/// when done.
+#[no_mangle]
+pub extern "C" fn db_alerts_create(capacity: usize) -> *mut DbAlertEngine {
+    catch_panic(|| {
+        let engine = AlertEngine::new(capacity);
+        let rx = Mutex::new(engine.subscribe());
+        Box::into_raw(Box::new(DbAlertEngine { engine, rx }))
+    })
+}
+
+/// Frees an alert engine created by `db_alerts_create`.
+///
+/// # Safety
+///
+/// This function must be called with a pointer returned by `db_alerts_create` once no
+/// other thread is using it. Calling it with any other pointer is undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn db_alerts_destroy(engine: *mut DbAlertEngine) {
+    catch_panic(|| {
+        if !engine.is_null() {
+            drop(Box::from_raw(engine));
+        }
+    })
+}
+
+/// Registers a level at `price`. `kind` is a `LevelKind` and `policy` a
+/// `CRetriggerPolicy`. `debounce_ms` is only used with `CRetriggerPolicy::Debounce`.
+///
+/// # Returns
+///
+/// The level's ID for `db_alerts_remove_level`, or -1 if `engine` is null or `kind` or
+/// `policy` isn't one of their values.
+///
+/// # Safety
+///
+/// `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn db_alerts_add_level(
+    engine: *const DbAlertEngine,
+    kind: u32,
+    price: f64,
+    policy: u32,
+    debounce_ms: u64,
+) -> i64 {
+    catch_panic(|| {
+        let Some(engine) = engine.as_ref() else {
+            set_last_error("Alert engine cannot be null");
+            return -1;
+        };
+        let (kind, policy) = match (
+            LevelKind::try_from(kind),
+            CRetriggerPolicy::try_from(policy),
+        ) {
+            (Ok(kind), Ok(policy)) => (kind, policy),
+            (Err(e), _) | (_, Err(e)) => {
+                set_last_error(&e.to_string());
+                return -1;
+            }
+        };
+        let policy = match policy {
+            CRetriggerPolicy::Always => RetriggerPolicy::Always,
+            CRetriggerPolicy::Debounce => RetriggerPolicy::Debounce(
+                chrono::Duration::milliseconds(i64::try_from(debounce_ms).unwrap_or(i64::MAX)),
+            ),
+            CRetriggerPolicy::Once => RetriggerPolicy::Once,
+        };
+        clear_last_error();
+        engine.engine.add_level(kind, price, policy).0 as i64
+    })
+}
+
+/// Removes the level with `level_id`, returning `false` if it wasn't registered.
+///
+/// # Safety
+///
+/// `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn db_alerts_remove_level(
+    engine: *const DbAlertEngine,
+    level_id: u64,
+) -> bool {
+    catch_panic(|| {
+        engine
+            .as_ref()
+            .is_some_and(|engine| engine.engine.remove_level(LevelId(level_id)))
+    })
+}
+
+/// Checks a trade at `price` against the registered levels.
+///
+/// # Returns
+///
+/// The number of alerts generated, which can be retrieved with `db_alerts_poll`.
+///
+/// # Safety
+///
+/// `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn db_alerts_on_trade(
+    engine: *const DbAlertEngine,
+    instrument_id: u32,
+    price: f64,
+    ts_event: u64,
+) -> usize {
+    catch_panic(|| {
+        engine.as_ref().map_or(0, |engine| {
+            engine.engine.on_price(
+                instrument_id,
+                price,
+                chrono::DateTime::from_timestamp_nanos(ts_event as i64),
+            )
+        })
+    })
+}
+
+/// Retrieves the oldest unread alert without blocking. Alerts are dropped when more
+/// than the engine's capacity are unread.
+///
+/// # Returns
+///
+/// `true` if an alert was written to `event`, `false` if there are none or
+/// `event.struct_size` is smaller than this library's `CAlertEvent`.
+///
+/// # Safety
+///
+/// `engine` must be null or a pointer returned by `db_alerts_create` that hasn't been
+/// destroyed, and `event` must be null or valid for reads and writes of
+/// `event.struct_size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn db_alerts_poll(
+    engine: *const DbAlertEngine,
+    event: *mut CAlertEvent,
+) -> bool {
+    catch_panic(|| {
+        let (Some(engine), false) = (engine.as_ref(), event.is_null()) else {
+            return false;
+        };
+        if !check_struct_size::<CAlertEvent>((*event).struct_size) {
+            return false;
+        }
+        let mut rx = engine
+            .rx
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let alert = loop {
+            match rx.try_recv() {
+                Ok(alert) => break alert,
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => return false,
+            }
+        };
+        event.write(CAlertEvent {
+            struct_size: struct_size::<CAlertEvent>(),
+            level_id: alert.level.id.0,
+            kind: alert.level.kind,
+            level_price: alert.level.price,
+            direction: match alert.direction {
+                CrossDirection::Up => 1,
+                CrossDirection::Down => -1,
+            },
+            instrument_id: alert.instrument_id,
+            price: alert.price,
+            ts_event: alert.timestamp.timestamp_nanos_opt().unwrap_or(0) as u64,
+        });
+        true
+    })
+}
+
+/// An opaque handle to a position and PnL tracker for one instrument.
+pub struct DbPnlTracker {
+    tracker: Mutex<PnLTracker>,
+}
+
+impl DbPnlTracker {
+    fn tracker(&self) -> std::sync::MutexGuard<'_, PnLTracker> {
+        self.tracker
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// C-compatible position and PnL. See `PnL`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct CPnL {
+    /// The size of this struct in bytes. The caller must set it before passing the
+    /// struct to `db_pnl_get`, which checks it's large enough and sets it to the
+    /// size it wrote
+    pub struct_size: u32,
+    /// The net quantity, negative when short
+    pub position: i64,
+    /// The average entry price of the open position, NaN if flat
+    pub average_price: f64,
+    /// The latest price the position was marked at, NaN if none
+    pub mark_price: f64,
+    /// The realized PnL in ticks
+    pub realized_ticks: f64,
+    /// The realized PnL in dollars
+    pub realized_dollars: f64,
+    /// The unrealized PnL in ticks, 0 when there's no mark price
+    pub unrealized_ticks: f64,
+    /// The unrealized PnL in dollars, 0 when there's no mark price
+    pub unrealized_dollars: f64,
+}
+
+/// Creates a position and PnL tracker for the instrument with `instrument_id`, whose
+/// `tick_size` and `multiplier` can be taken from its instrument definition. The
+/// caller must free the handle by calling `db_pnl_destroy` when done.
+#[no_mangle]
+pub extern "C" fn db_pnl_create(
+    instrument_id: u32,
+    tick_size: f64,
+    multiplier: f64,
+) -> *mut DbPnlTracker {
+    catch_panic(|| {
+        let tracker = PnLTracker::new(instrument_id, InstrumentSpec::new(tick_size, multiplier));
+        Box::into_raw(Box::new(DbPnlTracker {
+            tracker: Mutex::new(tracker),
+        }))
+    })
+}
+
+/// Frees a tracker created by `db_pnl_create`.
+///
+/// # Safety
+///
+/// This function must be called with a pointer returned by `db_pnl_create` once no
+/// other thread is using it. Calling it with any other pointer is undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn db_pnl_destroy(tracker: *mut DbPnlTracker) {
+    catch_panic(|| {
+        if !tracker.is_null() {
+            drop(Box::from_raw(tracker));
+        }
+    })
+}
+
+/// Applies a fill of `quantity` contracts at `price` and marks the position at it.
+/// `side` is an `OrderSide`.
+///
+/// # Returns
+///
+/// `false` if `tracker` is null or `side` isn't one of the values of `OrderSide`.
+///
+/// # Safety
+///
+/// `tracker` must be null or a pointer returned by `db_pnl_create` that hasn't been
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn db_pnl_on_fill(
+    tracker: *const DbPnlTracker,
+    side: u32,
+    quantity: u32,
+    price: f64,
+) -> bool {
+    catch_panic(|| {
+        let Some(tracker) = tracker.as_ref() else {
+            set_last_error("PnL tracker cannot be null");
+            return false;
+        };
+        let side = match OrderSide::try_from(side) {
+            Ok(side) => side,
+            Err(e) => {
+                set_last_error(&e.to_string());
+                return false;
+            }
+        };
+        clear_last_error();
+        tracker.tracker().on_fill(side, quantity, price);
+        true
+    })
+}
+
+/// Marks the position at the price of a trade. Trades in other instruments are
+/// ignored.
+///
+/// # Safety
+///
+/// `tracker` must be null or a pointer returned by `db_pnl_create` that hasn't been
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn db_pnl_on_trade(
+    tracker: *const DbPnlTracker,
+    instrument_id: u32,
+    price: f64,
+) {
+    catch_panic(|| {
+        if let Some(tracker) = tracker.as_ref() {
+            let mut tracker = tracker.tracker();
+            if tracker.instrument_id() == instrument_id {
+                tracker.mark(price);
+            }
+        }
+    })
+}
+
+/// Retrieves the current position and PnL.
+///
+/// # Returns
+///
+/// `true` if the PnL was written to `pnl`, `false` if either pointer is null or
+/// `pnl.struct_size` is smaller than this library's `CPnL`.
+///
+/// # Safety
+///
+/// `tracker` must be null or a pointer returned by `db_pnl_create` that hasn't been
+/// destroyed, and `pnl` must be null or valid for reads and writes of
+/// `pnl.struct_size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn db_pnl_get(tracker: *const DbPnlTracker, pnl: *mut CPnL) -> bool {
+    catch_panic(|| {
+        let (Some(tracker), false) = (tracker.as_ref(), pnl.is_null()) else {
+            return false;
+        };
+        if !check_struct_size::<CPnL>((*pnl).struct_size) {
+            return false;
+        }
+        let snapshot = tracker.tracker().pnl();
+        pnl.write(CPnL {
+            struct_size: struct_size::<CPnL>(),
+            position: snapshot.position,
+            average_price: snapshot.average_price.unwrap_or(f64::NAN),
+            mark_price: snapshot.mark_price.unwrap_or(f64::NAN),
+            realized_ticks: snapshot.realized_ticks,
+            realized_dollars: snapshot.realized_dollars,
+            unrealized_ticks: snapshot.unrealized_ticks,
+            unrealized_dollars: snapshot.unrealized_dollars,
+        });
+        true
+    })
+}
+
+/// An opaque handle to a board of the latest quote of each instrument.
+pub struct DbQuoteBoard {
+    board: Mutex<QuoteBoard>,
+}
+
+impl DbQuoteBoard {
+    fn board(&self) -> std::sync::MutexGuard<'_, QuoteBoard> {
+        self.board
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// C-compatible best bid and offer. See `Quote`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct CQuote {
+    /// The size of this struct in bytes. The caller must set it before passing the
+    /// struct to `db_quotes_get`, which checks it's large enough and sets it to the
+    /// size it wrote
+    pub struct_size: u32,
+    /// The instrument ID
+    pub instrument_id: u32,
+    /// The best bid price, NaN if there's no bid
+    pub bid_px: f64,
+    /// The best offer price, NaN if there's no offer
+    pub ask_px: f64,
+    /// The total quantity at the best bid
+    pub bid_sz: u32,
+    /// The total quantity at the best offer
+    pub ask_sz: u32,
+    /// When the exchange published the update in nanoseconds since the UNIX epoch
+    pub ts_event: u64,
+    /// When Databento received the update in nanoseconds since the UNIX epoch
+    pub ts_recv: u64,
+}
+
+/// C-compatible prior-day key levels result struct
+#[repr(C)]
+#[derive(Debug)]
+pub struct CLevelsResult {
+    /// The size of this struct in bytes, for detecting a mismatched layout
+    pub struct_size: u32,
+    /// Error code (0 = success)
+    pub error_code: PmzErrorCode,
+    /// Error message if error_code != 0, otherwise null
+    pub error_message: *mut c_char,
+    /// Trading date the levels are for (format: YYYY-MM-DD)
+    pub date: *mut c_char,
+    /// Previous trading date (format: YYYY-MM-DD)
+    pub prev_date: *mut c_char,
+    /// Previous regular trading hours (RTH) session high, NaN if missing
+    pub prev_high: f64,
+    /// Previous RTH session low, NaN if missing
+    pub prev_low: f64,
+    /// Previous RTH session close, NaN if missing
+    pub prev_close: f64,
+    /// Previous day's settlement price, NaN if missing
+    pub prev_settlement: f64,
+    /// High between the previous RTH close and the current RTH open, NaN if missing
+    pub overnight_high: f64,
+    /// Low between the previous RTH close and the current RTH open, NaN if missing
+    pub overnight_low: f64,
+}
+
+/// Creates an empty quote board. The caller must free the handle by calling
+/// `db_quotes_destroy` when done.
+#[no_mangle]
+pub extern "C" fn db_quotes_create() -> *mut DbQuoteBoard {
+    catch_panic(|| {
+        Box::into_raw(Box::new(DbQuoteBoard {
+            board: Mutex::new(QuoteBoard::new()),
+        }))
+    })
+}
+
+/// Frees a quote board created by `db_quotes_create`.
+///
+/// # Safety
+///
+/// This function must be called with a pointer returned by `db_quotes_create` once no
+/// other thread is using it. Calling it with any other pointer is undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn db_quotes_destroy(board: *mut DbQuoteBoard) {
+    catch_panic(|| {
+        if !board.is_null() {
+            drop(Box::from_raw(board));
+        }
+    })
+}
+
+/// Updates the quote of the instrument with `instrument_id` and maps `symbol` to it
+/// unless `symbol` is null. Pass NaN for an empty side. Updates older than the current
+/// quote by `ts_recv` are ignored.
+///
+/// # Returns
+///
+/// `false` if `board` is null or `symbol` isn't valid UTF-8.
+///
+/// # Safety
+///
+/// `board` must be null or a pointer returned by `db_quotes_create` that hasn't been
+/// destroyed, and `symbol` must be null or a valid null-terminated string.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn db_quotes_update(
+    board: *const DbQuoteBoard,
+    symbol: *const c_char,
+    instrument_id: u32,
+    bid_px: f64,
+    bid_sz: u32,
+    ask_px: f64,
+    ask_sz: u32,
+    ts_event: u64,
+    ts_recv: u64,
+) -> bool {
+    catch_panic(|| {
+        let Some(board) = board.as_ref() else {
+            set_last_error("Quote board cannot be null");
+            return false;
+        };
+        let symbol = if symbol.is_null() {
+            None
+        } else {
+            match CStr::from_ptr(symbol).to_str() {
+                Ok(symbol) => Some(symbol),
                 Err(_) => {
-                    return create_error_result(
-                        PmzErrorCode::InvalidDate,
-                        "Invalid date format, expected YYYY-MM-DD",
-                    );
+                    set_last_error("Symbol contains invalid UTF-8");
+                    return false;
                 }
-            },
-            Err(_) => {
-                return create_error_result(
-                    PmzErrorCode::InvalidDate,
-                    "Date contains invalid UTF-8",
-                );
             }
+        };
+        clear_last_error();
+        let mut board = board.board();
+        if let Some(symbol) = symbol {
+            board.insert_symbol(symbol, instrument_id);
         }
-    };
+        board.update(Quote {
+            instrument_id,
+            bid_px: (!bid_px.is_nan()).then_some(bid_px),
+            ask_px: (!ask_px.is_nan()).then_some(ask_px),
+            bid_sz,
+            ask_sz,
+            ts_event: chrono::DateTime::from_timestamp_nanos(ts_event as i64),
+            ts_recv: chrono::DateTime::from_timestamp_nanos(ts_recv as i64),
+        });
+        true
+    })
+}
 
-    // Create a tokio runtime for async execution
-    let runtime = match Runtime::new() {
-        Ok(rt) => rt,
-        Err(_) => {
-            return create_error_result(
-                PmzErrorCode::Other,
-                "Failed to create async runtime",
-            );
+/// Retrieves the latest quote of the instrument mapped to `symbol`.
+///
+/// # Returns
+///
+/// `true` if the quote was written to `quote`, `false` if there's no quote for
+/// `symbol`, any pointer is null, or `quote.struct_size` is smaller than this library's
+/// `CQuote`.
+///
+/// # Safety
+///
+/// `board` must be null or a pointer returned by `db_quotes_create` that hasn't been
+/// destroyed, `symbol` must be null or a valid null-terminated string, and `quote`
+/// must be null or valid for reads and writes of `quote.struct_size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn db_quotes_get(
+    board: *const DbQuoteBoard,
+    symbol: *const c_char,
+    quote: *mut CQuote,
+) -> bool {
+    catch_panic(|| {
+        let (Some(board), false, false) = (board.as_ref(), symbol.is_null(), quote.is_null())
+        else {
+            return false;
+        };
+        if !check_struct_size::<CQuote>((*quote).struct_size) {
+            return false;
         }
-    };
+        let Ok(symbol) = CStr::from_ptr(symbol).to_str() else {
+            return false;
+        };
+        let Some(latest) = board.board().get(symbol).copied() else {
+            return false;
+        };
+        quote.write(CQuote {
+            struct_size: struct_size::<CQuote>(),
+            instrument_id: latest.instrument_id,
+            bid_px: latest.bid_px.unwrap_or(f64::NAN),
+            ask_px: latest.ask_px.unwrap_or(f64::NAN),
+            bid_sz: latest.bid_sz,
+            ask_sz: latest.ask_sz,
+            ts_event: latest.ts_event.timestamp_nanos_opt().unwrap_or(0) as u64,
+            ts_recv: latest.ts_recv.timestamp_nanos_opt().unwrap_or(0) as u64,
+        });
+        true
+    })
+}
 
-    // Run the PMZ calculation
-    let result = runtime.block_on(async {
-        es_futures_pmz::calculate_pmz(api_key_cstr, parse_date, false).await
-    });
+/// Fetches the prior-day key levels of a CME Globex futures symbol: the previous
+/// regular trading hours (RTH) session's high, low, close, and settlement, and the
+/// overnight high and low before the RTH open.
+///
+/// # Parameters
+///
+/// * `api_key` - Databento API key (null-terminated C string)
+/// * `dataset` - Dataset code such as `GLBX.MDP3` (null-terminated C string)
+/// * `symbol` - Symbol such as `ES.c.0` or `ESM5` (null-terminated C string)
+/// * `date` - Optional date in YYYY-MM-DD format (null-terminated C string), or NULL for today
+///
+/// # Returns
+///
+/// A pointer to a heap-allocated `CLevelsResult` struct. The caller must free this
+/// memory by calling `levels_free_result` when done.
+///
+/// # Safety
+///
+/// `api_key`, `dataset`, `symbol`, and `date` must be null or valid null-terminated C
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn levels_calculate(
+    api_key: *const c_char,
+    dataset: *const c_char,
+    symbol: *const c_char,
+    date: *const c_char,
+) -> *mut CLevelsResult {
+    catch_panic(|| {
+        let api_key = match to_api_key(api_key) {
+            Ok(key) => key,
+            Err(e) => return create_levels_error_result(PmzErrorCode::InvalidApiKey, &e),
+        };
+        let dataset = match to_str(dataset, "Dataset").and_then(|dataset| {
+            dataset
+                .parse::<dbn::Dataset>()
+                .map_err(|e| format!("Invalid dataset: {e}"))
+        }) {
+            Ok(dataset) => dataset,
+            Err(e) => return create_levels_error_result(PmzErrorCode::Other, &e),
+        };
+        let symbol = match to_str(symbol, "Symbol") {
+            Ok(symbol) => symbol,
+            Err(e) => return create_levels_error_result(PmzErrorCode::Other, &e),
+        };
+        let date = match to_date(date) {
+            Ok(date) => date,
+            Err(e) => return create_levels_error_result(PmzErrorCode::InvalidDate, &e),
+        };
+        let client = match DbClient::with_key(api_key) {
+            Ok(client) => client,
+            Err(e) => return create_levels_error_result(PmzErrorCode::Other, &e),
+        };
+        let mut historical = client.client.as_async().clone();
+        let result = client.client.block_on(levels::calculate_levels(
+            &mut historical,
+            dataset.as_str(),
+            symbol,
+            date,
+        ));
+        match result {
+            Ok(levels) => {
+                clear_last_error();
+                let to_c_string = |date: NaiveDate| {
+                    CString::new(date.to_string())
+                        .unwrap_or_default()
+                        .into_raw()
+                };
+                Box::into_raw(Box::new(CLevelsResult {
+                    struct_size: struct_size::<CLevelsResult>(),
+                    error_code: PmzErrorCode::Success,
+                    error_message: ptr::null_mut(),
+                    date: to_c_string(levels.date),
+                    prev_date: to_c_string(levels.prev_date),
+                    prev_high: levels.prev_high.unwrap_or(f64::NAN),
+                    prev_low: levels.prev_low.unwrap_or(f64::NAN),
+                    prev_close: levels.prev_close.unwrap_or(f64::NAN),
+                    prev_settlement: levels.prev_settlement.unwrap_or(f64::NAN),
+                    overnight_high: levels.overnight_high.unwrap_or(f64::NAN),
+                    overnight_low: levels.overnight_low.unwrap_or(f64::NAN),
+                }))
+            }
+            Err(e) => create_levels_error_result(
+                PmzErrorCode::from(&e),
+                &format!("Levels calculation failed: {e}"),
+            ),
+        }
+    })
+}
+
+/// Frees memory allocated by `levels_calculate`.
+///
+/// # Safety
+///
+/// This function must be called with a pointer returned by `levels_calculate`.
+/// Calling it with any other pointer is undefined behavior.
+#[no_mangle]
+pub unsafe extern "C" fn levels_free_result(result: *mut CLevelsResult) {
+    catch_panic(|| {
+        if result.is_null() {
+            return;
+        }
+        let result = Box::from_raw(result);
+        for s in [result.error_message, result.date, result.prev_date] {
+            if !s.is_null() {
+                drop(CString::from_raw(s));
+            }
+        }
+    })
+}
+
+/// Converts and validates a C string API key, returning an error result on failure.
+/// The key is zeroed out when dropped and is never included in error messages.
+unsafe fn parse_api_key(api_key: *const c_char) -> Result<ApiKey, *mut CPmzResult> {
+    if api_key.is_null() {
+        return Err(create_error_result(
+            PmzErrorCode::InvalidApiKey,
+            "API key cannot be null",
+        ));
+    }
+    match CStr::from_ptr(api_key).to_str() {
+        Ok(s) => s
+            .parse::<ApiKey>()
+            .map_err(|e| create_error_result(PmzErrorCode::InvalidApiKey, &e.to_string())),
+        Err(_) => Err(create_error_result(
+            PmzErrorCode::InvalidApiKey,
+            "API key contains invalid UTF-8",
+        )),
+    }
+}
+
+/// Converts and validates a C string API key.
+unsafe fn to_api_key(api_key: *const c_char) -> Result<ApiKey, String> {
+    if api_key.is_null() {
+        return Err("API key cannot be null".to_owned());
+    }
+    CStr::from_ptr(api_key)
+        .to_str()
+        .map_err(|_| "API key contains invalid UTF-8".to_owned())?
+        .parse::<ApiKey>()
+        .map_err(|e| e.to_string())
+}
+
+/// Converts a required C string argument described by `name`.
+unsafe fn to_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{name} cannot be null"));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| format!("{name} contains invalid UTF-8"))
+}
 
-    // Convert the result to a C-compatible struct
+/// Parses an optional C string date in YYYY-MM-DD format, returning an error result on
+/// failure.
+unsafe fn parse_date(date: *const c_char) -> Result<Option<NaiveDate>, *mut CPmzResult> {
+    to_date(date).map_err(|e| create_error_result(PmzErrorCode::InvalidDate, &e))
+}
+
+/// Parses an optional C string date in YYYY-MM-DD format.
+unsafe fn to_date(date: *const c_char) -> Result<Option<NaiveDate>, String> {
+    if date.is_null() {
+        return Ok(None);
+    }
+    let date_str = CStr::from_ptr(date)
+        .to_str()
+        .map_err(|_| "Date contains invalid UTF-8".to_owned())?;
+    NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+        .map(Some)
+        .map_err(|_| "Invalid date format, expected YYYY-MM-DD".to_owned())
+}
+
+/// Converts the result of a PMZ calculation to a C-compatible struct.
+unsafe fn into_c_result(result: es_futures_pmz::Result<PmzResult>) -> *mut CPmzResult {
     match result {
         Ok(pmz_result) => {
             let date_cstring = match CString::new(pmz_result.date.to_string()) {
@@ -181,38 +1375,236 @@ pub unsafe extern "C" fn pmz_calculate(
                 }
             };
 
+            // A partial result is still returned, with the missing values set to NaN
+            let (error_code, error_message) = if pmz_result.is_complete() {
+                clear_last_error();
+                (PmzErrorCode::Success, ptr::null_mut())
+            } else {
+                let missing = pmz_result
+                    .missing
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let message = format!("Partial PMZ result, missing: {missing}");
+                set_last_error(&message);
+                let message = CString::new(message).unwrap_or_default();
+                (PmzErrorCode::InsufficientData, message.into_raw())
+            };
+
+            let pmz_window = pmz_result.pre_market_times();
+            let lis = pmz_result.lis_times();
+            let to_nanos = |dt: Option<DateTime<Tz>>| {
+                dt.and_then(|dt| dt.timestamp_nanos_opt())
+                    .map_or(0, |nanos| nanos as u64)
+            };
             let result = Box::new(CPmzResult {
-                error_code: PmzErrorCode::Success,
-                error_message: ptr::null_mut(),
+                struct_size: struct_size::<CPmzResult>(),
+                struct_version: PMZ_RESULT_VERSION,
+                error_code,
+                error_message,
                 date: date_cstring.into_raw(),
-                pmh: pmz_result.pmh,
-                pml: pmz_result.pml,
-                prev_day_lis: pmz_result.prev_day_lis,
-                is_gap_up: if pmz_result.is_gap_up { 1 } else { 0 },
-                pmz_high: pmz_result.pmz_high,
-                pmz_low: pmz_result.pmz_low,
-                risk: pmz_result.risk,
+                pmh: pmz_result.pmh.unwrap_or(f64::NAN),
+                pml: pmz_result.pml.unwrap_or(f64::NAN),
+                prev_day_lis: pmz_result.prev_day_lis.unwrap_or(f64::NAN),
+                is_gap_up: match pmz_result.is_gap_up {
+                    Some(true) => 1,
+                    Some(false) => 0,
+                    None => -1,
+                },
+                pmz_high: pmz_result.pmz_high.unwrap_or(f64::NAN),
+                pmz_low: pmz_result.pmz_low.unwrap_or(f64::NAN),
+                risk: pmz_result.risk.unwrap_or(f64::NAN),
+                missing_flags: pmz_result.missing_flags(),
+                gap_points: pmz_result.gap_points.unwrap_or(f64::NAN),
+                gap_percent: pmz_result.gap_percent.unwrap_or(f64::NAN),
+                utc_offset_secs: pmz_window
+                    .map_or(0, |(start, _)| start.offset().fix().local_minus_utc()),
+                pmz_window_start_ns: to_nanos(pmz_window.map(|(start, _)| start)),
+                pmz_window_end_ns: to_nanos(pmz_window.map(|(_, end)| end)),
+                lis_start_ns: to_nanos(lis.map(|(start, _)| start)),
+                lis_end_ns: to_nanos(lis.map(|(_, end)| end)),
             });
 
             Box::into_raw(result)
         }
         Err(e) => {
             create_error_result(
-                PmzErrorCode::DataProcessingFailed,
+                PmzErrorCode::from(&e),
                 &format!("PMZ calculation failed: {}", e),
             )
         }
     }
 }
 
+/// The version of the FFI layer returned by `db_ffi_version`. It's incremented whenever
+/// a struct layout or function signature changes.
+pub const DB_FFI_VERSION: u32 = 2;
+
+/// Returns the version of the FFI layer of the loaded library, `DB_FFI_VERSION`. Callers
+/// should check it matches the version of the header or bindings they were built
+/// against before calling any other function.
+#[no_mangle]
+pub extern "C" fn db_ffi_version() -> u32 {
+    DB_FFI_VERSION
+}
+
+/// Returns the size of `T` for the `struct_size` field of FFI structs.
+const fn struct_size<T>() -> u32 {
+    std::mem::size_of::<T>() as u32
+}
+
+/// Checks the `struct_size` a caller set on a struct it allocated is large enough for
+/// this library's `T`, setting the last error if it isn't, so a caller built against
+/// an older header isn't written past the end of its struct.
+fn check_struct_size<T>(caller_size: u32) -> bool {
+    if caller_size < struct_size::<T>() {
+        set_last_error(&format!(
+            "struct_size of {caller_size} is smaller than the {} bytes of {}",
+            struct_size::<T>(),
+            std::any::type_name::<T>()
+                .rsplit("::")
+                .next()
+                .unwrap_or_default()
+        ));
+        return false;
+    }
+    true
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Returns a description of the last error from an FFI function called on the current
+/// thread, or NULL if the last call succeeded. For `pmz_calculate_async`, errors from
+/// the calculation are set on the worker thread, so they can be retrieved from the
+/// callback.
+///
+/// The returned string is owned by the library and is valid until the next FFI call
+/// on the same thread. It must not be freed.
+#[no_mangle]
+pub extern "C" fn db_last_error_message() -> *const c_char {
+    catch_panic(|| {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message)
+        .unwrap_or_else(|_| CString::new("Error message contains null bytes").unwrap());
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+}
+
+/// Runs the body of an FFI function, returning `T::from_panic()` if it panics instead of
+/// unwinding across the FFI boundary. The panic message is set as the last error.
+fn catch_panic<T: FromPanic>(f: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) => {
+            let reason = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown cause");
+            let message = format!("Internal error: panicked with {reason}");
+            set_last_error(&message);
+            T::from_panic(&message)
+        }
+    }
+}
+
+/// The value an FFI function returns when it panics.
+trait FromPanic {
+    fn from_panic(message: &str) -> Self;
+}
+
+impl FromPanic for () {
+    fn from_panic(_message: &str) -> Self {}
+}
+
+impl FromPanic for bool {
+    fn from_panic(_message: &str) -> Self {
+        false
+    }
+}
+
+impl FromPanic for i64 {
+    fn from_panic(_message: &str) -> Self {
+        -1
+    }
+}
+
+impl FromPanic for usize {
+    fn from_panic(_message: &str) -> Self {
+        0
+    }
+}
+
+impl FromPanic for PmzErrorCode {
+    fn from_panic(_message: &str) -> Self {
+        PmzErrorCode::Other
+    }
+}
+
+impl FromPanic for *const c_char {
+    fn from_panic(_message: &str) -> Self {
+        ptr::null()
+    }
+}
+
+impl FromPanic for *mut CPmzResult {
+    fn from_panic(message: &str) -> Self {
+        unsafe { create_error_result(PmzErrorCode::Other, message) }
+    }
+}
+
+impl FromPanic for *mut CLevelsResult {
+    fn from_panic(message: &str) -> Self {
+        unsafe { create_levels_error_result(PmzErrorCode::Other, message) }
+    }
+}
+
+macro_rules! impl_from_panic_null {
+    ($($handle:ty),+) => {
+        $(
+            impl FromPanic for *mut $handle {
+                fn from_panic(_message: &str) -> Self {
+                    ptr::null_mut()
+                }
+            }
+        )+
+    };
+}
+
+impl_from_panic_null!(
+    DbAlertEngine,
+    DbClient,
+    DbPnlTracker,
+    DbQuoteBoard,
+    DbRequest
+);
+
 /// Creates an error result for returning from C API functions.
 unsafe fn create_error_result(code: PmzErrorCode, message: &str) -> *mut CPmzResult {
+    set_last_error(message);
     let error_message = match CString::new(message) {
         Ok(cs) => cs,
         Err(_) => CString::new("Error message contains null bytes").unwrap(),
     };
 
     let result = Box::new(CPmzResult {
+        struct_size: struct_size::<CPmzResult>(),
+        struct_version: PMZ_RESULT_VERSION,
         error_code: code,
         error_message: error_message.into_raw(),
         date: ptr::null_mut(),
@@ -223,7 +1615,376 @@ unsafe fn create_error_result(code: PmzErrorCode, message: &str) -> *mut CPmzRes
         pmz_high: 0.0,
         pmz_low: 0.0,
         risk: 0.0,
+        missing_flags: 0,
+        gap_points: 0.0,
+        gap_percent: 0.0,
+        utc_offset_secs: 0,
+        pmz_window_start_ns: 0,
+        pmz_window_end_ns: 0,
+        lis_start_ns: 0,
+        lis_end_ns: 0,
     });
 
     Box::into_raw(result)
-}
\ No newline at end of file
+}
+
+/// Creates an error result for returning from `levels_calculate`.
+unsafe fn create_levels_error_result(code: PmzErrorCode, message: &str) -> *mut CLevelsResult {
+    set_last_error(message);
+    let error_message = CString::new(message)
+        .unwrap_or_else(|_| CString::new("Error message contains null bytes").unwrap());
+    Box::into_raw(Box::new(CLevelsResult {
+        struct_size: struct_size::<CLevelsResult>(),
+        error_code: code,
+        error_message: error_message.into_raw(),
+        date: ptr::null_mut(),
+        prev_date: ptr::null_mut(),
+        prev_high: f64::NAN,
+        prev_low: f64::NAN,
+        prev_close: f64::NAN,
+        prev_settlement: f64::NAN,
+        overnight_high: f64::NAN,
+        overnight_low: f64::NAN,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::es_futures_pmz::PmzComponent;
+
+    /// Returns a zeroed struct with `struct_size` set like a C caller would, assuming
+    /// the struct starts with a `u32` `struct_size` field.
+    fn sized<T>(size: u32) -> std::mem::MaybeUninit<T> {
+        let mut out = std::mem::MaybeUninit::<T>::zeroed();
+        unsafe { out.as_mut_ptr().cast::<u32>().write(size) };
+        out
+    }
+
+    #[test]
+    fn test_alerts() {
+        let engine = db_alerts_create(8);
+        unsafe {
+            assert_eq!(
+                db_alerts_add_level(engine, 4, 5300.0, CRetriggerPolicy::Once as u32, 0),
+                -1
+            );
+            assert!(CStr::from_ptr(db_last_error_message())
+                .to_str()
+                .unwrap()
+                .contains("level kind"));
+            assert_eq!(
+                db_alerts_add_level(engine, LevelKind::Custom as u32, 5300.0, 3, 0),
+                -1
+            );
+            let id = db_alerts_add_level(
+                engine,
+                LevelKind::Custom as u32,
+                5300.0,
+                CRetriggerPolicy::Once as u32,
+                0,
+            );
+            assert!(id >= 0);
+            assert_eq!(db_alerts_on_trade(engine, 1, 5299.0, 1), 0);
+            assert_eq!(db_alerts_on_trade(engine, 1, 5300.5, 2), 1);
+            let mut event = sized::<CAlertEvent>(struct_size::<CAlertEvent>());
+            assert!(db_alerts_poll(engine, event.as_mut_ptr()));
+            let event = event.assume_init();
+            assert_eq!(
+                event.struct_size as usize,
+                std::mem::size_of::<CAlertEvent>()
+            );
+            assert_eq!(event.level_id, id as u64);
+            assert_eq!(event.direction, 1);
+            assert_eq!(event.ts_event, 2);
+            let mut next = sized::<CAlertEvent>(struct_size::<CAlertEvent>());
+            assert!(!db_alerts_poll(engine, next.as_mut_ptr()));
+            // Removed after alerting once
+            assert!(!db_alerts_remove_level(engine, id as u64));
+            db_alerts_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_pnl() {
+        let tracker = db_pnl_create(1, 0.25, 50.0);
+        unsafe {
+            assert!(!db_pnl_on_fill(tracker, 2, 1, 5300.0));
+            assert!(CStr::from_ptr(db_last_error_message())
+                .to_str()
+                .unwrap()
+                .contains("order side"));
+            assert!(db_pnl_on_fill(tracker, OrderSide::Buy as u32, 2, 5300.0));
+            db_pnl_on_trade(tracker, 1, 5301.0);
+            let mut pnl = sized::<CPnL>(struct_size::<CPnL>());
+            assert!(db_pnl_get(tracker, pnl.as_mut_ptr()));
+            let pnl = pnl.assume_init();
+            assert_eq!(pnl.position, 2);
+            assert_eq!(pnl.average_price, 5300.0);
+            assert_eq!(pnl.unrealized_dollars, 100.0);
+            db_pnl_destroy(tracker);
+        }
+    }
+
+    #[test]
+    fn test_quotes() {
+        let board = db_quotes_create();
+        let symbol = CString::new("ESM5").unwrap();
+        unsafe {
+            let mut quote = sized::<CQuote>(struct_size::<CQuote>());
+            assert!(!db_quotes_get(board, symbol.as_ptr(), quote.as_mut_ptr()));
+            assert!(db_quotes_update(
+                board,
+                symbol.as_ptr(),
+                1,
+                5300.0,
+                5,
+                f64::NAN,
+                0,
+                1,
+                2
+            ));
+            // A caller built against an older, smaller `CQuote`
+            let mut old_quote = sized::<CQuote>(8);
+            assert!(!db_quotes_get(
+                board,
+                symbol.as_ptr(),
+                old_quote.as_mut_ptr()
+            ));
+            assert_eq!(old_quote.assume_init().instrument_id, 0);
+            assert!(CStr::from_ptr(db_last_error_message())
+                .to_str()
+                .unwrap()
+                .contains("struct_size"));
+            assert!(db_quotes_get(board, symbol.as_ptr(), quote.as_mut_ptr()));
+            let quote = quote.assume_init();
+            assert_eq!(quote.struct_size as usize, std::mem::size_of::<CQuote>());
+            assert_eq!(quote.instrument_id, 1);
+            assert_eq!(quote.bid_px, 5300.0);
+            assert!(quote.ask_px.is_nan());
+            assert_eq!(quote.ts_recv, 2);
+            db_quotes_destroy(board);
+        }
+    }
+
+    #[test]
+    fn test_db_ffi_version() {
+        assert_eq!(db_ffi_version(), DB_FFI_VERSION);
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        assert!(!catch_panic(|| -> bool { panic!("oops") }));
+        let message = unsafe { CStr::from_ptr(db_last_error_message()) };
+        assert!(message.to_str().unwrap().contains("oops"));
+
+        let result = catch_panic(|| -> *mut CPmzResult { panic!("{}", 1) });
+        unsafe {
+            assert!(matches!((*result).error_code, PmzErrorCode::Other));
+            assert!(!(*result).error_message.is_null());
+            pmz_free_result(result);
+        }
+        assert!(catch_panic(|| -> *mut DbClient { panic!() }).is_null());
+        assert!(matches!(catch_panic(|| PmzErrorCode::Success), PmzErrorCode::Success));
+    }
+
+    #[test]
+    fn test_missing_flags_match_components() {
+        let flags = [
+            PMZ_MISSING_PMH,
+            PMZ_MISSING_PML,
+            PMZ_MISSING_PREV_DAY_LIS,
+            PMZ_MISSING_CLOSE_925,
+            PMZ_MISSING_GAP_DIRECTION,
+            PMZ_MISSING_PMZ_HIGH,
+            PMZ_MISSING_PMZ_LOW,
+            PMZ_MISSING_RISK,
+        ];
+        for (component, flag) in PmzComponent::ALL.iter().zip(flags) {
+            assert_eq!(component.flag(), flag, "{}", component.as_str());
+        }
+    }
+
+    #[test]
+    fn test_c_result_timestamps() {
+        use crate::calendar::{TradingCalendar, UsEquityCalendar};
+        use chrono::NaiveTime;
+
+        let calendar = UsEquityCalendar;
+        let date = NaiveDate::from_ymd_opt(2025, 4, 21).unwrap();
+        let pmz_result =
+            PmzResult::from_inputs(date, Some(5310.0), Some(5290.0), Some(5300.0), Some(5305.0))
+                .with_sessions(
+                    calendar.session(date).unwrap(),
+                    calendar
+                        .session(calendar.previous_trading_day(date))
+                        .unwrap(),
+                )
+                .with_pre_market_window(
+                    NaiveTime::from_hms_opt(7, 25, 0).unwrap(),
+                    NaiveTime::from_hms_opt(9, 25, 0).unwrap(),
+                );
+        unsafe {
+            let result = into_c_result(Ok(pmz_result));
+            assert_eq!(
+                (*result).struct_size as usize,
+                std::mem::size_of::<CPmzResult>()
+            );
+            assert_eq!((*result).struct_version, PMZ_RESULT_VERSION);
+            assert_eq!((*result).utc_offset_secs, -4 * 3600);
+            // 2025-04-21 11:25 UTC
+            assert_eq!((*result).pmz_window_start_ns, 1_745_234_700_000_000_000);
+            assert_eq!(
+                (*result).pmz_window_end_ns - (*result).pmz_window_start_ns,
+                2 * 3600 * 1_000_000_000
+            );
+            // 2025-04-17 19:55 UTC
+            assert_eq!((*result).lis_start_ns, 1_744_919_700_000_000_000);
+            assert_eq!(
+                (*result).lis_end_ns - (*result).lis_start_ns,
+                300_000_000_000
+            );
+            pmz_free_result(result);
+
+            let result = create_error_result(PmzErrorCode::Other, "error");
+            assert_eq!(
+                (*result).struct_size as usize,
+                std::mem::size_of::<CPmzResult>()
+            );
+            assert_eq!((*result).struct_version, PMZ_RESULT_VERSION);
+            assert_eq!((*result).lis_start_ns, 0);
+            pmz_free_result(result);
+        }
+    }
+
+    #[test]
+    fn test_db_client_create() {
+        unsafe {
+            assert!(db_client_create(ptr::null()).is_null());
+            let invalid = CString::new("too-short").unwrap();
+            assert!(db_client_create(invalid.as_ptr()).is_null());
+            assert!(CStr::from_ptr(db_last_error_message())
+                .to_str()
+                .unwrap()
+                .contains("key"));
+            let key = CString::new("32-character-with-lots-of-filler").unwrap();
+            let client = db_client_create(key.as_ptr());
+            assert!(!client.is_null());
+            assert!(db_last_error_message().is_null());
+            let date = CString::new("2025-13-01").unwrap();
+            let result = pmz_calculate_with_client(client, date.as_ptr());
+            assert!(matches!((*result).error_code, PmzErrorCode::InvalidDate));
+            assert_eq!(
+                CStr::from_ptr(db_last_error_message()),
+                CStr::from_ptr((*result).error_message)
+            );
+            pmz_free_result(result);
+            db_client_destroy(client);
+        }
+    }
+
+    #[test]
+    fn test_pmz_validate_key_malformed() {
+        unsafe {
+            assert!(matches!(
+                pmz_validate_key(ptr::null()),
+                PmzErrorCode::InvalidApiKey
+            ));
+            let invalid = CString::new("too-short").unwrap();
+            assert!(matches!(
+                pmz_validate_key(invalid.as_ptr()),
+                PmzErrorCode::InvalidApiKey
+            ));
+            assert!(CStr::from_ptr(db_last_error_message())
+                .to_str()
+                .unwrap()
+                .contains("key"));
+        }
+    }
+
+    #[test]
+    fn test_levels_calculate_invalid_args() {
+        unsafe {
+            let key = CString::new("32-character-with-lots-of-filler").unwrap();
+            let dataset = CString::new("GLBX.MDP3").unwrap();
+            let symbol = CString::new("ES.c.0").unwrap();
+            let result =
+                levels_calculate(ptr::null(), dataset.as_ptr(), symbol.as_ptr(), ptr::null());
+            assert!(matches!((*result).error_code, PmzErrorCode::InvalidApiKey));
+            assert!((*result).date.is_null());
+            assert!((*result).prev_high.is_nan());
+            levels_free_result(result);
+
+            let result = levels_calculate(key.as_ptr(), dataset.as_ptr(), ptr::null(), ptr::null());
+            assert!(matches!((*result).error_code, PmzErrorCode::Other));
+            levels_free_result(result);
+
+            let unknown = CString::new("GLBX.MDP4").unwrap();
+            let result =
+                levels_calculate(key.as_ptr(), unknown.as_ptr(), symbol.as_ptr(), ptr::null());
+            assert!(matches!((*result).error_code, PmzErrorCode::Other));
+            assert!(CStr::from_ptr((*result).error_message)
+                .to_str()
+                .unwrap()
+                .starts_with("Invalid dataset"));
+            levels_free_result(result);
+
+            let date = CString::new("04/21/2025").unwrap();
+            let result = levels_calculate(
+                key.as_ptr(),
+                dataset.as_ptr(),
+                symbol.as_ptr(),
+                date.as_ptr(),
+            );
+            assert!(matches!((*result).error_code, PmzErrorCode::InvalidDate));
+            assert_eq!(
+                CStr::from_ptr((*result).error_message).to_str().unwrap(),
+                "Invalid date format, expected YYYY-MM-DD"
+            );
+            levels_free_result(result);
+            levels_free_result(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_cancelled_request() {
+        unsafe {
+            let key = CString::new("32-character-with-lots-of-filler").unwrap();
+            let client = db_client_create(key.as_ptr());
+            let request = db_request_begin();
+            db_request_cancel(request);
+            let result = pmz_calculate_cancellable(client, ptr::null(), request);
+            assert!(matches!((*result).error_code, PmzErrorCode::Cancelled));
+            pmz_free_result(result);
+            db_request_free(request);
+            db_client_destroy(client);
+        }
+    }
+
+    extern "C" fn send_error_code(result: *mut CPmzResult, user_data: *mut c_void) {
+        // Take ownership so the sender outlives the send even after the test returns
+        let tx = unsafe { Box::from_raw(user_data as *mut std::sync::mpsc::Sender<i32>) };
+        tx.send(unsafe { (*result).error_code } as i32).unwrap();
+        unsafe { pmz_free_result(result) };
+    }
+
+    #[test]
+    fn test_pmz_calculate_async() {
+        let (tx, rx) = std::sync::mpsc::channel::<i32>();
+        let key = CString::new("32-character-with-lots-of-filler").unwrap();
+        let date = CString::new("not-a-date").unwrap();
+        let code = unsafe {
+            pmz_calculate_async(
+                key.as_ptr(),
+                date.as_ptr(),
+                Some(send_error_code),
+                Box::into_raw(Box::new(tx)) as *mut c_void,
+            )
+        };
+        assert!(matches!(code, PmzErrorCode::Success));
+        // The strings are copied before returning
+        drop((key, date));
+        let code = rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+        assert_eq!(code, PmzErrorCode::InvalidDate as i32);
+    }
+}
@@ -0,0 +1,320 @@
+//! Detecting large and block trades.
+//!
+//! A [`BlockTradeDetector`] flags trades at or above a size or notional threshold in a
+//! [`BlockTradeConfig`]. The notional value of a trade is its price times its size
+//! times the contract multiplier of its instrument, which is taken from the
+//! instrument's definition, so the same dollar threshold works across contracts. The
+//! detector accepts records from a live session or a historical decoder through
+//! [`on_record()`](BlockTradeDetector::on_record) and summarizes the flagged trades of
+//! each instrument per trading session.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use dbn::{enums::Side, InstrumentDefMsg, Mbp1Msg, RecordRef, TradeMsg, UNDEF_PRICE};
+
+use crate::{calendar::Session, portfolio::InstrumentSpec};
+
+/// The thresholds a trade must meet to be flagged. A trade is flagged when it meets
+/// either threshold that's set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BlockTradeConfig {
+    /// The smallest size in contracts or shares to flag.
+    pub min_size: Option<u32>,
+    /// The smallest notional value to flag, in dollars or the instrument's currency.
+    pub min_notional: Option<f64>,
+}
+
+/// A trade that met a threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockTrade {
+    /// The instrument ID of the trade.
+    pub instrument_id: u32,
+    /// The trade's `ts_event`.
+    pub timestamp: DateTime<Utc>,
+    /// The trade price.
+    pub price: f64,
+    /// The trade size.
+    pub size: u32,
+    /// The price times the size times the contract multiplier.
+    pub notional: f64,
+    /// The aggressor side, if any.
+    pub side: Option<Side>,
+    /// Whether the trade met the size threshold.
+    pub exceeds_size: bool,
+    /// Whether the trade met the notional threshold.
+    pub exceeds_notional: bool,
+}
+
+/// The flagged trades of an instrument in a trading session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockTradeSummary {
+    /// The trading date of the session.
+    pub date: NaiveDate,
+    /// The instrument ID of the trades.
+    pub instrument_id: u32,
+    /// The number of flagged trades.
+    pub count: u64,
+    /// The total size of the flagged trades.
+    pub volume: u64,
+    /// The size of the flagged trades initiated by buyers.
+    pub buy_volume: u64,
+    /// The size of the flagged trades initiated by sellers.
+    pub sell_volume: u64,
+    /// The total notional value of the flagged trades.
+    pub notional: f64,
+    /// The flagged trade with the largest notional value.
+    pub largest: BlockTrade,
+}
+
+/// Flags trades meeting the thresholds of a [`BlockTradeConfig`] and summarizes them
+/// per instrument and trading session.
+#[derive(Debug, Clone)]
+pub struct BlockTradeDetector {
+    config: BlockTradeConfig,
+    session: Option<Session>,
+    multipliers: HashMap<u32, f64>,
+    summaries: HashMap<(NaiveDate, u32), BlockTradeSummary>,
+}
+
+impl BlockTradeDetector {
+    /// Creates a detector with the thresholds in `config` that summarizes trades by
+    /// their UTC date.
+    pub fn new(config: BlockTradeConfig) -> Self {
+        Self {
+            config,
+            session: None,
+            multipliers: HashMap::new(),
+            summaries: HashMap::new(),
+        }
+    }
+
+    /// Summarizes trades by the trading date of `session` instead of the UTC date,
+    /// e.g. [`Session::CME_GLOBEX`] to group evening trades with the next day. Trades
+    /// while the market is closed are summarized by their date in the session's
+    /// timezone.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    /// Sets the contract multiplier of `instrument_id`. Instruments without one use a
+    /// multiplier of 1.
+    pub fn set_multiplier(&mut self, instrument_id: u32, multiplier: f64) {
+        self.multipliers.insert(instrument_id, multiplier);
+    }
+
+    /// Sets the contract multiplier of the instrument of `definition` as described in
+    /// [`InstrumentSpec::from_definition()`].
+    pub fn on_definition(&mut self, definition: &InstrumentDefMsg) {
+        self.set_multiplier(
+            definition.hd.instrument_id,
+            InstrumentSpec::from_definition(definition).multiplier,
+        );
+    }
+
+    /// Handles `rec` if it's a trade, an MBP-1 trade, or an instrument definition,
+    /// returning the trade if it was flagged.
+    pub fn on_record(&mut self, rec: &RecordRef) -> Option<BlockTrade> {
+        if let Some(trade) = rec.get::<TradeMsg>() {
+            self.on_trade(trade)
+        } else if let Some(mbp1) = rec.get::<Mbp1Msg>() {
+            self.on_mbp1(mbp1)
+        } else {
+            if let Some(definition) = rec.get::<InstrumentDefMsg>() {
+                self.on_definition(definition);
+            }
+            None
+        }
+    }
+
+    /// Returns `trade` as a [`BlockTrade`] if it meets a threshold, adding it to the
+    /// summary of its session.
+    pub fn on_trade(&mut self, trade: &TradeMsg) -> Option<BlockTrade> {
+        self.check(
+            trade.hd.instrument_id,
+            trade.hd.ts_event,
+            trade.price,
+            trade.size,
+            trade.side().ok(),
+        )
+    }
+
+    /// Like [`on_trade()`](Self::on_trade) for MBP-1 records whose action is a trade.
+    /// Other actions are ignored.
+    pub fn on_mbp1(&mut self, mbp1: &Mbp1Msg) -> Option<BlockTrade> {
+        if mbp1.action as u8 != b'T' {
+            return None;
+        }
+        self.check(
+            mbp1.hd.instrument_id,
+            mbp1.hd.ts_event,
+            mbp1.price,
+            mbp1.size,
+            mbp1.side().ok(),
+        )
+    }
+
+    /// Returns the summary of `instrument_id` for the session on `date`, if any of
+    /// its trades were flagged.
+    pub fn summary(&self, date: NaiveDate, instrument_id: u32) -> Option<&BlockTradeSummary> {
+        self.summaries.get(&(date, instrument_id))
+    }
+
+    /// Returns the summaries of every session and instrument with flagged trades,
+    /// ordered by date and then instrument ID.
+    pub fn summaries(&self) -> Vec<BlockTradeSummary> {
+        let mut summaries: Vec<_> = self.summaries.values().copied().collect();
+        summaries.sort_by_key(|summary| (summary.date, summary.instrument_id));
+        summaries
+    }
+
+    /// Clears the summaries, keeping the multipliers.
+    pub fn reset(&mut self) {
+        self.summaries.clear();
+    }
+
+    fn check(
+        &mut self,
+        instrument_id: u32,
+        ts_event: u64,
+        price: i64,
+        size: u32,
+        side: Option<Side>,
+    ) -> Option<BlockTrade> {
+        let price = if price == UNDEF_PRICE {
+            f64::NAN
+        } else {
+            price as f64 * 1e-9
+        };
+        let multiplier = self.multipliers.get(&instrument_id).copied().unwrap_or(1.0);
+        let notional = price * f64::from(size) * multiplier;
+        let exceeds_size = self.config.min_size.is_some_and(|min| size >= min);
+        // Comparisons with NaN are false, so trades without a price only meet the size
+        // threshold
+        let exceeds_notional = self.config.min_notional.is_some_and(|min| notional >= min);
+        if !exceeds_size && !exceeds_notional {
+            return None;
+        }
+        let timestamp = DateTime::from_timestamp_nanos(ts_event as i64);
+        let trade = BlockTrade {
+            instrument_id,
+            timestamp,
+            price,
+            size,
+            notional,
+            side: side.filter(|side| *side != Side::None),
+            exceeds_size,
+            exceeds_notional,
+        };
+        let date = match self.session {
+            Some(session) => session
+                .trading_date(&timestamp)
+                .unwrap_or_else(|| timestamp.with_timezone(&session.tz).date_naive()),
+            None => timestamp.date_naive(),
+        };
+        let summary = self
+            .summaries
+            .entry((date, instrument_id))
+            .or_insert(BlockTradeSummary {
+                date,
+                instrument_id,
+                count: 0,
+                volume: 0,
+                buy_volume: 0,
+                sell_volume: 0,
+                notional: 0.0,
+                largest: trade,
+            });
+        summary.count += 1;
+        summary.volume += u64::from(size);
+        match trade.side {
+            Some(Side::Bid) => summary.buy_volume += u64::from(size),
+            Some(Side::Ask) => summary.sell_volume += u64::from(size),
+            _ => {}
+        }
+        if notional.is_finite() {
+            summary.notional += notional;
+        }
+        if trade.notional > summary.largest.notional {
+            summary.largest = trade;
+        }
+        Some(trade)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use chrono::TimeZone;
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+    use crate::test_util;
+
+    fn trade(instrument_id: u32, ts: DateTime<Utc>, price: f64, size: u32) -> TradeMsg {
+        test_util::trade(
+            instrument_id,
+            ts.timestamp_nanos_opt().unwrap() as u64,
+            (price * 1e9) as i64,
+            size,
+        )
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let mut detector = BlockTradeDetector::new(BlockTradeConfig {
+            min_size: Some(100),
+            min_notional: Some(5_000_000.0),
+        });
+        let ts = Utc.with_ymd_and_hms(2025, 4, 22, 14, 0, 0).unwrap();
+        assert!(detector.on_trade(&trade(1, ts, 5300.0, 10)).is_none());
+        let block = detector.on_trade(&trade(1, ts, 5300.0, 100)).unwrap();
+        assert!(block.exceeds_size && !block.exceeds_notional);
+        assert_eq!(block.side, Some(Side::Bid));
+        let definition = InstrumentDefMsg {
+            hd: RecordHeader::new::<InstrumentDefMsg>(rtype::INSTRUMENT_DEF, 1, 1, 0),
+            min_price_increment: 250_000_000,
+            unit_of_measure_qty: 50_000_000_000,
+            ..Default::default()
+        };
+        assert!(detector.on_record(&RecordRef::from(&definition)).is_none());
+        // 20 contracts of $265,000 each
+        let block = detector
+            .on_record(&RecordRef::from(&trade(1, ts, 5300.0, 20)))
+            .unwrap();
+        assert!(!block.exceeds_size && block.exceeds_notional);
+        assert_eq!(block.notional, 5_300_000.0);
+    }
+
+    #[test]
+    fn test_session_summaries() {
+        let mut detector = BlockTradeDetector::new(BlockTradeConfig {
+            min_size: Some(50),
+            min_notional: None,
+        })
+        .with_session(Session::CME_GLOBEX);
+        // 19:00 ET on the 21st is in the session of the 22nd
+        let evening = Utc.with_ymd_and_hms(2025, 4, 21, 23, 0, 0).unwrap();
+        let day = Utc.with_ymd_and_hms(2025, 4, 22, 14, 0, 0).unwrap();
+        detector.on_trade(&trade(1, evening, 5300.0, 50));
+        let mut sell = trade(1, day, 5310.0, 80);
+        sell.side = b'A' as c_char;
+        detector.on_trade(&sell);
+        detector.on_trade(&trade(2, day, 100.0, 60));
+        let date = NaiveDate::from_ymd_opt(2025, 4, 22).unwrap();
+        let summary = detector.summary(date, 1).unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(
+            (summary.volume, summary.buy_volume, summary.sell_volume),
+            (130, 50, 80)
+        );
+        assert_eq!(summary.largest.price, 5310.0);
+        let summaries = detector.summaries();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[1].instrument_id, 2);
+        detector.reset();
+        assert!(detector.summaries().is_empty());
+    }
+}
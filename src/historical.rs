@@ -1,18 +1,30 @@
 //! Historical client and related API types.
 
+pub mod adjustment;
+mod api;
 pub mod batch;
+pub mod billing;
+pub mod checksum;
 mod client;
 mod deserialize;
 pub mod metadata;
+mod pool;
+mod rate_limit;
 pub mod symbology;
 pub mod timeseries;
 
+pub use api::HistoricalApi;
 pub use client::*;
+pub use pool::ClientPool;
+pub use rate_limit::RateLimiter;
 use time::{
     format_description::BorrowedFormatItem, macros::format_description, Duration, Time, UtcOffset,
 };
 
-use crate::{Error, Symbols};
+use crate::{
+    timeconv::{ToOffsetDateTime, ToTimeDate},
+    Error, Symbols,
+};
 
 /// The current Databento historical API version.
 pub const API_VERSION: u32 = 0;
@@ -139,6 +151,71 @@ impl TryFrom<(u64, u64)> for DateTimeRange {
     }
 }
 
+/// # Errors
+/// Returns an error if either date is out of the range of [`time::Date`].
+impl TryFrom<(chrono::NaiveDate, chrono::NaiveDate)> for DateRange {
+    type Error = crate::Error;
+
+    fn try_from(value: (chrono::NaiveDate, chrono::NaiveDate)) -> Result<Self, Self::Error> {
+        Ok(Self::from((
+            value.0.to_time_date()?,
+            value.1.to_time_date()?,
+        )))
+    }
+}
+
+/// # Errors
+/// Returns an error if the date is out of the range of [`time::Date`].
+impl TryFrom<chrono::NaiveDate> for DateRange {
+    type Error = crate::Error;
+
+    fn try_from(date: chrono::NaiveDate) -> Result<Self, Self::Error> {
+        Ok(Self::from(date.to_time_date()?))
+    }
+}
+
+/// # Errors
+/// Returns an error if the date is out of the range of [`time::Date`].
+impl TryFrom<chrono::NaiveDate> for DateTimeRange {
+    type Error = crate::Error;
+
+    fn try_from(date: chrono::NaiveDate) -> Result<Self, Self::Error> {
+        Ok(Self::from(date.to_time_date()?))
+    }
+}
+
+/// Works with any [`chrono::TimeZone`], including the timezones of `chrono-tz`.
+///
+/// # Errors
+/// Returns an error if either datetime is out of the range of UNIX nanosecond
+/// timestamps, i.e. before 1677 or after 2262.
+impl<Tz: chrono::TimeZone> TryFrom<(chrono::DateTime<Tz>, chrono::DateTime<Tz>)> for DateTimeRange {
+    type Error = crate::Error;
+
+    fn try_from(value: (chrono::DateTime<Tz>, chrono::DateTime<Tz>)) -> Result<Self, Self::Error> {
+        Ok(Self::from((
+            value.0.to_offset_date_time()?,
+            value.1.to_offset_date_time()?,
+        )))
+    }
+}
+
+/// # Errors
+/// Returns an error if the start or end is out of the range of UNIX nanosecond
+/// timestamps, i.e. before 1677 or after 2262.
+impl<Tz: chrono::TimeZone> TryFrom<(chrono::DateTime<Tz>, chrono::Duration)> for DateTimeRange {
+    type Error = crate::Error;
+
+    fn try_from(value: (chrono::DateTime<Tz>, chrono::Duration)) -> Result<Self, Self::Error> {
+        let end = value
+            .0
+            .clone()
+            .checked_add_signed(value.1)
+            .ok_or_else(|| Error::bad_arg("duration", "end is out of range"))?;
+        Self::try_from((value.0, end))
+    }
+}
+
 trait AddToQuery<T> {
     fn add_to_query(self, param: &T) -> Self;
 }
@@ -168,6 +245,16 @@ impl AddToQuery<Symbols> for reqwest::RequestBuilder {
 }
 
 impl DateRange {
+    #[cfg(feature = "testing")]
+    pub(crate) fn start(&self) -> time::Date {
+        self.start
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn end(&self) -> time::Date {
+        self.end
+    }
+
     pub(crate) fn add_to_form(&self, form: &mut Vec<(&'static str, String)>) {
         form.push(("start_date", self.start.format(DATE_FORMAT).unwrap()));
         form.push(("end_date", self.end.format(DATE_FORMAT).unwrap()));
@@ -175,6 +262,61 @@ impl DateRange {
 }
 
 impl DateTimeRange {
+    /// Creates a range covering the `days` days up to now.
+    pub fn last_n_days(days: u32) -> Self {
+        let end = time::OffsetDateTime::now_utc();
+        Self {
+            start: end - Duration::days(days.into()),
+            end,
+        }
+    }
+
+    /// Creates a range covering the session of `date` in `calendar`, including the
+    /// overnight part of sessions that open the evening before, like CME Globex.
+    ///
+    /// # Errors
+    /// This function returns an error when `date` isn't a trading day in `calendar` or
+    /// the session times are out of range.
+    pub fn trading_day(
+        date: chrono::NaiveDate,
+        calendar: &impl crate::calendar::TradingCalendar,
+    ) -> crate::Result<Self> {
+        let session = calendar
+            .session(date)
+            .ok_or_else(|| Error::bad_arg("date", format!("{date} isn't a trading day")))?;
+        Self::session(&session, calendar.timezone())
+    }
+
+    /// Creates a range from the open to the close of `session`, whose times are local
+    /// to `tz`. A session that closes at or before the time it opens is an overnight
+    /// session, which opens on the day before its trading date, e.g. 18:00–17:00 ET.
+    ///
+    /// Open and close times falling in a daylight saving time transition are resolved
+    /// to the earliest instant.
+    ///
+    /// # Errors
+    /// This function returns an error when the session times are out of range.
+    pub fn session(
+        session: &crate::calendar::TradingSession,
+        tz: chrono_tz::Tz,
+    ) -> crate::Result<Self> {
+        let (open, close) = session.open_close(tz)?;
+        Ok(Self {
+            start: open.to_offset_date_time()?,
+            end: close.to_offset_date_time()?,
+        })
+    }
+
+    /// Returns the start of the range (inclusive).
+    pub fn start(&self) -> time::OffsetDateTime {
+        self.start
+    }
+
+    /// Returns the end of the range (exclusive).
+    pub fn end(&self) -> time::OffsetDateTime {
+        self.end
+    }
+
     pub(crate) fn add_to_form(&self, form: &mut Vec<(&'static str, String)>) {
         form.push(("start", self.start.unix_timestamp_nanos().to_string()));
         form.push(("end", self.end.unix_timestamp_nanos().to_string()));
@@ -215,6 +357,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn relative_ranges() {
+        use chrono::{NaiveDate, NaiveTime};
+
+        use crate::calendar::{FixedHoursCalendar, UsEquityCalendar};
+
+        let target = DateTimeRange::last_n_days(5);
+        assert_eq!(target.end() - target.start(), time::Duration::days(5));
+
+        let thursday = NaiveDate::from_ymd_opt(2025, 3, 27).unwrap();
+        assert_eq!(
+            DateTimeRange::trading_day(thursday, &UsEquityCalendar).unwrap(),
+            DateTimeRange::from((
+                datetime!(2025 - 03 - 27 13:30 UTC),
+                datetime!(2025 - 03 - 27 20:00 UTC)
+            ))
+        );
+        let globex = FixedHoursCalendar::new(
+            NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            chrono_tz::America::New_York,
+        );
+        // Monday's session opens on Sunday evening
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 24).unwrap();
+        assert_eq!(
+            DateTimeRange::trading_day(monday, &globex).unwrap(),
+            DateTimeRange::from((
+                datetime!(2025 - 03 - 23 22:00 UTC),
+                datetime!(2025 - 03 - 24 21:00 UTC)
+            ))
+        );
+        let saturday = NaiveDate::from_ymd_opt(2025, 3, 29).unwrap();
+        assert!(DateTimeRange::trading_day(saturday, &globex).is_err());
+    }
+
+    #[test]
+    fn chrono_conversion() {
+        use chrono::{NaiveDate, TimeZone, Utc};
+        use chrono_tz::America::Chicago;
+
+        let start = NaiveDate::from_ymd_opt(2025, 3, 27).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 4, 10).unwrap();
+        assert_eq!(
+            DateRange::try_from((start, end)).unwrap(),
+            DateRange::from((date!(2025 - 03 - 27), date!(2025 - 04 - 10)))
+        );
+        assert_eq!(
+            DateRange::try_from(start).unwrap(),
+            DateRange::from(date!(2025 - 03 - 27))
+        );
+        assert_eq!(
+            DateTimeRange::try_from(start).unwrap(),
+            DateTimeRange::from(date!(2025 - 03 - 27))
+        );
+        let expected = DateTimeRange::from((
+            datetime!(2025 - 03 - 27 13:30 UTC),
+            datetime!(2025 - 03 - 27 20:00 UTC),
+        ));
+        assert_eq!(
+            DateTimeRange::try_from((
+                Utc.with_ymd_and_hms(2025, 3, 27, 13, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2025, 3, 27, 20, 0, 0).unwrap(),
+            ))
+            .unwrap(),
+            expected
+        );
+        assert_eq!(
+            DateTimeRange::try_from((
+                Chicago.with_ymd_and_hms(2025, 3, 27, 8, 30, 0).unwrap(),
+                chrono::Duration::minutes(390),
+            ))
+            .unwrap(),
+            expected
+        );
+
+        // Valid chrono values outside the range of UNIX nanosecond timestamps
+        assert!(DateRange::try_from(NaiveDate::MAX).is_err());
+        let late = Utc.with_ymd_and_hms(2300, 1, 1, 0, 0, 0).unwrap();
+        assert!(DateTimeRange::try_from((late, late)).is_err());
+        assert!(DateTimeRange::try_from((
+            Utc.with_ymd_and_hms(2262, 1, 1, 0, 0, 0).unwrap(),
+            chrono::Duration::days(365),
+        ))
+        .is_err());
+    }
+
     #[test]
     fn range_equivalency() {
         let date_range = DateRange::from((date!(2025 - 03 - 27), date!(2025 - 04 - 10)));
@@ -0,0 +1,157 @@
+//! The historical adjustment factors API for equity corporate actions.
+
+use dbn::SType;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use typed_builder::TypedBuilder;
+
+use crate::Symbols;
+
+use super::{check_http_error, deserialize::deserialize_date, DateTimeRange, SendWithRetry};
+
+/// A client for the adjustment factors group of Historical API endpoints.
+#[derive(Debug)]
+pub struct AdjustmentClient<'a> {
+    pub(crate) inner: &'a mut super::Client,
+}
+
+impl AdjustmentClient<'_> {
+    /// Lists the split and dividend adjustment factors of `params.symbols` with an
+    /// ex-date in `params.date_time_range`.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API,
+    /// the API indicates there's an issue with the request, or a record can't be
+    /// parsed.
+    pub async fn get_range(
+        &mut self,
+        params: &GetAdjustmentFactorsParams,
+    ) -> crate::Result<Vec<AdjustmentFactor>> {
+        let mut form = vec![
+            ("symbols", params.symbols.to_api_string()),
+            ("stype_in", params.stype_in.to_string()),
+        ];
+        params.date_time_range.add_to_form(&mut form);
+        let resp = self
+            .post("get_range")?
+            .form(&form)
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
+        let body = check_http_error(resp).await?.text().await?;
+        // The response is newline-delimited JSON
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| {
+                    crate::Error::internal(format!("failed to parse adjustment factor: {e}"))
+                })
+            })
+            .collect()
+    }
+
+    fn post(&mut self, slug: &str) -> crate::Result<RequestBuilder> {
+        self.inner.post(&format!("adjustment_factors.{slug}"))
+    }
+}
+
+/// The parameters for [`AdjustmentClient::get_range()`]. Use
+/// [`GetAdjustmentFactorsParams::builder()`] to get a builder type with all the preset
+/// defaults.
+#[derive(Debug, Clone, TypedBuilder, PartialEq, Eq)]
+pub struct GetAdjustmentFactorsParams {
+    /// The symbols to get adjustment factors for.
+    #[builder(setter(into))]
+    pub symbols: Symbols,
+    /// The symbology type of `symbols`. Defaults to
+    /// [`RawSymbol`](dbn::enums::SType::RawSymbol).
+    #[builder(default = SType::RawSymbol)]
+    pub stype_in: SType,
+    /// The range of ex-dates to get adjustment factors for.
+    #[builder(setter(into))]
+    pub date_time_range: DateTimeRange,
+}
+
+/// A price adjustment factor for a corporate action.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct AdjustmentFactor {
+    /// The symbol of the security.
+    pub symbol: String,
+    /// The first date the security trades without the entitlement.
+    #[serde(deserialize_with = "deserialize_date")]
+    pub ex_date: time::Date,
+    /// The kind of corporate action, e.g. `FSPLT` for a forward split or `DIV` for a
+    /// cash dividend.
+    pub event_type: String,
+    /// The factor to multiply prices before the ex-date by, e.g. `0.25` for a
+    /// four-for-one split.
+    pub factor: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use time::macros::{date, datetime};
+    use wiremock::{
+        matchers::{basic_auth, body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        historical::{HistoricalGateway, API_VERSION},
+        HistoricalClient,
+    };
+
+    const API_KEY: &str = "test-adjustment";
+
+    #[tokio::test]
+    async fn test_get_range() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/adjustment_factors.get_range")))
+            .and(body_string_contains("symbols=NVDA"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_string(
+                concat!(
+                    r#"{"symbol":"NVDA","ex_date":"2024-06-10","event_type":"FSPLT","factor":0.1,"currency":"USD"}"#,
+                    "\n",
+                    r#"{"symbol":"NVDA","ex_date":"2024-06-11","event_type":"DIV","factor":0.9999}"#,
+                    "\n",
+                ),
+            ))
+            .mount(&mock_server)
+            .await;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let factors = target
+            .adjustment()
+            .get_range(
+                &GetAdjustmentFactorsParams::builder()
+                    .symbols("NVDA")
+                    .date_time_range((
+                        datetime!(2024-01-01 00:00 UTC),
+                        datetime!(2025-01-01 00:00 UTC),
+                    ))
+                    .build(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(factors.len(), 2);
+        assert_eq!(factors[0].ex_date, date!(2024 - 06 - 10));
+        assert_eq!(factors[0].factor, 0.1);
+        assert_eq!(factors[1].event_type, "DIV");
+        let table = crate::adjust::AdjustmentTable::from_factors(&factors);
+        let kinds: Vec<_> = table.get("NVDA").iter().map(|a| a.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                crate::adjust::AdjustmentKind::Split,
+                crate::adjust::AdjustmentKind::Dividend
+            ]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! A trait over the historical API requests used by the PMZ calculation and candle
+//! helpers, so they can run against a mock client in tests.
+
+use std::future::Future;
+
+use dbn::decode::AsyncDbnDecoder;
+use tokio::io::AsyncReadExt;
+
+use super::{
+    metadata::{DataAvailability, DatasetInfo, DatasetRange},
+    symbology::{Resolution, ResolveParams},
+    timeseries::GetRangeParams,
+    Client, DateRange,
+};
+
+/// The timeseries, symbology, and metadata requests of the historical API.
+///
+/// Implemented by [`HistoricalClient`](crate::HistoricalClient) and, with the
+/// `testing` feature, by [`MockHistoricalClient`](crate::testing::MockHistoricalClient).
+pub trait HistoricalApi {
+    /// Makes a streaming request for timeseries data. See
+    /// [`TimeseriesClient::get_range()`](super::timeseries::TimeseriesClient::get_range).
+    ///
+    /// # Errors
+    /// This function returns an error when the request fails.
+    fn get_range(
+        &mut self,
+        params: &GetRangeParams,
+    ) -> impl Future<Output = crate::Result<AsyncDbnDecoder<impl AsyncReadExt + Unpin + Send>>> + Send;
+
+    /// Resolves a list of symbols from an input symbology type to an output one. See
+    /// [`SymbologyClient::resolve()`](super::symbology::SymbologyClient::resolve).
+    ///
+    /// # Errors
+    /// This function returns an error when the request fails.
+    fn resolve(
+        &mut self,
+        params: &ResolveParams,
+    ) -> impl Future<Output = crate::Result<Resolution>> + Send;
+
+    /// Requests the availability of `dataset` over `date_range`. See
+    /// [`MetadataClient::get_data_availability()`](super::metadata::MetadataClient::get_data_availability).
+    ///
+    /// # Errors
+    /// This function returns an error when the request fails.
+    fn get_data_availability(
+        &mut self,
+        dataset: &str,
+        date_range: DateRange,
+    ) -> impl Future<Output = crate::Result<DataAvailability>> + Send;
+
+    /// Requests the publishers and schemas of `dataset` and combines them with its
+    /// available `range`, e.g. from
+    /// [`get_data_availability()`](Self::get_data_availability), which isn't requested
+    /// again. See
+    /// [`MetadataClient::get_dataset_info()`](super::metadata::MetadataClient::get_dataset_info).
+    ///
+    /// The default implementation returns `range` without any publishers or schemas.
+    ///
+    /// # Errors
+    /// This function returns an error when the request fails.
+    fn get_dataset_info(
+        &mut self,
+        dataset: &str,
+        range: DatasetRange,
+    ) -> impl Future<Output = crate::Result<DatasetInfo>> + Send {
+        let info = DatasetInfo {
+            dataset: dataset.to_owned(),
+            publishers: Vec::new(),
+            schemas: Vec::new(),
+            range,
+        };
+        async { Ok(info) }
+    }
+}
+
+impl HistoricalApi for Client {
+    async fn get_range(
+        &mut self,
+        params: &GetRangeParams,
+    ) -> crate::Result<AsyncDbnDecoder<impl AsyncReadExt + Unpin + Send>> {
+        self.timeseries().get_range(params).await
+    }
+
+    async fn resolve(&mut self, params: &ResolveParams) -> crate::Result<Resolution> {
+        self.symbology().resolve(params).await
+    }
+
+    async fn get_data_availability(
+        &mut self,
+        dataset: &str,
+        date_range: DateRange,
+    ) -> crate::Result<DataAvailability> {
+        self.metadata()
+            .get_data_availability(dataset, date_range)
+            .await
+    }
+
+    async fn get_dataset_info(
+        &mut self,
+        dataset: &str,
+        range: DatasetRange,
+    ) -> crate::Result<DatasetInfo> {
+        self.metadata()
+            .get_dataset_info_with_range(dataset, range)
+            .await
+    }
+}
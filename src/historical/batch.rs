@@ -11,18 +11,19 @@ use std::{
 
 use dbn::{Compression, Encoding, SType, Schema};
 use futures::StreamExt;
-use reqwest::RequestBuilder;
+use reqwest::{header::RANGE, RequestBuilder, StatusCode};
 use serde::{de, Deserialize, Deserializer};
 use time::OffsetDateTime;
-use tokio::io::BufWriter;
-use tracing::info;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tracing::{info, warn};
 use typed_builder::TypedBuilder;
 
 use crate::{historical::check_http_error, Error, Symbols};
 
 use super::{
+    checksum,
     deserialize::{deserialize_date_time, deserialize_opt_date_time},
-    handle_response, DateTimeRange,
+    handle_response, DateTimeRange, SendWithRetry,
 };
 
 /// A client for the batch group of Historical API endpoints.
@@ -65,7 +66,9 @@ impl BatchClient<'_> {
             form.push(("limit", limit.to_string()));
         }
         let builder = self.post("submit_job")?.form(&form);
-        let resp = builder.send().await?;
+        let resp = builder
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -90,7 +93,9 @@ impl BatchClient<'_> {
         if let Some(ref since) = params.since {
             builder = builder.query(&[("since", &since.unix_timestamp_nanos().to_string())]);
         }
-        let resp = builder.send().await?;
+        let resp = builder
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -103,17 +108,26 @@ impl BatchClient<'_> {
         let resp = self
             .get("list_files")?
             .query(&[("job_id", job_id)])
-            .send()
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
             .await?;
         handle_response(resp).await
     }
 
     /// Downloads the file specified in `params` or all files associated with the job ID.
     ///
+    /// When [`DownloadParams::resume`] is `true` (the default), files that were already
+    /// fully downloaded to `output_dir` are skipped and partially-downloaded files are
+    /// resumed from where they left off.
+    ///
+    /// Every file, including skipped ones, is verified against the SHA256 hash listed by
+    /// the API. When [`DownloadParams::refetch_corrupt`] is `true` (the default), a file
+    /// that doesn't match is downloaded again from the beginning.
+    ///
     /// # Errors
     /// This function returns an error when it fails to communicate with the Databento API
     /// or the API indicates there's an issue with the request. It will also return an
-    /// error if it encounters an issue downloading a file.
+    /// error if it encounters an issue downloading a file, or an [`Error::CorruptCache`]
+    /// if a file doesn't match its hash.
     pub async fn download(&mut self, params: &DownloadParams) -> crate::Result<Vec<PathBuf>> {
         let job_dir = params.output_dir.join(&params.job_id);
         if job_dir.exists() {
@@ -138,11 +152,8 @@ impl BatchClient<'_> {
                 ));
             };
             let output_path = job_dir.join(filename_to_download);
-            let https_url = file_desc
-                .urls
-                .get("https")
-                .ok_or_else(|| Error::internal("Missing https URL for batch file"))?;
-            self.download_file(https_url, &output_path).await?;
+            self.download_verified(file_desc, &output_path, params)
+                .await?;
             Ok(vec![output_path])
         } else {
             let mut paths = Vec::new();
@@ -151,27 +162,108 @@ impl BatchClient<'_> {
                     .output_dir
                     .join(&params.job_id)
                     .join(&file_desc.filename);
-                let https_url = file_desc
-                    .urls
-                    .get("https")
-                    .ok_or_else(|| Error::internal("Missing https URL for batch file"))?;
-                self.download_file(https_url, &output_path).await?;
+                self.download_verified(file_desc, &output_path, params)
+                    .await?;
                 paths.push(output_path);
             }
             Ok(paths)
         }
     }
 
-    async fn download_file(&mut self, url: &str, path: impl AsRef<Path>) -> crate::Result<()> {
+    async fn download_verified(
+        &mut self,
+        file_desc: &BatchFileDesc,
+        path: &Path,
+        params: &DownloadParams,
+    ) -> crate::Result<()> {
+        let https_url = file_desc
+            .urls
+            .get("https")
+            .ok_or_else(|| Error::internal("Missing https URL for batch file"))?;
+        self.download_file(https_url, path, file_desc.size, params.resume)
+            .await?;
+        let expected = match file_desc.hash.split_once(':') {
+            Some(("sha256", expected)) => expected,
+            None if !file_desc.hash.is_empty() => file_desc.hash.as_str(),
+            _ => {
+                warn!(
+                    path = %path.display(),
+                    hash = file_desc.hash,
+                    "Skipping verification of file with unsupported hash"
+                );
+                return Ok(());
+            }
+        };
+        match checksum::verify(path, expected, &checksum::file_sha256(path).await?) {
+            Err(err) if params.refetch_corrupt => {
+                warn!(?err, "Downloading corrupt file again");
+                self.download_file(https_url, path, file_desc.size, false)
+                    .await?;
+                checksum::verify(path, expected, &checksum::file_sha256(path).await?)
+            }
+            res => res,
+        }
+    }
+
+    async fn download_file(
+        &mut self,
+        url: &str,
+        path: impl AsRef<Path>,
+        expected_size: u64,
+        resume: bool,
+    ) -> crate::Result<()> {
+        let path = path.as_ref();
         let url = reqwest::Url::parse(url)
             .map_err(|e| Error::internal(format!("Unable to parse URL: {e:?}")))?;
-        let resp = self.inner.get_with_path(url.path())?.send().await?;
-        let mut stream = check_http_error(resp).await?.bytes_stream();
-        info!(%url, path=%path.as_ref().display(), "Downloading file");
+        let existing_size = if resume {
+            match tokio::fs::metadata(path).await {
+                Ok(metadata) => metadata.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(e.into()),
+            }
+        } else {
+            0
+        };
+        if existing_size == expected_size && expected_size > 0 {
+            info!(%url, path=%path.display(), "Skipping already downloaded file");
+            return Ok(());
+        }
+        let mut builder = self.inner.get_with_path(url.path())?;
+        let resume_from = if existing_size > 0 && existing_size < expected_size {
+            builder = builder.header(RANGE, format!("bytes={existing_size}-"));
+            Some(existing_size)
+        } else {
+            None
+        };
+        let resp = check_http_error(
+            builder
+                .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+                .await?,
+        )
+        .await?;
+        let append = match resume_from {
+            Some(_) if resp.status() == StatusCode::PARTIAL_CONTENT => true,
+            Some(offset) => {
+                warn!(
+                    %url,
+                    offset,
+                    "Server doesn't support resuming downloads, restarting from the beginning"
+                );
+                false
+            }
+            None => false,
+        };
+        let mut stream = resp.bytes_stream();
+        if let Some(offset) = resume_from.filter(|_| append) {
+            info!(%url, path=%path.display(), offset, "Resuming download of file");
+        } else {
+            info!(%url, path=%path.display(), "Downloading file");
+        }
         let mut output = BufWriter::new(
             tokio::fs::OpenOptions::new()
                 .create(true)
-                .truncate(true)
+                .append(append)
+                .truncate(!append)
                 .write(true)
                 .open(path)
                 .await?,
@@ -179,6 +271,7 @@ impl BatchClient<'_> {
         while let Some(chunk) = stream.next().await {
             tokio::io::copy(&mut chunk?.as_ref(), &mut output).await?;
         }
+        output.flush().await?;
         Ok(())
     }
 
@@ -410,6 +503,14 @@ pub struct DownloadParams {
     /// `None` means all files associated with the job will be downloaded.
     #[builder(default, setter(strip_option))]
     pub filename_to_download: Option<String>,
+    /// Whether to skip files that were already downloaded and resume partial
+    /// downloads. Defaults to `true`.
+    #[builder(default = true)]
+    pub resume: bool,
+    /// Whether to download a file again when it doesn't match its hash, instead of
+    /// returning an error. Defaults to `true`.
+    #[builder(default = true)]
+    pub refetch_corrupt: bool,
 }
 
 impl SplitDuration {
@@ -557,9 +658,10 @@ fn deserialize_compression<'de, D: serde::Deserializer<'de>>(
 mod tests {
     use reqwest::StatusCode;
     use serde_json::json;
+    use sha2::{Digest, Sha256};
     use time::macros::datetime;
     use wiremock::{
-        matchers::{basic_auth, method, path, query_param_is_missing},
+        matchers::{basic_auth, header, method, path, query_param_is_missing},
         Mock, MockServer, ResponseTemplate,
     };
 
@@ -716,6 +818,116 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_download_resumes_partial_file() -> crate::Result<()> {
+        const JOB_ID: &str = "GLBX-20230614-ABCDEF";
+        const FILENAME: &str = "glbx-mdp3-20230614.trades.dbn.zst";
+        const CONTENTS: &[u8] = b"0123456789";
+
+        let mock_server = MockServer::start().await;
+        let file_path = format!("/v{API_VERSION}/job_download/{JOB_ID}/{FILENAME}");
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/batch.list_files")))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!([{
+                    "filename": FILENAME,
+                    "size": CONTENTS.len(),
+                    "hash": format!("sha256:{}", hex::encode(Sha256::digest(CONTENTS))),
+                    "urls": {
+                        "https": format!("{}{file_path}", mock_server.uri()),
+                    }
+                }])),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(file_path))
+            .and(header(RANGE.as_str(), "bytes=4-"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::PARTIAL_CONTENT.as_u16())
+                    .set_body_bytes(&CONTENTS[4..]),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let temp_dir = tempfile::TempDir::new()?;
+        let job_dir = temp_dir.path().join(JOB_ID);
+        tokio::fs::create_dir_all(&job_dir).await?;
+        tokio::fs::write(job_dir.join(FILENAME), &CONTENTS[..4]).await?;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )?;
+        let params = DownloadParams::builder()
+            .output_dir(temp_dir.path())
+            .job_id(JOB_ID)
+            .build();
+        let paths = target.batch().download(&params).await?;
+        assert_eq!(paths, vec![job_dir.join(FILENAME)]);
+        assert_eq!(tokio::fs::read(&paths[0]).await?, CONTENTS);
+        // Already complete: no additional file request
+        target.batch().download(&params).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_refetches_corrupt_file() -> crate::Result<()> {
+        const JOB_ID: &str = "GLBX-20230614-ABCDEF";
+        const FILENAME: &str = "glbx-mdp3-20230614.trades.dbn.zst";
+        const CONTENTS: &[u8] = b"0123456789";
+
+        let mock_server = MockServer::start().await;
+        let file_path = format!("/v{API_VERSION}/job_download/{JOB_ID}/{FILENAME}");
+        Mock::given(method("GET"))
+            .and(path(format!("/v{API_VERSION}/batch.list_files")))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!([{
+                    "filename": FILENAME,
+                    "size": CONTENTS.len(),
+                    "hash": format!("sha256:{}", hex::encode(Sha256::digest(CONTENTS))),
+                    "urls": {
+                        "https": format!("{}{file_path}", mock_server.uri()),
+                    }
+                }])),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(file_path))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(CONTENTS))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let temp_dir = tempfile::TempDir::new()?;
+        let job_dir = temp_dir.path().join(JOB_ID);
+        tokio::fs::create_dir_all(&job_dir).await?;
+        // The right size, so it's not resumed
+        tokio::fs::write(job_dir.join(FILENAME), b"0123456780").await?;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )?;
+        let params = DownloadParams::builder()
+            .output_dir(temp_dir.path())
+            .job_id(JOB_ID)
+            .refetch_corrupt(false)
+            .build();
+        let err = target.batch().download(&params).await.unwrap_err();
+        assert!(matches!(err, Error::CorruptCache { .. }), "{err}");
+
+        let params = DownloadParams {
+            refetch_corrupt: true,
+            ..params
+        };
+        let paths = target.batch().download(&params).await?;
+        assert_eq!(tokio::fs::read(&paths[0]).await?, CONTENTS);
+        Ok(())
+    }
+
     #[test]
     fn test_deserialize_compression() {
         #[derive(serde::Deserialize)]
@@ -0,0 +1,241 @@
+//! The historical billing API for reporting usage and spend.
+
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+
+use super::{handle_response, metadata::GetCostParams, AddToQuery, DateRange, SendWithRetry};
+
+/// A client for the billing group of Historical API endpoints.
+#[derive(Debug)]
+pub struct BillingClient<'a> {
+    pub(crate) inner: &'a mut super::Client,
+}
+
+impl BillingClient<'_> {
+    /// Gets the cost and billable size of the data downloaded by the account over
+    /// `date_range`, broken down by dataset.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub async fn get_usage(&mut self, date_range: impl Into<DateRange>) -> crate::Result<Usage> {
+        let resp = self
+            .get("get_usage")?
+            .add_to_query(&date_range.into())
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
+        let datasets = handle_response(resp).await?;
+        Ok(Usage { datasets })
+    }
+
+    /// Gets the usage of the current calendar month in UTC so far.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub async fn get_month_to_date_usage(&mut self) -> crate::Result<Usage> {
+        let today = time::OffsetDateTime::now_utc().date();
+        let start = today.replace_day(1).map_err(crate::Error::internal)?;
+        let end = today
+            .next_day()
+            .ok_or_else(|| crate::Error::internal("date out of range"))?;
+        self.get_usage((start, end)).await
+    }
+
+    /// Estimates the cost of the request described by `params` with
+    /// [`MetadataClient::get_cost()`](super::metadata::MetadataClient::get_cost) and
+    /// checks it against what's left of `budget` this month. Returns the estimated cost
+    /// if the request fits within the budget.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API,
+    /// the API indicates there's an issue with the request, or the estimated cost
+    /// exceeds the remaining budget.
+    pub async fn check_budget(
+        &mut self,
+        budget: &Budget,
+        params: &GetCostParams,
+    ) -> crate::Result<f64> {
+        let usage = self.get_month_to_date_usage().await?;
+        let cost = self.inner.metadata().get_cost(params).await?;
+        let remaining = budget.remaining(&usage);
+        if cost > remaining {
+            return Err(crate::Error::bad_arg(
+                "params",
+                format!(
+                    "estimated cost of ${cost:.2} exceeds the remaining monthly budget of ${remaining:.2}"
+                ),
+            ));
+        }
+        Ok(cost)
+    }
+
+    fn get(&mut self, slug: &str) -> crate::Result<RequestBuilder> {
+        self.inner.get(&format!("billing.{slug}"))
+    }
+}
+
+/// The usage of a single dataset.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct DatasetUsage {
+    /// The dataset code.
+    pub dataset: String,
+    /// The cost in US dollars.
+    pub cost: f64,
+    /// The billable uncompressed raw binary size in bytes.
+    pub billable_size: u64,
+}
+
+/// The usage of an account over a date range. Returned by
+/// [`BillingClient::get_usage()`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    /// The usage of each dataset with a nonzero cost or size.
+    pub datasets: Vec<DatasetUsage>,
+}
+
+impl Usage {
+    /// Returns the usage of `dataset`, if any.
+    pub fn get(&self, dataset: &str) -> Option<&DatasetUsage> {
+        self.datasets.iter().find(|usage| usage.dataset == dataset)
+    }
+
+    /// Returns the total cost in US dollars across all datasets.
+    pub fn total_cost(&self) -> f64 {
+        self.datasets.iter().map(|usage| usage.cost).sum()
+    }
+
+    /// Returns the total billable size in bytes across all datasets.
+    pub fn total_billable_size(&self) -> u64 {
+        self.datasets.iter().map(|usage| usage.billable_size).sum()
+    }
+}
+
+/// A monthly spending limit for [`BillingClient::check_budget()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    /// The maximum spend per calendar month in US dollars.
+    pub monthly_limit: f64,
+}
+
+impl Budget {
+    /// Creates a budget of `monthly_limit` US dollars per calendar month.
+    pub const fn new(monthly_limit: f64) -> Self {
+        Self { monthly_limit }
+    }
+
+    /// Returns how much of the budget is left after `usage`, which is never negative.
+    pub fn remaining(&self, usage: &Usage) -> f64 {
+        (self.monthly_limit - usage.total_cost()).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::Schema;
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use time::macros::{date, datetime};
+    use wiremock::{
+        matchers::{basic_auth, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        historical::{HistoricalGateway, API_VERSION},
+        HistoricalClient,
+    };
+
+    const API_KEY: &str = "test-billing";
+
+    async fn mock_usage(mock_server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/billing.get_usage")))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!([
+                    {"dataset": "GLBX.MDP3", "cost": 12.5, "billable_size": 1_000_000},
+                    {"dataset": "XNAS.ITCH", "cost": 7.25, "billable_size": 500_000},
+                ])),
+            )
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_get_usage() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/billing.get_usage")))
+            .and(query_param("start_date", "2025-04-01"))
+            .and(query_param("end_date", "2025-05-01"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!([
+                    {"dataset": "GLBX.MDP3", "cost": 12.5, "billable_size": 1_000_000},
+                    {"dataset": "XNAS.ITCH", "cost": 7.25, "billable_size": 500_000},
+                ])),
+            )
+            .mount(&mock_server)
+            .await;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let usage = target
+            .billing()
+            .get_usage((date!(2025 - 04 - 01), date!(2025 - 05 - 01)))
+            .await
+            .unwrap();
+        assert_eq!(usage.datasets.len(), 2);
+        assert_eq!(usage.get("XNAS.ITCH").unwrap().billable_size, 500_000);
+        assert!(usage.get("OPRA.PILLAR").is_none());
+        assert_eq!(usage.total_cost(), 19.75);
+        assert_eq!(usage.total_billable_size(), 1_500_000);
+        assert_eq!(Budget::new(50.0).remaining(&usage), 30.25);
+        assert_eq!(Budget::new(10.0).remaining(&usage), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_check_budget() {
+        let mock_server = MockServer::start().await;
+        mock_usage(&mock_server).await;
+        Mock::given(method("POST"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/metadata.get_cost")))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!(5.0)))
+            .mount(&mock_server)
+            .await;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let params = GetCostParams::builder()
+            .dataset("GLBX.MDP3")
+            .symbols("ESM5")
+            .schema(Schema::Trades)
+            .date_time_range((
+                datetime!(2025 - 04 - 21 00:00 UTC),
+                datetime!(2025 - 04 - 22 00:00 UTC),
+            ))
+            .build();
+        let cost = target
+            .billing()
+            .check_budget(&Budget::new(30.0), &params)
+            .await
+            .unwrap();
+        assert_eq!(cost, 5.0);
+        let res = target
+            .billing()
+            .check_budget(&Budget::new(20.0), &params)
+            .await;
+        assert!(
+            matches!(res, Err(crate::Error::BadArgument { param_name, .. }) if param_name == "params")
+        );
+    }
+}
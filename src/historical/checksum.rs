@@ -0,0 +1,110 @@
+//! Verifying downloaded files against SHA256 checksums.
+//!
+//! [`TimeseriesClient::download_range()`](super::timeseries::TimeseriesClient::download_range)
+//! records the checksum of each file it writes in a `.sha256` file next to it, in the
+//! format of `sha256sum`, and [`BatchClient::download()`](super::batch::BatchClient::download)
+//! checks files against the checksums listed by the API. [`verify_file()`] checks a
+//! file against its recorded checksum before it's reused, so a file corrupted on disk
+//! is detected instead of silently producing wrong results.
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+use crate::Error;
+
+const BUF_SIZE: usize = 1 << 16;
+
+/// Returns the path of the file recording the checksum of the file at `path`, i.e.
+/// `path` with `.sha256` appended.
+pub fn checksum_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut checksum_path = path.as_ref().as_os_str().to_owned();
+    checksum_path.push(".sha256");
+    PathBuf::from(checksum_path)
+}
+
+/// Verifies the file at `path` matches the checksum recorded in its
+/// [`checksum_path()`].
+///
+/// # Errors
+/// This function returns an [`Error::CorruptCache`] when the file doesn't match its
+/// checksum and an I/O error when either file can't be read, including when no
+/// checksum was recorded.
+pub async fn verify_file(path: impl AsRef<Path>) -> crate::Result<()> {
+    let path = path.as_ref();
+    let recorded = tokio::fs::read_to_string(checksum_path(path)).await?;
+    let expected = recorded.split_whitespace().next().unwrap_or_default();
+    verify(path, expected, &file_sha256(path).await?)
+}
+
+/// Returns the lowercase hex SHA256 hash of the file at `path`.
+///
+/// # Errors
+/// This function returns an error when it fails to read the file.
+pub async fn file_sha256(path: impl AsRef<Path>) -> crate::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let len = file.read(&mut buf).await?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// Records the lowercase hex SHA256 hash `sha256` of the file at `path`
+pub(crate) async fn write_checksum(path: &Path, sha256: &str) -> crate::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    tokio::fs::write(checksum_path(path), format!("{sha256}  {file_name}\n")).await?;
+    Ok(())
+}
+
+pub(crate) fn verify(path: &Path, expected: &str, actual: &str) -> crate::Result<()> {
+    if expected.eq_ignore_ascii_case(actual) {
+        Ok(())
+    } else {
+        Err(Error::CorruptCache {
+            path: path.to_owned(),
+            expected: expected.to_owned(),
+            actual: actual.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("test.dbn.zst");
+        tokio::fs::write(&path, b"abc").await.unwrap();
+        assert!(verify_file(&path).await.is_err());
+        let sha256 = file_sha256(&path).await.unwrap();
+        assert_eq!(
+            sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        write_checksum(&path, &sha256).await.unwrap();
+        assert_eq!(
+            tokio::fs::read_to_string(dir.path().join("test.dbn.zst.sha256"))
+                .await
+                .unwrap(),
+            format!("{sha256}  test.dbn.zst\n")
+        );
+        verify_file(&path).await.unwrap();
+        tokio::fs::write(&path, b"abd").await.unwrap();
+        assert!(matches!(
+            verify_file(&path).await,
+            Err(Error::CorruptCache { expected, .. }) if expected == sha256
+        ));
+    }
+}
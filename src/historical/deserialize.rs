@@ -28,3 +28,10 @@ pub(crate) fn deserialize_opt_date_time<'de, D: serde::Deserializer<'de>>(
         Ok(None)
     }
 }
+
+pub(crate) fn deserialize_date<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<time::Date, D::Error> {
+    let date_str = String::deserialize(deserializer)?;
+    time::Date::parse(&date_str, super::DATE_FORMAT).map_err(serde::de::Error::custom)
+}
@@ -1,16 +1,18 @@
 //! The historical metadata download API.
 
-use std::{collections::HashMap, num::NonZeroU64, str::FromStr};
+use std::{collections::HashMap, fmt, num::NonZeroU64, str::FromStr};
 
 use dbn::{Encoding, SType, Schema};
 use reqwest::RequestBuilder;
 use serde::{Deserialize, Deserializer};
+use time::format_description::well_known::Rfc3339;
 use typed_builder::TypedBuilder;
 
 use crate::Symbols;
 
 use super::{
     deserialize::deserialize_date_time, handle_response, AddToQuery, DateRange, DateTimeRange,
+    SendWithRetry,
 };
 
 /// A client for the metadata group of Historical API endpoints.
@@ -25,7 +27,10 @@ impl MetadataClient<'_> {
     /// # Errors
     /// This function returns an error when it fails to communicate with the Databento API.
     pub async fn list_publishers(&mut self) -> crate::Result<Vec<PublisherDetail>> {
-        let resp = self.get("list_publishers")?.send().await?;
+        let resp = self
+            .get("list_publishers")?
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -42,7 +47,9 @@ impl MetadataClient<'_> {
         if let Some(date_range) = date_range {
             builder = builder.add_to_query(&date_range);
         }
-        let resp = builder.send().await?;
+        let resp = builder
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -55,7 +62,7 @@ impl MetadataClient<'_> {
         let resp = self
             .get("list_schemas")?
             .query(&[("dataset", dataset)])
-            .send()
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
             .await?;
         handle_response(resp).await
     }
@@ -73,7 +80,9 @@ impl MetadataClient<'_> {
             ("encoding", params.encoding.as_str()),
             ("schema", params.schema.as_str()),
         ]);
-        let resp = builder.send().await?;
+        let resp = builder
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -89,7 +98,9 @@ impl MetadataClient<'_> {
         let builder = self
             .get("list_unit_prices")?
             .query(&[("dataset", &dataset)]);
-        let resp = builder.send().await?;
+        let resp = builder
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -110,7 +121,9 @@ impl MetadataClient<'_> {
         if let Some(ref date_range) = params.date_range {
             builder = builder.add_to_query(date_range);
         }
-        let resp = builder.send().await?;
+        let resp = builder
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -125,11 +138,73 @@ impl MetadataClient<'_> {
         let resp = self
             .get("get_dataset_range")?
             .query(&[("dataset", dataset)])
-            .send()
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
             .await?;
         handle_response(resp).await
     }
 
+    /// Gets the available range and the per-day conditions of `dataset` over
+    /// `date_range`, combined into a [`DataAvailability`] that can be checked before
+    /// making a timeseries request.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub async fn get_data_availability(
+        &mut self,
+        dataset: &str,
+        date_range: impl Into<DateRange>,
+    ) -> crate::Result<DataAvailability> {
+        let range = self.get_dataset_range(dataset).await?;
+        let conditions = self
+            .get_dataset_condition(
+                &GetDatasetConditionParams::builder()
+                    .dataset(dataset)
+                    .date_range(date_range)
+                    .build(),
+            )
+            .await?;
+        Ok(DataAvailability {
+            dataset: dataset.to_owned(),
+            range,
+            conditions,
+        })
+    }
+
+    /// Gets the publishers, schemas, and available range of `dataset` in a single
+    /// [`DatasetInfo`], e.g. for reporting what a dataset offers before requesting
+    /// data from it.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub async fn get_dataset_info(&mut self, dataset: &str) -> crate::Result<DatasetInfo> {
+        let range = self.get_dataset_range(dataset).await?;
+        self.get_dataset_info_with_range(dataset, range).await
+    }
+
+    // Like `get_dataset_info()` with an already known available `range`, e.g. from
+    // `get_data_availability()`
+    pub(crate) async fn get_dataset_info_with_range(
+        &mut self,
+        dataset: &str,
+        range: DatasetRange,
+    ) -> crate::Result<DatasetInfo> {
+        let publishers = self
+            .list_publishers()
+            .await?
+            .into_iter()
+            .filter(|publisher| publisher.dataset == dataset)
+            .collect();
+        let schemas = self.list_schemas(dataset).await?;
+        Ok(DatasetInfo {
+            dataset: dataset.to_owned(),
+            publishers,
+            schemas,
+            range,
+        })
+    }
+
     /// Gets the record count of the time series data query.
     ///
     /// # Errors
@@ -138,7 +213,11 @@ impl MetadataClient<'_> {
     pub async fn get_record_count(&mut self, params: &GetRecordCountParams) -> crate::Result<u64> {
         let mut form = Vec::new();
         params.add_to_form(&mut form);
-        let resp = self.post("get_record_count")?.form(&form).send().await?;
+        let resp = self
+            .post("get_record_count")?
+            .form(&form)
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -154,7 +233,11 @@ impl MetadataClient<'_> {
     ) -> crate::Result<u64> {
         let mut form = Vec::new();
         params.add_to_form(&mut form);
-        let resp = self.post("get_billable_size")?.form(&form).send().await?;
+        let resp = self
+            .post("get_billable_size")?
+            .form(&form)
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -167,7 +250,11 @@ impl MetadataClient<'_> {
     pub async fn get_cost(&mut self, params: &GetCostParams) -> crate::Result<f64> {
         let mut form = Vec::new();
         params.add_to_form(&mut form);
-        let resp = self.post("get_cost")?.form(&form).send().await?;
+        let resp = self
+            .post("get_cost")?
+            .form(&form)
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         handle_response(resp).await
     }
 
@@ -307,6 +394,122 @@ impl<'de> Deserialize<'de> for DatasetRange {
     }
 }
 
+/// The availability of a dataset over a range of dates. Returned by
+/// [`MetadataClient::get_data_availability()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataAvailability {
+    /// The dataset code.
+    pub dataset: String,
+    /// The available range for the dataset given the user's entitlements.
+    pub range: DatasetRange,
+    /// The condition of the dataset on each day in the requested date range.
+    pub conditions: Vec<DatasetConditionDetail>,
+}
+
+impl DataAvailability {
+    /// Returns the condition of the dataset on `date`, or `None` if `date` wasn't
+    /// in the requested date range.
+    pub fn condition_on(&self, date: time::Date) -> Option<DatasetCondition> {
+        self.conditions
+            .iter()
+            .find(|detail| detail.date == date)
+            .map(|detail| detail.condition)
+    }
+
+    /// Returns `true` if data exists for `date`, i.e. its condition is
+    /// [`Available`](DatasetCondition::Available),
+    /// [`Degraded`](DatasetCondition::Degraded), or
+    /// [`Intraday`](DatasetCondition::Intraday).
+    pub fn has_data_on(&self, date: time::Date) -> bool {
+        matches!(
+            self.condition_on(date),
+            Some(
+                DatasetCondition::Available
+                    | DatasetCondition::Degraded
+                    | DatasetCondition::Intraday
+            )
+        )
+    }
+
+    /// Returns the dates in the requested range without data.
+    pub fn missing_dates(&self) -> Vec<time::Date> {
+        self.conditions
+            .iter()
+            .filter(|detail| !self.has_data_on(detail.date))
+            .map(|detail| detail.date)
+            .collect()
+    }
+
+    /// Returns the dates in the requested range whose data may be incomplete.
+    pub fn degraded_dates(&self) -> Vec<time::Date> {
+        self.conditions
+            .iter()
+            .filter(|detail| detail.condition == DatasetCondition::Degraded)
+            .map(|detail| detail.date)
+            .collect()
+    }
+
+    /// Returns `true` if `dt_range` lies within the available range of the dataset.
+    pub fn covers(&self, dt_range: &DateTimeRange) -> bool {
+        dt_range.start >= self.range.start && dt_range.end <= self.range.end
+    }
+
+    /// Returns `dt_range` with its end clamped to the end of the available range, or
+    /// `None` if no part of `dt_range` is available.
+    pub fn clamp(&self, dt_range: &DateTimeRange) -> Option<DateTimeRange> {
+        let start = dt_range.start.max(self.range.start);
+        let end = dt_range.end.min(self.range.end);
+        (start < end).then_some(DateTimeRange { start, end })
+    }
+}
+
+/// The publishers, schemas, and available range of a dataset. Returned by
+/// [`MetadataClient::get_dataset_info()`].
+///
+/// The [`Display`](fmt::Display) implementation gives a one-line summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetInfo {
+    /// The dataset code.
+    pub dataset: String,
+    /// The publishers of the dataset.
+    pub publishers: Vec<PublisherDetail>,
+    /// The schemas available for the dataset.
+    pub schemas: Vec<Schema>,
+    /// The available range for the dataset given the user's entitlements.
+    pub range: DatasetRange,
+}
+
+impl DatasetInfo {
+    /// Returns `true` if `schema` is available for the dataset.
+    pub fn supports(&self, schema: Schema) -> bool {
+        self.schemas.contains(&schema)
+    }
+
+    /// Returns the publisher with `publisher_id`, if it's one of the dataset's
+    /// publishers.
+    pub fn publisher(&self, publisher_id: u16) -> Option<&PublisherDetail> {
+        self.publishers
+            .iter()
+            .find(|publisher| publisher.publisher_id == publisher_id)
+    }
+}
+
+impl fmt::Display for DatasetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let venues: Vec<_> = self.publishers.iter().map(|p| p.venue.as_str()).collect();
+        let schemas: Vec<_> = self.schemas.iter().map(|s| s.as_str()).collect();
+        write!(
+            f,
+            "{} available from {} to {}, venues: {}, schemas: {}",
+            self.dataset,
+            self.range.start.format(&Rfc3339).map_err(|_| fmt::Error)?,
+            self.range.end.format(&Rfc3339).map_err(|_| fmt::Error)?,
+            venues.join(", "),
+            schemas.join(", ")
+        )
+    }
+}
+
 /// The parameters for several metadata requests.
 #[derive(Debug, Clone, TypedBuilder, PartialEq, Eq)]
 pub struct GetQueryParams {
@@ -674,6 +877,150 @@ mod tests {
         assert_eq!(range.end, datetime!(2023 - 07 - 20 00:00:00.000000+00:00));
     }
 
+    #[tokio::test]
+    async fn test_get_data_availability() {
+        const DATASET: &str = "GLBX.MDP3";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/metadata.get_dataset_range")))
+            .and(query_param("dataset", DATASET))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!({
+                    "start": "2017-05-21T00:00:00.000000000Z",
+                    "end": "2025-04-23T12:00:00.000000000Z",
+                })),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!(
+                "/v{API_VERSION}/metadata.get_dataset_condition"
+            )))
+            .and(query_param("dataset", DATASET))
+            .and(query_param("start_date", "2025-04-21"))
+            .and(query_param("end_date", "2025-04-24"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!([
+                    {
+                        "date": "2025-04-21",
+                        "condition": "available",
+                        "last_modified_date": "2025-04-22",
+                    },
+                    {
+                        "date": "2025-04-22",
+                        "condition": "degraded",
+                        "last_modified_date": "2025-04-23",
+                    },
+                    {
+                        "date": "2025-04-23",
+                        "condition": "pending",
+                        "last_modified_date": "2025-04-23",
+                    }
+                ])),
+            )
+            .mount(&mock_server)
+            .await;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let availability = target
+            .metadata()
+            .get_data_availability(DATASET, (date!(2025 - 04 - 21), date!(2025 - 04 - 24)))
+            .await
+            .unwrap();
+        assert!(availability.has_data_on(date!(2025 - 04 - 21)));
+        assert!(availability.has_data_on(date!(2025 - 04 - 22)));
+        assert!(!availability.has_data_on(date!(2025 - 04 - 23)));
+        assert!(!availability.has_data_on(date!(2025 - 04 - 24)));
+        assert_eq!(availability.missing_dates(), vec![date!(2025 - 04 - 23)]);
+        assert_eq!(availability.degraded_dates(), vec![date!(2025 - 04 - 22)]);
+        let dt_range = DateTimeRange::from((
+            datetime!(2025 - 04 - 22 11:00 UTC),
+            datetime!(2025 - 04 - 23 20:00 UTC),
+        ));
+        assert!(!availability.covers(&dt_range));
+        assert_eq!(
+            availability.clamp(&dt_range),
+            Some(DateTimeRange::from((
+                datetime!(2025 - 04 - 22 11:00 UTC),
+                datetime!(2025 - 04 - 23 12:00 UTC),
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_dataset_info() {
+        const DATASET: &str = "GLBX.MDP3";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/metadata.list_publishers")))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!([
+                    {
+                        "publisher_id": 1,
+                        "dataset": "GLBX.MDP3",
+                        "venue": "GLBX",
+                        "description": "CME Globex MDP 3.0",
+                    },
+                    {
+                        "publisher_id": 2,
+                        "dataset": "XNAS.ITCH",
+                        "venue": "XNAS",
+                        "description": "Nasdaq TotalView-ITCH",
+                    }
+                ])),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/metadata.list_schemas")))
+            .and(query_param("dataset", DATASET))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16())
+                    .set_body_json(json!(["mbo", "trades", "ohlcv-1m"])),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/metadata.get_dataset_range")))
+            .and(query_param("dataset", DATASET))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(json!({
+                    "start": "2017-05-21T00:00:00.000000000Z",
+                    "end": "2025-04-23T12:00:00.000000000Z",
+                })),
+            )
+            .mount(&mock_server)
+            .await;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let info = target.metadata().get_dataset_info(DATASET).await.unwrap();
+        assert_eq!(info.dataset, DATASET);
+        assert_eq!(info.publishers.len(), 1);
+        assert_eq!(info.publisher(1).unwrap().venue, "GLBX");
+        assert!(info.publisher(2).is_none());
+        assert!(info.supports(Schema::Ohlcv1M));
+        assert!(!info.supports(Schema::Ohlcv1D));
+        assert_eq!(info.range.end, datetime!(2025 - 04 - 23 12:00 UTC));
+        assert_eq!(
+            info.to_string(),
+            "GLBX.MDP3 available from 2017-05-21T00:00:00Z to 2025-04-23T12:00:00Z, \
+             venues: GLBX, schemas: mbo, trades, ohlcv-1m"
+        );
+    }
+
     #[tokio::test]
     async fn test_get_dataset_range_no_dates() {
         const DATASET: &str = "XNAS.ITCH";
@@ -0,0 +1,154 @@
+//! Historical clients for multiple API keys.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{Client, ClientBuilder, RateLimiter, Unset};
+use crate::ApiKey;
+
+/// A pool of historical clients for different API keys, e.g. for an HTTP service where
+/// each request supplies the user's key.
+///
+/// Every client shares the settings and HTTP connection pool of the builder passed to
+/// [`new()`](Self::new), so requests for any key reuse open connections. A key's client,
+/// including its own rate limiter if [`rate_limit()`](Self::rate_limit) is set, is kept
+/// until it's unused for the idle timeout.
+#[derive(Debug)]
+pub struct ClientPool {
+    template: Client,
+    idle_timeout: Duration,
+    rate_limit: Option<(f64, u32)>,
+    clients: Mutex<HashMap<ApiKey, PooledClient>>,
+}
+
+#[derive(Debug)]
+struct PooledClient {
+    client: Client,
+    last_used: Instant,
+}
+
+impl ClientPool {
+    /// Creates an empty pool whose clients use the settings of `builder` and are
+    /// dropped after being unused for `idle_timeout`.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to build the HTTP client, e.g.
+    /// when the proxy URL is invalid.
+    pub fn new(builder: ClientBuilder<Unset>, idle_timeout: Duration) -> crate::Result<Self> {
+        Ok(Self {
+            template: builder.build_template()?,
+            idle_timeout,
+            rate_limit: None,
+            clients: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Gives each key's client its own [`RateLimiter`] allowing `requests_per_second`
+    /// on average and bursts of up to `burst` requests, instead of sharing the
+    /// builder's limiter between all keys.
+    ///
+    /// # Panics
+    /// This function panics when `requests_per_second` isn't positive and finite or
+    /// `burst` is zero.
+    pub fn rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        // Validate eagerly rather than on the first request
+        RateLimiter::new(requests_per_second, burst);
+        self.rate_limit = Some((requests_per_second, burst));
+        self
+    }
+
+    /// Returns the client for `key`, creating it if there isn't one. Clients that have
+    /// been idle for longer than the idle timeout are dropped first.
+    pub fn get(&self, key: ApiKey) -> Client {
+        self.get_at(key, Instant::now())
+    }
+
+    /// Returns the number of clients in the pool, including idle ones that haven't
+    /// been dropped yet.
+    pub fn len(&self) -> usize {
+        self.clients().len()
+    }
+
+    /// Returns `true` if there are no clients in the pool.
+    pub fn is_empty(&self) -> bool {
+        self.clients().is_empty()
+    }
+
+    /// Drops the clients that have been idle for longer than the idle timeout and
+    /// returns how many were dropped.
+    pub fn evict_idle(&self) -> usize {
+        let mut clients = self.clients();
+        let len = clients.len();
+        self.retain_active(&mut clients, Instant::now());
+        len - clients.len()
+    }
+
+    fn get_at(&self, key: ApiKey, now: Instant) -> Client {
+        let mut clients = self.clients();
+        self.retain_active(&mut clients, now);
+        let pooled = clients.entry(key).or_insert_with_key(|key| {
+            let mut client = self.template.with_key(key.clone());
+            if let Some((requests_per_second, burst)) = self.rate_limit {
+                client
+                    .set_rate_limiter(Some(Arc::new(RateLimiter::new(requests_per_second, burst))));
+            }
+            PooledClient {
+                client,
+                last_used: now,
+            }
+        });
+        pooled.last_used = now;
+        pooled.client.clone()
+    }
+
+    fn retain_active(&self, clients: &mut HashMap<ApiKey, PooledClient>, now: Instant) {
+        clients.retain(|_, pooled| {
+            now.saturating_duration_since(pooled.last_used) <= self.idle_timeout
+        });
+    }
+
+    fn clients(&self) -> std::sync::MutexGuard<'_, HashMap<ApiKey, PooledClient>> {
+        self.clients
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> ApiKey {
+        ApiKey::new(c.to_string().repeat(crate::API_KEY_LENGTH)).unwrap()
+    }
+
+    #[test]
+    fn test_get() {
+        let pool = ClientPool::new(Client::builder(), Duration::from_secs(60))
+            .unwrap()
+            .rate_limit(10.0, 5);
+        let start = Instant::now();
+        let a = pool.get_at(key('a'), start);
+        assert_eq!(a.key(), key('a').as_str());
+        let b = pool.get_at(key('b'), start);
+        assert_eq!(b.key(), key('b').as_str());
+        assert_eq!(pool.len(), 2);
+        // Each key has its own rate limiter, which is kept while the client is in use
+        assert!(!Arc::ptr_eq(
+            a.rate_limiter().unwrap(),
+            b.rate_limiter().unwrap()
+        ));
+        let later = start + Duration::from_secs(45);
+        let a_again = pool.get_at(key('a'), later);
+        assert!(Arc::ptr_eq(
+            a.rate_limiter().unwrap(),
+            a_again.rate_limiter().unwrap()
+        ));
+        // `b` has been idle for longer than the timeout
+        pool.get_at(key('a'), later + Duration::from_secs(30));
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,109 @@
+//! Client-side rate limiting of historical requests.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// A token-bucket rate limiter consulted before each historical request.
+///
+/// The bucket holds up to `burst` tokens and refills at `requests_per_second`. Each
+/// request, including each retry, takes a token, waiting for one to be refilled when
+/// the bucket is empty. Waiting requests are served in the order they arrived.
+///
+/// Share a limiter between clients, e.g. clones used by different tasks of a
+/// backfill, by passing the same [`Arc`](std::sync::Arc) to
+/// [`ClientBuilder::rate_limiter()`](super::ClientBuilder::rate_limiter) or
+/// [`HistoricalClient::set_rate_limiter()`](super::Client::set_rate_limiter).
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    // Negative when requests are waiting for tokens that haven't been refilled yet
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `requests_per_second` on average and bursts of up to
+    /// `burst` requests. The bucket starts full.
+    ///
+    /// # Panics
+    /// This function panics when `requests_per_second` isn't positive and finite or
+    /// `burst` is zero.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        assert!(
+            requests_per_second.is_finite() && requests_per_second > 0.0,
+            "requests_per_second must be positive"
+        );
+        assert!(burst > 0, "burst must be positive");
+        Self {
+            requests_per_second,
+            burst: f64::from(burst),
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(burst),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Returns the average number of requests allowed per second.
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    /// Returns the maximum number of requests allowed at once.
+    pub fn burst(&self) -> u32 {
+        self.burst as u32
+    }
+
+    /// Waits until a request is allowed.
+    pub async fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // Takes a token, returning how long to wait for it to be refilled
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|err| err.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.requests_per_second).min(self.burst);
+        bucket.refilled_at = now;
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.requests_per_second)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(4.0, 2);
+        let start = Instant::now();
+        limiter.bucket.lock().unwrap().refilled_at = start;
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        // Each waiting request is queued behind the previous ones
+        assert_eq!(limiter.reserve(start), Duration::from_millis(250));
+        assert_eq!(limiter.reserve(start), Duration::from_millis(500));
+        // The bucket refills after the queued requests are served, up to the burst
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::from_millis(250));
+    }
+}
@@ -5,11 +5,13 @@ use std::{collections::HashMap, sync::Arc};
 use dbn::{MappingInterval, Metadata, SType, TsSymbolMap};
 use reqwest::RequestBuilder;
 use serde::Deserialize;
+use time::OffsetDateTime;
+use tracing::instrument;
 use typed_builder::TypedBuilder;
 
 use crate::Symbols;
 
-use super::{handle_response, timeseries, DateRange, DateTimeRange};
+use super::{handle_response, timeseries, DateRange, DateTimeRange, SendWithRetry};
 
 /// A client for the symbology group of Historical API endpoints.
 #[derive(Debug)]
@@ -25,6 +27,16 @@ impl SymbologyClient<'_> {
     /// # Errors
     /// This function returns an error when it fails to communicate with the Databento API
     /// or the API indicates there's an issue with the request.
+    #[instrument(
+        name = "resolve",
+        skip_all,
+        fields(
+            dataset = %params.dataset,
+            stype_in = %params.stype_in,
+            stype_out = %params.stype_out,
+            symbols = %params.symbols.to_api_string(),
+        )
+    )]
     pub async fn resolve(&mut self, params: &ResolveParams) -> crate::Result<Resolution> {
         let mut form = vec![
             ("dataset", params.dataset.to_string()),
@@ -33,7 +45,11 @@ impl SymbologyClient<'_> {
             ("symbols", params.symbols.to_api_string()),
         ];
         params.date_range.add_to_form(&mut form);
-        let resp = self.post("resolve")?.form(&form).send().await?;
+        let resp = self
+            .post("resolve")?
+            .form(&form)
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
+            .await?;
         let ResolutionResp {
             mappings,
             partial,
@@ -142,43 +158,150 @@ impl Resolution {
     /// instrument ID.
     pub fn symbol_map(&self) -> crate::Result<TsSymbolMap> {
         let mut map = TsSymbolMap::new();
-        if self.stype_in == SType::InstrumentId {
-            for (iid, intervals) in self.mappings.iter() {
+        for interval in self.intervals() {
+            let (instrument_id, symbol, interval) = interval?;
+            map.insert(
+                instrument_id,
+                interval.start_date,
+                interval.end_date,
+                Arc::new(symbol.to_owned()),
+            )?;
+        }
+        Ok(map)
+    }
+
+    /// Creates a [`SymbolMapTimeline`] for looking up symbols by instrument ID and
+    /// instrument IDs by symbol at a point in time.
+    ///
+    /// # Errors
+    /// This function returns an error if it's unable to parse a symbol into an
+    /// instrument ID.
+    pub fn timeline(&self) -> crate::Result<SymbolMapTimeline> {
+        let mut timeline = SymbolMapTimeline::default();
+        for interval in self.intervals() {
+            let (instrument_id, symbol, interval) = interval?;
+            timeline.insert(
+                instrument_id,
+                symbol,
+                interval.start_date,
+                interval.end_date,
+            );
+        }
+        timeline.sort();
+        Ok(timeline)
+    }
+
+    // Returns each mapping interval with its instrument ID and text symbol, whichever
+    // side of the resolution they're on
+    fn intervals(&self) -> impl Iterator<Item = crate::Result<(u32, &str, &MappingInterval)>> + '_ {
+        self.mappings.iter().flat_map(move |(key, intervals)| {
+            intervals.iter().map(move |interval| {
+                let (iid, symbol) = if self.stype_in == SType::InstrumentId {
+                    (key.as_str(), interval.symbol.as_str())
+                } else {
+                    (interval.symbol.as_str(), key.as_str())
+                };
                 let iid = iid.parse().map_err(|_| {
-                    crate::Error::internal(format!("Unable to parse '{iid}' to an instrument ID",))
+                    crate::Error::internal(format!("Unable to parse '{iid}' to an instrument ID"))
                 })?;
-                for interval in intervals {
-                    map.insert(
-                        iid,
-                        interval.start_date,
-                        interval.end_date,
-                        Arc::new(interval.symbol.clone()),
-                    )?;
-                }
-            }
-        } else {
-            for (raw_symbol, intervals) in self.mappings.iter() {
-                let raw_symbol = Arc::new(raw_symbol.clone());
-                for interval in intervals {
-                    let iid = interval.symbol.parse().map_err(|_| {
-                        crate::Error::internal(format!(
-                            "Unable to parse '{}' to an instrument ID",
-                            interval.symbol
-                        ))
-                    })?;
-                    map.insert(
-                        iid,
-                        interval.start_date,
-                        interval.end_date,
-                        raw_symbol.clone(),
-                    )?;
-                }
-            }
+                Ok((iid, symbol, interval))
+            })
+        })
+    }
+}
+
+/// The symbols of instruments over time from a [`Resolution`], which can be looked up
+/// in both directions.
+///
+/// Mapping intervals are in UTC dates, with the end date exclusive, so lookups use the
+/// UTC date of the timestamp.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolMapTimeline {
+    by_id: HashMap<u32, Vec<TimelineEntry<Arc<str>>>>,
+    by_symbol: HashMap<Arc<str>, Vec<TimelineEntry<u32>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TimelineEntry<T> {
+    start_date: time::Date,
+    end_date: time::Date,
+    value: T,
+}
+
+impl SymbolMapTimeline {
+    /// Returns the symbol of the instrument with `instrument_id` at `ts`.
+    pub fn symbol_for(&self, instrument_id: u32, ts: OffsetDateTime) -> Option<&str> {
+        find(self.by_id.get(&instrument_id)?, ts).map(|symbol| &**symbol)
+    }
+
+    /// Returns the ID of the instrument with `symbol` at `ts`.
+    pub fn instrument_for(&self, symbol: &str, ts: OffsetDateTime) -> Option<u32> {
+        find(self.by_symbol.get(symbol)?, ts).copied()
+    }
+
+    /// Returns the IDs of every instrument that had `symbol` with the start date and
+    /// exclusive end date of each, in chronological order.
+    pub fn instruments(&self, symbol: &str) -> Vec<(time::Date, time::Date, u32)> {
+        self.by_symbol.get(symbol).map_or_else(Vec::new, |entries| {
+            entries
+                .iter()
+                .map(|entry| (entry.start_date, entry.end_date, entry.value))
+                .collect()
+        })
+    }
+
+    /// Returns `true` if the timeline has no mappings.
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    fn insert(
+        &mut self,
+        instrument_id: u32,
+        symbol: &str,
+        start_date: time::Date,
+        end_date: time::Date,
+    ) {
+        let symbol: Arc<str> = match self.by_symbol.get_key_value(symbol) {
+            Some((symbol, _)) => symbol.clone(),
+            None => symbol.into(),
+        };
+        self.by_id
+            .entry(instrument_id)
+            .or_default()
+            .push(TimelineEntry {
+                start_date,
+                end_date,
+                value: symbol.clone(),
+            });
+        self.by_symbol
+            .entry(symbol)
+            .or_default()
+            .push(TimelineEntry {
+                start_date,
+                end_date,
+                value: instrument_id,
+            });
+    }
+
+    fn sort(&mut self) {
+        for entries in self.by_id.values_mut() {
+            entries.sort_by_key(|entry| entry.start_date);
+        }
+        for entries in self.by_symbol.values_mut() {
+            entries.sort_by_key(|entry| entry.start_date);
         }
-        Ok(map)
     }
 }
 
+// Binary searches entries sorted by start date for the one containing the date of `ts`
+fn find<T>(entries: &[TimelineEntry<T>], ts: OffsetDateTime) -> Option<&T> {
+    let date = ts.to_offset(time::UtcOffset::UTC).date();
+    let i = entries.partition_point(|entry| entry.start_date <= date);
+    let entry = entries[..i].last()?;
+    (date < entry.end_date).then_some(&entry.value)
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct ResolutionResp {
     #[serde(rename = "result")]
@@ -191,7 +314,7 @@ struct ResolutionResp {
 mod tests {
     use reqwest::StatusCode;
     use serde_json::json;
-    use time::macros::date;
+    use time::macros::{date, datetime};
     use wiremock::{
         matchers::{basic_auth, method, path},
         Mock, MockServer, ResponseTemplate,
@@ -277,4 +400,69 @@ mod tests {
         assert!(res.partial.is_empty());
         assert_eq!(res.not_found, vec!["ES.d.0"]);
     }
+
+    #[test]
+    fn test_timeline() {
+        let interval = |start_date, end_date, symbol: &str| MappingInterval {
+            start_date,
+            end_date,
+            symbol: symbol.to_owned(),
+        };
+        let resolution = Resolution {
+            mappings: HashMap::from([
+                (
+                    "ES.c.0".to_owned(),
+                    vec![
+                        interval(date!(2023 - 06 - 15), date!(2023 - 06 - 16), "10248"),
+                        interval(date!(2023 - 06 - 14), date!(2023 - 06 - 15), "10245"),
+                    ],
+                ),
+                (
+                    "NQ.c.0".to_owned(),
+                    vec![interval(date!(2023 - 06 - 14), date!(2023 - 06 - 16), "20")],
+                ),
+            ]),
+            partial: Vec::new(),
+            not_found: Vec::new(),
+            stype_in: SType::Continuous,
+            stype_out: SType::InstrumentId,
+        };
+        let timeline = resolution.timeline().unwrap();
+        let ts = datetime!(2023-06-14 23:59 UTC);
+        assert_eq!(timeline.instrument_for("ES.c.0", ts), Some(10245));
+        assert_eq!(timeline.symbol_for(10245, ts), Some("ES.c.0"));
+        // In New York on June 15 in UTC
+        let ts = datetime!(2023-06-14 21:00 -4);
+        assert_eq!(timeline.instrument_for("ES.c.0", ts), Some(10248));
+        assert_eq!(timeline.symbol_for(10245, ts), None);
+        assert_eq!(timeline.symbol_for(20, ts), Some("NQ.c.0"));
+        let ts = datetime!(2023-06-16 00:00 UTC);
+        assert_eq!(timeline.instrument_for("ES.c.0", ts), None);
+        assert_eq!(timeline.instrument_for("ES.c.1", ts), None);
+        assert_eq!(
+            timeline.instruments("ES.c.0"),
+            [
+                (date!(2023 - 06 - 14), date!(2023 - 06 - 15), 10245),
+                (date!(2023 - 06 - 15), date!(2023 - 06 - 16), 10248)
+            ]
+        );
+
+        let resolution = Resolution {
+            mappings: HashMap::from([(
+                "10245".to_owned(),
+                vec![interval(
+                    date!(2023 - 06 - 14),
+                    date!(2023 - 06 - 15),
+                    "ESM3",
+                )],
+            )]),
+            stype_in: SType::InstrumentId,
+            stype_out: SType::RawSymbol,
+            ..resolution
+        };
+        let timeline = resolution.timeline().unwrap();
+        let ts = datetime!(2023-06-14 12:00 UTC);
+        assert_eq!(timeline.symbol_for(10245, ts), Some("ESM3"));
+        assert_eq!(resolution.symbol_map().unwrap().len(), 1);
+    }
 }
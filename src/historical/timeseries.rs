@@ -1,20 +1,37 @@
 //! The historical timeseries API.
 
-use std::{num::NonZeroU64, path::PathBuf};
+use std::{
+    fmt,
+    future::Future,
+    num::NonZeroU64,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
-use dbn::{encode::AsyncDbnEncoder, Compression, Encoding, SType, Schema, VersionUpgradePolicy};
+use dbn::{
+    Compression, Encoding, HasRType, Metadata, Record, RecordHeader, RecordRef, SType, Schema,
+    SymbolIndex, TsSymbolMap, VersionUpgradePolicy,
+};
 use futures::{Stream, TryStreamExt};
 use reqwest::{header::ACCEPT, RequestBuilder};
+use sha2::{Digest, Sha256};
 use tokio::{
     fs::File,
     io::{AsyncReadExt, AsyncWriteExt, BufWriter},
+    sync::mpsc,
 };
 use tokio_util::{bytes::Bytes, io::StreamReader};
+use tracing::{debug, instrument, warn, Instrument};
 use typed_builder::TypedBuilder;
 
 use crate::Symbols;
 
-use super::{check_http_error, DateTimeRange};
+use super::{
+    check_http_error, checksum, metadata::GetRecordCountParams, DateTimeRange, SendWithRetry,
+};
 
 // Re-export because it's returned.
 pub use dbn::decode::AsyncDbnDecoder;
@@ -51,6 +68,7 @@ impl TimeseriesClient<'_> {
                 &params.symbols,
                 &params.date_time_range,
                 params.limit,
+                None,
             )
             .await?;
         let mut decoder: AsyncDbnDecoder<_> = AsyncDbnDecoder::with_zstd_buffer(reader).await?;
@@ -58,10 +76,96 @@ impl TimeseriesClient<'_> {
         Ok(decoder)
     }
 
-    /// Makes a streaming request for timeseries data from Databento.
+    /// Makes a streaming request for timeseries data from Databento, returning a
+    /// decoder that pairs each record with its symbol from the response metadata.
     ///
-    /// This method returns a stream decoder. For larger requests, consider using
-    /// [`BatchClient::submit_job()`](super::batch::BatchClient::submit_job()).
+    /// <div class="warning">
+    /// Calling this method will incur a cost.
+    /// </div>
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API,
+    /// the API indicates there's an issue with the request, or the symbology mappings
+    /// in the response can't be parsed.
+    pub async fn get_range_with_symbols(
+        &mut self,
+        params: &GetRangeParams,
+    ) -> crate::Result<SymbolDecoder<impl AsyncReadExt + Unpin>> {
+        let decoder = self.get_range(params).await?;
+        SymbolDecoder::new(decoder)
+    }
+
+    /// Makes a streaming request for timeseries data from Databento, reporting
+    /// progress to `callback` as records are decoded.
+    ///
+    /// Before the request, the expected number of records is queried with
+    /// [`MetadataClient::get_record_count()`](super::metadata::MetadataClient::get_record_count)
+    /// to estimate completion. If that query fails, progress is still reported, but
+    /// without an estimate.
+    ///
+    /// <div class="warning">
+    /// Calling this method will incur a cost.
+    /// </div>
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request.
+    pub async fn get_range_with_progress(
+        &mut self,
+        params: &GetRangeParams,
+        callback: impl FnMut(&DownloadProgress) + Send + 'static,
+    ) -> crate::Result<ProgressDecoder<impl AsyncReadExt + Unpin>> {
+        let count_params = GetRecordCountParams {
+            dataset: params.dataset.clone(),
+            symbols: params.symbols.clone(),
+            schema: params.schema,
+            date_time_range: params.date_time_range.clone(),
+            stype_in: params.stype_in,
+            limit: params.limit,
+        };
+        let estimated_records = match self.inner.metadata().get_record_count(&count_params).await {
+            Ok(count) => Some(count),
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "Failed to estimate record count for progress reporting"
+                );
+                None
+            }
+        };
+        let bytes_received = Arc::new(AtomicU64::new(0));
+        let reader = self
+            .get_range_impl(
+                &params.dataset,
+                params.schema,
+                params.stype_in,
+                params.stype_out,
+                &params.symbols,
+                &params.date_time_range,
+                params.limit,
+                Some(bytes_received.clone()),
+            )
+            .await?;
+        let mut decoder = AsyncDbnDecoder::with_zstd_buffer(reader).await?;
+        decoder.set_upgrade_policy(params.upgrade_policy);
+        Ok(ProgressDecoder {
+            decoder,
+            bytes_received,
+            records_decoded: 0,
+            estimated_records,
+            finished: false,
+            callback: Box::new(callback),
+        })
+    }
+
+    /// Makes a streaming request for timeseries data from Databento and persists the
+    /// Zstandard-compressed DBN response to the file at `params.path`, returning a
+    /// decoder for the file.
+    ///
+    /// The response is written to disk as it's received without being decoded, so
+    /// records are only decoded, and `params.upgrade_policy` applied, when read from
+    /// the returned decoder. To skip opening a decoder, e.g. for archival jobs, use
+    /// [`download_range()`](Self::download_range).
     ///
     /// <div class="warning">
     /// Calling this method will incur a cost.
@@ -75,7 +179,46 @@ impl TimeseriesClient<'_> {
         &mut self,
         params: &GetRangeToFileParams,
     ) -> crate::Result<AsyncDbnDecoder<impl AsyncReadExt>> {
-        let reader = self
+        self.download_range(params).await?;
+        let mut decoder = AsyncDbnDecoder::from_zstd_file(&params.path).await?;
+        decoder.set_upgrade_policy(params.upgrade_policy);
+        Ok(decoder)
+    }
+
+    /// Makes a streaming request for timeseries data from Databento and writes the
+    /// Zstandard-compressed DBN response to the file at `params.path` as it's received,
+    /// without decoding it. Returns the number of bytes written.
+    ///
+    /// The file can be decoded later with [`AsyncDbnDecoder::from_zstd_file()`]. If the
+    /// request fails partway through, the incomplete file is removed. The SHA256
+    /// checksum of the file is recorded next to it, so it can be checked with
+    /// [`verify_file()`](super::checksum::verify_file). When
+    /// [`GetRangeToFileParams::reuse_existing`] is `true`, an existing file matching its
+    /// checksum is reused without making a request and `0` is returned.
+    ///
+    /// <div class="warning">
+    /// Calling this method will incur a cost.
+    /// </div>
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to communicate with the Databento API
+    /// or the API indicates there's an issue with the request. An error will also be returned
+    /// if it fails to create or write the file at `path`.
+    pub async fn download_range(&mut self, params: &GetRangeToFileParams) -> crate::Result<u64> {
+        if params.reuse_existing {
+            match checksum::verify_file(&params.path).await {
+                Ok(()) => {
+                    debug!(path = %params.path.display(), "Reusing verified file");
+                    return Ok(0);
+                }
+                Err(err @ crate::Error::CorruptCache { .. }) => {
+                    warn!(?err, "Requesting data for corrupt file again");
+                }
+                // Missing file or checksum
+                Err(_) => {}
+            }
+        }
+        let mut reader = self
             .get_range_impl(
                 &params.dataset,
                 params.schema,
@@ -84,20 +227,43 @@ impl TimeseriesClient<'_> {
                 &params.symbols,
                 &params.date_time_range,
                 params.limit,
+                None,
             )
             .await?;
-        let mut http_decoder = AsyncDbnDecoder::with_zstd_buffer(reader).await?;
-        http_decoder.set_upgrade_policy(params.upgrade_policy);
-        let file = BufWriter::new(File::create(&params.path).await?);
-        let mut encoder = AsyncDbnEncoder::with_zstd(file, http_decoder.metadata()).await?;
-        while let Some(rec_ref) = http_decoder.decode_record_ref().await? {
-            encoder.encode_record_ref(rec_ref).await?;
+        let mut file = BufWriter::new(File::create(&params.path).await?);
+        let res = async {
+            let mut hasher = Sha256::new();
+            let mut buf = vec![0; 1 << 16];
+            let mut len = 0;
+            loop {
+                let n = reader.read(&mut buf).await?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+                file.write_all(&buf[..n]).await?;
+                len += n as u64;
+            }
+            file.shutdown().await?;
+            checksum::write_checksum(&params.path, &hex::encode(hasher.finalize())).await?;
+            Ok(len)
         }
-        encoder.get_mut().shutdown().await?;
-        Ok(AsyncDbnDecoder::from_zstd_file(&params.path).await?)
+        .await;
+        if res.is_err() {
+            drop(file);
+            if let Err(err) = tokio::fs::remove_file(&params.path).await {
+                warn!(?err, path = %params.path.display(), "Failed to remove incomplete file");
+            }
+        }
+        res
     }
 
     #[allow(clippy::too_many_arguments)] // private method
+    #[instrument(
+        name = "get_range",
+        skip_all,
+        fields(dataset, %schema, %stype_in, symbols = %symbols.to_api_string())
+    )]
     async fn get_range_impl(
         &mut self,
         dataset: &str,
@@ -107,6 +273,7 @@ impl TimeseriesClient<'_> {
         symbols: &Symbols,
         date_time_range: &DateTimeRange,
         limit: Option<NonZeroU64>,
+        bytes_received: Option<Arc<AtomicU64>>,
     ) -> crate::Result<StreamReader<impl Stream<Item = std::io::Result<Bytes>>, Bytes>> {
         let mut form = vec![
             ("dataset", dataset.to_owned()),
@@ -126,13 +293,18 @@ impl TimeseriesClient<'_> {
             // unlike almost every other request, it's not JSON
             .header(ACCEPT, "application/octet-stream")
             .form(&form)
-            .send()
+            .send_with_retry(self.inner.retry_policy(), self.inner.rate_limiter())
             .await?;
         let stream = check_http_error(resp)
             .await?
             .error_for_status()?
             .bytes_stream()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+            .inspect_ok(move |bytes| {
+                if let Some(counter) = bytes_received.as_ref() {
+                    counter.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                }
+            })
+            .map_err(std::io::Error::other);
         Ok(tokio_util::io::StreamReader::new(stream))
     }
 
@@ -141,6 +313,456 @@ impl TimeseriesClient<'_> {
     }
 }
 
+/// A snapshot of the progress of a streaming timeseries request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// The number of compressed bytes received from the API so far.
+    pub bytes_received: u64,
+    /// The number of records decoded so far.
+    pub records_decoded: u64,
+    /// The expected total number of records, if it could be determined.
+    pub estimated_records: Option<u64>,
+}
+
+impl DownloadProgress {
+    /// Returns the estimated fraction of the request that's complete in the range
+    /// `[0.0, 1.0]`, or `None` if the total number of records is unknown.
+    pub fn fraction_complete(&self) -> Option<f64> {
+        self.estimated_records.map(|total| {
+            if total == 0 {
+                1.0
+            } else {
+                (self.records_decoded as f64 / total as f64).min(1.0)
+            }
+        })
+    }
+}
+
+/// A decoder returned by [`TimeseriesClient::get_range_with_progress()`] that reports
+/// [`DownloadProgress`] to a callback as records are decoded.
+///
+/// The callback is invoked every [`PROGRESS_INTERVAL`](Self::PROGRESS_INTERVAL)
+/// records and once more when the end of the stream is first reached.
+pub struct ProgressDecoder<R>
+where
+    R: AsyncReadExt + Unpin,
+{
+    decoder: AsyncDbnDecoder<R>,
+    bytes_received: Arc<AtomicU64>,
+    records_decoded: u64,
+    estimated_records: Option<u64>,
+    finished: bool,
+    callback: Box<dyn FnMut(&DownloadProgress) + Send>,
+}
+
+impl<R> ProgressDecoder<R>
+where
+    R: AsyncReadExt + Unpin,
+{
+    /// The number of records between invocations of the progress callback.
+    pub const PROGRESS_INTERVAL: u64 = 10_000;
+
+    /// Returns a reference to the decoded DBN metadata.
+    pub fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    /// Returns the current progress.
+    pub fn progress(&self) -> DownloadProgress {
+        DownloadProgress {
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            records_decoded: self.records_decoded,
+            estimated_records: self.estimated_records,
+        }
+    }
+
+    /// Tries to decode the next record of type `T`. Returns `Ok(None)` if the end of
+    /// the stream has been reached.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error of a
+    /// kind other than `io::ErrorKind::UnexpectedEof` upon reading.
+    ///
+    /// If the next record is of a different type than `T`, this function returns an
+    /// error of kind `io::ErrorKind::InvalidData`.
+    pub async fn decode_record<'a, T: HasRType + 'a>(&'a mut self) -> crate::Result<Option<&'a T>> {
+        let rec = self.decoder.decode_record::<T>().await?;
+        Self::on_decode(
+            rec.is_some(),
+            &mut self.records_decoded,
+            &mut self.finished,
+            &self.bytes_received,
+            self.estimated_records,
+            &mut self.callback,
+        );
+        Ok(rec)
+    }
+
+    /// Tries to decode a generic reference to the next record. Returns `Ok(None)` if
+    /// the end of the stream has been reached.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error of a
+    /// kind other than `io::ErrorKind::UnexpectedEof` upon reading.
+    pub async fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+        let rec = self.decoder.decode_record_ref().await?;
+        Self::on_decode(
+            rec.is_some(),
+            &mut self.records_decoded,
+            &mut self.finished,
+            &self.bytes_received,
+            self.estimated_records,
+            &mut self.callback,
+        );
+        Ok(rec)
+    }
+
+    /// Consumes the progress decoder and returns the inner decoder.
+    pub fn into_inner(self) -> AsyncDbnDecoder<R> {
+        self.decoder
+    }
+
+    fn on_decode(
+        decoded: bool,
+        records_decoded: &mut u64,
+        finished: &mut bool,
+        bytes_received: &AtomicU64,
+        estimated_records: Option<u64>,
+        callback: &mut Box<dyn FnMut(&DownloadProgress) + Send>,
+    ) {
+        if decoded {
+            *records_decoded += 1;
+        } else if *finished {
+            return;
+        } else {
+            *finished = true;
+        }
+        if !decoded || records_decoded.is_multiple_of(Self::PROGRESS_INTERVAL) {
+            callback(&DownloadProgress {
+                bytes_received: bytes_received.load(Ordering::Relaxed),
+                records_decoded: *records_decoded,
+                estimated_records,
+            });
+        }
+    }
+}
+
+impl<R> fmt::Debug for ProgressDecoder<R>
+where
+    R: AsyncReadExt + Unpin,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressDecoder")
+            .field("bytes_received", &self.bytes_received)
+            .field("records_decoded", &self.records_decoded)
+            .field("estimated_records", &self.estimated_records)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A decoder that pairs each record with its symbol from the symbology mappings in
+/// the response metadata, returned by [`TimeseriesClient::get_range_with_symbols()`]
+/// or created from any decoder with [`SymbolDecoder::new()`].
+pub struct SymbolDecoder<R>
+where
+    R: AsyncReadExt + Unpin,
+{
+    decoder: AsyncDbnDecoder<R>,
+    symbol_map: TsSymbolMap,
+}
+
+impl<R> SymbolDecoder<R>
+where
+    R: AsyncReadExt + Unpin,
+{
+    /// Creates a symbol decoder from the mappings in the metadata of `decoder`.
+    ///
+    /// # Errors
+    /// This function returns an error when the mappings can't be parsed as instrument
+    /// IDs, or neither the input nor output symbology type is instrument ID.
+    pub fn new(decoder: AsyncDbnDecoder<R>) -> crate::Result<Self> {
+        let symbol_map = decoder.metadata().symbol_map()?;
+        Ok(Self {
+            decoder,
+            symbol_map,
+        })
+    }
+
+    /// Returns a reference to the decoded DBN metadata.
+    pub fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    /// Returns the symbol map built from the metadata.
+    pub fn symbol_map(&self) -> &TsSymbolMap {
+        &self.symbol_map
+    }
+
+    /// Tries to decode the next record of type `T` along with its symbol on the date
+    /// of the record, or `None` if the metadata has no mapping for its instrument on
+    /// that date. Returns `Ok(None)` if the end of the stream has been reached.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error of a
+    /// kind other than `io::ErrorKind::UnexpectedEof` upon reading.
+    ///
+    /// If the next record is of a different type than `T`, this function returns an
+    /// error of kind `io::ErrorKind::InvalidData`.
+    pub async fn decode_record_with_symbol<'a, T: HasRType + 'a>(
+        &'a mut self,
+    ) -> crate::Result<Option<(&'a T, Option<&'a str>)>> {
+        let Some(rec) = self.decoder.decode_record::<T>().await? else {
+            return Ok(None);
+        };
+        let symbol = self.symbol_map.get_for_rec(rec).map(String::as_str);
+        Ok(Some((rec, symbol)))
+    }
+
+    /// Tries to decode a generic reference to the next record along with its symbol.
+    /// Returns `Ok(None)` if the end of the stream has been reached.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error of a
+    /// kind other than `io::ErrorKind::UnexpectedEof` upon reading.
+    pub async fn decode_record_ref_with_symbol(
+        &mut self,
+    ) -> crate::Result<Option<(RecordRef<'_>, Option<&str>)>> {
+        let Some(rec) = self.decoder.decode_record_ref().await? else {
+            return Ok(None);
+        };
+        let symbol = self.symbol_map.get_for_rec(&rec).map(String::as_str);
+        Ok(Some((rec, symbol)))
+    }
+
+    /// Consumes the symbol decoder and returns the inner decoder.
+    pub fn into_inner(self) -> AsyncDbnDecoder<R> {
+        self.decoder
+    }
+}
+
+impl<R> fmt::Debug for SymbolDecoder<R>
+where
+    R: AsyncReadExt + Unpin,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SymbolDecoder")
+            .field("metadata", self.metadata())
+            .field("symbol_map", &self.symbol_map.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A decoder that skips records whose header doesn't match a predicate, returned by
+/// [`DecoderExt::filter()`].
+///
+/// The predicate is checked against the [`RecordHeader`] of each record before it's
+/// converted to a typed record, so skipped records, e.g. of other instruments in a
+/// request for all symbols, are never copied or allocated.
+pub struct FilteredDecoder<R, F>
+where
+    R: AsyncReadExt + Unpin,
+{
+    decoder: AsyncDbnDecoder<R>,
+    predicate: F,
+    records_skipped: u64,
+}
+
+impl<R, F> FilteredDecoder<R, F>
+where
+    R: AsyncReadExt + Unpin,
+    F: FnMut(&RecordHeader) -> bool,
+{
+    /// Returns a reference to the decoded DBN metadata.
+    pub fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    /// Returns the number of records skipped so far.
+    pub fn records_skipped(&self) -> u64 {
+        self.records_skipped
+    }
+
+    /// Tries to decode the next record of type `T` matching the predicate. Returns
+    /// `Ok(None)` if the end of the stream has been reached.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error of a
+    /// kind other than `io::ErrorKind::UnexpectedEof` upon reading.
+    ///
+    /// If the next matching record is of a different type than `T`, this function
+    /// returns an error of kind `io::ErrorKind::InvalidData`.
+    pub async fn decode_record<'a, T: HasRType + 'a>(&'a mut self) -> crate::Result<Option<&'a T>> {
+        let Some(rec) = self.decode_record_ref().await? else {
+            return Ok(None);
+        };
+        rec.get::<T>().map(Some).ok_or_else(|| {
+            crate::Error::from(dbn::Error::conversion::<T>(format!(
+                "record with rtype {:#04X}",
+                rec.header().rtype
+            )))
+        })
+    }
+
+    /// Tries to decode a generic reference to the next record matching the predicate.
+    /// Returns `Ok(None)` if the end of the stream has been reached.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error of a
+    /// kind other than `io::ErrorKind::UnexpectedEof` upon reading.
+    pub async fn decode_record_ref(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+        loop {
+            let Some(rec) = self.decoder.decode_record_ref().await? else {
+                return Ok(None);
+            };
+            if (self.predicate)(rec.header()) {
+                // SAFETY: `rec` was created from these bytes by the decoder, which isn't
+                // modified before the returned reference expires. Recreating it works
+                // around the borrow checker rejecting a conditional return from a loop.
+                return Ok(Some(unsafe {
+                    RecordRef::new(std::slice::from_raw_parts(
+                        rec.as_ref().as_ptr(),
+                        rec.record_size(),
+                    ))
+                }));
+            }
+            self.records_skipped += 1;
+        }
+    }
+
+    /// Consumes the filtered decoder and returns the inner decoder.
+    pub fn into_inner(self) -> AsyncDbnDecoder<R> {
+        self.decoder
+    }
+}
+
+impl<R, F> fmt::Debug for FilteredDecoder<R, F>
+where
+    R: AsyncReadExt + Unpin,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilteredDecoder")
+            .field("metadata", self.decoder.metadata())
+            .field("records_skipped", &self.records_skipped)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Extension methods for processing the records of an [`AsyncDbnDecoder`] as they're
+/// decoded, so large responses such as MBO data can be handled with bounded memory
+/// instead of being collected into a `Vec`.
+pub trait DecoderExt {
+    /// The type of the underlying reader.
+    type Reader: AsyncReadExt + Unpin;
+
+    /// Calls `f` with each record of type `T` until the end of the stream, returning
+    /// the number of records processed.
+    ///
+    /// # Errors
+    /// This function returns an error if the underlying reader returns an error or a
+    /// record of a different type than `T` is decoded.
+    fn for_each_record<T, F>(&mut self, f: F) -> impl Future<Output = crate::Result<u64>> + Send
+    where
+        T: HasRType,
+        F: FnMut(&T) + Send;
+
+    /// Spawns a task that decodes records of type `T` and sends them through a channel
+    /// holding at most `capacity` records. When the channel is full, decoding pauses,
+    /// and with it reading from the response, until records are received.
+    ///
+    /// The channel closes at the end of the stream or after sending an error. Dropping
+    /// the receiver stops the task.
+    fn into_channel<T>(self, capacity: usize) -> mpsc::Receiver<crate::Result<T>>
+    where
+        T: HasRType + Clone + Send + 'static,
+        Self: 'static;
+
+    /// Converts the decoder into a [`Stream`] of records of type `T`, so they can be
+    /// processed with [`StreamExt`](futures::StreamExt) and
+    /// [`TryStreamExt`] combinators. The stream ends after the last record or the first
+    /// error.
+    fn into_stream<T>(self) -> impl Stream<Item = crate::Result<T>> + Send + Unpin
+    where
+        T: HasRType + Clone + Send;
+
+    /// Wraps the decoder so it only returns records whose header matches `predicate`,
+    /// e.g. only certain instrument IDs or a range of `ts_event`. Records are checked
+    /// before being converted to a typed record, so the rest are skipped cheaply.
+    fn filter<F>(self, predicate: F) -> FilteredDecoder<Self::Reader, F>
+    where
+        F: FnMut(&RecordHeader) -> bool;
+}
+
+impl<R> DecoderExt for AsyncDbnDecoder<R>
+where
+    R: AsyncReadExt + Unpin + Send,
+{
+    type Reader = R;
+
+    #[instrument(level = "debug", skip_all)]
+    async fn for_each_record<T, F>(&mut self, mut f: F) -> crate::Result<u64>
+    where
+        T: HasRType,
+        F: FnMut(&T) + Send,
+    {
+        let mut count = 0;
+        while let Some(rec) = self.decode_record::<T>().await? {
+            f(rec);
+            count += 1;
+        }
+        debug!(records = count, "Decoded records");
+        Ok(count)
+    }
+
+    fn into_channel<T>(mut self, capacity: usize) -> mpsc::Receiver<crate::Result<T>>
+    where
+        T: HasRType + Clone + Send + 'static,
+        Self: 'static,
+    {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let decode = async move {
+            loop {
+                let res = match self.decode_record::<T>().await {
+                    Ok(Some(rec)) => Ok(rec.clone()),
+                    Ok(None) => break,
+                    Err(err) => Err(crate::Error::from(err)),
+                };
+                let is_err = res.is_err();
+                if tx.send(res).await.is_err() || is_err {
+                    break;
+                }
+            }
+        };
+        // Decode within the caller's span
+        tokio::spawn(decode.in_current_span());
+        rx
+    }
+
+    fn into_stream<T>(self) -> impl Stream<Item = crate::Result<T>> + Send + Unpin
+    where
+        T: HasRType + Clone + Send,
+    {
+        Box::pin(futures::stream::try_unfold(
+            self,
+            |mut decoder| async move {
+                let rec = decoder.decode_record::<T>().await?.cloned();
+                Ok::<_, crate::Error>(rec.map(|rec| (rec, decoder)))
+            },
+        ))
+    }
+
+    fn filter<F>(self, predicate: F) -> FilteredDecoder<R, F>
+    where
+        F: FnMut(&RecordHeader) -> bool,
+    {
+        FilteredDecoder {
+            decoder: self,
+            predicate,
+            records_skipped: 0,
+        }
+    }
+}
+
 /// The parameters for [`TimeseriesClient::get_range()`]. Use
 /// [`GetRangeParams::builder()`] to get a builder type with all the preset defaults.
 #[derive(Debug, Clone, TypedBuilder, PartialEq, Eq)]
@@ -206,6 +828,11 @@ pub struct GetRangeToFileParams {
     /// The file path to persist the stream data to.
     #[builder(default, setter(transform = |p: impl Into<PathBuf>| p.into()))]
     pub path: PathBuf,
+    /// Whether to reuse an existing file at `path` that matches its recorded checksum
+    /// instead of requesting the data again. A file that doesn't match is requested
+    /// again. Defaults to `false`.
+    #[builder(default)]
+    pub reuse_existing: bool,
 }
 
 impl From<GetRangeToFileParams> for GetRangeParams {
@@ -237,6 +864,7 @@ impl GetRangeParams {
             limit: self.limit,
             upgrade_policy: self.upgrade_policy,
             path: path.into(),
+            reuse_existing: false,
         }
     }
 }
@@ -310,6 +938,167 @@ mod tests {
         assert!(decoder.decode_record::<TradeMsg>().await.unwrap().is_none());
     }
 
+    #[tokio::test]
+    async fn test_get_range_with_progress() {
+        const START: time::OffsetDateTime = datetime!(2023 - 06 - 14 00:00 UTC);
+        const END: time::OffsetDateTime = datetime!(2023 - 06 - 17 00:00 UTC);
+        const SCHEMA: Schema = Schema::Trades;
+
+        let mock_server = MockServer::start().await;
+        let bytes = tokio::fs::read(zst_test_data_path(SCHEMA)).await.unwrap();
+        let byte_len = bytes.len() as u64;
+        Mock::given(method("POST"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/metadata.get_record_count")))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(2))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(basic_auth(API_KEY, ""))
+            .and(path(format!("/v{API_VERSION}/timeseries.get_range")))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes))
+            .mount(&mock_server)
+            .await;
+        let mut target = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reports_clone = reports.clone();
+        let mut decoder = target
+            .timeseries()
+            .get_range_with_progress(
+                &GetRangeParams::builder()
+                    .dataset(dbn::Dataset::XnasItch)
+                    .schema(SCHEMA)
+                    .symbols(vec!["SPOT", "AAPL"])
+                    .date_time_range((START, END))
+                    .build(),
+                move |progress| reports_clone.lock().unwrap().push(*progress),
+            )
+            .await
+            .unwrap();
+        while decoder.decode_record::<TradeMsg>().await.unwrap().is_some() {}
+        // Decoding past the end shouldn't report progress again
+        assert!(decoder.decode_record::<TradeMsg>().await.unwrap().is_none());
+        assert!(decoder.decode_record_ref().await.unwrap().is_none());
+        let progress = decoder.progress();
+        assert_eq!(progress.records_decoded, 2);
+        assert_eq!(progress.bytes_received, byte_len);
+        assert_eq!(progress.fraction_complete(), Some(1.0));
+        let reports = reports.lock().unwrap();
+        assert_eq!(*reports, vec![progress]);
+    }
+
+    #[tokio::test]
+    async fn test_for_each_record() {
+        let mut decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let mut sizes = Vec::new();
+        let count = decoder
+            .for_each_record(|trade: &TradeMsg| sizes.push(trade.size))
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(sizes.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_symbol_decoder() {
+        let decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let mut decoder = SymbolDecoder::new(decoder).unwrap();
+        let mut symbols = Vec::new();
+        while let Some((trade, symbol)) = decoder
+            .decode_record_with_symbol::<TradeMsg>()
+            .await
+            .unwrap()
+        {
+            symbols.push((trade.hd.instrument_id, symbol.map(str::to_owned)));
+        }
+        assert_eq!(
+            symbols,
+            [
+                (5482, Some("ESH1".to_owned())),
+                (5482, Some("ESH1".to_owned()))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_into_channel() {
+        let decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let mut rx = decoder.into_channel::<TradeMsg>(1);
+        assert!(rx.recv().await.unwrap().is_ok());
+        assert!(rx.recv().await.unwrap().is_ok());
+        assert!(rx.recv().await.is_none());
+
+        // A record of the wrong type ends the stream with an error
+        let decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let mut rx = decoder.into_channel::<dbn::MboMsg>(1);
+        assert!(rx.recv().await.unwrap().is_err());
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_into_stream() {
+        use futures::StreamExt;
+
+        let decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let trades: Vec<TradeMsg> = decoder
+            .into_stream()
+            .filter(|res| {
+                std::future::ready(res.as_ref().is_ok_and(|trade: &TradeMsg| trade.size > 0))
+            })
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_filter() {
+        let decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let mut decoder = decoder.filter(|hd| hd.instrument_id == 5482);
+        let mut count = 0;
+        while let Some(trade) = decoder.decode_record::<TradeMsg>().await.unwrap() {
+            assert_eq!(trade.hd.instrument_id, 5482);
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        assert_eq!(decoder.records_skipped(), 0);
+
+        let decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let mut decoder = decoder.filter(|hd| [1, 2, 3].contains(&hd.instrument_id));
+        assert!(decoder.decode_record_ref().await.unwrap().is_none());
+        assert_eq!(decoder.records_skipped(), 2);
+
+        // The wrong type is only an error for matching records
+        let decoder = AsyncDbnDecoder::from_zstd_file(zst_test_data_path(Schema::Trades))
+            .await
+            .unwrap();
+        let mut decoder = decoder.filter(|hd| hd.ts_event == 0);
+        assert!(decoder
+            .decode_record::<dbn::MboMsg>()
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_get_range_to_file() {
         const START: time::OffsetDateTime = datetime!(2024 - 05 - 17 00:00 UTC);
@@ -334,7 +1123,10 @@ mod tests {
             // // default
             .and(body_contains("stype_in", "parent"))
             .and(body_contains("stype_out", "instrument_id"))
-            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes.clone()),
+            )
+            .expect(2)
             .mount(&mock_server)
             .await;
         let mut target = HistoricalClient::with_url(
@@ -363,5 +1155,30 @@ mod tests {
         decoder.decode_record::<TradeMsg>().await.unwrap().unwrap();
         decoder.decode_record::<TradeMsg>().await.unwrap().unwrap();
         assert!(decoder.decode_record::<TradeMsg>().await.unwrap().is_none());
+        // Persisted as received
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), bytes);
+        checksum::verify_file(&path).await.unwrap();
+
+        let params = GetRangeToFileParams::builder()
+            .dataset(DATASET)
+            .schema(SCHEMA)
+            .symbols(vec!["BRN.FUT"])
+            .stype_in(SType::Parent)
+            .date_time_range((START, END))
+            .path(path.clone())
+            .reuse_existing(true)
+            .build();
+        // Reused without a request
+        assert_eq!(
+            target.timeseries().download_range(&params).await.unwrap(),
+            0
+        );
+        // Requested again when corrupt
+        tokio::fs::write(&path, &bytes[1..]).await.unwrap();
+        assert_eq!(
+            target.timeseries().download_range(&params).await.unwrap(),
+            bytes.len() as u64
+        );
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), bytes);
     }
 }
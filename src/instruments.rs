@@ -0,0 +1,278 @@
+//! A security master of instrument definitions.
+//!
+//! A [`SecurityMaster`] indexes the [`InstrumentDefMsg`]s of a dataset by instrument
+//! ID, raw symbol, and asset, e.g. to look up the contract multiplier or expiration of
+//! the instruments in a candle series. [`snapshot()`] downloads the definitions of
+//! every instrument in a dataset for a date, and [`cached_snapshot()`] keeps a copy on
+//! disk so later runs don't download them again.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use dbn::InstrumentDefMsg;
+
+use crate::portfolio::InstrumentSpec;
+
+/// The instrument definitions of a dataset, indexed by instrument ID, raw symbol, and
+/// asset.
+#[derive(Debug, Clone, Default)]
+pub struct SecurityMaster {
+    definitions: Vec<InstrumentDefMsg>,
+    by_id: HashMap<u32, usize>,
+    by_symbol: HashMap<String, usize>,
+    by_asset: HashMap<String, Vec<usize>>,
+}
+
+impl SecurityMaster {
+    /// Creates a security master from `definitions` in publication order. When an
+    /// instrument has several definitions, the latest one is kept.
+    pub fn from_definitions(definitions: impl IntoIterator<Item = InstrumentDefMsg>) -> Self {
+        let mut master = Self::default();
+        for definition in definitions {
+            master.insert(definition);
+        }
+        master
+    }
+
+    /// Adds `definition`, replacing any earlier definition of its instrument.
+    pub fn insert(&mut self, definition: InstrumentDefMsg) {
+        let instrument_id = definition.hd.instrument_id;
+        if let Some(&idx) = self.by_id.get(&instrument_id) {
+            let old = std::mem::replace(&mut self.definitions[idx], definition);
+            if let Ok(symbol) = old.raw_symbol() {
+                self.by_symbol.remove(symbol);
+            }
+            if let Some(ids) = old
+                .asset()
+                .ok()
+                .and_then(|asset| self.by_asset.get_mut(asset))
+            {
+                ids.retain(|&i| i != idx);
+            }
+            self.index(idx);
+        } else {
+            self.definitions.push(definition);
+            let idx = self.definitions.len() - 1;
+            self.by_id.insert(instrument_id, idx);
+            self.index(idx);
+        }
+    }
+
+    /// Returns the definition of `instrument_id`.
+    pub fn get(&self, instrument_id: u32) -> Option<&InstrumentDefMsg> {
+        self.by_id
+            .get(&instrument_id)
+            .map(|&idx| &self.definitions[idx])
+    }
+
+    /// Returns the definition of the instrument with the raw symbol `symbol`, e.g.
+    /// `ESM5`.
+    pub fn by_symbol(&self, symbol: &str) -> Option<&InstrumentDefMsg> {
+        self.by_symbol
+            .get(symbol)
+            .map(|&idx| &self.definitions[idx])
+    }
+
+    /// Returns the definitions of every instrument of `asset`, e.g. `ES` for the
+    /// E-mini S&P 500 futures, spreads, and options.
+    pub fn by_asset(&self, asset: &str) -> Vec<&InstrumentDefMsg> {
+        self.by_asset.get(asset).map_or_else(Vec::new, |ids| {
+            ids.iter().map(|&idx| &self.definitions[idx]).collect()
+        })
+    }
+
+    /// Returns the tick size and contract multiplier of `instrument_id`.
+    pub fn spec(&self, instrument_id: u32) -> Option<InstrumentSpec> {
+        self.get(instrument_id).map(InstrumentSpec::from_definition)
+    }
+
+    /// Returns the number of instruments.
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    /// Returns `true` if there are no instruments.
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    /// Returns an iterator over the definitions in the order their instruments were
+    /// first added.
+    pub fn iter(&self) -> impl Iterator<Item = &InstrumentDefMsg> {
+        self.definitions.iter()
+    }
+
+    fn index(&mut self, idx: usize) {
+        let definition = &self.definitions[idx];
+        if let Ok(symbol) = definition.raw_symbol() {
+            self.by_symbol.insert(symbol.to_owned(), idx);
+        }
+        if let Ok(asset) = definition.asset() {
+            if !asset.is_empty() {
+                self.by_asset.entry(asset.to_owned()).or_default().push(idx);
+            }
+        }
+    }
+}
+
+/// Downloads the definitions of every instrument in `dataset` published on `date`,
+/// including the snapshot of instruments defined before it.
+///
+/// # Errors
+/// This function returns an error when `date` is out of range, the request fails, or
+/// the response can't be decoded.
+#[cfg(feature = "historical")]
+pub async fn snapshot(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    date: NaiveDate,
+) -> crate::Result<SecurityMaster> {
+    let (_, definitions) = fetch_definitions(client, dataset, date).await?;
+    Ok(SecurityMaster::from_definitions(definitions))
+}
+
+/// Like [`snapshot()`], but reads the definitions from a Zstandard-compressed DBN file in
+/// `cache_dir` if an earlier call saved one for `dataset` and `date`, and otherwise
+/// saves the downloaded definitions there.
+///
+/// # Errors
+/// This function returns an error when `date` is out of range, the request fails, the
+/// response can't be decoded, or the cache file can't be read or written.
+#[cfg(feature = "historical")]
+pub async fn cached_snapshot(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    date: NaiveDate,
+    cache_dir: impl AsRef<std::path::Path>,
+) -> crate::Result<SecurityMaster> {
+    use std::{fs::File, io::BufWriter};
+
+    use dbn::encode::{DbnEncoder, EncodeRecord};
+
+    let dir = cache_dir.as_ref().join(dataset);
+    let path = dir.join(format!("definition-{date}.dbn.zst"));
+    if path.exists() {
+        let mut decoder = dbn::decode::AsyncDbnDecoder::from_zstd_file(&path).await?;
+        let mut definitions = Vec::new();
+        while let Some(definition) = decoder.decode_record::<InstrumentDefMsg>().await? {
+            definitions.push(definition.clone());
+        }
+        return Ok(SecurityMaster::from_definitions(definitions));
+    }
+    let (metadata, definitions) = fetch_definitions(client, dataset, date).await?;
+    std::fs::create_dir_all(&dir)?;
+    // Write to a temporary file first so an interrupted write isn't mistaken for a
+    // complete snapshot
+    let partial = path.with_extension("zst.partial");
+    {
+        let mut encoder =
+            DbnEncoder::with_zstd(BufWriter::new(File::create(&partial)?), &metadata)?;
+        for definition in &definitions {
+            encoder.encode_record(definition)?;
+        }
+    }
+    std::fs::rename(&partial, &path)?;
+    Ok(SecurityMaster::from_definitions(definitions))
+}
+
+#[cfg(feature = "historical")]
+async fn fetch_definitions(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    date: NaiveDate,
+) -> crate::Result<(dbn::Metadata, Vec<InstrumentDefMsg>)> {
+    use crate::timeconv::ToTimeDate;
+
+    let params = crate::historical::timeseries::GetRangeParams::builder()
+        .dataset(dataset)
+        .symbols(crate::Symbols::All)
+        .schema(dbn::Schema::Definition)
+        .date_time_range(date.to_time_date()?)
+        .build();
+    let mut decoder = client.get_range(&params).await?;
+    let metadata = decoder.metadata().clone();
+    let mut definitions = Vec::new();
+    while let Some(definition) = decoder.decode_record::<InstrumentDefMsg>().await? {
+        definitions.push(definition.clone());
+    }
+    Ok((metadata, definitions))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+
+    fn definition(instrument_id: u32, raw_symbol: &str, asset: &str) -> InstrumentDefMsg {
+        let mut definition = InstrumentDefMsg {
+            hd: RecordHeader::new::<InstrumentDefMsg>(rtype::INSTRUMENT_DEF, 1, instrument_id, 0),
+            min_price_increment: 250_000_000,
+            unit_of_measure_qty: 50_000_000_000,
+            ..Default::default()
+        };
+        for (dst, src) in definition.raw_symbol.iter_mut().zip(raw_symbol.bytes()) {
+            *dst = src as c_char;
+        }
+        for (dst, src) in definition.asset.iter_mut().zip(asset.bytes()) {
+            *dst = src as c_char;
+        }
+        definition
+    }
+
+    #[test]
+    fn test_security_master() {
+        let mut master = SecurityMaster::from_definitions([
+            definition(1, "ESM5", "ES"),
+            definition(2, "ESU5", "ES"),
+            definition(3, "NQM5", "NQ"),
+        ]);
+        assert_eq!(master.len(), 3);
+        assert_eq!(master.by_symbol("ESU5").unwrap().hd.instrument_id, 2);
+        assert_eq!(master.by_asset("ES").len(), 2);
+        assert_eq!(master.spec(3).unwrap().multiplier, 50.0);
+        // A later definition replaces the earlier one
+        master.insert(definition(2, "ESU5-NEW", "MES"));
+        assert_eq!(master.len(), 3);
+        assert!(master.by_symbol("ESU5").is_none());
+        assert_eq!(master.by_symbol("ESU5-NEW").unwrap().hd.instrument_id, 2);
+        assert_eq!(master.by_asset("ES").len(), 1);
+        assert_eq!(master.by_asset("MES").len(), 1);
+        assert!(master.get(4).is_none());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_cached_snapshot() {
+        use dbn::Schema;
+
+        use crate::testing::MockHistoricalClient;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 4, 22).unwrap();
+        let mut client = MockHistoricalClient::new()
+            .with_records(
+                "GLBX.MDP3",
+                Schema::Definition,
+                &[definition(1, "ESM5", "ES"), definition(3, "NQM5", "NQ")],
+            )
+            .unwrap();
+        let master = cached_snapshot(&mut client, "GLBX.MDP3", date, dir.path())
+            .await
+            .unwrap();
+        assert_eq!(master.len(), 2);
+        assert!(dir
+            .path()
+            .join("GLBX.MDP3/definition-2025-04-22.dbn.zst")
+            .exists());
+        // A client without fixtures would fail, so the second call reads the cache
+        let mut empty = MockHistoricalClient::new();
+        let cached = cached_snapshot(&mut empty, "GLBX.MDP3", date, dir.path())
+            .await
+            .unwrap();
+        assert_eq!(cached.by_symbol("NQM5").unwrap().hd.instrument_id, 3);
+        assert!(snapshot(&mut empty, "GLBX.MDP3", date).await.is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Sources of API keys that don't depend on ambient environment state.
+//!
+//! A [`KeyProvider`] is passed to `key_from_provider()` of the
+//! [historical](crate::historical::ClientBuilder::key_from_provider) and
+//! [live](crate::live::ClientBuilder::key_from_provider) client builders.
+//! [`EnvKeyProvider`] reads an environment variable, [`FileKeyProvider`] reads a file
+//! such as `~/.databento/key`, and [`KeychainKeyProvider`] reads the operating
+//! system's keychain. Closures returning an [`ApiKey`] are also providers, e.g. for a
+//! key injected by an application embedding the library.
+//!
+//! ```no_run
+//! use databento::{key_provider::FileKeyProvider, HistoricalClient};
+//!
+//! # fn main() -> databento::Result<()> {
+//! let client = HistoricalClient::builder()
+//!     .key_from_provider(FileKeyProvider::default())?
+//!     .build()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{env, path::PathBuf};
+
+use zeroize::Zeroize;
+
+use crate::{ApiKey, Error, Result};
+
+/// A source of an API key.
+pub trait KeyProvider {
+    /// Returns the API key.
+    ///
+    /// # Errors
+    /// This function returns an error when the key can't be retrieved or is invalid.
+    fn key(&self) -> Result<ApiKey>;
+}
+
+impl<F: Fn() -> Result<ApiKey>> KeyProvider for F {
+    fn key(&self) -> Result<ApiKey> {
+        self()
+    }
+}
+
+/// Reads the API key from an environment variable, `DATABENTO_API_KEY` by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvKeyProvider {
+    var: String,
+}
+
+impl EnvKeyProvider {
+    /// Creates a provider reading the environment variable `var`.
+    pub fn new(var: impl ToString) -> Self {
+        Self {
+            var: var.to_string(),
+        }
+    }
+}
+
+impl Default for EnvKeyProvider {
+    fn default() -> Self {
+        Self::new("DATABENTO_API_KEY")
+    }
+}
+
+impl KeyProvider for EnvKeyProvider {
+    fn key(&self) -> Result<ApiKey> {
+        let key = env::var(&self.var).map_err(|e| {
+            Error::bad_arg(
+                "key",
+                match e {
+                    env::VarError::NotPresent => format!(
+                        "tried to read API key from environment variable {} but it is not set",
+                        self.var
+                    ),
+                    env::VarError::NotUnicode(_) => {
+                        format!("environment variable {} contains invalid unicode", self.var)
+                    }
+                },
+            )
+        })?;
+        ApiKey::new(key)
+    }
+}
+
+/// Reads the API key from a file containing only the key, `~/.databento/key` by
+/// default. Leading and trailing whitespace is ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileKeyProvider {
+    // `None` when the home directory is unknown
+    path: Option<PathBuf>,
+}
+
+impl FileKeyProvider {
+    /// Creates a provider reading the file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+        }
+    }
+
+    /// Returns the default path of the key file, `.databento/key` in the current user's
+    /// home directory, or `None` if the home directory is unknown.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .filter(|home| !home.is_empty())
+            .map(|home| PathBuf::from(home).join(".databento").join("key"))
+    }
+}
+
+impl Default for FileKeyProvider {
+    fn default() -> Self {
+        Self {
+            path: Self::default_path(),
+        }
+    }
+}
+
+impl KeyProvider for FileKeyProvider {
+    fn key(&self) -> Result<ApiKey> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            Error::bad_arg(
+                "key",
+                "couldn't determine the home directory for the key file",
+            )
+        })?;
+        let mut contents = std::fs::read_to_string(path).map_err(|e| {
+            Error::bad_arg(
+                "key",
+                format!("failed to read API key from {}: {e}", path.display()),
+            )
+        })?;
+        let key = contents.trim().to_owned();
+        contents.zeroize();
+        ApiKey::new(key)
+    }
+}
+
+/// Reads the API key from the operating system's keychain: the login keychain with
+/// the `security` tool on macOS and the Secret Service with `secret-tool` on Linux.
+///
+/// Other platforms, including Windows and its Credential Manager, aren't supported:
+/// [`key()`](KeyProvider::key) always returns an error there. Use a
+/// [`FileKeyProvider`], an [`EnvKeyProvider`], or a closure returning a key from the
+/// embedding application instead.
+///
+/// The key is stored as a generic password for a service and account, by default
+/// `databento` and `api_key`, e.g. with
+/// `security add-generic-password -s databento -a api_key -w` on macOS or
+/// `secret-tool store --label=Databento service databento account api_key` on Linux.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeychainKeyProvider {
+    service: String,
+    account: String,
+}
+
+impl KeychainKeyProvider {
+    /// Creates a provider reading the password of `account` for `service`.
+    pub fn new(service: impl ToString, account: impl ToString) -> Self {
+        Self {
+            service: service.to_string(),
+            account: account.to_string(),
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn command(&self) -> Result<std::process::Command> {
+        let mut cmd = std::process::Command::new("security");
+        cmd.args(["find-generic-password", "-s", &self.service])
+            .args(["-a", &self.account, "-w"]);
+        Ok(cmd)
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn command(&self) -> Result<std::process::Command> {
+        let mut cmd = std::process::Command::new("secret-tool");
+        cmd.args(["lookup", "service", &self.service])
+            .args(["account", &self.account]);
+        Ok(cmd)
+    }
+
+    #[cfg(not(unix))]
+    fn command(&self) -> Result<std::process::Command> {
+        Err(Error::bad_arg(
+            "key",
+            format!(
+                "reading the API key from the OS keychain is unsupported on {}; \
+                 use a file or environment variable key provider instead",
+                env::consts::OS
+            ),
+        ))
+    }
+}
+
+impl Default for KeychainKeyProvider {
+    fn default() -> Self {
+        Self::new("databento", "api_key")
+    }
+}
+
+impl KeyProvider for KeychainKeyProvider {
+    fn key(&self) -> Result<ApiKey> {
+        let mut cmd = self.command()?;
+        let output = cmd
+            .output()
+            .map_err(|e| Error::bad_arg("key", format!("failed to run the keychain tool: {e}")))?;
+        if !output.status.success() {
+            return Err(Error::bad_arg(
+                "key",
+                format!(
+                    "no API key in the keychain for service {} and account {}: {}",
+                    self.service,
+                    self.account,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        let mut stdout = output.stdout;
+        let key = String::from_utf8_lossy(&stdout).trim().to_owned();
+        stdout.zeroize();
+        ApiKey::new(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const KEY: &str = "32-character-with-lots-of-filler";
+
+    #[test]
+    fn test_env_key_provider() {
+        const VAR: &str = "DATABENTO_TEST_ENV_KEY_PROVIDER";
+        env::set_var(VAR, KEY);
+        assert_eq!(EnvKeyProvider::new(VAR).key().unwrap().as_str(), KEY);
+        env::remove_var(VAR);
+        let err = EnvKeyProvider::new(VAR).key().unwrap_err();
+        assert!(err.to_string().contains(VAR));
+    }
+
+    #[test]
+    fn test_file_key_provider() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "{KEY}").unwrap();
+        assert_eq!(
+            FileKeyProvider::new(file.path()).key().unwrap().as_str(),
+            KEY
+        );
+        assert!(FileKeyProvider::new(file.path().with_extension("missing"))
+            .key()
+            .is_err());
+        assert!(
+            FileKeyProvider::default_path().is_some_and(|path| path.ends_with(".databento/key"))
+        );
+    }
+
+    #[cfg(not(unix))]
+    #[test]
+    fn test_keychain_key_provider_unsupported() {
+        let err = KeychainKeyProvider::default().key().unwrap_err();
+        assert!(err.to_string().contains("unsupported"));
+    }
+
+    #[test]
+    fn test_closure_key_provider() {
+        let provider = || ApiKey::new(KEY.to_owned());
+        assert_eq!(provider.key().unwrap().as_str(), KEY);
+    }
+}
@@ -0,0 +1,207 @@
+//! Prior-day key levels for futures.
+//!
+//! [`KeyLevels`] holds the levels traders watch alongside the PMZ: the previous regular
+//! trading hours (RTH) session's high, low, close, and settlement, and the overnight
+//! high and low between that session's close and the current session's open.
+//! [`calculate_levels()`] fetches them from any [`MarketDataSource`].
+
+use chrono::{NaiveDate, Utc};
+use dbn::SType;
+
+use crate::{
+    calendar::{TradingCalendar, UsEquityCalendar},
+    examples::es_futures_pmz::{infer_stype, Candle, PmzError, Result, DEFAULT_CANDLE_TZ},
+    source::{DataRequest, MarketDataSource},
+    timeutil::{resolve_local, LocalTimePolicy},
+};
+
+/// The prior-day and overnight levels for a trading day. Levels without any candles
+/// are `None`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyLevels {
+    /// The trading day the levels are for.
+    pub date: NaiveDate,
+    /// The previous trading day.
+    pub prev_date: NaiveDate,
+    /// The high of the previous RTH session.
+    pub prev_high: Option<f64>,
+    /// The low of the previous RTH session.
+    pub prev_low: Option<f64>,
+    /// The close of the last candle of the previous RTH session.
+    pub prev_close: Option<f64>,
+    /// The settlement price of the previous trading day.
+    pub prev_settlement: Option<f64>,
+    /// The high between the previous RTH close and the current RTH open.
+    pub overnight_high: Option<f64>,
+    /// The low between the previous RTH close and the current RTH open.
+    pub overnight_low: Option<f64>,
+}
+
+impl KeyLevels {
+    /// Computes the levels for the trading day `date` in the [`UsEquityCalendar`] from
+    /// 1-minute `candles` in timestamp order covering the previous RTH session through
+    /// the current RTH open, and the previous day's `settlement`, if known.
+    ///
+    /// # Errors
+    /// This function returns an error when `date` isn't a trading day or the session
+    /// times are out of range.
+    pub fn from_candles(
+        date: NaiveDate,
+        candles: &[Candle],
+        settlement: Option<f64>,
+    ) -> Result<Self> {
+        let window = Window::new(date)?;
+        let prev_rth: Vec<_> = candles
+            .iter()
+            .filter(|c| c.timestamp >= window.prev_open && c.timestamp < window.prev_close)
+            .collect();
+        let overnight: Vec<_> = candles
+            .iter()
+            .filter(|c| c.timestamp >= window.prev_close && c.timestamp < window.open)
+            .collect();
+        Ok(Self {
+            date,
+            prev_date: window.prev_date,
+            prev_high: prev_rth.iter().map(|c| c.high).reduce(f64::max),
+            prev_low: prev_rth.iter().map(|c| c.low).reduce(f64::min),
+            prev_close: prev_rth.last().map(|c| c.close),
+            prev_settlement: settlement,
+            overnight_high: overnight.iter().map(|c| c.high).reduce(f64::max),
+            overnight_low: overnight.iter().map(|c| c.low).reduce(f64::min),
+        })
+    }
+}
+
+/// Fetches the candles and settlement for `symbol` in `dataset` from `source` and
+/// computes the [`KeyLevels`] for the trading day on or before `date_opt`, or today if
+/// `None`.
+///
+/// # Errors
+/// This function returns an error when the data can't be retrieved, the symbol can't
+/// be resolved, or there are no candles.
+pub async fn calculate_levels(
+    source: &mut impl MarketDataSource,
+    dataset: &str,
+    symbol: &str,
+    date_opt: Option<NaiveDate>,
+) -> Result<KeyLevels> {
+    let calendar = UsEquityCalendar;
+    let date =
+        calendar.trading_day_on_or_before(date_opt.unwrap_or_else(|| Utc::now().date_naive()));
+    let window = Window::new(date)?;
+    let request = DataRequest {
+        dataset: dataset.to_owned(),
+        symbol: symbol.to_owned(),
+        stype_in: infer_stype(symbol),
+        start: window.prev_open.with_timezone(&Utc),
+        end: window.open.with_timezone(&Utc),
+    };
+    let candles = source.get_candles(&request).await?;
+    if candles.is_empty() {
+        return Err(PmzError::NoData(format!(
+            "no candles for {symbol} between {} and {}",
+            request.start, request.end
+        )));
+    }
+    let settlement = if request.stype_in == SType::Parent {
+        // A parent symbol has a settlement for each contract
+        None
+    } else {
+        source
+            .get_settlement(dataset, symbol, request.stype_in, window.prev_date)
+            .await?
+            .map(|s| s.price)
+    };
+    KeyLevels::from_candles(date, &candles, settlement)
+}
+
+// The previous RTH session and the current open
+struct Window {
+    prev_date: NaiveDate,
+    prev_open: chrono::DateTime<chrono_tz::Tz>,
+    prev_close: chrono::DateTime<chrono_tz::Tz>,
+    open: chrono::DateTime<chrono_tz::Tz>,
+}
+
+impl Window {
+    fn new(date: NaiveDate) -> Result<Self> {
+        let calendar = UsEquityCalendar;
+        let prev_date = calendar.previous_trading_day(date);
+        let (session, prev_session) = calendar
+            .session(date)
+            .zip(calendar.session(prev_date))
+            .ok_or_else(|| PmzError::InvalidDate(format!("{date} is not a trading day")))?;
+        let local = |date: NaiveDate, time| {
+            resolve_local(
+                date.and_time(time),
+                &DEFAULT_CANDLE_TZ,
+                LocalTimePolicy::Earliest,
+            )
+            .map_err(|e| PmzError::InvalidDate(e.to_string()))
+        };
+        Ok(Self {
+            prev_date,
+            prev_open: local(prev_date, prev_session.open)?,
+            prev_close: local(prev_date, prev_session.close)?,
+            open: local(date, session.open)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn candle(day: u32, hour: u32, minute: u32, low: f64, high: f64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2025, 4, day, hour, minute))
+            .ohlc(low, high, low, high)
+            .build()
+    }
+
+    #[test]
+    fn test_from_candles() {
+        let candles = [
+            // Before the previous RTH open
+            candle(17, 9, 0, 5200.0, 5400.0),
+            candle(17, 9, 30, 5290.0, 5300.0),
+            candle(17, 15, 59, 5280.0, 5310.0),
+            // Overnight, skipping Good Friday and the weekend
+            candle(17, 16, 0, 5270.0, 5280.0),
+            candle(20, 18, 0, 5250.0, 5320.0),
+            candle(21, 9, 29, 5260.0, 5265.0),
+            // Current RTH session
+            candle(21, 9, 30, 5100.0, 5500.0),
+        ];
+        let levels = KeyLevels::from_candles(
+            NaiveDate::from_ymd_opt(2025, 4, 21).unwrap(),
+            &candles,
+            Some(5305.25),
+        )
+        .unwrap();
+        assert_eq!(
+            levels.prev_date,
+            NaiveDate::from_ymd_opt(2025, 4, 17).unwrap()
+        );
+        assert_eq!(levels.prev_high, Some(5310.0));
+        assert_eq!(levels.prev_low, Some(5280.0));
+        assert_eq!(levels.prev_close, Some(5310.0));
+        assert_eq!(levels.prev_settlement, Some(5305.25));
+        assert_eq!(levels.overnight_high, Some(5320.0));
+        assert_eq!(levels.overnight_low, Some(5250.0));
+
+        let levels = KeyLevels::from_candles(
+            NaiveDate::from_ymd_opt(2025, 4, 21).unwrap(),
+            &candles[..1],
+            None,
+        )
+        .unwrap();
+        assert!(levels.prev_high.is_none() && levels.overnight_low.is_none());
+        // Good Friday
+        assert!(
+            KeyLevels::from_candles(NaiveDate::from_ymd_opt(2025, 4, 18).unwrap(), &[], None)
+                .is_err()
+        );
+    }
+}
@@ -8,22 +8,98 @@
 //! with output on the [Databento docs site](https://databento.com/docs/?historical=rust&live=rust).
 //!
 //! # Feature flags
-//! By default both features are enabled.
+//! By default the `historical` and `live` features are enabled.
 //! - `historical`: enables the [historical client](HistoricalClient) for data older than 24 hours
+//!   and its [blocking wrapper](blocking::HistoricalClient) for use outside of an async
+//!   runtime
 //! - `live`: enables the [live client](LiveClient) for real-time and intraday
 //!   historical data
+//! - `decimal`: enables [`rust_decimal::Decimal`] as a candle price type for exact
+//!   conversion of fixed-point prices
+//! - `config`: enables loading client and PMZ settings from a TOML
+//!   [config file](config::Config)
+//! - `cli`: builds the `databento-pmz` command-line tool for PMZ calculations, candles,
+//!   and symbology resolution
+//! - `server`: enables an [HTTP service](server) exposing PMZ values and candles as JSON
+//! - `python`: enables the [`databento_pmz` Python module](python) built with maturin
+//! - `replay`: enables [replaying](replay) records from local DBN files
+//! - `arrow`: enables converting candles to [Arrow](arrow) record batches and IPC
+//!   streams for Polars and DataFusion
+//! - `serde`: enables serializing [footprint bars](orderflow::FootprintBar) with serde,
+//!   e.g. to JSON for charting front-ends
+//! - `metrics`: enables collecting request, throughput, and live latency
+//!   [metrics] in the Prometheus text format, served by the [server]
+//! - `parallel`: enables aggregating candles across threads by instrument with rayon in
+//!   [`par_aggregate_candles()`](examples::es_futures_pmz::par_aggregate_candles)
+//! - `sqlite`, `duckdb`: enable writing records to a SQLite or DuckDB database with
+//!   the [sinks](sink)
+//! - `testing`: enables a [mock historical client](testing::MockHistoricalClient) serving
+//!   canned DBN fixtures for unit tests without an API key or network access
+//! - `cbindgen`: regenerates the C header `include/databento_pmz.h` for the [FFI](ffi)
+//!   functions at build time
 
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![deny(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 #![deny(clippy::missing_errors_doc)]
 
+pub mod adjust;
+pub mod alerts;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod auction;
+#[cfg(feature = "historical")]
+pub mod backfill;
+pub mod backtest;
+pub mod bars;
+#[cfg(feature = "historical")]
+pub mod blocking;
+pub mod calendar;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod continuous;
+pub mod dispatch;
 /// Error types for the Databento client
 pub mod error;
+pub mod flow;
 #[cfg(feature = "historical")]
 pub mod historical;
+pub mod instruments;
+pub mod key_provider;
+pub mod levels;
 #[cfg(feature = "live")]
 pub mod live;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod options;
+pub mod orderflow;
+pub mod patterns;
+pub mod portfolio;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quality;
+pub mod quotes;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod series;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod sink;
+pub mod source;
+pub mod spill;
+pub mod statistics;
+pub mod status;
+#[cfg(all(feature = "historical", feature = "live"))]
+pub mod stitched;
+pub mod store;
+pub mod structure;
+pub mod synthetics;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timeconv;
+pub mod timeutil;
 
 /// Foreign Function Interface (FFI) for C/C# interoperability
 pub mod ffi;
@@ -43,13 +119,24 @@ pub use live::Client as LiveClient;
 pub use dbn;
 
 // Export the FFI functions to make them visible in the dynamic library
-pub use ffi::{pmz_calculate, pmz_free_result, CPmzResult, PmzErrorCode};
+pub use ffi::{
+    db_alerts_add_level, db_alerts_create, db_alerts_destroy, db_alerts_on_trade, db_alerts_poll,
+    db_alerts_remove_level, db_client_create, db_client_destroy, db_ffi_version,
+    db_last_error_message, db_pnl_create, db_pnl_destroy, db_pnl_get, db_pnl_on_fill,
+    db_pnl_on_trade, db_quotes_create, db_quotes_destroy, db_quotes_get, db_quotes_update,
+    db_request_begin, db_request_cancel, db_request_free, levels_calculate, levels_free_result,
+    pmz_calculate, pmz_calculate_async, pmz_calculate_cancellable, pmz_calculate_with_client,
+    pmz_calculate_with_diagnostics, pmz_free_result, pmz_validate_key, CAlertEvent, CLevelsResult,
+    CPmzResult, CPnL, CQuote, CRetriggerPolicy, DbAlertEngine, DbClient, DbPnlTracker,
+    DbQuoteBoard, DbRequest, PmzCallback, PmzDiagnosticCallback, PmzErrorCode,
+};
 
 use std::fmt::{self, Display, Write};
 
 #[cfg(feature = "historical")]
 use serde::{Deserialize, Deserializer};
 use tracing::error;
+use zeroize::Zeroize;
 
 /// A set of symbols for a particular [`SType`](dbn::enums::SType).
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -225,20 +312,49 @@ impl<'de> Deserialize<'de> for Symbols {
     }
 }
 
-/// A struct for holding an API key that implements Debug, but will only print the last
-/// five characters of the key.
-#[derive(Clone)]
+/// A struct for holding an API key that implements Debug and Display, but will only
+/// print the last five characters of the key. The key is zeroed out when dropped.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct ApiKey(String);
 
 pub(crate) const BUCKET_ID_LENGTH: usize = 5;
 
 impl fmt::Debug for ApiKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "\"…{}\"",
-            &self.0[self.0.len().saturating_sub(BUCKET_ID_LENGTH)..]
-        )
+        write!(f, "\"{self}\"")
+    }
+}
+
+impl Display for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `get` to avoid panicking if the key isn't on a char boundary
+        let suffix = self
+            .0
+            .get(self.0.len().saturating_sub(BUCKET_ID_LENGTH)..)
+            .unwrap_or_default();
+        write!(f, "…{suffix}")
+    }
+}
+
+impl Drop for ApiKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::str::FromStr for ApiKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s.to_owned())
+    }
+}
+
+impl TryFrom<String> for ApiKey {
+    type Error = Error;
+
+    fn try_from(key: String) -> Result<Self> {
+        Self::new(key)
     }
 }
 
@@ -248,27 +364,29 @@ impl ApiKey {
     /// # Errors
     /// This function returns an error if the key is invalid.
     pub fn new(key: String) -> crate::Result<ApiKey> {
-        if key == "$YOUR_API_KEY" {
+        // Wrap first so the key is zeroed out even if it's invalid
+        let key = ApiKey(key);
+        if key.0 == "$YOUR_API_KEY" {
             Err(Error::bad_arg(
                 "key",
                 "got placeholder API key '$YOUR_API_KEY'. Please pass a real API key",
             ))
-        } else if key.len() != API_KEY_LENGTH {
+        } else if key.0.len() != API_KEY_LENGTH {
             Err(Error::bad_arg(
                 "key",
                 format!(
                     "expected to be 32-characters long, got {} characters",
-                    key.len()
+                    key.0.len()
                 ),
             ))
-        } else if !key.is_ascii() {
-            error!("API key '{key}' contains non-ASCII characters");
+        } else if !key.0.is_ascii() {
+            error!("API key {key} contains non-ASCII characters");
             Err(Error::bad_arg(
                 "key",
                 "expected to be composed of only ASCII characters",
             ))
         } else {
-            Ok(ApiKey(key))
+            Ok(key)
         }
     }
 
@@ -283,6 +401,11 @@ impl ApiKey {
     pub fn as_str(&self) -> &str {
         self.0.as_str()
     }
+
+    /// Moves the key out, leaving an empty key to be dropped.
+    pub(crate) fn into_inner(mut self) -> String {
+        std::mem::take(&mut self.0)
+    }
 }
 
 #[cfg(test)]
@@ -330,4 +453,18 @@ mod tests {
     fn test_key_debug_doesnt_underflow() {
         assert_eq!(format!("{:?}", ApiKey("test".to_owned())), "\"…test\"");
     }
+
+    #[test]
+    fn test_key_display_redacts() {
+        let key: ApiKey = "32-character-with-lots-of-filler".parse().unwrap();
+        assert_eq!(key.to_string(), "…iller");
+        assert_eq!(key.as_str(), "32-character-with-lots-of-filler");
+    }
+
+    #[test]
+    fn test_invalid_key_error_doesnt_contain_key() {
+        let err = ApiKey::new("a-secret-key".to_owned()).unwrap_err();
+        assert!(!err.to_string().contains("a-secret-key"));
+        assert!(!format!("{err:?}").contains("a-secret-key"));
+    }
 }
@@ -1,7 +1,9 @@
 //! The Live client and related API types. Used for both real-time data and intraday historical.
 
+mod capture;
 mod client;
 pub mod protocol;
+mod watchdog;
 
 use std::{net::SocketAddr, sync::Arc};
 
@@ -11,9 +13,11 @@ use tokio::net::{lookup_host, ToSocketAddrs};
 use tracing::warn;
 use typed_builder::TypedBuilder;
 
-use crate::{ApiKey, Symbols};
+use crate::{key_provider::KeyProvider, ApiKey, Symbols};
 
+pub use capture::{Capture, Rotation};
 pub use client::Client;
+pub use watchdog::{LiveEvent, Watchdog};
 
 /// A subscription for real-time or intraday historical data.
 #[derive(Debug, Clone, TypedBuilder, PartialEq, Eq)]
@@ -58,6 +62,16 @@ pub struct ClientBuilder<AK, D> {
     send_ts_out: bool,
     upgrade_policy: VersionUpgradePolicy,
     heartbeat_interval: Option<Duration>,
+    connect_options: ConnectOptions,
+}
+
+/// How the client opens its connection to the gateway.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectOptions {
+    /// An HTTP proxy to tunnel the connection through with `CONNECT`.
+    pub proxy: Option<Arc<Vec<SocketAddr>>>,
+    /// The maximum time to wait for the connection to be established.
+    pub connect_timeout: Option<std::time::Duration>,
 }
 
 impl Default for ClientBuilder<Unset, Unset> {
@@ -69,6 +83,7 @@ impl Default for ClientBuilder<Unset, Unset> {
             send_ts_out: false,
             upgrade_policy: VersionUpgradePolicy::UpgradeToV2,
             heartbeat_interval: None,
+            connect_options: ConnectOptions::default(),
         }
     }
 }
@@ -120,6 +135,32 @@ impl<AK, D> ClientBuilder<AK, D> {
         self.addr = Some(Arc::new(addrs));
         Ok(self)
     }
+
+    /// Connects to the gateway through the HTTP proxy at `addr`, which must support
+    /// tunneling with `CONNECT`. Reconnects go through the same proxy.
+    ///
+    /// The live protocol isn't carried over TLS, so unlike the historical client
+    /// there are no certificate options.
+    ///
+    /// # Errors
+    /// This function returns an error when `addr` fails to resolve.
+    pub async fn proxy(mut self, addr: impl ToSocketAddrs) -> crate::Result<Self> {
+        const PARAM_NAME: &str = "proxy";
+        let addrs: Vec<_> = lookup_host(addr)
+            .await
+            .map_err(|e| crate::Error::bad_arg(PARAM_NAME, format!("{e}")))?
+            .collect();
+        self.connect_options.proxy = Some(Arc::new(addrs));
+        Ok(self)
+    }
+
+    /// Sets the maximum time to wait for the connection to the gateway, or to the
+    /// proxy and through it, to be established. This also applies to
+    /// [`reconnect()`](Client::reconnect). By default, connecting doesn't time out.
+    pub fn connect_timeout(mut self, connect_timeout: std::time::Duration) -> Self {
+        self.connect_options.connect_timeout = Some(connect_timeout);
+        self
+    }
 }
 
 impl ClientBuilder<Unset, Unset> {
@@ -135,14 +176,20 @@ impl<D> ClientBuilder<Unset, D> {
     /// # Errors
     /// This function returns an error when the API key is invalid.
     pub fn key(self, key: impl ToString) -> crate::Result<ClientBuilder<ApiKey, D>> {
-        Ok(ClientBuilder {
+        Ok(self.api_key(ApiKey::new(key.to_string())?))
+    }
+
+    /// Sets the API key from an already-validated [`ApiKey`].
+    pub fn api_key(self, key: ApiKey) -> ClientBuilder<ApiKey, D> {
+        ClientBuilder {
             addr: self.addr,
-            key: ApiKey::new(key.to_string())?,
+            key,
             dataset: self.dataset,
             send_ts_out: self.send_ts_out,
             upgrade_policy: self.upgrade_policy,
             heartbeat_interval: self.heartbeat_interval,
-        })
+            connect_options: self.connect_options,
+        }
     }
 
     /// Sets the API key reading it from the `DATABENTO_API_KEY` environment
@@ -155,6 +202,20 @@ impl<D> ClientBuilder<Unset, D> {
         let key = crate::key_from_env()?;
         self.key(key)
     }
+
+    /// Sets the API key from `provider`, e.g. a
+    /// [`FileKeyProvider`](crate::key_provider::FileKeyProvider) or
+    /// [`KeychainKeyProvider`](crate::key_provider::KeychainKeyProvider), instead of
+    /// the environment.
+    ///
+    /// # Errors
+    /// This function returns an error when `provider` fails to retrieve a valid API key.
+    pub fn key_from_provider(
+        self,
+        provider: impl KeyProvider,
+    ) -> crate::Result<ClientBuilder<ApiKey, D>> {
+        Ok(self.api_key(provider.key()?))
+    }
 }
 
 impl<AK> ClientBuilder<AK, Unset> {
@@ -167,6 +228,7 @@ impl<AK> ClientBuilder<AK, Unset> {
             send_ts_out: self.send_ts_out,
             upgrade_policy: self.upgrade_policy,
             heartbeat_interval: self.heartbeat_interval,
+            connect_options: self.connect_options,
         }
     }
 }
@@ -178,25 +240,19 @@ impl ClientBuilder<ApiKey, String> {
     /// This function returns an error when its unable
     /// to connect and authenticate with the Live gateway.
     pub async fn build(self) -> crate::Result<Client> {
-        if let Some(addr) = self.addr {
-            Client::connect_with_addr(
-                addr.as_slice(),
-                self.key.0,
-                self.dataset,
-                self.send_ts_out,
-                self.upgrade_policy,
-                self.heartbeat_interval,
-            )
-            .await
-        } else {
-            Client::connect(
-                self.key.0,
-                self.dataset,
-                self.send_ts_out,
-                self.upgrade_policy,
-                self.heartbeat_interval,
-            )
-            .await
-        }
+        let gateway = match self.addr {
+            Some(addr) => client::Gateway::Addrs(addr),
+            None => client::Gateway::Host(protocol::determine_gateway(&self.dataset)),
+        };
+        Client::connect_with_options(
+            gateway,
+            self.key,
+            self.dataset,
+            self.send_ts_out,
+            self.upgrade_policy,
+            self.heartbeat_interval,
+            self.connect_options,
+        )
+        .await
     }
 }
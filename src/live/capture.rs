@@ -0,0 +1,184 @@
+use std::{
+    fmt,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use dbn::{
+    encode::{DbnEncoder, DynWriter, EncodeRecord, EncodeRecordRef},
+    Compression, Metadata, Record, RecordRef,
+};
+use tracing::info;
+
+/// When [`Capture`] starts a new file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// Write each session to a single file.
+    #[default]
+    Never,
+    /// Start a new file once the current one reaches this many bytes of records.
+    Size(u64),
+    /// Start a new file once the current one has been open this long.
+    Interval(Duration),
+}
+
+/// Writes every record received by a [`LiveClient`](crate::LiveClient) to DBN files
+/// while they're still returned to the application, so a session can be replayed or
+/// inspected later exactly as it was received.
+///
+/// Set it with [`LiveClient::capture()`](crate::LiveClient::capture). Each file
+/// starts with the session's metadata, so every file can be read on its own. Files
+/// are Zstandard-compressed by default and named `{prefix}-{timestamp}-{index}.dbn.zst`,
+/// where `timestamp` is when the file was created in UTC and `index` counts the files
+/// written by the capture. A compressed file is only complete once the capture moves
+/// on to the next file or is [closed](Self::close).
+pub struct Capture {
+    dir: PathBuf,
+    prefix: String,
+    rotation: Rotation,
+    compression: Compression,
+    metadata: Option<Metadata>,
+    file: Option<CaptureFile>,
+    file_count: u32,
+}
+
+struct CaptureFile {
+    path: PathBuf,
+    encoder: DbnEncoder<DynWriter<'static, BufWriter<File>>>,
+    opened_at: Instant,
+    bytes: u64,
+}
+
+impl Capture {
+    /// Creates a capture writing files starting with `prefix` to `dir`, which is
+    /// created if it doesn't exist.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to create `dir`.
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        prefix: impl ToString,
+        rotation: Rotation,
+    ) -> crate::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            prefix: prefix.to_string(),
+            rotation,
+            compression: Compression::ZStd,
+            metadata: None,
+            file: None,
+            file_count: 0,
+        })
+    }
+
+    /// Sets the compression of new files. Uncompressed files are named
+    /// `{prefix}-{timestamp}-{index}.dbn`.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns the path of the file currently being written, if any.
+    pub fn current_path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|file| file.path.as_path())
+    }
+
+    /// Flushes buffered records to the current file.
+    ///
+    /// # Errors
+    /// This function returns an error when writing to the file fails.
+    pub fn flush(&mut self) -> crate::Result<()> {
+        if let Some(file) = self.file.as_mut() {
+            file.encoder.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and closes the current file, completing it. Later records are written
+    /// to a new file.
+    ///
+    /// # Errors
+    /// This function returns an error when writing to the file fails.
+    pub fn close(&mut self) -> crate::Result<()> {
+        self.close_file()
+    }
+
+    // Called when a session starts. Records of the new session go in a new file with
+    // its metadata.
+    pub(crate) fn start(&mut self, metadata: &Metadata) -> crate::Result<()> {
+        self.close_file()?;
+        self.metadata = Some(metadata.clone());
+        Ok(())
+    }
+
+    pub(crate) fn write(&mut self, rec: RecordRef) -> crate::Result<()> {
+        let rotate = self.file.as_ref().is_some_and(|file| match self.rotation {
+            Rotation::Never => false,
+            Rotation::Size(max_bytes) => file.bytes >= max_bytes,
+            Rotation::Interval(interval) => file.opened_at.elapsed() >= interval,
+        });
+        if rotate {
+            self.close_file()?;
+        }
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => self.open_file()?,
+        };
+        file.encoder.encode_record_ref(rec)?;
+        file.bytes += rec.record_size() as u64;
+        Ok(())
+    }
+
+    fn open_file(&mut self) -> crate::Result<&mut CaptureFile> {
+        let Some(metadata) = self.metadata.as_ref() else {
+            return Err(crate::Error::internal(
+                "can't capture records before the session has started",
+            ));
+        };
+        let path = self.dir.join(format!(
+            "{}-{}-{}.dbn{}",
+            self.prefix,
+            chrono::Utc::now().format("%Y%m%dT%H%M%SZ"),
+            self.file_count,
+            match self.compression {
+                Compression::ZStd => ".zst",
+                Compression::None => "",
+            }
+        ));
+        let writer = DynWriter::new(BufWriter::new(File::create(&path)?), self.compression)?;
+        let encoder = DbnEncoder::new(writer, metadata)?;
+        info!(path = %path.display(), "Capturing records");
+        self.file_count += 1;
+        Ok(self.file.insert(CaptureFile {
+            path,
+            encoder,
+            opened_at: Instant::now(),
+            bytes: 0,
+        }))
+    }
+
+    fn close_file(&mut self) -> crate::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.encoder.flush()?;
+            // Dropping the encoder writes the end of the Zstandard frame
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capture")
+            .field("dir", &self.dir)
+            .field("prefix", &self.prefix)
+            .field("rotation", &self.rotation)
+            .field("compression", &self.compression)
+            .field("current_path", &self.current_path())
+            .field("file_count", &self.file_count)
+            .finish_non_exhaustive()
+    }
+}
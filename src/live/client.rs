@@ -1,21 +1,21 @@
-use std::{fmt, net::SocketAddr};
+use std::{collections::HashMap, fmt, io, net::SocketAddr, sync::Arc};
 
 use dbn::{
     decode::dbn::{AsyncMetadataDecoder, AsyncRecordDecoder},
-    Metadata, RecordRef, VersionUpgradePolicy,
+    ErrorMsg, Metadata, Record, RecordRef, SymbolMappingMsg, SystemMsg, VersionUpgradePolicy,
 };
 use time::Duration;
 use tokio::{
-    io::{BufReader, ReadHalf, WriteHalf},
-    net::{TcpStream, ToSocketAddrs},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf},
+    net::{lookup_host, TcpStream, ToSocketAddrs},
 };
 use tracing::{info, info_span, instrument, warn, Span};
 
-use crate::ApiKey;
+use crate::{dispatch::RecordDispatcher, ApiKey, Symbols};
 
 use super::{
     protocol::{self, Protocol},
-    ClientBuilder, Subscription, Unset,
+    Capture, ClientBuilder, ConnectOptions, LiveEvent, Subscription, Unset, Watchdog,
 };
 
 /// The Live client. Used for subscribing to real-time and intraday historical market data.
@@ -30,19 +30,34 @@ pub struct Client {
     heartbeat_interval: Option<Duration>,
     protocol: Protocol<WriteHalf<TcpStream>>,
     peer_addr: SocketAddr,
+    gateway: Gateway,
+    connect_options: ConnectOptions,
     sub_counter: u32,
     subscriptions: Vec<Subscription>,
+    // The input symbol of each instrument from symbol mapping records
+    instrument_symbols: HashMap<u32, String>,
+    capture: Option<Capture>,
     decoder: Decoder,
     session_id: String,
     span: Span,
 }
 
+#[allow(clippy::large_enum_variant)]
 enum Decoder {
     Metadata(AsyncMetadataDecoder<BufReader<ReadHalf<TcpStream>>>),
     Record(AsyncRecordDecoder<BufReader<ReadHalf<TcpStream>>>),
     Empty,
 }
 
+/// The address of a Live gateway.
+#[derive(Debug, Clone)]
+pub(crate) enum Gateway {
+    /// A `host:port` address, resolved when connecting.
+    Host(String),
+    /// Resolved addresses, tried in order.
+    Addrs(Arc<Vec<SocketAddr>>),
+}
+
 impl Client {
     /// Creates a new client connected to a Live gateway.
     ///
@@ -58,13 +73,16 @@ impl Client {
         upgrade_policy: VersionUpgradePolicy,
         heartbeat_interval: Option<Duration>,
     ) -> crate::Result<Self> {
-        Self::connect_with_addr(
-            protocol::determine_gateway(&dataset),
+        let key = ApiKey::new(key)?;
+        let gateway = Gateway::Host(protocol::determine_gateway(&dataset));
+        Self::connect_with_options(
+            gateway,
             key,
             dataset,
             send_ts_out,
             upgrade_policy,
             heartbeat_interval,
+            ConnectOptions::default(),
         )
         .await
     }
@@ -84,8 +102,36 @@ impl Client {
         heartbeat_interval: Option<Duration>,
     ) -> crate::Result<Self> {
         let key = ApiKey::new(key)?;
-        let stream = TcpStream::connect(&addr).await?;
+        let addrs = lookup_host(addr).await?.collect();
+        Self::connect_with_options(
+            Gateway::Addrs(Arc::new(addrs)),
+            key,
+            dataset,
+            send_ts_out,
+            upgrade_policy,
+            heartbeat_interval,
+            ConnectOptions::default(),
+        )
+        .await
+    }
+
+    pub(crate) async fn connect_with_options(
+        gateway: Gateway,
+        key: ApiKey,
+        dataset: String,
+        send_ts_out: bool,
+        upgrade_policy: VersionUpgradePolicy,
+        heartbeat_interval: Option<Duration>,
+        connect_options: ConnectOptions,
+    ) -> crate::Result<Self> {
+        let stream = connect_options.connect(&gateway).await?;
         let peer_addr = stream.peer_addr()?;
+        // Without a proxy, reconnect to the same gateway instance
+        let gateway = if connect_options.proxy.is_none() {
+            Gateway::Addrs(Arc::new(vec![peer_addr]))
+        } else {
+            gateway
+        };
         let (recver, sender) = tokio::io::split(stream);
         let mut recver = BufReader::new(recver);
         let mut protocol = Protocol::new(sender);
@@ -107,11 +153,15 @@ impl Client {
             heartbeat_interval,
             protocol,
             peer_addr,
+            gateway,
+            connect_options,
             decoder: Decoder::Metadata(AsyncMetadataDecoder::new(recver)),
             session_id,
             span,
             sub_counter: 0,
             subscriptions: Vec::new(),
+            instrument_symbols: HashMap::new(),
+            capture: None,
         })
     }
 
@@ -152,7 +202,8 @@ impl Client {
         self.heartbeat_interval
     }
 
-    /// Returns an immutable reference to all subscriptions made with this instance.
+    /// Returns an immutable reference to all current subscriptions made with this
+    /// instance, excluding ones removed with [`unsubscribe()`](Self::unsubscribe).
     pub fn subscriptions(&self) -> &Vec<Subscription> {
         &self.subscriptions
     }
@@ -168,9 +219,99 @@ impl Client {
     /// This function returns an error if the shutdown of the TCP stream is unsuccessful, this usually
     /// means the stream is no longer usable.
     pub async fn close(&mut self) -> crate::Result<()> {
+        self.close_capture();
         self.protocol.shutdown().await
     }
 
+    fn close_capture(&mut self) {
+        if let Some(capture) = self.capture.as_mut() {
+            if let Err(err) = capture.close() {
+                warn!(?err, "Failed to close capture file");
+            }
+        }
+    }
+
+    /// Writes every record returned from [`next_record()`](Self::next_record) to the
+    /// DBN files of `capture`, replacing any previous capture. Must be called before
+    /// the session is [started](Self::start), so the files can include the session's
+    /// metadata. Captures continue in a new file after a
+    /// [`reconnect()`](Self::reconnect).
+    ///
+    /// If writing a record fails, the error is logged and capturing stops, but records
+    /// continue to be returned.
+    ///
+    /// # Errors
+    /// This function returns an error if the session has already been started.
+    pub fn capture(&mut self, capture: Capture) -> crate::Result<()> {
+        if matches!(self.decoder, Decoder::Record(_)) {
+            return Err(crate::Error::bad_arg(
+                "capture",
+                "must be set before the session is started",
+            ));
+        }
+        self.capture = Some(capture);
+        Ok(())
+    }
+
+    /// Stops capturing records, closing the current file and returning the capture set
+    /// with [`capture()`](Self::capture), if any.
+    ///
+    /// # Errors
+    /// This function returns an error if writing the captured records fails.
+    pub fn stop_capture(&mut self) -> crate::Result<Option<Capture>> {
+        if let Some(capture) = self.capture.as_mut() {
+            capture.close()?;
+        }
+        Ok(self.capture.take())
+    }
+
+    /// Closes the connection like [`close()`](Self::close), then routes the records the
+    /// gateway already sent to the handlers of `dispatcher` until the gateway closes
+    /// its side of the connection or `timeout` elapses, so no received data is lost
+    /// when shutting down, e.g. in a Ctrl+C handler. Returns the number of records
+    /// drained. Any [capture](Self::capture) is closed after the drained records are
+    /// written to it.
+    ///
+    /// Builders of in-progress bars such as
+    /// [`LiveCandleBuilder`](crate::examples::es_futures_pmz::LiveCandleBuilder) should
+    /// be flushed afterwards to emit the partial bar.
+    ///
+    /// # Errors
+    /// This function returns an error if the shutdown of the TCP stream is
+    /// unsuccessful or it's unable to decode a drained record.
+    pub async fn close_and_drain(
+        &mut self,
+        dispatcher: &mut RecordDispatcher<'_>,
+        timeout: std::time::Duration,
+    ) -> crate::Result<u64> {
+        // The capture is closed after draining so the drained records are captured too
+        if let Err(err) = self.protocol.shutdown().await {
+            self.close_capture();
+            return Err(err);
+        }
+        if !matches!(self.decoder, Decoder::Record(_)) {
+            self.close_capture();
+            return Ok(0);
+        }
+        let mut count = 0;
+        let drain = async {
+            while let Some(rec) = self.next_record().await? {
+                dispatcher.dispatch(&rec);
+                count += 1;
+            }
+            Ok::<_, crate::Error>(())
+        };
+        let res = match tokio::time::timeout(timeout, drain).await {
+            Ok(res) => res,
+            Err(_) => {
+                warn!(count, "Timed out draining records");
+                Ok(())
+            }
+        };
+        self.close_capture();
+        res.map(|_| count)
+    }
+
     /// Attempts to add a new subscription to the session. Note that
     /// an `Ok(())` result from this function does not necessarily indicate that
     /// the subscription succeeded, only that it was sent to the gateway.
@@ -198,6 +339,94 @@ impl Client {
         Ok(())
     }
 
+    /// Removes the subscriptions with the ID `sub_id`, returning them.
+    ///
+    /// The gateway doesn't support removing subscriptions from a session, so it
+    /// continues sending data for the removed symbols until the next
+    /// [`reconnect()`](Self::reconnect) and [`resubscribe()`](Self::resubscribe),
+    /// which only resubscribes to the remaining subscriptions. Until then, use
+    /// [`is_subscribed()`](Self::is_subscribed) to drop records of removed symbols.
+    ///
+    /// # Errors
+    /// This function returns an error if there's no subscription with the ID `sub_id`.
+    pub fn unsubscribe(&mut self, sub_id: u32) -> crate::Result<Vec<Subscription>> {
+        let (removed, kept) = std::mem::take(&mut self.subscriptions)
+            .into_iter()
+            .partition::<Vec<_>, _>(|sub| sub.id == Some(sub_id));
+        self.subscriptions = kept;
+        if removed.is_empty() {
+            return Err(crate::Error::bad_arg(
+                "sub_id",
+                format!("no subscription with ID {sub_id}"),
+            ));
+        }
+        info!(sub_id, "Removed subscription");
+        Ok(removed)
+    }
+
+    /// Replaces the symbols of the subscription with the ID `sub_id`, subscribing to
+    /// any symbols that weren't already part of it with the same schema and symbology
+    /// type. Like with [`unsubscribe()`](Self::unsubscribe), data for symbols that were
+    /// removed continues until the next reconnect, so use
+    /// [`is_subscribed()`](Self::is_subscribed) to drop it.
+    ///
+    /// # Errors
+    /// This function returns an error if there's no subscription with the ID `sub_id`
+    /// or it's unable to communicate with the gateway.
+    ///
+    /// # Cancel safety
+    /// This method is not cancellation safe for the same reasons as
+    /// [`subscribe()`](Self::subscribe).
+    #[instrument(parent = &self.span, skip_all)]
+    pub async fn modify(&mut self, sub_id: u32, symbols: impl Into<Symbols>) -> crate::Result<()> {
+        let symbols = symbols.into();
+        let Some(sub) = self
+            .subscriptions
+            .iter_mut()
+            .find(|sub| sub.id == Some(sub_id))
+        else {
+            return Err(crate::Error::bad_arg(
+                "sub_id",
+                format!("no subscription with ID {sub_id}"),
+            ));
+        };
+        if let Some(added) = added_symbols(&sub.symbols, &symbols) {
+            self.protocol
+                .subscribe(&Subscription {
+                    symbols: added,
+                    start: None,
+                    use_snapshot: false,
+                    ..sub.clone()
+                })
+                .await?;
+        }
+        sub.symbols = symbols;
+        Ok(())
+    }
+
+    /// Returns `true` if `rec` is for a symbol in one of the current subscriptions,
+    /// or isn't for an instrument, like system and error messages. Records are matched
+    /// to symbols by the symbol mapping records returned from
+    /// [`next_record()`](Self::next_record), regardless of schema.
+    ///
+    /// Only needed to drop records after [`unsubscribe()`](Self::unsubscribe) or
+    /// [`modify()`](Self::modify), since the gateway only sends data for subscribed
+    /// symbols. Because records returned from `next_record()` borrow the client, copy
+    /// them, e.g. with [`RecordRef::as_enum()`], before calling this method.
+    pub fn is_subscribed(&self, rec: &RecordRef) -> bool {
+        if rec.has::<SymbolMappingMsg>() || rec.has::<SystemMsg>() || rec.has::<ErrorMsg>() {
+            return true;
+        }
+        let instrument_id = rec.header().instrument_id;
+        let symbol = self.instrument_symbols.get(&instrument_id);
+        self.subscriptions.iter().any(|sub| match &sub.symbols {
+            Symbols::All => true,
+            Symbols::Ids(ids) => ids.contains(&instrument_id),
+            // Can't tell without a symbol mapping
+            Symbols::Symbols(symbols) => symbol.is_none_or(|symbol| symbols.contains(symbol)),
+        })
+    }
+
     /// Instructs the gateway to start sending data, starting the session. Except
     /// in cases of a reconnect, this method should only be called once on a given
     /// instance.
@@ -237,6 +466,9 @@ impl Client {
         )?);
         // Should match `send_ts_out` but set again here for safety
         metadata.upgrade(self.upgrade_policy);
+        if let Some(capture) = self.capture.as_mut() {
+            capture.start(&metadata)?;
+        }
         Ok(metadata)
     }
 
@@ -255,14 +487,86 @@ impl Client {
     /// This method is cancel safe. It can be used within a [`tokio::select!`] statement
     /// without the potential for corrupting the input stream.
     #[instrument(parent = &self.span, level = "debug", skip_all)]
-    pub async fn next_record(&mut self) -> crate::Result<Option<RecordRef>> {
+    pub async fn next_record(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
         let Decoder::Record(decoder) = &mut self.decoder else {
             return Err(crate::Error::BadArgument {
                 param_name: "self".to_owned(),
                 desc: "Can't call LiveClient::next_record before starting session".to_owned(),
             });
         };
-        Ok(decoder.decode_ref().await?)
+        let rec = decoder.decode_ref().await?;
+        #[cfg(feature = "metrics")]
+        if let Some(rec) = rec.as_ref() {
+            crate::metrics::Registry::global().observe_live_record(rec);
+        }
+        if let (Some(capture), Some(rec)) = (self.capture.as_mut(), rec.as_ref()) {
+            if let Err(err) = capture.write(*rec) {
+                warn!(?err, "Failed to capture record. Stopping capture");
+                self.capture = None;
+            }
+        }
+        if let Some(mapping) = rec.as_ref().and_then(|rec| rec.get::<SymbolMappingMsg>()) {
+            if let Ok(symbol) = mapping.stype_in_symbol() {
+                self.instrument_symbols
+                    .insert(mapping.hd.instrument_id, symbol.to_owned());
+            }
+        }
+        Ok(rec)
+    }
+
+    /// Fetches the next record like [`next_record()`](Self::next_record), but returns
+    /// [`LiveEvent::Stale`] if no record is received within the timeout of `watchdog`
+    /// while it's [active](Watchdog::is_active), so a silently dropped connection can
+    /// be detected, e.g. to [`reconnect()`](Self::reconnect). The same `watchdog`
+    /// should be passed to every call to track the time since the last record.
+    ///
+    /// # Errors
+    /// This function returns an error when it's unable to decode the next record
+    /// or it's unable to read from the TCP stream. It will also return an error if the
+    /// session hasn't been started.
+    ///
+    /// # Cancel safety
+    /// This method is cancel safe. It can be used within a [`tokio::select!`] statement
+    /// without the potential for corrupting the input stream.
+    pub async fn next_event(
+        &mut self,
+        watchdog: &mut Watchdog,
+    ) -> crate::Result<Option<LiveEvent<'_>>> {
+        let next_record = self.next_record();
+        tokio::pin!(next_record);
+        loop {
+            tokio::select! {
+                rec = &mut next_record => {
+                    watchdog.on_record();
+                    return Ok(rec?.map(LiveEvent::Record));
+                }
+                _ = tokio::time::sleep_until(watchdog.deadline()) => {
+                    let elapsed = watchdog.on_timeout();
+                    if watchdog.is_active(chrono::Utc::now()) {
+                        warn!(?elapsed, "No records received within watchdog timeout");
+                        return Ok(Some(LiveEvent::Stale { elapsed }));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fetches records with [`next_record()`](Self::next_record) and routes each one to
+    /// the handlers of `dispatcher` until the gateway closes the connection.
+    ///
+    /// # Errors
+    /// This function returns an error when it's unable to decode the next record
+    /// or it's unable to read from the TCP stream. It will also return an error if the
+    /// session hasn't been started.
+    ///
+    /// # Cancel safety
+    /// This method is cancel safe. If it's cancelled, no record is skipped: every record
+    /// fetched has already been dispatched.
+    pub async fn dispatch(&mut self, dispatcher: &mut RecordDispatcher<'_>) -> crate::Result<()> {
+        while let Some(rec) = self.next_record().await? {
+            dispatcher.dispatch(&rec);
+        }
+        Ok(())
     }
 
     /// Closes the current connection, then reopens the connection and authenticates
@@ -284,11 +588,12 @@ impl Client {
                 "Failed to close connection before reconnect. Proceeding"
             );
         }
-        let stream = TcpStream::connect(self.peer_addr).await?;
+        let stream = self.connect_options.connect(&self.gateway).await?;
         let (recver, sender) = tokio::io::split(stream);
         let mut recver = BufReader::new(recver);
         self.protocol = Protocol::new(sender);
         self.sub_counter = 0;
+        self.instrument_symbols.clear();
         self.session_id = self
             .protocol
             .authenticate(
@@ -329,6 +634,7 @@ impl fmt::Debug for Client {
             .field("upgrade_policy", &self.upgrade_policy)
             .field("heartbeat_interval", &self.heartbeat_interval)
             .field("peer_addr", &self.peer_addr)
+            .field("proxy", &self.connect_options.proxy)
             .field("sub_counter", &self.sub_counter)
             .field("subscriptions", &self.subscriptions)
             .field("session_id", &self.session_id)
@@ -336,6 +642,91 @@ impl fmt::Debug for Client {
     }
 }
 
+// Returns the symbols in `new` that aren't in `old`, or `None` if there are none
+fn added_symbols(old: &Symbols, new: &Symbols) -> Option<Symbols> {
+    let added = match (old, new) {
+        (Symbols::All, _) => return None,
+        (Symbols::Ids(old), Symbols::Ids(new)) => {
+            Symbols::Ids(new.iter().filter(|id| !old.contains(id)).copied().collect())
+        }
+        (Symbols::Symbols(old), Symbols::Symbols(new)) => Symbols::Symbols(
+            new.iter()
+                .filter(|symbol| !old.contains(symbol))
+                .cloned()
+                .collect(),
+        ),
+        (_, new) => new.clone(),
+    };
+    match &added {
+        Symbols::Ids(ids) if ids.is_empty() => None,
+        Symbols::Symbols(symbols) if symbols.is_empty() => None,
+        _ => Some(added),
+    }
+}
+
+impl ConnectOptions {
+    // Opens a connection to `gateway`, tunneled through the proxy if there is one
+    async fn connect(&self, gateway: &Gateway) -> crate::Result<TcpStream> {
+        let connect = async {
+            match (&self.proxy, gateway) {
+                (None, Gateway::Host(host)) => TcpStream::connect(host.as_str()).await,
+                (None, Gateway::Addrs(addrs)) => TcpStream::connect(addrs.as_slice()).await,
+                (Some(proxy), gateway) => {
+                    let target = match gateway {
+                        Gateway::Host(host) => host.clone(),
+                        Gateway::Addrs(addrs) => addrs
+                            .first()
+                            .ok_or_else(|| {
+                                io::Error::new(io::ErrorKind::InvalidInput, "no gateway address")
+                            })?
+                            .to_string(),
+                    };
+                    let mut stream = TcpStream::connect(proxy.as_slice()).await?;
+                    tunnel(&mut stream, &target).await?;
+                    Ok(stream)
+                }
+            }
+        };
+        let stream = match self.connect_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, connect).await.map_err(|_| {
+                io::Error::new(io::ErrorKind::TimedOut, "timed out connecting to gateway")
+            })??,
+            None => connect.await?,
+        };
+        Ok(stream)
+    }
+}
+
+// Asks the HTTP proxy at the other end of `stream` to tunnel it to `target`
+async fn tunnel<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, target: &str) -> io::Result<()> {
+    const MAX_RESPONSE_LEN: usize = 8 * 1024;
+
+    stream
+        .write_all(format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n\r\n").as_bytes())
+        .await?;
+    // Read one byte at a time so nothing sent through the tunnel is consumed
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_RESPONSE_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "proxy response headers are too long",
+            ));
+        }
+        response.push(stream.read_u8().await?);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) == Some("200") {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            format!("proxy refused to connect to {target}: {status_line}"),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{ffi::c_char, fmt};
@@ -345,7 +736,7 @@ mod tests {
         enums::rtype,
         publishers::Dataset,
         record::{HasRType, OhlcvMsg, RecordHeader, TradeMsg, WithTsOut},
-        FlagSet, Mbp10Msg, MetadataBuilder, Record, SType, Schema,
+        FlagSet, Mbp10Msg, MetadataBuilder, Record, RecordEnum, SType, Schema,
     };
     use time::{Duration, OffsetDateTime};
     use tokio::{
@@ -359,6 +750,12 @@ mod tests {
     use tracing::level_filters::LevelFilter;
 
     use super::*;
+    use crate::live::Rotation;
+
+    // Records returned from `next_record()` borrow the client
+    fn owned(rec: RecordRef) -> RecordEnum {
+        rec.as_enum().unwrap().to_owned()
+    }
 
     struct MockLsgServer {
         dataset: String,
@@ -733,6 +1130,62 @@ mod tests {
         fixture.stop().await;
     }
 
+    #[tokio::test]
+    async fn test_dispatch() {
+        let (mut fixture, mut client) = setup(Dataset::GlbxMdp3, false, None).await;
+        fixture.start();
+        client.start().await.unwrap();
+        for instrument_id in [1, 2] {
+            fixture.send_record(TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, 0),
+                ..Default::default()
+            });
+        }
+        fixture.send_record(OhlcvMsg {
+            hd: RecordHeader::new::<OhlcvMsg>(rtype::OHLCV_1M, 1, 3, 0),
+            open: 1,
+            high: 2,
+            low: 3,
+            close: 4,
+            volume: 5,
+        });
+        fixture.disconnect();
+
+        let mut trades = Vec::new();
+        let mut bars = Vec::new();
+        let mut dispatcher = RecordDispatcher::new()
+            .on::<TradeMsg>(|trade| trades.push(trade.hd.instrument_id))
+            .on::<OhlcvMsg>(|bar| bars.push(bar.volume));
+        client.dispatch(&mut dispatcher).await.unwrap();
+        drop(dispatcher);
+        assert_eq!(trades, [1, 2]);
+        assert_eq!(bars, [5]);
+        fixture.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_next_event_stale() {
+        let (mut fixture, mut client) = setup(Dataset::GlbxMdp3, false, None).await;
+        fixture.start();
+        client.start().await.unwrap();
+        let mut watchdog = Watchdog::new(std::time::Duration::from_millis(50));
+        let event = client.next_event(&mut watchdog).await.unwrap().unwrap();
+        assert!(
+            matches!(event, LiveEvent::Stale { elapsed } if elapsed >= watchdog.timeout()),
+            "{event:?}"
+        );
+        fixture.send_record(TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 1, 0),
+            ..Default::default()
+        });
+        let event = client.next_event(&mut watchdog).await.unwrap().unwrap();
+        assert!(
+            matches!(event, LiveEvent::Record(rec) if rec.has::<TradeMsg>()),
+            "{event:?}"
+        );
+        fixture.stop().await;
+    }
+
     #[tokio::test]
     async fn test_close() {
         let (mut fixture, mut client) =
@@ -743,6 +1196,128 @@ mod tests {
         fixture.stop().await;
     }
 
+    #[tokio::test]
+    async fn test_close_and_drain() {
+        let (mut fixture, mut client) = setup(Dataset::GlbxMdp3, false, None).await;
+        fixture.start();
+        client.start().await.unwrap();
+        for instrument_id in [1, 2, 3] {
+            fixture.send_record(TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, 0),
+                ..Default::default()
+            });
+        }
+        fixture.disconnect();
+        let rec = client.next_record().await.unwrap().unwrap();
+        assert_eq!(rec.header().instrument_id, 1);
+
+        let mut drained = Vec::new();
+        let mut dispatcher =
+            RecordDispatcher::new().on::<TradeMsg>(|trade| drained.push(trade.hd.instrument_id));
+        let count = client
+            .close_and_drain(&mut dispatcher, std::time::Duration::from_secs(5))
+            .await
+            .unwrap();
+        drop(dispatcher);
+        assert_eq!(count, 2);
+        assert_eq!(drained, [2, 3]);
+        fixture.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_close_and_drain_with_capture() {
+        use dbn::decode::{DbnDecoder, DecodeRecord};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let (mut fixture, mut client) = setup(Dataset::GlbxMdp3, false, None).await;
+        client
+            .capture(Capture::new(dir.path(), "glbx", Default::default()).unwrap())
+            .unwrap();
+        fixture.start();
+        client.start().await.unwrap();
+        for instrument_id in [1, 2, 3] {
+            fixture.send_record(TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, 0),
+                ..Default::default()
+            });
+        }
+        fixture.disconnect();
+        client.next_record().await.unwrap().unwrap();
+        let count = client
+            .close_and_drain(
+                &mut RecordDispatcher::new(),
+                std::time::Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        fixture.stop().await;
+
+        // The drained records are captured in the same file, which is finished before
+        // the client is dropped
+        let paths: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(paths.len(), 1);
+        let trades: Vec<TradeMsg> = DbnDecoder::from_zstd_file(&paths[0])
+            .unwrap()
+            .decode_records()
+            .unwrap();
+        assert_eq!(
+            trades
+                .iter()
+                .map(|t| t.hd.instrument_id)
+                .collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn test_capture() {
+        use dbn::decode::{DbnDecoder, DbnMetadata, DecodeRecord};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let (mut fixture, mut client) = setup(Dataset::GlbxMdp3, false, None).await;
+        // Each file holds one trade
+        let trade_size = std::mem::size_of::<TradeMsg>() as u64;
+        client
+            .capture(Capture::new(dir.path(), "glbx", Rotation::Size(trade_size)).unwrap())
+            .unwrap();
+        fixture.start();
+        client.start().await.unwrap();
+        assert!(client
+            .capture(Capture::new(dir.path(), "other", Default::default()).unwrap())
+            .is_err());
+        for instrument_id in [1, 2] {
+            fixture.send_record(TradeMsg {
+                hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, 0),
+                ..Default::default()
+            });
+            let rec = client.next_record().await.unwrap().unwrap();
+            assert_eq!(rec.header().instrument_id, instrument_id);
+        }
+        let capture = client.stop_capture().unwrap().unwrap();
+        assert!(capture.current_path().is_none());
+        fixture.stop().await;
+
+        let mut paths: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        assert_eq!(paths.len(), 2);
+        for (path, instrument_id) in paths.iter().zip([1, 2]) {
+            assert!(path.to_str().unwrap().ends_with(".dbn.zst"));
+            let decoder = DbnDecoder::from_zstd_file(path).unwrap();
+            assert_eq!(decoder.metadata().dataset, Dataset::GlbxMdp3.as_str());
+            let trades: Vec<TradeMsg> = decoder.decode_records().unwrap();
+            assert_eq!(trades.len(), 1);
+            assert_eq!(trades[0].hd.instrument_id, instrument_id);
+        }
+    }
+
     #[tokio::test]
     async fn test_error_without_success() {
         const DATASET: Dataset = Dataset::OpraPillar;
@@ -820,6 +1395,65 @@ mod tests {
         fixture.stop().await;
     }
 
+    #[tokio::test]
+    async fn test_modify_and_unsubscribe() {
+        let (mut fixture, mut client) = setup(Dataset::XnasItch, false, None).await;
+        let sub_base = Subscription::builder()
+            .schema(Schema::Trades)
+            .stype_in(SType::RawSymbol);
+        let subscription = sub_base.clone().symbols(vec!["MSFT", "TSLA"]).id(1).build();
+        fixture.expect_subscribe(subscription.clone());
+        client.subscribe(subscription).await.unwrap();
+        let subscription = sub_base.clone().symbols("QQQ").id(2).build();
+        fixture.expect_subscribe(subscription.clone());
+        client.subscribe(subscription).await.unwrap();
+        // Only the new symbol is sent
+        fixture.expect_subscribe(sub_base.clone().symbols("NVDA").build());
+        client.modify(1, vec!["MSFT", "NVDA"]).await.unwrap();
+        assert_eq!(
+            client.subscriptions()[0].symbols,
+            Symbols::from(vec!["MSFT", "NVDA"])
+        );
+        assert!(client.modify(3, "AAPL").await.is_err());
+
+        fixture.start();
+        client.start().await.unwrap();
+        for (instrument_id, symbol) in [(1, "MSFT"), (2, "TSLA"), (3, "QQQ")] {
+            fixture.send_record(
+                SymbolMappingMsg::new(
+                    instrument_id,
+                    0,
+                    SType::RawSymbol,
+                    symbol,
+                    SType::InstrumentId,
+                    &instrument_id.to_string(),
+                    0,
+                    0,
+                )
+                .unwrap(),
+            );
+            let rec = owned(client.next_record().await.unwrap().unwrap());
+            assert!(client.is_subscribed(&RecordRef::from(&rec)));
+        }
+        let trade = |instrument_id| TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, 0),
+            ..Default::default()
+        };
+        let removed = client.unsubscribe(2).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(client.unsubscribe(2).is_err());
+        for (instrument_id, expected) in [(1, true), (2, false), (3, false)] {
+            fixture.send_record(trade(instrument_id));
+            let rec = owned(client.next_record().await.unwrap().unwrap());
+            assert_eq!(
+                client.is_subscribed(&RecordRef::from(&rec)),
+                expected,
+                "{instrument_id}"
+            );
+        }
+        fixture.stop().await;
+    }
+
     #[tokio::test]
     async fn test_reconnect() {
         let (mut fixture, mut client) = setup(Dataset::EqusMini, true, None).await;
@@ -886,4 +1520,46 @@ mod tests {
 
         fixture.stop().await;
     }
+
+    #[tokio::test]
+    async fn test_tunnel() {
+        const TARGET: &str = "glbx-mdp3.lsg.databento.com:13000";
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+        let proxy_task = tokio::spawn(async move {
+            let mut proxy = BufReader::new(&mut proxy);
+            let mut request_line = String::new();
+            proxy.read_line(&mut request_line).await.unwrap();
+            assert_eq!(request_line, format!("CONNECT {TARGET} HTTP/1.1\r\n"));
+            let mut line = String::new();
+            while line != "\r\n" {
+                line.clear();
+                proxy.read_line(&mut line).await.unwrap();
+            }
+            proxy
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\nlsg_version=0.1\n")
+                .await
+                .unwrap();
+        });
+        tunnel(&mut client, TARGET).await.unwrap();
+        proxy_task.await.unwrap();
+        // Data sent through the tunnel isn't consumed
+        let mut greeting = String::new();
+        BufReader::new(client)
+            .read_line(&mut greeting)
+            .await
+            .unwrap();
+        assert_eq!(greeting, "lsg_version=0.1\n");
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_refused() {
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+        proxy
+            .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        let err = tunnel(&mut client, "localhost:13000").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        assert!(err.to_string().contains("403 Forbidden"));
+    }
 }
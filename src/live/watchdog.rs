@@ -0,0 +1,164 @@
+use std::{fmt, time::Duration};
+
+use chrono::{DateTime, Utc};
+use dbn::RecordRef;
+use tokio::time::Instant;
+
+use crate::calendar::TradingCalendar;
+
+/// Detects a live session that has silently stopped receiving data.
+///
+/// Pass it to [`LiveClient::next_event()`](crate::LiveClient::next_event), which
+/// returns [`LiveEvent::Stale`] instead of waiting indefinitely when no record arrives
+/// within the timeout. Heartbeats count as records, so the timeout should be longer
+/// than the session's [heartbeat interval](super::ClientBuilder::heartbeat_interval),
+/// which defaults to 30 seconds.
+pub struct Watchdog {
+    timeout: Duration,
+    calendar: Option<Box<dyn TradingCalendar + Send + Sync>>,
+    last_record: Option<Instant>,
+    deadline: Option<Instant>,
+}
+
+/// The result of waiting for the next record with a [`Watchdog`].
+#[derive(Debug)]
+pub enum LiveEvent<'a> {
+    /// A record was received.
+    Record(RecordRef<'a>),
+    /// No records were received for at least the watchdog's timeout. Returned again
+    /// after every further timeout until a record is received.
+    Stale {
+        /// How long it's been since the last record, or since the watchdog was first
+        /// used if no record has been received.
+        elapsed: Duration,
+    },
+}
+
+impl Watchdog {
+    /// Creates a watchdog that reports a stale session after `timeout` without any
+    /// records, at any time of day.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            calendar: None,
+            last_record: None,
+            deadline: None,
+        }
+    }
+
+    /// Only reports a stale session during the sessions of `calendar`, since
+    /// outside market hours long gaps between records are expected.
+    pub fn during_sessions(
+        mut self,
+        calendar: impl TradingCalendar + Send + Sync + 'static,
+    ) -> Self {
+        self.calendar = Some(Box::new(calendar));
+        self
+    }
+
+    /// Returns the timeout without records after which the session is stale.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns `true` if the watchdog reports stale sessions at `now`, i.e. `now` is
+    /// within a session of its calendar or it doesn't have one. Overnight sessions
+    /// count from their open on the evening before their trading date.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        let Some(calendar) = self.calendar.as_ref() else {
+            return true;
+        };
+        let tz = calendar.timezone();
+        let date = now.with_timezone(&tz).date_naive();
+        [Some(date), date.succ_opt()]
+            .into_iter()
+            .flatten()
+            .filter_map(|date| calendar.session(date)?.open_close(tz).ok())
+            .any(|(open, close)| open <= now && now < close)
+    }
+
+    pub(crate) fn deadline(&mut self) -> Instant {
+        let now = Instant::now();
+        self.last_record.get_or_insert(now);
+        *self.deadline.get_or_insert(now + self.timeout)
+    }
+
+    pub(crate) fn on_record(&mut self) {
+        let now = Instant::now();
+        self.last_record = Some(now);
+        self.deadline = Some(now + self.timeout);
+    }
+
+    // Returns the time since the last record and restarts the timeout
+    pub(crate) fn on_timeout(&mut self) -> Duration {
+        let now = Instant::now();
+        self.deadline = Some(now + self.timeout);
+        now.duration_since(*self.last_record.get_or_insert(now))
+    }
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("timeout", &self.timeout)
+            .field("during_sessions", &self.calendar.is_some())
+            .field("last_record", &self.last_record)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeZone};
+    use chrono_tz::America::New_York;
+
+    use super::*;
+    use crate::calendar::{FixedHoursCalendar, Session};
+
+    #[test]
+    fn test_is_active() {
+        let watchdog = Watchdog::new(Duration::from_secs(60));
+        let monday_night = New_York.with_ymd_and_hms(2025, 4, 21, 20, 0, 0).unwrap();
+        assert!(watchdog.is_active(monday_night.to_utc()));
+
+        let watchdog = watchdog.during_sessions(FixedHoursCalendar::new(
+            NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            New_York,
+        ));
+        assert!(!watchdog.is_active(monday_night.to_utc()));
+        for (day, hour, minute, expected) in [
+            (21, 9, 29, false),
+            (21, 9, 30, true),
+            (21, 15, 59, true),
+            (21, 16, 0, false),
+            // Saturday
+            (26, 12, 0, false),
+        ] {
+            let now = New_York
+                .with_ymd_and_hms(2025, 4, day, hour, minute, 0)
+                .unwrap();
+            assert_eq!(watchdog.is_active(now.to_utc()), expected, "{now}");
+        }
+    }
+    #[test]
+    fn test_is_active_overnight_session() {
+        let watchdog = Watchdog::new(Duration::from_secs(60)).during_sessions(Session::CME_GLOBEX);
+        for (day, hour, expected) in [
+            // Sunday evening open of Monday's session
+            (20, 17, false),
+            (20, 18, true),
+            (22, 12, true),
+            // Daily maintenance break
+            (22, 17, false),
+            (22, 18, true),
+            // Friday close until Sunday evening
+            (25, 16, true),
+            (25, 17, false),
+            (26, 12, false),
+        ] {
+            let now = New_York.with_ymd_and_hms(2025, 4, day, hour, 0, 0).unwrap();
+            assert_eq!(watchdog.is_active(now.to_utc()), expected, "{now}");
+        }
+    }
+}
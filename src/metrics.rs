@@ -0,0 +1,322 @@
+//! Latency and throughput metrics.
+//!
+//! A [`Registry`] collects the durations of historical API requests, the number of
+//! records decoded from the live feed, and the latency of the live feed,
+//! measured as the difference between when Databento received a record (`ts_recv`) and
+//! its matching engine timestamp (`ts_event`). The historical client records its
+//! requests and the live client its records into the
+//! [global registry](Registry::global), and [`Registry::render()`] formats the metrics in
+//! the Prometheus text exposition format, which the [server](crate::server) serves on
+//! `GET /metrics`. The decoded records are exposed as a counter, so their rate can be
+//! computed with Prometheus's `rate()`.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use dbn::{Record, RecordRef, UNDEF_TIMESTAMP};
+
+/// The upper bounds in seconds of the request duration buckets.
+pub const REQUEST_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+/// The upper bounds in seconds of the live latency buckets.
+pub const LATENCY_BUCKETS: &[f64] = &[
+    0.000_1, 0.000_25, 0.000_5, 0.001, 0.002_5, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+/// A histogram of durations with fixed buckets.
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    // Non-cumulative counts of each bucket, with a final one for `+Inf`
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    /// Creates an empty histogram with buckets for the ascending upper bounds in
+    /// seconds `bounds`.
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Records `duration`.
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        let idx = self.bounds.partition_point(|&bound| bound < secs);
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(
+            u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Returns the number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the sum of the recorded durations.
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Returns the mean of the recorded durations, or `None` if there are none.
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count() {
+            0 => None,
+            count => Some(self.sum() / u32::try_from(count).unwrap_or(u32::MAX)),
+        }
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, bucket) in self
+            .bounds
+            .iter()
+            .map(ToString::to_string)
+            .chain(["+Inf".to_owned()])
+            .zip(self.buckets.iter())
+        {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let sep = if labels.is_empty() { "" } else { "," };
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {cumulative}"
+            );
+        }
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let _ = writeln!(out, "{name}_sum{labels} {}", self.sum().as_secs_f64());
+        let _ = writeln!(out, "{name}_count{labels} {}", self.count());
+    }
+}
+
+/// A collection of client metrics.
+#[derive(Debug)]
+pub struct Registry {
+    request_durations: Mutex<BTreeMap<String, Histogram>>,
+    request_errors: Mutex<BTreeMap<String, u64>>,
+    records_decoded: AtomicU64,
+    live_latency: Histogram,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            request_durations: Mutex::default(),
+            request_errors: Mutex::default(),
+            records_decoded: AtomicU64::new(0),
+            live_latency: Histogram::new(LATENCY_BUCKETS),
+        }
+    }
+}
+
+impl Registry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the registry the clients record into.
+    pub fn global() -> &'static Self {
+        static GLOBAL: OnceLock<Registry> = OnceLock::new();
+        GLOBAL.get_or_init(Self::new)
+    }
+
+    /// Records a request to `endpoint`, e.g. `timeseries.get_range`, that took
+    /// `duration` including any retries. `success` is `false` if the request failed
+    /// or returned an error status.
+    pub fn observe_request(&self, endpoint: &str, duration: Duration, success: bool) {
+        let mut durations = self
+            .request_durations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        durations
+            .entry(endpoint.to_owned())
+            .or_insert_with(|| Histogram::new(REQUEST_BUCKETS))
+            .observe(duration);
+        drop(durations);
+        if !success {
+            *self
+                .request_errors
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .entry(endpoint.to_owned())
+                .or_default() += 1;
+        }
+    }
+
+    /// Returns the number of requests to `endpoint` and their total duration.
+    pub fn request_stats(&self, endpoint: &str) -> Option<(u64, Duration)> {
+        self.request_durations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(endpoint)
+            .map(|histogram| (histogram.count(), histogram.sum()))
+    }
+
+    /// Adds `count` to the number of decoded records. Only the live client records into
+    /// the global registry; callers decoding historical data can add their own counts.
+    pub fn records_decoded(&self, count: u64) {
+        self.records_decoded.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Returns the total number of decoded records.
+    pub fn total_records_decoded(&self) -> u64 {
+        self.records_decoded.load(Ordering::Relaxed)
+    }
+
+    /// Records the latency of a live record.
+    pub fn observe_live_latency(&self, latency: Duration) {
+        self.live_latency.observe(latency);
+    }
+
+    /// Returns the histogram of live record latencies.
+    pub fn live_latency(&self) -> &Histogram {
+        &self.live_latency
+    }
+
+    /// Counts `rec` as decoded from the live feed and records its latency if it has
+    /// distinct receive and event timestamps.
+    pub fn observe_live_record(&self, rec: &RecordRef) {
+        self.records_decoded(1);
+        let ts_event = rec.header().ts_event;
+        let ts_recv = rec.raw_index_ts();
+        if ts_event != UNDEF_TIMESTAMP && ts_recv != UNDEF_TIMESTAMP && ts_recv > ts_event {
+            self.observe_live_latency(Duration::from_nanos(ts_recv - ts_event));
+        }
+    }
+
+    /// Formats the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "# HELP databento_request_duration_seconds Duration of historical API requests including retries.\n\
+             # TYPE databento_request_duration_seconds histogram\n",
+        );
+        for (endpoint, histogram) in self
+            .request_durations
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+        {
+            histogram.render(
+                &mut out,
+                "databento_request_duration_seconds",
+                &format!("endpoint=\"{endpoint}\""),
+            );
+        }
+        out.push_str(
+            "# HELP databento_request_errors_total Historical API requests that failed.\n\
+             # TYPE databento_request_errors_total counter\n",
+        );
+        for (endpoint, errors) in self
+            .request_errors
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "databento_request_errors_total{{endpoint=\"{endpoint}\"}} {errors}"
+            );
+        }
+        let _ = write!(
+            out,
+            "# HELP databento_records_decoded_total Records decoded.\n\
+             # TYPE databento_records_decoded_total counter\n\
+             databento_records_decoded_total {}\n",
+            self.total_records_decoded(),
+        );
+        out.push_str(
+            "# HELP databento_live_latency_seconds Difference between the receive and event timestamps of live records.\n\
+             # TYPE databento_live_latency_seconds histogram\n",
+        );
+        self.live_latency
+            .render(&mut out, "databento_live_latency_seconds", "");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{rtype, RecordHeader, TradeMsg};
+
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram::new(&[0.1, 1.0]);
+        assert!(histogram.mean().is_none());
+        histogram.observe(Duration::from_millis(50));
+        histogram.observe(Duration::from_millis(100));
+        histogram.observe(Duration::from_secs(2));
+        assert_eq!(histogram.count(), 3);
+        assert_eq!(histogram.sum(), Duration::from_millis(2150));
+        let mut out = String::new();
+        histogram.render(&mut out, "test", "");
+        assert_eq!(
+            out,
+            "test_bucket{le=\"0.1\"} 2\n\
+             test_bucket{le=\"1\"} 2\n\
+             test_bucket{le=\"+Inf\"} 3\n\
+             test_sum 2.15\n\
+             test_count 3\n"
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = Registry::new();
+        registry.observe_request("timeseries.get_range", Duration::from_millis(200), true);
+        registry.observe_request("timeseries.get_range", Duration::from_millis(300), false);
+        assert_eq!(
+            registry.request_stats("timeseries.get_range"),
+            Some((2, Duration::from_millis(500)))
+        );
+        assert!(registry.request_stats("metadata.get_cost").is_none());
+
+        let trade = TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 1, 1_000_000),
+            ts_recv: 1_250_000,
+            ..Default::default()
+        };
+        registry.observe_live_record(&RecordRef::from(&trade));
+        assert_eq!(registry.total_records_decoded(), 1);
+        assert_eq!(
+            registry.live_latency().mean(),
+            Some(Duration::from_micros(250))
+        );
+
+        let out = registry.render();
+        assert!(out.contains(
+            "databento_request_duration_seconds_bucket{endpoint=\"timeseries.get_range\",le=\"0.25\"} 1\n"
+        ));
+        assert!(
+            out.contains("databento_request_errors_total{endpoint=\"timeseries.get_range\"} 1\n")
+        );
+        assert!(out.contains("databento_records_decoded_total 1\n"));
+        assert!(out.contains("databento_live_latency_seconds_bucket{le=\"0.00025\"} 1\n"));
+        assert!(out.contains("databento_live_latency_seconds_count 1\n"));
+        // Scraping doesn't change any state
+        assert_eq!(out, registry.render());
+    }
+}
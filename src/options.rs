@@ -0,0 +1,339 @@
+//! Retrieving and filtering options chains.
+//!
+//! [`OptionInstrument`] is a typed view of the fields of an option's
+//! [`InstrumentDefMsg`] needed for pricing. [`get_chain()`] fetches the definitions of
+//! every option on an underlying product for a date with
+//! [`SType::Parent`](dbn::SType::Parent) symbology, e.g. `ES.OPT`, and narrows them
+//! down with a [`ChainFilter`].
+//!
+//! [`iv_snapshot()`] combines the quotes of a chain with those of the underlying
+//! futures to compute the Black-76 implied volatility and [`Greeks`] of each option,
+//! e.g. for analyzing the volatility skew.
+
+mod greeks;
+
+use std::{cmp::Ordering, collections::HashMap, ops::RangeInclusive};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use dbn::{enums::InstrumentClass, InstrumentDefMsg, UNDEF_PRICE, UNDEF_TIMESTAMP};
+
+pub use greeks::{
+    black76_price, implied_volatility, iv_snapshot, iv_snapshot_from_candles, Greeks, IvPoint,
+};
+
+/// Whether an option is a call or a put.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OptionKind {
+    /// The right to buy the underlying at the strike price.
+    Call,
+    /// The right to sell the underlying at the strike price.
+    Put,
+}
+
+/// An option from an instrument definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionInstrument {
+    /// The instrument ID.
+    pub instrument_id: u32,
+    /// The raw symbol, e.g. `ESM5 C5300`.
+    pub raw_symbol: String,
+    /// The raw symbol of the underlying, e.g. `ESM5`.
+    pub underlying: String,
+    /// The instrument ID of the underlying, or 0 if it's unknown.
+    pub underlying_id: u32,
+    /// Whether the option is a call or a put.
+    pub kind: OptionKind,
+    /// The strike price.
+    pub strike: f64,
+    /// When the option expires.
+    pub expiration: DateTime<Utc>,
+    /// The minimum price increment.
+    pub tick_size: f64,
+    /// The contract multiplier, or 1 if it's undefined.
+    pub multiplier: f64,
+}
+
+impl OptionInstrument {
+    /// Returns the option in `definition`, or `None` if it isn't a call or put or its
+    /// strike price or expiration is undefined.
+    pub fn from_definition(definition: &InstrumentDefMsg) -> Option<Self> {
+        let kind = match definition.instrument_class().ok()? {
+            InstrumentClass::Call => OptionKind::Call,
+            InstrumentClass::Put => OptionKind::Put,
+            _ => return None,
+        };
+        if definition.expiration == UNDEF_TIMESTAMP {
+            return None;
+        }
+        Some(Self {
+            instrument_id: definition.hd.instrument_id,
+            raw_symbol: definition.raw_symbol().ok()?.to_owned(),
+            underlying: definition.underlying().unwrap_or_default().to_owned(),
+            underlying_id: definition.underlying_id,
+            kind,
+            strike: to_price(definition.strike_price)?,
+            expiration: DateTime::from_timestamp_nanos(definition.expiration as i64),
+            tick_size: to_price(definition.min_price_increment).unwrap_or(0.0),
+            multiplier: to_price(definition.unit_of_measure_qty).unwrap_or(1.0),
+        })
+    }
+
+    /// Returns the date the option expires in UTC.
+    pub fn expiration_date(&self) -> NaiveDate {
+        self.expiration.date_naive()
+    }
+}
+
+/// Criteria for narrowing down an options chain. The default filter matches every
+/// option.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChainFilter {
+    expirations: Option<RangeInclusive<NaiveDate>>,
+    strikes: Option<RangeInclusive<f64>>,
+    kind: Option<OptionKind>,
+    underlying: Option<String>,
+}
+
+impl ChainFilter {
+    /// Creates a filter that matches every option.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches options expiring on `date`.
+    pub fn expiring_on(self, date: NaiveDate) -> Self {
+        self.expiring_between(date..=date)
+    }
+
+    /// Only matches options expiring within `dates`.
+    pub fn expiring_between(mut self, dates: RangeInclusive<NaiveDate>) -> Self {
+        self.expirations = Some(dates);
+        self
+    }
+
+    /// Only matches options with strike prices within `strikes`.
+    pub fn strikes(mut self, strikes: RangeInclusive<f64>) -> Self {
+        self.strikes = Some(strikes);
+        self
+    }
+
+    /// Only matches calls or puts.
+    pub fn kind(mut self, kind: OptionKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only matches options on the underlying with the raw symbol `underlying`, e.g.
+    /// `ESM5`.
+    pub fn underlying(mut self, underlying: impl ToString) -> Self {
+        self.underlying = Some(underlying.to_string());
+        self
+    }
+
+    /// Returns `true` if `option` meets every criterion of the filter.
+    pub fn matches(&self, option: &OptionInstrument) -> bool {
+        self.expirations
+            .as_ref()
+            .is_none_or(|dates| dates.contains(&option.expiration_date()))
+            && self
+                .strikes
+                .as_ref()
+                .is_none_or(|strikes| strikes.contains(&option.strike))
+            && self.kind.is_none_or(|kind| kind == option.kind)
+            && self
+                .underlying
+                .as_ref()
+                .is_none_or(|underlying| *underlying == option.underlying)
+    }
+}
+
+/// Builds the chain of the options in `definitions` that match `filter`. When an
+/// instrument has several definitions, the last one is used. Options are ordered by
+/// expiration, strike, and then calls before puts.
+pub fn build_chain<'a>(
+    definitions: impl IntoIterator<Item = &'a InstrumentDefMsg>,
+    filter: &ChainFilter,
+) -> Vec<OptionInstrument> {
+    let mut by_id = HashMap::new();
+    for option in definitions
+        .into_iter()
+        .filter_map(OptionInstrument::from_definition)
+        .filter(|option| filter.matches(option))
+    {
+        by_id.insert(option.instrument_id, option);
+    }
+    let mut chain: Vec<_> = by_id.into_values().collect();
+    chain.sort_by(|a, b| {
+        a.expiration
+            .cmp(&b.expiration)
+            .then(a.strike.partial_cmp(&b.strike).unwrap_or(Ordering::Equal))
+            .then(a.kind.cmp(&b.kind))
+    });
+    chain
+}
+
+/// Fetches the options on `underlying` defined on `date` that match `filter`, ordered
+/// as in [`build_chain()`]. `underlying` is the root of the options' product, e.g. `ES`
+/// for options on E-mini S&P 500 futures, or its parent symbol, e.g. `ES.OPT`.
+///
+/// # Errors
+/// This function returns an error when `date` is out of range, the request fails, or
+/// the response can't be decoded.
+#[cfg(feature = "historical")]
+pub async fn get_chain(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    underlying: &str,
+    date: NaiveDate,
+    filter: &ChainFilter,
+) -> crate::Result<Vec<OptionInstrument>> {
+    use crate::timeconv::ToTimeDate;
+
+    let start = date.to_time_date()?;
+    let parent = if underlying.ends_with(".OPT") {
+        underlying.to_owned()
+    } else {
+        format!("{underlying}.OPT")
+    };
+    let params = crate::historical::timeseries::GetRangeParams::builder()
+        .dataset(dataset)
+        .symbols(parent)
+        .stype_in(dbn::SType::Parent)
+        .schema(dbn::Schema::Definition)
+        .date_time_range(start)
+        .build();
+    let mut decoder = client.get_range(&params).await?;
+    let mut definitions = Vec::new();
+    while let Some(definition) = decoder.decode_record::<InstrumentDefMsg>().await? {
+        definitions.push(definition.clone());
+    }
+    Ok(build_chain(&definitions, filter))
+}
+
+fn to_price(px: i64) -> Option<f64> {
+    (px != UNDEF_PRICE).then_some(px as f64 * 1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+
+    fn definition(
+        instrument_id: u32,
+        class: InstrumentClass,
+        strike: f64,
+        expiration_day: u32,
+    ) -> InstrumentDefMsg {
+        let mut definition = InstrumentDefMsg {
+            hd: RecordHeader::new::<InstrumentDefMsg>(rtype::INSTRUMENT_DEF, 1, instrument_id, 0),
+            instrument_class: class as u8 as std::ffi::c_char,
+            strike_price: (strike * 1e9) as i64,
+            expiration: Utc
+                .with_ymd_and_hms(2025, 6, expiration_day, 13, 30, 0)
+                .unwrap()
+                .timestamp_nanos_opt()
+                .unwrap() as u64,
+            min_price_increment: 250_000_000,
+            unit_of_measure_qty: 50_000_000_000,
+            ..Default::default()
+        };
+        let kind = if class == InstrumentClass::Call {
+            'C'
+        } else {
+            'P'
+        };
+        for (dst, src) in definition
+            .raw_symbol
+            .iter_mut()
+            .zip(format!("ESM5 {kind}{strike}").bytes())
+        {
+            *dst = src as std::ffi::c_char;
+        }
+        for (dst, src) in definition.underlying.iter_mut().zip(b"ESM5") {
+            *dst = *src as std::ffi::c_char;
+        }
+        definition
+    }
+
+    fn fixture() -> Vec<InstrumentDefMsg> {
+        vec![
+            definition(1, InstrumentClass::Put, 5300.0, 20),
+            definition(2, InstrumentClass::Call, 5300.0, 20),
+            definition(3, InstrumentClass::Call, 5250.0, 20),
+            definition(4, InstrumentClass::Call, 5300.0, 13),
+            definition(5, InstrumentClass::Future, 0.0, 20),
+            // Updated definition
+            definition(3, InstrumentClass::Call, 5250.0, 20),
+        ]
+    }
+
+    #[test]
+    fn test_from_definition() {
+        let option =
+            OptionInstrument::from_definition(&definition(1, InstrumentClass::Put, 5300.0, 20))
+                .unwrap();
+        assert_eq!(option.kind, OptionKind::Put);
+        assert_eq!(option.raw_symbol, "ESM5 P5300");
+        assert_eq!(option.underlying, "ESM5");
+        assert_eq!(option.strike, 5300.0);
+        assert_eq!(option.tick_size, 0.25);
+        assert_eq!(option.multiplier, 50.0);
+        assert_eq!(
+            option.expiration_date(),
+            NaiveDate::from_ymd_opt(2025, 6, 20).unwrap()
+        );
+        assert!(OptionInstrument::from_definition(&definition(
+            5,
+            InstrumentClass::Future,
+            0.0,
+            20
+        ))
+        .is_none());
+    }
+
+    #[test]
+    fn test_build_chain() {
+        let definitions = fixture();
+        let chain = build_chain(&definitions, &ChainFilter::new());
+        let ids: Vec<_> = chain.iter().map(|option| option.instrument_id).collect();
+        assert_eq!(ids, [4, 3, 2, 1]);
+
+        let filter = ChainFilter::new()
+            .expiring_on(NaiveDate::from_ymd_opt(2025, 6, 20).unwrap())
+            .strikes(5275.0..=5325.0)
+            .kind(OptionKind::Call)
+            .underlying("ESM5");
+        let chain = build_chain(&definitions, &filter);
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].instrument_id, 2);
+        assert!(build_chain(&definitions, &ChainFilter::new().underlying("ESU5")).is_empty());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_get_chain() {
+        use crate::testing::MockHistoricalClient;
+
+        let mut client = MockHistoricalClient::new()
+            .with_records("GLBX.MDP3", dbn::Schema::Definition, &fixture())
+            .unwrap();
+        let date = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let chain = get_chain(
+            &mut client,
+            "GLBX.MDP3",
+            "ES",
+            date,
+            &ChainFilter::new().kind(OptionKind::Put),
+        )
+        .await
+        .unwrap();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain[0].instrument_id, 1);
+        let request = &client.requests()[0];
+        assert_eq!(request.stype_in, dbn::SType::Parent);
+        assert_eq!(request.symbols.to_api_string(), "ES.OPT");
+    }
+}
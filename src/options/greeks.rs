@@ -0,0 +1,387 @@
+use std::{collections::HashMap, f64::consts::SQRT_2};
+
+use chrono::{DateTime, Utc};
+
+use super::{OptionInstrument, OptionKind};
+use crate::{
+    examples::es_futures_pmz::{Candle, CandlePrice},
+    quotes::QuoteBoard,
+};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0;
+const MIN_VOLATILITY: f64 = 1e-6;
+const MAX_VOLATILITY: f64 = 10.0;
+
+/// The sensitivities of a Black-76 option price.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Greeks {
+    /// The change in price per one-point change in the futures price.
+    pub delta: f64,
+    /// The change in delta per one-point change in the futures price.
+    pub gamma: f64,
+    /// The change in price per one percentage point change in volatility.
+    pub vega: f64,
+    /// The change in price per calendar day that passes.
+    pub theta: f64,
+    /// The change in price per one percentage point change in the interest rate.
+    pub rho: f64,
+}
+
+impl Greeks {
+    /// Computes the greeks of an option of `kind` on a future at `forward` with
+    /// `strike`, `years` until expiration, the continuously compounded interest
+    /// `rate`, and `volatility`, all annualized.
+    pub fn black76(
+        kind: OptionKind,
+        forward: f64,
+        strike: f64,
+        years: f64,
+        rate: f64,
+        volatility: f64,
+    ) -> Self {
+        let discount = (-rate * years).exp();
+        let sqrt_t = years.sqrt();
+        let (d1, d2) = d1_d2(forward, strike, years, volatility);
+        let pdf = norm_pdf(d1);
+        let price = black76_price(kind, forward, strike, years, rate, volatility);
+        let delta = match kind {
+            OptionKind::Call => discount * norm_cdf(d1),
+            OptionKind::Put => -discount * norm_cdf(-d1),
+        };
+        let carry = match kind {
+            OptionKind::Call => {
+                rate * forward * discount * norm_cdf(d1) - rate * strike * discount * norm_cdf(d2)
+            }
+            OptionKind::Put => {
+                rate * strike * discount * norm_cdf(-d2) - rate * forward * discount * norm_cdf(-d1)
+            }
+        };
+        let theta = -forward * discount * pdf * volatility / (2.0 * sqrt_t) + carry;
+        Self {
+            delta,
+            gamma: discount * pdf / (forward * volatility * sqrt_t),
+            vega: forward * discount * pdf * sqrt_t / 100.0,
+            theta: theta / 365.0,
+            rho: -years * price / 100.0,
+        }
+    }
+}
+
+/// Returns the Black-76 price of an option of `kind` on a future at `forward` with
+/// `strike`, `years` until expiration, the continuously compounded interest `rate`,
+/// and `volatility`, all annualized.
+pub fn black76_price(
+    kind: OptionKind,
+    forward: f64,
+    strike: f64,
+    years: f64,
+    rate: f64,
+    volatility: f64,
+) -> f64 {
+    let discount = (-rate * years).exp();
+    let (d1, d2) = d1_d2(forward, strike, years, volatility);
+    match kind {
+        OptionKind::Call => discount * (forward * norm_cdf(d1) - strike * norm_cdf(d2)),
+        OptionKind::Put => discount * (strike * norm_cdf(-d2) - forward * norm_cdf(-d1)),
+    }
+}
+
+/// Returns the Black-76 volatility that prices an option of `kind` on a future at
+/// `forward` with `strike` and `years` until expiration at `price`, or `None` if
+/// `price` is outside the range possible for any volatility, e.g. below the
+/// discounted intrinsic value.
+pub fn implied_volatility(
+    kind: OptionKind,
+    price: f64,
+    forward: f64,
+    strike: f64,
+    years: f64,
+    rate: f64,
+) -> Option<f64> {
+    if !(price > 0.0 && forward > 0.0 && strike > 0.0 && years > 0.0) {
+        return None;
+    }
+    let price_at = |volatility| black76_price(kind, forward, strike, years, rate, volatility);
+    let (mut low, mut high) = (MIN_VOLATILITY, MAX_VOLATILITY);
+    if price < price_at(low) || price > price_at(high) {
+        return None;
+    }
+    // Newton's method, falling back to bisection when a step leaves the bracket
+    let mut volatility = 0.2;
+    for _ in 0..100 {
+        let diff = price_at(volatility) - price;
+        if diff.abs() < 1e-10 * price.max(1.0) {
+            break;
+        }
+        if diff > 0.0 {
+            high = volatility;
+        } else {
+            low = volatility;
+        }
+        let (d1, _) = d1_d2(forward, strike, years, volatility);
+        let vega = forward * (-rate * years).exp() * norm_pdf(d1) * years.sqrt();
+        let next = volatility - diff / vega;
+        volatility = if vega > 0.0 && next > low && next < high {
+            next
+        } else {
+            (low + high) / 2.0
+        };
+    }
+    Some(volatility)
+}
+
+/// The implied volatility and greeks of an option at a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IvPoint {
+    /// The option.
+    pub option: OptionInstrument,
+    /// The option price the volatility was implied from.
+    pub price: f64,
+    /// The price of the underlying future.
+    pub forward: f64,
+    /// The time until expiration in years.
+    pub years: f64,
+    /// The implied volatility, or `None` if no volatility matches the price.
+    pub volatility: Option<f64>,
+    /// The greeks at the implied volatility.
+    pub greeks: Option<Greeks>,
+}
+
+/// Computes the implied volatility of each option in `chain` at `now` from the
+/// mid-prices of the options and their underlying futures in `quotes`, skipping
+/// options that have expired or lack a two-sided quote for themselves or their
+/// underlying. The points are in the order of `chain`, so a chain from
+/// [`get_chain()`](super::get_chain) gives the volatility skew of each expiration.
+pub fn iv_snapshot(
+    chain: &[OptionInstrument],
+    quotes: &QuoteBoard,
+    now: DateTime<Utc>,
+    rate: f64,
+) -> Vec<IvPoint> {
+    snapshot(chain, now, rate, |instrument_id, symbol| {
+        let quote = match symbol {
+            Some(symbol) => quotes.get(symbol),
+            None => quotes.get_by_id(instrument_id),
+        };
+        quote.and_then(|quote| quote.mid())
+    })
+}
+
+/// Computes the implied volatility of each option in `chain` at `now` like
+/// [`iv_snapshot()`], but from the latest close of the options and their underlying
+/// futures in `candles`.
+pub fn iv_snapshot_from_candles<P: CandlePrice>(
+    chain: &[OptionInstrument],
+    candles: &[Candle<P>],
+    now: DateTime<Utc>,
+    rate: f64,
+) -> Vec<IvPoint> {
+    let mut by_id = HashMap::new();
+    let mut by_symbol = HashMap::new();
+    for candle in candles.iter().filter(|candle| candle.timestamp <= now) {
+        let close = candle.close.to_f64();
+        by_id
+            .entry(candle.instrument_id)
+            .and_modify(|latest: &mut (DateTime<_>, f64)| {
+                if candle.timestamp >= latest.0 {
+                    *latest = (candle.timestamp, close);
+                }
+            })
+            .or_insert((candle.timestamp, close));
+        by_symbol
+            .entry(&*candle.symbol)
+            .and_modify(|latest: &mut (DateTime<_>, f64)| {
+                if candle.timestamp >= latest.0 {
+                    *latest = (candle.timestamp, close);
+                }
+            })
+            .or_insert((candle.timestamp, close));
+    }
+    snapshot(chain, now, rate, |instrument_id, symbol| {
+        let latest = match symbol {
+            Some(symbol) => by_symbol.get(symbol),
+            None => by_id.get(&instrument_id),
+        };
+        latest.map(|(_, close)| *close)
+    })
+}
+
+// Looks up option prices by instrument ID and underlying prices by symbol
+fn snapshot(
+    chain: &[OptionInstrument],
+    now: DateTime<Utc>,
+    rate: f64,
+    price_of: impl Fn(u32, Option<&str>) -> Option<f64>,
+) -> Vec<IvPoint> {
+    chain
+        .iter()
+        .filter_map(|option| {
+            let years =
+                (option.expiration - now).num_milliseconds() as f64 / 1e3 / SECONDS_PER_YEAR;
+            if years <= 0.0 {
+                return None;
+            }
+            let price = price_of(option.instrument_id, None)?;
+            let forward = price_of(option.underlying_id, Some(&option.underlying))
+                .or_else(|| price_of(option.underlying_id, None))?;
+            let volatility =
+                implied_volatility(option.kind, price, forward, option.strike, years, rate);
+            Some(IvPoint {
+                option: option.clone(),
+                price,
+                forward,
+                years,
+                volatility,
+                greeks: volatility.map(|volatility| {
+                    Greeks::black76(option.kind, forward, option.strike, years, rate, volatility)
+                }),
+            })
+        })
+        .collect()
+}
+
+fn d1_d2(forward: f64, strike: f64, years: f64, volatility: f64) -> (f64, f64) {
+    let std_dev = volatility * years.sqrt();
+    let d1 = ((forward / strike).ln() + std_dev * std_dev / 2.0) / std_dev;
+    (d1, d1 - std_dev)
+}
+
+fn norm_pdf(x: f64) -> f64 {
+    (-x * x / 2.0).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+fn norm_cdf(x: f64) -> f64 {
+    erfc(-x / SQRT_2) / 2.0
+}
+
+// The complementary error function with a relative error below 1.2e-7, from
+// Numerical Recipes
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + z / 2.0);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let r = t * poly.exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use dbn::{rtype, BidAskPair, Mbp1Msg, RecordHeader};
+
+    use super::*;
+    use crate::test_util;
+
+    fn option(instrument_id: u32, kind: OptionKind, strike: f64) -> OptionInstrument {
+        OptionInstrument {
+            instrument_id,
+            raw_symbol: format!("ESM5 {strike}"),
+            underlying: "ESM5".to_owned(),
+            underlying_id: 100,
+            kind,
+            strike,
+            expiration: Utc.with_ymd_and_hms(2025, 6, 20, 13, 30, 0).unwrap(),
+            tick_size: 0.25,
+            multiplier: 50.0,
+        }
+    }
+
+    #[test]
+    fn test_black76() {
+        // Hull, Options, Futures, and Other Derivatives, example 18.8
+        let put = black76_price(OptionKind::Put, 20.0, 20.0, 4.0 / 12.0, 0.09, 0.25);
+        assert!((put - 1.1166).abs() < 1e-4, "{put}");
+        // Put-call parity
+        let call = black76_price(OptionKind::Call, 20.0, 20.0, 4.0 / 12.0, 0.09, 0.25);
+        assert!((call - put).abs() < 1e-9);
+
+        let greeks = Greeks::black76(OptionKind::Call, 5300.0, 5300.0, 0.25, 0.04, 0.2);
+        assert!((greeks.delta - 0.5148).abs() < 1e-4, "{greeks:?}");
+        assert!(greeks.gamma > 0.0);
+        assert!(greeks.vega > 0.0);
+        assert!(greeks.theta < 0.0);
+        let put = Greeks::black76(OptionKind::Put, 5300.0, 5300.0, 0.25, 0.04, 0.2);
+        assert!((greeks.delta - put.delta - (-0.04f64 * 0.25).exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_implied_volatility() {
+        for (kind, strike, volatility) in [
+            (OptionKind::Call, 5300.0, 0.2),
+            (OptionKind::Put, 5000.0, 0.35),
+            (OptionKind::Call, 5800.0, 0.12),
+        ] {
+            let price = black76_price(kind, 5300.0, strike, 0.1, 0.04, volatility);
+            let implied = implied_volatility(kind, price, 5300.0, strike, 0.1, 0.04).unwrap();
+            assert!(
+                (implied - volatility).abs() < 1e-6,
+                "{implied} != {volatility}"
+            );
+        }
+        // Below intrinsic value
+        assert!(implied_volatility(OptionKind::Call, 1.0, 5300.0, 5000.0, 0.1, 0.04).is_none());
+        assert!(implied_volatility(OptionKind::Call, 1.0, 5300.0, 5000.0, 0.0, 0.04).is_none());
+    }
+
+    #[test]
+    fn test_iv_snapshot() {
+        let now = Utc.with_ymd_and_hms(2025, 5, 21, 13, 30, 0).unwrap();
+        let chain = [
+            option(1, OptionKind::Call, 5300.0),
+            option(2, OptionKind::Put, 5200.0),
+            // No quote
+            option(3, OptionKind::Put, 5100.0),
+        ];
+        let years = 30.0 / 365.0;
+        let call_px = black76_price(OptionKind::Call, 5300.0, 5300.0, years, 0.04, 0.18);
+        let put_px = black76_price(OptionKind::Put, 5300.0, 5200.0, years, 0.04, 0.21);
+        let mut quotes = QuoteBoard::new();
+        quotes.insert_symbol("ESM5", 100);
+        for (instrument_id, mid) in [(100, 5300.0), (1, call_px), (2, put_px)] {
+            quotes.on_mbp1(&Mbp1Msg {
+                hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, instrument_id, 0),
+                levels: [BidAskPair {
+                    bid_px: ((mid - 0.125) * 1e9).round() as i64,
+                    ask_px: ((mid + 0.125) * 1e9).round() as i64,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            });
+        }
+        let points = iv_snapshot(&chain, &quotes, now, 0.04);
+        assert_eq!(points.len(), 2);
+        assert!((points[0].volatility.unwrap() - 0.18).abs() < 1e-6);
+        assert!((points[1].volatility.unwrap() - 0.21).abs() < 1e-6);
+        assert!(points[1].greeks.unwrap().delta < 0.0);
+        // Expired
+        assert!(iv_snapshot(&chain, &quotes, chain[0].expiration, 0.04).is_empty());
+
+        let candle = |instrument_id, symbol: &str, close| {
+            test_util::candle()
+                .timestamp(now.with_timezone(&chrono_tz::America::New_York))
+                .instrument_id(instrument_id)
+                .symbol(symbol)
+                .price(close)
+                .volume(1)
+                .build()
+        };
+        let candles = [
+            candle(100, "ESM5", 5300.0),
+            candle(1, "ESM5 C5300", call_px),
+        ];
+        let points = iv_snapshot_from_candles(&chain, &candles, now, 0.04);
+        assert_eq!(points.len(), 1);
+        assert!((points[0].volatility.unwrap() - 0.18).abs() < 1e-6);
+    }
+}
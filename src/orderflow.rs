@@ -0,0 +1,553 @@
+//! Order flow from the aggressor side of trades.
+//!
+//! Each [`TradeMsg`] records the side of the order that initiated it: a buyer lifting
+//! the offer or a seller hitting the bid. [`DeltaAggregator`] sums the volume of each
+//! side per time bucket into [`DeltaCandle`]s, whose delta is the buy volume minus
+//! the sell volume. Buckets are aligned like [`Candle`](crate::examples::es_futures_pmz::Candle)s
+//! aggregated with the same interval and anchor, so delta candles can be joined with
+//! OHLCV bars by timestamp and instrument ID.
+//!
+//! [`FootprintBuilder`] breaks the same buckets down by price into [`FootprintBar`]s
+//! for footprint charts. With the `serde` feature, footprint bars can be serialized,
+//! e.g. to JSON, and with the `arrow` feature
+//! [`footprints_to_arrow()`](crate::arrow::footprints_to_arrow) converts them to a
+//! record batch.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+use dbn::{enums::Side, Mbp1Msg, TradeMsg};
+
+use crate::examples::es_futures_pmz::{bucket_start, BucketAnchor, CandlePrice};
+
+/// The buy and sell volume of trades in a time bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeltaCandle {
+    /// The start of the bucket.
+    pub timestamp: DateTime<Tz>,
+    /// The instrument ID of the trades.
+    pub instrument_id: u32,
+    /// The symbol of the trades.
+    pub symbol: Arc<str>,
+    /// The volume of trades initiated by buyers.
+    pub buy_volume: u64,
+    /// The volume of trades initiated by sellers.
+    pub sell_volume: u64,
+    /// The volume of trades without an aggressor side, e.g. from auctions, which
+    /// doesn't count toward the delta.
+    pub unclassified_volume: u64,
+    /// The buy volume minus the sell volume.
+    pub delta: i64,
+    /// The running total of the delta at the end of the bucket, since the first trade
+    /// or [`reset_cumulative()`](DeltaAggregator::reset_cumulative).
+    pub cumulative_delta: i64,
+    /// The highest delta within the bucket after any trade.
+    pub max_delta: i64,
+    /// The lowest delta within the bucket after any trade.
+    pub min_delta: i64,
+}
+
+/// Incrementally aggregates trades into [`DeltaCandle`]s, emitting each once a trade
+/// of the same instrument arrives for a later bucket.
+#[derive(Debug, Clone)]
+pub struct DeltaAggregator {
+    interval: Duration,
+    anchor: BucketAnchor,
+    tz: Tz,
+    instruments: HashMap<u32, InstrumentDelta>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct InstrumentDelta {
+    current: Option<DeltaCandle>,
+    cumulative_delta: i64,
+}
+
+impl DeltaAggregator {
+    /// Creates an aggregator for `interval` buckets aligned to `anchor` with timestamps
+    /// in `tz`.
+    pub fn new(interval: Duration, anchor: BucketAnchor, tz: Tz) -> Self {
+        Self {
+            interval,
+            anchor,
+            tz,
+            instruments: HashMap::new(),
+        }
+    }
+
+    /// Returns the in-progress candle of `instrument_id`, if any.
+    pub fn current(&self, instrument_id: u32) -> Option<&DeltaCandle> {
+        self.instruments.get(&instrument_id)?.current.as_ref()
+    }
+
+    /// Adds a trade for `symbol`, returning the previous candle of its instrument if
+    /// the trade starts a new bucket. Trades must be in timestamp order.
+    pub fn push(&mut self, trade: &TradeMsg, symbol: &str) -> Option<DeltaCandle> {
+        let timestamp =
+            DateTime::from_timestamp_nanos(trade.hd.ts_event as i64).with_timezone(&self.tz);
+        let timestamp = bucket_start(timestamp, self.interval, self.anchor);
+        let state = self.instruments.entry(trade.hd.instrument_id).or_default();
+        let completed = match state.current.as_ref() {
+            Some(current) if current.timestamp == timestamp => None,
+            _ => state.current.take(),
+        };
+        let cumulative_delta = state.cumulative_delta;
+        let current = state.current.get_or_insert_with(|| DeltaCandle {
+            timestamp,
+            instrument_id: trade.hd.instrument_id,
+            symbol: symbol.into(),
+            buy_volume: 0,
+            sell_volume: 0,
+            unclassified_volume: 0,
+            delta: 0,
+            cumulative_delta,
+            max_delta: 0,
+            min_delta: 0,
+        });
+        let size = u64::from(trade.size);
+        let delta = match trade.side() {
+            Ok(Side::Bid) => {
+                current.buy_volume += size;
+                i64::from(trade.size)
+            }
+            Ok(Side::Ask) => {
+                current.sell_volume += size;
+                -i64::from(trade.size)
+            }
+            _ => {
+                current.unclassified_volume += size;
+                0
+            }
+        };
+        current.delta += delta;
+        current.max_delta = current.max_delta.max(current.delta);
+        current.min_delta = current.min_delta.min(current.delta);
+        current.cumulative_delta += delta;
+        state.cumulative_delta = current.cumulative_delta;
+        completed
+    }
+
+    /// Returns the in-progress candles of every instrument, ordered by timestamp and
+    /// then instrument ID, e.g. at the end of a session. The cumulative deltas are kept.
+    pub fn flush(&mut self) -> Vec<DeltaCandle> {
+        let mut candles: Vec<_> = self
+            .instruments
+            .values_mut()
+            .filter_map(|state| state.current.take())
+            .collect();
+        candles.sort_by_key(|candle| (candle.timestamp, candle.instrument_id));
+        candles
+    }
+
+    /// Restarts the cumulative delta of every instrument from zero, e.g. at the start
+    /// of a session. Call it after [`flush()`](Self::flush) so in-progress candles
+    /// aren't affected.
+    pub fn reset_cumulative(&mut self) {
+        for state in self.instruments.values_mut() {
+            state.cumulative_delta = 0;
+        }
+    }
+}
+
+/// Aggregates `trades`, in timestamp order, into `interval` delta candles aligned to
+/// `anchor` for each instrument, labeled with `symbol`.
+///
+/// Candles are ordered by timestamp and then instrument ID.
+pub fn delta_candles(
+    trades: &[TradeMsg],
+    interval: Duration,
+    anchor: BucketAnchor,
+    symbol: &str,
+    tz: Tz,
+) -> Vec<DeltaCandle> {
+    let mut aggregator = DeltaAggregator::new(interval, anchor, tz);
+    let mut candles: Vec<_> = trades
+        .iter()
+        .filter_map(|trade| aggregator.push(trade, symbol))
+        .collect();
+    candles.extend(aggregator.flush());
+    candles.sort_by_key(|candle| (candle.timestamp, candle.instrument_id));
+    candles
+}
+
+/// The volume traded at a price within a [`FootprintBar`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FootprintLevel<P = f64> {
+    /// The trade price.
+    pub price: P,
+    /// The volume of trades initiated by buyers, i.e. at the ask.
+    pub buy_volume: u64,
+    /// The volume of trades initiated by sellers, i.e. at the bid.
+    pub sell_volume: u64,
+    /// The volume of trades without an aggressor side.
+    pub unclassified_volume: u64,
+}
+
+impl<P> FootprintLevel<P> {
+    /// Returns the total volume traded at the price.
+    pub fn volume(&self) -> u64 {
+        self.buy_volume + self.sell_volume + self.unclassified_volume
+    }
+
+    /// Returns the buy volume minus the sell volume at the price.
+    pub fn delta(&self) -> i64 {
+        self.buy_volume as i64 - self.sell_volume as i64
+    }
+}
+
+/// The volume traded at each price in a time bucket, split by aggressor side.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FootprintBar<P = f64> {
+    /// The start of the bucket.
+    pub timestamp: DateTime<Tz>,
+    /// The instrument ID of the trades.
+    pub instrument_id: u32,
+    /// The symbol of the trades.
+    pub symbol: Arc<str>,
+    /// The traded prices in ascending order.
+    pub levels: Vec<FootprintLevel<P>>,
+}
+
+impl<P> FootprintBar<P> {
+    /// Returns the total volume of the bar.
+    pub fn volume(&self) -> u64 {
+        self.levels.iter().map(FootprintLevel::volume).sum()
+    }
+
+    /// Returns the buy volume minus the sell volume of the bar.
+    pub fn delta(&self) -> i64 {
+        self.levels.iter().map(FootprintLevel::delta).sum()
+    }
+
+    /// Returns the level with the most volume, the point of control, preferring the
+    /// lowest price on ties, or `None` if the bar has no levels.
+    pub fn point_of_control(&self) -> Option<&FootprintLevel<P>> {
+        self.levels.iter().rev().max_by_key(|level| level.volume())
+    }
+}
+
+/// Incrementally builds [`FootprintBar`]s from trades or MBP-1 records, emitting each
+/// once a trade of the same instrument arrives for a later bucket.
+#[derive(Debug, Clone)]
+pub struct FootprintBuilder<P = f64> {
+    interval: Duration,
+    anchor: BucketAnchor,
+    tz: Tz,
+    instruments: HashMap<u32, PendingFootprint>,
+    _price: std::marker::PhantomData<P>,
+}
+
+// The in-progress bar of an instrument, keyed by fixed-point price
+#[derive(Debug, Clone)]
+struct PendingFootprint {
+    timestamp: DateTime<Tz>,
+    symbol: Arc<str>,
+    levels: BTreeMap<i64, FootprintLevel<i64>>,
+}
+
+impl<P: CandlePrice> FootprintBuilder<P> {
+    /// Creates a builder for `interval` buckets aligned to `anchor` with timestamps in
+    /// `tz`.
+    pub fn new(interval: Duration, anchor: BucketAnchor, tz: Tz) -> Self {
+        Self {
+            interval,
+            anchor,
+            tz,
+            instruments: HashMap::new(),
+            _price: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns the in-progress bar of `instrument_id`, if any.
+    pub fn current(&self, instrument_id: u32) -> Option<FootprintBar<P>> {
+        self.instruments
+            .get(&instrument_id)
+            .map(|pending| pending.to_bar(instrument_id))
+    }
+
+    /// Adds a trade for `symbol`, returning the previous bar of its instrument if the
+    /// trade starts a new bucket. Trades must be in timestamp order.
+    pub fn push(&mut self, trade: &TradeMsg, symbol: &str) -> Option<FootprintBar<P>> {
+        self.push_trade(
+            trade.hd.ts_event,
+            trade.hd.instrument_id,
+            trade.price,
+            trade.size,
+            trade.side(),
+            symbol,
+        )
+    }
+
+    /// Adds an MBP-1 record for `symbol`, returning the previous bar of its instrument
+    /// if the record is a trade that starts a new bucket. Other actions are ignored.
+    pub fn push_mbp1(&mut self, mbp1: &Mbp1Msg, symbol: &str) -> Option<FootprintBar<P>> {
+        if mbp1.action as u8 != b'T' {
+            return None;
+        }
+        self.push_trade(
+            mbp1.hd.ts_event,
+            mbp1.hd.instrument_id,
+            mbp1.price,
+            mbp1.size,
+            mbp1.side(),
+            symbol,
+        )
+    }
+
+    /// Returns the in-progress bars of every instrument, ordered by timestamp and then
+    /// instrument ID, e.g. at the end of a session.
+    pub fn flush(&mut self) -> Vec<FootprintBar<P>> {
+        let mut bars: Vec<_> = self
+            .instruments
+            .drain()
+            .map(|(instrument_id, pending)| pending.to_bar(instrument_id))
+            .collect();
+        bars.sort_by_key(|bar| (bar.timestamp, bar.instrument_id));
+        bars
+    }
+
+    fn push_trade(
+        &mut self,
+        ts_event: u64,
+        instrument_id: u32,
+        price: i64,
+        size: u32,
+        side: dbn::Result<Side>,
+        symbol: &str,
+    ) -> Option<FootprintBar<P>> {
+        let timestamp = DateTime::from_timestamp_nanos(ts_event as i64).with_timezone(&self.tz);
+        let timestamp = bucket_start(timestamp, self.interval, self.anchor);
+        let completed = match self.instruments.get(&instrument_id) {
+            Some(pending) if pending.timestamp == timestamp => None,
+            _ => self
+                .instruments
+                .remove(&instrument_id)
+                .map(|pending| pending.to_bar(instrument_id)),
+        };
+        let pending = self
+            .instruments
+            .entry(instrument_id)
+            .or_insert_with(|| PendingFootprint {
+                timestamp,
+                symbol: symbol.into(),
+                levels: BTreeMap::new(),
+            });
+        let level = pending.levels.entry(price).or_insert(FootprintLevel {
+            price,
+            buy_volume: 0,
+            sell_volume: 0,
+            unclassified_volume: 0,
+        });
+        let size = u64::from(size);
+        match side {
+            Ok(Side::Bid) => level.buy_volume += size,
+            Ok(Side::Ask) => level.sell_volume += size,
+            _ => level.unclassified_volume += size,
+        }
+        completed
+    }
+}
+
+impl PendingFootprint {
+    fn to_bar<P: CandlePrice>(&self, instrument_id: u32) -> FootprintBar<P> {
+        FootprintBar {
+            timestamp: self.timestamp,
+            instrument_id,
+            symbol: self.symbol.clone(),
+            levels: self
+                .levels
+                .values()
+                .map(|level| FootprintLevel {
+                    price: P::from_fixed(level.price),
+                    buy_volume: level.buy_volume,
+                    sell_volume: level.sell_volume,
+                    unclassified_volume: level.unclassified_volume,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Builds `interval` footprint bars aligned to `anchor` for each instrument from
+/// `trades`, in timestamp order, labeled with `symbol`.
+///
+/// Bars are ordered by timestamp and then instrument ID.
+pub fn footprint_bars<P: CandlePrice>(
+    trades: &[TradeMsg],
+    interval: Duration,
+    anchor: BucketAnchor,
+    symbol: &str,
+    tz: Tz,
+) -> Vec<FootprintBar<P>> {
+    let mut builder = FootprintBuilder::new(interval, anchor, tz);
+    let mut bars: Vec<_> = trades
+        .iter()
+        .filter_map(|trade| builder.push(trade, symbol))
+        .collect();
+    bars.extend(builder.flush());
+    bars.sort_by_key(|bar| (bar.timestamp, bar.instrument_id));
+    bars
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_char;
+
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+    use crate::test_util;
+
+    const MINUTE: u64 = 60_000_000_000;
+
+    fn trade(instrument_id: u32, ts_event: u64, side: u8, size: u32) -> TradeMsg {
+        priced_trade(instrument_id, ts_event, side, size, 5_300_000_000_000)
+    }
+
+    fn priced_trade(
+        instrument_id: u32,
+        ts_event: u64,
+        side: u8,
+        size: u32,
+        price: i64,
+    ) -> TradeMsg {
+        TradeMsg {
+            side: side as c_char,
+            ..test_util::trade(instrument_id, ts_event, price, size)
+        }
+    }
+
+    #[test]
+    fn test_delta_candles() {
+        let trades = [
+            trade(1, 0, b'B', 5),
+            trade(1, 1, b'A', 8),
+            trade(1, 2, b'B', 1),
+            trade(2, 3, b'A', 4),
+            trade(1, MINUTE, b'N', 10),
+            trade(1, MINUTE + 1, b'B', 6),
+        ];
+        let candles = delta_candles(
+            &trades,
+            Duration::minutes(1),
+            BucketAnchor::Epoch,
+            "ES",
+            Tz::UTC,
+        );
+        assert_eq!(candles.len(), 3);
+        let first = &candles[0];
+        assert_eq!(
+            (first.instrument_id, first.buy_volume, first.sell_volume),
+            (1, 6, 8)
+        );
+        assert_eq!(first.delta, -2);
+        assert_eq!((first.max_delta, first.min_delta), (5, -3));
+        assert_eq!(first.cumulative_delta, -2);
+        assert_eq!(candles[1].instrument_id, 2);
+        assert_eq!(candles[1].cumulative_delta, -4);
+        let second = &candles[2];
+        assert_eq!(second.timestamp.timestamp(), 60);
+        assert_eq!(second.unclassified_volume, 10);
+        assert_eq!(second.delta, 6);
+        assert_eq!(second.cumulative_delta, 4);
+        assert_eq!((second.max_delta, second.min_delta), (6, 0));
+    }
+
+    #[test]
+    fn test_reset_cumulative() {
+        let mut aggregator =
+            DeltaAggregator::new(Duration::minutes(1), BucketAnchor::Epoch, Tz::UTC);
+        assert!(aggregator.push(&trade(1, 0, b'B', 5), "ES").is_none());
+        assert_eq!(aggregator.current(1).unwrap().cumulative_delta, 5);
+        assert_eq!(aggregator.flush().len(), 1);
+        aggregator.reset_cumulative();
+        aggregator.push(&trade(1, MINUTE, b'A', 2), "ES");
+        assert_eq!(aggregator.current(1).unwrap().cumulative_delta, -2);
+        assert!(aggregator.current(2).is_none());
+    }
+
+    #[test]
+    fn test_footprint_bars() {
+        let trades = [
+            priced_trade(1, 0, b'B', 5, 5_300_250_000_000),
+            priced_trade(1, 1, b'A', 3, 5_300_000_000_000),
+            priced_trade(1, 2, b'B', 2, 5_300_000_000_000),
+            priced_trade(1, 3, b'N', 1, 5_300_250_000_000),
+            priced_trade(1, MINUTE, b'A', 4, 5_299_750_000_000),
+        ];
+        let bars: Vec<FootprintBar> = footprint_bars(
+            &trades,
+            Duration::minutes(1),
+            BucketAnchor::Epoch,
+            "ES",
+            Tz::UTC,
+        );
+        assert_eq!(bars.len(), 2);
+        let first = &bars[0];
+        assert_eq!(
+            first.levels,
+            [
+                FootprintLevel {
+                    price: 5300.0,
+                    buy_volume: 2,
+                    sell_volume: 3,
+                    unclassified_volume: 0
+                },
+                FootprintLevel {
+                    price: 5300.25,
+                    buy_volume: 5,
+                    sell_volume: 0,
+                    unclassified_volume: 1
+                },
+            ]
+        );
+        assert_eq!((first.volume(), first.delta()), (11, 4));
+        assert_eq!(first.point_of_control().unwrap().price, 5300.25);
+        assert_eq!(bars[1].levels.len(), 1);
+        assert_eq!(bars[1].delta(), -4);
+    }
+
+    #[test]
+    fn test_footprint_mbp1() {
+        let mut builder =
+            FootprintBuilder::<f64>::new(Duration::minutes(1), BucketAnchor::Epoch, Tz::UTC);
+        let mut mbp1 = Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, 1, 0),
+            price: 5_300_000_000_000,
+            size: 7,
+            action: b'A' as c_char,
+            side: b'B' as c_char,
+            ..Default::default()
+        };
+        assert!(builder.push_mbp1(&mbp1, "ES").is_none());
+        assert!(builder.current(1).is_none());
+        mbp1.action = b'T' as c_char;
+        builder.push_mbp1(&mbp1, "ES");
+        assert_eq!(builder.current(1).unwrap().levels[0].buy_volume, 7);
+        assert_eq!(builder.flush().len(), 1);
+        assert!(builder.current(1).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_footprint_json() {
+        let bars: Vec<FootprintBar> = footprint_bars(
+            &[trade(1, 0, b'A', 2)],
+            Duration::minutes(1),
+            BucketAnchor::Epoch,
+            "ES",
+            Tz::UTC,
+        );
+        let json = serde_json::to_value(&bars[0]).unwrap();
+        assert_eq!(json["symbol"], "ES");
+        assert_eq!(json["timestamp"], "1970-01-01T00:00:00Z");
+        assert_eq!(json["levels"][0]["price"], 5300.0);
+        assert_eq!(json["levels"][0]["sell_volume"], 2);
+    }
+}
@@ -0,0 +1,289 @@
+//! Candlestick pattern detection.
+//!
+//! [`PatternDetector`] checks each candle pushed to it, in timestamp order, against the
+//! previous candle of the same instrument and emits a [`PatternEvent`] tagged with the
+//! [`Pattern`] for every match, so it can follow a live candle builder.
+//! [`detect_patterns()`] scans a whole series. How strict the single-candle patterns
+//! are is set with a [`PatternConfig`].
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use crate::examples::es_futures_pmz::{Candle, CandlePrice};
+
+/// A candlestick pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// A bullish candle whose body engulfs the body of the previous, bearish candle.
+    BullishEngulfing,
+    /// A bearish candle whose body engulfs the body of the previous, bullish candle.
+    BearishEngulfing,
+    /// A candle whose open and close are nearly equal relative to its range.
+    Doji,
+    /// A candle with a small body at the top of its range and a long lower shadow.
+    Hammer,
+    /// A candle whose range is within the range of the previous candle.
+    InsideBar,
+}
+
+impl Pattern {
+    /// Converts the enum to its `str` representation.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Pattern::BullishEngulfing => "bullish_engulfing",
+            Pattern::BearishEngulfing => "bearish_engulfing",
+            Pattern::Doji => "doji",
+            Pattern::Hammer => "hammer",
+            Pattern::InsideBar => "inside_bar",
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Thresholds for the single-candle patterns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PatternConfig {
+    /// The largest body of a doji as a fraction of its range. Defaults to `0.1`.
+    pub doji_max_body_ratio: f64,
+    /// The shortest lower shadow of a hammer as a multiple of its body. Defaults to
+    /// `2.0`.
+    pub hammer_min_shadow_ratio: f64,
+    /// The longest upper shadow of a hammer as a multiple of its body. Defaults to
+    /// `0.5`.
+    pub hammer_max_upper_ratio: f64,
+}
+
+impl Default for PatternConfig {
+    fn default() -> Self {
+        Self {
+            doji_max_body_ratio: 0.1,
+            hammer_min_shadow_ratio: 2.0,
+            hammer_max_upper_ratio: 0.5,
+        }
+    }
+}
+
+/// A pattern completed by a candle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternEvent {
+    /// The detected pattern.
+    pub pattern: Pattern,
+    /// The start of the candle completing the pattern.
+    pub timestamp: DateTime<Tz>,
+    /// The instrument ID of the candle.
+    pub instrument_id: u32,
+    /// The symbol of the candle.
+    pub symbol: Arc<str>,
+}
+
+/// Detects patterns in candles as they're pushed, tracking the previous candle of each
+/// instrument.
+#[derive(Debug, Clone)]
+pub struct PatternDetector<P = f64> {
+    config: PatternConfig,
+    prev: HashMap<u32, Candle<P>>,
+}
+
+impl<P: CandlePrice> Default for PatternDetector<P> {
+    fn default() -> Self {
+        Self::new(PatternConfig::default())
+    }
+}
+
+impl<P: CandlePrice> PatternDetector<P> {
+    /// Creates a detector with the thresholds in `config`.
+    pub fn new(config: PatternConfig) -> Self {
+        Self {
+            config,
+            prev: HashMap::new(),
+        }
+    }
+
+    /// Returns the patterns completed by `candle`, which must be later than the
+    /// previous candle pushed for its instrument.
+    pub fn push(&mut self, candle: &Candle<P>) -> Vec<PatternEvent> {
+        let bar = Bar::new(candle);
+        let mut patterns = Vec::new();
+        if bar.is_doji(&self.config) {
+            patterns.push(Pattern::Doji);
+        }
+        if bar.is_hammer(&self.config) {
+            patterns.push(Pattern::Hammer);
+        }
+        if let Some(prev) = self.prev.get(&candle.instrument_id).map(Bar::new) {
+            if bar.engulfs(&prev) {
+                patterns.push(if bar.close > bar.open {
+                    Pattern::BullishEngulfing
+                } else {
+                    Pattern::BearishEngulfing
+                });
+            }
+            if bar.is_inside(&prev) {
+                patterns.push(Pattern::InsideBar);
+            }
+        }
+        self.prev.insert(candle.instrument_id, candle.clone());
+        patterns
+            .into_iter()
+            .map(|pattern| PatternEvent {
+                pattern,
+                timestamp: candle.timestamp,
+                instrument_id: candle.instrument_id,
+                symbol: candle.symbol.clone(),
+            })
+            .collect()
+    }
+
+    /// Forgets the previous candles, e.g. between sessions.
+    pub fn reset(&mut self) {
+        self.prev.clear();
+    }
+}
+
+/// Returns the patterns in `candles`, which must be in timestamp order, in the order
+/// they were completed.
+pub fn detect_patterns<P: CandlePrice>(
+    candles: &[Candle<P>],
+    config: PatternConfig,
+) -> Vec<PatternEvent> {
+    let mut detector = PatternDetector::new(config);
+    candles
+        .iter()
+        .flat_map(|candle| detector.push(candle))
+        .collect()
+}
+
+// The prices of a candle as `f64` for ratios
+#[derive(Debug, Clone, Copy)]
+struct Bar {
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+impl Bar {
+    fn new<P: CandlePrice>(candle: &Candle<P>) -> Self {
+        Self {
+            open: candle.open.to_f64(),
+            high: candle.high.to_f64(),
+            low: candle.low.to_f64(),
+            close: candle.close.to_f64(),
+        }
+    }
+
+    fn body(&self) -> f64 {
+        (self.close - self.open).abs()
+    }
+
+    fn range(&self) -> f64 {
+        self.high - self.low
+    }
+
+    fn is_doji(&self, config: &PatternConfig) -> bool {
+        self.range() > 0.0 && self.body() <= config.doji_max_body_ratio * self.range()
+    }
+
+    fn is_hammer(&self, config: &PatternConfig) -> bool {
+        let body = self.body();
+        let lower_shadow = self.open.min(self.close) - self.low;
+        let upper_shadow = self.high - self.open.max(self.close);
+        body > 0.0
+            && lower_shadow >= config.hammer_min_shadow_ratio * body
+            && upper_shadow <= config.hammer_max_upper_ratio * body
+    }
+
+    // Whether this candle's body engulfs the opposite-colored body of `prev`
+    fn engulfs(&self, prev: &Bar) -> bool {
+        let bullish = self.close > self.open && prev.close < prev.open;
+        let bearish = self.close < self.open && prev.close > prev.open;
+        let (top, bottom) = (self.open.max(self.close), self.open.min(self.close));
+        let (prev_top, prev_bottom) = (prev.open.max(prev.close), prev.open.min(prev.close));
+        (bullish || bearish)
+            && top >= prev_top
+            && bottom <= prev_bottom
+            && self.body() > prev.body()
+    }
+
+    fn is_inside(&self, prev: &Bar) -> bool {
+        self.high <= prev.high && self.low >= prev.low && self.range() < prev.range()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn candle(minute: u32, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2025, 4, 22, 9, 30 + minute))
+            .ohlc(open, high, low, close)
+            .build()
+    }
+
+    fn patterns(candles: &[Candle]) -> Vec<(u32, Pattern)> {
+        detect_patterns(candles, PatternConfig::default())
+            .into_iter()
+            .map(|event| (event.timestamp.minute() - 30, event.pattern))
+            .collect()
+    }
+
+    #[test]
+    fn test_engulfing() {
+        let candles = [
+            candle(0, 5302.0, 5303.0, 5299.0, 5300.0),
+            candle(1, 5299.5, 5304.0, 5299.0, 5303.0),
+            candle(2, 5303.5, 5304.0, 5298.0, 5298.5),
+        ];
+        assert_eq!(
+            patterns(&candles),
+            [
+                (1, Pattern::BullishEngulfing),
+                (2, Pattern::BearishEngulfing)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_candle_patterns() {
+        let candles = [
+            candle(0, 5300.0, 5302.0, 5298.0, 5300.25),
+            candle(1, 5300.0, 5301.25, 5296.0, 5301.0),
+        ];
+        assert_eq!(
+            patterns(&candles),
+            [(0, Pattern::Doji), (1, Pattern::Hammer)]
+        );
+    }
+
+    #[test]
+    fn test_inside_bar() {
+        let mut detector = PatternDetector::default();
+        let mother = candle(0, 5300.0, 5310.0, 5290.0, 5305.0);
+        assert!(detector.push(&mother).is_empty());
+        // Another instrument doesn't affect the first
+        let other = Candle {
+            instrument_id: 2,
+            ..candle(1, 5300.0, 5301.0, 5299.0, 5300.5)
+        };
+        assert!(detector.push(&other).is_empty());
+        let events = detector.push(&candle(1, 5304.0, 5308.0, 5295.0, 5302.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].pattern, Pattern::InsideBar);
+        assert_eq!(&*events[0].symbol, "ESM5");
+        detector.reset();
+        assert!(detector
+            .push(&candle(2, 5304.0, 5306.0, 5300.0, 5302.0))
+            .is_empty());
+    }
+}
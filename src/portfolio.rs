@@ -0,0 +1,326 @@
+//! Tracking positions and profit and loss (PnL).
+//!
+//! A [`Position`] accumulates fills into a net quantity, average entry price, and
+//! realized PnL in price points. A [`PnLTracker`] pairs a position with the
+//! [`InstrumentSpec`] of its instrument, e.g. from an [`InstrumentDefMsg`], and marks
+//! it to live trades or candles to report realized and unrealized PnL in ticks and
+//! dollars.
+
+use dbn::{InstrumentDefMsg, TradeMsg, UNDEF_PRICE};
+
+use crate::{backtest::OrderSide, examples::es_futures_pmz::Candle};
+
+/// The tick size and contract multiplier of an instrument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstrumentSpec {
+    /// The minimum price increment.
+    pub tick_size: f64,
+    /// The value of a one-point price move for one contract, e.g. 50 for E-mini S&P
+    /// 500 futures.
+    pub multiplier: f64,
+}
+
+impl InstrumentSpec {
+    /// Creates a spec with `tick_size` and `multiplier`.
+    pub fn new(tick_size: f64, multiplier: f64) -> Self {
+        Self {
+            tick_size,
+            multiplier,
+        }
+    }
+
+    /// Creates a spec from the `min_price_increment` and `unit_of_measure_qty` of
+    /// `definition`. When the unit of measure quantity is undefined, the multiplier is
+    /// derived from the value of a tick in `min_price_increment_amount`, and otherwise
+    /// defaults to 1.
+    pub fn from_definition(definition: &InstrumentDefMsg) -> Self {
+        let tick_size = to_price(definition.min_price_increment).unwrap_or(0.0);
+        let multiplier = to_price(definition.unit_of_measure_qty)
+            .or_else(|| {
+                to_price(definition.min_price_increment_amount)
+                    .filter(|_| tick_size > 0.0)
+                    .map(|tick_value| tick_value / tick_size)
+            })
+            .unwrap_or(1.0);
+        Self::new(tick_size, multiplier)
+    }
+
+    /// Converts `points` to ticks. Returns 0 if the tick size is unknown.
+    pub fn to_ticks(self, points: f64) -> f64 {
+        if self.tick_size > 0.0 {
+            points / self.tick_size
+        } else {
+            0.0
+        }
+    }
+
+    /// Converts `points` to dollars, or the instrument's currency.
+    pub fn to_dollars(self, points: f64) -> f64 {
+        points * self.multiplier
+    }
+}
+
+/// A net position in one instrument.
+///
+/// Fills that reduce the position realize PnL against the average entry price, and
+/// fills that flip it from long to short or vice versa open the remainder at the fill
+/// price. PnL is in price points summed over contracts.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Position {
+    quantity: i64,
+    average_price: f64,
+    realized: f64,
+}
+
+impl Position {
+    /// Creates a flat position.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the net quantity, negative when short.
+    pub fn quantity(&self) -> i64 {
+        self.quantity
+    }
+
+    /// Returns `true` if there's no open position.
+    pub fn is_flat(&self) -> bool {
+        self.quantity == 0
+    }
+
+    /// Returns the average entry price of the open position, or `None` if it's flat.
+    pub fn average_price(&self) -> Option<f64> {
+        (!self.is_flat()).then_some(self.average_price)
+    }
+
+    /// Returns the realized PnL in points.
+    pub fn realized_points(&self) -> f64 {
+        self.realized
+    }
+
+    /// Returns the unrealized PnL in points of the open position marked at `price`.
+    pub fn unrealized_points(&self, price: f64) -> f64 {
+        (price - self.average_price) * self.quantity as f64
+    }
+
+    /// Applies a fill of `quantity` contracts at `price`, returning the PnL in points
+    /// it realized.
+    pub fn apply_fill(&mut self, side: OrderSide, quantity: u32, price: f64) -> f64 {
+        let delta = match side {
+            OrderSide::Buy => i64::from(quantity),
+            OrderSide::Sell => -i64::from(quantity),
+        };
+        let mut realized = 0.0;
+        if self.quantity != 0 && self.quantity.signum() != delta.signum() {
+            // Closing some or all of the position
+            let closed = delta.abs().min(self.quantity.abs());
+            realized = (price - self.average_price) * (closed * self.quantity.signum()) as f64;
+            self.realized += realized;
+        }
+        let new_quantity = self.quantity + delta;
+        if new_quantity == 0 {
+            self.average_price = 0.0;
+        } else if self.quantity == 0 || new_quantity.signum() != self.quantity.signum() {
+            // Opened or flipped
+            self.average_price = price;
+        } else if new_quantity.abs() > self.quantity.abs() {
+            // Added to the position
+            self.average_price = (self.average_price * self.quantity as f64 + price * delta as f64)
+                / new_quantity as f64;
+        }
+        self.quantity = new_quantity;
+        realized
+    }
+}
+
+/// A snapshot of a [`PnLTracker`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PnL {
+    /// The net quantity, negative when short.
+    pub position: i64,
+    /// The average entry price of the open position, or `None` if it's flat.
+    pub average_price: Option<f64>,
+    /// The latest price the position was marked at, if any.
+    pub mark_price: Option<f64>,
+    /// The realized PnL in ticks.
+    pub realized_ticks: f64,
+    /// The realized PnL in dollars.
+    pub realized_dollars: f64,
+    /// The unrealized PnL in ticks, 0 when there's no mark price.
+    pub unrealized_ticks: f64,
+    /// The unrealized PnL in dollars, 0 when there's no mark price.
+    pub unrealized_dollars: f64,
+}
+
+impl PnL {
+    /// Returns the realized plus unrealized PnL in dollars.
+    pub fn total_dollars(&self) -> f64 {
+        self.realized_dollars + self.unrealized_dollars
+    }
+}
+
+/// Tracks the position and PnL of one instrument, marked to live trades or candles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnLTracker {
+    instrument_id: u32,
+    spec: InstrumentSpec,
+    position: Position,
+    mark_price: Option<f64>,
+}
+
+impl PnLTracker {
+    /// Creates a tracker for the instrument with `instrument_id` and `spec`.
+    pub fn new(instrument_id: u32, spec: InstrumentSpec) -> Self {
+        Self {
+            instrument_id,
+            spec,
+            position: Position::new(),
+            mark_price: None,
+        }
+    }
+
+    /// Creates a tracker for the instrument of `definition`.
+    pub fn from_definition(definition: &InstrumentDefMsg) -> Self {
+        Self::new(
+            definition.hd.instrument_id,
+            InstrumentSpec::from_definition(definition),
+        )
+    }
+
+    /// Returns the instrument ID of the tracked instrument.
+    pub fn instrument_id(&self) -> u32 {
+        self.instrument_id
+    }
+
+    /// Returns the instrument's tick size and multiplier.
+    pub fn spec(&self) -> &InstrumentSpec {
+        &self.spec
+    }
+
+    /// Returns the position.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Applies a fill of `quantity` contracts at `price` and marks the position at it.
+    pub fn on_fill(&mut self, side: OrderSide, quantity: u32, price: f64) {
+        self.position.apply_fill(side, quantity, price);
+        self.mark_price = Some(price);
+    }
+
+    /// Marks the position at the price of `trade`. Trades in other instruments are
+    /// ignored.
+    pub fn on_trade(&mut self, trade: &TradeMsg) {
+        if trade.hd.instrument_id == self.instrument_id {
+            self.mark(trade.price as f64 * 1e-9);
+        }
+    }
+
+    /// Marks the position at the close of `candle`. Candles of other instruments are
+    /// ignored.
+    pub fn on_candle(&mut self, candle: &Candle) {
+        if candle.instrument_id == self.instrument_id {
+            self.mark(candle.close);
+        }
+    }
+
+    /// Marks the position at `price`.
+    pub fn mark(&mut self, price: f64) {
+        self.mark_price = Some(price);
+    }
+
+    /// Returns the current position and PnL.
+    pub fn pnl(&self) -> PnL {
+        let realized = self.position.realized_points();
+        let unrealized = self
+            .mark_price
+            .map_or(0.0, |price| self.position.unrealized_points(price));
+        PnL {
+            position: self.position.quantity(),
+            average_price: self.position.average_price(),
+            mark_price: self.mark_price,
+            realized_ticks: self.spec.to_ticks(realized),
+            realized_dollars: self.spec.to_dollars(realized),
+            unrealized_ticks: self.spec.to_ticks(unrealized),
+            unrealized_dollars: self.spec.to_dollars(unrealized),
+        }
+    }
+}
+
+fn to_price(px: i64) -> Option<f64> {
+    (px != UNDEF_PRICE && px > 0).then_some(px as f64 * 1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+
+    #[test]
+    fn test_position_fills() {
+        let mut position = Position::new();
+        assert_eq!(position.apply_fill(OrderSide::Buy, 2, 100.0), 0.0);
+        assert_eq!(position.apply_fill(OrderSide::Buy, 2, 102.0), 0.0);
+        assert_eq!(position.average_price(), Some(101.0));
+        assert_eq!(position.apply_fill(OrderSide::Sell, 1, 104.0), 3.0);
+        assert_eq!(position.quantity(), 3);
+        assert_eq!(position.average_price(), Some(101.0));
+        // Flips to short 2 at 99
+        assert_eq!(position.apply_fill(OrderSide::Sell, 5, 99.0), -6.0);
+        assert_eq!(position.quantity(), -2);
+        assert_eq!(position.average_price(), Some(99.0));
+        assert_eq!(position.unrealized_points(98.0), 2.0);
+        assert_eq!(position.apply_fill(OrderSide::Buy, 2, 97.5), 3.0);
+        assert!(position.is_flat());
+        assert_eq!(position.average_price(), None);
+        assert_eq!(position.realized_points(), 0.0);
+    }
+
+    #[test]
+    fn test_tracker_from_definition() {
+        let definition = InstrumentDefMsg {
+            hd: RecordHeader::new::<InstrumentDefMsg>(rtype::INSTRUMENT_DEF, 1, 42, 0),
+            min_price_increment: 250_000_000,
+            unit_of_measure_qty: 50_000_000_000,
+            ..Default::default()
+        };
+        let mut tracker = PnLTracker::from_definition(&definition);
+        assert_eq!(*tracker.spec(), InstrumentSpec::new(0.25, 50.0));
+        tracker.on_fill(OrderSide::Buy, 1, 5300.0);
+        tracker.on_trade(&TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 42, 0),
+            price: 5_302_500_000_000,
+            ..Default::default()
+        });
+        // Another instrument
+        tracker.on_trade(&TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 43, 0),
+            price: 1_000_000_000,
+            ..Default::default()
+        });
+        let pnl = tracker.pnl();
+        assert_eq!(pnl.mark_price, Some(5302.5));
+        assert_eq!(pnl.unrealized_ticks, 10.0);
+        assert_eq!(pnl.unrealized_dollars, 125.0);
+        tracker.on_fill(OrderSide::Sell, 1, 5301.0);
+        let pnl = tracker.pnl();
+        assert_eq!(pnl.position, 0);
+        assert_eq!(pnl.realized_ticks, 4.0);
+        assert_eq!(pnl.realized_dollars, 50.0);
+        assert_eq!(pnl.total_dollars(), 50.0);
+    }
+
+    #[test]
+    fn test_spec_from_tick_value() {
+        let definition = InstrumentDefMsg {
+            min_price_increment: 250_000_000,
+            min_price_increment_amount: 12_500_000_000,
+            ..Default::default()
+        };
+        assert_eq!(
+            InstrumentSpec::from_definition(&definition),
+            InstrumentSpec::new(0.25, 50.0)
+        );
+    }
+}
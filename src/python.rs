@@ -0,0 +1,264 @@
+//! Python bindings for the PMZ calculation and candles through PyO3.
+//!
+//! Build the `databento_pmz` extension module with [maturin](https://www.maturin.rs/)
+//! from the repository root: `maturin develop --release`.
+//!
+//! ```python
+//! import datetime as dt
+//! import databento_pmz
+//!
+//! pmz = databento_pmz.calculate_pmz(API_KEY, dt.date(2025, 4, 22))
+//! candles = databento_pmz.fetch_candles(
+//!     API_KEY,
+//!     "ES.c.0",
+//!     dt.datetime(2025, 4, 22, 13, 30, tzinfo=dt.timezone.utc),
+//!     dt.datetime(2025, 4, 22, 20, 0, tzinfo=dt.timezone.utc),
+//! )
+//! bars = databento_pmz.aggregate_candles(candles, 5)
+//! ```
+
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use chrono_tz::Tz;
+use dbn::SType;
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use time::OffsetDateTime;
+use tokio::runtime::Runtime;
+
+use crate::{
+    examples::es_futures_pmz::{self, Candle, PmzResult},
+    timeconv::ToOffsetDateTime,
+    HistoricalClient,
+};
+
+create_exception!(
+    databento_pmz,
+    PmzError,
+    PyException,
+    "Raised when a PMZ calculation or candle request fails."
+);
+
+/// PMZ values for a trading day. Components that couldn't be calculated are `None`
+/// and listed in `missing`.
+#[pyclass(name = "PmzResult", module = "databento_pmz", frozen, get_all)]
+#[derive(Debug, Clone)]
+struct PyPmzResult {
+    date: NaiveDate,
+    pmh: Option<f64>,
+    pml: Option<f64>,
+    prev_day_lis: Option<f64>,
+    close_925: Option<f64>,
+    is_gap_up: Option<bool>,
+    gap_points: Option<f64>,
+    gap_percent: Option<f64>,
+    pmz_high: Option<f64>,
+    pmz_low: Option<f64>,
+    risk: Option<f64>,
+    missing: Vec<&'static str>,
+}
+
+#[pymethods]
+impl PyPmzResult {
+    /// Returns `True` if every component was calculated.
+    fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl From<PmzResult> for PyPmzResult {
+    fn from(res: PmzResult) -> Self {
+        Self {
+            date: res.date,
+            pmh: res.pmh,
+            pml: res.pml,
+            prev_day_lis: res.prev_day_lis,
+            close_925: res.close_925,
+            is_gap_up: res.is_gap_up,
+            gap_points: res.gap_points,
+            gap_percent: res.gap_percent,
+            pmz_high: res.pmz_high,
+            pmz_low: res.pmz_low,
+            risk: res.risk,
+            missing: res.missing.iter().map(|c| c.as_str()).collect(),
+        }
+    }
+}
+
+/// An OHLCV candle with a timezone-aware timestamp.
+#[pyclass(name = "Candle", module = "databento_pmz", frozen)]
+#[derive(Debug, Clone)]
+struct PyCandle(Candle);
+
+#[pymethods]
+impl PyCandle {
+    #[getter]
+    fn timestamp(&self) -> DateTime<FixedOffset> {
+        self.0.timestamp.fixed_offset()
+    }
+
+    #[getter]
+    fn instrument_id(&self) -> u32 {
+        self.0.instrument_id
+    }
+
+    #[getter]
+    fn symbol(&self) -> &str {
+        &self.0.symbol
+    }
+
+    #[getter]
+    fn open(&self) -> f64 {
+        self.0.open
+    }
+
+    #[getter]
+    fn high(&self) -> f64 {
+        self.0.high
+    }
+
+    #[getter]
+    fn low(&self) -> f64 {
+        self.0.low
+    }
+
+    #[getter]
+    fn close(&self) -> f64 {
+        self.0.close
+    }
+
+    #[getter]
+    fn volume(&self) -> u64 {
+        self.0.volume
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Candle(timestamp={}, symbol={}, open={}, high={}, low={}, close={}, volume={})",
+            self.0.timestamp.to_rfc3339(),
+            self.0.symbol,
+            self.0.open,
+            self.0.high,
+            self.0.low,
+            self.0.close,
+            self.0.volume
+        )
+    }
+}
+
+/// Calculates PMZ values for `date`, defaulting to today.
+#[pyfunction]
+#[pyo3(signature = (api_key, date=None))]
+fn calculate_pmz(py: Python<'_>, api_key: &str, date: Option<NaiveDate>) -> PyResult<PyPmzResult> {
+    let runtime = runtime()?;
+    let res = py
+        .allow_threads(|| runtime.block_on(es_futures_pmz::calculate_pmz(api_key, date, |_| {})))
+        .map_err(to_py_err)?;
+    Ok(res.into())
+}
+
+/// Fetches 1-minute candles for `symbol` between the timezone-aware datetimes `start`
+/// (inclusive) and `end` (exclusive), aggregated to `interval_minutes` candles with
+/// timestamps in `tz`. The symbology type is inferred from `symbol` unless `stype_in`
+/// is passed.
+#[pyfunction]
+#[pyo3(signature = (
+    api_key,
+    symbol,
+    start,
+    end,
+    interval_minutes=1,
+    dataset="GLBX.MDP3",
+    tz="America/New_York",
+    stype_in=None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn fetch_candles(
+    py: Python<'_>,
+    api_key: String,
+    symbol: &str,
+    start: DateTime<FixedOffset>,
+    end: DateTime<FixedOffset>,
+    interval_minutes: u32,
+    dataset: &str,
+    tz: &str,
+    stype_in: Option<&str>,
+) -> PyResult<Vec<PyCandle>> {
+    let tz = Tz::from_str(tz).map_err(|e| PyValueError::new_err(format!("invalid tz: {e}")))?;
+    let stype_in = match stype_in {
+        Some(stype_in) => SType::from_str(stype_in)
+            .map_err(|e| PyValueError::new_err(format!("invalid stype_in: {e}")))?,
+        None => es_futures_pmz::infer_stype(symbol),
+    };
+    let range = (to_offset_date_time(start)?, to_offset_date_time(end)?);
+    let mut client = HistoricalClient::builder()
+        .key(api_key)
+        .and_then(|builder| builder.build())
+        .map_err(|e| PmzError::new_err(e.to_string()))?;
+    let runtime = runtime()?;
+    let candles = py
+        .allow_threads(|| {
+            runtime.block_on(es_futures_pmz::fetch_candles(
+                &mut client,
+                dataset,
+                symbol,
+                stype_in,
+                range,
+                interval_minutes,
+                tz,
+            ))
+        })
+        .map_err(to_py_err)?;
+    Ok(candles.into_iter().map(PyCandle).collect())
+}
+
+/// Aggregates 1-minute `candles` into `interval_minutes` candles aligned to the local
+/// clock of each candle's timezone.
+#[pyfunction]
+fn aggregate_candles(candles: Vec<PyCandle>, interval_minutes: u32) -> PyResult<Vec<PyCandle>> {
+    if interval_minutes == 0 {
+        return Err(PyValueError::new_err("interval_minutes must be at least 1"));
+    }
+    let candles: Vec<Candle> = candles.into_iter().map(|c| c.0).collect();
+    Ok(
+        es_futures_pmz::aggregate_candles(&candles, interval_minutes)
+            .into_iter()
+            .map(PyCandle)
+            .collect(),
+    )
+}
+
+#[pymodule]
+fn databento_pmz(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("PmzError", m.py().get_type::<PmzError>())?;
+    m.add_class::<PyPmzResult>()?;
+    m.add_class::<PyCandle>()?;
+    m.add_function(wrap_pyfunction!(calculate_pmz, m)?)?;
+    m.add_function(wrap_pyfunction!(fetch_candles, m)?)?;
+    m.add_function(wrap_pyfunction!(aggregate_candles, m)?)?;
+    Ok(())
+}
+
+fn runtime() -> PyResult<Runtime> {
+    Runtime::new().map_err(|e| PyRuntimeError::new_err(format!("failed to create runtime: {e}")))
+}
+
+fn to_offset_date_time(dt: DateTime<FixedOffset>) -> PyResult<OffsetDateTime> {
+    dt.to_offset_date_time()
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn to_py_err(err: es_futures_pmz::PmzError) -> PyErr {
+    match err {
+        es_futures_pmz::PmzError::InvalidDate(_) => PyValueError::new_err(err.to_string()),
+        _ => PmzError::new_err(err.to_string()),
+    }
+}
@@ -0,0 +1,221 @@
+//! Data-quality checks for candle series.
+//!
+//! [`check_candles()`] scans a series for missing intervals, zero-volume bars, and
+//! out-of-order or duplicate timestamps. Note that OHLCV schemas omit intervals without
+//! any trades, so gaps in illiquid periods aren't necessarily missing data.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+
+use crate::examples::es_futures_pmz::{Candle, CandlePrice};
+
+/// A run of missing candles for an instrument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    /// The instrument ID of the candles around the gap.
+    pub instrument_id: u32,
+    /// The start of the first missing interval.
+    pub start: DateTime<Tz>,
+    /// The end of the last missing interval (exclusive), i.e. the start of the next
+    /// candle or the end of the checked range.
+    pub end: DateTime<Tz>,
+    /// The number of missing intervals.
+    pub missing: u64,
+}
+
+/// The result of checking a candle series. Indices refer to the checked slice.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GapReport {
+    /// The number of candles checked.
+    pub candle_count: usize,
+    /// Runs of missing intervals, in the order they were found.
+    pub gaps: Vec<Gap>,
+    /// Indices of candles with zero volume.
+    pub zero_volume: Vec<usize>,
+    /// Indices of candles with the same instrument and timestamp as an earlier candle.
+    pub duplicates: Vec<usize>,
+    /// Indices of candles with an earlier timestamp than the preceding candle for the
+    /// same instrument.
+    pub out_of_order: Vec<usize>,
+}
+
+impl GapReport {
+    /// Returns `true` if no issues were found.
+    pub fn is_clean(&self) -> bool {
+        self.gaps.is_empty()
+            && self.zero_volume.is_empty()
+            && self.duplicates.is_empty()
+            && self.out_of_order.is_empty()
+    }
+
+    /// Returns the total number of missing intervals across all gaps.
+    pub fn missing_intervals(&self) -> u64 {
+        self.gaps.iter().map(|gap| gap.missing).sum()
+    }
+}
+
+/// Checks `candles` spaced `interval` apart for gaps between consecutive candles of
+/// each instrument, zero-volume candles, and out-of-order or duplicate timestamps.
+pub fn check_candles<P: CandlePrice>(candles: &[Candle<P>], interval: Duration) -> GapReport {
+    check_impl(candles, interval, None)
+}
+
+/// Like [`check_candles()`], but also reports missing intervals between `start` and
+/// the first candle and between the last candle and `end` for each instrument.
+/// Candles outside `start..end` are ignored when checking for gaps.
+pub fn check_candles_within<P: CandlePrice>(
+    candles: &[Candle<P>],
+    interval: Duration,
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+) -> GapReport {
+    let mut report = check_impl(candles, interval, Some((start, end)));
+    if candles.is_empty() && end > start {
+        // No instruments to attribute the gap to
+        report.gaps.push(Gap {
+            instrument_id: 0,
+            start,
+            end,
+            missing: missing_between(start, end, interval),
+        });
+    }
+    report
+}
+
+fn check_impl<P: CandlePrice>(
+    candles: &[Candle<P>],
+    interval: Duration,
+    range: Option<(DateTime<Tz>, DateTime<Tz>)>,
+) -> GapReport {
+    let mut report = GapReport {
+        candle_count: candles.len(),
+        ..Default::default()
+    };
+    // The latest timestamp seen for each instrument
+    let mut latest: HashMap<u32, DateTime<Tz>> = HashMap::new();
+    // Instruments in order of first appearance
+    let mut instrument_ids = Vec::new();
+    for (idx, candle) in candles.iter().enumerate() {
+        if candle.volume == 0 {
+            report.zero_volume.push(idx);
+        }
+        if range.is_some_and(|(start, end)| candle.timestamp < start || candle.timestamp >= end) {
+            continue;
+        }
+        match latest.get(&candle.instrument_id).copied() {
+            Some(prev) if candle.timestamp == prev => report.duplicates.push(idx),
+            Some(prev) if candle.timestamp < prev => report.out_of_order.push(idx),
+            Some(prev) => {
+                let missing = missing_between(prev + interval, candle.timestamp, interval);
+                if missing > 0 {
+                    report.gaps.push(Gap {
+                        instrument_id: candle.instrument_id,
+                        start: prev + interval,
+                        end: candle.timestamp,
+                        missing,
+                    });
+                }
+                latest.insert(candle.instrument_id, candle.timestamp);
+            }
+            None => {
+                if let Some((start, _)) = range {
+                    let missing = missing_between(start, candle.timestamp, interval);
+                    if missing > 0 {
+                        report.gaps.push(Gap {
+                            instrument_id: candle.instrument_id,
+                            start,
+                            end: candle.timestamp,
+                            missing,
+                        });
+                    }
+                }
+                instrument_ids.push(candle.instrument_id);
+                latest.insert(candle.instrument_id, candle.timestamp);
+            }
+        }
+    }
+    if let Some((_, end)) = range {
+        for instrument_id in instrument_ids {
+            let gap_start = latest[&instrument_id] + interval;
+            let missing = missing_between(gap_start, end, interval);
+            if missing > 0 {
+                report.gaps.push(Gap {
+                    instrument_id,
+                    start: gap_start,
+                    end,
+                    missing,
+                });
+            }
+        }
+    }
+    report
+}
+
+// The number of whole intervals from `start` to `end`
+fn missing_between(start: DateTime<Tz>, end: DateTime<Tz>, interval: Duration) -> u64 {
+    let (Some(span), Some(interval)) = (
+        (end - start).num_nanoseconds(),
+        interval.num_nanoseconds().filter(|&i| i > 0),
+    ) else {
+        return 0;
+    };
+    (span.max(0) / interval) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn candle(instrument_id: u32, minute: u32, volume: u64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2025, 4, 22, 7, minute))
+            .instrument_id(instrument_id)
+            .ohlc(5300.0, 5301.0, 5299.0, 5300.5)
+            .volume(volume)
+            .build()
+    }
+
+    #[test]
+    fn test_check_candles() {
+        let candles = [
+            candle(1, 25, 10),
+            candle(1, 26, 0),
+            candle(2, 26, 5),
+            candle(1, 30, 3),
+            candle(1, 30, 3),
+            candle(1, 29, 3),
+        ];
+        let report = check_candles(&candles, Duration::minutes(1));
+        assert_eq!(
+            report.gaps,
+            vec![Gap {
+                instrument_id: 1,
+                start: candle(1, 27, 0).timestamp,
+                end: candle(1, 30, 0).timestamp,
+                missing: 3,
+            }]
+        );
+        assert_eq!(report.zero_volume, vec![1]);
+        assert_eq!(report.duplicates, vec![4]);
+        assert_eq!(report.out_of_order, vec![5]);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_check_candles_within() {
+        let candles: Vec<_> = (27..33).map(|minute| candle(1, minute, 1)).collect();
+        let start = candle(1, 25, 0).timestamp;
+        let end = candle(1, 35, 0).timestamp;
+        let report = check_candles_within(&candles, Duration::minutes(1), start, end);
+        assert_eq!(report.gaps.len(), 2);
+        assert_eq!(report.gaps[0].missing, 2);
+        assert_eq!(report.gaps[1].start, candle(1, 33, 0).timestamp);
+        assert_eq!(report.missing_intervals(), 4);
+
+        let report = check_candles_within::<f64>(&[], Duration::minutes(1), start, end);
+        assert_eq!(report.missing_intervals(), 10);
+    }
+}
@@ -0,0 +1,226 @@
+//! Tracking the best bid and offer of each instrument.
+//!
+//! A [`QuoteBoard`] keeps the latest top of book from [`Mbp1Msg`] and [`BboMsg`]
+//! records per instrument, including the initial snapshot of a live session, and maps
+//! symbols to instruments from [`SymbolMappingMsg`] records so quotes can be looked up
+//! by symbol.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use dbn::{BboMsg, BidAskPair, Mbp1Msg, RecordRef, SymbolMappingMsg, UNDEF_PRICE};
+
+/// The best bid and offer of an instrument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quote {
+    /// The instrument ID.
+    pub instrument_id: u32,
+    /// The best bid price, or `None` if there's no bid.
+    pub bid_px: Option<f64>,
+    /// The best offer price, or `None` if there's no offer.
+    pub ask_px: Option<f64>,
+    /// The total quantity at the best bid.
+    pub bid_sz: u32,
+    /// The total quantity at the best offer.
+    pub ask_sz: u32,
+    /// When the exchange published the update.
+    pub ts_event: DateTime<Utc>,
+    /// When Databento received the update.
+    pub ts_recv: DateTime<Utc>,
+}
+
+impl Quote {
+    fn new(instrument_id: u32, level: &BidAskPair, ts_event: u64, ts_recv: u64) -> Self {
+        Self {
+            instrument_id,
+            bid_px: to_price(level.bid_px),
+            ask_px: to_price(level.ask_px),
+            bid_sz: level.bid_sz,
+            ask_sz: level.ask_sz,
+            ts_event: DateTime::from_timestamp_nanos(ts_event as i64),
+            ts_recv: DateTime::from_timestamp_nanos(ts_recv as i64),
+        }
+    }
+
+    /// Returns the midpoint of the bid and offer, or `None` if either side is empty.
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.bid_px? + self.ask_px?) / 2.0)
+    }
+
+    /// Returns the difference between the offer and bid, or `None` if either side is
+    /// empty.
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.ask_px? - self.bid_px?)
+    }
+}
+
+/// The latest [`Quote`] of each instrument.
+///
+/// Updates older than the current quote of an instrument by `ts_recv` are ignored, so
+/// records replayed out of order don't overwrite newer quotes.
+#[derive(Debug, Clone, Default)]
+pub struct QuoteBoard {
+    quotes: HashMap<u32, Quote>,
+    symbols: HashMap<String, u32>,
+}
+
+impl QuoteBoard {
+    /// Creates an empty board.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the board from `rec` if it's an MBP-1, BBO, or symbol mapping record.
+    /// Returns `true` if the record was used.
+    pub fn on_record(&mut self, rec: &RecordRef) -> bool {
+        if let Some(mbp1) = rec.get::<Mbp1Msg>() {
+            self.on_mbp1(mbp1);
+        } else if let Some(bbo) = rec.get::<BboMsg>() {
+            self.on_bbo(bbo);
+        } else if let Some(mapping) = rec.get::<SymbolMappingMsg>() {
+            self.on_symbol_mapping(mapping);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Updates the quote of the instrument of `mbp1`.
+    pub fn on_mbp1(&mut self, mbp1: &Mbp1Msg) {
+        self.update(Quote::new(
+            mbp1.hd.instrument_id,
+            &mbp1.levels[0],
+            mbp1.hd.ts_event,
+            mbp1.ts_recv,
+        ));
+    }
+
+    /// Updates the quote of the instrument of `bbo`.
+    pub fn on_bbo(&mut self, bbo: &BboMsg) {
+        self.update(Quote::new(
+            bbo.hd.instrument_id,
+            &bbo.levels[0],
+            bbo.hd.ts_event,
+            bbo.ts_recv,
+        ));
+    }
+
+    /// Maps both the input and output symbols of `mapping` to its instrument.
+    pub fn on_symbol_mapping(&mut self, mapping: &SymbolMappingMsg) {
+        let instrument_id = mapping.hd.instrument_id;
+        for symbol in [mapping.stype_in_symbol(), mapping.stype_out_symbol()]
+            .into_iter()
+            .flatten()
+        {
+            self.insert_symbol(symbol, instrument_id);
+        }
+    }
+
+    /// Maps `symbol` to the instrument with `instrument_id`. When a symbol maps to
+    /// several instruments, such as a parent symbol, the most recent mapping wins.
+    pub fn insert_symbol(&mut self, symbol: impl ToString, instrument_id: u32) {
+        self.symbols.insert(symbol.to_string(), instrument_id);
+    }
+
+    /// Replaces the quote of `quote.instrument_id` unless the current one is newer.
+    pub fn update(&mut self, quote: Quote) {
+        self.quotes
+            .entry(quote.instrument_id)
+            .and_modify(|current| {
+                if quote.ts_recv >= current.ts_recv {
+                    *current = quote;
+                }
+            })
+            .or_insert(quote);
+    }
+
+    /// Returns the latest quote of the instrument mapped to `symbol`.
+    pub fn get(&self, symbol: &str) -> Option<&Quote> {
+        self.get_by_id(*self.symbols.get(symbol)?)
+    }
+
+    /// Returns the latest quote of the instrument with `instrument_id`.
+    pub fn get_by_id(&self, instrument_id: u32) -> Option<&Quote> {
+        self.quotes.get(&instrument_id)
+    }
+
+    /// Returns an iterator over the latest quote of every instrument in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = &Quote> {
+        self.quotes.values()
+    }
+
+    /// Returns the number of instruments with a quote.
+    pub fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    /// Returns `true` if no instrument has a quote.
+    pub fn is_empty(&self) -> bool {
+        self.quotes.is_empty()
+    }
+}
+
+fn to_price(px: i64) -> Option<f64> {
+    (px != UNDEF_PRICE).then_some(px as f64 * 1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{rtype, RecordHeader, SType};
+
+    use super::*;
+
+    fn mbp1(instrument_id: u32, bid_px: i64, ask_px: i64, ts_recv: u64) -> Mbp1Msg {
+        Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, instrument_id, ts_recv - 10),
+            ts_recv,
+            levels: [BidAskPair {
+                bid_px,
+                ask_px,
+                bid_sz: 5,
+                ask_sz: 7,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_quote_board() {
+        let mapping = SymbolMappingMsg::new(
+            1,
+            0,
+            SType::Continuous,
+            "ES.c.0",
+            SType::RawSymbol,
+            "ESM5",
+            0,
+            u64::MAX,
+        )
+        .unwrap();
+        let mut board = QuoteBoard::new();
+        assert!(board.on_record(&RecordRef::from(&mapping)));
+        assert!(board.get("ES.c.0").is_none());
+
+        let snapshot = mbp1(1, 5_300_000_000_000, 5_300_250_000_000, 100);
+        assert!(board.on_record(&RecordRef::from(&snapshot)));
+        let quote = board.get("ESM5").unwrap();
+        assert_eq!(quote.bid_px, Some(5300.0));
+        assert_eq!(quote.ask_px, Some(5300.25));
+        assert_eq!(quote.mid(), Some(5300.125));
+        assert_eq!(quote.spread(), Some(0.25));
+        assert_eq!(quote.ts_event.timestamp_nanos_opt(), Some(90));
+        assert_eq!(board.get("ES.c.0"), Some(quote));
+
+        // Stale updates are ignored
+        board.on_mbp1(&mbp1(1, 5_299_000_000_000, 5_299_250_000_000, 50));
+        assert_eq!(board.get_by_id(1).unwrap().bid_px, Some(5300.0));
+
+        board.on_mbp1(&mbp1(1, UNDEF_PRICE, 5_300_500_000_000, 200));
+        let quote = board.get_by_id(1).unwrap();
+        assert_eq!(quote.bid_px, None);
+        assert_eq!(quote.mid(), None);
+        assert_eq!(board.len(), 1);
+    }
+}
@@ -0,0 +1,191 @@
+//! Replaying records from local DBN files.
+//!
+//! [`Replay`] reads a DBN file, such as one saved with
+//! `TimeseriesClient::get_range_to_file()` or recorded from a live session, and returns its records through the same
+//! [`next_record()`](Replay::next_record) interface as the live client. Use
+//! [`Replay::open()`] to read a file whether or not it's Zstandard-compressed. Records can be
+//! returned as fast as possible or paced to their original timestamps, so code built
+//! on live data can be tested offline.
+
+use std::path::Path;
+
+use async_compression::tokio::bufread::ZstdDecoder;
+use dbn::{
+    decode::{AsyncDbnDecoder, AsyncDynReader},
+    Metadata, Record, RecordRef, UNDEF_TIMESTAMP,
+};
+use tokio::{
+    io::{AsyncReadExt, BufReader},
+    time::{Duration, Instant},
+};
+
+/// How fast [`Replay`] returns records.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReplaySpeed {
+    /// Return records as fast as they can be decoded.
+    #[default]
+    Unthrottled,
+    /// Return records with the same spacing as their index timestamps.
+    RealTime,
+    /// Return records this many times faster than real time, e.g. `60.0` replays an
+    /// hour in a minute.
+    Accelerated(f64),
+}
+
+/// Returns records from a DBN file through the same interface as the live client.
+pub struct Replay<R>
+where
+    R: AsyncReadExt + Unpin,
+{
+    decoder: AsyncDbnDecoder<R>,
+    pacer: Pacer,
+}
+
+impl Replay<BufReader<tokio::fs::File>> {
+    /// Opens the uncompressed DBN file at `path`.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to open the file or decode its
+    /// metadata.
+    pub async fn from_file(
+        path: impl AsRef<Path>,
+        speed: ReplaySpeed,
+    ) -> crate::Result<Replay<BufReader<tokio::fs::File>>> {
+        Ok(Replay::new(AsyncDbnDecoder::from_file(path).await?, speed))
+    }
+}
+
+impl Replay<ZstdDecoder<BufReader<tokio::fs::File>>> {
+    /// Opens the Zstandard-compressed DBN file at `path`, e.g. a `.dbn.zst` file.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to open the file or decode its
+    /// metadata.
+    pub async fn from_zstd_file(
+        path: impl AsRef<Path>,
+        speed: ReplaySpeed,
+    ) -> crate::Result<Replay<ZstdDecoder<BufReader<tokio::fs::File>>>> {
+        Ok(Replay::new(
+            AsyncDbnDecoder::from_zstd_file(path).await?,
+            speed,
+        ))
+    }
+}
+
+impl Replay<AsyncDynReader<BufReader<tokio::fs::File>>> {
+    /// Opens the DBN file at `path`, decompressing it as it's read if it's
+    /// Zstandard-compressed.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to open the file or decode its
+    /// metadata.
+    pub async fn open(
+        path: impl AsRef<Path>,
+        speed: ReplaySpeed,
+    ) -> crate::Result<Replay<AsyncDynReader<BufReader<tokio::fs::File>>>> {
+        let reader = AsyncDynReader::from_file(path).await?;
+        Ok(Replay::new(AsyncDbnDecoder::new(reader).await?, speed))
+    }
+}
+
+impl<R: AsyncReadExt + Unpin> Replay<R> {
+    /// Creates a replay of the records from `decoder`.
+    pub fn new(decoder: AsyncDbnDecoder<R>, speed: ReplaySpeed) -> Self {
+        Self {
+            decoder,
+            pacer: Pacer::new(speed),
+        }
+    }
+
+    /// Returns the metadata of the file.
+    pub fn metadata(&self) -> &Metadata {
+        self.decoder.metadata()
+    }
+
+    /// Returns the next record, waiting until it's due according to the
+    /// [`ReplaySpeed`]. Returns `Ok(None)` at the end of the file.
+    ///
+    /// # Errors
+    /// This function returns an error when it's unable to read from the file or decode
+    /// the next record.
+    pub async fn next_record(&mut self) -> crate::Result<Option<RecordRef<'_>>> {
+        let Some(rec) = self.decoder.decode_record_ref().await? else {
+            return Ok(None);
+        };
+        if let Some(deadline) = self.pacer.deadline(rec.raw_index_ts()) {
+            tokio::time::sleep_until(deadline).await;
+        }
+        Ok(Some(rec))
+    }
+}
+
+#[derive(Debug)]
+struct Pacer {
+    // How many times faster than real time to replay
+    speedup: Option<f64>,
+    // The index timestamp and the instant of the first paced record
+    origin: Option<(u64, Instant)>,
+}
+
+impl Pacer {
+    fn new(speed: ReplaySpeed) -> Self {
+        let speedup = match speed {
+            ReplaySpeed::Unthrottled => None,
+            ReplaySpeed::RealTime => Some(1.0),
+            ReplaySpeed::Accelerated(multiplier) => Some(multiplier).filter(|m| *m > 0.0),
+        };
+        Self {
+            speedup,
+            origin: None,
+        }
+    }
+
+    // Returns when a record with `index_ts` is due, or `None` if it's due immediately
+    fn deadline(&mut self, index_ts: u64) -> Option<Instant> {
+        let speedup = self.speedup?;
+        if index_ts == UNDEF_TIMESTAMP {
+            return None;
+        }
+        let (origin_ts, origin) = *self.origin.get_or_insert((index_ts, Instant::now()));
+        let elapsed = index_ts.checked_sub(origin_ts)? as f64 / speedup;
+        Some(origin + Duration::from_nanos(elapsed as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{OhlcvMsg, Schema};
+
+    use super::*;
+    use crate::zst_test_data_path;
+
+    #[tokio::test]
+    async fn test_replay_unthrottled() {
+        let mut replay = Replay::open(
+            zst_test_data_path(Schema::Ohlcv1M),
+            ReplaySpeed::Unthrottled,
+        )
+        .await
+        .unwrap();
+        assert_eq!(replay.metadata().schema, Some(Schema::Ohlcv1M));
+        let mut count = 0;
+        while let Some(rec) = replay.next_record().await.unwrap() {
+            assert!(rec.get::<OhlcvMsg>().is_some());
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_replay_paced() {
+        let mut pacer = Pacer::new(ReplaySpeed::Accelerated(60.0));
+        let origin = pacer.deadline(1_000_000_000).unwrap();
+        // A minute of record time takes a second
+        assert_eq!(
+            pacer.deadline(61_000_000_000).unwrap() - origin,
+            Duration::from_secs(1)
+        );
+        assert!(pacer.deadline(UNDEF_TIMESTAMP).is_none());
+        assert!(Pacer::new(ReplaySpeed::Unthrottled).deadline(1).is_none());
+    }
+}
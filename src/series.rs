@@ -0,0 +1,405 @@
+//! Time-ordered candle series.
+//!
+//! A [`CandleSeries`] keeps its candles sorted by timestamp so time ranges can be
+//! selected with a binary search with [`slice()`](CandleSeries::slice), borrowing the
+//! candles instead of filtering and cloning the whole series for every range. Queries
+//! such as [`highest_high()`](CandleSeries::highest_high) and
+//! [`close_at()`](CandleSeries::close_at) build on it, and
+//! [`resample()`](CandleSeries::resample) aggregates the series into longer candles.
+//!
+//! A [`MultiTimeframe`] keeps series of several intervals, e.g. 1, 5, 15, and 60
+//! minutes, in sync from a single feed of 1-minute candles, updating each interval's
+//! in-progress candle as the feed arrives instead of re-aggregating.
+
+use std::{
+    collections::BTreeMap,
+    ops::{Bound, RangeBounds},
+};
+
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+
+use crate::{
+    calendar::Session,
+    examples::es_futures_pmz::{
+        aggregate_candles_by, BucketAnchor, Candle, CandlePrice, LiveCandleBuilder,
+    },
+};
+
+/// Candles sorted by timestamp, usually of a single instrument. Use
+/// [`by_instrument()`](Self::by_instrument) to split candles of many instruments into
+/// a series for each.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleSeries<P = f64> {
+    candles: Vec<Candle<P>>,
+}
+
+impl<P> Default for CandleSeries<P> {
+    fn default() -> Self {
+        Self {
+            candles: Vec::new(),
+        }
+    }
+}
+
+impl<P: CandlePrice> CandleSeries<P> {
+    /// Creates a series from `candles`, sorting them by timestamp if they're out of
+    /// order. Candles with the same timestamp keep their relative order.
+    pub fn new(mut candles: Vec<Candle<P>>) -> Self {
+        if !candles.is_sorted_by_key(|candle| candle.timestamp) {
+            candles.sort_by_key(|candle| candle.timestamp);
+        }
+        Self { candles }
+    }
+
+    /// Splits `candles` into a series for each instrument ID.
+    pub fn by_instrument(candles: impl IntoIterator<Item = Candle<P>>) -> BTreeMap<u32, Self> {
+        let mut by_instrument: BTreeMap<u32, Vec<Candle<P>>> = BTreeMap::new();
+        for candle in candles {
+            by_instrument
+                .entry(candle.instrument_id)
+                .or_default()
+                .push(candle);
+        }
+        by_instrument
+            .into_iter()
+            .map(|(instrument_id, candles)| (instrument_id, Self::new(candles)))
+            .collect()
+    }
+
+    /// Adds `candle`, which is usually the latest, after any candles with the same
+    /// timestamp.
+    pub fn push(&mut self, candle: Candle<P>) {
+        if self
+            .candles
+            .last()
+            .is_none_or(|last| last.timestamp <= candle.timestamp)
+        {
+            self.candles.push(candle);
+        } else {
+            let idx = self
+                .candles
+                .partition_point(|c| c.timestamp <= candle.timestamp);
+            self.candles.insert(idx, candle);
+        }
+    }
+
+    /// Returns the candles with timestamps within `range`, found by binary search.
+    pub fn slice(&self, range: impl RangeBounds<DateTime<Tz>>) -> &[Candle<P>] {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.candles.partition_point(|c| c.timestamp < *start),
+            Bound::Excluded(start) => self.candles.partition_point(|c| c.timestamp <= *start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.candles.partition_point(|c| c.timestamp <= *end),
+            Bound::Excluded(end) => self.candles.partition_point(|c| c.timestamp < *end),
+            Bound::Unbounded => self.candles.len(),
+        };
+        // An empty or inverted range
+        self.candles.get(start..end).unwrap_or_default()
+    }
+
+    /// Returns the highest high of the candles with timestamps within `range`, or
+    /// `None` if there are none.
+    pub fn highest_high(&self, range: impl RangeBounds<DateTime<Tz>>) -> Option<P> {
+        self.slice(range)
+            .iter()
+            .map(|candle| candle.high)
+            .reduce(P::max_price)
+    }
+
+    /// Returns the lowest low of the candles with timestamps within `range`, or `None`
+    /// if there are none.
+    pub fn lowest_low(&self, range: impl RangeBounds<DateTime<Tz>>) -> Option<P> {
+        self.slice(range)
+            .iter()
+            .map(|candle| candle.low)
+            .reduce(P::min_price)
+    }
+
+    /// Returns the latest close as of `ts`, i.e. the close of the last candle starting
+    /// before `ts`, or `None` if there are none. For 1-minute candles, the close at
+    /// 09:25 is the close of the 09:24 candle.
+    pub fn close_at(&self, ts: DateTime<Tz>) -> Option<P> {
+        self.slice(..ts).last().map(|candle| candle.close)
+    }
+
+    /// Aggregates the series into candles of `interval` for each instrument, aligned to
+    /// local midnight like
+    /// [`aggregate_candles()`](crate::examples::es_futures_pmz::aggregate_candles).
+    pub fn resample(&self, interval: Duration) -> Self {
+        self.resample_by(interval, BucketAnchor::LocalMidnight)
+    }
+
+    /// Aggregates the series into candles of `interval` for each instrument, aligned
+    /// according to `anchor`.
+    pub fn resample_by(&self, interval: Duration, anchor: BucketAnchor) -> Self {
+        // Aggregated candles are already ordered by timestamp
+        Self {
+            candles: aggregate_candles_by(&self.candles, interval, anchor),
+        }
+    }
+
+    /// Returns all the candles in timestamp order.
+    pub fn as_slice(&self) -> &[Candle<P>] {
+        &self.candles
+    }
+
+    /// Returns the number of candles.
+    pub fn len(&self) -> usize {
+        self.candles.len()
+    }
+
+    /// Returns `true` if the series has no candles.
+    pub fn is_empty(&self) -> bool {
+        self.candles.is_empty()
+    }
+
+    /// Consumes the series and returns its candles in timestamp order.
+    pub fn into_vec(self) -> Vec<Candle<P>> {
+        self.candles
+    }
+}
+
+impl<P: CandlePrice> From<Vec<Candle<P>>> for CandleSeries<P> {
+    fn from(candles: Vec<Candle<P>>) -> Self {
+        Self::new(candles)
+    }
+}
+
+/// The intervals in minutes of a [`MultiTimeframe`] created with
+/// [`MultiTimeframe::new()`].
+pub const DEFAULT_TIMEFRAMES: [u32; 4] = [1, 5, 15, 60];
+
+/// Synchronized candle series of several intervals for a single instrument, built
+/// incrementally from a feed of 1-minute candles.
+///
+/// Each 1-minute candle updates the in-progress candle of every interval, so the
+/// current 15-minute candle, for example, can be queried at any time with
+/// [`current()`](Self::current). Candles are added to an interval's
+/// [`series()`](Self::series) once they're complete.
+#[derive(Debug, Clone)]
+pub struct MultiTimeframe<P = f64> {
+    // Ordered by interval
+    frames: Vec<Timeframe<P>>,
+}
+
+#[derive(Debug, Clone)]
+struct Timeframe<P> {
+    interval_minutes: u32,
+    builder: LiveCandleBuilder<P>,
+    completed: CandleSeries<P>,
+}
+
+impl<P: CandlePrice> MultiTimeframe<P> {
+    /// Creates series of the [`DEFAULT_TIMEFRAMES`] aligned to the local clock of `tz`.
+    pub fn new(tz: Tz) -> Self {
+        Self::with_intervals(DEFAULT_TIMEFRAMES, tz)
+    }
+
+    /// Creates series of `intervals_minutes` aligned to the local clock of `tz`.
+    /// Duplicate intervals are ignored and an interval of 0 is treated as 1.
+    pub fn with_intervals(intervals_minutes: impl IntoIterator<Item = u32>, tz: Tz) -> Self {
+        let mut intervals: Vec<u32> = intervals_minutes
+            .into_iter()
+            .map(|interval| interval.max(1))
+            .collect();
+        intervals.sort_unstable();
+        intervals.dedup();
+        Self {
+            frames: intervals
+                .into_iter()
+                .map(|interval_minutes| Timeframe {
+                    interval_minutes,
+                    builder: LiveCandleBuilder::new(interval_minutes, tz),
+                    completed: CandleSeries::default(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Restricts every interval to the hours of `session` like
+    /// [`LiveCandleBuilder::with_session()`], so no candle spans a break.
+    pub fn with_session(mut self, session: Session) -> Self {
+        for frame in &mut self.frames {
+            frame.builder = frame.builder.clone().with_session(session);
+        }
+        self
+    }
+
+    /// Returns the intervals in minutes in ascending order.
+    pub fn intervals(&self) -> impl Iterator<Item = u32> + '_ {
+        self.frames.iter().map(|frame| frame.interval_minutes)
+    }
+
+    /// Adds a 1-minute candle to every interval, returning the intervals whose
+    /// previous candle it completed in ascending order.
+    pub fn push(&mut self, candle: Candle<P>) -> Vec<u32> {
+        let mut completed = Vec::new();
+        for frame in &mut self.frames {
+            if let Some(prev) = frame.builder.push(candle.clone()) {
+                frame.completed.push(prev);
+                completed.push(frame.interval_minutes);
+            }
+        }
+        completed
+    }
+
+    /// Returns the in-progress candle of `interval_minutes`, or `None` if it isn't one
+    /// of the intervals or no candles have been added.
+    pub fn current(&self, interval_minutes: u32) -> Option<&Candle<P>> {
+        self.frame(interval_minutes)?.builder.current()
+    }
+
+    /// Returns the completed candles of `interval_minutes`, or `None` if it isn't one of
+    /// the intervals.
+    pub fn series(&self, interval_minutes: u32) -> Option<&CandleSeries<P>> {
+        self.frame(interval_minutes).map(|frame| &frame.completed)
+    }
+
+    /// Completes the in-progress candle of every interval, e.g. at the end of a
+    /// session.
+    pub fn flush(&mut self) {
+        for frame in &mut self.frames {
+            if let Some(current) = frame.builder.flush() {
+                frame.completed.push(current);
+            }
+        }
+    }
+
+    /// Removes the completed and in-progress candles of every interval.
+    pub fn clear(&mut self) {
+        for frame in &mut self.frames {
+            frame.builder.flush();
+            frame.completed = CandleSeries::default();
+        }
+    }
+
+    fn frame(&self, interval_minutes: u32) -> Option<&Timeframe<P>> {
+        self.frames
+            .binary_search_by_key(&interval_minutes, |frame| frame.interval_minutes)
+            .ok()
+            .map(|idx| &self.frames[idx])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn ts(minute: u32) -> DateTime<Tz> {
+        eastern(2025, 4, 22, 7, minute)
+    }
+
+    fn candle(minute: u32) -> Candle {
+        test_util::candle()
+            .timestamp(ts(minute))
+            .ohlc(5300.0, 5301.0, 5299.0, f64::from(minute))
+            .volume(1)
+            .build()
+    }
+
+    fn closes(candles: &[Candle]) -> Vec<f64> {
+        candles.iter().map(|c| c.close).collect()
+    }
+
+    #[test]
+    fn test_slice() {
+        let series = CandleSeries::new([30, 25, 26, 28].map(candle).to_vec());
+        assert_eq!(closes(series.as_slice()), [25.0, 26.0, 28.0, 30.0]);
+        assert_eq!(closes(series.slice(ts(26)..ts(30))), [26.0, 28.0]);
+        assert_eq!(closes(series.slice(ts(26)..=ts(30))), [26.0, 28.0, 30.0]);
+        assert_eq!(closes(series.slice(ts(27)..)), [28.0, 30.0]);
+        assert_eq!(closes(series.slice(..ts(26))), [25.0]);
+        assert_eq!(series.slice(..).len(), 4);
+        assert!(series.slice(ts(31)..).is_empty());
+        assert!(series.slice(ts(28)..ts(26)).is_empty());
+        // Ranges in another timezone select the same instants
+        assert_eq!(
+            closes(series.slice(ts(25).with_timezone(&Tz::UTC)..ts(26).with_timezone(&Tz::UTC))),
+            [25.0]
+        );
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut candles: Vec<Candle> = [25, 26, 27, 28, 29, 30].map(candle).to_vec();
+        candles[1].high = 5310.0;
+        candles[4].low = 5290.0;
+        let series = CandleSeries::new(candles);
+        assert_eq!(series.highest_high(ts(25)..ts(30)), Some(5310.0));
+        assert_eq!(series.highest_high(ts(27)..), Some(5301.0));
+        assert_eq!(series.lowest_low(..), Some(5290.0));
+        assert_eq!(series.lowest_low(..ts(29)), Some(5299.0));
+        assert_eq!(series.highest_high(ts(31)..), None);
+        assert_eq!(series.close_at(ts(28)), Some(27.0));
+        assert_eq!(series.close_at(ts(45)), Some(30.0));
+        assert_eq!(series.close_at(ts(25)), None);
+
+        let five_min = series.resample(Duration::minutes(5));
+        assert_eq!(five_min.len(), 2);
+        assert_eq!(five_min.as_slice()[0].timestamp, ts(25));
+        assert_eq!(five_min.as_slice()[0].high, 5310.0);
+        assert_eq!(five_min.as_slice()[0].low, 5290.0);
+        assert_eq!(five_min.as_slice()[0].close, 29.0);
+        assert_eq!(five_min.as_slice()[0].volume, 5);
+        assert_eq!(five_min.close_at(ts(35)), Some(30.0));
+    }
+
+    #[test]
+    fn test_by_instrument() {
+        let mut other = candle(26);
+        other.instrument_id = 2;
+        let by_instrument = CandleSeries::by_instrument([candle(27), other, candle(25)]);
+        assert_eq!(by_instrument.len(), 2);
+        assert_eq!(closes(by_instrument[&1].as_slice()), [25.0, 27.0]);
+        assert_eq!(closes(by_instrument[&2].as_slice()), [26.0]);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut series = CandleSeries::new(vec![candle(25), candle(27)]);
+        series.push(candle(28));
+        series.push(candle(26));
+        assert_eq!(closes(series.as_slice()), [25.0, 26.0, 27.0, 28.0]);
+    }
+
+    #[test]
+    fn test_multi_timeframe() {
+        let mut mtf = MultiTimeframe::new(Tz::America__New_York);
+        assert_eq!(mtf.intervals().collect::<Vec<_>>(), DEFAULT_TIMEFRAMES);
+        let mut completed = Vec::new();
+        for minute in 25..46 {
+            completed.push(mtf.push(candle(minute)));
+        }
+        // 07:30 completes the 07:25 5-minute and 07:15 15-minute candles
+        assert_eq!(completed[5], [1, 5, 15]);
+        assert_eq!(completed[10], [1, 5]);
+        assert_eq!(completed[20], [1, 5, 15]);
+        assert_eq!(mtf.series(1).unwrap().len(), 20);
+        assert_eq!(mtf.series(5).unwrap().len(), 4);
+        assert_eq!(mtf.series(15).unwrap().len(), 2);
+        assert_eq!(mtf.series(60).unwrap().len(), 0);
+        assert!(mtf.series(2).is_none());
+
+        let current = mtf.current(15).unwrap();
+        assert_eq!(current.timestamp, ts(45));
+        assert_eq!(current.close, 45.0);
+        let hour = mtf.current(60).unwrap();
+        assert_eq!(hour.open, 5300.0);
+        assert_eq!(hour.close, 45.0);
+        assert_eq!(hour.volume, 21);
+        let completed = &mtf.series(15).unwrap().as_slice()[1];
+        assert_eq!(completed.timestamp, ts(30));
+        assert_eq!(completed.close, 44.0);
+        assert_eq!(completed.volume, 15);
+
+        mtf.flush();
+        assert!(mtf.current(15).is_none());
+        assert_eq!(mtf.series(60).unwrap().len(), 1);
+        mtf.clear();
+        assert!(mtf.series(1).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,477 @@
+//! An HTTP service exposing PMZ values and candles as JSON.
+//!
+//! # Endpoints
+//! - `GET /pmz?date=YYYY-MM-DD`: PMZ values for a trading day, defaulting to today
+//! - `GET /candles?symbol=&interval=&start=&end=&dataset=&tz=`: OHLCV candles
+//!   aggregated from 1-minute bars. `start` and `end` default to the previous UTC day
+//!
+//! - `GET /metrics`: client metrics in the Prometheus text format, with the `metrics`
+//!   feature
+//!
+//! Errors are returned as `{"error": "..."}` with an appropriate status code.
+//!
+//! A router created with [`router_with_pool()`] serves multiple users with their own
+//! API keys, which each request passes in the `X-Api-Key` header.
+//!
+//! With the `live` feature, [`ws`] pushes live candles and pre-market updates to
+//! WebSocket subscribers.
+
+#[cfg(feature = "live")]
+pub mod ws;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::net::{TcpListener, ToSocketAddrs};
+
+use crate::{
+    config::Config,
+    examples::es_futures_pmz::{
+        calculate_pmz_with_client, fetch_candles, infer_stype, Candle, Diagnostic, PmzConfig,
+        PmzError, PmzResult, DEFAULT_CANDLE_TZ,
+    },
+    historical::ClientPool,
+    ApiKey, HistoricalClient,
+};
+
+const DEFAULT_DATASET: &str = "GLBX.MDP3";
+const KEY_HEADER: &str = "x-api-key";
+
+#[derive(Debug, Clone)]
+struct AppState {
+    clients: Clients,
+    dataset: String,
+    pmz: PmzConfig,
+}
+
+#[derive(Debug, Clone)]
+enum Clients {
+    Shared(HistoricalClient),
+    Pool(Arc<ClientPool>),
+}
+
+impl AppState {
+    fn client(&self, headers: &HeaderMap) -> Result<HistoricalClient, ErrorResponse> {
+        match &self.clients {
+            Clients::Shared(client) => Ok(client.clone()),
+            Clients::Pool(pool) => {
+                let key = headers
+                    .get(KEY_HEADER)
+                    .ok_or_else(|| ErrorResponse::unauthorized("missing X-Api-Key header"))?
+                    .to_str()
+                    .map_err(|_| ErrorResponse::unauthorized("invalid X-Api-Key header"))?
+                    .parse::<ApiKey>()
+                    .map_err(ErrorResponse::unauthorized)?;
+                Ok(pool.get(key))
+            }
+        }
+    }
+}
+
+/// Creates the router with a historical client built from `config`.
+///
+/// # Errors
+/// This function returns an error when `config` doesn't contain an API key or it fails
+/// to build the historical client.
+pub fn router(config: &Config) -> crate::Result<Router> {
+    let client = HistoricalClient::builder().config(config)?.build()?;
+    Ok(router_with_client(client, config))
+}
+
+/// Creates the router using `client` for requests to Databento and the dataset and
+/// PMZ settings from `config`.
+pub fn router_with_client(client: HistoricalClient, config: &Config) -> Router {
+    router_with_clients(Clients::Shared(client), config)
+}
+
+/// Creates the router using a client from `pool` for the API key in the `X-Api-Key`
+/// header of each request, and the dataset and PMZ settings from `config`. Requests
+/// without a valid key are rejected with `401 Unauthorized`.
+pub fn router_with_pool(pool: Arc<ClientPool>, config: &Config) -> Router {
+    router_with_clients(Clients::Pool(pool), config)
+}
+
+fn router_with_clients(clients: Clients, config: &Config) -> Router {
+    let state = AppState {
+        clients,
+        dataset: config
+            .dataset
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DATASET.to_owned()),
+        pmz: config.pmz.clone(),
+    };
+    let router = Router::new()
+        .route("/pmz", get(pmz))
+        .route("/candles", get(candles));
+    #[cfg(feature = "metrics")]
+    let router = router.route("/metrics", get(metrics));
+    router.with_state(state)
+}
+
+/// Serves the PMZ and candle endpoints on `addr` until the process is terminated.
+///
+/// # Errors
+/// This function returns an error when `config` is invalid or it fails to bind to
+/// `addr`.
+pub async fn serve(addr: impl ToSocketAddrs, config: &Config) -> crate::Result<()> {
+    let router = router(config)?;
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr: SocketAddr = listener.local_addr()?;
+    tracing::info!(%local_addr, "Serving PMZ and candles");
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct PmzQuery {
+    date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+struct PmzResponse {
+    date: NaiveDate,
+    pmh: Option<f64>,
+    pml: Option<f64>,
+    prev_day_lis: Option<f64>,
+    is_gap_up: Option<bool>,
+    gap_points: Option<f64>,
+    gap_percent: Option<f64>,
+    pmz_high: Option<f64>,
+    pmz_low: Option<f64>,
+    risk: Option<f64>,
+    missing: Vec<&'static str>,
+    diagnostics: Vec<String>,
+}
+
+impl PmzResponse {
+    fn new(res: PmzResult, diagnostics: Vec<Diagnostic>) -> Self {
+        Self {
+            date: res.date,
+            pmh: res.pmh,
+            pml: res.pml,
+            prev_day_lis: res.prev_day_lis,
+            is_gap_up: res.is_gap_up,
+            gap_points: res.gap_points,
+            gap_percent: res.gap_percent,
+            pmz_high: res.pmz_high,
+            pmz_low: res.pmz_low,
+            risk: res.risk,
+            missing: res.missing.iter().map(|c| c.as_str()).collect(),
+            diagnostics: diagnostics.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+async fn pmz(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<PmzQuery>,
+) -> Result<Json<PmzResponse>, ErrorResponse> {
+    let client = state.client(&headers)?;
+    let mut diagnostics = Vec::new();
+    let res =
+        calculate_pmz_with_client(client, &state.pmz, query.date, |d| diagnostics.push(d)).await?;
+    Ok(Json(PmzResponse::new(res, diagnostics)))
+}
+
+#[derive(Debug, Deserialize)]
+struct CandlesQuery {
+    symbol: String,
+    #[serde(default = "default_interval")]
+    interval: u32,
+    start: Option<String>,
+    end: Option<String>,
+    dataset: Option<String>,
+    tz: Option<String>,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+#[derive(Debug, Serialize)]
+struct CandleResponse {
+    timestamp: String,
+    symbol: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: u64,
+}
+
+impl From<Candle> for CandleResponse {
+    fn from(candle: Candle) -> Self {
+        Self {
+            timestamp: candle.timestamp.to_rfc3339(),
+            symbol: candle.symbol.to_string(),
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume,
+        }
+    }
+}
+
+async fn candles(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<CandlesQuery>,
+) -> Result<Json<Vec<CandleResponse>>, ErrorResponse> {
+    let mut client = state.client(&headers)?;
+    if query.interval == 0 {
+        return Err(ErrorResponse::bad_request("interval must be at least 1"));
+    }
+    let parse = |param: &str, val: &str| {
+        OffsetDateTime::parse(val, &Rfc3339)
+            .map_err(|e| ErrorResponse::bad_request(format!("invalid {param}: {e}")))
+    };
+    let start = match query.start.as_deref() {
+        Some(start) => parse("start", start)?,
+        None => OffsetDateTime::now_utc().replace_time(time::Time::MIDNIGHT) - time::Duration::DAY,
+    };
+    let end = match query.end.as_deref() {
+        Some(end) => parse("end", end)?,
+        None => start + time::Duration::DAY,
+    };
+    let tz = match query.tz.as_deref() {
+        Some(tz) => tz
+            .parse::<Tz>()
+            .map_err(|e| ErrorResponse::bad_request(format!("invalid tz: {e}")))?,
+        None => DEFAULT_CANDLE_TZ,
+    };
+    let candles = fetch_candles(
+        &mut client,
+        query.dataset.as_deref().unwrap_or(&state.dataset),
+        &query.symbol,
+        infer_stype(&query.symbol),
+        (start, end),
+        query.interval,
+        tz,
+    )
+    .await?;
+    Ok(Json(
+        candles.into_iter().map(CandleResponse::from).collect(),
+    ))
+}
+
+#[cfg(feature = "metrics")]
+async fn metrics() -> impl IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        crate::metrics::Registry::global().render(),
+    )
+}
+
+#[derive(Debug)]
+struct ErrorResponse {
+    status: StatusCode,
+    message: String,
+}
+
+impl ErrorResponse {
+    fn bad_request(message: impl ToString) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.to_string(),
+        }
+    }
+
+    fn unauthorized(message: impl ToString) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<PmzError> for ErrorResponse {
+    fn from(err: PmzError) -> Self {
+        let status = match &err {
+            PmzError::InvalidDate(_) => StatusCode::BAD_REQUEST,
+            PmzError::NoData(_) | PmzError::SymbologyError(_) => StatusCode::NOT_FOUND,
+            PmzError::PartialData { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            PmzError::ApiError(crate::Error::BadArgument { .. }) => StatusCode::BAD_REQUEST,
+            PmzError::ApiError(crate::Error::Api(historical_err))
+                if historical_err.status_code.is_client_error() =>
+            {
+                StatusCode::BAD_REQUEST
+            }
+            PmzError::ApiError(_) | PmzError::InvalidTimestamp(_) => StatusCode::BAD_GATEWAY,
+        };
+        Self {
+            status,
+            message: err.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(serde_json::json!({ "error": self.message })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::Schema;
+    use reqwest::StatusCode;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        body_contains,
+        historical::{HistoricalGateway, API_VERSION},
+        zst_test_data_path,
+    };
+
+    const API_KEY: &str = "test-API";
+
+    async fn spawn(mock_server: &MockServer) -> String {
+        let client = HistoricalClient::with_url(
+            mock_server.uri(),
+            API_KEY.to_owned(),
+            HistoricalGateway::Bo1,
+        )
+        .unwrap();
+        let router = router_with_client(client, &Config::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_candles() {
+        let mock_server = MockServer::start().await;
+        let bytes = tokio::fs::read(zst_test_data_path(Schema::Ohlcv1M))
+            .await
+            .unwrap();
+        Mock::given(method("POST"))
+            .and(path(format!("/v{API_VERSION}/timeseries.get_range")))
+            .and(body_contains("schema", "ohlcv-1m"))
+            .and(body_contains("stype_in", "raw_symbol"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes))
+            .mount(&mock_server)
+            .await;
+        let base_url = spawn(&mock_server).await;
+        let resp = reqwest::get(format!(
+            "{base_url}/candles?symbol=ESM3&start=2023-06-14T00:00:00Z&tz=UTC"
+        ))
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let candles: Vec<serde_json::Value> = resp.json().await.unwrap();
+        assert!(!candles.is_empty());
+        assert_eq!(candles[0]["symbol"], "ESM3");
+        assert!(candles[0]["timestamp"]
+            .as_str()
+            .unwrap()
+            .ends_with("+00:00"));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_metrics() {
+        let mock_server = MockServer::start().await;
+        let bytes = tokio::fs::read(zst_test_data_path(Schema::Ohlcv1M))
+            .await
+            .unwrap();
+        Mock::given(method("POST"))
+            .and(path(format!("/v{API_VERSION}/timeseries.get_range")))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes))
+            .mount(&mock_server)
+            .await;
+        let base_url = spawn(&mock_server).await;
+        reqwest::get(format!(
+            "{base_url}/candles?symbol=ESM3&start=2023-06-14T00:00:00Z"
+        ))
+        .await
+        .unwrap();
+        let resp = reqwest::get(format!("{base_url}/metrics")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = resp.text().await.unwrap();
+        assert!(body.contains(
+            "databento_request_duration_seconds_count{endpoint=\"timeseries.get_range\"}"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_candles_bad_request() {
+        let mock_server = MockServer::start().await;
+        let base_url = spawn(&mock_server).await;
+        let resp = reqwest::get(format!("{base_url}/candles?symbol=ESM3&interval=0"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert!(body["error"].as_str().unwrap().contains("interval"));
+    }
+
+    #[tokio::test]
+    async fn test_candles_with_pool() {
+        let mock_server = MockServer::start().await;
+        let bytes = tokio::fs::read(zst_test_data_path(Schema::Ohlcv1M))
+            .await
+            .unwrap();
+        Mock::given(method("POST"))
+            .and(path(format!("/v{API_VERSION}/timeseries.get_range")))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_bytes(bytes))
+            .mount(&mock_server)
+            .await;
+        let pool = Arc::new(
+            ClientPool::new(
+                HistoricalClient::builder().base_url(mock_server.uri().parse().unwrap()),
+                std::time::Duration::from_secs(60),
+            )
+            .unwrap(),
+        );
+        let router = router_with_pool(pool.clone(), &Config::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        let url = format!("http://{addr}/candles?symbol=ESM3&start=2023-06-14T00:00:00Z");
+        let http = reqwest::Client::new();
+
+        let resp = http.get(&url).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let resp = http
+            .get(&url)
+            .header(KEY_HEADER, "short")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(pool.is_empty());
+
+        let resp = http
+            .get(&url)
+            .header(KEY_HEADER, "32-character-with-lots-of-filler")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,183 @@
+//! WebSocket push of live candles and intraday pre-market ranges.
+//!
+//! [`publish_live()`] aggregates 1-minute bars from a [`LiveClient`] and broadcasts
+//! each completed candle and every change to the pre-market high (PMH) or low (PML)
+//! as a [`LiveUpdate`]. Each subscriber to the `/ws` endpoint of [`router()`] receives
+//! the updates as JSON text messages tagged with a `type` of `bar` or `premarket`.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use dbn::{OhlcvMsg, PitSymbolMap};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::CandleResponse;
+use crate::{
+    examples::es_futures_pmz::{
+        Candle, LiveCandleBuilder, PmzConfig, PremarketRange, PremarketTracker,
+    },
+    LiveClient,
+};
+
+/// An update broadcast to WebSocket subscribers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiveUpdate {
+    /// A completed candle.
+    Bar(Candle),
+    /// The pre-market high or low changed.
+    Premarket(PremarketRange),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum UpdateMessage {
+    Bar(CandleResponse),
+    Premarket { date: NaiveDate, pmh: f64, pml: f64 },
+}
+
+impl From<LiveUpdate> for UpdateMessage {
+    fn from(update: LiveUpdate) -> Self {
+        match update {
+            LiveUpdate::Bar(candle) => Self::Bar(candle.into()),
+            LiveUpdate::Premarket(range) => Self::Premarket {
+                date: range.date,
+                pmh: range.high,
+                pml: range.low,
+            },
+        }
+    }
+}
+
+/// Creates a router with a `/ws` endpoint that forwards every update sent on `tx` to
+/// each connected WebSocket client.
+pub fn router(tx: broadcast::Sender<LiveUpdate>) -> Router {
+    Router::new().route("/ws", get(subscribe)).with_state(tx)
+}
+
+async fn subscribe(
+    State(tx): State<broadcast::Sender<LiveUpdate>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let rx = tx.subscribe();
+    ws.on_upgrade(move |socket| forward(socket, rx))
+}
+
+async fn forward(mut socket: WebSocket, mut rx: broadcast::Receiver<LiveUpdate>) {
+    loop {
+        let update = match rx.recv().await {
+            Ok(update) => update,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "WebSocket subscriber fell behind");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        let json = match serde_json::to_string(&UpdateMessage::from(update)) {
+            Ok(json) => json,
+            Err(err) => {
+                tracing::error!(%err, "Failed to serialize live update");
+                continue;
+            }
+        };
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            // Client disconnected
+            break;
+        }
+    }
+}
+
+/// Reads 1-minute OHLCV records from `client`, which should already be subscribed
+/// and started, and sends completed `interval_minutes` candles in `tz` and pre-market
+/// range changes for the window in `pmz` on `tx`.
+///
+/// Updates are dropped while there are no subscribers, and bars with an out-of-range
+/// timestamp are skipped. Returns once the session ends.
+///
+/// # Errors
+/// This function returns an error when it fails to read from the gateway or a symbol
+/// mapping record is invalid.
+pub async fn publish_live(
+    client: &mut LiveClient,
+    interval_minutes: u32,
+    tz: Tz,
+    pmz: &PmzConfig,
+    tx: &broadcast::Sender<LiveUpdate>,
+) -> crate::Result<()> {
+    let mut symbol_map = PitSymbolMap::new();
+    let mut builders: HashMap<u32, LiveCandleBuilder> = HashMap::new();
+    let mut tracker = PremarketTracker::new(pmz);
+    while let Some(rec) = client.next_record().await? {
+        if let Some(ohlcv) = rec.get::<OhlcvMsg>() {
+            let instrument_id = ohlcv.hd.instrument_id;
+            let symbol = symbol_map
+                .get(instrument_id)
+                .cloned()
+                .unwrap_or_else(|| instrument_id.to_string());
+            let candle = match Candle::try_with_symbol(ohlcv, symbol.into(), tz) {
+                Ok(candle) => candle,
+                Err(err) => {
+                    tracing::warn!(%err, instrument_id, "Skipping malformed bar");
+                    continue;
+                }
+            };
+            if let Some(range) = tracker.update(&candle) {
+                // Only fails when there are no subscribers
+                let _ = tx.send(LiveUpdate::Premarket(range));
+            }
+            let builder = builders
+                .entry(instrument_id)
+                .or_insert_with(|| LiveCandleBuilder::new(interval_minutes, tz));
+            if let Some(bar) = builder.push(candle) {
+                let _ = tx.send(LiveUpdate::Bar(bar));
+            }
+        }
+        symbol_map.on_record(rec)?;
+    }
+    for bar in builders.values_mut().filter_map(LiveCandleBuilder::flush) {
+        let _ = tx.send(LiveUpdate::Bar(bar));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn test_update_message_json() {
+        let candle = test_util::candle()
+            .timestamp(Tz::UTC.with_ymd_and_hms(2025, 4, 21, 13, 30, 0).unwrap())
+            .ohlc(5300.0, 5302.5, 5298.5, 5299.0)
+            .volume(30)
+            .build();
+        let json = serde_json::to_value(UpdateMessage::from(LiveUpdate::Bar(candle))).unwrap();
+        assert_eq!(json["type"], "bar");
+        assert_eq!(json["symbol"], "ESM5");
+        assert_eq!(json["timestamp"], "2025-04-21T13:30:00+00:00");
+
+        let range = PremarketRange {
+            date: NaiveDate::from_ymd_opt(2025, 4, 21).unwrap(),
+            high: 5310.25,
+            low: 5290.0,
+        };
+        let json = serde_json::to_value(UpdateMessage::from(LiveUpdate::Premarket(range))).unwrap();
+        assert_eq!(json["type"], "premarket");
+        assert_eq!(json["date"], "2025-04-21");
+        assert_eq!(json["pmh"], 5310.25);
+        assert_eq!(json["pml"], 5290.0);
+    }
+}
@@ -0,0 +1,408 @@
+//! Writing decoded records to a local database for SQL analysis.
+//!
+//! [`SqliteSink`] (feature `sqlite`) and [`DuckDbSink`] (feature `duckdb`) implement
+//! [`RecordSink`], storing OHLCV records in an `ohlcv` table and trades in a `trades`
+//! table, which are created if they don't exist:
+//!
+//! ```sql
+//! CREATE TABLE ohlcv (
+//!     rtype BIGINT, instrument_id BIGINT, ts_event BIGINT,
+//!     open DOUBLE, high DOUBLE, low DOUBLE, close DOUBLE, volume BIGINT,
+//!     PRIMARY KEY (rtype, instrument_id, ts_event)
+//! );
+//! CREATE TABLE trades (
+//!     instrument_id BIGINT, ts_event BIGINT, ts_recv BIGINT, sequence BIGINT,
+//!     price DOUBLE, size BIGINT, side TEXT, flags BIGINT,
+//!     PRIMARY KEY (instrument_id, ts_event, ts_recv, sequence)
+//! );
+//! ```
+//!
+//! Timestamps are nanoseconds since the UNIX epoch and prices are converted from
+//! fixed-point. Writes are upserts: a record with the same key as a stored one
+//! replaces it, so overlapping ranges of an incremental backfill can be written again
+//! without creating duplicates. The `rtype` distinguishes OHLCV intervals, e.g.
+//! [`rtype::OHLCV_1M`](dbn::rtype::OHLCV_1M).
+
+use dbn::{decode::AsyncDbnDecoder, OhlcvMsg, TradeMsg};
+use tokio::io::AsyncReadExt;
+
+/// A destination for decoded records.
+pub trait RecordSink {
+    /// Upserts OHLCV records of any interval, returning the number written.
+    ///
+    /// # Errors
+    /// This function returns an error when the database write fails.
+    fn write_ohlcv(&mut self, records: &[OhlcvMsg]) -> crate::Result<usize>;
+
+    /// Upserts trades, returning the number written.
+    ///
+    /// # Errors
+    /// This function returns an error when the database write fails.
+    fn write_trades(&mut self, records: &[TradeMsg]) -> crate::Result<usize>;
+}
+
+// The number of records written per transaction by `write_decoded()`
+const BATCH_SIZE: usize = 10_000;
+
+/// Writes every OHLCV or trade record from `decoder` to `sink` in batches, returning
+/// the number written. Records of other types are skipped.
+///
+/// # Errors
+/// This function returns an error when decoding or a database write fails.
+pub async fn write_decoded<R>(
+    decoder: &mut AsyncDbnDecoder<R>,
+    sink: &mut impl RecordSink,
+) -> crate::Result<usize>
+where
+    R: AsyncReadExt + Unpin,
+{
+    let mut ohlcv = Vec::new();
+    let mut trades = Vec::new();
+    let mut count = 0;
+    while let Some(rec) = decoder.decode_record_ref().await? {
+        if let Some(bar) = rec.get::<OhlcvMsg>() {
+            ohlcv.push(bar.clone());
+            if ohlcv.len() == BATCH_SIZE {
+                count += sink.write_ohlcv(&ohlcv)?;
+                ohlcv.clear();
+            }
+        } else if let Some(trade) = rec.get::<TradeMsg>() {
+            trades.push(trade.clone());
+            if trades.len() == BATCH_SIZE {
+                count += sink.write_trades(&trades)?;
+                trades.clear();
+            }
+        }
+    }
+    if !ohlcv.is_empty() {
+        count += sink.write_ohlcv(&ohlcv)?;
+    }
+    if !trades.is_empty() {
+        count += sink.write_trades(&trades)?;
+    }
+    Ok(count)
+}
+
+// Column types are chosen to have the same meaning in SQLite and DuckDB
+#[cfg(any(feature = "sqlite", feature = "duckdb"))]
+const CREATE_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS ohlcv (
+    rtype BIGINT NOT NULL,
+    instrument_id BIGINT NOT NULL,
+    ts_event BIGINT NOT NULL,
+    open DOUBLE NOT NULL,
+    high DOUBLE NOT NULL,
+    low DOUBLE NOT NULL,
+    close DOUBLE NOT NULL,
+    volume BIGINT NOT NULL,
+    PRIMARY KEY (rtype, instrument_id, ts_event)
+);
+CREATE TABLE IF NOT EXISTS trades (
+    instrument_id BIGINT NOT NULL,
+    ts_event BIGINT NOT NULL,
+    ts_recv BIGINT NOT NULL,
+    sequence BIGINT NOT NULL,
+    price DOUBLE NOT NULL,
+    size BIGINT NOT NULL,
+    side TEXT NOT NULL,
+    flags BIGINT NOT NULL,
+    PRIMARY KEY (instrument_id, ts_event, ts_recv, sequence)
+);
+";
+
+#[cfg(any(feature = "sqlite", feature = "duckdb"))]
+const UPSERT_OHLCV: &str = "
+INSERT INTO ohlcv (rtype, instrument_id, ts_event, open, high, low, close, volume)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (rtype, instrument_id, ts_event) DO UPDATE SET
+    open = excluded.open,
+    high = excluded.high,
+    low = excluded.low,
+    close = excluded.close,
+    volume = excluded.volume
+";
+
+#[cfg(any(feature = "sqlite", feature = "duckdb"))]
+const UPSERT_TRADE: &str = "
+INSERT INTO trades (instrument_id, ts_event, ts_recv, sequence, price, size, side, flags)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+ON CONFLICT (instrument_id, ts_event, ts_recv, sequence) DO UPDATE SET
+    price = excluded.price,
+    size = excluded.size,
+    side = excluded.side,
+    flags = excluded.flags
+";
+
+#[cfg(any(feature = "sqlite", feature = "duckdb"))]
+fn to_price(px: i64) -> f64 {
+    px as f64 * 1e-9
+}
+
+#[cfg(any(feature = "sqlite", feature = "duckdb"))]
+fn side_str(trade: &TradeMsg) -> String {
+    (trade.side as u8 as char).to_string()
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteSink;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::path::Path;
+
+    use dbn::{OhlcvMsg, TradeMsg};
+    use rusqlite::{params, Connection};
+
+    use super::{side_str, to_price, RecordSink, CREATE_TABLES, UPSERT_OHLCV, UPSERT_TRADE};
+
+    /// A [`RecordSink`] writing to a SQLite database.
+    #[derive(Debug)]
+    pub struct SqliteSink {
+        conn: Connection,
+    }
+
+    impl SqliteSink {
+        /// Opens or creates the database at `path` and creates the tables.
+        ///
+        /// # Errors
+        /// This function returns an error when the database can't be opened or the
+        /// tables can't be created.
+        pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+            Self::from_connection(Connection::open(path)?)
+        }
+
+        /// Creates an in-memory database with the tables.
+        ///
+        /// # Errors
+        /// This function returns an error when the tables can't be created.
+        pub fn open_in_memory() -> crate::Result<Self> {
+            Self::from_connection(Connection::open_in_memory()?)
+        }
+
+        /// Creates the tables in `conn` if they don't exist.
+        ///
+        /// # Errors
+        /// This function returns an error when the tables can't be created.
+        pub fn from_connection(conn: Connection) -> crate::Result<Self> {
+            conn.execute_batch(CREATE_TABLES)?;
+            Ok(Self { conn })
+        }
+
+        /// Returns the connection for querying the stored records.
+        pub fn connection(&self) -> &Connection {
+            &self.conn
+        }
+    }
+
+    impl RecordSink for SqliteSink {
+        fn write_ohlcv(&mut self, records: &[OhlcvMsg]) -> crate::Result<usize> {
+            let tx = self.conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached(UPSERT_OHLCV)?;
+                for bar in records {
+                    stmt.execute(params![
+                        bar.hd.rtype,
+                        bar.hd.instrument_id,
+                        bar.hd.ts_event as i64,
+                        to_price(bar.open),
+                        to_price(bar.high),
+                        to_price(bar.low),
+                        to_price(bar.close),
+                        bar.volume as i64,
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(records.len())
+        }
+
+        fn write_trades(&mut self, records: &[TradeMsg]) -> crate::Result<usize> {
+            let tx = self.conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached(UPSERT_TRADE)?;
+                for trade in records {
+                    stmt.execute(params![
+                        trade.hd.instrument_id,
+                        trade.hd.ts_event as i64,
+                        trade.ts_recv as i64,
+                        trade.sequence,
+                        to_price(trade.price),
+                        trade.size,
+                        side_str(trade),
+                        trade.flags.raw(),
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(records.len())
+        }
+    }
+}
+
+#[cfg(feature = "duckdb")]
+pub use duck::DuckDbSink;
+
+#[cfg(feature = "duckdb")]
+mod duck {
+    use std::path::Path;
+
+    use dbn::{OhlcvMsg, TradeMsg};
+    use duckdb::{params, Connection};
+
+    use super::{side_str, to_price, RecordSink, CREATE_TABLES, UPSERT_OHLCV, UPSERT_TRADE};
+
+    /// A [`RecordSink`] writing to a DuckDB database.
+    #[derive(Debug)]
+    pub struct DuckDbSink {
+        conn: Connection,
+    }
+
+    impl DuckDbSink {
+        /// Opens or creates the database at `path` and creates the tables.
+        ///
+        /// # Errors
+        /// This function returns an error when the database can't be opened or the
+        /// tables can't be created.
+        pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+            Self::from_connection(Connection::open(path)?)
+        }
+
+        /// Creates an in-memory database with the tables.
+        ///
+        /// # Errors
+        /// This function returns an error when the tables can't be created.
+        pub fn open_in_memory() -> crate::Result<Self> {
+            Self::from_connection(Connection::open_in_memory()?)
+        }
+
+        /// Creates the tables in `conn` if they don't exist.
+        ///
+        /// # Errors
+        /// This function returns an error when the tables can't be created.
+        pub fn from_connection(conn: Connection) -> crate::Result<Self> {
+            conn.execute_batch(CREATE_TABLES)?;
+            Ok(Self { conn })
+        }
+
+        /// Returns the connection for querying the stored records.
+        pub fn connection(&self) -> &Connection {
+            &self.conn
+        }
+    }
+
+    impl RecordSink for DuckDbSink {
+        fn write_ohlcv(&mut self, records: &[OhlcvMsg]) -> crate::Result<usize> {
+            let tx = self.conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached(UPSERT_OHLCV)?;
+                for bar in records {
+                    stmt.execute(params![
+                        bar.hd.rtype,
+                        bar.hd.instrument_id,
+                        bar.hd.ts_event as i64,
+                        to_price(bar.open),
+                        to_price(bar.high),
+                        to_price(bar.low),
+                        to_price(bar.close),
+                        bar.volume as i64,
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(records.len())
+        }
+
+        fn write_trades(&mut self, records: &[TradeMsg]) -> crate::Result<usize> {
+            let tx = self.conn.transaction()?;
+            {
+                let mut stmt = tx.prepare_cached(UPSERT_TRADE)?;
+                for trade in records {
+                    stmt.execute(params![
+                        trade.hd.instrument_id,
+                        trade.hd.ts_event as i64,
+                        trade.ts_recv as i64,
+                        trade.sequence,
+                        to_price(trade.price),
+                        trade.size,
+                        side_str(trade),
+                        trade.flags.raw(),
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(records.len())
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "sqlite", feature = "duckdb")))]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn bar(ts_event: u64, close: i64) -> OhlcvMsg {
+        OhlcvMsg {
+            close,
+            ..test_util::ohlcv(1, ts_event)
+        }
+    }
+
+    fn trade(sequence: u32) -> TradeMsg {
+        TradeMsg {
+            ts_recv: 1_100,
+            sequence,
+            ..test_util::trade(1, 1_000, 5_300_250_000_000, 2)
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_upsert() {
+        let mut sink = SqliteSink::open_in_memory().unwrap();
+        sink.write_ohlcv(&[bar(0, 5_300_000_000_000), bar(60, 5_300_500_000_000)])
+            .unwrap();
+        // Overlaps the first write
+        sink.write_ohlcv(&[bar(60, 5_301_000_000_000)]).unwrap();
+        sink.write_trades(&[trade(1), trade(2), trade(2)]).unwrap();
+        let conn = sink.connection();
+        let (count, close): (i64, f64) = conn
+            .query_row(
+                "SELECT COUNT(*), MAX(close) FROM ohlcv WHERE instrument_id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(close, 5301.0);
+        let (count, side): (i64, String) = conn
+            .query_row("SELECT COUNT(*), MIN(side) FROM trades", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(side, "B");
+    }
+
+    #[cfg(feature = "duckdb")]
+    #[test]
+    fn test_duckdb_upsert() {
+        let mut sink = DuckDbSink::open_in_memory().unwrap();
+        sink.write_ohlcv(&[bar(0, 5_300_000_000_000), bar(60, 5_300_500_000_000)])
+            .unwrap();
+        sink.write_ohlcv(&[bar(60, 5_301_000_000_000)]).unwrap();
+        sink.write_trades(&[trade(1), trade(2)]).unwrap();
+        sink.write_trades(&[trade(2)]).unwrap();
+        let conn = sink.connection();
+        let (count, close): (i64, f64) = conn
+            .query_row("SELECT COUNT(*), MAX(close) FROM ohlcv", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(close, 5301.0);
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM trades", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,355 @@
+//! A trait over sources of market data.
+//!
+//! [`MarketDataSource`] decouples the PMZ calculation and indicators from where the
+//! data comes from. It's implemented for every [`HistoricalApi`] client, including
+//! [`HistoricalClient`](crate::HistoricalClient) and
+//! [`MockHistoricalClient`](crate::testing::MockHistoricalClient), and for
+//! [`Replay`](crate::replay::Replay)s of local DBN files.
+//!
+//! [`HistoricalApi`]: crate::historical::HistoricalApi
+
+use std::{collections::HashMap, future::Future};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use dbn::{MappingInterval, SType, TradeMsg};
+
+use crate::{
+    examples::es_futures_pmz::{Candle, Result},
+    statistics::Settlement,
+};
+
+/// A request for the data of a single symbol over a time range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataRequest {
+    /// The dataset code.
+    pub dataset: String,
+    /// The symbol to request.
+    pub symbol: String,
+    /// The symbology type of `symbol`.
+    pub stype_in: SType,
+    /// The start of the range (inclusive).
+    pub start: DateTime<Utc>,
+    /// The end of the range (exclusive).
+    pub end: DateTime<Utc>,
+}
+
+/// A source of candles, symbology, trades, and settlements.
+pub trait MarketDataSource {
+    /// Returns the 1-minute candles for the request in timestamp order, with
+    /// timestamps in [`DEFAULT_CANDLE_TZ`](crate::examples::es_futures_pmz::DEFAULT_CANDLE_TZ).
+    ///
+    /// # Errors
+    /// This function returns an error when the symbol can't be resolved or the data
+    /// can't be retrieved.
+    fn get_candles(
+        &mut self,
+        request: &DataRequest,
+    ) -> impl Future<Output = Result<Vec<Candle>>> + Send;
+
+    /// Resolves `symbols` of symbology type `stype_in` to the instrument IDs they
+    /// referred to on `date`. Symbols that couldn't be resolved are omitted.
+    ///
+    /// # Errors
+    /// This function returns an error when the symbology can't be retrieved.
+    fn resolve_symbols(
+        &mut self,
+        dataset: &str,
+        symbols: &[String],
+        stype_in: SType,
+        date: NaiveDate,
+    ) -> impl Future<Output = Result<HashMap<String, u32>>> + Send;
+
+    /// Calls `on_trade` with each trade for the request in order.
+    ///
+    /// # Errors
+    /// This function returns an error when the symbol can't be resolved or the data
+    /// can't be retrieved.
+    fn stream_trades<F>(
+        &mut self,
+        request: &DataRequest,
+        on_trade: F,
+    ) -> impl Future<Output = Result<()>> + Send
+    where
+        F: FnMut(&TradeMsg) + Send;
+
+    /// Returns the settlement price of `symbol` for the trading day `date`, or `None`
+    /// if none was published or the source doesn't provide settlements, which is the
+    /// default.
+    ///
+    /// # Errors
+    /// This function returns an error when the statistics can't be retrieved.
+    fn get_settlement(
+        &mut self,
+        dataset: &str,
+        symbol: &str,
+        stype_in: SType,
+        date: NaiveDate,
+    ) -> impl Future<Output = Result<Option<Settlement>>> + Send {
+        let _ = (dataset, symbol, stype_in, date);
+        async { Ok(None) }
+    }
+}
+
+#[cfg(feature = "historical")]
+mod historical {
+    use std::collections::HashMap;
+
+    use chrono::NaiveDate;
+    use dbn::{SType, Schema, TradeMsg};
+
+    use super::{instrument_id_on, DataRequest, MarketDataSource};
+    use crate::{
+        examples::es_futures_pmz::{
+            fetch_candles, to_offset_date_time, to_time_date, Candle, PmzError, Result,
+            DEFAULT_CANDLE_TZ,
+        },
+        historical::{
+            symbology::ResolveParams, timeseries::GetRangeParams, DateTimeRange, HistoricalApi,
+        },
+        statistics::{get_settlement, Settlement},
+    };
+
+    fn date_time_range(request: &DataRequest) -> Result<DateTimeRange> {
+        Ok(DateTimeRange::from((
+            to_offset_date_time(request.start)?,
+            to_offset_date_time(request.end)?,
+        )))
+    }
+
+    impl<C: HistoricalApi + Send> MarketDataSource for C {
+        async fn get_candles(&mut self, request: &DataRequest) -> Result<Vec<Candle>> {
+            fetch_candles(
+                self,
+                &request.dataset,
+                &request.symbol,
+                request.stype_in,
+                date_time_range(request)?,
+                1,
+                DEFAULT_CANDLE_TZ,
+            )
+            .await
+        }
+
+        async fn resolve_symbols(
+            &mut self,
+            dataset: &str,
+            symbols: &[String],
+            stype_in: SType,
+            date: NaiveDate,
+        ) -> Result<HashMap<String, u32>> {
+            let date = to_time_date(date)?;
+            let resolution = self
+                .resolve(
+                    &ResolveParams::builder()
+                        .dataset(dataset)
+                        .symbols(symbols.to_vec())
+                        .stype_in(stype_in)
+                        .date_range(date)
+                        .build(),
+                )
+                .await?;
+            Ok(resolution
+                .mappings
+                .iter()
+                .filter_map(|(symbol, intervals)| {
+                    Some((symbol.clone(), instrument_id_on(intervals, date)?))
+                })
+                .collect())
+        }
+
+        async fn stream_trades<F>(&mut self, request: &DataRequest, mut on_trade: F) -> Result<()>
+        where
+            F: FnMut(&TradeMsg) + Send,
+        {
+            let params = GetRangeParams::builder()
+                .dataset(&request.dataset)
+                .symbols(request.symbol.as_str())
+                .stype_in(request.stype_in)
+                .schema(Schema::Trades)
+                .date_time_range(date_time_range(request)?)
+                .build();
+            let mut decoder = self.get_range(&params).await?;
+            if decoder.metadata().not_found.contains(&request.symbol) {
+                return Err(PmzError::SymbologyError(format!(
+                    "{} could not be resolved in {}",
+                    request.symbol, request.dataset
+                )));
+            }
+            while let Some(trade) = decoder.decode_record::<TradeMsg>().await? {
+                on_trade(trade);
+            }
+            Ok(())
+        }
+
+        async fn get_settlement(
+            &mut self,
+            dataset: &str,
+            symbol: &str,
+            stype_in: SType,
+            date: NaiveDate,
+        ) -> Result<Option<Settlement>> {
+            Ok(get_settlement(self, dataset, symbol, stype_in, date).await?)
+        }
+    }
+}
+
+#[cfg(feature = "replay")]
+mod replay {
+    use std::{collections::HashMap, sync::Arc};
+
+    use chrono::NaiveDate;
+    use dbn::{OhlcvMsg, Record, SType, TradeMsg};
+    use tokio::io::AsyncReadExt;
+
+    use super::{instrument_id_on, DataRequest, MarketDataSource};
+    use crate::{
+        examples::es_futures_pmz::{to_time_date, Candle, Result, DEFAULT_CANDLE_TZ},
+        replay::Replay,
+    };
+
+    /// Replays are read once from start to end: each request consumes the records up
+    /// to the end of its range, including the first record after it, so requests
+    /// should be made in chronological order. Every record in the file is assumed to
+    /// be for the requested symbol.
+    impl<R: AsyncReadExt + Unpin + Send> MarketDataSource for Replay<R> {
+        async fn get_candles(&mut self, request: &DataRequest) -> Result<Vec<Candle>> {
+            let (start, end) = range_nanos(request);
+            let symbol = Arc::<str>::from(request.symbol.as_str());
+            let mut candles = Vec::new();
+            while let Some(rec) = self.next_record().await? {
+                let ts_event = rec.header().ts_event;
+                if ts_event >= end {
+                    break;
+                }
+                if let Some(ohlcv) = rec.get::<OhlcvMsg>().filter(|_| ts_event >= start) {
+                    candles.push(Candle::try_with_symbol(
+                        ohlcv,
+                        symbol.clone(),
+                        DEFAULT_CANDLE_TZ,
+                    )?);
+                }
+            }
+            Ok(candles)
+        }
+
+        async fn resolve_symbols(
+            &mut self,
+            _dataset: &str,
+            symbols: &[String],
+            _stype_in: SType,
+            date: NaiveDate,
+        ) -> Result<HashMap<String, u32>> {
+            let date = to_time_date(date)?;
+            Ok(self
+                .metadata()
+                .mappings
+                .iter()
+                .filter(|mapping| symbols.contains(&mapping.raw_symbol))
+                .filter_map(|mapping| {
+                    Some((
+                        mapping.raw_symbol.clone(),
+                        instrument_id_on(&mapping.intervals, date)?,
+                    ))
+                })
+                .collect())
+        }
+
+        async fn stream_trades<F>(&mut self, request: &DataRequest, mut on_trade: F) -> Result<()>
+        where
+            F: FnMut(&TradeMsg) + Send,
+        {
+            let (start, end) = range_nanos(request);
+            while let Some(rec) = self.next_record().await? {
+                let ts_event = rec.header().ts_event;
+                if ts_event >= end {
+                    break;
+                }
+                if let Some(trade) = rec.get::<TradeMsg>().filter(|_| ts_event >= start) {
+                    on_trade(trade);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    // Returns the bounds of the range of `request` in UNIX nanoseconds
+    fn range_nanos(request: &DataRequest) -> (u64, u64) {
+        let nanos = |dt: chrono::DateTime<chrono::Utc>| {
+            dt.timestamp_nanos_opt()
+                .map_or(0, |nanos| nanos.max(0) as u64)
+        };
+        (nanos(request.start), nanos(request.end))
+    }
+}
+
+// Returns the instrument ID `intervals` mapped to on `date`
+fn instrument_id_on(intervals: &[MappingInterval], date: time::Date) -> Option<u32> {
+    intervals
+        .iter()
+        .find(|interval| interval.start_date <= date && date < interval.end_date)?
+        .symbol
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    #[test]
+    fn test_instrument_id_on() {
+        let intervals = [
+            MappingInterval {
+                start_date: date!(2025 - 03 - 01),
+                end_date: date!(2025 - 03 - 14),
+                symbol: "4916".to_owned(),
+            },
+            MappingInterval {
+                start_date: date!(2025 - 03 - 14),
+                end_date: date!(2025 - 06 - 13),
+                symbol: "294973".to_owned(),
+            },
+        ];
+        assert_eq!(
+            instrument_id_on(&intervals, date!(2025 - 03 - 13)),
+            Some(4916)
+        );
+        assert_eq!(
+            instrument_id_on(&intervals, date!(2025 - 03 - 14)),
+            Some(294973)
+        );
+        assert_eq!(instrument_id_on(&intervals, date!(2025 - 06 - 13)), None);
+    }
+
+    #[cfg(feature = "replay")]
+    #[tokio::test]
+    async fn test_replay_candles() {
+        use dbn::Schema;
+
+        use crate::{
+            replay::{Replay, ReplaySpeed},
+            zst_test_data_path,
+        };
+
+        let mut replay = Replay::from_zstd_file(
+            zst_test_data_path(Schema::Ohlcv1M),
+            ReplaySpeed::Unthrottled,
+        )
+        .await
+        .unwrap();
+        let request = DataRequest {
+            dataset: "GLBX.MDP3".to_owned(),
+            symbol: "ESH1".to_owned(),
+            stype_in: SType::RawSymbol,
+            start: DateTime::UNIX_EPOCH,
+            end: Utc::now(),
+        };
+        let candles = replay.get_candles(&request).await.unwrap();
+        assert!(!candles.is_empty());
+        assert!(candles.iter().all(|c| &*c.symbol == "ESH1"));
+        // The replay is exhausted
+        assert!(replay.get_candles(&request).await.unwrap().is_empty());
+    }
+}
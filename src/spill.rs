@@ -0,0 +1,368 @@
+//! Aggregating candles with bounded memory.
+//!
+//! [`aggregate_candles()`](crate::examples::es_futures_pmz::aggregate_candles) needs
+//! every candle in memory, which doesn't scale to multi-month queries across all
+//! symbols. [`SpillAggregator`] aggregates candles as they arrive in timestamp order
+//! and writes each bucket to a DBN file as soon as a later bucket starts, so only one
+//! open bucket per instrument is kept in memory. Use [`read_spilled()`] to read the
+//! aggregated candles back.
+//!
+//! Files created with [`SpillAggregator::create()`] are Zstandard-compressed, so they
+//! should be named with a `.dbn.zst` extension. [`read_spilled()`] decompresses them
+//! as they're read.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+use dbn::{
+    decode::{DbnDecoder, DecodeRecord, DynReader},
+    encode::{DbnEncoder, DynWriter, EncodeRecord},
+    rtype, Compression, MetadataBuilder, OhlcvMsg, RecordHeader, SType, Schema,
+};
+
+use crate::{
+    examples::es_futures_pmz::{bucket_start, BucketAnchor, Candle, CandlePrice, SymbolTable},
+    Error,
+};
+
+/// Aggregates candles into `interval` buckets, spilling completed buckets to a DBN
+/// file of OHLCV records.
+///
+/// Candles must be pushed in timestamp order, as they're returned by the historical
+/// API. Symbols aren't stored in the file: use [`symbols()`](Self::symbols) when
+/// reading it back.
+pub struct SpillAggregator<W: io::Write, P = f64> {
+    dataset: String,
+    interval: Duration,
+    anchor: BucketAnchor,
+    // Created when the first bucket is spilled, so the metadata has its start
+    writer: Option<W>,
+    encoder: Option<DbnEncoder<W>>,
+    // The bucket being aggregated for each instrument
+    open: HashMap<u32, Candle<P>>,
+    // The start of the latest bucket
+    watermark: Option<DateTime<Tz>>,
+    symbols: SymbolTable,
+    spilled: u64,
+}
+
+impl<P: CandlePrice> SpillAggregator<DynWriter<'static, BufWriter<File>>, P> {
+    /// Creates an aggregator spilling to a new Zstandard-compressed file at `path`.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to create the file.
+    pub fn create(
+        path: impl AsRef<Path>,
+        dataset: &str,
+        interval: Duration,
+        anchor: BucketAnchor,
+    ) -> crate::Result<Self> {
+        let file = File::create(path)?;
+        let writer = DynWriter::new(BufWriter::new(file), Compression::ZStd)?;
+        Ok(Self::new(writer, dataset, interval, anchor))
+    }
+}
+
+impl<W: io::Write, P: CandlePrice> SpillAggregator<W, P> {
+    /// Creates an aggregator spilling to `writer`.
+    pub fn new(writer: W, dataset: &str, interval: Duration, anchor: BucketAnchor) -> Self {
+        Self {
+            dataset: dataset.to_owned(),
+            interval,
+            anchor,
+            writer: Some(writer),
+            encoder: None,
+            open: HashMap::new(),
+            watermark: None,
+            symbols: SymbolTable::new(),
+            spilled: 0,
+        }
+    }
+
+    /// Adds `candle` to its bucket, first spilling every bucket that started before
+    /// it.
+    ///
+    /// # Errors
+    /// This function returns an error when `candle` belongs to a bucket that was
+    /// already spilled, i.e. it's out of order, or writing to the file fails.
+    pub fn push(&mut self, candle: Candle<P>) -> crate::Result<()> {
+        let start = bucket_start(candle.timestamp, self.interval, self.anchor);
+        match self.watermark {
+            Some(watermark) if start < watermark => {
+                return Err(Error::bad_arg(
+                    "candle",
+                    format!(
+                        "candle at {} is before the current bucket at {watermark}",
+                        candle.timestamp
+                    ),
+                ));
+            }
+            Some(watermark) if start == watermark => {}
+            _ => {
+                self.spill_before(start)?;
+                self.watermark = Some(start);
+            }
+        }
+        self.symbols.insert(candle.instrument_id, &candle.symbol);
+        match self.open.get_mut(&candle.instrument_id) {
+            Some(bucket) => {
+                bucket.high = bucket.high.max_price(candle.high);
+                bucket.low = bucket.low.min_price(candle.low);
+                bucket.close = candle.close;
+                bucket.volume += candle.volume;
+            }
+            None => {
+                self.open.insert(
+                    candle.instrument_id,
+                    Candle {
+                        timestamp: start,
+                        ..candle
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of buckets being aggregated in memory.
+    pub fn open_buckets(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Returns the number of aggregated candles written so far.
+    pub fn spilled(&self) -> u64 {
+        self.spilled
+    }
+
+    /// Returns the symbols of the instruments pushed so far.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Spills the remaining open buckets and closes the file, returning the total
+    /// number of aggregated candles written. Later candles can't be written.
+    ///
+    /// # Errors
+    /// This function returns an error when writing to the file fails.
+    pub fn finish(&mut self) -> crate::Result<u64> {
+        if let Some(watermark) = self.watermark {
+            self.spill_before(watermark + self.interval)?;
+        }
+        if self.writer.is_some() {
+            self.encoder(0)?;
+        }
+        // Dropping the encoder writes the end of the Zstandard frame of compressed files
+        if let Some(mut encoder) = self.encoder.take() {
+            encoder.flush()?;
+        }
+        Ok(self.spilled)
+    }
+
+    fn spill_before(&mut self, start: DateTime<Tz>) -> crate::Result<()> {
+        let mut completed: Vec<_> = self
+            .open
+            .values()
+            .filter(|bucket| bucket.timestamp < start)
+            .map(|bucket| (bucket.timestamp, bucket.instrument_id))
+            .collect();
+        completed.sort_unstable();
+        for (timestamp, instrument_id) in completed {
+            let bucket = self.open.remove(&instrument_id).unwrap();
+            let ts_event = timestamp.timestamp_nanos_opt().unwrap_or(0).max(0) as u64;
+            let record = OhlcvMsg {
+                hd: RecordHeader::new::<OhlcvMsg>(
+                    ohlcv_rtype(self.interval),
+                    0,
+                    instrument_id,
+                    ts_event,
+                ),
+                open: bucket.open.to_fixed(),
+                high: bucket.high.to_fixed(),
+                low: bucket.low.to_fixed(),
+                close: bucket.close.to_fixed(),
+                volume: bucket.volume,
+            };
+            self.encoder(ts_event)?.encode_record(&record)?;
+            self.spilled += 1;
+        }
+        Ok(())
+    }
+
+    fn encoder(&mut self, start: u64) -> crate::Result<&mut DbnEncoder<W>> {
+        if let Some(writer) = self.writer.take() {
+            let metadata = MetadataBuilder::new()
+                .dataset(self.dataset.clone())
+                .schema(ohlcv_schema(self.interval))
+                .start(start)
+                .stype_in(None)
+                .stype_out(SType::InstrumentId)
+                .build();
+            self.encoder = Some(DbnEncoder::new(writer, &metadata)?);
+        }
+        self.encoder
+            .as_mut()
+            .ok_or_else(|| Error::internal("missing spill encoder"))
+    }
+}
+
+impl<W: io::Write, P> std::fmt::Debug for SpillAggregator<W, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpillAggregator")
+            .field("dataset", &self.dataset)
+            .field("interval", &self.interval)
+            .field("anchor", &self.anchor)
+            .field("open_buckets", &self.open.len())
+            .field("watermark", &self.watermark)
+            .field("spilled", &self.spilled)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads the candles written by a [`SpillAggregator`] from `reader`, calling `visit`
+/// with each in timestamp order, and returns the number of candles read. Symbols are
+/// looked up in `symbols`, falling back to the instrument ID. Zstandard-compressed
+/// input is detected and decompressed while reading.
+///
+/// # Errors
+/// This function returns an error when reading or decoding fails, including when a
+/// record's `ts_event` is out of range.
+pub fn read_spilled<R: io::BufRead, P: CandlePrice>(
+    reader: R,
+    symbols: &mut SymbolTable,
+    tz: Tz,
+    mut visit: impl FnMut(Candle<P>),
+) -> crate::Result<u64> {
+    let mut decoder = DbnDecoder::new(DynReader::inferred_with_buffer(reader)?)?;
+    let mut count = 0;
+    while let Some(record) = decoder.decode_record::<OhlcvMsg>()? {
+        let symbol = symbols.get_or_id(record.hd.instrument_id);
+        visit(Candle::try_with_symbol(record, symbol, tz).map_err(dbn::Error::decode)?);
+        count += 1;
+    }
+    Ok(count)
+}
+
+// The schemas with a fixed interval have their own rtype. Other intervals use the
+// generic OHLCV rtype, which has no schema.
+#[allow(deprecated)]
+fn ohlcv_rtype(interval: Duration) -> u8 {
+    match ohlcv_schema(interval) {
+        Some(Schema::Ohlcv1S) => rtype::OHLCV_1S,
+        Some(Schema::Ohlcv1M) => rtype::OHLCV_1M,
+        Some(Schema::Ohlcv1H) => rtype::OHLCV_1H,
+        Some(Schema::Ohlcv1D) => rtype::OHLCV_1D,
+        _ => rtype::OHLCV_DEPRECATED,
+    }
+}
+
+fn ohlcv_schema(interval: Duration) -> Option<Schema> {
+    match interval.num_seconds() {
+        1 => Some(Schema::Ohlcv1S),
+        60 => Some(Schema::Ohlcv1M),
+        3_600 => Some(Schema::Ohlcv1H),
+        86_400 => Some(Schema::Ohlcv1D),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{examples::es_futures_pmz::aggregate_candles_by, test_util};
+
+    fn candle(instrument_id: u32, minute: u32, price: f64) -> Candle {
+        test_util::candle()
+            .timestamp(
+                Tz::UTC
+                    .with_ymd_and_hms(2025, 4, 21, 13, 30 + minute, 0)
+                    .unwrap(),
+            )
+            .instrument_id(instrument_id)
+            .symbol(if instrument_id == 1 { "ESM5" } else { "NQM5" })
+            .ohlc(price, price + 0.5, price - 0.25, price + 0.25)
+            .build()
+    }
+
+    #[test]
+    fn test_spill_aggregator() {
+        let candles: Vec<_> = (0..15)
+            .flat_map(|minute| {
+                [
+                    candle(1, minute, 5300.0 + minute as f64),
+                    candle(2, minute, 18000.0 - minute as f64),
+                ]
+            })
+            .collect();
+        let mut buf = Vec::new();
+        let mut aggregator = SpillAggregator::new(
+            &mut buf,
+            "GLBX.MDP3",
+            Duration::minutes(5),
+            BucketAnchor::Epoch,
+        );
+        for candle in candles.iter().take(12) {
+            aggregator.push(candle.clone()).unwrap();
+        }
+        // The first bucket of each instrument was spilled when minute 5 arrived
+        assert_eq!(aggregator.spilled(), 2);
+        assert_eq!(aggregator.open_buckets(), 2);
+        assert!(aggregator.push(candles[0].clone()).is_err());
+        for candle in candles.iter().skip(12) {
+            aggregator.push(candle.clone()).unwrap();
+        }
+        assert_eq!(aggregator.finish().unwrap(), 6);
+        let mut symbols = aggregator.symbols().clone();
+        drop(aggregator);
+
+        let mut spilled: Vec<Candle> = Vec::new();
+        let count =
+            read_spilled(buf.as_slice(), &mut symbols, Tz::UTC, |c| spilled.push(c)).unwrap();
+        assert_eq!(count, 6);
+        let expected = aggregate_candles_by(&candles, Duration::minutes(5), BucketAnchor::Epoch);
+        assert_eq!(spilled.len(), expected.len());
+        for (spilled, expected) in spilled.iter().zip(&expected) {
+            assert_eq!(spilled.timestamp, expected.timestamp);
+            assert_eq!(spilled.symbol, expected.symbol);
+            assert_eq!(spilled.volume, expected.volume);
+            assert!((spilled.open - expected.open).abs() < 1e-6);
+            assert!((spilled.high - expected.high).abs() < 1e-6);
+            assert!((spilled.low - expected.low).abs() < 1e-6);
+            assert!((spilled.close - expected.close).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_spill_to_compressed_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("spill.dbn.zst");
+        let mut aggregator = SpillAggregator::create(
+            &path,
+            "GLBX.MDP3",
+            Duration::minutes(5),
+            BucketAnchor::Epoch,
+        )
+        .unwrap();
+        for minute in 0..10 {
+            aggregator.push(candle(1, minute, 5300.0)).unwrap();
+        }
+        assert_eq!(aggregator.finish().unwrap(), 2);
+        let mut symbols = aggregator.symbols().clone();
+
+        let file = std::fs::read(&path).unwrap();
+        assert!(dbn::decode::zstd::starts_with_prefix(&file));
+        let mut spilled: Vec<Candle> = Vec::new();
+        let count =
+            read_spilled(file.as_slice(), &mut symbols, Tz::UTC, |c| spilled.push(c)).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(spilled[1].volume, 50);
+        assert_eq!(&*spilled[1].symbol, "ESM5");
+    }
+}
@@ -0,0 +1,218 @@
+//! Typed views of statistics and imbalance records.
+//!
+//! [`StatMsg`] records carry many kinds of statistics distinguished by `stat_type`.
+//! [`Settlement`] and [`OpenInterest`] extract the ones most useful for futures, and
+//! [`get_settlement()`] fetches the settlement price for a trading day, which is a
+//! better reference for the prior day than a closing candle. [`Imbalance`] converts
+//! the fixed-point prices of auction [`ImbalanceMsg`] records.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use dbn::{
+    enums::{Side, StatType},
+    ImbalanceMsg, StatMsg, UNDEF_PRICE, UNDEF_STAT_QUANTITY, UNDEF_TIMESTAMP,
+};
+
+// Bit 0 of `stat_flags` on CME settlement prices
+const FINAL_SETTLEMENT_FLAG: u8 = 1;
+
+/// A settlement price from a statistics record.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settlement {
+    /// The instrument ID of the settled instrument.
+    pub instrument_id: u32,
+    /// The trading date the price settles.
+    pub date: NaiveDate,
+    /// The settlement price.
+    pub price: f64,
+    /// Whether this is the final rather than the preliminary settlement price.
+    pub is_final: bool,
+    /// When the record was published.
+    pub ts_event: DateTime<Utc>,
+}
+
+impl Settlement {
+    /// Returns the settlement in `stat`, or `None` if it isn't a settlement price
+    /// or the price is undefined.
+    pub fn from_stat(stat: &StatMsg) -> Option<Self> {
+        if stat.stat_type().ok()? != StatType::SettlementPrice {
+            return None;
+        }
+        let ts_event = DateTime::from_timestamp_nanos(stat.hd.ts_event as i64);
+        // The reference timestamp is the trading date, when present
+        let date = if stat.ts_ref == UNDEF_TIMESTAMP {
+            ts_event.date_naive()
+        } else {
+            DateTime::from_timestamp_nanos(stat.ts_ref as i64).date_naive()
+        };
+        Some(Self {
+            instrument_id: stat.hd.instrument_id,
+            date,
+            price: to_price(stat.price)?,
+            is_final: stat.stat_flags & FINAL_SETTLEMENT_FLAG != 0,
+            ts_event,
+        })
+    }
+}
+
+/// Open interest from a statistics record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenInterest {
+    /// The instrument ID.
+    pub instrument_id: u32,
+    /// The number of open contracts.
+    pub quantity: i64,
+    /// When the record was published.
+    pub ts_event: DateTime<Utc>,
+}
+
+impl OpenInterest {
+    /// Returns the open interest in `stat`, or `None` if it isn't an open interest
+    /// statistic or the quantity is undefined.
+    pub fn from_stat(stat: &StatMsg) -> Option<Self> {
+        if stat.stat_type().ok()? != StatType::OpenInterest || stat.quantity == UNDEF_STAT_QUANTITY
+        {
+            return None;
+        }
+        Some(Self {
+            instrument_id: stat.hd.instrument_id,
+            quantity: i64::from(stat.quantity),
+            ts_event: DateTime::from_timestamp_nanos(stat.hd.ts_event as i64),
+        })
+    }
+}
+
+/// An auction imbalance with prices converted from fixed-point. Undefined prices are
+/// `None`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Imbalance {
+    /// The instrument ID.
+    pub instrument_id: u32,
+    /// When the imbalance was published.
+    pub ts_event: DateTime<Utc>,
+    /// The price at which the imbalance shares are calculated.
+    pub ref_price: Option<f64>,
+    /// The hypothetical auction-clearing price for both cross and continuous orders.
+    pub cont_book_clr_price: Option<f64>,
+    /// The hypothetical auction-clearing price for cross orders only.
+    pub auct_interest_clr_price: Option<f64>,
+    /// The quantity of shares that are eligible to be matched at `ref_price`.
+    pub paired_qty: u32,
+    /// The quantity of shares that are not paired at `ref_price`.
+    pub total_imbalance_qty: u32,
+    /// The side of the imbalance, if any.
+    pub side: Option<Side>,
+}
+
+impl From<&ImbalanceMsg> for Imbalance {
+    fn from(imbalance: &ImbalanceMsg) -> Self {
+        Self {
+            instrument_id: imbalance.hd.instrument_id,
+            ts_event: DateTime::from_timestamp_nanos(imbalance.hd.ts_event as i64),
+            ref_price: to_price(imbalance.ref_price),
+            cont_book_clr_price: to_price(imbalance.cont_book_clr_price),
+            auct_interest_clr_price: to_price(imbalance.auct_interest_clr_price),
+            paired_qty: imbalance.paired_qty,
+            total_imbalance_qty: imbalance.total_imbalance_qty,
+            side: Side::try_from(imbalance.side as u8)
+                .ok()
+                .filter(|side| *side != Side::None),
+        }
+    }
+}
+
+/// Fetches the settlement price of `symbol` for the trading day `date`, preferring
+/// the final settlement and otherwise the latest preliminary one. Returns `None` if no
+/// settlement was published that day.
+///
+/// # Errors
+/// This function returns an error when `date` is out of range, the request fails, or
+/// the response can't be decoded.
+#[cfg(feature = "historical")]
+pub async fn get_settlement(
+    client: &mut impl crate::historical::HistoricalApi,
+    dataset: &str,
+    symbol: &str,
+    stype_in: dbn::SType,
+    date: NaiveDate,
+) -> crate::Result<Option<Settlement>> {
+    use crate::timeconv::ToTimeDate;
+
+    let start = date.to_time_date()?;
+    let params = crate::historical::timeseries::GetRangeParams::builder()
+        .dataset(dataset)
+        .symbols(symbol)
+        .stype_in(stype_in)
+        .schema(dbn::Schema::Statistics)
+        .date_time_range(start)
+        .build();
+    let mut decoder = client.get_range(&params).await?;
+    let mut settlement: Option<Settlement> = None;
+    while let Some(stat) = decoder.decode_record::<StatMsg>().await? {
+        let Some(new) = Settlement::from_stat(stat).filter(|s| s.date == date) else {
+            continue;
+        };
+        // Records are in publication order, so later ones supersede earlier ones
+        if new.is_final || !settlement.is_some_and(|s| s.is_final) {
+            settlement = Some(new);
+        }
+    }
+    Ok(settlement)
+}
+
+fn to_price(px: i64) -> Option<f64> {
+    (px != UNDEF_PRICE).then_some(px as f64 * 1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+
+    fn stat(stat_type: StatType, price: i64, quantity: i32, stat_flags: u8) -> StatMsg {
+        StatMsg {
+            // 2025-04-21 21:00 UTC
+            hd: RecordHeader::new::<StatMsg>(rtype::STATISTICS, 0, 1, 1_745_269_200_000_000_000),
+            price,
+            quantity,
+            stat_type: stat_type as u16,
+            stat_flags,
+            // 2025-04-21
+            ts_ref: 1_745_193_600_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_settlement() {
+        let settlement =
+            Settlement::from_stat(&stat(StatType::SettlementPrice, 5_300_250_000_000, 0, 1))
+                .unwrap();
+        assert_eq!(settlement.price, 5300.25);
+        assert_eq!(
+            settlement.date,
+            NaiveDate::from_ymd_opt(2025, 4, 21).unwrap()
+        );
+        assert!(settlement.is_final);
+        assert!(
+            Settlement::from_stat(&stat(StatType::SettlementPrice, UNDEF_PRICE, 0, 1)).is_none()
+        );
+        assert!(
+            Settlement::from_stat(&stat(StatType::OpenInterest, 5_300_250_000_000, 0, 1)).is_none()
+        );
+    }
+
+    #[test]
+    fn test_open_interest() {
+        let oi = OpenInterest::from_stat(&stat(StatType::OpenInterest, UNDEF_PRICE, 2_000_000, 0))
+            .unwrap();
+        assert_eq!(oi.quantity, 2_000_000);
+        assert!(OpenInterest::from_stat(&stat(
+            StatType::OpenInterest,
+            UNDEF_PRICE,
+            UNDEF_STAT_QUANTITY,
+            0
+        ))
+        .is_none());
+    }
+}
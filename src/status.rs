@@ -0,0 +1,378 @@
+//! Trading status from the Status schema.
+//!
+//! [`Status`] is a typed view of a [`StatusMsg`], and [`TradingState`] condenses its
+//! many actions into whether an instrument is opening, trading, halted, or closed. A
+//! [`MarketStatusTracker`] follows the status records of each instrument to report its
+//! current state and halts, mark candles that overlap a halt with
+//! [`halted_candles()`](MarketStatusTracker::halted_candles), and flag abnormal
+//! sessions, e.g. a premarket with a halt, whose PMZ shouldn't be trusted.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use dbn::{
+    enums::{StatusAction, StatusReason, TradingEvent},
+    RecordRef, StatusMsg,
+};
+
+use crate::examples::es_futures_pmz::Candle;
+
+/// A status update with its enums decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// The instrument ID.
+    pub instrument_id: u32,
+    /// When the status changed.
+    pub ts_event: DateTime<Utc>,
+    /// The type of status change.
+    pub action: StatusAction,
+    /// The cause of the status change.
+    pub reason: StatusReason,
+    /// Further information about the status change.
+    pub trading_event: TradingEvent,
+    /// Whether the instrument is trading, if known.
+    pub is_trading: Option<bool>,
+    /// Whether the instrument is quoting, if known.
+    pub is_quoting: Option<bool>,
+    /// Whether short selling is restricted, if known.
+    pub is_short_sell_restricted: Option<bool>,
+}
+
+impl Status {
+    /// Decodes `status`, returning `None` if it has an unknown action, reason, or
+    /// trading event.
+    pub fn from_msg(status: &StatusMsg) -> Option<Self> {
+        Some(Self {
+            instrument_id: status.hd.instrument_id,
+            ts_event: DateTime::from_timestamp_nanos(status.hd.ts_event as i64),
+            action: status.action().ok()?,
+            reason: status.reason().ok()?,
+            trading_event: status.trading_event().ok()?,
+            is_trading: status.is_trading(),
+            is_quoting: status.is_quoting(),
+            is_short_sell_restricted: status.is_short_sell_restricted(),
+        })
+    }
+
+    /// Returns the trading state the update moves the instrument to, or `None` if it
+    /// doesn't change the state, e.g. a new price indication or a change in short
+    /// selling restrictions.
+    pub fn state(&self) -> Option<TradingState> {
+        match self.action {
+            StatusAction::PreOpen
+            | StatusAction::PreCross
+            | StatusAction::Quoting
+            | StatusAction::Cross
+            | StatusAction::Rotation => Some(TradingState::PreOpen),
+            StatusAction::Trading | StatusAction::PreClose => Some(TradingState::Open),
+            StatusAction::Halt | StatusAction::Pause | StatusAction::Suspend => {
+                Some(TradingState::Halted)
+            }
+            StatusAction::Close
+            | StatusAction::PostClose
+            | StatusAction::NotAvailableForTrading => Some(TradingState::Closed),
+            // Fall back to the trading flag for other actions
+            _ => match self.is_trading? {
+                true => Some(TradingState::Open),
+                false => None,
+            },
+        }
+    }
+}
+
+/// The state of trading in an instrument.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TradingState {
+    /// No status has been received.
+    #[default]
+    Unknown,
+    /// Quoting before the open, including opening auctions.
+    PreOpen,
+    /// Trading normally.
+    Open,
+    /// Trading is halted, paused, or suspended.
+    Halted,
+    /// Trading has closed.
+    Closed,
+}
+
+/// A period when trading in an instrument was halted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Halt {
+    /// When the halt started.
+    pub start: DateTime<Utc>,
+    /// When trading resumed or closed, or `None` if it's still halted.
+    pub end: Option<DateTime<Utc>>,
+    /// The cause of the halt.
+    pub reason: StatusReason,
+}
+
+impl Halt {
+    /// Returns `true` if the halt overlaps the period from `start` to `end`
+    /// (exclusive).
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.start < end && self.end.is_none_or(|halt_end| halt_end > start)
+    }
+}
+
+/// The trading status of an instrument over a period.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionStatus {
+    /// The halts overlapping the period.
+    pub halts: Vec<Halt>,
+    /// The time spent halted within the period.
+    pub halted_duration: Duration,
+    /// Whether the instrument was open for trading at any point in the period.
+    pub traded: bool,
+}
+
+impl SessionStatus {
+    /// Returns `true` if the instrument was halted or never open during the period.
+    pub fn is_abnormal(&self) -> bool {
+        !self.halts.is_empty() || !self.traded
+    }
+}
+
+/// Tracks the trading state of each instrument from its status records.
+#[derive(Debug, Clone, Default)]
+pub struct MarketStatusTracker {
+    // The state changes of each instrument in timestamp order
+    transitions: HashMap<u32, Vec<Transition>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Transition {
+    ts: DateTime<Utc>,
+    state: TradingState,
+    reason: StatusReason,
+}
+
+impl MarketStatusTracker {
+    /// Creates a tracker without any status.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the tracker from `rec` if it's a status record. Returns `true` if the
+    /// record was used.
+    pub fn on_record(&mut self, rec: &RecordRef) -> bool {
+        match rec.get::<StatusMsg>() {
+            Some(status) => {
+                self.on_status(status);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Updates the state of the instrument of `status`. Records must be in timestamp
+    /// order for each instrument.
+    pub fn on_status(&mut self, status: &StatusMsg) {
+        let Some(status) = Status::from_msg(status) else {
+            return;
+        };
+        let Some(state) = status.state() else {
+            return;
+        };
+        let transitions = self.transitions.entry(status.instrument_id).or_default();
+        if transitions.last().is_some_and(|last| last.state == state) {
+            return;
+        }
+        transitions.push(Transition {
+            ts: status.ts_event,
+            state,
+            reason: status.reason,
+        });
+    }
+
+    /// Returns the latest trading state of `instrument_id`.
+    pub fn state(&self, instrument_id: u32) -> TradingState {
+        self.transitions
+            .get(&instrument_id)
+            .and_then(|transitions| transitions.last())
+            .map_or(TradingState::Unknown, |transition| transition.state)
+    }
+
+    /// Returns the trading state of `instrument_id` at `ts`.
+    pub fn state_at(&self, instrument_id: u32, ts: DateTime<Utc>) -> TradingState {
+        self.transitions
+            .get(&instrument_id)
+            .and_then(|transitions| {
+                transitions
+                    .iter()
+                    .take_while(|transition| transition.ts <= ts)
+                    .last()
+            })
+            .map_or(TradingState::Unknown, |transition| transition.state)
+    }
+
+    /// Returns the halts of `instrument_id` in timestamp order.
+    pub fn halts(&self, instrument_id: u32) -> Vec<Halt> {
+        let Some(transitions) = self.transitions.get(&instrument_id) else {
+            return Vec::new();
+        };
+        let mut halts: Vec<Halt> = Vec::new();
+        for transition in transitions {
+            match (transition.state, halts.last_mut()) {
+                (TradingState::Halted, _) => halts.push(Halt {
+                    start: transition.ts,
+                    end: None,
+                    reason: transition.reason,
+                }),
+                (_, Some(halt)) if halt.end.is_none() => halt.end = Some(transition.ts),
+                _ => {}
+            }
+        }
+        halts
+    }
+
+    /// Returns the status of `instrument_id` from `start` to `end` (exclusive).
+    pub fn session_status(
+        &self,
+        instrument_id: u32,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> SessionStatus {
+        let halts: Vec<_> = self
+            .halts(instrument_id)
+            .into_iter()
+            .filter(|halt| halt.overlaps(start, end))
+            .collect();
+        let halted_duration = halts
+            .iter()
+            .map(|halt| halt.end.map_or(end, |halt_end| halt_end.min(end)) - halt.start.max(start))
+            .sum();
+        let traded = self.state_at(instrument_id, start) == TradingState::Open
+            || self
+                .transitions
+                .get(&instrument_id)
+                .is_some_and(|transitions| {
+                    transitions.iter().any(|transition| {
+                        transition.state == TradingState::Open
+                            && transition.ts >= start
+                            && transition.ts < end
+                    })
+                });
+        SessionStatus {
+            halts,
+            halted_duration,
+            traded,
+        }
+    }
+
+    /// Returns whether each of `candles`, which span `interval` from their timestamp,
+    /// overlaps a halt of its instrument.
+    pub fn halted_candles<P>(&self, candles: &[Candle<P>], interval: Duration) -> Vec<bool> {
+        let mut halts: HashMap<u32, Vec<Halt>> = HashMap::new();
+        candles
+            .iter()
+            .map(|candle| {
+                let start = candle.timestamp.with_timezone(&Utc);
+                halts
+                    .entry(candle.instrument_id)
+                    .or_insert_with(|| self.halts(candle.instrument_id))
+                    .iter()
+                    .any(|halt| halt.overlaps(start, start + interval))
+            })
+            .collect()
+    }
+
+    /// Forgets the status of every instrument, e.g. between sessions.
+    pub fn reset(&mut self) {
+        self.transitions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::America::New_York;
+    use dbn::{rtype, RecordHeader};
+
+    use super::*;
+    use crate::test_util;
+
+    fn ts(hour: u32, minute: u32) -> DateTime<Utc> {
+        New_York
+            .with_ymd_and_hms(2025, 4, 22, hour, minute, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn status(at: DateTime<Utc>, action: StatusAction, reason: StatusReason) -> StatusMsg {
+        StatusMsg {
+            hd: RecordHeader::new::<StatusMsg>(
+                rtype::STATUS,
+                1,
+                1,
+                at.timestamp_nanos_opt().unwrap() as u64,
+            ),
+            action: action as u16,
+            reason: reason as u16,
+            is_trading: b'~' as std::ffi::c_char,
+            ..Default::default()
+        }
+    }
+
+    fn tracker() -> MarketStatusTracker {
+        let mut tracker = MarketStatusTracker::new();
+        for (at, action, reason) in [
+            (ts(9, 0), StatusAction::PreOpen, StatusReason::Scheduled),
+            (ts(9, 30), StatusAction::Trading, StatusReason::Scheduled),
+            (ts(10, 0), StatusAction::Halt, StatusReason::LuldPause),
+            (ts(10, 5), StatusAction::Trading, StatusReason::Scheduled),
+        ] {
+            assert!(tracker.on_record(&RecordRef::from(&status(at, action, reason))));
+        }
+        tracker
+    }
+
+    #[test]
+    fn test_tracker() {
+        let tracker = tracker();
+        assert_eq!(tracker.state(1), TradingState::Open);
+        assert_eq!(tracker.state(2), TradingState::Unknown);
+        assert_eq!(tracker.state_at(1, ts(9, 15)), TradingState::PreOpen);
+        assert_eq!(tracker.state_at(1, ts(10, 2)), TradingState::Halted);
+        let halts = tracker.halts(1);
+        assert_eq!(
+            halts,
+            [Halt {
+                start: ts(10, 0),
+                end: Some(ts(10, 5)),
+                reason: StatusReason::LuldPause,
+            }]
+        );
+
+        let premarket = tracker.session_status(1, ts(9, 0), ts(9, 30));
+        assert!(!premarket.traded);
+        assert!(premarket.is_abnormal());
+        let morning = tracker.session_status(1, ts(9, 30), ts(12, 0));
+        assert_eq!(morning.halts.len(), 1);
+        assert_eq!(morning.halted_duration, Duration::minutes(5));
+        assert!(morning.is_abnormal());
+        assert!(!tracker
+            .session_status(1, ts(10, 5), ts(16, 0))
+            .is_abnormal());
+    }
+
+    #[test]
+    fn test_halted_candles() {
+        let tracker = tracker();
+        let candles: Vec<Candle> = [(9, 55), (10, 0), (10, 5)]
+            .into_iter()
+            .map(|(hour, minute)| {
+                test_util::candle()
+                    .timestamp(ts(hour, minute).with_timezone(&New_York))
+                    .symbol("AAPL")
+                    .price(200.0)
+                    .volume(0)
+                    .build()
+            })
+            .collect();
+        assert_eq!(
+            tracker.halted_candles(&candles, Duration::minutes(5)),
+            [false, true, false]
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Starting live candles mid-session with the earlier part of the session filled in
+//! from historical data.
+//!
+//! A [`StitchedSource`] fetches the 1-minute candles from a start time up to the end
+//! of the available historical data, then subscribes a [`LiveClient`] with intraday
+//! replay from the last historical candle. Live candles that were already returned
+//! from history are dropped, so [`next_candle()`](StitchedSource::next_candle) returns
+//! each minute once and in order. This lets a [`LiveCandleBuilder`] or [`PmzTracker`]
+//! started after the open see the whole session.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), databento::examples::es_futures_pmz::PmzError> {
+//! use chrono::{TimeZone, Utc};
+//! use databento::{
+//!     examples::es_futures_pmz::{PmzConfig, PmzTracker},
+//!     stitched::{StitchParams, StitchedSource},
+//!     HistoricalClient, LiveClient,
+//! };
+//!
+//! let mut historical = HistoricalClient::builder().key_from_env()?.build()?;
+//! let live = LiveClient::builder()
+//!     .key_from_env()?
+//!     .dataset("GLBX.MDP3")
+//!     .build()
+//!     .await?;
+//! let params = StitchParams::builder()
+//!     .dataset("GLBX.MDP3")
+//!     .symbol("ES.c.0")
+//!     .stype_in(databento::dbn::SType::Continuous)
+//!     .start(Utc.with_ymd_and_hms(2025, 4, 21, 8, 0, 0).unwrap())
+//!     .build();
+//! let config = PmzConfig::default();
+//! let mut tracker = PmzTracker::seed(&mut historical, &config, params.start.date_naive()).await?;
+//! let mut source = StitchedSource::start(&mut historical, live, &params).await?;
+//! while let Some(candle) = source.next_candle().await? {
+//!     if let Some(event) = tracker.update(&candle) {
+//!         println!("{event:?}");
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`LiveCandleBuilder`]: crate::examples::es_futures_pmz::LiveCandleBuilder
+//! [`PmzTracker`]: crate::examples::es_futures_pmz::PmzTracker
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use dbn::{OhlcvMsg, SType, Schema};
+use typed_builder::TypedBuilder;
+
+use crate::{
+    examples::es_futures_pmz::{
+        fetch_candles, to_offset_date_time, to_time_date, Candle, Result, DEFAULT_CANDLE_TZ,
+    },
+    historical::{DateRange, DateTimeRange, HistoricalApi},
+    live::Subscription,
+    LiveClient,
+};
+
+/// The parameters for [`StitchedSource::start()`]. Use [`StitchParams::builder()`]
+/// to get a builder type with all the preset defaults.
+#[derive(Debug, Clone, TypedBuilder, PartialEq, Eq)]
+pub struct StitchParams {
+    /// The dataset code. Must match the dataset of the live client.
+    #[builder(setter(transform = |dt: impl ToString| dt.to_string()))]
+    pub dataset: String,
+    /// The symbol to stream.
+    #[builder(setter(transform = |symbol: impl ToString| symbol.to_string()))]
+    pub symbol: String,
+    /// The symbology type of `symbol`. Defaults to
+    /// [`RawSymbol`](dbn::enums::SType::RawSymbol).
+    #[builder(default = SType::RawSymbol)]
+    pub stype_in: SType,
+    /// The time of the first candle (inclusive).
+    pub start: DateTime<Utc>,
+}
+
+/// A stream of 1-minute candles that starts with historical data and continues with
+/// live data.
+///
+/// Candles have timestamps in [`DEFAULT_CANDLE_TZ`] and the symbol from the
+/// [`StitchParams`].
+#[derive(Debug)]
+pub struct StitchedSource {
+    live: LiveClient,
+    backlog: VecDeque<Candle>,
+    overlap: Overlap,
+    symbol: Arc<str>,
+}
+
+impl StitchedSource {
+    /// Fetches the candles since `params.start` from `historical`, then subscribes
+    /// `live` to 1-minute bars with intraday replay from the last historical candle and
+    /// starts the session. `live` must not have been started.
+    ///
+    /// The live gateway only replays the last 24 hours, so if historical data isn't
+    /// yet available for part of that window, candles before the replay window will be
+    /// missing.
+    ///
+    /// # Errors
+    /// This function returns an error when the historical request fails, the symbol
+    /// can't be resolved, or subscribing or starting the live session fails.
+    pub async fn start(
+        historical: &mut (impl HistoricalApi + Send),
+        mut live: LiveClient,
+        params: &StitchParams,
+    ) -> Result<Self> {
+        let history = fetch_history(historical, params, Utc::now()).await?;
+        let mut overlap = Overlap::default();
+        for candle in &history {
+            overlap.observe(candle.instrument_id, candle_ts(candle));
+        }
+        let replay_start = history
+            .last()
+            .map_or(params.start, |candle| candle.timestamp.with_timezone(&Utc));
+        tracing::debug!(
+            candles = history.len(),
+            %replay_start,
+            "Fetched historical candles, starting live replay"
+        );
+        live.subscribe(
+            Subscription::builder()
+                .symbols(params.symbol.as_str())
+                .schema(Schema::Ohlcv1M)
+                .stype_in(params.stype_in)
+                .start(to_offset_date_time(replay_start)?)
+                .build(),
+        )
+        .await?;
+        live.start().await?;
+        Ok(Self {
+            live,
+            backlog: history.into(),
+            overlap,
+            symbol: Arc::from(params.symbol.as_str()),
+        })
+    }
+
+    /// Returns the next candle: first the historical candles, then live ones as each
+    /// minute completes. Returns `Ok(None)` once the live session ends.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to read from the live gateway or a
+    /// record has an out-of-range timestamp.
+    pub async fn next_candle(&mut self) -> Result<Option<Candle>> {
+        if let Some(candle) = self.backlog.pop_front() {
+            return Ok(Some(candle));
+        }
+        while let Some(rec) = self.live.next_record().await? {
+            if let Some(ohlcv) = rec.get::<OhlcvMsg>() {
+                if self.overlap.accept(ohlcv) {
+                    return Candle::try_with_symbol(ohlcv, self.symbol.clone(), DEFAULT_CANDLE_TZ)
+                        .map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the number of historical candles that haven't been returned yet.
+    pub fn backlog_len(&self) -> usize {
+        self.backlog.len()
+    }
+
+    /// Returns the live client.
+    pub fn live(&self) -> &LiveClient {
+        &self.live
+    }
+
+    /// Returns the live client, e.g. to close the session.
+    pub fn into_live(self) -> LiveClient {
+        self.live
+    }
+}
+
+// Fetches the candles from `params.start` to the end of the available data before `now`
+async fn fetch_history(
+    historical: &mut (impl HistoricalApi + Send),
+    params: &StitchParams,
+    now: DateTime<Utc>,
+) -> Result<Vec<Candle>> {
+    if params.start >= now {
+        return Ok(Vec::new());
+    }
+    let start_date = to_time_date(params.start.date_naive())?;
+    let end_date = to_time_date(now.date_naive())?;
+    let availability = historical
+        .get_data_availability(
+            &params.dataset,
+            DateRange::from((start_date, end_date.next_day().unwrap_or(end_date))),
+        )
+        .await?;
+    let requested = DateTimeRange::from((
+        to_offset_date_time(params.start)?,
+        to_offset_date_time(now)?,
+    ));
+    let Some(range) = availability.clamp(&requested) else {
+        return Ok(Vec::new());
+    };
+    fetch_candles(
+        historical,
+        &params.dataset,
+        &params.symbol,
+        params.stype_in,
+        range,
+        1,
+        DEFAULT_CANDLE_TZ,
+    )
+    .await
+}
+
+fn candle_ts(candle: &Candle) -> u64 {
+    candle.timestamp.timestamp_nanos_opt().unwrap_or_default() as u64
+}
+
+// The latest bar returned per instrument, for dropping replayed bars that were already
+// returned from history
+#[derive(Debug, Default)]
+struct Overlap {
+    last_ts_event: HashMap<u32, u64>,
+}
+
+impl Overlap {
+    fn observe(&mut self, instrument_id: u32, ts_event: u64) {
+        let last = self.last_ts_event.entry(instrument_id).or_default();
+        *last = (*last).max(ts_event);
+    }
+
+    // Returns `true` if `ohlcv` is newer than every bar returned for its instrument
+    fn accept(&mut self, ohlcv: &OhlcvMsg) -> bool {
+        let instrument_id = ohlcv.hd.instrument_id;
+        if self
+            .last_ts_event
+            .get(&instrument_id)
+            .is_some_and(|last| ohlcv.hd.ts_event <= *last)
+        {
+            return false;
+        }
+        self.observe(instrument_id, ohlcv.hd.ts_event);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_util;
+
+    fn bar(instrument_id: u32, ts_event: DateTime<Utc>) -> OhlcvMsg {
+        test_util::ohlcv(
+            instrument_id,
+            ts_event.timestamp_nanos_opt().unwrap() as u64,
+        )
+    }
+
+    #[test]
+    fn test_overlap_drops_replayed_bars() {
+        let start = Utc.with_ymd_and_hms(2025, 4, 21, 13, 30, 0).unwrap();
+        let minute = chrono::Duration::minutes(1);
+        let mut overlap = Overlap::default();
+        let candle = Candle::try_with_tz(&bar(42, start), "ES.c.0", DEFAULT_CANDLE_TZ).unwrap();
+        overlap.observe(candle.instrument_id, candle_ts(&candle));
+        // Replay starts at the last historical bar
+        assert!(!overlap.accept(&bar(42, start)));
+        assert!(overlap.accept(&bar(42, start + minute)));
+        assert!(!overlap.accept(&bar(42, start + minute)));
+        // Other instruments, e.g. after a roll, are tracked separately
+        assert!(overlap.accept(&bar(43, start)));
+        assert!(overlap.accept(&bar(42, start + minute * 2)));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_fetch_history_clamps_to_available() {
+        use crate::{
+            historical::metadata::{DataAvailability, DatasetRange},
+            testing::MockHistoricalClient,
+        };
+
+        let start = Utc.with_ymd_and_hms(2025, 4, 21, 13, 30, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 4, 21, 14, 0, 0).unwrap();
+        let available_end = Utc.with_ymd_and_hms(2025, 4, 21, 13, 45, 0).unwrap();
+        let records = [
+            bar(42, start),
+            bar(42, start + chrono::Duration::minutes(1)),
+        ];
+        let mut client = MockHistoricalClient::new()
+            .with_records("GLBX.MDP3", Schema::Ohlcv1M, &records)
+            .unwrap()
+            .with_availability(DataAvailability {
+                dataset: "GLBX.MDP3".to_owned(),
+                range: DatasetRange {
+                    start: to_offset_date_time(start - chrono::Duration::days(1)).unwrap(),
+                    end: to_offset_date_time(available_end).unwrap(),
+                },
+                conditions: Vec::new(),
+            });
+        let params = StitchParams::builder()
+            .dataset("GLBX.MDP3")
+            .symbol("ES.c.0")
+            .stype_in(SType::Continuous)
+            .start(start)
+            .build();
+        let candles = fetch_history(&mut client, &params, now).await.unwrap();
+        assert_eq!(candles.len(), 2);
+        assert_eq!(&*candles[0].symbol, "ES.c.0");
+        assert_eq!(
+            client.requests()[0].date_time_range,
+            DateTimeRange::from((
+                to_offset_date_time(start).unwrap(),
+                to_offset_date_time(available_end).unwrap()
+            ))
+        );
+        // Nothing to fetch when starting in the future
+        let candles = fetch_history(&mut client, &params, start).await.unwrap();
+        assert!(candles.is_empty());
+        assert_eq!(client.requests().len(), 1);
+    }
+}
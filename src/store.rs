@@ -0,0 +1,205 @@
+//! Merging candles from multiple, possibly overlapping, queries.
+//!
+//! Incremental backfills often request ranges that overlap earlier ones. Aggregating
+//! the concatenated results double-counts volume, so [`CandleStore`] deduplicates
+//! candles by instrument and timestamp and reports candles that disagree.
+
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use crate::examples::es_futures_pmz::{Candle, CandlePrice};
+
+/// How [`CandleStore`] resolves a candle that conflicts with one already stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ConflictPolicy {
+    /// Keep the stored candle.
+    #[default]
+    KeepExisting,
+    /// Replace the stored candle with the incoming one, e.g. when later queries
+    /// include corrections.
+    Replace,
+}
+
+/// Two candles for the same instrument and timestamp with different values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleConflict<P = f64> {
+    /// The candle that was stored.
+    pub existing: Candle<P>,
+    /// The candle being merged.
+    pub incoming: Candle<P>,
+}
+
+/// The outcome of [`CandleStore::merge()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeReport<P = f64> {
+    /// The number of new candles added.
+    pub inserted: usize,
+    /// The number of candles identical to one already stored.
+    pub duplicates: usize,
+    /// Candles that differed from one already stored.
+    pub conflicts: Vec<CandleConflict<P>>,
+}
+
+impl<P> Default for MergeReport<P> {
+    fn default() -> Self {
+        Self {
+            inserted: 0,
+            duplicates: 0,
+            conflicts: Vec::new(),
+        }
+    }
+}
+
+/// A set of candles unique by instrument ID and timestamp, ordered by timestamp and
+/// then instrument ID.
+#[derive(Debug, Clone)]
+pub struct CandleStore<P = f64> {
+    candles: BTreeMap<(DateTime<Tz>, u32), Candle<P>>,
+    policy: ConflictPolicy,
+}
+
+impl<P> Default for CandleStore<P> {
+    fn default() -> Self {
+        Self {
+            candles: BTreeMap::new(),
+            policy: ConflictPolicy::default(),
+        }
+    }
+}
+
+impl<P: CandlePrice> CandleStore<P> {
+    /// Creates an empty store that keeps existing candles on conflicts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how conflicting candles are resolved.
+    pub fn with_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Merges `candles` into the store, skipping duplicates and resolving conflicts
+    /// according to the store's [`ConflictPolicy`].
+    pub fn merge(&mut self, candles: impl IntoIterator<Item = Candle<P>>) -> MergeReport<P> {
+        let mut report = MergeReport::default();
+        for candle in candles {
+            let key = (candle.timestamp, candle.instrument_id);
+            match self.candles.get_mut(&key) {
+                None => {
+                    self.candles.insert(key, candle);
+                    report.inserted += 1;
+                }
+                Some(existing) if same_values(existing, &candle) => report.duplicates += 1,
+                Some(existing) => {
+                    let conflict = match self.policy {
+                        ConflictPolicy::KeepExisting => CandleConflict {
+                            existing: existing.clone(),
+                            incoming: candle,
+                        },
+                        ConflictPolicy::Replace => CandleConflict {
+                            incoming: candle.clone(),
+                            existing: std::mem::replace(existing, candle),
+                        },
+                    };
+                    report.conflicts.push(conflict);
+                }
+            }
+        }
+        report
+    }
+
+    /// Returns the number of stored candles.
+    pub fn len(&self) -> usize {
+        self.candles.len()
+    }
+
+    /// Returns `true` if the store contains no candles.
+    pub fn is_empty(&self) -> bool {
+        self.candles.is_empty()
+    }
+
+    /// Returns an iterator over the stored candles in timestamp order.
+    pub fn iter(&self) -> impl Iterator<Item = &Candle<P>> {
+        self.candles.values()
+    }
+
+    /// Returns an iterator over the stored candles for `instrument_id` in timestamp
+    /// order.
+    pub fn iter_instrument(&self, instrument_id: u32) -> impl Iterator<Item = &Candle<P>> {
+        self.iter()
+            .filter(move |candle| candle.instrument_id == instrument_id)
+    }
+
+    /// Returns the stored candles in timestamp order.
+    pub fn into_vec(self) -> Vec<Candle<P>> {
+        self.candles.into_values().collect()
+    }
+}
+
+impl<P: CandlePrice> FromIterator<Candle<P>> for CandleStore<P> {
+    fn from_iter<T: IntoIterator<Item = Candle<P>>>(iter: T) -> Self {
+        let mut store = Self::new();
+        store.merge(iter);
+        store
+    }
+}
+
+fn same_values<P: CandlePrice>(a: &Candle<P>, b: &Candle<P>) -> bool {
+    a.open == b.open
+        && a.high == b.high
+        && a.low == b.low
+        && a.close == b.close
+        && a.volume == b.volume
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        examples::es_futures_pmz::aggregate_candles,
+        test_util::{self, eastern},
+    };
+
+    fn candle(instrument_id: u32, minute: u32, close: f64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2025, 4, 22, 9, minute))
+            .instrument_id(instrument_id)
+            .ohlc(5300.0, 5310.0, 5290.0, close)
+            .build()
+    }
+
+    #[test]
+    fn test_merge_overlapping() {
+        let mut store: CandleStore = (30..35).map(|m| candle(1, m, 5300.0)).collect();
+        let report = store.merge((33..38).map(|m| candle(1, m, 5300.0)));
+        assert_eq!(report.inserted, 3);
+        assert_eq!(report.duplicates, 2);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(store.len(), 8);
+        let agg = aggregate_candles(&store.into_vec(), 5);
+        assert_eq!(agg[0].volume, 50);
+        assert_eq!(agg[1].volume, 30);
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let mut store: CandleStore = [candle(1, 30, 5300.0), candle(2, 30, 5300.0)]
+            .into_iter()
+            .collect();
+        let report = store.merge([candle(1, 30, 5301.0)]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].existing.close, 5300.0);
+        assert_eq!(report.conflicts[0].incoming.close, 5301.0);
+        assert_eq!(store.iter_instrument(1).next().unwrap().close, 5300.0);
+
+        let mut store = store.with_policy(ConflictPolicy::Replace);
+        let report = store.merge([candle(1, 30, 5302.0)]);
+        assert_eq!(report.conflicts[0].existing.close, 5300.0);
+        assert_eq!(report.conflicts[0].incoming.close, 5302.0);
+        assert_eq!(store.iter_instrument(1).next().unwrap().close, 5302.0);
+        assert_eq!(store.len(), 2);
+    }
+}
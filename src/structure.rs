@@ -0,0 +1,279 @@
+//! Swing highs and lows and market structure.
+//!
+//! A swing high is a candle whose high is above the highs of the `lookback` candles on
+//! either side of it, a fractal pivot, and a swing low is the reverse. Each swing is
+//! labeled by comparing it with the previous swing of the same kind, e.g. a
+//! [`HigherHigh`](StructureLabel::HigherHigh), and the latest labels give the
+//! [`Trend`]. Swings can be compared with the PMZ or other key levels to find where
+//! structure formed at a level.
+//!
+//! [`StructureDetector`] finds swings as candles are pushed, confirming each once
+//! `lookback` later candles have arrived, and [`find_swings()`] scans a whole series.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use crate::examples::es_futures_pmz::{Candle, CandlePrice};
+
+/// Whether a swing is a high or a low.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SwingKind {
+    /// A local high.
+    High,
+    /// A local low.
+    Low,
+}
+
+/// How a swing compares with the previous swing of the same kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StructureLabel {
+    /// A swing high above the previous swing high.
+    HigherHigh,
+    /// A swing high below the previous swing high.
+    LowerHigh,
+    /// A swing low above the previous swing low.
+    HigherLow,
+    /// A swing low below the previous swing low.
+    LowerLow,
+}
+
+/// The direction of the market structure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Trend {
+    /// Higher highs and higher lows.
+    Up,
+    /// Lower highs and lower lows.
+    Down,
+    /// Mixed labels or not enough swings.
+    #[default]
+    Sideways,
+}
+
+/// A swing high or low.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swing {
+    /// Whether it's a high or a low.
+    pub kind: SwingKind,
+    /// How it compares with the previous swing of the same kind, or `None` for the
+    /// first one or one at the same price.
+    pub label: Option<StructureLabel>,
+    /// The high of a swing high or the low of a swing low.
+    pub price: f64,
+    /// The start of the pivot candle.
+    pub timestamp: DateTime<Tz>,
+    /// The start of the candle that confirmed the swing.
+    pub confirmed_at: DateTime<Tz>,
+    /// The instrument ID of the candles.
+    pub instrument_id: u32,
+    /// The symbol of the candles.
+    pub symbol: Arc<str>,
+}
+
+/// Finds swings in candles as they're pushed, tracking the structure of each
+/// instrument separately.
+#[derive(Debug, Clone)]
+pub struct StructureDetector<P = f64> {
+    lookback: usize,
+    instruments: HashMap<u32, InstrumentStructure<P>>,
+}
+
+#[derive(Debug, Clone)]
+struct InstrumentStructure<P> {
+    // The last `2 * lookback + 1` candles
+    window: VecDeque<Candle<P>>,
+    last_high: Option<f64>,
+    last_low: Option<f64>,
+    high_label: Option<StructureLabel>,
+    low_label: Option<StructureLabel>,
+}
+
+impl<P> Default for InstrumentStructure<P> {
+    fn default() -> Self {
+        Self {
+            window: VecDeque::new(),
+            last_high: None,
+            last_low: None,
+            high_label: None,
+            low_label: None,
+        }
+    }
+}
+
+impl<P: CandlePrice> StructureDetector<P> {
+    /// Creates a detector for swings with `lookback` candles on either side, e.g. `2`
+    /// for the classic five-candle fractal.
+    ///
+    /// # Panics
+    /// This function panics when `lookback` is zero.
+    pub fn new(lookback: usize) -> Self {
+        assert!(lookback > 0, "lookback must be positive");
+        Self {
+            lookback,
+            instruments: HashMap::new(),
+        }
+    }
+
+    /// Returns the swings confirmed by `candle`, which must be later than the previous
+    /// candle pushed for its instrument. A pivot candle can be both a swing high and a
+    /// swing low.
+    pub fn push(&mut self, candle: &Candle<P>) -> Vec<Swing> {
+        let lookback = self.lookback;
+        let state = self.instruments.entry(candle.instrument_id).or_default();
+        state.window.push_back(candle.clone());
+        if state.window.len() < 2 * lookback + 1 {
+            return Vec::new();
+        }
+        let pivot = &state.window[lookback];
+        let others = || {
+            state
+                .window
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != lookback)
+                .map(|(_, c)| c)
+        };
+        let is_high = others().all(|c| c.high < pivot.high);
+        let is_low = others().all(|c| c.low > pivot.low);
+        let pivot = pivot.clone();
+        state.window.pop_front();
+
+        let mut swings = Vec::new();
+        if is_high {
+            let price = pivot.high.to_f64();
+            let label = match state.last_high {
+                Some(last) if price > last => Some(StructureLabel::HigherHigh),
+                Some(last) if price < last => Some(StructureLabel::LowerHigh),
+                _ => None,
+            };
+            state.last_high = Some(price);
+            state.high_label = label.or(state.high_label);
+            swings.push(swing(SwingKind::High, label, price, &pivot, candle));
+        }
+        if is_low {
+            let price = pivot.low.to_f64();
+            let label = match state.last_low {
+                Some(last) if price > last => Some(StructureLabel::HigherLow),
+                Some(last) if price < last => Some(StructureLabel::LowerLow),
+                _ => None,
+            };
+            state.last_low = Some(price);
+            state.low_label = label.or(state.low_label);
+            swings.push(swing(SwingKind::Low, label, price, &pivot, candle));
+        }
+        swings
+    }
+
+    /// Returns the trend of `instrument_id` from its latest labeled swing high and low.
+    pub fn trend(&self, instrument_id: u32) -> Trend {
+        let Some(state) = self.instruments.get(&instrument_id) else {
+            return Trend::Sideways;
+        };
+        match (state.high_label, state.low_label) {
+            (Some(StructureLabel::HigherHigh), Some(StructureLabel::HigherLow)) => Trend::Up,
+            (Some(StructureLabel::LowerHigh), Some(StructureLabel::LowerLow)) => Trend::Down,
+            _ => Trend::Sideways,
+        }
+    }
+
+    /// Forgets the candles and swings of every instrument, e.g. between sessions.
+    pub fn reset(&mut self) {
+        self.instruments.clear();
+    }
+}
+
+/// Returns the swings in `candles`, which must be in timestamp order, with `lookback`
+/// candles on either side, in the order they were confirmed.
+///
+/// # Panics
+/// This function panics when `lookback` is zero.
+pub fn find_swings<P: CandlePrice>(candles: &[Candle<P>], lookback: usize) -> Vec<Swing> {
+    let mut detector = StructureDetector::new(lookback);
+    candles
+        .iter()
+        .flat_map(|candle| detector.push(candle))
+        .collect()
+}
+
+fn swing<P>(
+    kind: SwingKind,
+    label: Option<StructureLabel>,
+    price: f64,
+    pivot: &Candle<P>,
+    confirmed_by: &Candle<P>,
+) -> Swing {
+    Swing {
+        kind,
+        label,
+        price,
+        timestamp: pivot.timestamp,
+        confirmed_at: confirmed_by.timestamp,
+        instrument_id: pivot.instrument_id,
+        symbol: pivot.symbol.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn candles(highs: &[f64]) -> Vec<Candle> {
+        highs
+            .iter()
+            .enumerate()
+            .map(|(i, &high)| {
+                test_util::candle()
+                    .timestamp(eastern(2025, 4, 22, 9, 30 + i as u32))
+                    .ohlc(high - 1.0, high, high - 2.0, high - 1.0)
+                    .build()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_swings() {
+        // Peaks at 3, 7, and 11, troughs at 5 and 9
+        let candles = candles(&[
+            5300.0, 5301.0, 5302.0, 5305.0, 5303.0, 5299.0, 5304.0, 5308.0, 5306.0, 5302.0, 5303.0,
+            5307.0, 5305.0, 5304.0,
+        ]);
+        let swings = find_swings(&candles, 1);
+        let summary: Vec<_> = swings.iter().map(|s| (s.kind, s.label, s.price)).collect();
+        assert_eq!(
+            summary,
+            [
+                (SwingKind::High, None, 5305.0),
+                (SwingKind::Low, None, 5297.0),
+                (SwingKind::High, Some(StructureLabel::HigherHigh), 5308.0),
+                (SwingKind::Low, Some(StructureLabel::HigherLow), 5300.0),
+                (SwingKind::High, Some(StructureLabel::LowerHigh), 5307.0),
+            ]
+        );
+        assert_eq!(swings[0].timestamp, candles[3].timestamp);
+        assert_eq!(swings[0].confirmed_at, candles[4].timestamp);
+    }
+
+    #[test]
+    fn test_trend() {
+        let mut detector = StructureDetector::new(1);
+        let candles = candles(&[
+            5300.0, 5305.0, 5302.0, 5304.0, 5310.0, 5306.0, 5308.0, 5312.0, 5309.0,
+        ]);
+        for candle in &candles[..6] {
+            detector.push(candle);
+        }
+        assert_eq!(detector.trend(1), Trend::Sideways);
+        for candle in &candles[6..] {
+            detector.push(candle);
+        }
+        assert_eq!(detector.trend(1), Trend::Up);
+        assert_eq!(detector.trend(2), Trend::Sideways);
+        detector.reset();
+        assert_eq!(detector.trend(1), Trend::Sideways);
+    }
+}
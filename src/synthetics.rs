@@ -0,0 +1,407 @@
+//! Pricing calendar spreads, butterflies, and other synthetic instruments from the
+//! prices of their legs.
+//!
+//! A [`Synthetic`] is a weighted combination of outright legs, e.g. `ESM5-ESU5` buys
+//! the June contract and sells the September one. Request the legs with
+//! [`SType::Parent`](dbn::SType::Parent) and [`Synthetic::parent_symbols()`] to get
+//! every leg in a single request, then combine their candles with
+//! [`synthetic_candles()`] or their quotes with [`SyntheticCandleBuilder`]. Both
+//! produce ordinary [`Candle`]s, so synthetic prices can be aggregated further with
+//! [`aggregate_candles_by()`](crate::examples::es_futures_pmz::aggregate_candles_by)
+//! like any other candles.
+
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+
+use chrono::Duration;
+use chrono_tz::Tz;
+use dbn::{Record, RecordRef};
+
+use crate::{
+    bars::QuoteCandleBuilder,
+    examples::es_futures_pmz::{BucketAnchor, Candle, CandlePrice},
+    quotes::QuoteBoard,
+};
+
+// CME month codes
+const MONTH_CODES: &str = "FGHJKMNQUVXZ";
+
+/// One outright leg of a [`Synthetic`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Leg {
+    /// The raw symbol of the leg, e.g. `ESM5`.
+    pub symbol: String,
+    /// The number of contracts of the leg per unit of the synthetic, positive when
+    /// bought and negative when sold.
+    pub ratio: i64,
+}
+
+/// A synthetic instrument priced as the sum of the prices of its legs weighted by
+/// their ratios.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Synthetic {
+    name: Arc<str>,
+    legs: Vec<Leg>,
+}
+
+impl Synthetic {
+    /// Creates a synthetic named `name` from `legs`.
+    ///
+    /// # Errors
+    /// This function returns an error when `legs` is empty, a leg has a ratio of zero,
+    /// or a symbol appears in more than one leg.
+    pub fn new(name: impl ToString, legs: Vec<Leg>) -> crate::Result<Self> {
+        if legs.is_empty() {
+            return Err(crate::Error::bad_arg(
+                "legs",
+                "must contain at least one leg",
+            ));
+        }
+        for (i, leg) in legs.iter().enumerate() {
+            if leg.ratio == 0 {
+                return Err(crate::Error::bad_arg(
+                    "legs",
+                    format!("ratio of {} can't be zero", leg.symbol),
+                ));
+            }
+            if legs[..i].iter().any(|other| other.symbol == leg.symbol) {
+                return Err(crate::Error::bad_arg(
+                    "legs",
+                    format!("{} appears more than once", leg.symbol),
+                ));
+            }
+        }
+        Ok(Self {
+            name: name.to_string().into(),
+            legs,
+        })
+    }
+
+    /// Creates a calendar spread that buys `front` and sells `back`, named
+    /// `{front}-{back}` as on CME.
+    pub fn calendar_spread(front: &str, back: &str) -> Self {
+        Self {
+            name: format!("{front}-{back}").into(),
+            legs: vec![leg(front, 1), leg(back, -1)],
+        }
+    }
+
+    /// Creates a butterfly that buys `front` and `back` and sells two of `middle`,
+    /// named `{front}-{middle}-{back}`.
+    pub fn butterfly(front: &str, middle: &str, back: &str) -> Self {
+        Self {
+            name: format!("{front}-{middle}-{back}").into(),
+            legs: vec![leg(front, 1), leg(middle, -2), leg(back, 1)],
+        }
+    }
+
+    /// Returns the name of the synthetic, used as the symbol of its candles.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the legs of the synthetic.
+    pub fn legs(&self) -> &[Leg] {
+        &self.legs
+    }
+
+    /// Returns the parent symbols of the futures legs, e.g. `ES.FUT` for `ESM5`, for
+    /// requesting every leg with [`SType::Parent`](dbn::SType::Parent).
+    pub fn parent_symbols(&self) -> Vec<String> {
+        let mut parents: Vec<String> = Vec::new();
+        for leg in self.legs.iter() {
+            let parent = format!("{}.FUT", future_root(&leg.symbol));
+            if !parents.contains(&parent) {
+                parents.push(parent);
+            }
+        }
+        parents
+    }
+
+    /// Returns the price of the synthetic from the fixed-point price of each leg
+    /// returned by `leg_price`, or `None` if any leg has no price.
+    fn price(&self, mut leg_price: impl FnMut(&Leg) -> Option<i64>) -> Option<i64> {
+        self.legs.iter().try_fold(0i64, |sum, leg| {
+            Some(sum.saturating_add(leg.ratio.saturating_mul(leg_price(leg)?)))
+        })
+    }
+}
+
+impl FromStr for Synthetic {
+    type Err = crate::Error;
+
+    /// Parses a calendar spread like `ESM5-ESU5` or a butterfly like
+    /// `ESM5-ESU5-ESZ5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split('-').collect::<Vec<_>>().as_slice() {
+            [front, back] if !front.is_empty() && !back.is_empty() => {
+                Ok(Self::calendar_spread(front, back))
+            }
+            [front, middle, back]
+                if !front.is_empty() && !middle.is_empty() && !back.is_empty() =>
+            {
+                Ok(Self::butterfly(front, middle, back))
+            }
+            _ => Err(crate::Error::bad_arg(
+                "s",
+                format!(
+                    "expected a spread like ESM5-ESU5 or a butterfly like ESM5-ESU5-ESZ5, got {s}"
+                ),
+            )),
+        }
+    }
+}
+
+/// Combines the `candles` of the legs of `synthetic`, matched by symbol, into
+/// synthetic candles for each timestamp at which every leg has a candle.
+///
+/// The opens and closes of the synthetic candles are exact, but since the highs and
+/// lows of the legs rarely occur at the same time, the high and low of each candle
+/// only span its open and close. The volume is the smallest volume of the legs.
+/// Synthetic candles have an instrument ID of 0 and are ordered by timestamp.
+pub fn synthetic_candles<P: CandlePrice>(
+    synthetic: &Synthetic,
+    candles: &[Candle<P>],
+) -> Vec<Candle<P>> {
+    let mut by_timestamp = BTreeMap::new();
+    for candle in candles {
+        if synthetic
+            .legs
+            .iter()
+            .any(|leg| *leg.symbol == *candle.symbol)
+        {
+            by_timestamp
+                .entry(candle.timestamp)
+                .or_insert_with(Vec::new)
+                .push(candle);
+        }
+    }
+    by_timestamp
+        .into_iter()
+        .filter_map(|(timestamp, legs)| {
+            let find = |leg: &Leg| legs.iter().find(|candle| *candle.symbol == *leg.symbol);
+            let open = synthetic.price(|leg| Some(find(leg)?.open.to_fixed()))?;
+            let close = synthetic.price(|leg| Some(find(leg)?.close.to_fixed()))?;
+            let volume = legs.iter().map(|candle| candle.volume).min()?;
+            Some(Candle {
+                timestamp,
+                instrument_id: 0,
+                symbol: synthetic.name.clone(),
+                open: P::from_fixed(open),
+                high: P::from_fixed(open.max(close)),
+                low: P::from_fixed(open.min(close)),
+                close: P::from_fixed(close),
+                volume,
+            })
+        })
+        .collect()
+}
+
+/// Incrementally builds candles from the synthetic mid-price implied by the best bid
+/// and offer of each leg of a [`Synthetic`].
+///
+/// Pass every record of a session to [`push()`](Self::push), including symbol
+/// mappings so legs can be matched by symbol. A new synthetic price is computed
+/// whenever a leg's quote changes and every leg has a two-sided quote. The synthetic
+/// bid is the price for selling the bought legs at their bids and buying the sold
+/// legs at their offers, and the offer the reverse.
+#[derive(Debug, Clone)]
+pub struct SyntheticCandleBuilder<P = f64> {
+    synthetic: Synthetic,
+    board: QuoteBoard,
+    builder: QuoteCandleBuilder<P>,
+}
+
+impl<P: CandlePrice> SyntheticCandleBuilder<P> {
+    /// Creates a builder for `interval` candles of `synthetic` aligned to `anchor` with
+    /// timestamps in `tz`.
+    pub fn new(synthetic: Synthetic, interval: Duration, anchor: BucketAnchor, tz: Tz) -> Self {
+        Self {
+            synthetic,
+            board: QuoteBoard::new(),
+            builder: QuoteCandleBuilder::new(interval, anchor, tz),
+        }
+    }
+
+    /// Returns the quotes of the legs received so far.
+    pub fn quotes(&self) -> &QuoteBoard {
+        &self.board
+    }
+
+    /// Returns the in-progress candle, if any.
+    pub fn current(&self) -> Option<&Candle<P>> {
+        self.builder.current()
+    }
+
+    /// Updates the quotes of the legs from `rec`, returning the previous candle if the
+    /// synthetic price starts a new interval.
+    pub fn push(&mut self, rec: &RecordRef) -> Option<Candle<P>> {
+        if !self.board.on_record(rec) {
+            return None;
+        }
+        let instrument_id = rec.header().instrument_id;
+        let is_leg = self.synthetic.legs.iter().any(|leg| {
+            self.board
+                .get(&leg.symbol)
+                .is_some_and(|quote| quote.instrument_id == instrument_id)
+        });
+        if !is_leg {
+            return None;
+        }
+        let board = &self.board;
+        let quote_px = |leg: &Leg, buy: bool| {
+            let quote = board.get(&leg.symbol)?;
+            let px = if buy { quote.ask_px } else { quote.bid_px };
+            px.map(|px| (px * 1e9).round() as i64)
+        };
+        let bid_px = self.synthetic.price(|leg| quote_px(leg, leg.ratio < 0))?;
+        let ask_px = self.synthetic.price(|leg| quote_px(leg, leg.ratio > 0))?;
+        let ts = self.board.get_by_id(instrument_id)?.ts_recv;
+        self.builder.push_quote(
+            ts.timestamp_nanos_opt().unwrap_or(0) as u64,
+            0,
+            bid_px,
+            ask_px,
+            0,
+            &self.synthetic.name,
+        )
+    }
+
+    /// Returns the in-progress candle and resets the builder, e.g. at the end of a
+    /// session.
+    pub fn flush(&mut self) -> Option<Candle<P>> {
+        self.builder.flush()
+    }
+}
+
+fn leg(symbol: &str, ratio: i64) -> Leg {
+    Leg {
+        symbol: symbol.to_owned(),
+        ratio,
+    }
+}
+
+// Returns the root of a futures symbol like `ESM5` by removing the month code and
+// year, or the whole symbol if it doesn't end with them.
+fn future_root(symbol: &str) -> &str {
+    let root = symbol.trim_end_matches(|c: char| c.is_ascii_digit());
+    match root.char_indices().last() {
+        Some((i, month)) if root.len() < symbol.len() && i > 0 && MONTH_CODES.contains(month) => {
+            &root[..i]
+        }
+        _ => symbol,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::America::New_York;
+    use dbn::{rtype, BidAskPair, Mbp1Msg, RecordHeader, SType, SymbolMappingMsg};
+
+    use super::*;
+    use crate::test_util::{self, eastern};
+
+    fn candle(minute: u32, symbol: &str, open: f64, close: f64, volume: u64) -> Candle {
+        test_util::candle()
+            .timestamp(eastern(2025, 4, 21, 9, minute))
+            .symbol(symbol)
+            .ohlc(open, open.max(close) + 1.0, open.min(close) - 1.0, close)
+            .volume(volume)
+            .build()
+    }
+
+    #[test]
+    fn test_parse() {
+        let spread: Synthetic = "ESM5-ESU5".parse().unwrap();
+        assert_eq!(spread, Synthetic::calendar_spread("ESM5", "ESU5"));
+        assert_eq!(spread.parent_symbols(), ["ES.FUT"]);
+        let fly: Synthetic = "ESM5-ESU5-ESZ5".parse().unwrap();
+        assert_eq!(
+            fly.legs().iter().map(|leg| leg.ratio).collect::<Vec<_>>(),
+            [1, -2, 1]
+        );
+        assert!("ESM5".parse::<Synthetic>().is_err());
+        assert!("ESM5--ESZ5".parse::<Synthetic>().is_err());
+        assert_eq!(future_root("ESM25"), "ES");
+        assert_eq!(future_root("SPY"), "SPY");
+        assert!(Synthetic::new("ESM5", vec![leg("ESM5", 0)]).is_err());
+    }
+
+    #[test]
+    fn test_synthetic_candles() {
+        let candles = vec![
+            candle(30, "ESM5", 5300.0, 5302.0, 100),
+            candle(30, "ESU5", 5350.0, 5351.0, 40),
+            candle(30, "ESZ5", 5400.0, 5401.0, 20),
+            // Missing the September leg
+            candle(31, "ESM5", 5302.0, 5303.0, 100),
+            candle(31, "ESZ5", 5401.0, 5402.0, 20),
+        ];
+        let spread = synthetic_candles(&Synthetic::calendar_spread("ESM5", "ESU5"), &candles);
+        assert_eq!(spread.len(), 1);
+        assert_eq!(&*spread[0].symbol, "ESM5-ESU5");
+        assert_eq!(spread[0].open, -50.0);
+        assert_eq!(spread[0].close, -49.0);
+        assert_eq!(spread[0].high, -49.0);
+        assert_eq!(spread[0].low, -50.0);
+        assert_eq!(spread[0].volume, 40);
+
+        let fly = synthetic_candles(&Synthetic::butterfly("ESM5", "ESU5", "ESZ5"), &candles);
+        assert_eq!(fly.len(), 1);
+        assert_eq!(fly[0].open, 0.0);
+        assert_eq!(fly[0].close, 1.0);
+    }
+
+    #[test]
+    fn test_synthetic_candle_builder() {
+        let quote = |instrument_id, bid_px: f64, ask_px: f64, minute: u64| Mbp1Msg {
+            hd: RecordHeader::new::<Mbp1Msg>(rtype::MBP_1, 1, instrument_id, 0),
+            ts_recv: 1_745_242_200_000_000_000 + minute * 60_000_000_000,
+            levels: [BidAskPair {
+                bid_px: (bid_px * 1e9) as i64,
+                ask_px: (ask_px * 1e9) as i64,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut builder = SyntheticCandleBuilder::<f64>::new(
+            "ESM5-ESU5".parse().unwrap(),
+            Duration::minutes(1),
+            BucketAnchor::LocalMidnight,
+            New_York,
+        );
+        for (instrument_id, symbol) in [(1, "ESM5"), (2, "ESU5")] {
+            let mapping = SymbolMappingMsg::new(
+                instrument_id,
+                0,
+                SType::RawSymbol,
+                symbol,
+                SType::RawSymbol,
+                symbol,
+                0,
+                0,
+            )
+            .unwrap();
+            assert!(builder.push(&RecordRef::from(&mapping)).is_none());
+        }
+        assert!(builder
+            .push(&RecordRef::from(&quote(1, 5300.0, 5300.25, 0)))
+            .is_none());
+        assert!(builder.current().is_none());
+        assert!(builder
+            .push(&RecordRef::from(&quote(2, 5350.0, 5350.5, 0)))
+            .is_none());
+        // Bid -50.5, offer -49.75
+        assert_eq!(builder.current().unwrap().open, -50.125);
+        assert!(builder
+            .push(&RecordRef::from(&quote(1, 5301.0, 5301.25, 0)))
+            .is_none());
+        let candle = builder
+            .push(&RecordRef::from(&quote(2, 5351.0, 5351.5, 1)))
+            .unwrap();
+        assert_eq!(&*candle.symbol, "ESM5-ESU5");
+        assert_eq!(candle.instrument_id, 0);
+        assert_eq!(candle.high, -49.125);
+        assert_eq!(candle.close, -49.125);
+        assert_eq!(builder.flush().unwrap().open, -50.125);
+    }
+}
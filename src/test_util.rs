@@ -0,0 +1,103 @@
+//! Shared helpers for the crate's unit tests.
+
+use std::ffi::c_char;
+
+use chrono::{DateTime, TimeZone};
+use chrono_tz::Tz;
+use dbn::{rtype, OhlcvMsg, RecordHeader, TradeMsg};
+
+use crate::examples::es_futures_pmz::{Candle, DEFAULT_CANDLE_TZ};
+
+/// Returns a [`CandleBuilder`] for a flat ESM5 candle with instrument ID 1 and a volume
+/// of 10 at the 2025-04-22 RTH open.
+pub(crate) fn candle() -> CandleBuilder {
+    CandleBuilder(Candle {
+        timestamp: eastern(2025, 4, 22, 9, 30),
+        instrument_id: 1,
+        symbol: "ESM5".into(),
+        open: 5300.0,
+        high: 5300.0,
+        low: 5300.0,
+        close: 5300.0,
+        volume: 10,
+    })
+}
+
+/// Returns a 1-minute bar for `instrument_id` at `ts_event` that opens at 5300.00, has
+/// a range of 5299.00-5301.00, closes at 5300.50, and has a volume of 10. Override
+/// fields with struct update syntax.
+pub(crate) fn ohlcv(instrument_id: u32, ts_event: u64) -> OhlcvMsg {
+    OhlcvMsg {
+        hd: RecordHeader::new::<OhlcvMsg>(rtype::OHLCV_1M, 1, instrument_id, ts_event),
+        open: 5_300_000_000_000,
+        high: 5_301_000_000_000,
+        low: 5_299_000_000_000,
+        close: 5_300_500_000_000,
+        volume: 10,
+    }
+}
+
+/// Returns a buy trade of `size` contracts of `instrument_id` at `price`, a fixed-point
+/// price with 1e-9 scaling, received at `ts_event`. Override fields with struct update
+/// syntax.
+pub(crate) fn trade(instrument_id: u32, ts_event: u64, price: i64, size: u32) -> TradeMsg {
+    TradeMsg {
+        hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, instrument_id, ts_event),
+        price,
+        size,
+        action: b'T' as c_char,
+        side: b'B' as c_char,
+        ts_recv: ts_event,
+        ..Default::default()
+    }
+}
+
+/// Returns the given minute in [`DEFAULT_CANDLE_TZ`].
+pub(crate) fn eastern(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+    DEFAULT_CANDLE_TZ
+        .with_ymd_and_hms(year, month, day, hour, minute, 0)
+        .unwrap()
+}
+
+/// Builds test [`Candle`]s from the defaults of [`candle()`].
+#[derive(Debug, Clone)]
+pub(crate) struct CandleBuilder(Candle);
+
+impl CandleBuilder {
+    pub(crate) fn timestamp(mut self, timestamp: DateTime<Tz>) -> Self {
+        self.0.timestamp = timestamp;
+        self
+    }
+
+    pub(crate) fn instrument_id(mut self, instrument_id: u32) -> Self {
+        self.0.instrument_id = instrument_id;
+        self
+    }
+
+    pub(crate) fn symbol(mut self, symbol: &str) -> Self {
+        self.0.symbol = symbol.into();
+        self
+    }
+
+    /// Sets all four prices to `price`.
+    pub(crate) fn price(self, price: f64) -> Self {
+        self.ohlc(price, price, price, price)
+    }
+
+    pub(crate) fn ohlc(mut self, open: f64, high: f64, low: f64, close: f64) -> Self {
+        self.0.open = open;
+        self.0.high = high;
+        self.0.low = low;
+        self.0.close = close;
+        self
+    }
+
+    pub(crate) fn volume(mut self, volume: u64) -> Self {
+        self.0.volume = volume;
+        self
+    }
+
+    pub(crate) fn build(self) -> Candle {
+        self.0
+    }
+}
@@ -0,0 +1,344 @@
+//! Test doubles for code built on the historical API.
+//!
+//! [`MockHistoricalClient`] implements [`HistoricalApi`] by serving canned DBN
+//! fixtures, so functions generic over [`HistoricalApi`] such as
+//! [`calculate_pmz_with_client()`](crate::examples::es_futures_pmz::calculate_pmz_with_client)
+//! can be unit-tested without an API key or network access.
+
+use std::{collections::HashMap, io::Cursor, path::Path};
+
+use dbn::{
+    decode::AsyncDbnDecoder,
+    encode::{DbnEncodable, DbnEncoder, EncodeDbn},
+    MetadataBuilder, SType, Schema,
+};
+use time::{OffsetDateTime, Time};
+use tokio::io::AsyncReadExt;
+
+use crate::{
+    historical::{
+        metadata::{
+            DataAvailability, DatasetCondition, DatasetConditionDetail, DatasetInfo, DatasetRange,
+        },
+        symbology::{Resolution, ResolveParams},
+        timeseries::GetRangeParams,
+        DateRange, HistoricalApi,
+    },
+    Error,
+};
+
+/// A [`HistoricalApi`] implementation that serves canned responses.
+///
+/// Timeseries responses are Zstandard-compressed DBN fixtures registered per schema.
+/// The whole fixture is returned for every request in its schema regardless of the
+/// requested symbols and time range, so fixtures should only contain the records a
+/// test expects. Requests are recorded and can be inspected with
+/// [`requests()`](Self::requests).
+#[derive(Debug, Clone, Default)]
+pub struct MockHistoricalClient {
+    ranges: HashMap<Schema, Vec<u8>>,
+    resolution: Option<Resolution>,
+    availability: Option<DataAvailability>,
+    dataset_info: Option<DatasetInfo>,
+    requests: Vec<GetRangeParams>,
+}
+
+impl MockHistoricalClient {
+    /// Creates a mock client without any fixtures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves the Zstandard-compressed DBN `bytes` for requests in `schema`.
+    pub fn with_range(mut self, schema: Schema, bytes: impl Into<Vec<u8>>) -> Self {
+        self.ranges.insert(schema, bytes.into());
+        self
+    }
+
+    /// Serves the Zstandard-compressed DBN file at `path`, such as one saved with
+    /// `TimeseriesClient::get_range_to_file()`, for requests in `schema`.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to read the file.
+    pub fn with_range_file(self, schema: Schema, path: impl AsRef<Path>) -> crate::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Ok(self.with_range(schema, bytes))
+    }
+
+    /// Encodes `records` as DBN and serves them for requests in `schema`.
+    ///
+    /// # Errors
+    /// This function returns an error when it fails to encode the records.
+    pub fn with_records<R: DbnEncodable>(
+        self,
+        dataset: &str,
+        schema: Schema,
+        records: &[R],
+    ) -> crate::Result<Self> {
+        let metadata = MetadataBuilder::new()
+            .dataset(dataset.to_owned())
+            .schema(Some(schema))
+            .start(records.first().map_or(0, |rec| rec.header().ts_event))
+            .stype_in(Some(SType::Continuous))
+            .stype_out(SType::InstrumentId)
+            .build();
+        let mut bytes = Vec::new();
+        {
+            // The Zstandard frame is completed when the encoder is dropped
+            let mut encoder = DbnEncoder::with_zstd(&mut bytes, &metadata)?;
+            encoder.encode_records(records)?;
+        }
+        Ok(self.with_range(schema, bytes))
+    }
+
+    /// Returns `resolution` from [`resolve()`](HistoricalApi::resolve).
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    /// Returns `availability` from
+    /// [`get_data_availability()`](HistoricalApi::get_data_availability). By default,
+    /// every requested date is available.
+    pub fn with_availability(mut self, availability: DataAvailability) -> Self {
+        self.availability = Some(availability);
+        self
+    }
+
+    /// Returns `info` from [`get_dataset_info()`](HistoricalApi::get_dataset_info). By
+    /// default, the dataset has no publishers, offers the schemas with fixtures, and has
+    /// the requested range.
+    pub fn with_dataset_info(mut self, info: DatasetInfo) -> Self {
+        self.dataset_info = Some(info);
+        self
+    }
+
+    /// Returns the parameters of the timeseries requests made so far.
+    pub fn requests(&self) -> &[GetRangeParams] {
+        &self.requests
+    }
+}
+
+impl HistoricalApi for MockHistoricalClient {
+    async fn get_range(
+        &mut self,
+        params: &GetRangeParams,
+    ) -> crate::Result<AsyncDbnDecoder<impl AsyncReadExt + Unpin + Send>> {
+        self.requests.push(params.clone());
+        let bytes = self
+            .ranges
+            .get(&params.schema)
+            .ok_or_else(|| Error::bad_arg("schema", format!("no fixture for {}", params.schema)))?;
+        let mut decoder = AsyncDbnDecoder::with_zstd_buffer(Cursor::new(bytes.clone())).await?;
+        decoder.set_upgrade_policy(params.upgrade_policy);
+        Ok(decoder)
+    }
+
+    async fn resolve(&mut self, _params: &ResolveParams) -> crate::Result<Resolution> {
+        self.resolution
+            .clone()
+            .ok_or_else(|| Error::bad_arg("symbols", "no resolution fixture"))
+    }
+
+    async fn get_data_availability(
+        &mut self,
+        dataset: &str,
+        date_range: DateRange,
+    ) -> crate::Result<DataAvailability> {
+        if let Some(availability) = &self.availability {
+            return Ok(availability.clone());
+        }
+        let midnight =
+            |date: time::Date| -> OffsetDateTime { date.with_time(Time::MIDNIGHT).assume_utc() };
+        let mut conditions = Vec::new();
+        let mut date = date_range.start();
+        while date < date_range.end() {
+            conditions.push(DatasetConditionDetail {
+                date,
+                condition: DatasetCondition::Available,
+                last_modified_date: date,
+            });
+            date = date.next_day().unwrap();
+        }
+        Ok(DataAvailability {
+            dataset: dataset.to_owned(),
+            range: DatasetRange {
+                start: midnight(date_range.start()),
+                end: midnight(date_range.end()),
+            },
+            conditions,
+        })
+    }
+
+    async fn get_dataset_info(
+        &mut self,
+        dataset: &str,
+        range: DatasetRange,
+    ) -> crate::Result<DatasetInfo> {
+        if let Some(info) = &self.dataset_info {
+            return Ok(info.clone());
+        }
+        let mut schemas: Vec<_> = self.ranges.keys().copied().collect();
+        schemas.sort_by_key(|schema| *schema as u16);
+        Ok(DatasetInfo {
+            dataset: dataset.to_owned(),
+            publishers: Vec::new(),
+            schemas,
+            range,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use dbn::{enums::StatType, rtype, OhlcvMsg, RecordHeader, StatMsg};
+
+    use super::*;
+    use crate::{
+        examples::es_futures_pmz::{
+            calculate_pmz_with_client, Diagnostic, GapReference, PmzConfig,
+        },
+        test_util, zst_test_data_path,
+    };
+
+    // 2025-04-17 19:55 UTC, the LIS candle before the Good Friday holiday
+    const LIS_NANOS: u64 = 1_744_919_700_000_000_000;
+    // 2025-04-21 11:25 UTC, the start of the pre-market window
+    const PMZ_NANOS: u64 = 1_745_234_700_000_000_000;
+    const NANOS_PER_MIN: u64 = 60_000_000_000;
+
+    fn ohlcv(ts_event: u64, price: f64) -> OhlcvMsg {
+        let px = (price * 1e9) as i64;
+        OhlcvMsg {
+            open: px,
+            high: px + 250_000_000,
+            low: px - 250_000_000,
+            close: px,
+            ..test_util::ohlcv(1, ts_event)
+        }
+    }
+
+    // The LIS candle and a full pre-market window for 2025-04-21
+    fn pmz_client() -> MockHistoricalClient {
+        let lis = (0..5).map(|i| ohlcv(LIS_NANOS + i * NANOS_PER_MIN, 5300.0));
+        let pre_market =
+            (0..120).map(|i| ohlcv(PMZ_NANOS + i * NANOS_PER_MIN, 5310.0 + (i % 10) as f64));
+        let records: Vec<_> = lis.chain(pre_market).collect();
+        MockHistoricalClient::new()
+            .with_records("GLBX.MDP3", Schema::Ohlcv1M, &records)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_calculate_pmz_with_mock() {
+        let client = pmz_client();
+        let config = PmzConfig {
+            dataset_info: true,
+            ..PmzConfig::default()
+        };
+
+        let mut diagnostics = Vec::new();
+        let res =
+            calculate_pmz_with_client(client, &config, NaiveDate::from_ymd_opt(2025, 4, 21), |d| {
+                diagnostics.push(d)
+            })
+            .await
+            .unwrap();
+        assert!(res.is_complete());
+        assert_eq!(res.prev_day_lis, Some(5300.0));
+        assert_eq!(res.pmh, Some(5319.25));
+        assert_eq!(res.pml, Some(5309.75));
+        assert_eq!(res.is_gap_up, Some(true));
+        assert_eq!(res.gap_points, Some(19.0));
+        assert_eq!(res.gap_percent, Some(19.0 / 5300.0 * 100.0));
+        assert!(res.candles.is_none());
+        assert!(diagnostics
+            .iter()
+            .any(|d| matches!(d, Diagnostic::Dataset(info) if info.supports(Schema::Ohlcv1M))));
+        assert!(diagnostics.contains(&Diagnostic::CandlesRetrieved { count: 125 }));
+        assert!(diagnostics.contains(&Diagnostic::Aggregated { count: 24 }));
+        assert!(!diagnostics
+            .iter()
+            .any(|d| matches!(d, Diagnostic::Incomplete { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_calculate_pmz_with_candles() {
+        let config = PmzConfig {
+            include_candles: true,
+            ..PmzConfig::default()
+        };
+        let res = calculate_pmz_with_client(
+            pmz_client(),
+            &config,
+            NaiveDate::from_ymd_opt(2025, 4, 21),
+            |_| {},
+        )
+        .await
+        .unwrap();
+        let candles = res.candles.unwrap();
+        assert_eq!(candles.lis.len(), 1);
+        assert_eq!(Some(candles.lis[0].close), res.prev_day_lis);
+        assert_eq!(candles.pre_market.len(), 24);
+        assert_eq!(candles.pre_market.last().map(|c| c.close), res.close_925);
+    }
+
+    #[tokio::test]
+    async fn test_calculate_pmz_with_settlement_reference() {
+        let settlement = StatMsg {
+            // 2025-04-17 21:00 UTC
+            hd: RecordHeader::new::<StatMsg>(rtype::STATISTICS, 1, 1, 1_744_923_600_000_000_000),
+            price: 5_325_000_000_000,
+            stat_type: StatType::SettlementPrice as u16,
+            stat_flags: 1,
+            // 2025-04-17
+            ts_ref: 1_744_848_000_000_000_000,
+            ..Default::default()
+        };
+        let client = pmz_client()
+            .with_records("GLBX.MDP3", Schema::Statistics, &[settlement])
+            .unwrap();
+        let config = PmzConfig {
+            gap_reference: GapReference::Settlement,
+            ..PmzConfig::default()
+        };
+        let res = calculate_pmz_with_client(
+            client,
+            &config,
+            NaiveDate::from_ymd_opt(2025, 4, 21),
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.prev_day_lis, Some(5300.0));
+        assert_eq!(res.gap_reference, Some(5325.0));
+        // The 9:25 close of 5319 is below the settlement
+        assert_eq!(res.is_gap_up, Some(false));
+        assert_eq!(res.gap_points, Some(-6.0));
+        assert!(res.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_mock_requests() {
+        let mut client = MockHistoricalClient::new()
+            .with_range_file(Schema::Trades, zst_test_data_path(Schema::Trades))
+            .unwrap();
+        let params = GetRangeParams::builder()
+            .dataset("XNAS.ITCH")
+            .symbols("SPOT")
+            .schema(Schema::Trades)
+            .date_time_range(time::macros::date!(2023 - 06 - 22))
+            .build();
+        {
+            let mut decoder = client.get_range(&params).await.unwrap();
+            assert!(decoder.decode_record_ref().await.unwrap().is_some());
+        }
+        let mbo_params = GetRangeParams {
+            schema: Schema::Mbo,
+            ..params.clone()
+        };
+        assert!(client.get_range(&mbo_params).await.is_err());
+        assert_eq!(client.requests(), [params, mbo_params]);
+    }
+}
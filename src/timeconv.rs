@@ -0,0 +1,192 @@
+//! Conversions between [`chrono`] types and the [`time`] types used by the Databento
+//! API.
+//!
+//! [`ToOffsetDateTime`] and [`ToTimeDate`] convert chrono datetimes and dates, as well
+//! as UNIX nanosecond timestamps like the `ts_event` of records, to the types accepted
+//! by request parameters such as
+//! [`DateTimeRange`](crate::historical::DateTimeRange). [`ToChrono`] converts back.
+//!
+//! ```
+//! use chrono::{NaiveDate, TimeZone, Utc};
+//! use databento::timeconv::{ToChrono, ToOffsetDateTime, ToTimeDate};
+//!
+//! # fn main() -> databento::Result<()> {
+//! let dt = Utc.with_ymd_and_hms(2025, 4, 21, 13, 30, 0).unwrap();
+//! let odt = dt.to_offset_date_time()?;
+//! assert_eq!(odt.to_chrono()?, dt);
+//! let date = NaiveDate::from_ymd_opt(2025, 4, 21).unwrap();
+//! assert_eq!(date.to_time_date()?.to_chrono()?, date);
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use time::OffsetDateTime;
+
+use crate::{Error, Result};
+
+/// Conversion to a [`time::OffsetDateTime`] in UTC.
+pub trait ToOffsetDateTime {
+    /// Converts `self` to an [`OffsetDateTime`] in UTC.
+    ///
+    /// # Errors
+    /// This function returns an error when `self` is out of the range of either type.
+    fn to_offset_date_time(&self) -> Result<OffsetDateTime>;
+}
+
+/// Conversion to a [`time::Date`].
+pub trait ToTimeDate {
+    /// Converts `self` to a [`time::Date`].
+    ///
+    /// # Errors
+    /// This function returns an error when `self` is out of the range of
+    /// [`time::Date`].
+    fn to_time_date(&self) -> Result<time::Date>;
+}
+
+/// Conversion from a [`time`] type to its [`chrono`] equivalent.
+pub trait ToChrono {
+    /// The chrono type.
+    type Output;
+
+    /// Converts `self` to its chrono equivalent.
+    ///
+    /// # Errors
+    /// This function returns an error when `self` is out of the range of the chrono
+    /// type.
+    fn to_chrono(&self) -> Result<Self::Output>;
+}
+
+impl<Tz: TimeZone> ToOffsetDateTime for DateTime<Tz> {
+    fn to_offset_date_time(&self) -> Result<OffsetDateTime> {
+        let nanos = self
+            .timestamp_nanos_opt()
+            .ok_or_else(|| out_of_range("datetime", self.naive_utc()))?;
+        nanos.to_offset_date_time()
+    }
+}
+
+/// Midnight UTC on the date.
+impl ToOffsetDateTime for NaiveDate {
+    fn to_offset_date_time(&self) -> Result<OffsetDateTime> {
+        Ok(self.to_time_date()?.midnight().assume_utc())
+    }
+}
+
+/// The number of nanoseconds since the UNIX epoch.
+impl ToOffsetDateTime for i64 {
+    fn to_offset_date_time(&self) -> Result<OffsetDateTime> {
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(*self))
+            .map_err(|e| Error::bad_arg("nanos", e))
+    }
+}
+
+/// The number of nanoseconds since the UNIX epoch, like the timestamps in DBN records.
+impl ToOffsetDateTime for u64 {
+    fn to_offset_date_time(&self) -> Result<OffsetDateTime> {
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(*self))
+            .map_err(|e| Error::bad_arg("nanos", e))
+    }
+}
+
+impl ToTimeDate for NaiveDate {
+    fn to_time_date(&self) -> Result<time::Date> {
+        let month = time::Month::try_from(self.month() as u8)
+            .map_err(|e| Error::bad_arg("date", format!("{self}: {e}")))?;
+        time::Date::from_calendar_date(self.year(), month, self.day() as u8)
+            .map_err(|e| Error::bad_arg("date", format!("{self}: {e}")))
+    }
+}
+
+/// The date in the datetime's timezone.
+impl<Tz: TimeZone> ToTimeDate for DateTime<Tz> {
+    fn to_time_date(&self) -> Result<time::Date> {
+        self.date_naive().to_time_date()
+    }
+}
+
+impl ToChrono for OffsetDateTime {
+    type Output = DateTime<Utc>;
+
+    fn to_chrono(&self) -> Result<DateTime<Utc>> {
+        let nanos = i64::try_from(self.unix_timestamp_nanos())
+            .map_err(|_| out_of_range("datetime", self))?;
+        Ok(DateTime::from_timestamp_nanos(nanos))
+    }
+}
+
+impl ToChrono for time::Date {
+    type Output = NaiveDate;
+
+    fn to_chrono(&self) -> Result<NaiveDate> {
+        NaiveDate::from_ymd_opt(
+            self.year(),
+            u8::from(self.month()).into(),
+            self.day().into(),
+        )
+        .ok_or_else(|| out_of_range("date", self))
+    }
+}
+
+/// The number of nanoseconds since the UNIX epoch, like the timestamps in DBN records.
+impl ToChrono for u64 {
+    type Output = DateTime<Utc>;
+
+    fn to_chrono(&self) -> Result<DateTime<Utc>> {
+        let nanos = i64::try_from(*self).map_err(|_| out_of_range("nanos", self))?;
+        Ok(DateTime::from_timestamp_nanos(nanos))
+    }
+}
+
+fn out_of_range(param_name: &str, value: impl std::fmt::Display) -> Error {
+    Error::bad_arg(param_name, format!("{value} is out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::America::New_York;
+    use time::macros::{date, datetime};
+
+    use super::*;
+
+    #[test]
+    fn test_to_offset_date_time() {
+        let dt = New_York.with_ymd_and_hms(2025, 4, 21, 9, 30, 0).unwrap();
+        assert_eq!(
+            dt.to_offset_date_time().unwrap(),
+            datetime!(2025 - 04 - 21 13:30 UTC)
+        );
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2025, 4, 21)
+                .unwrap()
+                .to_offset_date_time()
+                .unwrap(),
+            datetime!(2025 - 04 - 21 00:00 UTC)
+        );
+        assert_eq!(
+            1_745_242_200_000_000_000u64.to_offset_date_time().unwrap(),
+            datetime!(2025 - 04 - 21 13:30 UTC)
+        );
+        assert_eq!(dt.to_time_date().unwrap(), date!(2025 - 04 - 21));
+        let far_future = Utc.with_ymd_and_hms(3000, 1, 1, 0, 0, 0).unwrap();
+        assert!(far_future.to_offset_date_time().is_err());
+    }
+
+    #[test]
+    fn test_to_chrono() {
+        assert_eq!(
+            datetime!(2025 - 04 - 21 13:30 UTC).to_chrono().unwrap(),
+            Utc.with_ymd_and_hms(2025, 4, 21, 13, 30, 0).unwrap()
+        );
+        assert_eq!(
+            date!(2025 - 04 - 21).to_chrono().unwrap(),
+            NaiveDate::from_ymd_opt(2025, 4, 21).unwrap()
+        );
+        assert_eq!(
+            1_745_242_200_000_000_000u64.to_chrono().unwrap(),
+            Utc.with_ymd_and_hms(2025, 4, 21, 13, 30, 0).unwrap()
+        );
+        assert!(dbn::UNDEF_TIMESTAMP.to_chrono().is_err());
+        assert!(datetime!(3000 - 01 - 01 00:00 UTC).to_chrono().is_err());
+    }
+}
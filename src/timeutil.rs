@@ -0,0 +1,126 @@
+//! Helpers for converting local wall-clock times to instants.
+//!
+//! Around daylight saving time transitions, a local time can occur twice (when
+//! clocks fall back) or not at all (when clocks spring forward), so
+//! [`TimeZone::from_local_datetime()`] doesn't always return a single result.
+//! [`resolve_local()`] resolves these cases according to a [`LocalTimePolicy`].
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+
+use crate::{Error, Result};
+
+/// How to resolve a local time that's ambiguous or nonexistent in a timezone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LocalTimePolicy {
+    /// Resolve to the earlier of the possible instants. For a nonexistent local time,
+    /// this interprets it with the offset after the transition.
+    #[default]
+    Earliest,
+    /// Resolve to the later of the possible instants. For a nonexistent local time,
+    /// this interprets it with the offset before the transition, i.e. shifts it
+    /// forward by the length of the gap.
+    Latest,
+    /// Return an error.
+    Error,
+}
+
+/// Converts `naive` local time in `tz` to an instant, resolving ambiguous and
+/// nonexistent times according to `policy`.
+///
+/// # Errors
+/// This function returns an error when `naive` is ambiguous or nonexistent in `tz` and
+/// `policy` is [`LocalTimePolicy::Error`], or when it's out of range.
+pub fn resolve_local<Tz>(
+    naive: NaiveDateTime,
+    tz: &Tz,
+    policy: LocalTimePolicy,
+) -> Result<DateTime<Tz>>
+where
+    Tz: TimeZone,
+    Tz::Offset: std::fmt::Display,
+{
+    match (tz.from_local_datetime(&naive), policy) {
+        (LocalResult::Single(dt), _) => Ok(dt),
+        (LocalResult::Ambiguous(earliest, _), LocalTimePolicy::Earliest) => Ok(earliest),
+        (LocalResult::Ambiguous(_, latest), LocalTimePolicy::Latest) => Ok(latest),
+        (LocalResult::Ambiguous(earliest, latest), LocalTimePolicy::Error) => Err(Error::bad_arg(
+            "naive",
+            format!(
+                "{naive} is ambiguous, it could be {} or {}",
+                earliest.offset(),
+                latest.offset()
+            ),
+        )),
+        (LocalResult::None, LocalTimePolicy::Error) => Err(Error::bad_arg(
+            "naive",
+            format!("{naive} doesn't exist, it falls in a daylight saving time gap"),
+        )),
+        (LocalResult::None, policy) => {
+            // DST gaps are at most a few hours long, so the offsets a day either side
+            // are those before and after the transition
+            let offset_at = |local: Option<NaiveDateTime>| {
+                local
+                    .and_then(|local| tz.from_local_datetime(&local).earliest())
+                    .map(|dt| dt.offset().fix())
+            };
+            let before = offset_at(naive.checked_sub_signed(Duration::days(1)));
+            let after = offset_at(naive.checked_add_signed(Duration::days(1)));
+            let offset = match policy {
+                LocalTimePolicy::Earliest => after,
+                _ => before,
+            };
+            offset
+                .and_then(|offset| naive.checked_sub_offset(offset))
+                .map(|utc| tz.from_utc_datetime(&utc))
+                .ok_or_else(|| Error::bad_arg("naive", format!("{naive} is out of range")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Utc};
+    use chrono_tz::America::New_York;
+
+    use super::*;
+
+    fn naive(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn utc(dt: DateTime<chrono_tz::Tz>) -> NaiveDateTime {
+        dt.with_timezone(&Utc).naive_utc()
+    }
+
+    #[test]
+    fn test_resolve_single() {
+        let res =
+            resolve_local(naive(2025, 4, 22, 9, 25), &New_York, LocalTimePolicy::Error).unwrap();
+        assert_eq!(utc(res), naive(2025, 4, 22, 13, 25));
+    }
+
+    #[test]
+    fn test_resolve_ambiguous() {
+        // Clocks fall back from 2:00 EDT to 1:00 EST
+        let local = naive(2024, 11, 3, 1, 30);
+        let earliest = resolve_local(local, &New_York, LocalTimePolicy::Earliest).unwrap();
+        assert_eq!(utc(earliest), naive(2024, 11, 3, 5, 30));
+        let latest = resolve_local(local, &New_York, LocalTimePolicy::Latest).unwrap();
+        assert_eq!(utc(latest), naive(2024, 11, 3, 6, 30));
+        assert!(resolve_local(local, &New_York, LocalTimePolicy::Error).is_err());
+    }
+
+    #[test]
+    fn test_resolve_nonexistent() {
+        // Clocks spring forward from 2:00 EST to 3:00 EDT
+        let local = naive(2025, 3, 9, 2, 30);
+        let earliest = resolve_local(local, &New_York, LocalTimePolicy::Earliest).unwrap();
+        assert_eq!(utc(earliest), naive(2025, 3, 9, 6, 30));
+        let latest = resolve_local(local, &New_York, LocalTimePolicy::Latest).unwrap();
+        assert_eq!(utc(latest), naive(2025, 3, 9, 7, 30));
+        assert!(resolve_local(local, &New_York, LocalTimePolicy::Error).is_err());
+    }
+}